@@ -105,6 +105,7 @@ mod helpers {
                 "i16" => Some(format_ident! { "put_i16" }),
                 "i32" => Some(format_ident! { "put_i32" }),
                 "i64" => Some(format_ident! { "put_i64" }),
+                "f64" => Some(format_ident! { "put_f64" }),
                 _ => None,
             }
         } else {
@@ -1,19 +1,45 @@
-use kafka::broker::Broker;
+use kafka::{broker::Broker, config::BrokerConfig};
 
 use anyhow::{bail, Context};
-use tracing_subscriber::FmtSubscriber;
-use tracing::error;
+use tracing::{error, Subscriber};
+use tracing_subscriber::{fmt::MakeWriter, EnvFilter, FmtSubscriber};
+
+/// Logs go to stdout, filtered by `RUST_LOG` (`info` if unset), e.g.
+/// `RUST_LOG=info,kafka::request_log=debug` also logs every request.
+/// `KAFKA_LOG_FORMAT=json` writes them as JSON lines, with the fields of
+/// the connection and request spans they belong to.
+fn init_logging() -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let format = std::env::var("KAFKA_LOG_FORMAT").unwrap_or_default();
+    tracing::subscriber::set_global_default(subscriber(&format, filter, std::io::stdout)?)
+        .context("setting default subscriber failed")
+}
+
+/// Builds the subscriber writing logs in `format`, text or json, to `writer`
+fn subscriber<W>(
+    format: &str,
+    filter: EnvFilter,
+    writer: W,
+) -> anyhow::Result<Box<dyn Subscriber + Send + Sync>>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = FmtSubscriber::builder()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    Ok(match format {
+        "" | "text" => Box::new(builder.finish()),
+        "json" => Box::new(builder.json().with_current_span(false).finish()),
+        other => bail!("Unknown KAFKA_LOG_FORMAT {other:?}, expected text or json"),
+    })
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let subscriber = FmtSubscriber::builder()
-        //.with_max_level(tracing::Level::ERROR)
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber)
-        .context("setting default subscriber failed")?;
+    init_logging()?;
 
-    let broker = Broker::new().await?;
+    let config = BrokerConfig::from_args(std::env::args().skip(1))?;
+    let broker = Broker::new(config).await?;
     if let Err(e) = broker.run().await {
         error!("Broker's event loop returned an error: {}", e);
         bail!("Broker's event loop returned an error: {}", e)
@@ -21,3 +47,63 @@ async fn main() -> anyhow::Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use kafka::broker::REQUEST_LOG_TARGET;
+    use tracing::{debug, info_span};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields() {
+        let buffer = Buffer::default();
+        let filter = EnvFilter::new(format!("info,{REQUEST_LOG_TARGET}=debug"));
+        let writer = buffer.clone();
+        let subscriber = subscriber("json", filter, move || writer.clone()).unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            let _connection = info_span!("connection", peer = "127.0.0.1:9092").entered();
+            let _request = info_span!("request", api_key = "Fetch", correlation_id = 7).entered();
+            debug!(target: REQUEST_LOG_TARGET, response_size = 42, "Completed request");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{output}");
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["level"], "DEBUG");
+        assert_eq!(line["target"], REQUEST_LOG_TARGET);
+        assert_eq!(line["fields"]["message"], "Completed request");
+        assert_eq!(line["fields"]["response_size"], 42);
+        let spans = line["spans"].as_array().unwrap();
+        assert_eq!(spans[0]["name"], "connection");
+        assert_eq!(spans[0]["peer"], "127.0.0.1:9092");
+        assert_eq!(spans[1]["name"], "request");
+        assert_eq!(spans[1]["correlation_id"], 7);
+    }
+
+    #[test]
+    fn test_unknown_log_format() {
+        let err = subscriber("xml", EnvFilter::new("info"), io::sink).err().unwrap();
+        assert!(err.to_string().contains("KAFKA_LOG_FORMAT"));
+    }
+}
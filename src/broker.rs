@@ -1,90 +1,1038 @@
 #![deny(clippy::pedantic)]
-use std::{net::{IpAddr, Ipv4Addr, SocketAddr}, thread::panicking};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
-use anyhow::{bail, Context};
+use anyhow::{Context, bail};
 use bytes::BytesMut;
 use tokio::{
-    io::{self, AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::{TcpListener, TcpStream},
+    sync::{OwnedSemaphorePermit, Semaphore, mpsc, watch},
+    task::{JoinHandle, JoinSet},
 };
-use tracing::{debug, error, info, warn};
+use tracing::{Instrument, Span, debug, error, info, info_span, trace, warn};
 
 use crate::{
     WireLen,
     codec::{Decoder, Encoder},
-    handlers::handle_request,
+    config::{BrokerConfig, ConfigManager, Listener, SecurityProtocol},
+    controller::{self, BrokerLifecycleManager, QuorumController},
+    handlers::{
+        DelayedDeleteRecords, DelayedFetch, DelayedProduce, HandlerResponse, handle_request,
+        handle_sasl_request,
+    },
+    metadata::{
+        AclsPublisher, ConfigsPublisher, ControllerPublisher, MetadataImage, MetadataLoader,
+        PartitionsPublisher, QuotasPublisher, ScramPublisher, TopicsPublisher,
+    },
+    metrics::{self, BrokerMetrics, RequestTimes},
+    purgatory::{self, Purgatory},
+    quota::{self, ConnectionQuotas, ConnectionSlot, QuotaManager, QuotaType},
+    raft::{self, RaftClient},
+    replica::{self, ReplicaManager},
     request::KafkaRequest,
+    response::KafkaResponse,
+    security::{AclAuthorizer, Authorizer, CredentialStore, SaslAuthenticator, TlsServer},
+    storage::{self, LogCleaner, LogManager, MetaProperties, TopicPartition},
+    types::ApiKeys,
 };
 
+/// Target of the request log, an event for every answered request with its sizes,
+/// timings and error code. Logged at debug level, enable it with
+/// `RUST_LOG=info,kafka::request_log=debug`.
+pub const REQUEST_LOG_TARGET: &str = "kafka::request_log";
+
+/// State shared by the handlers of every connection
+#[derive(Debug)]
+pub struct BrokerState {
+    pub config: BrokerConfig,
+    /// Id of the cluster, taken from the `meta.properties` of the log dirs
+    pub cluster_id: String,
+    pub log_manager: Arc<LogManager>,
+    /// Topic and broker configs altered at runtime
+    pub configs: Arc<ConfigManager>,
+    /// The partitions hosted by the broker, led or followed
+    pub replica_manager: Arc<ReplicaManager>,
+    /// `DeleteRecords` requests waiting for the low watermark of their partitions
+    pub delete_records_purgatory: Arc<Purgatory<TopicPartition, DelayedDeleteRecords>>,
+    /// `Produce` requests with `acks=all` waiting for the high watermark of their partitions
+    pub produce_purgatory: Arc<Purgatory<TopicPartition, DelayedProduce>>,
+    /// `Fetch` requests waiting for enough records
+    pub fetch_purgatory: Arc<Purgatory<TopicPartition, DelayedFetch>>,
+    pub metrics: Arc<BrokerMetrics>,
+    pub quotas: Arc<QuotaManager>,
+    pub connection_quotas: ConnectionQuotas,
+    /// What clients of SASL listeners authenticate against
+    pub credentials: Arc<CredentialStore>,
+    /// Decides what clients may do, everything is allowed without one
+    pub authorizer: Option<Arc<dyn Authorizer>>,
+    /// The replica of the `__cluster_metadata` log, on nodes of a `KRaft` quorum
+    pub raft: Option<Arc<RaftClient>>,
+    /// The cluster metadata replayed from the metadata log, empty without a quorum
+    pub metadata_image: Arc<RwLock<MetadataImage>>,
+    /// Registers and fences the brokers of the cluster, on controllers of a `KRaft` quorum
+    pub controller: Option<Arc<QuorumController>>,
+}
+
+impl BrokerState {
+    /// Retries the requests waiting on `tp`, after its records, high watermark or
+    /// leadership changed
+    pub fn complete_delayed_requests(&self, tp: &TopicPartition) {
+        self.produce_purgatory.check_and_complete(tp);
+        self.fetch_purgatory.check_and_complete(tp);
+        self.delete_records_purgatory.check_and_complete(tp);
+    }
+}
+
+/// What the handlers know about the connection a request arrived on
+#[derive(Debug, Clone)]
+pub struct ConnectionContext {
+    /// Name of the listener the connection was accepted by, e.g. `PLAINTEXT`
+    pub listener_name: String,
+    pub security_protocol: SecurityProtocol,
+    pub peer_addr: SocketAddr,
+    /// Who the client authenticated as, the user its quotas are looked up for. Taken
+    /// from the client certificate on `SSL` listeners, [`quota::ANONYMOUS`] without
+    /// authentication.
+    pub principal: String,
+}
+
+/// A pipelined request still being handled, along with its slot among the
+/// connection's in-flight requests
+struct PendingResponse {
+    api_key: ApiKeys,
+    api_version: i16,
+    /// Who sent the request, the user and client id its quotas are looked up for
+    principal: String,
+    client_id: String,
+    /// Size of the request on the wire, including its size prefix
+    size: usize,
+    received: Instant,
+    /// Span of the request, holding its api key, version, correlation and client id
+    span: Span,
+    /// The handler's response, and when the handler started and returned
+    handled: JoinHandle<(anyhow::Result<HandlerResponse>, Instant, Instant)>,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// A bound listener and the config it was bound from
+struct BoundListener {
+    listener: Listener,
+    security_protocol: SecurityProtocol,
+    socket: TcpListener,
+    /// Wraps the connections in TLS, on `SSL` and `SASL_SSL` listeners
+    tls: Option<TlsServer>,
+}
+
+/// Stops the broker it was taken from, the same way SIGTERM does. Lets tests
+/// and embedding applications shut a broker down without sending it signals.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle(watch::Sender<bool>);
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+/// What a node of a `KRaft` quorum runs, depending on its roles
+#[derive(Default)]
+struct QuorumNode {
+    raft: Option<Arc<RaftClient>>,
+    controller: Option<Arc<QuorumController>>,
+    lifecycle: Option<Arc<BrokerLifecycleManager>>,
+}
+
 pub struct Broker {
-    listener: TcpListener,
+    listeners: Vec<BoundListener>,
+    /// Serves the metrics over HTTP, if `metrics.address` is set
+    metrics_socket: Option<TcpListener>,
+    state: Arc<BrokerState>,
+    log_cleaner: Arc<LogCleaner>,
+    /// Registers the broker with the controller, on brokers of a `KRaft` cluster
+    lifecycle: Option<Arc<BrokerLifecycleManager>>,
+    /// Set to true by the [`ShutdownHandle`]s of the broker
+    requested: watch::Sender<bool>,
+    /// Set to true once the broker starts closing connections. Every connection holds a
+    /// receiver, so the broker knows they are all gone once the channel closes.
+    shutdown: watch::Sender<bool>,
 }
 
 impl Broker {
-    pub const ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9092);
-    pub const READ_SIZE: usize = 128;
+    pub const READ_SIZE: usize = 4096;
+    /// How long connections get to finish their in-flight requests on shutdown
+    pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Creates a broker listening on every one of the configured `listeners`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a listener or the metrics address cannot be bound, the certificates of SSL
+    /// listeners, the ACLs, the dynamic configs or the log directories cannot be loaded, or
+    /// the log directories belong to another node or cluster. Nodes of a `KRaft` quorum
+    /// also fail if the metadata log cannot be opened.
+    pub async fn new(config: BrokerConfig) -> anyhow::Result<Self> {
+        Self::warn_if_unformatted(&config)?;
+        let log_manager = LogManager::load(config.log_dirs.clone(), config.log.clone())
+            .context("Loading logs")?;
+        log_manager.recover().context("Recovering logs")?;
+        let log_manager = Arc::new(log_manager);
+        let cluster_id = MetaProperties::load(&config.log_dirs, config.node_id)
+            .context("Loading meta.properties")?;
+        let metadata_image = Arc::new(RwLock::new(MetadataImage::new()));
+        let quorum = Self::open_quorum(&config, &cluster_id, &metadata_image)?;
+        let log_cleaner =
+            LogCleaner::new(Arc::clone(&log_manager)).context("Loading log cleaner")?;
+        let replica_manager =
+            ReplicaManager::new(&config, Arc::clone(&log_manager)).context("Loading replicas")?;
+        let configs = ConfigManager::load(
+            &config.log_dirs[0],
+            config.node_id,
+            config.log.clone(),
+            Arc::clone(&log_manager),
+        )
+        .context("Loading dynamic configs")?;
+        let quotas = QuotaManager::load(
+            &config.log_dirs[0],
+            config.quota_window_num,
+            config.quota_window_size,
+        )
+        .context("Loading client quotas")?;
+        let connection_quotas = ConnectionQuotas::new(&config)?;
+        let credentials = CredentialStore::load(
+            config.sasl_plain_credentials_file.as_deref(),
+            &config.log_dirs[0],
+        )
+        .context("Loading SASL credentials")?;
+        let authorizer = Self::load_authorizer(&config)?;
+        let tls = config
+            .listeners
+            .iter()
+            .any(|l| {
+                config
+                    .security_protocol(&l.name)
+                    .is_some_and(SecurityProtocol::is_ssl)
+            })
+            .then(|| TlsServer::from_config(&config))
+            .transpose()
+            .context("Loading SSL certificates")?;
 
-    pub async fn new() -> io::Result<Self> {
+        let mut listeners = Vec::with_capacity(config.listeners.len());
+        for listener in &config.listeners {
+            let security_protocol = config
+                .security_protocol(&listener.name)
+                .with_context(|| format!("No security protocol for listener {listener}"))?;
+            let socket = TcpListener::bind(listener.bind_address())
+                .await
+                .with_context(|| format!("Binding listener {listener}"))?;
+            listeners.push(BoundListener {
+                listener: listener.clone(),
+                security_protocol,
+                socket,
+                tls: tls.clone().filter(|_| security_protocol.is_ssl()),
+            });
+        }
+        let metrics_socket = match &config.metrics_address {
+            Some(address) => Some(
+                TcpListener::bind(address)
+                    .await
+                    .with_context(|| format!("Binding metrics address {address}"))?,
+            ),
+            None => None,
+        };
         Ok(Self {
-            listener: TcpListener::bind(Self::ADDR).await?,
+            listeners,
+            metrics_socket,
+            state: Arc::new(BrokerState {
+                config,
+                cluster_id,
+                log_manager,
+                configs: Arc::new(configs),
+                replica_manager: Arc::new(replica_manager),
+                delete_records_purgatory: Arc::new(Purgatory::new("DeleteRecords")),
+                produce_purgatory: Arc::new(Purgatory::new("Produce")),
+                fetch_purgatory: Arc::new(Purgatory::new("Fetch")),
+                metrics: Arc::default(),
+                quotas: Arc::new(quotas),
+                connection_quotas,
+                credentials: Arc::new(credentials),
+                authorizer,
+                raft: quorum.raft,
+                metadata_image,
+                controller: quorum.controller,
+            }),
+            log_cleaner: Arc::new(log_cleaner),
+            lifecycle: quorum.lifecycle,
+            requested: watch::Sender::new(false),
+            shutdown: watch::Sender::new(false),
         })
     }
 
-    async fn handle_socket(stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
-        info!("{addr} connected");
-        let (mut r, mut w) = tokio::io::split(stream);
-        loop {
-            let mut buf = BytesMut::with_capacity(Self::READ_SIZE); // <- TODO: handle bigger input sized or frames/dynamic reading
-            let n = match r.read_buf(&mut buf).await {
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    warn!("Reading socket would block, advancing");
+    /// The authorizer set by `authorizer.class.name`, with the ACLs stored in the first
+    /// log dir
+    fn load_authorizer(config: &BrokerConfig) -> anyhow::Result<Option<Arc<dyn Authorizer>>> {
+        if config.authorizer_class_name.is_none() {
+            return Ok(None);
+        }
+        let authorizer = AclAuthorizer::load(
+            &config.log_dirs[0],
+            &config.super_users,
+            config.allow_everyone_if_no_acl_found,
+        )
+        .context("Loading ACLs")?;
+        Ok(Some(Arc::new(authorizer)))
+    }
+
+    /// Log dirs are formatted with a new cluster id on the first start, see
+    /// [`MetaProperties::load`]. That is all a quorum of a single voter needs, but every
+    /// node of a larger quorum has to agree on the cluster id, so they should be formatted
+    /// with the same one before their first start.
+    fn warn_if_unformatted(config: &BrokerConfig) -> anyhow::Result<()> {
+        if !config.is_kraft() || config.controller_quorum_voters.len() < 2 {
+            return Ok(());
+        }
+        let formatted = config
+            .log_dirs
+            .iter()
+            .find_map(|dir| MetaProperties::read(dir).transpose())
+            .transpose()?;
+        if formatted.is_none() {
+            warn!(
+                "No meta.properties in the log dirs, formatting them with a new cluster id \
+                 the other voters of the quorum do not share"
+            );
+        }
+        Ok(())
+    }
+
+    /// Opens the metadata log, and creates the controller of controller nodes and the
+    /// lifecycle manager of broker nodes, on nodes of a `KRaft` quorum
+    fn open_quorum(
+        config: &BrokerConfig,
+        cluster_id: &str,
+        image: &Arc<RwLock<MetadataImage>>,
+    ) -> anyhow::Result<QuorumNode> {
+        if !config.is_kraft() {
+            return Ok(QuorumNode::default());
+        }
+        let raft = Arc::new(RaftClient::new(config, cluster_id).context("Loading metadata log")?);
+        let controller = config.is_controller().then(|| {
+            Arc::new(QuorumController::new(
+                Arc::clone(&raft),
+                Arc::clone(image),
+                config,
+            ))
+        });
+        let lifecycle = config
+            .is_broker()
+            .then(|| BrokerLifecycleManager::new(Arc::clone(&raft), Arc::clone(image), config))
+            .transpose()
+            .context("Reading the directory ids of the log dirs")?
+            .map(Arc::new);
+        Ok(QuorumNode {
+            raft: Some(raft),
+            controller,
+            lifecycle,
+        })
+    }
+
+    #[must_use]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle(self.requested.clone())
+    }
+
+    /// The address the listener called `listener_name` accepts connections on,
+    /// useful when it was bound to port 0
+    #[must_use]
+    pub fn local_addr(&self, listener_name: &str) -> Option<SocketAddr> {
+        self.listeners
+            .iter()
+            .find(|l| l.listener.name == listener_name)
+            .and_then(|l| l.socket.local_addr().ok())
+    }
+
+    /// The address the metrics are served on, if they are
+    #[must_use]
+    pub fn metrics_addr(&self) -> Option<SocketAddr> {
+        self.metrics_socket
+            .as_ref()
+            .and_then(|socket| socket.local_addr().ok())
+    }
+
+    /// Serves a single connection. Requests are pipelined: every complete request in the
+    /// read buffer is handed to its own blocking task right away, so a slow request does not
+    /// hold up the ones after it, while a writer task sends the responses strictly in the
+    /// order the requests arrived, as the protocol requires.
+    async fn handle_socket<R, W>(
+        (mut r, w): (R, W),
+        conn: ConnectionContext,
+        state: Arc<BrokerState>,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        info!("Connection accepted");
+        let _connection = state.metrics.connection_opened(&conn.listener_name);
+        let mut conn = Arc::new(conn);
+        // clients of SASL listeners have to authenticate before anything but ApiVersions
+        let mut sasl = conn
+            .security_protocol
+            .is_sasl()
+            .then(|| SaslAuthenticator::from_config(&state.config));
+        let max_request_size = state.config.socket_request_max_bytes;
+        let max_in_flight = state.config.max_in_flight_requests_per_connection;
+        let in_flight = Arc::new(Semaphore::new(max_in_flight));
+        let idle_timeout = state.config.connections_max_idle;
+
+        let (responses, pending) = mpsc::unbounded_channel();
+        // set by the writer when the client exceeds a quota, no requests are read until then
+        let (mute, mut muted_until) = watch::channel(Instant::now());
+        let writer = tokio::spawn(
+            Self::write_responses(w, pending, mute, Arc::clone(&state)).in_current_span(),
+        );
+
+        let mut buf = BytesMut::with_capacity(Self::READ_SIZE);
+        let reading: anyhow::Result<()> = async {
+            loop {
+                let until = *muted_until.borrow_and_update();
+                if until > Instant::now() {
+                    if !Self::sleep_muted(until, &responses, &mut shutdown).await {
+                        return Ok(());
+                    }
                     continue;
                 }
-                Err(e) => {
-                    let err = format!("Error reading from socket: {}", e);
-                    error!(err);
-                    bail!(err);
+
+                while let Some(mut frame) = Self::next_frame(&mut buf, max_request_size)? {
+                    let received = Instant::now();
+                    let size = frame.len();
+                    let (req, span) = Self::decode_frame(&mut frame)?;
+                    let (api_key, api_version) =
+                        (req.header.request_api_key, req.header.request_api_version);
+                    if let Some(auth) = &sasl {
+                        if !Self::check_session(auth, api_key, received)? {
+                            info!(
+                                "Closing connection, its session expired without re-authentication"
+                            );
+                            return Ok(());
+                        }
+                    }
+                    // stops reading from the socket while too many requests are in flight
+                    let permit = Arc::clone(&in_flight).acquire_owned().await?;
+                    let client_id = req.header.client_id.as_deref().map(ToString::to_string);
+                    let principal = conn.principal.clone();
+                    let handled = match sasl.as_mut() {
+                        Some(auth) if Self::is_sasl_request(api_key) => {
+                            span.in_scope(|| Self::handle_sasl(&req, auth, &mut conn, &state))
+                        }
+                        _ => Self::spawn_handler(req, &state, &conn, &span),
+                    };
+                    let pending = PendingResponse {
+                        api_key,
+                        api_version,
+                        principal,
+                        client_id: client_id.unwrap_or_default(),
+                        size,
+                        received,
+                        span,
+                        handled,
+                        _permit: permit,
+                    };
+                    if responses.send(pending).is_err() {
+                        return Ok(());
+                    }
+                    if sasl.as_ref().is_some_and(SaslAuthenticator::failed) {
+                        info!("Closing connection after failed authentication");
+                        return Ok(());
+                    }
                 }
-                Ok(0) => {
-                    info!("{addr} disconnected");
+
+                buf.reserve(Self::READ_SIZE);
+                let read = tokio::select! {
+                    read = r.read_buf(&mut buf) => read.context("Reading from socket")?,
+                    () = tokio::time::sleep(idle_timeout) => {
+                        // a connection waiting for its responses is not idle
+                        if in_flight.available_permits() < max_in_flight {
+                            continue;
+                        }
+                        info!("Closing connection idle for {idle_timeout:?}");
+                        return Ok(());
+                    }
+                    // the writer failed, its error is reported below
+                    () = responses.closed() => return Ok(()),
+                    _ = shutdown.wait_for(|&stopping| stopping) => {
+                        info!("Closing connection for shutdown");
+                        return Ok(());
+                    }
+                };
+                if read == 0 {
+                    info!("Disconnected");
                     return Ok(());
                 }
-                Ok(n) => n,
-            };
+            }
+        }
+        .await;
 
-            let req = KafkaRequest::decode(&mut buf.split_to(n), None);
-            
-            let req = req.context("Could not decode buffer #1 Result")?.context("Could not decode buffer #2 Option")?;
-            debug!("request decoded: {:?}", req);
-            let res = handle_request(&req).context("Handling request")?;
-            debug!("request handled, generated response: {:?}", res);
-            let mut buf = BytesMut::with_capacity(res.wire_len());
-            res.encode(&mut buf)
-                .context("Encoding response to buffer")?;
-            w.write_all_buf(&mut buf)
-                .await
-                .context("Sending response buffer")?;
+        // requests already read are still answered before the connection closes
+        drop(responses);
+        let writing = writer.await.context("Response writer panicked")?;
+        reading.and(writing)
+    }
+
+    /// Decodes a request frame and opens the span of the request, holding its api key,
+    /// version, correlation and client id
+    fn decode_frame(frame: &mut BytesMut) -> anyhow::Result<(KafkaRequest, Span)> {
+        let req = KafkaRequest::decode(frame, None)
+            .context("Decoding request")?
+            .context("Request frame is incomplete")?;
+        let span = info_span!(
+            "request",
+            api_key = ?req.header.request_api_key,
+            api_version = req.header.request_api_version,
+            correlation_id = req.header.correlation_id,
+            client_id = req.header.client_id.as_deref(),
+        );
+        span.in_scope(|| trace!(request = ?req, "Decoded request"));
+        Ok((req, span))
+    }
+
+    /// Hands a request to its own blocking task
+    fn spawn_handler(
+        req: KafkaRequest,
+        state: &Arc<BrokerState>,
+        conn: &Arc<ConnectionContext>,
+        span: &Span,
+    ) -> JoinHandle<(anyhow::Result<HandlerResponse>, Instant, Instant)> {
+        let (state, conn, span) = (Arc::clone(state), Arc::clone(conn), span.clone());
+        tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let started = Instant::now();
+            let response = handle_request(&req, &state, &conn);
+            (response, started, Instant::now())
+        })
+    }
+
+    fn is_sasl_request(api_key: ApiKeys) -> bool {
+        matches!(api_key, ApiKeys::SaslHandshake | ApiKeys::SaslAuthenticate)
+    }
+
+    /// Waits until a muted connection may be read from again. Returns false if it should
+    /// be closed instead, as the writer failed or the broker is shutting down.
+    async fn sleep_muted(
+        until: Instant,
+        responses: &mpsc::UnboundedSender<PendingResponse>,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> bool {
+        tokio::select! {
+            () = tokio::time::sleep_until(until.into()) => true,
+            // the writer failed, its error is reported by the connection
+            () = responses.closed() => false,
+            _ = shutdown.wait_for(|&stopping| stopping) => {
+                info!("Closing connection for shutdown");
+                false
+            }
         }
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
-        info!("Listening on {}", Self::ADDR);
-        loop {
-            let (stream, addr) = self
-                .listener
-                .accept()
-                .await
-                .context("Accepting new connection")?;
+    /// Whether a request may be handled on a SASL connection. Fails for anything but
+    /// `ApiVersions` and the SASL requests before the client authenticated, and returns
+    /// false once its session expired without re-authentication.
+    fn check_session(
+        auth: &SaslAuthenticator,
+        api_key: ApiKeys,
+        now: Instant,
+    ) -> anyhow::Result<bool> {
+        if api_key == ApiKeys::ApiVersions || Self::is_sasl_request(api_key) {
+            return Ok(true);
+        }
+        if !auth.is_authenticated() {
+            bail!("Received {api_key:?} request before authenticating");
+        }
+        Ok(!auth.session_expired(now))
+    }
 
-            tokio::spawn(async move {
-                if let Err(e) = Self::handle_socket(stream, addr).await {
-                    eprintln!("Error on socket's event loop");
-                    for (i, cause) in e.chain().enumerate() {
-                        eprintln!("\t{i}. {}", cause);
+    /// Answers a SASL request right in the reader, as whether the requests after it are
+    /// allowed depends on the outcome. Once the client authenticated, the context of its
+    /// next requests carries its principal.
+    fn handle_sasl(
+        req: &KafkaRequest,
+        auth: &mut SaslAuthenticator,
+        conn: &mut Arc<ConnectionContext>,
+        state: &BrokerState,
+    ) -> JoinHandle<(anyhow::Result<HandlerResponse>, Instant, Instant)> {
+        let started = Instant::now();
+        let response = handle_sasl_request(req, Some(&mut *auth), state);
+        let handled = Instant::now();
+        if let Some(principal) = auth.principal().filter(|&p| p != conn.principal) {
+            *conn = Arc::new(ConnectionContext {
+                principal: principal.to_string(),
+                ..(**conn).clone()
+            });
+        }
+        tokio::spawn(async move { (response.map(HandlerResponse::Ready), started, handled) })
+    }
+
+    /// Splits the next complete request off of `buf`, including its size prefix.
+    /// Returns `Ok(None)` if the request has not fully arrived yet.
+    fn next_frame(buf: &mut BytesMut, max_size: usize) -> anyhow::Result<Option<BytesMut>> {
+        let Some(size) = buf.get(..4) else {
+            return Ok(None);
+        };
+        let size = i32::from_be_bytes(size.try_into()?);
+        let Ok(size) = usize::try_from(size) else {
+            bail!("Request size cannot be negative ({size})");
+        };
+        if size > max_size {
+            bail!("Request of {size} bytes exceeds socket.request.max.bytes ({max_size})");
+        }
+        if buf.len() < 4 + size {
+            buf.reserve(4 + size - buf.len());
+            return Ok(None);
+        }
+        Ok(Some(buf.split_to(4 + size)))
+    }
+
+    /// Sends the responses of a connection in the order their requests were read, waiting
+    /// for delayed ones to leave their purgatory. Once a response tells the client it is
+    /// throttled, the connection is muted for as long.
+    async fn write_responses<W: AsyncWrite + Unpin>(
+        mut w: W,
+        mut pending: mpsc::UnboundedReceiver<PendingResponse>,
+        mute: watch::Sender<Instant>,
+        state: Arc<BrokerState>,
+    ) -> anyhow::Result<()> {
+        while let Some(request) = pending.recv().await {
+            let span = request.span.clone();
+            let throttle = Self::write_response(&mut w, request, &state)
+                .instrument(span)
+                .await?;
+            if !throttle.is_zero() {
+                mute.send_if_modified(|until| {
+                    let throttled_until = Instant::now() + throttle;
+                    let later = throttled_until > *until;
+                    if later {
+                        *until = throttled_until;
                     }
+                    later
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the response of a single request and sends it, then records the
+    /// request in the metrics and the request log. Returns how long the client is
+    /// throttled for.
+    async fn write_response<W: AsyncWrite + Unpin>(
+        w: &mut W,
+        request: PendingResponse,
+        state: &BrokerState,
+    ) -> anyhow::Result<Duration> {
+        let (response, started, handled) =
+            request.handled.await.context("Request handler panicked")?;
+        // responses carry the throttle time of the byte rate quotas themselves
+        let (res, completed, quota_throttle_ms) = match response.context("Handling request")? {
+            HandlerResponse::Ready(res) => (Some(res), handled, 0),
+            HandlerResponse::Delayed(completion) => (
+                Some(completion.await.context("Waiting for delayed response")?),
+                Instant::now(),
+                0,
+            ),
+            // still counts against the quota and in the metrics
+            HandlerResponse::NoResponse(throttle_ms) => (None, handled, throttle_ms),
+        };
+
+        // handler time counts against the request quota as a percentage of a thread
+        let request_throttle = state.quotas.record(
+            QuotaType::Request,
+            &request.principal,
+            &request.client_id,
+            (handled - started).as_secs_f64() * 100.0,
+            completed,
+        );
+        let request_throttle_ms = i32::try_from(request_throttle.as_millis()).unwrap_or(i32::MAX);
+        let (response_size, error_code, throttle_ms) = match res {
+            Some(res) => Self::send_response(w, res, request_throttle_ms).await?,
+            None => (0, 0, request_throttle_ms.max(quota_throttle_ms)),
+        };
+
+        let times = RequestTimes {
+            received: request.received,
+            started,
+            handled,
+            completed,
+            sent: Instant::now(),
+        };
+        state
+            .metrics
+            .record_request(request.api_key, request.api_version, &times);
+        let millis = |from: Instant, to: Instant| (to - from).as_secs_f64() * 1000.0;
+        debug!(
+            target: REQUEST_LOG_TARGET,
+            request_size = request.size,
+            response_size,
+            error_code,
+            total_ms = millis(times.received, times.sent),
+            queue_ms = millis(times.received, times.started),
+            local_ms = millis(times.started, times.handled),
+            remote_ms = millis(times.handled, times.completed),
+            send_ms = millis(times.completed, times.sent),
+            "Completed request"
+        );
+        Ok(Duration::from_millis(throttle_ms.unsigned_abs().into()))
+    }
+
+    /// Sends a response, telling the client it is throttled for at least
+    /// `request_throttle_ms`. Returns the size and error code of the response and how
+    /// long the client is throttled for.
+    async fn send_response<W: AsyncWrite + Unpin>(
+        w: &mut W,
+        mut res: KafkaResponse,
+        request_throttle_ms: i32,
+    ) -> anyhow::Result<(usize, i16, i32)> {
+        let throttle_ms = res.body.throttle_time_ms().max(request_throttle_ms);
+        if throttle_ms > 0 {
+            debug!(throttle_ms, "Throttling client");
+            res.body.set_throttle_time_ms(throttle_ms);
+        }
+        trace!(response = ?res, "Handled request");
+        let mut buf = BytesMut::with_capacity(res.wire_len());
+        res.encode(&mut buf)
+            .context("Encoding response to buffer")?;
+        let response_size = buf.len();
+        w.write_all_buf(&mut buf)
+            .await
+            .context("Sending response buffer")?;
+        // TLS streams hold on to the records they did not manage to send yet
+        w.flush().await.context("Flushing response")?;
+        Ok((response_size, res.body.error_code(), throttle_ms))
+    }
+
+    /// Runs until SIGINT or SIGTERM is received, or the [`ShutdownHandle`] is used.
+    /// Brokers of a `KRaft` cluster first have the controller fence them, so their
+    /// partitions get new leaders. Shutting down then stops accepting connections,
+    /// closes the open ones once their in-flight requests are answered, waiting at
+    /// most [`Self::SHUTDOWN_TIMEOUT`], then flushes and checkpoints the logs so the
+    /// next start does not recover them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if accepting a new connection fails, signal handlers cannot be
+    /// installed, or the logs cannot be checkpointed
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let config = &self.state.config;
+        let maintenance = self.spawn_maintenance();
+        let (quorum, quorum_expirations) = self.spawn_quorum();
+
+        let mut accepting = JoinSet::new();
+        for listener in self.listeners.drain(..) {
+            info!(
+                "Node {} listening on {} ({})",
+                config.node_id,
+                listener.socket.local_addr()?,
+                listener.listener
+            );
+            accepting.spawn(Self::accept(
+                listener,
+                Arc::clone(&self.state),
+                self.shutdown.subscribe(),
+            ));
+        }
+
+        if let Some(socket) = self.metrics_socket.take() {
+            info!("Serving metrics on http://{}/metrics", socket.local_addr()?);
+            accepting.spawn(metrics::serve_metrics(
+                socket,
+                Arc::clone(&self.state),
+                self.shutdown.subscribe(),
+            ));
+        }
+
+        let mut requested = self.requested.subscribe();
+        let result = tokio::select! {
+            Some(accepted) = accepting.join_next() => {
+                error!("A listener stopped, shutting down");
+                accepted.context("Listener task panicked").and_then(|r| r)
+            }
+            signal = Self::shutdown_signal() => signal,
+            _ = requested.wait_for(|&stopping| stopping) => {
+                info!("Shutdown requested");
+                Ok(())
+            }
+        };
+        drop(requested);
+
+        if let Some(lifecycle) = &self.lifecycle {
+            lifecycle.shut_down().await;
+        }
+        for task in &quorum {
+            task.abort();
+        }
+        if let Some(raft) = &self.state.raft {
+            raft::resign(raft).await;
+        }
+
+        self.shutdown.send_replace(true);
+        while accepting.join_next().await.is_some() {}
+        let open = self.shutdown.receiver_count();
+        if open > 0 {
+            info!("Waiting for {open} connections to close");
+        }
+        if tokio::time::timeout(Self::SHUTDOWN_TIMEOUT, self.shutdown.closed())
+            .await
+            .is_err()
+        {
+            warn!(
+                "{} connections still open after {:?}, closing the logs anyway",
+                self.shutdown.receiver_count(),
+                Self::SHUTDOWN_TIMEOUT
+            );
+        }
+
+        for task in maintenance.iter().chain(&quorum_expirations) {
+            task.abort();
+        }
+        self.state.replica_manager.shutdown();
+        self.state
+            .log_manager
+            .checkpoint()
+            .context("Checkpointing logs")?;
+        info!("Logs checkpointed, shut down cleanly");
+        result
+    }
+
+    /// Starts the retention and cleaning of the logs, and the expiration of delayed
+    /// requests and of replicas lagging behind the ISR
+    fn spawn_maintenance(&self) -> Vec<JoinHandle<()>> {
+        let config = &self.state.config;
+        let state = Arc::clone(&self.state);
+        vec![
+            tokio::spawn(storage::run_retention(
+                Arc::clone(&self.state.log_manager),
+                config.log_retention_check_interval,
+            )),
+            tokio::spawn(storage::run_cleaner(
+                Arc::clone(&self.log_cleaner),
+                config.log_cleaner_backoff,
+            )),
+            tokio::spawn(purgatory::run_expiration(Arc::clone(
+                &self.state.delete_records_purgatory,
+            ))),
+            tokio::spawn(purgatory::run_expiration(Arc::clone(
+                &self.state.produce_purgatory,
+            ))),
+            tokio::spawn(purgatory::run_expiration(Arc::clone(
+                &self.state.fetch_purgatory,
+            ))),
+            tokio::spawn(replica::run_isr_expiration(
+                Arc::clone(&self.state.replica_manager),
+                move |tp| state.complete_delayed_requests(tp),
+            )),
+        ]
+    }
+
+    /// Starts taking part in the `KRaft` quorum and replaying the metadata log, on nodes of
+    /// one, along with the controller and the registration of the broker. Returns the
+    /// tasks doing so, stopped right away on shutdown, and the ones expiring the requests
+    /// of the other nodes, which are still answered until their connections close.
+    fn spawn_quorum(&self) -> (Vec<JoinHandle<()>>, Vec<JoinHandle<()>>) {
+        let state = &self.state;
+        let Some(raft) = &state.raft else {
+            return (Vec::new(), Vec::new());
+        };
+        let mut loader = MetadataLoader::new(
+            Arc::clone(raft),
+            Arc::clone(&state.metadata_image),
+            &state.config,
+        )
+        .with_publisher(TopicsPublisher::new(
+            state.config.node_id,
+            Arc::clone(&state.log_manager),
+        ))
+        .with_publisher(PartitionsPublisher::new(
+            Arc::clone(&state.replica_manager),
+            {
+                let state = Arc::clone(&self.state);
+                move |tp| state.complete_delayed_requests(tp)
+            },
+        ))
+        .with_publisher(ConfigsPublisher::new(Arc::clone(&state.configs)))
+        .with_publisher(QuotasPublisher::new(Arc::clone(&state.quotas)))
+        .with_publisher(ScramPublisher::new(Arc::clone(&state.credentials)));
+        if let Some(authorizer) = &state.authorizer {
+            loader = loader.with_publisher(AclsPublisher::new(Arc::clone(authorizer)));
+        }
+        let mut expirations = vec![tokio::spawn(purgatory::run_expiration(Arc::clone(
+            raft.fetch_purgatory(),
+        )))];
+        let mut quorum = vec![tokio::spawn(raft::run_driver(Arc::clone(raft)))];
+        if let Some(controller) = &state.controller {
+            let write_purgatory = controller.write_purgatory();
+            loader = loader.with_publisher(ControllerPublisher::new(Arc::clone(write_purgatory)));
+            expirations.push(tokio::spawn(purgatory::run_expiration(Arc::clone(
+                write_purgatory,
+            ))));
+            quorum.push(tokio::spawn(controller::run_session_expiration(
+                Arc::clone(controller),
+            )));
+        }
+        if let Some(lifecycle) = &self.lifecycle {
+            quorum.push(tokio::spawn(Arc::clone(lifecycle).run()));
+        }
+        quorum.push(tokio::spawn(loader.run()));
+        (quorum, expirations)
+    }
+
+    /// Resolves once SIGINT, or on unix SIGTERM, is received
+    async fn shutdown_signal() -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            let mut terminate =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                    .context("Installing SIGTERM handler")?;
+            tokio::select! {
+                interrupt = tokio::signal::ctrl_c() => interrupt.context("Waiting for SIGINT")?,
+                _ = terminate.recv() => {}
+            }
+        }
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c()
+            .await
+            .context("Waiting for SIGINT")?;
+        info!("Received signal, shutting down");
+        Ok(())
+    }
+
+    /// Accepts connections of a single listener, handling each in its own task,
+    /// until the broker shuts down
+    async fn accept(
+        listener: BoundListener,
+        state: Arc<BrokerState>,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.socket.accept() => accepted,
+                _ = shutdown.wait_for(|&stopping| stopping) => return Ok(()),
+            };
+            let (stream, peer_addr) = accepted
+                .with_context(|| format!("Accepting new connection on {}", listener.listener))?;
+            let throttle = state.connection_quotas.record_creation(Instant::now());
+            match state.connection_quotas.try_open(peer_addr.ip()) {
+                Ok(slot) => {
+                    Self::spawn_connection(stream, peer_addr, slot, &listener, &state, &shutdown);
                 }
-            });
+                Err(rejected) => {
+                    warn!(
+                        "Closing connection from {peer_addr} on {}, {rejected}",
+                        listener.listener
+                    );
+                    state
+                        .metrics
+                        .connection_rejected(&listener.listener.name, rejected.reason());
+                }
+            }
+
+            // connections arriving too fast wait in the listen backlog
+            if !throttle.is_zero() {
+                debug!(
+                    "Throttling new connections on {} for {throttle:?}",
+                    listener.listener
+                );
+                tokio::select! {
+                    () = tokio::time::sleep(throttle) => {}
+                    _ = shutdown.wait_for(|&stopping| stopping) => return Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Serves an accepted connection in its own task, which keeps `slot` until it closes
+    fn spawn_connection(
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        slot: ConnectionSlot,
+        listener: &BoundListener,
+        state: &Arc<BrokerState>,
+        shutdown: &watch::Receiver<bool>,
+    ) {
+        let mut conn = ConnectionContext {
+            listener_name: listener.listener.name.clone(),
+            security_protocol: listener.security_protocol,
+            peer_addr,
+            principal: quota::ANONYMOUS.to_string(),
+        };
+        let span = info_span!(
+            "connection",
+            listener = %conn.listener_name,
+            peer = %peer_addr,
+        );
+        let tls = listener.tls.clone();
+        let state = Arc::clone(state);
+        let shutdown = shutdown.clone();
+        tokio::spawn(
+            async move {
+                let _slot = slot;
+                let served = match tls {
+                    None => Self::handle_socket(stream.into_split(), conn, state, shutdown).await,
+                    Some(tls) => match Self::accept_tls(stream, &tls, &mut conn, &state).await {
+                        Some(stream) => {
+                            let halves = tokio::io::split(stream);
+                            Self::handle_socket(halves, conn, state, shutdown).await
+                        }
+                        None => Ok(()),
+                    },
+                };
+                if let Err(e) = served {
+                    error!("Connection failed: {e:#}");
+                }
+            }
+            .instrument(span),
+        );
+    }
+
+    /// Performs the TLS handshake of a connection, which has as long as an idle
+    /// connection to finish it. Clients of `SSL` listeners presenting a certificate
+    /// become the principal mapped from it. Returns `None` if the connection is closed
+    /// instead, as the handshake or the mapping failed.
+    async fn accept_tls(
+        stream: TcpStream,
+        tls: &TlsServer,
+        conn: &mut ConnectionContext,
+        state: &BrokerState,
+    ) -> Option<tokio_rustls::server::TlsStream<TcpStream>> {
+        let timeout = state.config.connections_max_idle;
+        let stream = match tokio::time::timeout(timeout, tls.accept(stream)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                warn!("Closing connection, TLS handshake failed: {e}");
+                return None;
+            }
+            Err(_) => {
+                info!("Closing connection, no TLS handshake within {timeout:?}");
+                return None;
+            }
+        };
+        if conn.security_protocol == SecurityProtocol::Ssl {
+            match tls.principal(&stream) {
+                Ok(Some(principal)) => {
+                    debug!(principal, "Authenticated by client certificate");
+                    conn.principal = principal;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(
+                        "Closing connection, mapping its certificate to a principal failed: {e:#}"
+                    );
+                    return None;
+                }
+            }
         }
+        Some(stream)
     }
 }
 
@@ -93,3 +1041,1214 @@ impl Drop for Broker {
         info!("Dropping broker");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{ConfigEntity, Properties},
+        types::ErrorCode,
+    };
+
+    /// `ApiVersions` v4 with the given correlation id
+    fn api_versions_request(correlation_id: u8) -> Vec<u8> {
+        let mut request = b"\x00\x00\x00\x23\x00\x12\x00\x04\x00\x00\x00\x00\x00\x09kafka-cli\x00\x0akafka-cli\x040.1\x00".to_vec();
+        request[11] = correlation_id;
+        request
+    }
+
+    async fn start_broker(
+        dir: &std::path::Path,
+    ) -> (SocketAddr, ShutdownHandle, JoinHandle<anyhow::Result<()>>) {
+        let mut props = Properties::default();
+        props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+        props.set("log.dirs", dir.to_str().unwrap());
+        props.set("max.in.flight.requests.per.connection", "2");
+        let config = BrokerConfig::from_properties(&props).unwrap();
+
+        let broker = Broker::new(config).await.unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        (addr, handle, tokio::spawn(broker.run()))
+    }
+
+    /// A request with header v1, or v2 if `flexible`, and the client id `test`
+    fn request(
+        api_key: i16,
+        api_version: i16,
+        correlation_id: i32,
+        body: &[u8],
+        flexible: bool,
+    ) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&api_key.to_be_bytes());
+        message.extend_from_slice(&api_version.to_be_bytes());
+        message.extend_from_slice(&correlation_id.to_be_bytes());
+        message.extend_from_slice(b"\x00\x04test");
+        if flexible {
+            message.push(0);
+        }
+        message.extend_from_slice(body);
+        let mut request = u32::try_from(message.len()).unwrap().to_be_bytes().to_vec();
+        request.extend(message);
+        request
+    }
+
+    /// Reads a response, returns its correlation id and body
+    async fn read_response<S: AsyncRead + Unpin>(client: &mut S) -> (i32, Vec<u8>) {
+        let mut size = [0; 4];
+        client.read_exact(&mut size).await.unwrap();
+        let mut response = vec![0; u32::from_be_bytes(size) as usize];
+        client.read_exact(&mut response).await.unwrap();
+        let body = response.split_off(4);
+        (i32::from_be_bytes(response.try_into().unwrap()), body)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_waits_for_produce() {
+        use crate::{
+            request::{
+                FetchPartition, FetchRequestBody, FetchTopic, ProducePartitionData,
+                ProduceRequestBody, ProduceTopicData,
+            },
+            response::body::FetchResponseBody,
+            types::{Record, RecordBatch},
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("foo-0")).unwrap();
+        let (addr, handle, running) = start_broker(dir.path()).await;
+
+        // the log is empty, the fetch waits for its min_bytes
+        let mut consumer = TcpStream::connect(addr).await.unwrap();
+        let partitions = vec![FetchPartition::new(0, -1, 0, -1, 1024)];
+        let topics = vec![FetchTopic::new("foo", partitions.into())];
+        let mut body = BytesMut::new();
+        FetchRequestBody::new(
+            FetchRequestBody::CONSUMER_REPLICA_ID,
+            5000,
+            1,
+            1024,
+            topics.into(),
+        )
+        .encode(&mut body)
+        .unwrap();
+        consumer
+            .write_all(&request(1, 12, 1, &body, true))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // the broker is the only replica, so acks=all is answered once appended
+        let batch = RecordBatch::new(0, 0, vec![Record::new(0, None, Some("v".into()))]);
+        let mut records = BytesMut::new();
+        batch.encode(&mut records).unwrap();
+        let partitions = vec![ProducePartitionData::new(0, records.to_vec())];
+        let topics = vec![ProduceTopicData::new("foo", partitions.into())];
+        let mut body = BytesMut::new();
+        ProduceRequestBody::new(-1, 5000, topics.into())
+            .encode(&mut body)
+            .unwrap();
+        let mut producer = TcpStream::connect(addr).await.unwrap();
+        producer
+            .write_all(&request(0, 9, 2, &body, true))
+            .await
+            .unwrap();
+        let (correlation_id, response) = read_response(&mut producer).await;
+        assert_eq!(2, correlation_id);
+        // the tag buffer, the topic name, the partition count and the partition index come first
+        assert_eq!(b"\x00\x00", &response[11..13]);
+
+        // the header's tag buffer comes first
+        let (_, response) = read_response(&mut consumer).await;
+        let fetched = FetchResponseBody::decode(&mut BytesMut::from(&response[1..]), None)
+            .unwrap()
+            .unwrap();
+        let topic = fetched.responses.iter().next().unwrap();
+        let partition = topic.partitions.iter().next().unwrap();
+        assert_eq!(1, partition.high_watermark);
+        let fetched = RecordBatch::decode(&mut BytesMut::from(&partition.records.0[..]), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("v".into()), fetched.records().unwrap()[0].value);
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_responses_keep_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, handle, running) = start_broker(dir.path()).await;
+
+        // three requests in one write, more than may be in flight, the last one split
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let requests: Vec<u8> = (1..=3).flat_map(api_versions_request).collect();
+        let (first, rest) = requests.split_at(requests.len() - 10);
+        client.write_all(first).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.write_all(rest).await.unwrap();
+
+        for correlation_id in 1..=3 {
+            let mut size = [0; 4];
+            client.read_exact(&mut size).await.unwrap();
+            let mut response = vec![0; u32::from_be_bytes(size) as usize];
+            client.read_exact(&mut response).await.unwrap();
+            assert_eq!(
+                correlation_id,
+                i32::from_be_bytes(response[..4].try_into().unwrap())
+            );
+        }
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, handle, running) = start_broker(dir.path()).await;
+
+        // answered before the connection is closed
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(&api_versions_request(7)).await.unwrap();
+        let mut response = [0; 8];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(7, i32::from_be_bytes(response[4..].try_into().unwrap()));
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(
+            dir.path()
+                .join(storage::RECOVERY_POINT_CHECKPOINT_FILE)
+                .exists()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kraft_formats_on_first_start() {
+        // a combined node alone in its quorum, starting on empty log dirs
+        let dir = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut props = Properties::default();
+        props.set("process.roles", "broker,controller");
+        props.set("node.id", "1");
+        props.set("controller.quorum.voters", format!("1@127.0.0.1:{port}"));
+        props.set(
+            "listeners",
+            format!("PLAINTEXT://127.0.0.1:0,CONTROLLER://127.0.0.1:{port}"),
+        );
+        props.set(
+            "listener.security.protocol.map",
+            "PLAINTEXT:PLAINTEXT,CONTROLLER:PLAINTEXT",
+        );
+        props.set("controller.listener.names", "CONTROLLER");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        let config = BrokerConfig::from_properties(&props).unwrap();
+
+        let broker = Broker::new(config).await.unwrap();
+        let meta = MetaProperties::read(dir.path()).unwrap().unwrap();
+        assert_eq!(1, meta.node_id);
+        assert_eq!(22, meta.cluster_id.len());
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut props = Properties::default();
+        props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("metrics.address", "127.0.0.1:0");
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let metrics_addr = broker.metrics_addr().unwrap();
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+
+        // the first request is recorded once the second one is answered, as
+        // requests are recorded after their response is written, in order
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        for correlation_id in 1..=2 {
+            client
+                .write_all(&api_versions_request(correlation_id))
+                .await
+                .unwrap();
+            let mut size = [0; 4];
+            client.read_exact(&mut size).await.unwrap();
+            let mut response = vec![0; u32::from_be_bytes(size) as usize];
+            client.read_exact(&mut response).await.unwrap();
+        }
+
+        let mut scrape = TcpStream::connect(metrics_addr).await.unwrap();
+        scrape
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        scrape.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let requests = response
+            .lines()
+            .find_map(|l| {
+                l.strip_prefix(
+                    "kafka_network_requests_total{request=\"ApiVersions\",version=\"4\"} ",
+                )
+            })
+            .unwrap();
+        assert!(matches!(requests, "1" | "2"));
+        assert!(response.contains("kafka_server_active_connections{listener=\"PLAINTEXT\"} 1"));
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut props = Properties::default();
+        props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("max.connections.per.ip", "1");
+        props.set("connections.max.idle.ms", "200");
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        let state = Arc::clone(&broker.state);
+        let running = tokio::spawn(broker.run());
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(&api_versions_request(1)).await.unwrap();
+        let mut response = [0; 8];
+        first.read_exact(&mut response).await.unwrap();
+
+        // closed right away, as the address has a connection already
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let mut rest = Vec::new();
+        second.read_to_end(&mut rest).await.unwrap();
+        assert!(
+            state
+                .metrics
+                .render(&state.log_manager, 0)
+                .contains("kafka_server_rejected_connections_total{listener=\"PLAINTEXT\",reason=\"max_connections_per_ip\"} 1")
+        );
+
+        // the first one is closed once idle, which frees its slot
+        tokio::time::timeout(Duration::from_secs(5), first.read_to_end(&mut rest))
+            .await
+            .unwrap()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(0, state.connection_quotas.open());
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sasl_plain() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_file = dir.path().join("plain.properties");
+        std::fs::write(&plain_file, "alice=alice-secret\n").unwrap();
+        let mut props = Properties::default();
+        props.set("listeners", "SASL_PLAINTEXT://127.0.0.1:0");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("sasl.enabled.mechanisms", "PLAIN");
+        props.set("sasl.plain.credentials.file", plain_file.to_str().unwrap());
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("SASL_PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+        let handshake = request(17, 1, 1, b"\x00\x05PLAIN", false);
+        // DescribeClientQuotas without filter
+        let describe = request(48, 1, 3, b"\x01\x00\x00", true);
+
+        // closed without an answer before authenticating
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(&describe).await.unwrap();
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(&handshake).await.unwrap();
+        assert_eq!(
+            (1, b"\x00\x00\x00\x00\x00\x01\x00\x05PLAIN".to_vec()),
+            read_response(&mut client).await
+        );
+        let token = b"\x00alice\x00alice-secret";
+        let mut body = u32::try_from(token.len()).unwrap().to_be_bytes().to_vec();
+        body.extend_from_slice(token);
+        client
+            .write_all(&request(36, 1, 2, &body, false))
+            .await
+            .unwrap();
+        let (correlation_id, body) = read_response(&mut client).await;
+        assert_eq!(2, correlation_id);
+        assert_eq!(b"\x00\x00", &body[..2]);
+        client.write_all(&describe).await.unwrap();
+        assert_eq!(3, read_response(&mut client).await.0);
+
+        // a wrong password is answered, then the connection is closed
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(&handshake).await.unwrap();
+        read_response(&mut client).await;
+        let token = b"\x00alice\x00wrong";
+        let mut body = u32::try_from(token.len()).unwrap().to_be_bytes().to_vec();
+        body.extend_from_slice(token);
+        client
+            .write_all(&request(36, 1, 2, &body, false))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            ErrorCode::SaslAuthenticationFailed.code().to_be_bytes(),
+            body[..2]
+        );
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    /// Authenticates as `user` with SCRAM-SHA-256 on a new connection, returns the error
+    /// code of the exchange
+    async fn scram_authenticate(addr: SocketAddr, user: &str, password: &str) -> i16 {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+        use crate::security::ScramMechanism;
+
+        /// `SaslAuthenticate` v1 carrying `token`, returns its error code and the server's token
+        async fn authenticate(client: &mut TcpStream, token: &str) -> (i16, String) {
+            let mut body = u32::try_from(token.len()).unwrap().to_be_bytes().to_vec();
+            body.extend_from_slice(token.as_bytes());
+            client
+                .write_all(&request(36, 1, 2, &body, false))
+                .await
+                .unwrap();
+            let (_, body) = read_response(client).await;
+            let error_code = i16::from_be_bytes(body[..2].try_into().unwrap());
+            let message_len = i16::from_be_bytes(body[2..4].try_into().unwrap());
+            let token = 4 + usize::try_from(message_len).unwrap_or(0);
+            let token_len = u32::from_be_bytes(body[token..token + 4].try_into().unwrap());
+            let token = &body[token + 4..token + 4 + token_len as usize];
+            (error_code, String::from_utf8(token.to_vec()).unwrap())
+        }
+
+        let mechanism = ScramMechanism::Sha256;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(&request(17, 1, 1, b"\x00\x0dSCRAM-SHA-256", false))
+            .await
+            .unwrap();
+        read_response(&mut client).await;
+        let client_first_bare = format!("n={user},r=rOprNGfwEbeRWgbNEkqO");
+        let (error_code, server_first) =
+            authenticate(&mut client, &format!("n,,{client_first_bare}")).await;
+        if error_code != 0 {
+            return error_code;
+        }
+
+        let attribute = |name: &str| {
+            server_first
+                .split(',')
+                .find_map(|a| a.strip_prefix(name))
+                .unwrap()
+                .to_string()
+        };
+        let nonce = attribute("r=");
+        let salt = BASE64.decode(attribute("s=")).unwrap();
+        let iterations = attribute("i=").parse().unwrap();
+        let salted_password = mechanism.salt_password(password.as_bytes(), &salt, iterations);
+        let without_proof = format!("c=biws,r={nonce}");
+        let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+        let client_key = mechanism.hmac(&salted_password, b"Client Key");
+        let signature = mechanism.hmac(&mechanism.hash(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(&signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+        let client_final = format!("{without_proof},p={}", BASE64.encode(proof));
+        authenticate(&mut client, &client_final).await.0
+    }
+
+    #[tokio::test]
+    async fn test_scram_credentials() {
+        use crate::security::ScramMechanism;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut props = Properties::default();
+        props.set(
+            "listeners",
+            "PLAINTEXT://127.0.0.1:0,SASL_PLAINTEXT://127.0.0.1:0",
+        );
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("sasl.enabled.mechanisms", "SCRAM-SHA-256");
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let sasl_addr = broker.local_addr("SASL_PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let failed = ErrorCode::SaslAuthenticationFailed.code();
+        assert_eq!(
+            failed,
+            scram_authenticate(sasl_addr, "alice", "alice-secret").await
+        );
+
+        // the client salts the password, 4096 iterations
+        let salt = b"salt of alice";
+        let salted_password = ScramMechanism::Sha256.salt_password(b"alice-secret", salt, 4096);
+        let mut upsertion = b"\x01\x02\x06alice\x01\x00\x00\x10\x00".to_vec();
+        upsertion.push(u8::try_from(salt.len() + 1).unwrap());
+        upsertion.extend_from_slice(salt);
+        upsertion.push(u8::try_from(salted_password.len() + 1).unwrap());
+        upsertion.extend_from_slice(&salted_password);
+        upsertion.extend_from_slice(b"\x00\x00");
+        client
+            .write_all(&request(51, 0, 1, &upsertion, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x06alice\x00\x00\x00\x00\x00", &body[5..]);
+
+        // described by mechanism and iterations only, and used by the next exchange
+        client
+            .write_all(&request(50, 0, 2, b"\x01\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            b"\x00\x00\x00\x02\x06alice\x00\x00\x00\x02\x01\x00\x00\x10\x00\x00\x00\x00",
+            &body[5..]
+        );
+        assert_eq!(
+            0,
+            scram_authenticate(sasl_addr, "alice", "alice-secret").await
+        );
+        assert_eq!(
+            failed,
+            scram_authenticate(sasl_addr, "alice", "wrong").await
+        );
+
+        // deleted, alice can no longer authenticate
+        client
+            .write_all(&request(51, 0, 3, b"\x02\x06alice\x01\x00\x01\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x06alice\x00\x00\x00\x00\x00", &body[5..]);
+        client
+            .write_all(&request(50, 0, 4, b"\x02\x06alice\x00\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            ErrorCode::ResourceNotFound.code().to_be_bytes(),
+            body[15..17]
+        );
+        assert_eq!(
+            failed,
+            scram_authenticate(sasl_addr, "alice", "alice-secret").await
+        );
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acls() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut props = Properties::default();
+        props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("authorizer.class.name", AclAuthorizer::CLASS_NAMES[0]);
+        props.set("allow.everyone.if.no.acl.found", "true");
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let cluster_denied = ErrorCode::ClusterAuthorizationFailed.code().to_be_bytes();
+
+        // allowed while the cluster has no ACLs, afterwards only describing it is
+        let creation = b"\x02\x04\x0ekafka-cluster\x03\x0fUser:ANONYMOUS\x02*\x08\x03\x00\x00";
+        client
+            .write_all(&request(30, 3, 1, creation, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x00\x00\x00", &body[5..9]);
+        client
+            .write_all(&request(48, 1, 2, b"\x01\x00\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(cluster_denied, body[5..7]);
+
+        // any filter
+        let filter = b"\x01\x00\x01\x00\x00\x01\x01\x00";
+        client
+            .write_all(&request(29, 3, 3, filter, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            b"\x00\x00\x00\x02\x04\x0ekafka-cluster\x03\x02",
+            &body[5..26]
+        );
+        let mut filters = b"\x02".to_vec();
+        filters.extend_from_slice(filter);
+        filters.push(0);
+        client
+            .write_all(&request(31, 3, 4, &filters, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(cluster_denied, body[6..8]);
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (addr, handle, running) = start_broker(dir.path()).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        // set the retention of every broker, then a value that is not a number
+        let mut alter =
+            b"\x02\x04\x01\x02\x11log.retention.ms\x00\x051000\x00\x00\x00\x00".to_vec();
+        client
+            .write_all(&request(44, 1, 1, &alter, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x00\x00\x00\x04\x01", &body[5..11]);
+        alter[23..27].copy_from_slice(b"soon");
+        client
+            .write_all(&request(44, 1, 2, &alter, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(ErrorCode::InvalidConfig.code().to_be_bytes(), body[6..8]);
+
+        // this broker inherits the cluster wide value, the default is its synonym
+        let describe = b"\x02\x04\x021\x02\x11log.retention.ms\x00\x01\x00\x00";
+        client
+            .write_all(&request(32, 4, 3, describe, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x00\x00\x00\x04\x021\x02", &body[5..13]);
+        assert_eq!(b"\x051000\x00\x03\x00\x03", &body[30..39]);
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_describe_cluster_and_log_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("foo-0")).unwrap();
+        let (addr, handle, running) = start_broker(dir.path()).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let cluster_id = MetaProperties::read(dir.path())
+            .unwrap()
+            .unwrap()
+            .cluster_id;
+
+        client
+            .write_all(&request(60, 1, 1, b"\x00\x01\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x00\x00\x00\x01\x17", &body[5..10]);
+        assert_eq!(cluster_id.as_bytes(), &body[10..32]);
+        assert_eq!(
+            b"\x00\x00\x00\x01\x02\x00\x00\x00\x01\x0a127.0.0.1",
+            &body[32..51]
+        );
+        // a broker listener has no controller endpoints
+        client
+            .write_all(&request(60, 1, 2, b"\x00\x02\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            ErrorCode::MismatchedEndpointType.code().to_be_bytes(),
+            body[5..7]
+        );
+
+        // every partition, there is one empty log
+        client
+            .write_all(&request(35, 4, 3, b"\x00\x00", true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x00\x00\x02\x00\x00", &body[5..10]);
+        let topics = 11 + dir.path().to_str().unwrap().len();
+        // partition 0 of size 0 without lag, then the tagged fields of both
+        let foo = [b"\x02\x04foo\x02".as_slice(), &[0; 23]].concat();
+        assert_eq!(foo, body[topics..topics + foo.len()]);
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    /// Produces a batch of one record to partition 0 of `topic` with `acks=all`,
+    /// returns the error code of the partition
+    async fn produce(client: &mut TcpStream, correlation_id: i32, topic: &str) -> i16 {
+        use crate::{
+            request::{ProducePartitionData, ProduceRequestBody, ProduceTopicData},
+            types::{Record, RecordBatch},
+        };
+        let batch = RecordBatch::new(0, 0, vec![Record::new(0, None, Some("v".into()))]);
+        let mut records = BytesMut::new();
+        batch.encode(&mut records).unwrap();
+        let partitions = vec![ProducePartitionData::new(0, records.to_vec())];
+        let topics = vec![ProduceTopicData::new(topic, partitions.into())];
+        let mut body = BytesMut::new();
+        ProduceRequestBody::new(-1, 5000, topics.into())
+            .encode(&mut body)
+            .unwrap();
+        client
+            .write_all(&request(0, 9, correlation_id, &body, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(client).await;
+        // the topic name, the partition count and the partition index come first
+        let error_code = 3 + topic.len() + 5;
+        i16::from_be_bytes(body[error_code..error_code + 2].try_into().unwrap())
+    }
+
+    /// Waits for `check` to hold, for up to 10 seconds
+    async fn wait_for(mut check: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("timed out");
+    }
+
+    #[tokio::test]
+    async fn test_replication() {
+        use crate::request::{
+            LeaderAndIsrLiveLeader, LeaderAndIsrPartitionState, LeaderAndIsrRequestBody,
+            LeaderAndIsrTopicState,
+        };
+
+        let mut brokers = Vec::new();
+        for node_id in 1..=3 {
+            let dir = tempfile::tempdir().unwrap();
+            let mut props = Properties::default();
+            props.set("node.id", node_id.to_string());
+            props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+            props.set("log.dirs", dir.path().to_str().unwrap());
+            props.set("replica.lag.time.max.ms", "1000");
+            props.set("replica.fetch.wait.max.ms", "100");
+            props.set("replica.fetch.backoff.ms", "100");
+            let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+                .await
+                .unwrap();
+            let addr = broker.local_addr("PLAINTEXT").unwrap();
+            let handle = broker.shutdown_handle();
+            let state = Arc::clone(&broker.state);
+            brokers.push((dir, addr, handle, state, tokio::spawn(broker.run())));
+        }
+
+        // broker 1 leads, the others follow, and only the leader is in sync yet
+        let leader = brokers[0].1;
+        let state = LeaderAndIsrPartitionState::new(0, 1, 1, 0, vec![1], vec![1, 2, 3]);
+        let topics = vec![LeaderAndIsrTopicState::new("rep", vec![state].into())];
+        let live_leaders = vec![LeaderAndIsrLiveLeader::new(
+            1,
+            leader.ip().to_string(),
+            i32::from(leader.port()),
+        )];
+        let mut body = BytesMut::new();
+        LeaderAndIsrRequestBody::new(0, 1, topics.into(), live_leaders.into())
+            .encode(&mut body)
+            .unwrap();
+        for (_, addr, _, _, _) in &brokers {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(&request(4, 4, 1, &body, true))
+                .await
+                .unwrap();
+            let (_, response) = read_response(&mut client).await;
+            assert_eq!(b"\x00\x00\x02", &response[1..4]);
+        }
+
+        // the followers catch up and join the ISR
+        let tp = TopicPartition::new("rep", 0);
+        let partition = brokers[0].3.replica_manager.get_partition(&tp).unwrap();
+        wait_for(|| partition.lock().unwrap().isr().len() == 3).await;
+
+        // acks=all completes once every replica has the record
+        let mut client = TcpStream::connect(leader).await.unwrap();
+        assert_eq!(0, produce(&mut client, 2, "rep").await);
+        for (_, _, _, state, _) in &brokers {
+            let partition = state.replica_manager.get_partition(&tp).unwrap();
+            let partition = partition.lock().unwrap();
+            assert_eq!(1, partition.log().lock().unwrap().log_end_offset());
+        }
+
+        // a stopped follower falls out of the ISR, acks=all goes on without it
+        let (_dir, _, handle, _, running) = brokers.pop().unwrap();
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+        wait_for(|| partition.lock().unwrap().isr() == [1, 2]).await;
+        assert_eq!(0, produce(&mut client, 3, "rep").await);
+        assert_eq!(
+            2,
+            partition
+                .lock()
+                .unwrap()
+                .log()
+                .lock()
+                .unwrap()
+                .high_watermark()
+        );
+
+        for (_dir, _, handle, _, running) in brokers {
+            handle.shutdown();
+            running.await.unwrap().unwrap();
+        }
+    }
+
+    /// A node of the `KRaft` quorum of `voters`, combining broker and controller if it
+    /// has a `controller_port`, formatted with the same cluster id as the others. Clients
+    /// connect to the last address.
+    async fn start_kraft_node(
+        node_id: i32,
+        voters: &[String],
+        controller_port: Option<u16>,
+    ) -> (
+        tempfile::TempDir,
+        ShutdownHandle,
+        Arc<BrokerState>,
+        JoinHandle<anyhow::Result<()>>,
+        SocketAddr,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        MetaProperties {
+            cluster_id: "kraft-test".to_string(),
+            node_id,
+            directory_id: None,
+        }
+        .write(dir.path())
+        .unwrap();
+        let mut props = Properties::default();
+        props.set("node.id", node_id.to_string());
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set("controller.quorum.voters", voters.join(","));
+        props.set("controller.quorum.election.timeout.ms", "200");
+        props.set("controller.quorum.election.backoff.max.ms", "200");
+        props.set("controller.quorum.fetch.timeout.ms", "1000");
+        props.set("controller.quorum.request.timeout.ms", "1000");
+        props.set("broker.heartbeat.interval.ms", "100");
+        if let Some(port) = controller_port {
+            props.set("process.roles", "broker,controller");
+            props.set(
+                "listeners",
+                format!("PLAINTEXT://127.0.0.1:0,CONTROLLER://127.0.0.1:{port}"),
+            );
+            props.set(
+                "listener.security.protocol.map",
+                "PLAINTEXT:PLAINTEXT,CONTROLLER:PLAINTEXT",
+            );
+            props.set("controller.listener.names", "CONTROLLER");
+        } else {
+            props.set("process.roles", "broker");
+            props.set("listeners", "PLAINTEXT://127.0.0.1:0");
+        }
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("PLAINTEXT").unwrap();
+        let handle = broker.shutdown_handle();
+        let state = Arc::clone(&broker.state);
+        (dir, handle, state, tokio::spawn(broker.run()), addr)
+    }
+
+    #[tokio::test]
+    async fn test_kraft_quorum() {
+        use crate::{
+            metadata::{MetadataRecord, PartitionRecord, TopicRecord},
+            primitives::Uuid,
+        };
+
+        // the voters have to know each other's address before any of them starts
+        let mut ports = Vec::new();
+        for _ in 0..3 {
+            let socket = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            ports.push(socket.local_addr().unwrap().port());
+        }
+        let voters: Vec<String> = ports
+            .iter()
+            .zip(1..)
+            .map(|(port, id)| format!("{id}@127.0.0.1:{port}"))
+            .collect();
+
+        // three controllers, and a broker observing the quorum
+        let mut nodes = Vec::new();
+        for (node_id, port) in (1..=4).zip(ports.iter().copied().map(Some).chain([None])) {
+            nodes.push(start_kraft_node(node_id, &voters, port).await);
+        }
+
+        // the controllers elect one of them, the others and the observer follow it
+        let rafts: Vec<_> = nodes
+            .iter()
+            .map(|node| Arc::clone(node.2.raft.as_ref().unwrap()))
+            .collect();
+        wait_for(|| rafts.iter().filter(|raft| raft.is_leader()).count() == 1).await;
+        let leader = rafts.iter().position(|raft| raft.is_leader()).unwrap();
+        let leader_id = rafts[leader].node_id();
+        wait_for(|| {
+            rafts
+                .iter()
+                .all(|raft| raft.leader().leader_id == leader_id)
+        })
+        .await;
+        assert!(!rafts[3].is_voter());
+
+        // every node replays the committed records into its image
+        let id = Uuid::random();
+        let records = vec![
+            MetadataRecord::Topic(TopicRecord::new("meta", id))
+                .to_record(0)
+                .unwrap(),
+            MetadataRecord::Partition(PartitionRecord::new(0, id, vec![1, 2]))
+                .to_record(1)
+                .unwrap(),
+        ];
+        rafts[leader].append(records).unwrap();
+        for (_, _, state, _, _) in &nodes {
+            wait_for(|| {
+                state
+                    .metadata_image
+                    .read()
+                    .unwrap()
+                    .topic_by_name("meta")
+                    .is_some()
+            })
+            .await;
+            let image = state.metadata_image.read().unwrap();
+            assert_eq!(id, image.topic_by_name("meta").unwrap().id);
+        }
+
+        // every broker registers with the active controller, and is unfenced once caught up
+        let image = &nodes[leader].2.metadata_image;
+        wait_for(|| (1..=4).all(|id| !image.read().unwrap().is_fenced(id))).await;
+
+        // the quorum elects a new leader once the old one resigns on shutdown
+        let (_dir, handle, _, running, _) = nodes.remove(leader);
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+        let voters = || {
+            rafts[..3]
+                .iter()
+                .enumerate()
+                .filter(move |&(i, _)| i != leader)
+        };
+        wait_for(|| voters().any(|(_, raft)| raft.is_leader())).await;
+        // its broker was fenced on the way out
+        let image = &nodes[0].2.metadata_image;
+        wait_for(|| image.read().unwrap().is_fenced(leader_id)).await;
+
+        for (_dir, handle, _, running, _) in nodes {
+            handle.shutdown();
+            running.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_topics() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (dir, handle, state, running, addr) =
+            start_kraft_node(1, &[format!("1@127.0.0.1:{port}")], Some(port)).await;
+        let image = &state.metadata_image;
+        wait_for(|| image.read().unwrap().broker(1).is_some_and(|b| !b.fenced)).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        // a topic taking the default partition count and replication factor with a
+        // config, and one whose name is not allowed
+        let create =
+            b"\x03\x08created\xff\xff\xff\xff\xff\xff\x01\x02\x0dretention.ms\x051000\x00\x00\
+            \x0abad/topic\xff\xff\xff\xff\xff\xff\x01\x01\x00\x00\x00\x13\x88\x00\x00";
+        client
+            .write_all(&request(19, 7, 1, create, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        // the name, the topic id, then no error and one partition of one replica
+        let topic_id = &body[14..30];
+        assert_eq!(b"\x00\x00\x00\x00\x00\x00\x01\x00\x01", &body[30..39]);
+        assert_eq!(
+            b"\x02\x0dretention.ms\x051000\x00\x01\x00\x00",
+            &body[39..62]
+        );
+        // the second topic follows the tag buffer of the first one
+        let invalid = 63 + 10 + 16;
+        assert_eq!(
+            ErrorCode::InvalidTopicException.code().to_be_bytes(),
+            body[invalid..invalid + 2]
+        );
+
+        // every broker replays the records, creating the partition led by its replica
+        {
+            let image = image.read().unwrap();
+            let topic = image.topic_by_name("created").unwrap();
+            assert_eq!(topic_id, &topic.id.0[..]);
+            assert_eq!(vec![1], topic.partitions[&0].replicas);
+            let configs = image
+                .configs(&ConfigEntity::Topic("created".into()))
+                .unwrap();
+            assert_eq!("1000", configs["retention.ms"]);
+        }
+        let tp = TopicPartition::new("created", 0);
+        wait_for(|| state.replica_manager.get_partition(&tp).is_some()).await;
+        assert!(dir.path().join("created-0").is_dir());
+
+        // creating it again fails
+        let mut again = b"\x02".to_vec();
+        again.extend_from_slice(&create[1..37]);
+        again.extend_from_slice(b"\x00\x00\x13\x88\x00\x00");
+        client
+            .write_all(&request(19, 7, 2, &again, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(
+            ErrorCode::TopicAlreadyExists.code().to_be_bytes(),
+            body[30..32]
+        );
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_kraft_admin_changes() {
+        use crate::{
+            quota::{QuotaEntity, QuotaType},
+            security::ScramMechanism,
+        };
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (_dir, handle, state, running, addr) =
+            start_kraft_node(1, &[format!("1@127.0.0.1:{port}")], Some(port)).await;
+        let image = &state.metadata_image;
+        wait_for(|| image.read().unwrap().broker(1).is_some_and(|b| !b.fenced)).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        // the retention of every broker, answered once the record was replayed
+        let alter = b"\x02\x04\x01\x02\x11log.retention.ms\x00\x051000\x00\x00\x00\x00";
+        client
+            .write_all(&request(44, 1, 1, alter, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x00\x00\x00\x04\x01", &body[5..11]);
+        let configs = image
+            .read()
+            .unwrap()
+            .configs(&ConfigEntity::DefaultBroker)
+            .cloned()
+            .unwrap();
+        assert_eq!("1000", configs["log.retention.ms"]);
+        wait_for(|| {
+            let configs = state.configs.configs(&ConfigEntity::DefaultBroker);
+            configs.get("log.retention.ms").is_some_and(|v| v == "1000")
+        })
+        .await;
+
+        // a produce quota of alice
+        let mut alter = b"\x02\x02\x05user\x06alice\x00\x02\x13producer_byte_rate".to_vec();
+        alter.extend_from_slice(&1024.0_f64.to_be_bytes());
+        alter.extend_from_slice(b"\x00\x00\x00\x00\x00");
+        client
+            .write_all(&request(49, 1, 2, &alter, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x00\x00", &body[5..8]);
+        let alice = QuotaEntity::from_components([("user", Some("alice"))]).unwrap();
+        let quotas = image.read().unwrap().quotas(&alice).cloned().unwrap();
+        assert_eq!(Some(&1024.0), quotas.get("producer_byte_rate"));
+        wait_for(|| {
+            let quota = state.quotas.quota(QuotaType::Produce, "alice", "any");
+            quota.map(|(rate, _)| rate) == Some(1024.0)
+        })
+        .await;
+
+        // a SCRAM credential of alice, 4096 iterations
+        let salt = b"salt of alice";
+        let salted_password = ScramMechanism::Sha256.salt_password(b"alice-secret", salt, 4096);
+        let mut upsertion = b"\x01\x02\x06alice\x01\x00\x00\x10\x00".to_vec();
+        upsertion.push(u8::try_from(salt.len() + 1).unwrap());
+        upsertion.extend_from_slice(salt);
+        upsertion.push(u8::try_from(salted_password.len() + 1).unwrap());
+        upsertion.extend_from_slice(&salted_password);
+        upsertion.extend_from_slice(b"\x00\x00");
+        client
+            .write_all(&request(51, 0, 3, &upsertion, true))
+            .await
+            .unwrap();
+        let (_, body) = read_response(&mut client).await;
+        assert_eq!(b"\x02\x06alice\x00\x00\x00\x00\x00", &body[5..]);
+        let key = (ScramMechanism::Sha256, "alice".to_string());
+        let credential = image.read().unwrap().scram_credentials()[&key].clone();
+        assert_eq!(4096, credential.iterations);
+        wait_for(|| {
+            let stored = state.credentials.scram(ScramMechanism::Sha256, "alice");
+            stored.is_some_and(|c| c == credential)
+        })
+        .await;
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+
+    /// Writes a certificate for `name` signed by `ca` and its key to `dir/name.pem`,
+    /// returns the certificate and key
+    fn issue_certificate(
+        dir: &std::path::Path,
+        name: &str,
+        ca: &rcgen::Issuer<'_, rcgen::KeyPair>,
+    ) -> (
+        tokio_rustls::rustls::pki_types::CertificateDer<'static>,
+        tokio_rustls::rustls::pki_types::PrivateKeyDer<'static>,
+    ) {
+        let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "corp");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, name);
+        let key = rcgen::KeyPair::generate().unwrap();
+        let certificate = params.signed_by(&key, ca).unwrap();
+        std::fs::write(
+            dir.join(format!("{name}.pem")),
+            certificate.pem() + &key.serialize_pem(),
+        )
+        .unwrap();
+        let key = key.serialize_der().try_into().unwrap();
+        (certificate.der().clone(), key)
+    }
+
+    #[tokio::test]
+    async fn test_ssl_client_auth() {
+        use tokio_rustls::rustls::{
+            ClientConfig, RootCertStore, crypto::ring, pki_types::ServerName,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut ca_params = rcgen::CertificateParams::default();
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "test-ca");
+        let ca_key = rcgen::KeyPair::generate().unwrap();
+        let ca = rcgen::CertifiedIssuer::self_signed(ca_params, ca_key).unwrap();
+        std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+        issue_certificate(dir.path(), "broker", &ca);
+        let alice = issue_certificate(dir.path(), "alice", &ca);
+        let admin = issue_certificate(dir.path(), "admin", &ca);
+
+        let mut props = Properties::default();
+        props.set("listeners", "SSL://127.0.0.1:0");
+        props.set("log.dirs", dir.path().to_str().unwrap());
+        props.set(
+            "ssl.keystore.location",
+            dir.path().join("broker.pem").to_str().unwrap(),
+        );
+        props.set(
+            "ssl.truststore.location",
+            dir.path().join("ca.pem").to_str().unwrap(),
+        );
+        props.set("ssl.client.auth", "required");
+        props.set("ssl.principal.mapping.rules", "RULE:^CN=admin,O=corp$/$1/");
+        let broker = Broker::new(BrokerConfig::from_properties(&props).unwrap())
+            .await
+            .unwrap();
+        let addr = broker.local_addr("SSL").unwrap();
+        let handle = broker.shutdown_handle();
+        let running = tokio::spawn(broker.run());
+
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        let client_config = || {
+            ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots.clone())
+        };
+        let connect = |config: ClientConfig| async move {
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let mut client = connector
+                .connect(ServerName::try_from("localhost").unwrap(), stream)
+                .await?;
+            client.write_all(&api_versions_request(1)).await?;
+            let mut size = [0; 4];
+            client.read_exact(&mut size).await?;
+            Ok::<_, std::io::Error>(client)
+        };
+
+        let (certificate, key) = admin;
+        let config = client_config()
+            .with_client_auth_cert(vec![certificate], key)
+            .unwrap();
+        assert!(connect(config).await.is_ok());
+        // without a certificate, or one no mapping rule applies to
+        assert!(
+            connect(client_config().with_no_client_auth())
+                .await
+                .is_err()
+        );
+        let (certificate, key) = alice;
+        let config = client_config()
+            .with_client_auth_cert(vec![certificate], key)
+            .unwrap();
+        assert!(connect(config).await.is_err());
+
+        handle.shutdown();
+        running.await.unwrap().unwrap();
+    }
+}
@@ -1,6 +1,7 @@
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, BytesMut};
 
-pub const MAX_MESSAGE_SIZE: usize = 128;
+/// Largest request accepted, the default of Kafka's `socket.request.max.bytes`
+pub const MAX_MESSAGE_SIZE: usize = 100 * 1024 * 1024;
 
 pub trait Decoder {
     /// Decodes bytes read from `src` as a new instance of Self.
@@ -37,8 +38,33 @@ impl<T: WireLen> WireLen for &[T] {
     }
 }
 
+/// Fields missing from some versions of a message take no space when absent
+impl<T: WireLen> WireLen for Option<T> {
+    fn wire_len(&self) -> usize {
+        self.as_ref().map_or(0, WireLen::wire_len)
+    }
+}
+
 impl<T: WireLen> WireLen for Vec<T> {
     fn wire_len(&self) -> usize {
         self.as_slice().wire_len()
     }
 }
+
+/// Lets arrays of INT32, like the partition indexes of a topic, be decoded
+impl Decoder for i32 {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < size_of::<i32>() {
+            src.reserve(size_of::<i32>());
+            return Ok(None);
+        }
+        Ok(Some(src.get_i32()))
+    }
+}
+
+impl Encoder for i32 {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        dest.put_i32(*self);
+        Ok(())
+    }
+}
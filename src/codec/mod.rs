@@ -14,7 +14,7 @@ macro_rules! impl_wire_length {
     };
 }
 
-impl_wire_length!(u8 u16 u32 u64 i8 i16 i32 i64 usize isize bool);
+impl_wire_length!(u8 u16 u32 u64 i8 i16 i32 i64 f64 usize isize bool);
 
 /// A convenience macro which makes it easier to compose `decode` functions
 /// defined in both `tokio_util::codec::Decoder` and my custom Decoder.
@@ -24,7 +24,7 @@ impl_wire_length!(u8 u16 u32 u64 i8 i16 i32 i64 usize isize bool);
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```ignore
 /// let x: CustomType = match CustomType::decode(src)
 ///     Ok(opt) => match opt {
 ///         Some(val) => val    
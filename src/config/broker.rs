@@ -0,0 +1,830 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{Context, bail, ensure};
+use tracing::info;
+
+use super::{Listener, ProcessRole, Properties, QuorumVoter, SecurityProtocol};
+use crate::{
+    codec::MAX_MESSAGE_SIZE,
+    security::{AclAuthorizer, SaslMechanism, SslClientAuth, SslPrincipalMapper},
+    storage::{self, LogConfig},
+};
+
+/// Broker settings, read from a `server.properties` file using Kafka's key names.
+/// Keys the broker does not know about are ignored, so existing files can be reused as is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerConfig {
+    /// `node.id` (or the older `broker.id`)
+    pub node_id: i32,
+    /// `process.roles`, whether the node is a broker, a controller of the KRaft quorum or
+    /// both. Empty for a standalone broker without a metadata quorum.
+    pub process_roles: Vec<ProcessRole>,
+    /// `controller.quorum.voters`, the controllers of the KRaft quorum
+    pub controller_quorum_voters: Vec<QuorumVoter>,
+    /// `controller.quorum.election.timeout.ms`, how long a voter waits without hearing
+    /// from a leader before it starts an election
+    pub controller_quorum_election_timeout: Duration,
+    /// `controller.quorum.fetch.timeout.ms`, how long a follower keeps fetching from a
+    /// leader that does not answer before it looks for a new one
+    pub controller_quorum_fetch_timeout: Duration,
+    /// `controller.quorum.election.backoff.max.ms`, the longest a candidate waits before
+    /// starting another election after losing one
+    pub controller_quorum_election_backoff_max: Duration,
+    /// `controller.quorum.request.timeout.ms`, how long requests between the nodes of the
+    /// quorum may take
+    pub controller_quorum_request_timeout: Duration,
+    /// `metadata.log.max.record.bytes.between.snapshots`, how many bytes of the metadata
+    /// log are replayed before the image is snapshotted again
+    pub metadata_log_max_record_bytes_between_snapshots: u64,
+    /// `metadata.log.max.snapshot.interval.ms`, the longest the image goes without a
+    /// snapshot once the metadata log grew, zero to only snapshot by size
+    pub metadata_log_max_snapshot_interval: Duration,
+    /// `broker.session.timeout.ms`, how long the controller waits for a heartbeat before
+    /// fencing a broker
+    pub broker_session_timeout: Duration,
+    /// `broker.heartbeat.interval.ms`, how often brokers send heartbeats to the controller
+    pub broker_heartbeat_interval: Duration,
+    /// `listeners`, the addresses the broker accepts connections on
+    pub listeners: Vec<Listener>,
+    /// `advertised.listeners`, the addresses handed out to clients. Defaults to
+    /// `listeners`, without the controller listeners.
+    pub advertised_listeners: Vec<Listener>,
+    /// `listener.security.protocol.map`, the security protocol of every listener name
+    pub listener_security_protocol_map: BTreeMap<String, SecurityProtocol>,
+    /// `controller.listener.names`, listeners only used by the KRaft controller quorum
+    pub controller_listener_names: Vec<String>,
+    /// `broker.rack`, the rack the broker is in, as reported to clients
+    pub broker_rack: Option<String>,
+    /// `log.dirs` (or `log.dir`)
+    pub log_dirs: Vec<PathBuf>,
+    /// `num.partitions`, the partition count of created topics that do not ask for one
+    pub num_partitions: i32,
+    /// `default.replication.factor`, the replica count of created topics that do not ask
+    /// for one
+    pub default_replication_factor: i16,
+    /// Defaults of the topic level configs, set through their `log.` prefixed broker keys
+    pub log: LogConfig,
+    /// `log.retention.check.interval.ms`
+    pub log_retention_check_interval: Duration,
+    /// `log.cleaner.backoff.ms`
+    pub log_cleaner_backoff: Duration,
+    /// `socket.request.max.bytes`, requests larger than this close the connection
+    pub socket_request_max_bytes: usize,
+    /// `max.in.flight.requests.per.connection`, how many pipelined requests of a single
+    /// connection are handled at once. Reading from the connection pauses at the limit.
+    pub max_in_flight_requests_per_connection: usize,
+    /// `max.connections`, connections accepted past it are closed right away
+    pub max_connections: usize,
+    /// `max.connections.per.ip`, the same for the connections of a single client address
+    pub max_connections_per_ip: usize,
+    /// `max.connections.per.ip.overrides`, e.g. `127.0.0.1:200,hostname:50`, per address
+    /// limits replacing `max.connections.per.ip`
+    pub max_connections_per_ip_overrides: BTreeMap<String, usize>,
+    /// `max.connection.creation.rate`, new connections per second. Accepting slows down
+    /// once they arrive faster.
+    pub max_connection_creation_rate: u32,
+    /// `connections.max.idle.ms`, connections sending no requests for that long are closed
+    pub connections_max_idle: Duration,
+    /// `connections.max.reauth.ms`, how long SASL sessions last before clients have to
+    /// re-authenticate. Zero lets them last as long as the connection.
+    pub connections_max_reauth: Duration,
+    /// `sasl.enabled.mechanisms`, the mechanisms clients of SASL listeners may use
+    pub sasl_enabled_mechanisms: Vec<SaslMechanism>,
+    /// `sasl.plain.credentials.file`, a properties file of the `user=password` pairs
+    /// PLAIN authenticates against
+    pub sasl_plain_credentials_file: Option<PathBuf>,
+    /// `ssl.keystore.location`, a PEM file with the certificate chain of SSL listeners.
+    /// Also holds their private key, unless `ssl.key.location` is set.
+    pub ssl_keystore_location: Option<PathBuf>,
+    /// `ssl.key.location`, a PEM file with the private key of SSL listeners
+    pub ssl_key_location: Option<PathBuf>,
+    /// `ssl.truststore.location`, a PEM file with the CA certificates client certificates
+    /// are verified against
+    pub ssl_truststore_location: Option<PathBuf>,
+    /// `ssl.client.auth`, whether clients of SSL listeners authenticate with certificates
+    pub ssl_client_auth: SslClientAuth,
+    /// `ssl.principal.mapping.rules`, how principals are taken from client certificates
+    pub ssl_principal_mapping_rules: SslPrincipalMapper,
+    /// `authorizer.class.name`, the authorizer deciding what clients may do. Either of
+    /// [`AclAuthorizer::CLASS_NAMES`] enables ACLs, without one everything is allowed.
+    pub authorizer_class_name: Option<String>,
+    /// `super.users`, principals like `User:admin` separated by semicolons, which the
+    /// authorizer allows everything
+    pub super_users: Vec<String>,
+    /// `allow.everyone.if.no.acl.found`, whether resources without ACLs are accessible
+    /// to every principal
+    pub allow_everyone_if_no_acl_found: bool,
+    /// `metrics.address`, the `host:port` the Prometheus metrics are served on over
+    /// HTTP. Not served unless set.
+    pub metrics_address: Option<String>,
+    /// `quota.window.num`, how many windows client quota rates are measured over
+    pub quota_window_num: u32,
+    /// `quota.window.size.seconds`, the length of each of those windows
+    pub quota_window_size: Duration,
+    /// `replica.lag.time.max.ms`, followers that have not caught up with the leader's log
+    /// end offset for this long are removed from the ISR
+    pub replica_lag_time_max: Duration,
+    /// `replica.fetch.wait.max.ms`, how long the fetches of followers wait for new records
+    pub replica_fetch_wait_max: Duration,
+    /// `replica.fetch.min.bytes`, how many bytes the fetches of followers wait for
+    pub replica_fetch_min_bytes: i32,
+    /// `replica.fetch.max.bytes`, how many bytes followers fetch of every partition at most
+    pub replica_fetch_max_bytes: i32,
+    /// `replica.fetch.response.max.bytes`, how many bytes a fetch of a follower returns
+    /// at most
+    pub replica_fetch_response_max_bytes: i32,
+    /// `replica.fetch.backoff.ms`, how long followers wait after a fetch failed
+    pub replica_fetch_backoff: Duration,
+    /// `replica.socket.timeout.ms`, how long followers wait for the leader to respond
+    pub replica_socket_timeout: Duration,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        let listeners = vec![Listener {
+            name: "PLAINTEXT".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 9092,
+        }];
+        Self {
+            node_id: 1,
+            process_roles: Vec::new(),
+            controller_quorum_voters: Vec::new(),
+            controller_quorum_election_timeout: Duration::from_secs(1),
+            controller_quorum_fetch_timeout: Duration::from_secs(2),
+            controller_quorum_election_backoff_max: Duration::from_secs(1),
+            controller_quorum_request_timeout: Duration::from_secs(2),
+            metadata_log_max_record_bytes_between_snapshots: 20 * 1024 * 1024,
+            metadata_log_max_snapshot_interval: Duration::from_secs(60 * 60),
+            broker_session_timeout: Duration::from_secs(9),
+            broker_heartbeat_interval: Duration::from_secs(2),
+            advertised_listeners: listeners.clone(),
+            listeners,
+            listener_security_protocol_map: SecurityProtocol::default_map(),
+            controller_listener_names: Vec::new(),
+            broker_rack: None,
+            log_dirs: vec![PathBuf::from("/tmp/kraft-combined-logs")],
+            num_partitions: 1,
+            default_replication_factor: 1,
+            log: LogConfig::default(),
+            log_retention_check_interval: storage::DEFAULT_RETENTION_CHECK_INTERVAL,
+            log_cleaner_backoff: storage::DEFAULT_CLEANER_BACKOFF,
+            socket_request_max_bytes: MAX_MESSAGE_SIZE,
+            max_in_flight_requests_per_connection: 5,
+            max_connections: i32::MAX as usize,
+            max_connections_per_ip: i32::MAX as usize,
+            max_connections_per_ip_overrides: BTreeMap::new(),
+            max_connection_creation_rate: i32::MAX as u32,
+            connections_max_idle: Duration::from_secs(10 * 60),
+            connections_max_reauth: Duration::ZERO,
+            sasl_enabled_mechanisms: SaslMechanism::ALL.to_vec(),
+            sasl_plain_credentials_file: None,
+            ssl_keystore_location: None,
+            ssl_key_location: None,
+            ssl_truststore_location: None,
+            ssl_client_auth: SslClientAuth::None,
+            ssl_principal_mapping_rules: SslPrincipalMapper::default(),
+            authorizer_class_name: None,
+            super_users: Vec::new(),
+            allow_everyone_if_no_acl_found: false,
+            metrics_address: None,
+            quota_window_num: 11,
+            quota_window_size: Duration::from_secs(1),
+            replica_lag_time_max: Duration::from_secs(30),
+            replica_fetch_wait_max: Duration::from_millis(500),
+            replica_fetch_min_bytes: 1,
+            replica_fetch_max_bytes: 1024 * 1024,
+            replica_fetch_response_max_bytes: 10 * 1024 * 1024,
+            replica_fetch_backoff: Duration::from_secs(1),
+            replica_socket_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BrokerConfig {
+    const USAGE: &str = "Usage: codecrafters-kafka [server.properties] [--override key=value]...";
+
+    /// Builds the config from the command line: an optional properties file,
+    /// followed by any number of `--override key=value` pairs applied on top of it
+    pub fn from_args<I>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut path = None;
+        let mut overrides = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--override" => {
+                    let pair = args
+                        .next()
+                        .with_context(|| format!("--override needs a value\n{}", Self::USAGE))?;
+                    let Some((key, value)) = pair.split_once('=') else {
+                        bail!("Override {pair:?} is not a key=value pair\n{}", Self::USAGE);
+                    };
+                    overrides.push((key.trim().to_string(), value.trim().to_string()));
+                }
+                flag if flag.starts_with('-') => bail!("Unknown option {flag}\n{}", Self::USAGE),
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => bail!("Unexpected argument {arg}\n{}", Self::USAGE),
+            }
+        }
+
+        let mut props = match &path {
+            Some(path) => Properties::read(path)?,
+            None => Properties::default(),
+        };
+        for (key, value) in overrides {
+            props.set(key, value);
+        }
+        Self::from_properties(&props)
+    }
+
+    /// Builds and validates the config from `props`, using defaults for missing keys
+    pub fn from_properties(props: &Properties) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        let mut ignored = Vec::new();
+        for (key, value) in props.iter() {
+            let known = config
+                .set(key, value)
+                .with_context(|| format!("Invalid value {value:?} for {key}"))?;
+            if !known {
+                ignored.push(key);
+            }
+        }
+
+        // the most precise of the retention keys wins, as in Kafka
+        let retention_ms = [
+            ("log.retention.ms", 1),
+            ("log.retention.minutes", 60 * 1000),
+            ("log.retention.hours", 60 * 60 * 1000),
+        ]
+        .into_iter()
+        .find_map(|(key, unit)| props.get(key).map(|value| (key, value, unit)));
+        if let Some((key, value, unit)) = retention_ms {
+            let value: i64 = value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid value {value:?} for {key}"))?;
+            let ms = if value < 0 { -1 } else { value * unit };
+            config.log.set("retention.ms", &ms.to_string())?;
+        }
+        if props.get("advertised.listeners").is_none() {
+            config.advertised_listeners = config
+                .listeners
+                .iter()
+                .filter(|l| !config.controller_listener_names.contains(&l.name))
+                .cloned()
+                .collect();
+        }
+
+        if !ignored.is_empty() {
+            info!("Ignoring unsupported configs {}", ignored.join(", "));
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Applies a single broker key, returns false if the broker does not know it
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let value = value.trim();
+        match key {
+            "node.id" | "broker.id" => self.node_id = value.parse()?,
+            "process.roles" => self.process_roles = ProcessRole::parse_list(value)?,
+            "controller.quorum.voters" => {
+                self.controller_quorum_voters = QuorumVoter::parse_list(value)?;
+            }
+            "controller.quorum.election.timeout.ms" => {
+                self.controller_quorum_election_timeout = Duration::from_millis(value.parse()?);
+            }
+            "controller.quorum.fetch.timeout.ms" => {
+                self.controller_quorum_fetch_timeout = Duration::from_millis(value.parse()?);
+            }
+            "controller.quorum.election.backoff.max.ms" => {
+                self.controller_quorum_election_backoff_max =
+                    Duration::from_millis(value.parse()?);
+            }
+            "controller.quorum.request.timeout.ms" => {
+                self.controller_quorum_request_timeout = Duration::from_millis(value.parse()?);
+            }
+            "metadata.log.max.record.bytes.between.snapshots" => {
+                self.metadata_log_max_record_bytes_between_snapshots = value.parse()?;
+            }
+            "metadata.log.max.snapshot.interval.ms" => {
+                self.metadata_log_max_snapshot_interval = Duration::from_millis(value.parse()?);
+            }
+            "broker.session.timeout.ms" => {
+                self.broker_session_timeout = Duration::from_millis(value.parse()?);
+            }
+            "broker.heartbeat.interval.ms" => {
+                self.broker_heartbeat_interval = Duration::from_millis(value.parse()?);
+            }
+            "listeners" => self.listeners = Listener::parse_list(value)?,
+            "advertised.listeners" => self.advertised_listeners = Listener::parse_list(value)?,
+            "broker.rack" => self.broker_rack = Some(value.to_string()).filter(|r| !r.is_empty()),
+            "listener.security.protocol.map" => {
+                self.listener_security_protocol_map = SecurityProtocol::parse_map(value)?;
+            }
+            "controller.listener.names" => {
+                self.controller_listener_names = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+            }
+            "log.dirs" | "log.dir" => {
+                self.log_dirs = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(PathBuf::from)
+                    .collect();
+            }
+            "num.partitions" => self.num_partitions = value.parse()?,
+            "default.replication.factor" => self.default_replication_factor = value.parse()?,
+            "log.retention.check.interval.ms" => {
+                self.log_retention_check_interval = Duration::from_millis(value.parse()?);
+            }
+            "log.cleaner.backoff.ms" => {
+                self.log_cleaner_backoff = Duration::from_millis(value.parse()?);
+            }
+            "socket.request.max.bytes" => self.socket_request_max_bytes = value.parse()?,
+            "max.in.flight.requests.per.connection" => {
+                self.max_in_flight_requests_per_connection = value.parse()?;
+            }
+            "max.connections" => self.max_connections = value.parse()?,
+            "max.connections.per.ip" => self.max_connections_per_ip = value.parse()?,
+            "max.connections.per.ip.overrides" => {
+                self.max_connections_per_ip_overrides = Self::parse_overrides(value)?;
+            }
+            "max.connection.creation.rate" => self.max_connection_creation_rate = value.parse()?,
+            "connections.max.idle.ms" => {
+                self.connections_max_idle = Duration::from_millis(value.parse()?);
+            }
+            "connections.max.reauth.ms" => {
+                self.connections_max_reauth = Duration::from_millis(value.parse()?);
+            }
+            "sasl.enabled.mechanisms" => {
+                self.sasl_enabled_mechanisms = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| {
+                        SaslMechanism::from_name(m)
+                            .with_context(|| format!("Unsupported SASL mechanism {m}"))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+            }
+            "sasl.plain.credentials.file" => {
+                self.sasl_plain_credentials_file =
+                    Some(value).filter(|f| !f.is_empty()).map(PathBuf::from);
+            }
+            "ssl.keystore.location" => {
+                self.ssl_keystore_location =
+                    Some(value).filter(|f| !f.is_empty()).map(PathBuf::from);
+            }
+            "ssl.key.location" => {
+                self.ssl_key_location = Some(value).filter(|f| !f.is_empty()).map(PathBuf::from);
+            }
+            "ssl.truststore.location" => {
+                self.ssl_truststore_location =
+                    Some(value).filter(|f| !f.is_empty()).map(PathBuf::from);
+            }
+            "ssl.client.auth" => self.ssl_client_auth = SslClientAuth::parse(value)?,
+            "ssl.principal.mapping.rules" => {
+                self.ssl_principal_mapping_rules = SslPrincipalMapper::parse(value)?;
+            }
+            "authorizer.class.name" => {
+                self.authorizer_class_name =
+                    Some(value).filter(|c| !c.is_empty()).map(Into::into);
+            }
+            "super.users" => {
+                self.super_users = value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|u| !u.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+            }
+            "allow.everyone.if.no.acl.found" => {
+                self.allow_everyone_if_no_acl_found = value.parse()?;
+            }
+            "metrics.address" => {
+                self.metrics_address = Some(value).filter(|a| !a.is_empty()).map(Into::into);
+            }
+            "quota.window.num" => self.quota_window_num = value.parse()?,
+            "quota.window.size.seconds" => {
+                self.quota_window_size = Duration::from_secs(value.parse()?);
+            }
+            "replica.lag.time.max.ms" => {
+                self.replica_lag_time_max = Duration::from_millis(value.parse()?);
+            }
+            "replica.fetch.wait.max.ms" => {
+                self.replica_fetch_wait_max = Duration::from_millis(value.parse()?);
+            }
+            "replica.fetch.min.bytes" => self.replica_fetch_min_bytes = value.parse()?,
+            "replica.fetch.max.bytes" => self.replica_fetch_max_bytes = value.parse()?,
+            "replica.fetch.response.max.bytes" => {
+                self.replica_fetch_response_max_bytes = value.parse()?;
+            }
+            "replica.fetch.backoff.ms" => {
+                self.replica_fetch_backoff = Duration::from_millis(value.parse()?);
+            }
+            "replica.socket.timeout.ms" => {
+                self.replica_socket_timeout = Duration::from_millis(value.parse()?);
+            }
+            // handled once every key is known, as they take precedence over each other
+            "log.retention.ms" | "log.retention.minutes" | "log.retention.hours" => {}
+            _ => match LogConfig::topic_key(key) {
+                Some(topic_key) => self.log.set(topic_key, value)?,
+                None => return Ok(false),
+            },
+        }
+        Ok(true)
+    }
+
+    /// Whether the node takes part in a KRaft quorum, as a voter or as an observer
+    /// following the metadata log
+    pub fn is_kraft(&self) -> bool {
+        !self.process_roles.is_empty()
+    }
+
+    /// Whether the node is a controller, a voter of the KRaft quorum
+    pub fn is_controller(&self) -> bool {
+        self.process_roles.contains(&ProcessRole::Controller)
+    }
+
+    /// Whether the node is a broker of a KRaft cluster, registering with the controller
+    pub fn is_broker(&self) -> bool {
+        self.process_roles.contains(&ProcessRole::Broker)
+    }
+
+    /// The security protocol of the listener called `listener_name`
+    pub fn security_protocol(&self, listener_name: &str) -> Option<SecurityProtocol> {
+        self.listener_security_protocol_map
+            .get(listener_name)
+            .copied()
+    }
+
+    /// The address clients connected through the listener called `listener_name` are
+    /// told to use, as returned in Metadata and DescribeCluster responses. `None` for
+    /// listeners that are not advertised, like the controller ones.
+    pub fn advertised_listener(&self, listener_name: &str) -> Option<&Listener> {
+        self.advertised_listeners
+            .iter()
+            .find(|l| l.name == listener_name)
+    }
+
+    /// Parses `host:count` pairs separated by commas. The count follows the last colon,
+    /// so IPv6 addresses need no brackets.
+    fn parse_overrides(value: &str) -> anyhow::Result<BTreeMap<String, usize>> {
+        let mut overrides = BTreeMap::new();
+        for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((host, count)) = pair.rsplit_once(':') else {
+                bail!("Override {pair:?} is not a host:count pair");
+            };
+            let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+            overrides.insert(host.to_string(), count.trim().parse()?);
+        }
+        Ok(overrides)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        ensure!(self.node_id >= 0, "node.id cannot be negative");
+        ensure!(!self.listeners.is_empty(), "listeners cannot be empty");
+        ensure!(!self.log_dirs.is_empty(), "log.dirs cannot be empty");
+        ensure!(
+            (1..=MAX_MESSAGE_SIZE).contains(&self.socket_request_max_bytes),
+            "socket.request.max.bytes must be between 1 and {MAX_MESSAGE_SIZE}"
+        );
+        ensure!(
+            self.max_in_flight_requests_per_connection >= 1,
+            "max.in.flight.requests.per.connection must be at least 1"
+        );
+        ensure!(
+            self.max_connections >= 1,
+            "max.connections must be at least 1"
+        );
+        ensure!(
+            self.max_connections_per_ip >= 1 || !self.max_connections_per_ip_overrides.is_empty(),
+            "max.connections.per.ip can only be 0 if max.connections.per.ip.overrides is set"
+        );
+        ensure!(
+            self.max_connection_creation_rate >= 1,
+            "max.connection.creation.rate must be at least 1"
+        );
+        ensure!(
+            !self.connections_max_idle.is_zero(),
+            "connections.max.idle.ms must be positive"
+        );
+        ensure!(
+            self.quota_window_num >= 1 && !self.quota_window_size.is_zero(),
+            "quota.window.num and quota.window.size.seconds must be at least 1"
+        );
+        ensure!(
+            self.num_partitions >= 1,
+            "num.partitions must be at least 1"
+        );
+        ensure!(
+            self.default_replication_factor >= 1,
+            "default.replication.factor must be at least 1"
+        );
+        // a follower waiting for records would otherwise fall out of the ISR
+        ensure!(
+            self.replica_fetch_wait_max < self.replica_lag_time_max,
+            "replica.fetch.wait.max.ms must be less than replica.lag.time.max.ms"
+        );
+        ensure!(
+            self.replica_fetch_wait_max <= self.replica_socket_timeout,
+            "replica.socket.timeout.ms cannot be less than replica.fetch.wait.max.ms"
+        );
+        ensure!(
+            self.replica_fetch_min_bytes >= 1
+                && self.replica_fetch_max_bytes >= 0
+                && self.replica_fetch_response_max_bytes >= 0,
+            "replica.fetch.min.bytes must be at least 1, the max bytes cannot be negative"
+        );
+
+        let mut names = HashSet::new();
+        let mut ports = HashSet::new();
+        for listener in &self.listeners {
+            ensure!(
+                names.insert(&listener.name),
+                "Listener name {} is used more than once",
+                listener.name
+            );
+            ensure!(
+                listener.port == 0 || ports.insert(listener.port),
+                "Port {} is used by more than one listener",
+                listener.port
+            );
+        }
+        for listener in &self.listeners {
+            ensure!(
+                self.security_protocol(&listener.name).is_some(),
+                "Listener {} is missing from listener.security.protocol.map",
+                listener.name
+            );
+        }
+        ensure!(
+            !self.sasl_enabled_mechanisms.is_empty()
+                || !self.listeners.iter().any(|l| {
+                    self.security_protocol(&l.name)
+                        .is_some_and(SecurityProtocol::is_sasl)
+                }),
+            "sasl.enabled.mechanisms cannot be empty with SASL listeners"
+        );
+        if self.listeners.iter().any(|l| {
+            self.security_protocol(&l.name)
+                .is_some_and(SecurityProtocol::is_ssl)
+        }) {
+            ensure!(
+                self.ssl_keystore_location.is_some(),
+                "ssl.keystore.location must be set with SSL listeners"
+            );
+            ensure!(
+                self.ssl_client_auth == SslClientAuth::None
+                    || self.ssl_truststore_location.is_some(),
+                "ssl.truststore.location must be set for ssl.client.auth={}",
+                self.ssl_client_auth
+            );
+        }
+        if let Some(class_name) = &self.authorizer_class_name {
+            ensure!(
+                AclAuthorizer::CLASS_NAMES.contains(&class_name.as_str()),
+                "Unsupported authorizer.class.name {class_name}, expected one of {}",
+                AclAuthorizer::CLASS_NAMES.join(", ")
+            );
+        }
+        for name in &self.controller_listener_names {
+            ensure!(
+                names.contains(name),
+                "Controller listener {name} is not one of the listeners"
+            );
+        }
+        for advertised in &self.advertised_listeners {
+            ensure!(
+                names.contains(&advertised.name),
+                "Advertised listener {advertised} has no matching listener"
+            );
+        }
+        self.validate_quorum()
+    }
+
+    fn validate_quorum(&self) -> anyhow::Result<()> {
+        if !self.is_kraft() {
+            return Ok(());
+        }
+        ensure!(
+            !self.controller_quorum_voters.is_empty(),
+            "controller.quorum.voters must be set with process.roles"
+        );
+        let is_voter = self
+            .controller_quorum_voters
+            .iter()
+            .any(|v| v.id == self.node_id);
+        if self.is_controller() {
+            ensure!(
+                is_voter,
+                "Controller {} is missing from controller.quorum.voters",
+                self.node_id
+            );
+            ensure!(
+                !self.controller_listener_names.is_empty(),
+                "controller.listener.names must be set for controllers"
+            );
+        } else {
+            ensure!(
+                !is_voter,
+                "Node {} is a voter, so process.roles must include controller",
+                self.node_id
+            );
+        }
+        // the nodes of the quorum talk to each other in plaintext
+        for name in &self.controller_listener_names {
+            ensure!(
+                self.security_protocol(name) == Some(SecurityProtocol::Plaintext),
+                "Controller listener {name} must use PLAINTEXT"
+            );
+        }
+        ensure!(
+            !self.controller_quorum_election_timeout.is_zero()
+                && !self.controller_quorum_fetch_timeout.is_zero()
+                && !self.controller_quorum_request_timeout.is_zero(),
+            "The controller.quorum timeouts must be positive"
+        );
+        ensure!(
+            self.metadata_log_max_record_bytes_between_snapshots > 0,
+            "metadata.log.max.record.bytes.between.snapshots must be positive"
+        );
+        ensure!(
+            !self.broker_heartbeat_interval.is_zero()
+                && self.broker_heartbeat_interval < self.broker_session_timeout,
+            "broker.heartbeat.interval.ms must be positive and below broker.session.timeout.ms"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_args(args: &[&str]) -> anyhow::Result<BrokerConfig> {
+        BrokerConfig::from_args(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn test_server_properties() {
+        let props = Properties::parse(
+            "process.roles=broker,controller\n\
+             node.id=2\n\
+             controller.quorum.voters=1@host1:9093,2@host2:9093\n\
+             controller.listener.names=CONTROLLER\n\
+             listeners=PLAINTEXT://:9092,CONTROLLER://:9093\n\
+             listener.security.protocol.map=PLAINTEXT:PLAINTEXT,CONTROLLER:PLAINTEXT\n\
+             advertised.listeners=PLAINTEXT://localhost:9092\n\
+             log.dirs=/tmp/a,/tmp/b\n\
+             num.partitions=3\n\
+             default.replication.factor=2\n\
+             log.retention.hours=1\n\
+             log.retention.minutes=2\n\
+             log.cleanup.policy=compact\n\
+             metadata.log.max.record.bytes.between.snapshots=1024\n\
+             metadata.log.max.snapshot.interval.ms=0\n\
+             broker.session.timeout.ms=6000\n",
+        )
+        .unwrap();
+        let config = BrokerConfig::from_properties(&props).unwrap();
+        assert_eq!(2, config.node_id);
+        assert!(config.is_controller());
+        assert_eq!("host2:9093", config.controller_quorum_voters[1].address());
+        assert_eq!(2, config.listeners.len());
+        assert_eq!("0.0.0.0:9093", config.listeners[1].bind_address());
+        assert_eq!("localhost", config.advertised_listeners[0].host);
+        assert_eq!(
+            vec![PathBuf::from("/tmp/a"), PathBuf::from("/tmp/b")],
+            config.log_dirs
+        );
+        assert_eq!(3, config.num_partitions);
+        assert_eq!(2, config.default_replication_factor);
+        assert_eq!(2 * 60 * 1000, config.log.retention_ms);
+        assert_eq!(storage::CleanupPolicy::COMPACT, config.log.cleanup_policy);
+        assert_eq!(1024, config.metadata_log_max_record_bytes_between_snapshots);
+        assert!(config.metadata_log_max_snapshot_interval.is_zero());
+        assert!(config.is_broker());
+        assert_eq!(Duration::from_secs(6), config.broker_session_timeout);
+    }
+
+    #[test]
+    fn test_overrides_and_validation() {
+        let config = from_args(&["--override", "listeners=PLAINTEXT://127.0.0.1:9192"]).unwrap();
+        assert_eq!(9192, config.listeners[0].port);
+        assert_eq!(config.listeners, config.advertised_listeners);
+
+        assert!(from_args(&["--override", "num.partitions=0"]).is_err());
+        assert!(from_args(&["--override", "listeners=PLAINTEXT://:9092,SSL://:9092"]).is_err());
+        // every listener needs a security protocol
+        assert!(from_args(&["--override", "listeners=INTERNAL://:9092"]).is_err());
+        assert!(from_args(&["--override", "advertised.listeners=OTHER://host:1"]).is_err());
+        assert!(from_args(&["--bogus"]).is_err());
+
+        let config =
+            from_args(&["--override", "max.connections.per.ip.overrides=127.0.0.1:5, ::1:3"])
+                .unwrap();
+        assert_eq!(Some(&3), config.max_connections_per_ip_overrides.get("::1"));
+        assert!(from_args(&["--override", "max.connections.per.ip=0"]).is_err());
+
+        let config = from_args(&["--override", "sasl.enabled.mechanisms=SCRAM-SHA-512, PLAIN"])
+            .unwrap();
+        assert_eq!(
+            vec![
+                SaslMechanism::Scram(crate::security::ScramMechanism::Sha512),
+                SaslMechanism::Plain
+            ],
+            config.sasl_enabled_mechanisms
+        );
+        assert!(from_args(&["--override", "sasl.enabled.mechanisms=GSSAPI"]).is_err());
+        assert!(
+            from_args(&[
+                "--override",
+                "listeners=SASL_PLAINTEXT://:9092",
+                "--override",
+                "sasl.enabled.mechanisms="
+            ])
+            .is_err()
+        );
+
+        // SSL listeners need a certificate, and CAs to verify client certificates with
+        assert!(from_args(&["--override", "listeners=SSL://:9093"]).is_err());
+        let ssl = [
+            "--override",
+            "listeners=SSL://:9093",
+            "--override",
+            "ssl.keystore.location=broker.pem",
+            "--override",
+            "ssl.client.auth=required",
+        ];
+        assert!(from_args(&ssl).is_err());
+        let truststore = ["--override", "ssl.truststore.location=ca.pem"];
+        let config = from_args(&[&ssl[..], &truststore].concat()).unwrap();
+        assert_eq!(SslClientAuth::Required, config.ssl_client_auth);
+        assert!(from_args(&["--override", "ssl.client.auth=sometimes"]).is_err());
+        assert!(from_args(&["--override", "ssl.principal.mapping.rules=RULE:x"]).is_err());
+
+        let config = from_args(&[
+            "--override",
+            "authorizer.class.name=org.apache.kafka.metadata.authorizer.StandardAuthorizer",
+            "--override",
+            "super.users=User:admin; User:CN=broker,O=corp",
+        ])
+        .unwrap();
+        assert_eq!(vec!["User:admin", "User:CN=broker,O=corp"], config.super_users);
+        assert!(from_args(&["--override", "authorizer.class.name=com.example.Custom"]).is_err());
+
+        // controllers have to be voters, brokers only follow the quorum
+        let quorum = [
+            "--override",
+            "listeners=PLAINTEXT://:9092,CONTROLLER://:9093",
+            "--override",
+            "listener.security.protocol.map=PLAINTEXT:PLAINTEXT,CONTROLLER:PLAINTEXT",
+            "--override",
+            "controller.listener.names=CONTROLLER",
+            "--override",
+            "controller.quorum.voters=1@localhost:9093",
+        ];
+        let roles = |roles| [&quorum[..], &["--override", roles]].concat();
+        assert!(from_args(&roles("process.roles=controller")).unwrap().is_controller());
+        assert!(from_args(&roles("process.roles=broker")).is_err());
+        assert!(from_args(&["--override", "process.roles=broker"]).is_err());
+        assert!(from_args(&roles("process.roles=broker,observer")).is_err());
+        let node_id = ["--override", "node.id=4"];
+        let broker = from_args(&[&roles("process.roles=broker")[..], &node_id].concat()).unwrap();
+        assert!(broker.is_kraft() && !broker.is_controller());
+    }
+
+    #[test]
+    fn test_listener_lookup() {
+        let props = Properties::parse(
+            "listeners=INTERNAL://:9092,EXTERNAL://:9094,CONTROLLER://:9093\n\
+             listener.security.protocol.map=INTERNAL:PLAINTEXT,EXTERNAL:SASL_SSL,CONTROLLER:PLAINTEXT\n\
+             controller.listener.names=CONTROLLER\n\
+             ssl.keystore.location=/etc/kafka/broker.pem\n",
+        )
+        .unwrap();
+        let config = BrokerConfig::from_properties(&props).unwrap();
+        assert_eq!(
+            Some(SecurityProtocol::SaslSsl),
+            config.security_protocol("EXTERNAL")
+        );
+        assert_eq!(9094, config.advertised_listener("EXTERNAL").unwrap().port);
+        // controller listeners are not advertised by default
+        assert_eq!(2, config.advertised_listeners.len());
+        assert_eq!(None, config.advertised_listener("CONTROLLER"));
+    }
+}
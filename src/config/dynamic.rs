@@ -0,0 +1,520 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Context, bail, ensure};
+
+use crate::{
+    quota::{desanitize, sanitize},
+    storage::{LogConfig, LogManager, atomic_write},
+};
+
+/// Written in place of the node id of the cluster wide broker defaults
+const DEFAULT: &str = "<default>";
+
+/// What dynamic configs are set on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ConfigEntity {
+    Topic(String),
+    /// A single broker, by node id
+    Broker(i32),
+    /// The defaults of every broker without a config of its own
+    DefaultBroker,
+}
+
+impl ConfigEntity {
+    /// The entity's path in the config file, e.g. `topics/orders` or `brokers/<default>`
+    pub fn path(&self) -> String {
+        match self {
+            Self::Topic(topic) => format!("topics/{}", sanitize(topic)),
+            Self::Broker(node_id) => format!("brokers/{node_id}"),
+            Self::DefaultBroker => format!("brokers/{DEFAULT}"),
+        }
+    }
+
+    pub fn parse_path(path: &str) -> anyhow::Result<Self> {
+        Ok(match path.split_once('/') {
+            Some(("topics", topic)) => Self::Topic(desanitize(topic)?),
+            Some(("brokers", DEFAULT)) => Self::DefaultBroker,
+            Some(("brokers", node_id)) => Self::Broker(node_id.parse()?),
+            _ => bail!("Invalid config entity path {path:?}"),
+        })
+    }
+}
+
+impl Display for ConfigEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.path())
+    }
+}
+
+/// Where the value of a config comes from, as reported by DescribeConfigs
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    DynamicTopic = 1,
+    DynamicBroker = 2,
+    DynamicDefaultBroker = 3,
+    /// `server.properties` or the command line
+    StaticBroker = 4,
+    Default = 5,
+}
+
+impl ConfigSource {
+    pub fn code(self) -> i8 {
+        self as i8
+    }
+}
+
+/// The type of a config's values, as reported by DescribeConfigs
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigType {
+    String = 2,
+    Int = 3,
+    Long = 5,
+    Double = 6,
+    List = 7,
+}
+
+impl ConfigType {
+    pub fn code(self) -> i8 {
+        self as i8
+    }
+}
+
+/// A value a config would have if the ones taking precedence were not set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigSynonym {
+    pub name: &'static str,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+/// A config as reported by DescribeConfigs. Its value is the one of its first synonym.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribedConfig {
+    pub name: &'static str,
+    pub value: String,
+    pub source: ConfigSource,
+    pub config_type: ConfigType,
+    pub documentation: &'static str,
+    /// Every value set for the config, from the one in effect to the default
+    pub synonyms: Vec<ConfigSynonym>,
+}
+
+/// The type and documentation of every topic level key, see [`LogConfig::KEYS`]
+const CONFIG_DEFS: &[(&str, ConfigType, &str)] = &[
+    (
+        "cleanup.policy",
+        ConfigType::List,
+        "Whether old segments are deleted, compacted or both",
+    ),
+    (
+        "compression.type",
+        ConfigType::String,
+        "The codec batches are stored with, producer keeps the one they were produced with",
+    ),
+    (
+        "delete.retention.ms",
+        ConfigType::Long,
+        "How long tombstones of compacted topics are kept",
+    ),
+    (
+        "max.message.bytes",
+        ConfigType::Int,
+        "The largest record batch that can be appended",
+    ),
+    (
+        "min.cleanable.dirty.ratio",
+        ConfigType::Double,
+        "The share of uncompacted bytes needed before a log is compacted",
+    ),
+    (
+        "min.insync.replicas",
+        ConfigType::Int,
+        "How many replicas have to be in sync for produces with acks=all to be accepted",
+    ),
+    (
+        "retention.bytes",
+        ConfigType::Long,
+        "The size a partition is shrunk to by deleting old segments, -1 for no limit",
+    ),
+    (
+        "retention.ms",
+        ConfigType::Long,
+        "How long segments are kept before they are deleted, -1 for no limit",
+    ),
+    (
+        "segment.bytes",
+        ConfigType::Int,
+        "The size a segment is rolled at",
+    ),
+    (
+        "segment.ms",
+        ConfigType::Long,
+        "The age a segment is rolled at, even if it is not full",
+    ),
+];
+
+/// Configs by entity and key
+type Configs = BTreeMap<ConfigEntity, BTreeMap<String, String>>;
+
+/// Topic and broker configs changed at runtime through AlterConfigs and
+/// IncrementalAlterConfigs, stored in the first log directory so they survive restarts:
+///
+/// ```text
+/// 0                                                 <- version
+/// brokers/<default> log.retention.ms=86400000       <- entity path, then its configs
+/// topics/orders cleanup.policy=compact%2Cdelete
+/// ```
+///
+/// Topics take the topic level keys, brokers the `log.` prefixed broker level keys
+/// setting their defaults. Every change is handed to the [`LogManager`] right away.
+#[derive(Debug)]
+pub struct ConfigManager {
+    path: PathBuf,
+    node_id: i32,
+    /// The topic defaults set in `server.properties`
+    static_config: LogConfig,
+    log_manager: Arc<LogManager>,
+    configs: RwLock<Configs>,
+}
+
+impl ConfigManager {
+    const VERSION: u32 = 0;
+    const FILE_NAME: &str = "dynamic-configs";
+
+    /// Loads the configs stored in `dir` and applies them to `log_manager`
+    pub fn load(
+        dir: &Path,
+        node_id: i32,
+        static_config: LogConfig,
+        log_manager: Arc<LogManager>,
+    ) -> anyhow::Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        let configs = Self::read(&path)?;
+        let manager = Self {
+            path,
+            node_id,
+            static_config,
+            log_manager,
+            configs: RwLock::new(Configs::new()),
+        };
+        manager.apply(&configs, configs.keys())?;
+        *manager.configs.write().unwrap() = configs;
+        Ok(manager)
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Configs> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Configs::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing config file version")?
+            .trim()
+            .parse()?;
+        ensure!(
+            version == Self::VERSION,
+            "Unsupported config file version {version} in {}",
+            path.display()
+        );
+
+        let mut configs = Configs::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let entity = ConfigEntity::parse_path(fields.next().unwrap_or_default())?;
+            let mut values = BTreeMap::new();
+            for field in fields {
+                let Some((key, value)) = field.split_once('=') else {
+                    bail!("Malformed config {field:?} of {entity}");
+                };
+                values.insert(key.to_string(), desanitize(value)?);
+            }
+            configs.insert(entity, values);
+        }
+        Ok(configs)
+    }
+
+    /// Replaces the stored configs
+    fn write(&self, configs: &Configs) -> anyhow::Result<()> {
+        let mut content = format!("{}\n", Self::VERSION);
+        for (entity, values) in configs {
+            content.push_str(&entity.path());
+            for (key, value) in values {
+                content.push_str(&format!(" {key}={}", sanitize(value)));
+            }
+            content.push('\n');
+        }
+
+        atomic_write(&self.path, content.as_bytes())
+    }
+
+    /// The node id of this broker, whose own configs are the only broker ones applied
+    pub fn node_id(&self) -> i32 {
+        self.node_id
+    }
+
+    /// The entities with dynamic configs
+    pub fn entities(&self) -> Vec<ConfigEntity> {
+        self.configs.read().unwrap().keys().cloned().collect()
+    }
+
+    /// The dynamic configs set on `entity`
+    pub fn configs(&self, entity: &ConfigEntity) -> BTreeMap<String, String> {
+        self.configs
+            .read()
+            .unwrap()
+            .get(entity)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Checks that `values` can be set on `entity`
+    pub fn validate(
+        entity: &ConfigEntity,
+        values: &BTreeMap<String, String>,
+    ) -> anyhow::Result<()> {
+        for (key, value) in values {
+            let topic_key = match entity {
+                ConfigEntity::Topic(_) => LogConfig::broker_key(key).map(|_| key.as_str()),
+                ConfigEntity::Broker(_) | ConfigEntity::DefaultBroker => LogConfig::topic_key(key),
+            };
+            let Some(topic_key) = topic_key else {
+                bail!("Unknown or read-only config {key}");
+            };
+            LogConfig::default().set(topic_key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the configs of every entity in `changes` with already validated values and
+    /// stores the result. Either every change is applied or, if storing fails, none is.
+    pub fn alter(
+        &self,
+        changes: Vec<(ConfigEntity, BTreeMap<String, String>)>,
+    ) -> anyhow::Result<()> {
+        let mut configs = self.configs.write().unwrap();
+        let mut altered = configs.clone();
+        for (entity, values) in &changes {
+            if values.is_empty() {
+                altered.remove(entity);
+            } else {
+                altered.insert(entity.clone(), values.clone());
+            }
+        }
+        self.write(&altered)?;
+        self.apply(&altered, changes.iter().map(|(entity, _)| entity))?;
+        *configs = altered;
+        Ok(())
+    }
+
+    /// Hands the configs of `entities` to the log manager
+    fn apply<'a, I>(&self, configs: &Configs, entities: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = &'a ConfigEntity>,
+    {
+        let values = |entity: &ConfigEntity| {
+            configs
+                .get(entity)
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+        };
+        let mut defaults_changed = false;
+        for entity in entities {
+            match entity {
+                ConfigEntity::Topic(topic) => {
+                    self.log_manager
+                        .set_topic_overrides(topic, values(entity))?;
+                }
+                ConfigEntity::Broker(_) | ConfigEntity::DefaultBroker => defaults_changed = true,
+            }
+        }
+        if defaults_changed {
+            let mut defaults = self.static_config.clone();
+            let overrides = values(&ConfigEntity::DefaultBroker)
+                .chain(values(&ConfigEntity::Broker(self.node_id)))
+                .filter_map(|(key, value)| Some((LogConfig::topic_key(key)?, value)));
+            for (key, value) in overrides {
+                defaults.set(key, value)?;
+            }
+            self.log_manager.set_default_config(defaults);
+        }
+        Ok(())
+    }
+
+    /// The configs of `entity`, or only those named in `keys`. Brokers only report the
+    /// topic defaults, the cluster wide defaults only those that are set.
+    pub fn describe(&self, entity: &ConfigEntity, keys: Option<&[String]>) -> Vec<DescribedConfig> {
+        let configs = self.configs.read().unwrap();
+        let value = |entity: &ConfigEntity, key: &str| configs.get(entity)?.get(key).cloned();
+        let default = LogConfig::default();
+
+        let mut described = Vec::new();
+        for &(topic_key, config_type, documentation) in CONFIG_DEFS {
+            let broker_key = LogConfig::broker_key(topic_key).unwrap_or(topic_key);
+            let name = match entity {
+                ConfigEntity::Topic(_) => topic_key,
+                ConfigEntity::Broker(_) | ConfigEntity::DefaultBroker => broker_key,
+            };
+            if keys.is_some_and(|keys| !keys.iter().any(|k| k == name)) {
+                continue;
+            }
+
+            let mut synonyms = Vec::new();
+            let mut synonym = |name, value: Option<String>, source| {
+                if let Some(value) = value {
+                    synonyms.push(ConfigSynonym {
+                        name,
+                        value,
+                        source,
+                    });
+                }
+            };
+            if let ConfigEntity::Topic(_) = entity {
+                synonym(
+                    topic_key,
+                    value(entity, topic_key),
+                    ConfigSource::DynamicTopic,
+                );
+            }
+            if entity != &ConfigEntity::DefaultBroker {
+                let node = ConfigEntity::Broker(self.node_id);
+                synonym(
+                    broker_key,
+                    value(&node, broker_key),
+                    ConfigSource::DynamicBroker,
+                );
+            }
+            let cluster = value(&ConfigEntity::DefaultBroker, broker_key);
+            synonym(broker_key, cluster, ConfigSource::DynamicDefaultBroker);
+            if entity != &ConfigEntity::DefaultBroker {
+                let static_value = self.static_config.get(topic_key);
+                let default_value = default.get(topic_key);
+                let set_statically = static_value != default_value;
+                synonym(
+                    broker_key,
+                    static_value.filter(|_| set_statically),
+                    ConfigSource::StaticBroker,
+                );
+                synonym(broker_key, default_value, ConfigSource::Default);
+            }
+
+            let Some(first) = synonyms.first() else {
+                continue;
+            };
+            described.push(DescribedConfig {
+                name,
+                value: first.value.clone(),
+                source: first.source,
+                config_type,
+                documentation,
+                synonyms,
+            });
+        }
+        described
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_alter_describe_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_manager = Arc::new(
+            LogManager::load(vec![dir.path().join("logs")], LogConfig::default()).unwrap(),
+        );
+        let mut static_config = LogConfig::default();
+        static_config.set("retention.ms", "3600000").unwrap();
+        let configs = ConfigManager::load(
+            dir.path(),
+            1,
+            static_config.clone(),
+            Arc::clone(&log_manager),
+        )
+        .unwrap();
+        let orders = ConfigEntity::Topic("orders".to_string());
+
+        let topic_values = values(&[
+            ("cleanup.policy", "compact,delete"),
+            ("max.message.bytes", "1000"),
+        ]);
+        let broker_values = values(&[("log.retention.ms", "60000")]);
+        assert!(ConfigManager::validate(&orders, &broker_values).is_err());
+        assert!(ConfigManager::validate(&ConfigEntity::Broker(1), &topic_values).is_err());
+        assert!(ConfigManager::validate(&orders, &values(&[("retention.ms", "-2")])).is_err());
+        ConfigManager::validate(&orders, &topic_values).unwrap();
+        configs
+            .alter(vec![
+                (orders.clone(), topic_values),
+                (ConfigEntity::DefaultBroker, broker_values),
+            ])
+            .unwrap();
+
+        let config = log_manager.config_for("orders");
+        assert!(config.cleanup_policy.compact && config.cleanup_policy.delete);
+        assert_eq!(1000, config.max_message_bytes);
+        assert_eq!(60000, log_manager.config_for("payments").retention_ms);
+
+        let keys = ["retention.ms".to_string(), "max.message.bytes".to_string()];
+        let described = configs.describe(&orders, Some(&keys));
+        assert_eq!(2, described.len());
+        assert_eq!(
+            ("max.message.bytes", "1000", ConfigSource::DynamicTopic),
+            (
+                described[0].name,
+                described[0].value.as_str(),
+                described[0].source
+            )
+        );
+        let sources: Vec<_> = described[1]
+            .synonyms
+            .iter()
+            .map(|s| (s.value.as_str(), s.source))
+            .collect();
+        assert_eq!(
+            vec![
+                ("60000", ConfigSource::DynamicDefaultBroker),
+                ("3600000", ConfigSource::StaticBroker),
+                ("604800000", ConfigSource::Default),
+            ],
+            sources
+        );
+        assert_eq!(
+            1,
+            configs.describe(&ConfigEntity::DefaultBroker, None).len()
+        );
+
+        // removing the override falls back to the static default
+        configs
+            .alter(vec![(ConfigEntity::DefaultBroker, BTreeMap::new())])
+            .unwrap();
+        assert_eq!(3600000, log_manager.config_for("payments").retention_ms);
+
+        let log_manager = Arc::new(
+            LogManager::load(vec![dir.path().join("logs")], LogConfig::default()).unwrap(),
+        );
+        let reloaded =
+            ConfigManager::load(dir.path(), 1, static_config, Arc::clone(&log_manager)).unwrap();
+        assert_eq!(configs.configs(&orders), reloaded.configs(&orders));
+        assert_eq!(1000, log_manager.config_for("orders").max_message_bytes);
+    }
+}
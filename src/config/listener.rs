@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use anyhow::{Context, bail};
+
+/// An entry of `listeners` or `advertised.listeners`, e.g. `PLAINTEXT://localhost:9092`.
+/// An empty host binds every interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Listener {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        let Some((name, address)) = value.split_once("://") else {
+            bail!("Listener {value:?} is not of the form NAME://host:port");
+        };
+        let Some((host, port)) = address.rsplit_once(':') else {
+            bail!("Listener {value:?} is missing a port");
+        };
+        anyhow::ensure!(
+            !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_'),
+            "Invalid listener name {name:?}"
+        );
+        let port = port
+            .parse()
+            .with_context(|| format!("Invalid port of listener {value:?}"))?;
+        // IPv6 addresses are written in brackets
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Ok(Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// Parses a comma separated list of listeners, as used by `listeners`
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        value
+            .split(',')
+            .filter(|l| !l.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// The address to bind the listener to
+    pub fn bind_address(&self) -> String {
+        match self.host.as_str() {
+            "" => format!("0.0.0.0:{}", self.port),
+            host if host.contains(':') => format!("[{host}]:{}", self.port),
+            host => format!("{host}:{}", self.port),
+        }
+    }
+}
+
+impl Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "{}://[{}]:{}", self.name, self.host, self.port)
+        } else {
+            write!(f, "{}://{}:{}", self.name, self.host, self.port)
+        }
+    }
+}
+
+/// How the connections of a listener are secured, as named in `listener.security.protocol.map`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecurityProtocol {
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value.trim().to_ascii_uppercase().as_str() {
+            "PLAINTEXT" => Self::Plaintext,
+            "SSL" => Self::Ssl,
+            "SASL_PLAINTEXT" => Self::SaslPlaintext,
+            "SASL_SSL" => Self::SaslSsl,
+            v => bail!("Unknown security protocol {v}"),
+        })
+    }
+
+    /// Parses `listener.security.protocol.map`, a list of `NAME:PROTOCOL` pairs
+    pub fn parse_map(value: &str) -> anyhow::Result<BTreeMap<String, Self>> {
+        let mut map = BTreeMap::new();
+        for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((name, protocol)) = pair.split_once(':') else {
+                bail!("{pair:?} is not a NAME:PROTOCOL pair");
+            };
+            map.insert(name.trim().to_string(), Self::parse(protocol)?);
+        }
+        Ok(map)
+    }
+
+    /// The id of the protocol in Kafka's messages, like the listeners of a broker
+    /// registration
+    pub fn id(self) -> i16 {
+        match self {
+            Self::Plaintext => 0,
+            Self::Ssl => 1,
+            Self::SaslPlaintext => 2,
+            Self::SaslSsl => 3,
+        }
+    }
+
+    /// Whether clients of listeners with this protocol authenticate with SASL
+    pub fn is_sasl(self) -> bool {
+        matches!(self, Self::SaslPlaintext | Self::SaslSsl)
+    }
+
+    /// Whether connections of listeners with this protocol are encrypted with TLS
+    pub fn is_ssl(self) -> bool {
+        matches!(self, Self::Ssl | Self::SaslSsl)
+    }
+
+    /// The map used when none is configured, every protocol is its own listener name
+    pub fn default_map() -> BTreeMap<String, Self> {
+        [
+            Self::Plaintext,
+            Self::Ssl,
+            Self::SaslPlaintext,
+            Self::SaslSsl,
+        ]
+        .into_iter()
+        .map(|p| (p.to_string(), p))
+        .collect()
+    }
+}
+
+impl Display for SecurityProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Plaintext => "PLAINTEXT",
+            Self::Ssl => "SSL",
+            Self::SaslPlaintext => "SASL_PLAINTEXT",
+            Self::SaslSsl => "SASL_SSL",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,16 @@
+//! Broker configuration, read from a Kafka style `server.properties` file
+//! with overrides from the command line. Topic configs and their broker defaults can
+//! also be changed at runtime, see [`ConfigManager`].
+mod broker;
+mod dynamic;
+mod listener;
+mod properties;
+mod quorum;
+
+pub use broker::BrokerConfig;
+pub use dynamic::{
+    ConfigEntity, ConfigManager, ConfigSource, ConfigSynonym, ConfigType, DescribedConfig,
+};
+pub use listener::{Listener, SecurityProtocol};
+pub use properties::Properties;
+pub use quorum::{ProcessRole, QuorumVoter};
@@ -0,0 +1,85 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::Context;
+
+/// The `key=value` pairs of a Java style `.properties` file, like Kafka's `server.properties`.
+///
+/// Supported syntax: `key=value` and `key: value` pairs, `#` and `!` comment lines,
+/// and values continued on the next line by ending the line with a backslash.
+/// Later occurrences of a key override earlier ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    entries: BTreeMap<String, String>,
+}
+
+impl Properties {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Parsing {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut entries = BTreeMap::new();
+        let mut lines = content.lines().enumerate();
+        while let Some((number, line)) = lines.next() {
+            let mut line = line.trim_start().to_string();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some((_, next)) => line.push_str(next.trim_start()),
+                    None => break,
+                }
+            }
+
+            let Some(split) = line.find(['=', ':']) else {
+                anyhow::bail!("Line {} is not a key=value pair: {line:?}", number + 1);
+            };
+            let key = line[..split].trim();
+            anyhow::ensure!(!key.is_empty(), "Line {} has an empty key", number + 1);
+            entries.insert(key.to_string(), line[split + 1..].trim().to_string());
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries.insert(key.into(), value.into());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let props = Properties::parse(
+            "# comment\n\
+             ! also a comment\n\
+             \n\
+             node.id=1\n\
+             listeners : PLAINTEXT://:9092,\\\n    CONTROLLER://:9093\n\
+             log.dirs=/tmp/a\n\
+             log.dirs=/tmp/b\n",
+        )
+        .unwrap();
+        assert_eq!(Some("1"), props.get("node.id"));
+        assert_eq!(
+            Some("PLAINTEXT://:9092,CONTROLLER://:9093"),
+            props.get("listeners")
+        );
+        assert_eq!(Some("/tmp/b"), props.get("log.dirs"));
+        assert!(Properties::parse("no separator").is_err());
+    }
+}
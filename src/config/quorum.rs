@@ -0,0 +1,109 @@
+use std::fmt::Display;
+
+use anyhow::{Context, bail};
+
+/// A role of `process.roles`: brokers serve clients, controllers vote in the KRaft
+/// quorum replicating the cluster metadata. A node can have both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessRole {
+    Broker,
+    Controller,
+}
+
+impl ProcessRole {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value.trim().to_ascii_lowercase().as_str() {
+            "broker" => Self::Broker,
+            "controller" => Self::Controller,
+            v => bail!("Unknown process role {v}"),
+        })
+    }
+
+    /// Parses a comma separated list of roles, as used by `process.roles`
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        let mut roles = Vec::new();
+        for role in value.split(',').filter(|r| !r.trim().is_empty()) {
+            let role = Self::parse(role)?;
+            anyhow::ensure!(
+                !roles.contains(&role),
+                "Process role {role} is listed twice"
+            );
+            roles.push(role);
+        }
+        Ok(roles)
+    }
+}
+
+impl Display for ProcessRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Broker => write!(f, "broker"),
+            Self::Controller => write!(f, "controller"),
+        }
+    }
+}
+
+/// An entry of `controller.quorum.voters`, e.g. `1@localhost:9093`: the node id of a
+/// controller and the address of its controller listener
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumVoter {
+    pub id: i32,
+    pub host: String,
+    pub port: u16,
+}
+
+impl QuorumVoter {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        let Some((id, address)) = value.split_once('@') else {
+            bail!("Voter {value:?} is not of the form id@host:port");
+        };
+        let Some((host, port)) = address.rsplit_once(':') else {
+            bail!("Voter {value:?} is missing a port");
+        };
+        let id = id
+            .parse()
+            .with_context(|| format!("Invalid node id of voter {value:?}"))?;
+        anyhow::ensure!(id >= 0, "Node id of voter {value:?} cannot be negative");
+        let port = port
+            .parse()
+            .with_context(|| format!("Invalid port of voter {value:?}"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        anyhow::ensure!(!host.is_empty(), "Voter {value:?} is missing a host");
+        Ok(Self {
+            id,
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// Parses a comma separated list of voters, as used by `controller.quorum.voters`
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        let mut voters: Vec<Self> = Vec::new();
+        for voter in value.split(',').filter(|v| !v.trim().is_empty()) {
+            let voter = Self::parse(voter)?;
+            anyhow::ensure!(
+                voters.iter().all(|v| v.id != voter.id),
+                "Voter {} is listed twice",
+                voter.id
+            );
+            voters.push(voter);
+        }
+        Ok(voters)
+    }
+
+    /// The address to connect to the voter at
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl Display for QuorumVoter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.id, self.address())
+    }
+}
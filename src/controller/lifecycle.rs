@@ -0,0 +1,235 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use tracing::{debug, info, warn};
+
+use super::SUPPORTED_FEATURES;
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+    config::{BrokerConfig, SecurityProtocol},
+    metadata::{BrokerEndpoint, MetadataImage},
+    network,
+    primitives::Uuid,
+    raft::RaftClient,
+    request::{
+        BrokerHeartbeatRequestBody, BrokerRegistrationRequestBody, RegistrationFeature,
+        RegistrationListener,
+    },
+    response::body::{BrokerHeartbeatResponseBody, BrokerRegistrationResponseBody},
+    storage::MetaProperties,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Client id of the requests sent to the controller
+const CLIENT_ID: &str = "broker-lifecycle";
+const REGISTRATION_VERSION: i16 = 3;
+const HEARTBEAT_VERSION: i16 = 1;
+
+/// Where the broker stands with the controller
+#[derive(Debug, Clone, Copy)]
+struct LifecycleState {
+    /// The epoch the controller handed out on registration, -1 while not registered
+    broker_epoch: i64,
+    fenced: bool,
+}
+
+/// Registers the broker of a `KRaft` node with the active controller and keeps its
+/// session alive with heartbeats. The broker starts fenced, the controller unfences it
+/// once it replayed the metadata log up to its registration. Registering starts over
+/// whenever the controller no longer knows the broker's epoch.
+#[derive(Debug)]
+pub struct BrokerLifecycleManager {
+    raft: Arc<RaftClient>,
+    image: Arc<RwLock<MetadataImage>>,
+    /// A random id of this run of the broker
+    incarnation_id: Uuid,
+    endpoints: Vec<BrokerEndpoint>,
+    rack: Option<String>,
+    log_dirs: Vec<Uuid>,
+    heartbeat_interval: Duration,
+    state: Mutex<LifecycleState>,
+}
+
+impl BrokerLifecycleManager {
+    /// # Errors
+    ///
+    /// Fails if the directory ids of the log dirs cannot be read
+    pub fn new(
+        raft: Arc<RaftClient>,
+        image: Arc<RwLock<MetadataImage>>,
+        config: &BrokerConfig,
+    ) -> anyhow::Result<Self> {
+        let endpoints = config
+            .advertised_listeners
+            .iter()
+            .filter(|l| !config.controller_listener_names.contains(&l.name))
+            .map(|l| BrokerEndpoint {
+                name: l.name.clone(),
+                host: l.host.clone(),
+                port: l.port,
+                security_protocol: config
+                    .security_protocol(&l.name)
+                    .map_or(0, SecurityProtocol::id),
+            })
+            .collect();
+        Ok(Self {
+            raft,
+            image,
+            incarnation_id: Uuid::random(),
+            endpoints,
+            rack: config.broker_rack.clone(),
+            log_dirs: MetaProperties::directory_ids(&config.log_dirs)?,
+            heartbeat_interval: config.broker_heartbeat_interval,
+            state: Mutex::new(LifecycleState {
+                broker_epoch: -1,
+                fenced: true,
+            }),
+        })
+    }
+
+    /// Whether the controller fenced the broker, as it is until it registered
+    pub fn is_fenced(&self) -> bool {
+        self.state.lock().unwrap().fenced
+    }
+
+    /// Registers the broker, then sends heartbeats every `broker.heartbeat.interval.ms`,
+    /// for as long as the task is not aborted
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let registered = self.state.lock().unwrap().broker_epoch >= 0;
+            let sent = if registered {
+                self.heartbeat(false).await.map(|_| ())
+            } else {
+                self.register().await
+            };
+            if let Err(e) = sent {
+                warn!("Reaching the controller failed: {e:#}");
+            }
+            tokio::time::sleep(self.heartbeat_interval).await;
+        }
+    }
+
+    /// Tells the controller the broker shuts down, so it is fenced and its partitions
+    /// get new leaders right away rather than once its session expires. Failures are
+    /// only logged, the session expires all the same.
+    pub async fn shut_down(&self) {
+        if self.state.lock().unwrap().broker_epoch < 0 {
+            return;
+        }
+        info!("Telling the controller the broker shuts down");
+        let timeout = self.raft.config().request_timeout;
+        match tokio::time::timeout(timeout, self.heartbeat(true)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => debug!("Telling the controller about the shutdown failed: {e:#}"),
+            Err(_) => debug!("Telling the controller about the shutdown timed out"),
+        }
+    }
+
+    async fn register(&self) -> anyhow::Result<()> {
+        let config = self.raft.config();
+        let request = BrokerRegistrationRequestBody::new(
+            config.node_id,
+            &config.cluster_id,
+            self.incarnation_id,
+        )
+        .with_listeners(
+            self.endpoints
+                .iter()
+                .map(|e| RegistrationListener::new(&e.name, &e.host, e.port, e.security_protocol))
+                .collect(),
+        )
+        .with_features(
+            SUPPORTED_FEATURES
+                .iter()
+                .map(|&(name, min, max)| RegistrationFeature::new(name, min, max))
+                .collect(),
+        )
+        .with_rack(self.rack.clone())
+        .with_log_dirs(self.log_dirs.clone());
+        let response: BrokerRegistrationResponseBody = self
+            .send(ApiKeys::BrokerRegistration, REGISTRATION_VERSION, &request)
+            .await?;
+        let code = response.error_code;
+        if code != ErrorCode::None.code() {
+            bail!("Registering with the controller failed with error code {code}");
+        }
+        info!(
+            "Registered with the controller, broker epoch {}",
+            response.broker_epoch
+        );
+        *self.state.lock().unwrap() = LifecycleState {
+            broker_epoch: response.broker_epoch,
+            fenced: true,
+        };
+        Ok(())
+    }
+
+    async fn heartbeat(&self, want_shut_down: bool) -> anyhow::Result<BrokerHeartbeatResponseBody> {
+        let broker_epoch = self.state.lock().unwrap().broker_epoch;
+        let offset = self.image.read().unwrap().offset;
+        let request = BrokerHeartbeatRequestBody::new(
+            self.raft.node_id(),
+            broker_epoch,
+            offset,
+            false,
+            want_shut_down,
+        );
+        let response: BrokerHeartbeatResponseBody = self
+            .send(ApiKeys::BrokerHeartbeat, HEARTBEAT_VERSION, &request)
+            .await?;
+        let mut state = self.state.lock().unwrap();
+        match response.error_code {
+            code if code == ErrorCode::None.code() => {}
+            code if code == ErrorCode::StaleBrokerEpoch.code()
+                || code == ErrorCode::BrokerIdNotRegistered.code() =>
+            {
+                // the registration is gone, register again
+                *state = LifecycleState {
+                    broker_epoch: -1,
+                    fenced: true,
+                };
+                bail!("Heartbeat of epoch {broker_epoch} failed with error code {code}");
+            }
+            code => bail!("Heartbeat failed with error code {code}"),
+        }
+        let fenced = response.is_fenced.is_true();
+        if fenced != state.fenced && state.broker_epoch == broker_epoch {
+            info!(
+                "The controller {} the broker",
+                if fenced { "fenced" } else { "unfenced" }
+            );
+            state.fenced = fenced;
+        }
+        Ok(response)
+    }
+
+    /// Sends a single request to the active controller on a new connection
+    async fn send<Req, Resp>(
+        &self,
+        api_key: ApiKeys,
+        api_version: i16,
+        request: &Req,
+    ) -> anyhow::Result<Resp>
+    where
+        Req: Encoder + WireLen,
+        Resp: Decoder + WireLen,
+    {
+        let config = self.raft.config();
+        let leader_id = self.raft.leader().leader_id;
+        let address = config
+            .voters
+            .get(&leader_id)
+            .with_context(|| format!("No known controller to send {api_key:?} to"))?;
+        let timeout = config.request_timeout;
+        tokio::time::timeout(timeout, async {
+            let mut stream = network::connect(address, timeout).await?;
+            network::send_request(&mut stream, api_key, api_version, 0, CLIENT_ID, request).await
+        })
+        .await
+        .with_context(|| format!("{api_key:?} to {address} timed out"))?
+    }
+}
@@ -0,0 +1,14 @@
+//! The controller side of a `KRaft` cluster, and what brokers do to join one. The leader
+//! of the quorum is the active controller: it registers brokers, fences the ones whose
+//! heartbeats stop coming, and moves the leadership of their partitions elsewhere, all by
+//! appending records to the metadata log. `ElectLeaders` requests move leadership back to
+//! the preferred replica, or out of the ISR when no in-sync replica is left. Brokers
+//! register with it on start and keep their session alive with heartbeats.
+mod lifecycle;
+mod quorum;
+
+pub use lifecycle::BrokerLifecycleManager;
+pub use quorum::{ControllerResult, QuorumController, run_session_expiration};
+
+/// The features brokers of this node support, with the range of their levels
+pub const SUPPORTED_FEATURES: &[(&str, i16, i16)] = &[("metadata.version", 1, 21)];
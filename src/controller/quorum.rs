@@ -0,0 +1,1332 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use rand::Rng;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{BrokerConfig, ConfigEntity, ConfigManager, ConfigSource},
+    handlers::DelayedControllerWrite,
+    metadata::{
+        AccessControlEntryRecord, BrokerEndpoint, BrokerRegistration, ClientQuotaRecord,
+        ConfigRecord, FenceBrokerRecord, MetadataImage, MetadataRecord, PartitionRecord,
+        PartitionRegistration, RegisterBrokerRecord, RemoveAccessControlEntryRecord,
+        RemoveUserScramCredentialRecord, TopicImage, TopicRecord, UnfenceBrokerRecord,
+        UserScramCredentialRecord,
+    },
+    primitives::{CompactArray, Uuid},
+    purgatory::Purgatory,
+    quota::{QuotaEntity, QuotaOp},
+    raft::RaftClient,
+    request::{
+        BrokerHeartbeatRequestBody, BrokerRegistrationRequestBody, CreatableTopic,
+        CreateTopicsRequestBody, ElectLeadersRequestBody, ElectionType,
+    },
+    response::body::{
+        BrokerHeartbeatResponseBody, BrokerRegistrationResponseBody, CreatableTopicConfigs,
+        CreatableTopicResult, CreateTopicsResponseBody, ElectLeadersPartitionResult,
+        ElectLeadersResponseBody, ElectLeadersTopicResult,
+    },
+    security::{AclBinding, AclBindingFilter, ScramCredentialChange},
+    storage::TopicPartition,
+    types::ErrorCode,
+};
+
+/// The answer of the controller to a request, and the offset of the last record it
+/// appended for it. The request is answered once the metadata image got that far,
+/// right away without one.
+#[derive(Debug)]
+pub struct ControllerResult<T> {
+    pub response: T,
+    pub offset: Option<i64>,
+}
+
+impl<T> ControllerResult<T> {
+    fn ready(response: T) -> Self {
+        Self {
+            response,
+            offset: None,
+        }
+    }
+
+    fn written(response: T, offset: i64) -> Self {
+        Self {
+            response,
+            offset: Some(offset),
+        }
+    }
+}
+
+/// A topic the controller is about to create
+#[derive(Debug)]
+struct NewTopic {
+    /// The replicas of every partition, the first one leading
+    assignment: Vec<Vec<i32>>,
+    configs: BTreeMap<String, String>,
+}
+
+/// The heartbeats the active controller received in its epoch. Brokers start with a
+/// full session timeout once it learns of them, so a new controller fences nobody
+/// before they had a chance to send one.
+#[derive(Debug)]
+struct Sessions {
+    epoch: i32,
+    last_heartbeat: HashMap<i32, Instant>,
+    /// The offset of the last record appended in the epoch. Nothing is decided from an
+    /// image older than that, it would undo the changes in between.
+    written: i64,
+}
+
+impl Sessions {
+    fn touch(&mut self, broker_id: i32, now: Instant) {
+        self.last_heartbeat.insert(broker_id, now);
+    }
+
+    fn is_expired(&mut self, broker_id: i32, now: Instant, timeout: Duration) -> bool {
+        let last = *self.last_heartbeat.entry(broker_id).or_insert(now);
+        now.duration_since(last) > timeout
+    }
+}
+
+/// The controller of a `KRaft` cluster, active while its node leads the quorum. Requests
+/// sent to the other controllers fail with `NOT_CONTROLLER`, which has brokers look for
+/// the leader again.
+///
+/// Decisions are taken on the metadata image, and written to the metadata log for every
+/// node to replay. Only one is taken at a time.
+#[derive(Debug)]
+pub struct QuorumController {
+    raft: Arc<RaftClient>,
+    image: Arc<RwLock<MetadataImage>>,
+    session_timeout: Duration,
+    /// The partition count of created topics that do not ask for one
+    num_partitions: i32,
+    /// The replica count of created topics that do not ask for one
+    default_replication_factor: i16,
+    sessions: Mutex<Sessions>,
+    /// Requests waiting for the image to replay what the controller wrote for them
+    write_purgatory: Arc<Purgatory<TopicPartition, DelayedControllerWrite>>,
+}
+
+impl QuorumController {
+    pub fn new(
+        raft: Arc<RaftClient>,
+        image: Arc<RwLock<MetadataImage>>,
+        config: &BrokerConfig,
+    ) -> Self {
+        Self {
+            raft,
+            image,
+            session_timeout: config.broker_session_timeout,
+            num_partitions: config.num_partitions,
+            default_replication_factor: config.default_replication_factor,
+            sessions: Mutex::new(Sessions {
+                epoch: -1,
+                last_heartbeat: HashMap::new(),
+                written: -1,
+            }),
+            write_purgatory: Arc::new(Purgatory::new("ControllerWrite")),
+        }
+    }
+
+    pub fn image(&self) -> &Arc<RwLock<MetadataImage>> {
+        &self.image
+    }
+
+    pub fn write_purgatory(&self) -> &Arc<Purgatory<TopicPartition, DelayedControllerWrite>> {
+        &self.write_purgatory
+    }
+
+    /// How long requests wait for the records written for them to be replayed
+    pub fn write_timeout(&self) -> Duration {
+        self.raft.config().request_timeout
+    }
+
+    /// The sessions of the current epoch of the quorum, a new epoch starts afresh
+    fn sessions(&self) -> MutexGuard<'_, Sessions> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let (epoch, _) = self.raft.role();
+        if sessions.epoch != epoch {
+            *sessions = Sessions {
+                epoch,
+                last_heartbeat: HashMap::new(),
+                written: -1,
+            };
+        }
+        sessions
+    }
+
+    /// Appends `records`, returning the offset of the last one
+    fn append(&self, sessions: &mut Sessions, records: Vec<MetadataRecord>) -> anyhow::Result<i64> {
+        self.append_with(sessions, |_| records)
+    }
+
+    /// Appends the records `build` returns for the offset of the first one, returning
+    /// the offset of the last one
+    fn append_with<F>(&self, sessions: &mut Sessions, build: F) -> anyhow::Result<i64>
+    where
+        F: FnOnce(i64) -> Vec<MetadataRecord>,
+    {
+        let mut count = 0;
+        let base_offset = self.raft.append_with(|base_offset| {
+            let records = build(base_offset);
+            count = records.len() as i64;
+            records
+                .iter()
+                .enumerate()
+                .map(|(i, record)| record.to_record(i as i32))
+                .collect()
+        })?;
+        sessions.written = base_offset + count - 1;
+        Ok(sessions.written)
+    }
+
+    /// Registers a broker, fenced until it caught up with the metadata log. Its epoch is
+    /// the offset of the registration record, partitions its previous registration led
+    /// get new leaders. Brokers reusing the id of an unfenced one with a live session are
+    /// turned down, unless they are a restart of the same incarnation.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn register_broker(
+        &self,
+        request: &BrokerRegistrationRequestBody,
+    ) -> anyhow::Result<ControllerResult<BrokerRegistrationResponseBody>> {
+        let now = Instant::now();
+        let mut sessions = self.sessions();
+        let error = |code| {
+            Ok(ControllerResult::ready(
+                BrokerRegistrationResponseBody::error(code),
+            ))
+        };
+        if !self.raft.is_leader() {
+            return error(ErrorCode::NotController);
+        }
+        if request.cluster_id.0 != self.raft.config().cluster_id {
+            return error(ErrorCode::InconsistentClusterId);
+        }
+        let image = self.image.read().unwrap();
+        let id = request.broker_id;
+        let unsupported = image.finalized_features().iter().find(|&(name, &level)| {
+            !request.features.iter().any(|f| {
+                f.name.0 == *name
+                    && (f.min_supported_version..=f.max_supported_version).contains(&level)
+            })
+        });
+        if let Some((name, level)) = unsupported {
+            info!("Broker {id} does not support {name} at level {level}");
+            return error(ErrorCode::UnsupportedVersion);
+        }
+        if let Some(existing) = image.broker(id) {
+            if existing.incarnation_id != request.incarnation_id
+                && !existing.fenced
+                && !sessions.is_expired(id, now, self.session_timeout)
+            {
+                info!("Broker {id} is already registered by another incarnation");
+                return error(ErrorCode::DuplicateBrokerRegistration);
+            }
+        }
+
+        let mut registration = BrokerRegistration {
+            id,
+            epoch: -1,
+            incarnation_id: request.incarnation_id,
+            endpoints: request
+                .listeners
+                .iter()
+                .map(|l| BrokerEndpoint {
+                    name: l.name.0.clone(),
+                    host: l.host.0.clone(),
+                    port: l.port,
+                    security_protocol: l.security_protocol,
+                })
+                .collect(),
+            features: request
+                .features
+                .iter()
+                .map(|f| {
+                    let range = (f.min_supported_version, f.max_supported_version);
+                    (f.name.0.clone(), range)
+                })
+                .collect(),
+            rack: request.rack.0.clone(),
+            fenced: true,
+            in_controlled_shutdown: false,
+            log_dirs: request.log_dirs.iter().copied().collect(),
+        };
+        let moves = fail_over_leaders(&image, &[id], &[]);
+        let moved = moves.len();
+        let offset = self.append_with(&mut sessions, |base_offset| {
+            registration.epoch = base_offset;
+            let record = RegisterBrokerRecord::new(&registration);
+            let mut records = vec![MetadataRecord::RegisterBroker(record)];
+            records.extend(moves);
+            records
+        })?;
+        sessions.touch(id, now);
+        info!(
+            "Registered broker {id} with epoch {}, moving the leadership of {moved} partitions",
+            registration.epoch
+        );
+        let response = BrokerRegistrationResponseBody::new(registration.epoch);
+        Ok(ControllerResult::written(response, offset))
+    }
+
+    /// Keeps the session of a registered broker alive, and fences or unfences it.
+    /// Brokers are unfenced once they replayed the metadata log up to their registration
+    /// and do not want to be fenced, and fenced when they want to or shut down, the
+    /// leadership of their partitions moving along.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the offline log dirs cannot be read, or the records cannot be appended
+    /// to the metadata log
+    pub fn heartbeat(
+        &self,
+        request: &BrokerHeartbeatRequestBody,
+    ) -> anyhow::Result<ControllerResult<BrokerHeartbeatResponseBody>> {
+        let now = Instant::now();
+        let mut sessions = self.sessions();
+        let error = |code| {
+            Ok(ControllerResult::ready(BrokerHeartbeatResponseBody::error(
+                code,
+            )))
+        };
+        if !self.raft.is_leader() {
+            return error(ErrorCode::NotController);
+        }
+        let image = self.image.read().unwrap();
+        let id = request.broker_id;
+        let Some(broker) = image.broker(id) else {
+            return error(ErrorCode::BrokerIdNotRegistered);
+        };
+        if broker.epoch != request.broker_epoch {
+            return error(ErrorCode::StaleBrokerEpoch);
+        }
+        sessions.touch(id, now);
+        let offline_log_dirs = request.offline_log_dirs()?;
+        if !offline_log_dirs.is_empty() {
+            warn!("Broker {id} lost the log dirs {offline_log_dirs:?}");
+        }
+
+        let caught_up = request.current_metadata_offset >= broker.epoch;
+        let shut_down = request.want_shut_down.is_true();
+        let fence = (shut_down || request.want_fence.is_true()) && !broker.fenced;
+        let unfence = !shut_down && !request.want_fence.is_true() && broker.fenced && caught_up;
+        if !(fence || unfence) || image.offset < sessions.written {
+            let response = BrokerHeartbeatResponseBody::new(caught_up, broker.fenced, shut_down);
+            return Ok(ControllerResult::ready(response));
+        }
+
+        let mut records = if fence {
+            info!("Fencing broker {id} at its request");
+            vec![MetadataRecord::FenceBroker(FenceBrokerRecord::new(
+                id,
+                broker.epoch,
+            ))]
+        } else {
+            info!("Unfencing broker {id}");
+            vec![MetadataRecord::UnfenceBroker(UnfenceBrokerRecord::new(
+                id,
+                broker.epoch,
+            ))]
+        };
+        let (fenced, unfenced): (&[i32], &[i32]) = if fence { (&[id], &[]) } else { (&[], &[id]) };
+        records.extend(fail_over_leaders(&image, fenced, unfenced));
+        let offset = self.append(&mut sessions, records)?;
+        let response = BrokerHeartbeatResponseBody::new(caught_up, fence, shut_down);
+        Ok(ControllerResult::written(response, offset))
+    }
+
+    /// Elects new leaders for the partitions of the request, every partition without
+    /// one. Preferred elections move the leadership back to the first replica if it is
+    /// live and in sync. Unclean elections only elect partitions without a live leader,
+    /// out of the replicas that are not in sync if no live one is; those partitions lose
+    /// what only their ISR had. Requests deciding on an image that did not replay the
+    /// latest writes of the controller yet time out, to be retried.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn elect_leaders(
+        &self,
+        request: &ElectLeadersRequestBody,
+    ) -> anyhow::Result<ControllerResult<ElectLeadersResponseBody>> {
+        let mut sessions = self.sessions();
+        let error = |code| {
+            Ok(ControllerResult::ready(ElectLeadersResponseBody::error(
+                code,
+            )))
+        };
+        if !self.raft.is_leader() {
+            return error(ErrorCode::NotController);
+        }
+        let Some(election) = request.election() else {
+            return error(ErrorCode::InvalidRequest);
+        };
+        let image = self.image.read().unwrap();
+        if image.offset < sessions.written {
+            return error(ErrorCode::RequestTimedOut);
+        }
+
+        let requested: Vec<(String, i32)> = match &request.topic_partitions {
+            Some(topics) => topics
+                .iter()
+                .flat_map(|t| t.partitions.iter().map(|&p| (t.topic.0.clone(), p)))
+                .collect(),
+            None => image
+                .topics()
+                .flat_map(|t| t.partitions.keys().map(|&p| (t.name.clone(), p)))
+                .collect(),
+        };
+        let mut results: BTreeMap<String, Vec<ElectLeadersPartitionResult>> = BTreeMap::new();
+        let mut records = Vec::new();
+        for (name, index) in requested {
+            let found = image
+                .topic_by_name(&name)
+                .and_then(|topic| Some((topic, topic.partitions.get(&index)?)));
+            let result = match found {
+                None => ElectLeadersPartitionResult::new(
+                    index,
+                    ErrorCode::UnknownTopicOrPartition,
+                    Some(format!("Partition {name}-{index} does not exist")),
+                ),
+                Some((topic, partition)) => match elect(&image, partition, election) {
+                    Ok((leader, isr)) => {
+                        info!(
+                            "{election:?} election of {name}-{index} moves its leadership from {} to {leader}",
+                            partition.leader
+                        );
+                        records.push(new_leader(topic, index, partition, leader, isr));
+                        ElectLeadersPartitionResult::new(index, ErrorCode::None, None)
+                    }
+                    // asking for every partition only reports the ones that changed
+                    Err(ErrorCode::ElectionNotNeeded) if request.topic_partitions.is_none() => {
+                        continue;
+                    }
+                    Err(code) => ElectLeadersPartitionResult::new(
+                        index,
+                        code,
+                        Some(format!(
+                            "{election:?} election of {name}-{index} failed: {code}"
+                        )),
+                    ),
+                },
+            };
+            results.entry(name).or_default().push(result);
+        }
+
+        let mut topics = CompactArray::with_capacity(results.len());
+        for (name, partitions) in results {
+            let mut partition_results = CompactArray::with_capacity(partitions.len());
+            partitions
+                .into_iter()
+                .for_each(|p| partition_results.push(p));
+            topics.push(ElectLeadersTopicResult::new(name, partition_results));
+        }
+        let response = ElectLeadersResponseBody::new(topics);
+        if records.is_empty() {
+            return Ok(ControllerResult::ready(response));
+        }
+        let offset = self.append(&mut sessions, records)?;
+        Ok(ControllerResult::written(response, offset))
+    }
+
+    /// Creates the topics of the request, writing a topic record, a record for every
+    /// partition and one for every config of each. Replicas are assigned round-robin
+    /// over the unfenced brokers unless the request places them, the first replica
+    /// leading. Topics the client may not create, per `authorized`, fail with
+    /// `TOPIC_AUTHORIZATION_FAILED`; with `validate_only` nothing is written.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn create_topics<F>(
+        &self,
+        request: &CreateTopicsRequestBody,
+        authorized: F,
+    ) -> anyhow::Result<ControllerResult<CreateTopicsResponseBody>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut sessions = self.sessions();
+        let error = |code| {
+            let names = request.topics.iter().map(|t| t.name.0.as_str());
+            Ok(ControllerResult::ready(CreateTopicsResponseBody::error(
+                names, code,
+            )))
+        };
+        if !self.raft.is_leader() {
+            return error(ErrorCode::NotController);
+        }
+        let image = self.image.read().unwrap();
+        if image.offset < sessions.written {
+            return error(ErrorCode::RequestTimedOut);
+        }
+
+        let mut brokers: Vec<i32> = image
+            .brokers()
+            .filter(|broker| !broker.fenced)
+            .map(|broker| broker.id)
+            .collect();
+        brokers.sort_unstable();
+        let mut results = CompactArray::with_capacity(request.topics.len());
+        let mut records = Vec::new();
+        for topic in request.topics.iter() {
+            let name = &topic.name.0;
+            let duplicated = request.topics.iter().filter(|t| t.name.0 == *name).count() > 1;
+            let planned = if duplicated {
+                Err((
+                    ErrorCode::InvalidRequest,
+                    format!("Topic {name} is listed more than once"),
+                ))
+            } else if !authorized(name) {
+                Err((
+                    ErrorCode::TopicAuthorizationFailed,
+                    format!("Not authorized to create topic {name}"),
+                ))
+            } else {
+                self.plan_topic(&image, &brokers, topic)
+            };
+            let NewTopic {
+                assignment,
+                configs,
+            } = match planned {
+                Ok(planned) => planned,
+                Err((code, message)) => {
+                    info!("Not creating topic {name}: {message}");
+                    results.push(CreatableTopicResult::error(name, code, message));
+                    continue;
+                }
+            };
+
+            let mut result_configs = CompactArray::with_capacity(configs.len());
+            for (key, value) in &configs {
+                let source = ConfigSource::DynamicTopic.code();
+                result_configs.push(CreatableTopicConfigs::new(key, value, source));
+            }
+            let replication_factor = assignment.first().map_or(0, Vec::len) as i16;
+            let partitions = assignment.len() as i32;
+            let topic_id = if request.validate_only.is_true() {
+                Uuid::ZERO
+            } else {
+                let topic_id = Uuid::random();
+                info!(
+                    "Creating topic {name} with {partitions} partitions of {replication_factor} replicas"
+                );
+                records.push(MetadataRecord::Topic(TopicRecord::new(name, topic_id)));
+                for (index, replicas) in (0..).zip(assignment) {
+                    let record = PartitionRecord::new(index, topic_id, replicas);
+                    records.push(MetadataRecord::Partition(record));
+                }
+                let entity = ConfigEntity::Topic(name.clone());
+                for (key, value) in configs {
+                    let record = ConfigRecord::new(&entity, key, Some(value));
+                    records.push(MetadataRecord::Config(record));
+                }
+                topic_id
+            };
+            results.push(CreatableTopicResult::new(
+                name,
+                topic_id,
+                partitions,
+                replication_factor,
+                result_configs,
+            ));
+        }
+
+        let response = CreateTopicsResponseBody::new(results);
+        if records.is_empty() {
+            return Ok(ControllerResult::ready(response));
+        }
+        let offset = self.append(&mut sessions, records)?;
+        Ok(ControllerResult::written(response, offset))
+    }
+
+    /// How the topic of the request is to be created, or why it cannot be
+    fn plan_topic(
+        &self,
+        image: &MetadataImage,
+        brokers: &[i32],
+        topic: &CreatableTopic,
+    ) -> Result<NewTopic, (ErrorCode, String)> {
+        let name = &topic.name.0;
+        validate_topic_name(name).map_err(|e| (ErrorCode::InvalidTopicException, e))?;
+        if image.topic_by_name(name).is_some() {
+            return Err((
+                ErrorCode::TopicAlreadyExists,
+                format!("Topic {name} already exists"),
+            ));
+        }
+
+        let mut configs = BTreeMap::new();
+        for config in topic.configs.iter() {
+            let Some(value) = &config.value.0 else {
+                return Err((
+                    ErrorCode::InvalidConfig,
+                    format!("Config {} of topic {name} has no value", config.name.0),
+                ));
+            };
+            configs.insert(config.name.0.clone(), value.clone());
+        }
+        let entity = ConfigEntity::Topic(name.clone());
+        ConfigManager::validate(&entity, &configs)
+            .map_err(|e| (ErrorCode::InvalidConfig, format!("{e:#}")))?;
+
+        let assignment = if topic.assignments.is_empty() {
+            let partitions = match topic.num_partitions {
+                -1 => self.num_partitions,
+                n => n,
+            };
+            let replication_factor = match topic.replication_factor {
+                -1 => self.default_replication_factor,
+                n => n,
+            };
+            if partitions <= 0 {
+                return Err((
+                    ErrorCode::InvalidPartitions,
+                    format!("Topic {name} needs at least one partition"),
+                ));
+            }
+            if replication_factor <= 0 || replication_factor as usize > brokers.len() {
+                return Err((
+                    ErrorCode::InvalidReplicationFactor,
+                    format!(
+                        "Replication factor {replication_factor} of topic {name} is not within 1 and the {} unfenced brokers",
+                        brokers.len()
+                    ),
+                ));
+            }
+            assign_replicas(brokers, partitions, replication_factor as usize)
+        } else {
+            if topic.num_partitions != -1 || topic.replication_factor != -1 {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    format!(
+                        "Topic {name} places its replicas, its partition count and replication factor must be -1"
+                    ),
+                ));
+            }
+            let mut assignments: Vec<_> = topic.assignments.iter().collect();
+            assignments.sort_by_key(|a| a.partition_index);
+            let mut assignment = Vec::with_capacity(assignments.len());
+            for (index, a) in (0..).zip(assignments) {
+                let replicas: Vec<i32> = a.broker_ids.iter().copied().collect();
+                let mut distinct = replicas.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                let invalid = if a.partition_index != index {
+                    Some("partitions are not numbered from 0 without gaps")
+                } else if replicas.is_empty() {
+                    Some("a partition has no replicas")
+                } else if distinct.len() != replicas.len() {
+                    Some("a partition lists a replica twice")
+                } else if replicas.iter().any(|&id| image.broker(id).is_none()) {
+                    Some("a replica is not a registered broker")
+                } else {
+                    None
+                };
+                if let Some(invalid) = invalid {
+                    return Err((
+                        ErrorCode::InvalidReplicaAssignment,
+                        format!("Invalid replica assignment of topic {name}: {invalid}"),
+                    ));
+                }
+                assignment.push(replicas);
+            }
+            assignment
+        };
+        Ok(NewTopic {
+            assignment,
+            configs,
+        })
+    }
+
+    /// Appends the records `build` makes of the image for an admin request, answering
+    /// with what it returns. The request fails as a whole with `NOT_CONTROLLER` when the
+    /// node does not lead the quorum, and with `REQUEST_TIMED_OUT` while the image did
+    /// not replay the latest writes of the controller yet.
+    fn write_admin<T, F>(&self, build: F) -> anyhow::Result<ControllerResult<Result<T, ErrorCode>>>
+    where
+        F: FnOnce(&MetadataImage) -> (T, Vec<MetadataRecord>),
+    {
+        let mut sessions = self.sessions();
+        if !self.raft.is_leader() {
+            return Ok(ControllerResult::ready(Err(ErrorCode::NotController)));
+        }
+        let image = self.image.read().unwrap();
+        if image.offset < sessions.written {
+            return Ok(ControllerResult::ready(Err(ErrorCode::RequestTimedOut)));
+        }
+        let (response, records) = build(&image);
+        if records.is_empty() {
+            return Ok(ControllerResult::ready(Ok(response)));
+        }
+        let offset = self.append(&mut sessions, records)?;
+        Ok(ControllerResult::written(Ok(response), offset))
+    }
+
+    /// Adds the ACLs of `bindings` the image does not have yet, each under a new id
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn create_acls(
+        &self,
+        bindings: &[AclBinding],
+    ) -> anyhow::Result<ControllerResult<Result<(), ErrorCode>>> {
+        self.write_admin(|image| {
+            let mut existing: BTreeSet<&AclBinding> = image.acls().values().collect();
+            let records = bindings
+                .iter()
+                .filter(|&binding| existing.insert(binding))
+                .map(|binding| {
+                    let record = AccessControlEntryRecord::new(Uuid::random(), binding);
+                    MetadataRecord::AccessControlEntry(record)
+                })
+                .collect();
+            ((), records)
+        })
+    }
+
+    /// Removes the ACLs of the image matching any of `filters`, answering with the ones
+    /// each filter matched
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn delete_acls(
+        &self,
+        filters: &[AclBindingFilter],
+    ) -> anyhow::Result<ControllerResult<Result<Vec<Vec<AclBinding>>, ErrorCode>>> {
+        self.write_admin(|image| {
+            let mut removed = BTreeSet::new();
+            let mut records = Vec::new();
+            let mut matched = Vec::with_capacity(filters.len());
+            for filter in filters {
+                let mut bindings = BTreeSet::new();
+                for (&id, binding) in image.acls() {
+                    if !filter.matches(binding) {
+                        continue;
+                    }
+                    if removed.insert(id) {
+                        let record = RemoveAccessControlEntryRecord::new(id);
+                        records.push(MetadataRecord::RemoveAccessControlEntry(record));
+                    }
+                    bindings.insert(binding.clone());
+                }
+                matched.push(bindings.into_iter().collect());
+            }
+            (matched, records)
+        })
+    }
+
+    /// Replaces the dynamic configs of every entity of `changes`, empty values removing
+    /// all of them
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn alter_configs(
+        &self,
+        changes: &[(ConfigEntity, BTreeMap<String, String>)],
+    ) -> anyhow::Result<ControllerResult<Result<(), ErrorCode>>> {
+        self.write_admin(|image| {
+            let mut records = Vec::new();
+            for (entity, values) in changes {
+                let current = image.configs(entity).cloned().unwrap_or_default();
+                for key in current.keys().filter(|&key| !values.contains_key(key)) {
+                    let record = ConfigRecord::new(entity, key.clone(), None);
+                    records.push(MetadataRecord::Config(record));
+                }
+                for (key, value) in values {
+                    if current.get(key) != Some(value) {
+                        let record = ConfigRecord::new(entity, key.clone(), Some(value.clone()));
+                        records.push(MetadataRecord::Config(record));
+                    }
+                }
+            }
+            ((), records)
+        })
+    }
+
+    /// Sets or removes the client quotas of `changes`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn alter_client_quotas(
+        &self,
+        changes: &[(QuotaEntity, Vec<QuotaOp>)],
+    ) -> anyhow::Result<ControllerResult<Result<(), ErrorCode>>> {
+        self.write_admin(|_| {
+            let records = changes
+                .iter()
+                .flat_map(|(entity, ops)| {
+                    ops.iter().map(move |op| {
+                        let record = ClientQuotaRecord::new(entity, op.key.clone(), op.value);
+                        MetadataRecord::ClientQuota(record)
+                    })
+                })
+                .collect();
+            ((), records)
+        })
+    }
+
+    /// Sets or removes the SCRAM credentials of `changes`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn alter_scram_credentials(
+        &self,
+        changes: &[ScramCredentialChange],
+    ) -> anyhow::Result<ControllerResult<Result<(), ErrorCode>>> {
+        self.write_admin(|_| {
+            let records = changes
+                .iter()
+                .map(|(mechanism, user, credential)| match credential {
+                    Some(credential) => MetadataRecord::UserScramCredential(
+                        UserScramCredentialRecord::new(user.clone(), *mechanism, credential),
+                    ),
+                    None => MetadataRecord::RemoveUserScramCredential(
+                        RemoveUserScramCredentialRecord::new(user.clone(), *mechanism),
+                    ),
+                })
+                .collect();
+            ((), records)
+        })
+    }
+
+    /// Fences the unfenced brokers that sent no heartbeat within the session timeout,
+    /// returning the offset of the last record written for them
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records cannot be appended to the metadata log
+    pub fn fence_expired(&self, now: Instant) -> anyhow::Result<Option<i64>> {
+        let mut sessions = self.sessions();
+        if !self.raft.is_leader() {
+            return Ok(None);
+        }
+        let image = self.image.read().unwrap();
+        if image.offset < sessions.written {
+            return Ok(None);
+        }
+        let expired: Vec<_> = image
+            .brokers()
+            .filter(|broker| !broker.fenced)
+            .filter(|broker| sessions.is_expired(broker.id, now, self.session_timeout))
+            .map(|broker| (broker.id, broker.epoch))
+            .collect();
+        if expired.is_empty() {
+            return Ok(None);
+        }
+        info!(
+            "Fencing brokers {expired:?} without a heartbeat for {:?}",
+            self.session_timeout
+        );
+        let ids: Vec<_> = expired.iter().map(|&(id, _)| id).collect();
+        let mut records: Vec<_> = expired
+            .into_iter()
+            .map(|(id, epoch)| MetadataRecord::FenceBroker(FenceBrokerRecord::new(id, epoch)))
+            .collect();
+        records.extend(fail_over_leaders(&image, &ids, &[]));
+        self.append(&mut sessions, records).map(Some)
+    }
+}
+
+/// The partition records moving leadership once the brokers in `fenced` are fenced
+/// and the ones in `unfenced` unfenced. Partitions whose leader is fenced, or without a
+/// leader, are led by the first live member of their ISR, or by none if there is no
+/// such member. The ISR is left as is, leaders keep track of it themselves.
+fn fail_over_leaders(
+    image: &MetadataImage,
+    fenced: &[i32],
+    unfenced: &[i32],
+) -> Vec<MetadataRecord> {
+    let live = |id: i32| !fenced.contains(&id) && (unfenced.contains(&id) || !image.is_fenced(id));
+    let mut records = Vec::new();
+    for topic in image.topics() {
+        for (&index, partition) in &topic.partitions {
+            if partition.leader >= 0 && live(partition.leader) {
+                continue;
+            }
+            let leader = partition
+                .isr
+                .iter()
+                .copied()
+                .find(|&r| live(r))
+                .unwrap_or(-1);
+            if leader == partition.leader {
+                continue;
+            }
+            debug!(
+                "Moving the leadership of {}-{index} from {} to {leader}",
+                topic.name, partition.leader
+            );
+            records.push(new_leader(topic, index, partition, leader, None));
+        }
+    }
+    records
+}
+
+/// The leader `election` picks for a partition, along with its new ISR if the election
+/// had to leave the old one, or why there is none to pick
+fn elect(
+    image: &MetadataImage,
+    partition: &PartitionRegistration,
+    election: ElectionType,
+) -> Result<(i32, Option<Vec<i32>>), ErrorCode> {
+    let live = |id: i32| !image.is_fenced(id);
+    match election {
+        ElectionType::Preferred => {
+            let preferred = partition.replicas.first().copied().unwrap_or(-1);
+            if partition.leader == preferred {
+                Err(ErrorCode::ElectionNotNeeded)
+            } else if live(preferred) && partition.isr.contains(&preferred) {
+                Ok((preferred, None))
+            } else {
+                Err(ErrorCode::PreferredLeaderNotAvailable)
+            }
+        }
+        ElectionType::Unclean => {
+            if partition.leader >= 0 && live(partition.leader) {
+                return Err(ErrorCode::ElectionNotNeeded);
+            }
+            if let Some(&leader) = partition.isr.iter().find(|&&r| live(r)) {
+                return Ok((leader, None));
+            }
+            // whatever only the replicas of the ISR had is lost
+            match partition.replicas.iter().find(|&&r| live(r)) {
+                Some(&leader) => Ok((leader, Some(vec![leader]))),
+                None => Err(ErrorCode::EligibleLeadersNotAvailable),
+            }
+        }
+    }
+}
+
+/// The record moving the leadership of a partition to `leader` in a new leader epoch
+fn new_leader(
+    topic: &TopicImage,
+    index: i32,
+    partition: &PartitionRegistration,
+    leader: i32,
+    isr: Option<Vec<i32>>,
+) -> MetadataRecord {
+    let mut record = partition.to_record(index, topic.id);
+    record.leader = leader;
+    record.leader_epoch += 1;
+    record.partition_epoch += 1;
+    if let Some(isr) = isr {
+        record.isr = isr.into();
+    }
+    MetadataRecord::Partition(record)
+}
+
+/// Checks that `name` can name a topic: up to 249 ASCII letters, digits, `.`, `_`
+/// and `-`, and neither `.` nor `..`
+fn validate_topic_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(format!("Topic name {name:?} is not allowed"));
+    }
+    if name.len() > 249 {
+        return Err(format!("Topic name {name} is longer than 249 characters"));
+    }
+    if !name
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b))
+    {
+        return Err(format!(
+            "Topic name {name} has characters other than ASCII letters, digits, '.', '_' and '-'"
+        ));
+    }
+    Ok(())
+}
+
+/// The replicas of `partitions` partitions of `replication_factor` replicas each,
+/// assigned round-robin over `brokers` from a random one so that the leadership of
+/// new topics is spread
+fn assign_replicas(brokers: &[i32], partitions: i32, replication_factor: usize) -> Vec<Vec<i32>> {
+    let start = rand::thread_rng().gen_range(0..brokers.len());
+    (0..partitions as usize)
+        .map(|p| {
+            (0..replication_factor)
+                .map(|r| brokers[(start + p + r) % brokers.len()])
+                .collect()
+        })
+        .collect()
+}
+
+/// Fences the brokers whose session expired, checking every quarter of the session
+/// timeout, for as long as the task is not aborted
+pub async fn run_session_expiration(controller: Arc<QuorumController>) {
+    let mut interval = tokio::time::interval(controller.session_timeout / 4);
+    loop {
+        interval.tick().await;
+        let controller = Arc::clone(&controller);
+        let fenced = tokio::task::spawn_blocking(move || controller.fence_expired(Instant::now()))
+            .await
+            .context("Fencing expired brokers panicked")
+            .and_then(|r| r);
+        if let Err(e) = fenced {
+            warn!("Fencing the brokers with expired sessions failed: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::JoinHandle;
+
+    use crate::{metadata::MetadataLoader, raft::RaftConfig, request::ElectLeadersTopic};
+
+    async fn replayed(image: &RwLock<MetadataImage>, offset: Option<i64>) {
+        let offset = offset.expect("records were written");
+        for _ in 0..200 {
+            if image.read().unwrap().offset >= offset {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    /// A controller leading a quorum of its own, with a loader replaying what it writes
+    /// into its image
+    fn leading_controller(
+        dir: &std::path::Path,
+        session_timeout: Duration,
+    ) -> (QuorumController, Arc<RwLock<MetadataImage>>, JoinHandle<()>) {
+        let raft_config = RaftConfig {
+            node_id: 1,
+            cluster_id: "controller-test".to_string(),
+            voters: [(1, "localhost:0".to_string())].into(),
+            election_timeout: Duration::from_secs(1),
+            fetch_timeout: Duration::from_secs(1),
+            election_backoff_max: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(1),
+        };
+        let raft = Arc::new(RaftClient::open(raft_config, dir).unwrap());
+        raft.become_candidate().unwrap();
+        let config = BrokerConfig {
+            broker_session_timeout: session_timeout,
+            ..BrokerConfig::default()
+        };
+        let image = Arc::new(RwLock::new(MetadataImage::new()));
+        let controller = QuorumController::new(Arc::clone(&raft), Arc::clone(&image), &config);
+        let loader = MetadataLoader::new(raft, Arc::clone(&image), &config);
+        (controller, image, tokio::spawn(loader.run()))
+    }
+
+    /// An image of the live brokers 1 to 3 and of a topic whose partition 0 has all of
+    /// them as replicas, is led by broker 1 and has brokers 1 and 3 in sync
+    fn image_with_partition() -> MetadataImage {
+        let topic_id = Uuid::random();
+        let mut records = vec![MetadataRecord::Topic(TopicRecord::new("foo", topic_id))];
+        for id in 1..=3 {
+            let broker = BrokerRegistration {
+                id,
+                epoch: i64::from(id),
+                incarnation_id: Uuid::random(),
+                endpoints: Vec::new(),
+                features: BTreeMap::new(),
+                rack: None,
+                fenced: false,
+                in_controlled_shutdown: false,
+                log_dirs: Vec::new(),
+            };
+            records.push(MetadataRecord::RegisterBroker(RegisterBrokerRecord::new(
+                &broker,
+            )));
+        }
+        let mut partition = PartitionRecord::new(0, topic_id, vec![1, 2, 3]);
+        partition.isr = vec![1, 3].into();
+        records.push(MetadataRecord::Partition(partition));
+
+        let mut image = MetadataImage::new();
+        for (offset, record) in (0..).zip(&records) {
+            image.apply(offset, record);
+        }
+        image
+    }
+
+    /// The leader, leader epoch and ISR a partition record moves its partition to
+    fn leadership(record: &MetadataRecord) -> (i32, i32, Vec<i32>) {
+        let MetadataRecord::Partition(partition) = record else {
+            panic!("{record:?} is not a partition record");
+        };
+        (
+            partition.leader,
+            partition.leader_epoch,
+            partition.isr.iter().copied().collect(),
+        )
+    }
+
+    #[test]
+    fn test_fenced_leader_fails_over_to_isr() {
+        let image = image_with_partition();
+        assert!(fail_over_leaders(&image, &[2], &[]).is_empty());
+
+        // broker 2 is live but out of sync, so the leadership goes to broker 3
+        let moves = fail_over_leaders(&image, &[1], &[]);
+        assert_eq!(1, moves.len());
+        assert_eq!((3, 1, vec![1, 3]), leadership(&moves[0]));
+
+        // without a live member of the ISR the partition is left without a leader
+        let moves = fail_over_leaders(&image, &[1, 3], &[]);
+        assert_eq!((-1, 1, vec![1, 3]), leadership(&moves[0]));
+    }
+
+    #[test]
+    fn test_elect() {
+        let image = image_with_partition();
+        let partition = &image.topic_by_name("foo").unwrap().partitions[&0];
+        assert_eq!(
+            Err(ErrorCode::ElectionNotNeeded),
+            elect(&image, partition, ElectionType::Preferred)
+        );
+        assert_eq!(
+            Err(ErrorCode::ElectionNotNeeded),
+            elect(&image, partition, ElectionType::Unclean)
+        );
+
+        let mut led_by_3 = partition.clone();
+        led_by_3.leader = 3;
+        assert_eq!(
+            Ok((1, None)),
+            elect(&image, &led_by_3, ElectionType::Preferred)
+        );
+
+        // broker 2 only gets the leadership uncleanly, as the last live replica
+        let mut fenced = image.clone();
+        for id in [1, 3] {
+            let record = FenceBrokerRecord::new(id, i64::from(id));
+            fenced.apply(10 + i64::from(id), &MetadataRecord::FenceBroker(record));
+        }
+        assert_eq!(
+            Err(ErrorCode::PreferredLeaderNotAvailable),
+            elect(&fenced, &led_by_3, ElectionType::Preferred)
+        );
+        assert_eq!(
+            Ok((2, Some(vec![2]))),
+            elect(&fenced, &led_by_3, ElectionType::Unclean)
+        );
+    }
+
+    #[test]
+    fn test_validate_topic_name() {
+        assert!(validate_topic_name("foo.bar_baz-1").is_ok());
+        assert!(validate_topic_name(&"a".repeat(249)).is_ok());
+        for name in ["", ".", "..", "foo bar", "foo/bar", &"a".repeat(250)] {
+            assert!(validate_topic_name(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_assign_replicas() {
+        let assignment = assign_replicas(&[1, 2, 3], 4, 2);
+        assert_eq!(4, assignment.len());
+        // every partition starts on the broker after the previous one's first replica
+        for pair in assignment.windows(2) {
+            assert_eq!(pair[0][1], pair[1][0]);
+            assert_ne!(pair[0][0], pair[0][1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_elect_leaders_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let (controller, image, loader) = leading_controller(dir.path(), Duration::from_secs(10));
+        let mut records = image_with_partition().records();
+        // the leader of the partition is fenced, leaving broker 3 as the live ISR member
+        records.push(MetadataRecord::FenceBroker(FenceBrokerRecord::new(1, 1)));
+        let offset = controller
+            .append(&mut controller.sessions(), records)
+            .unwrap();
+        replayed(&image, Some(offset)).await;
+
+        let topics = vec![
+            ElectLeadersTopic::new("foo", vec![0, 7].into()),
+            ElectLeadersTopic::new("bar", vec![0].into()),
+        ];
+        let request =
+            ElectLeadersRequestBody::new(ElectionType::Unclean, Some(topics.into()), 1000);
+        let elected = controller.elect_leaders(&request).unwrap();
+        let response = &elected.response;
+        assert_eq!(ErrorCode::None.code(), response.error_code);
+        // topics are answered in the order of their names
+        let results: Vec<_> = response
+            .replica_election_results
+            .iter()
+            .map(|t| {
+                let partitions: Vec<_> = t
+                    .partition_result
+                    .iter()
+                    .map(|p| (p.partition_id, p.error_code, p.error_message.0.is_some()))
+                    .collect();
+                (t.topic.0.clone(), partitions)
+            })
+            .collect();
+        let unknown = ErrorCode::UnknownTopicOrPartition.code();
+        assert_eq!(
+            vec![
+                ("bar".to_string(), vec![(0, unknown, true)]),
+                (
+                    "foo".to_string(),
+                    vec![(0, ErrorCode::None.code(), false), (7, unknown, true)]
+                ),
+            ],
+            results
+        );
+        replayed(&image, elected.offset).await;
+        let leader = image
+            .read()
+            .unwrap()
+            .topic_by_name("foo")
+            .unwrap()
+            .partitions[&0]
+            .leader;
+        assert_eq!(3, leader);
+
+        // asking for every partition leaves out the ones without an election to hold
+        let request = ElectLeadersRequestBody::new(ElectionType::Unclean, None, 1000);
+        let elected = controller.elect_leaders(&request).unwrap();
+        assert_eq!(0, elected.response.replica_election_results.len());
+        assert!(elected.offset.is_none());
+        loader.abort();
+    }
+
+    #[tokio::test]
+    async fn test_registration_and_fencing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (controller, image, loader) =
+            leading_controller(dir.path(), Duration::from_millis(100));
+
+        let topic_id = Uuid::random();
+        // only broker 2 is in sync for the second partition
+        let mut out_of_sync = PartitionRecord::new(1, topic_id, vec![1, 2]);
+        out_of_sync.leader = 2;
+        out_of_sync.isr = vec![2].into();
+        let records = vec![
+            MetadataRecord::Topic(TopicRecord::new("foo", topic_id)),
+            MetadataRecord::Partition(PartitionRecord::new(0, topic_id, vec![1, 2])),
+            MetadataRecord::Partition(out_of_sync),
+        ];
+        let offset = controller
+            .append(&mut controller.sessions(), records)
+            .unwrap();
+        replayed(&image, Some(offset)).await;
+        let partition = |index| {
+            let image = image.read().unwrap();
+            let partition = &image.topic(&topic_id).unwrap().partitions[&index];
+            (
+                partition.leader,
+                partition.leader_epoch,
+                partition.isr.clone(),
+            )
+        };
+        let leader = || {
+            let (leader, leader_epoch, _) = partition(0);
+            (leader, leader_epoch)
+        };
+        let elect = |election, index| {
+            let topics = vec![ElectLeadersTopic::new("foo", vec![index].into())];
+            let request = ElectLeadersRequestBody::new(election, Some(topics.into()), 1000);
+            let elected = controller.elect_leaders(&request).unwrap();
+            let result = &elected.response.replica_election_results;
+            let code = result
+                .iter()
+                .next()
+                .unwrap()
+                .partition_result
+                .iter()
+                .next()
+                .unwrap()
+                .error_code;
+            (code, elected.offset)
+        };
+
+        let register =
+            |id| BrokerRegistrationRequestBody::new(id, "controller-test", Uuid::random());
+        let mut epochs = Vec::new();
+        for id in [1, 2] {
+            let registered = controller.register_broker(&register(id)).unwrap();
+            replayed(&image, registered.offset).await;
+            epochs.push(registered.response.broker_epoch);
+        }
+        // registered brokers are fenced, the partition has no live replica to lead it
+        assert!(image.read().unwrap().is_fenced(1));
+        assert_eq!((-1, 1), leader());
+        let mut other_cluster = register(3);
+        other_cluster.cluster_id.0 = "other".to_string();
+        let response = controller.register_broker(&other_cluster).unwrap().response;
+        assert_eq!(ErrorCode::InconsistentClusterId.code(), response.error_code);
+
+        let heartbeat = |id: i32, want_shut_down| {
+            let epoch = epochs[id as usize - 1];
+            let offset = image.read().unwrap().offset;
+            let request = BrokerHeartbeatRequestBody::new(id, epoch, offset, false, want_shut_down);
+            controller.heartbeat(&request).unwrap()
+        };
+        let stale = BrokerHeartbeatRequestBody::new(2, epochs[1] - 1, 0, false, false);
+        let response = controller.heartbeat(&stale).unwrap().response;
+        assert_eq!(ErrorCode::StaleBrokerEpoch.code(), response.error_code);
+
+        // caught up brokers are unfenced and lead the partitions without a leader
+        let unfenced = heartbeat(2, false);
+        assert!(!unfenced.response.is_fenced.is_true());
+        replayed(&image, unfenced.offset).await;
+        assert_eq!((2, 2), leader());
+
+        let unfenced = heartbeat(1, false);
+        replayed(&image, unfenced.offset).await;
+        assert_eq!((2, 2), leader());
+        // the preferred replica is back in sync, it takes over again
+        let (code, offset) = elect(ElectionType::Preferred, 0);
+        assert_eq!(ErrorCode::None.code(), code);
+        replayed(&image, offset).await;
+        assert_eq!((1, 3), leader());
+        let (code, _) = elect(ElectionType::Preferred, 0);
+        assert_eq!(ErrorCode::ElectionNotNeeded.code(), code);
+        // another incarnation of a live broker is turned down
+        let response = controller.register_broker(&register(1)).unwrap().response;
+        assert_eq!(
+            ErrorCode::DuplicateBrokerRegistration.code(),
+            response.error_code
+        );
+
+        // without heartbeats the session expires
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        heartbeat(1, false);
+        let fenced = controller.fence_expired(Instant::now()).unwrap();
+        replayed(&image, fenced).await;
+        assert!(image.read().unwrap().is_fenced(2));
+        assert_eq!((1, 3), leader());
+        // no replica of the ISR is left, only an unclean election finds a leader
+        assert_eq!(-1, partition(1).0);
+        let (code, _) = elect(ElectionType::Preferred, 1);
+        assert_eq!(ErrorCode::PreferredLeaderNotAvailable.code(), code);
+        let (code, offset) = elect(ElectionType::Unclean, 1);
+        assert_eq!(ErrorCode::None.code(), code);
+        replayed(&image, offset).await;
+        let (leader_id, _, isr) = partition(1);
+        assert_eq!((1, vec![1]), (leader_id, isr.to_vec()));
+
+        // shutting down fences the broker
+        let shut_down = heartbeat(1, true);
+        assert!(shut_down.response.should_shut_down.is_true());
+        replayed(&image, shut_down.offset).await;
+        assert!(image.read().unwrap().is_fenced(1));
+        assert_eq!((-1, 4), leader());
+        let (code, _) = elect(ElectionType::Unclean, 0);
+        assert_eq!(ErrorCode::EligibleLeadersNotAvailable.code(), code);
+        loader.abort();
+    }
+}
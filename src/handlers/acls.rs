@@ -0,0 +1,336 @@
+use anyhow::{self, bail};
+use tracing::{debug, error, info};
+
+use super::{
+    controller::{alter_metadata, respond_written, write_timed_out},
+    lib::{HandlerResponse, authorize_cluster},
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    request::{AclCreation, AclFilter, KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            AclCreationResult, AclDescription, CreateAclsResponseBody, DeleteAclsFilterResult,
+            DeleteAclsMatchingAcl, DeleteAclsResponseBody, DescribeAclsResource,
+            DescribeAclsResponseBody, ResponseBody,
+        },
+    },
+    security::{
+        AccessControlEntry, AclBinding, AclBindingFilter, AclOperation, AclPermissionType,
+        PatternType, ResourcePattern, ResourceType,
+    },
+    types::{ApiKeys, ErrorCode},
+};
+
+const SECURITY_DISABLED: &str = "No authorizer is configured";
+
+/// Answers with the ACLs matching the request's filter, grouped by resource pattern.
+/// Needs `DESCRIBE` on the cluster.
+pub(super) fn handle_describe_acls(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeAcls,
+        "request did not specify the DescribeAcls apikey"
+    );
+    let RequestBody::DescribeAcls(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeAcls")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let body = match (&state.authorizer, binding_filter(&reqbody.filter)) {
+        (None, _) => {
+            DescribeAclsResponseBody::error(0, ErrorCode::SecurityDisabled, SECURITY_DISABLED)
+        }
+        _ if !authorize_cluster(state, conn, AclOperation::Describe) => {
+            DescribeAclsResponseBody::error(
+                0,
+                ErrorCode::ClusterAuthorizationFailed,
+                "Not authorized to describe ACLs",
+            )
+        }
+        (_, Err(message)) => DescribeAclsResponseBody::error(0, ErrorCode::InvalidRequest, message),
+        (Some(authorizer), Ok(filter)) => {
+            // the ACLs come sorted, those of a pattern follow each other
+            let mut resources: Vec<(ResourcePattern, CompactArray<AclDescription>)> = Vec::new();
+            for AclBinding { pattern, entry } in authorizer.acls(&filter) {
+                if resources.last().map_or(true, |(last, _)| *last != pattern) {
+                    resources.push((pattern, CompactArray::new()));
+                }
+                let (_, acls) = resources.last_mut().unwrap();
+                acls.push(AclDescription::new(
+                    entry.principal,
+                    entry.host,
+                    entry.operation.code(),
+                    entry.permission_type.code(),
+                ));
+            }
+            let mut described = CompactArray::with_capacity(resources.len());
+            for (pattern, acls) in resources {
+                described.push(DescribeAclsResource::new(
+                    pattern.resource_type.code(),
+                    pattern.name,
+                    pattern.pattern_type.code(),
+                    acls,
+                ));
+            }
+            DescribeAclsResponseBody::new(0, described)
+        }
+    };
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::DescribeAcls(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// Adds ACLs. Every creation is validated on its own and reports its own error, the
+/// valid ones are then added together. Nodes of a `KRaft` quorum add them through the
+/// active controller, answering once they were replayed. Needs `ALTER` on the cluster.
+pub(super) fn handle_create_acls(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::CreateAcls,
+        "request did not specify the CreateAcls apikey"
+    );
+    let RequestBody::CreateAcls(ref reqbody) = req.body else {
+        bail!("Invalid request body for CreateAcls")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let authorized = authorize_cluster(state, conn, AclOperation::Alter);
+    let mut results = Vec::with_capacity(reqbody.creations.len());
+    let mut bindings = Vec::new();
+    for creation in reqbody.creations.iter() {
+        results.push(match binding(creation) {
+            _ if state.authorizer.is_none() => {
+                Some((ErrorCode::SecurityDisabled, SECURITY_DISABLED.to_string()))
+            }
+            _ if !authorized => Some((
+                ErrorCode::ClusterAuthorizationFailed,
+                "Not authorized to create ACLs".to_string(),
+            )),
+            Ok(binding) => {
+                bindings.push(binding);
+                None
+            }
+            Err(message) => Some((ErrorCode::InvalidRequest, message)),
+        });
+    }
+
+    let mut written = None;
+    if let (Some(authorizer), false) = (&state.authorizer, bindings.is_empty()) {
+        let (result, write) = alter_metadata(
+            state,
+            |controller| controller.create_acls(&bindings),
+            || authorizer.create_acls(&bindings),
+        );
+        written = write;
+        match result {
+            Ok(()) => {
+                let created: Vec<_> = bindings.iter().map(ToString::to_string).collect();
+                info!("Created ACLs: {}", created.join(", "));
+            }
+            Err(error) => {
+                error!("Creating ACLs failed: {}", error.1);
+                for result in results.iter_mut().filter(|r| r.is_none()) {
+                    *result = Some(error.clone());
+                }
+            }
+        }
+    }
+
+    let body = |results: Vec<Option<(ErrorCode, String)>>| {
+        let mut created = CompactArray::with_capacity(results.len());
+        for result in results {
+            created.push(match result {
+                None => AclCreationResult::new(),
+                Some((code, message)) => AclCreationResult::error(code, message),
+            });
+        }
+        ResponseBody::CreateAcls(CreateAclsResponseBody::new(0, created))
+    };
+    let timed_out: Vec<_> = results
+        .iter()
+        .map(|r| r.clone().or_else(|| Some(write_timed_out())))
+        .collect();
+    Ok(respond_written(req, written, body(results), || {
+        body(timed_out)
+    }))
+}
+
+/// Removes the ACLs matching any of the request's filters, answering with the ACLs each
+/// filter matched. Invalid filters report their own error, the ACLs of the valid ones
+/// are removed together, through the active controller on nodes of a `KRaft` quorum.
+/// Needs `ALTER` on the cluster.
+pub(super) fn handle_delete_acls(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DeleteAcls,
+        "request did not specify the DeleteAcls apikey"
+    );
+    let RequestBody::DeleteAcls(ref reqbody) = req.body else {
+        bail!("Invalid request body for DeleteAcls")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let authorized = authorize_cluster(state, conn, AclOperation::Alter);
+    let mut results: Vec<Result<Vec<AclBinding>, (ErrorCode, String)>> =
+        Vec::with_capacity(reqbody.filters.len());
+    let mut filters = Vec::new();
+    for filter in reqbody.filters.iter() {
+        results.push(match binding_filter(filter) {
+            _ if state.authorizer.is_none() => {
+                Err((ErrorCode::SecurityDisabled, SECURITY_DISABLED.to_string()))
+            }
+            _ if !authorized => Err((
+                ErrorCode::ClusterAuthorizationFailed,
+                "Not authorized to delete ACLs".to_string(),
+            )),
+            Ok(filter) => {
+                filters.push(filter);
+                Ok(Vec::new())
+            }
+            Err(message) => Err((ErrorCode::InvalidRequest, message)),
+        });
+    }
+
+    let mut written = None;
+    if let (Some(authorizer), false) = (&state.authorizer, filters.is_empty()) {
+        let (result, write) = alter_metadata(
+            state,
+            |controller| controller.delete_acls(&filters),
+            || authorizer.delete_acls(&filters),
+        );
+        written = write;
+        match result {
+            Ok(deleted) => {
+                let mut deleted = deleted.into_iter();
+                for result in results.iter_mut().filter_map(|r| r.as_mut().ok()) {
+                    *result = deleted.next().unwrap_or_default();
+                }
+                let deleted: Vec<_> = results
+                    .iter()
+                    .filter_map(|r| r.as_ref().ok())
+                    .flatten()
+                    .map(ToString::to_string)
+                    .collect();
+                if !deleted.is_empty() {
+                    info!("Deleted ACLs: {}", deleted.join(", "));
+                }
+            }
+            Err(error) => {
+                error!("Deleting ACLs failed: {}", error.1);
+                for result in results.iter_mut().filter(|r| r.is_ok()) {
+                    *result = Err(error.clone());
+                }
+            }
+        }
+    }
+
+    let timed_out: Vec<_> = results
+        .iter()
+        .map(|r| r.as_ref().err().cloned().unwrap_or_else(write_timed_out))
+        .map(Err)
+        .collect();
+    Ok(respond_written(
+        req,
+        written,
+        delete_acls_body(results),
+        || delete_acls_body(timed_out),
+    ))
+}
+
+fn delete_acls_body(results: Vec<Result<Vec<AclBinding>, (ErrorCode, String)>>) -> ResponseBody {
+    let mut filter_results = CompactArray::with_capacity(results.len());
+    for result in results {
+        filter_results.push(match result {
+            Ok(deleted) => {
+                let mut matching = CompactArray::with_capacity(deleted.len());
+                for AclBinding { pattern, entry } in deleted {
+                    matching.push(DeleteAclsMatchingAcl::new(
+                        pattern.resource_type.code(),
+                        pattern.name,
+                        pattern.pattern_type.code(),
+                        entry.principal,
+                        entry.host,
+                        entry.operation.code(),
+                        entry.permission_type.code(),
+                    ));
+                }
+                DeleteAclsFilterResult::new(matching)
+            }
+            Err((code, message)) => DeleteAclsFilterResult::error(code, message),
+        });
+    }
+    ResponseBody::DeleteAcls(DeleteAclsResponseBody::new(0, filter_results))
+}
+
+fn binding(creation: &AclCreation) -> Result<AclBinding, String> {
+    let binding = AclBinding {
+        pattern: ResourcePattern {
+            resource_type: code(
+                creation.resource_type,
+                ResourceType::from_code,
+                "resource type",
+            )?,
+            name: creation.resource_name.0.clone(),
+            pattern_type: code(
+                creation.resource_pattern_type,
+                PatternType::from_code,
+                "pattern type",
+            )?,
+        },
+        entry: AccessControlEntry {
+            principal: creation.principal.0.clone(),
+            host: creation.host.0.clone(),
+            operation: code(creation.operation, AclOperation::from_code, "operation")?,
+            permission_type: code(
+                creation.permission_type,
+                AclPermissionType::from_code,
+                "permission type",
+            )?,
+        },
+    };
+    binding.validate()?;
+    Ok(binding)
+}
+
+fn binding_filter(filter: &AclFilter) -> Result<AclBindingFilter, String> {
+    Ok(AclBindingFilter {
+        resource_type: code(
+            filter.resource_type_filter,
+            ResourceType::from_code,
+            "resource type",
+        )?,
+        name: filter.resource_name_filter.0.clone(),
+        pattern_type: code(
+            filter.pattern_type_filter,
+            PatternType::from_code,
+            "pattern type",
+        )?,
+        principal: filter.principal_filter.0.clone(),
+        host: filter.host_filter.0.clone(),
+        operation: code(filter.operation, AclOperation::from_code, "operation")?,
+        permission_type: code(
+            filter.permission_type,
+            AclPermissionType::from_code,
+            "permission type",
+        )?,
+    })
+}
+
+fn code<T>(code: i8, from_code: fn(i8) -> Option<T>, what: &str) -> Result<T, String> {
+    from_code(code).ok_or_else(|| format!("Unknown {what} {code}"))
+}
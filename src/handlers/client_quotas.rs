@@ -0,0 +1,197 @@
+use anyhow::{self, bail};
+use tracing::{debug, error, info};
+
+use super::{
+    controller::{alter_metadata, respond_written, write_timed_out},
+    lib::{HandlerResponse, authorize_cluster},
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    quota::{ComponentMatch, QuotaEntity, QuotaManager, QuotaOp},
+    request::{DescribeClientQuotasComponent, KafkaRequest, RequestBody},
+    security::AclOperation,
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            AlterClientQuotasEntryResult, AlterClientQuotasResponseBody, ClientQuotaValue,
+            DescribeClientQuotasEntry, DescribeClientQuotasResponseBody, ResponseBody,
+        },
+    },
+    types::{ApiKeys, ClientQuotaEntityData, ErrorCode},
+};
+
+/// Answers with the quotas of every entity matching the request's filter. A filter
+/// that cannot be understood fails the whole request with `INVALID_REQUEST`. Needs
+/// `DESCRIBE_CONFIGS` on the cluster.
+pub(super) fn handle_describe_client_quotas(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeClientQuotas,
+        "request did not specify the DescribeClientQuotas apikey"
+    );
+    let RequestBody::DescribeClientQuotas(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeClientQuotas")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let described = reqbody
+        .components
+        .iter()
+        .map(|c| Ok((c.entity_type.0.clone(), component_match(c)?)))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .and_then(|components| state.quotas.describe(&components, reqbody.strict.is_true()));
+    let body = match described {
+        _ if !authorize_cluster(state, conn, AclOperation::DescribeConfigs) => {
+            DescribeClientQuotasResponseBody::error(
+                0,
+                ErrorCode::ClusterAuthorizationFailed,
+                "Not authorized to describe client quotas",
+            )
+        }
+        Ok(described) => {
+            let mut entries = CompactArray::with_capacity(described.len());
+            for (entity, values) in described {
+                let mut quotas = CompactArray::with_capacity(values.len());
+                for (key, value) in values {
+                    quotas.push(ClientQuotaValue::new(key, value));
+                }
+                entries.push(DescribeClientQuotasEntry::new(entity_data(&entity), quotas));
+            }
+            DescribeClientQuotasResponseBody::new(0, entries)
+        }
+        Err(e) => {
+            DescribeClientQuotasResponseBody::error(0, ErrorCode::InvalidRequest, e.to_string())
+        }
+    };
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::DescribeClientQuotas(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+fn component_match(component: &DescribeClientQuotasComponent) -> anyhow::Result<ComponentMatch> {
+    Ok(match component.match_type {
+        DescribeClientQuotasComponent::MATCH_EXACT => match component.match_name.as_deref() {
+            Some(name) => ComponentMatch::Exact(name.to_string()),
+            None => ComponentMatch::Default,
+        },
+        DescribeClientQuotasComponent::MATCH_DEFAULT => ComponentMatch::Default,
+        DescribeClientQuotasComponent::MATCH_SPECIFIED => ComponentMatch::Specified,
+        other => bail!("Unknown match type {other}"),
+    })
+}
+
+/// Sets or removes quotas. Every entry is validated on its own and reports its own
+/// error, the valid ones are then applied together unless `validate_only` is set.
+/// Nodes of a `KRaft` quorum apply them through the active controller, answering once
+/// they were replayed. Needs `ALTER_CONFIGS` on the cluster, every entry fails without
+/// it.
+pub(super) fn handle_alter_client_quotas(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::AlterClientQuotas,
+        "request did not specify the AlterClientQuotas apikey"
+    );
+    let RequestBody::AlterClientQuotas(ref reqbody) = req.body else {
+        bail!("Invalid request body for AlterClientQuotas")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let mut results = Vec::with_capacity(reqbody.entries.len());
+    let mut changes = Vec::new();
+    let authorized = authorize_cluster(state, conn, AclOperation::AlterConfigs);
+    for entry in reqbody.entries.iter() {
+        if !authorized {
+            let message = "Not authorized to alter client quotas".to_string();
+            results.push(Some((ErrorCode::ClusterAuthorizationFailed, message)));
+            continue;
+        }
+        let entity = QuotaEntity::from_components(
+            entry
+                .entity
+                .iter()
+                .map(|e| (e.entity_type.0.as_str(), e.entity_name.as_deref())),
+        );
+        let ops: Vec<_> = entry
+            .ops
+            .iter()
+            .map(|op| QuotaOp {
+                key: op.key.0.clone(),
+                value: op.remove.is_false().then_some(op.value),
+            })
+            .collect();
+        let validated =
+            entity.and_then(|entity| QuotaManager::validate(&entity, &ops).map(|()| entity));
+        match validated {
+            Ok(entity) => {
+                results.push(None);
+                changes.push((entity, ops));
+            }
+            Err(e) => results.push(Some((ErrorCode::InvalidRequest, e.to_string()))),
+        }
+    }
+
+    let mut written = None;
+    if !changes.is_empty() && reqbody.validate_only.is_false() {
+        let altered: Vec<_> = changes.iter().map(|(e, _)| e.to_string()).collect();
+        let (result, write) = alter_metadata(
+            state,
+            |controller| controller.alter_client_quotas(&changes),
+            || state.quotas.alter(changes.clone()),
+        );
+        written = write;
+        match result {
+            Ok(()) => info!("Altered quotas of {}", altered.join(", ")),
+            Err(error) => {
+                error!("Altering quotas failed: {}", error.1);
+                for result in results.iter_mut().filter(|r| r.is_none()) {
+                    *result = Some(error.clone());
+                }
+            }
+        }
+    }
+
+    // the entities are echoed back as they were sent
+    let body = |results: Vec<Option<(ErrorCode, String)>>| {
+        let mut entries = CompactArray::with_capacity(results.len());
+        for (entry, result) in reqbody.entries.iter().zip(results) {
+            let mut entity = CompactArray::with_capacity(entry.entity.len());
+            for e in entry.entity.iter() {
+                entity.push(ClientQuotaEntityData::new(
+                    e.entity_type.0.clone(),
+                    e.entity_name.0.clone(),
+                ));
+            }
+            entries.push(match result {
+                None => AlterClientQuotasEntryResult::new(entity),
+                Some((code, message)) => AlterClientQuotasEntryResult::error(entity, code, message),
+            });
+        }
+        ResponseBody::AlterClientQuotas(AlterClientQuotasResponseBody::new(0, entries))
+    };
+    let timed_out: Vec<_> = results
+        .iter()
+        .map(|r| r.clone().or_else(|| Some(write_timed_out())))
+        .collect();
+    Ok(respond_written(req, written, body(results), || {
+        body(timed_out)
+    }))
+}
+
+fn entity_data(entity: &QuotaEntity) -> CompactArray<ClientQuotaEntityData> {
+    let components = entity.components();
+    let mut data = CompactArray::with_capacity(components.len());
+    for (entity_type, name) in components {
+        data.push(ClientQuotaEntityData::new(entity_type, name));
+    }
+    data
+}
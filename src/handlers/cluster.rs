@@ -0,0 +1,155 @@
+use anyhow::{self, bail};
+use tracing::debug;
+
+use super::lib::authorized_operations;
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    request::{DescribeClusterRequestBody, KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{DescribeClusterBroker, DescribeClusterResponseBody, ResponseBody},
+    },
+    security::{CLUSTER_NAME, ResourceType},
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers with the cluster id, the controller and the endpoints of the brokers, or of
+/// the controllers when asked on a controller listener. Nodes of a KRaft quorum list the
+/// unfenced brokers of the metadata image with their address on the listener the request
+/// arrived on, or the voters of the quorum, and report the leader of the quorum as the
+/// controller, -1 while there is none. Otherwise this broker is the only node of its
+/// cluster, so it reports itself as both.
+pub(super) fn handle_describe_cluster(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeCluster,
+        "request did not specify the DescribeCluster apikey"
+    );
+    let RequestBody::DescribeCluster(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeCluster")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let version = req.header.request_api_version;
+    let endpoint_type = reqbody.endpoint_type;
+    let config = &state.config;
+    let on_controller_listener = config
+        .controller_listener_names
+        .contains(&conn.listener_name);
+    let nodes = match endpoint_type {
+        DescribeClusterRequestBody::BROKERS if !on_controller_listener => {
+            Ok(listener_brokers(state, &conn.listener_name))
+        }
+        // controllers are reached at the addresses of the voters of the quorum
+        DescribeClusterRequestBody::CONTROLLERS if on_controller_listener => Ok(config
+            .controller_quorum_voters
+            .iter()
+            .map(|voter| ListenerBroker {
+                id: voter.id,
+                host: voter.host.clone(),
+                port: voter.port,
+                // only the rack of this node is known
+                rack: config
+                    .broker_rack
+                    .clone()
+                    .filter(|_| voter.id == config.node_id),
+            })
+            .collect()),
+        DescribeClusterRequestBody::BROKERS | DescribeClusterRequestBody::CONTROLLERS => Err((
+            ErrorCode::MismatchedEndpointType,
+            format!(
+                "Listener {} does not serve endpoint type {endpoint_type}",
+                conn.listener_name
+            ),
+        )),
+        _ => Err((
+            ErrorCode::UnsupportedEndpointType,
+            format!("Unknown endpoint type {endpoint_type}"),
+        )),
+    };
+
+    let body = match nodes {
+        Ok(nodes) => {
+            let brokers = nodes
+                .into_iter()
+                .map(|n| DescribeClusterBroker::new(n.id, n.host, i32::from(n.port), n.rack))
+                .collect::<Vec<_>>();
+            let operations = if reqbody.include_cluster_authorized_operations.is_true() {
+                authorized_operations(state, conn, ResourceType::Cluster, CLUSTER_NAME)
+            } else {
+                i32::MIN
+            };
+            DescribeClusterResponseBody::new(
+                version,
+                0,
+                endpoint_type,
+                state.cluster_id.clone(),
+                controller_id(state),
+                brokers.into(),
+                operations,
+            )
+        }
+        Err((code, message)) => {
+            DescribeClusterResponseBody::error(version, 0, endpoint_type, code, message)
+        }
+    };
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::DescribeCluster(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// A broker as clients of a listener reach it
+pub(super) struct ListenerBroker {
+    pub id: i32,
+    pub host: String,
+    pub port: u16,
+    pub rack: Option<String>,
+}
+
+/// The brokers clients of the listener called `listener_name` can reach, with their
+/// address on that listener. Nodes of a KRaft quorum know every registered broker that is
+/// not fenced, brokers without that listener are left out. Otherwise this broker is alone.
+pub(super) fn listener_brokers(state: &BrokerState, listener_name: &str) -> Vec<ListenerBroker> {
+    if state.raft.is_none() {
+        let config = &state.config;
+        return config
+            .advertised_listener(listener_name)
+            .map(|listener| ListenerBroker {
+                id: config.node_id,
+                host: listener.host.clone(),
+                port: listener.port,
+                rack: config.broker_rack.clone(),
+            })
+            .into_iter()
+            .collect();
+    }
+    let image = state.metadata_image.read().unwrap();
+    image
+        .brokers()
+        .filter(|broker| !broker.fenced)
+        .filter_map(|broker| {
+            let endpoint = broker.endpoints.iter().find(|e| e.name == listener_name)?;
+            Some(ListenerBroker {
+                id: broker.id,
+                host: endpoint.host.clone(),
+                port: endpoint.port,
+                rack: broker.rack.clone(),
+            })
+        })
+        .collect()
+}
+
+/// The node reported as the controller: the leader of the KRaft quorum, -1 while there
+/// is none, or else this broker, the only node of its cluster
+pub(super) fn controller_id(state: &BrokerState) -> i32 {
+    match &state.raft {
+        Some(raft) => raft.leader().leader_id,
+        None => state.config.node_id,
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{self, bail};
+use tracing::{debug, error, info};
+
+use super::{
+    controller::{alter_metadata, respond_written, write_timed_out},
+    lib::{HandlerResponse, authorize, authorize_cluster},
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    config::{ConfigEntity, ConfigManager, ConfigSource, ConfigType},
+    primitives::CompactArray,
+    request::{DescribeConfigsResource, IncrementalAlterableConfig, KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            AlterConfigsResourceResponse, AlterConfigsResponseBody, DescribeConfigsResourceResult,
+            DescribeConfigsResponseBody, DescribeConfigsResult, DescribeConfigsSynonym,
+            IncrementalAlterConfigsResponseBody, ResponseBody,
+        },
+    },
+    security::{AclOperation, ResourceType},
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers with the configs of every resource, or only the requested keys of it. Needs
+/// `DESCRIBE_CONFIGS` on each topic, or on the cluster for brokers.
+pub(super) fn handle_describe_configs(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeConfigs,
+        "request did not specify the DescribeConfigs apikey"
+    );
+    let RequestBody::DescribeConfigs(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeConfigs")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let mut results = CompactArray::with_capacity(reqbody.resources.len());
+    for resource in reqbody.resources.iter() {
+        let (resource_type, name) = (resource.resource_type, &resource.resource_name.0);
+        let entity = match entity(
+            state,
+            conn,
+            resource_type,
+            name,
+            AclOperation::DescribeConfigs,
+        ) {
+            Ok(entity) => entity,
+            Err((code, message)) => {
+                results.push(DescribeConfigsResult::error(
+                    resource_type,
+                    name,
+                    code,
+                    message,
+                ));
+                continue;
+            }
+        };
+        let keys: Vec<_> = resource
+            .configuration_keys
+            .iter()
+            .map(|k| k.0.clone())
+            .collect();
+        let described = state
+            .configs
+            .describe(&entity, (!keys.is_empty()).then_some(keys.as_slice()));
+
+        let mut configs = CompactArray::with_capacity(described.len());
+        for config in described {
+            let mut synonyms = CompactArray::new();
+            if reqbody.include_synonyms.is_true() {
+                for synonym in config.synonyms {
+                    synonyms.push(DescribeConfigsSynonym::new(
+                        synonym.name,
+                        synonym.value,
+                        synonym.source.code(),
+                    ));
+                }
+            }
+            configs.push(DescribeConfigsResourceResult::new(
+                config.name,
+                config.value,
+                config.source.code(),
+                synonyms,
+                config.config_type.code(),
+                reqbody
+                    .include_documentation
+                    .is_true()
+                    .then(|| config.documentation.to_string()),
+            ));
+        }
+        results.push(DescribeConfigsResult::new(
+            resource_type,
+            name.clone(),
+            configs,
+        ));
+    }
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::DescribeConfigs(DescribeConfigsResponseBody::new(0, results));
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// Replaces every config of the resources with the ones in the request, configs left
+/// out revert to their defaults. Every resource is validated on its own and reports
+/// its own error, the valid ones are then altered together unless `validate_only` is
+/// set. Nodes of a `KRaft` quorum alter them through the active controller, answering
+/// once the change was replayed. Needs `ALTER_CONFIGS` on each topic, or on the cluster
+/// for brokers.
+pub(super) fn handle_alter_configs(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::AlterConfigs,
+        "request did not specify the AlterConfigs apikey"
+    );
+    let RequestBody::AlterConfigs(ref reqbody) = req.body else {
+        bail!("Invalid request body for AlterConfigs")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let resources: Vec<_> = reqbody
+        .resources
+        .iter()
+        .map(|resource| {
+            let values = resource
+                .configs
+                .iter()
+                .map(|config| match config.value.as_deref() {
+                    Some(value) => Ok((config.name.0.clone(), value.to_string())),
+                    None => Err(format!("No value for config {}", config.name.0)),
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()
+                .map_err(|message| (ErrorCode::InvalidRequest, message));
+            (
+                resource.resource_type,
+                resource.resource_name.0.clone(),
+                values,
+            )
+        })
+        .collect();
+    Ok(alter(
+        req,
+        state,
+        conn,
+        resources,
+        reqbody.validate_only.is_true(),
+        |responses| ResponseBody::AlterConfigs(AlterConfigsResponseBody::new(0, responses)),
+    ))
+}
+
+/// Sets, removes, appends to or subtracts from single configs of the resources, leaving
+/// the others as they are. Validated and applied like AlterConfigs, see
+/// [`handle_alter_configs`].
+pub(super) fn handle_incremental_alter_configs(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::IncrementalAlterConfigs,
+        "request did not specify the IncrementalAlterConfigs apikey"
+    );
+    let RequestBody::IncrementalAlterConfigs(ref reqbody) = req.body else {
+        bail!("Invalid request body for IncrementalAlterConfigs")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let resources: Vec<_> = reqbody
+        .resources
+        .iter()
+        .map(|resource| {
+            let (resource_type, name) = (resource.resource_type, &resource.resource_name.0);
+            // the entity is checked again when altering, errors here only matter for
+            // resources that can be altered
+            let values = parse_entity(state, resource_type, name)
+                .map_err(|message| (ErrorCode::InvalidRequest, message))
+                .and_then(|entity| {
+                    incremental_values(&state.configs, &entity, resource.configs.iter())
+                });
+            (resource_type, name.clone(), values)
+        })
+        .collect();
+    Ok(alter(
+        req,
+        state,
+        conn,
+        resources,
+        reqbody.validate_only.is_true(),
+        |responses| {
+            ResponseBody::IncrementalAlterConfigs(IncrementalAlterConfigsResponseBody::new(
+                0, responses,
+            ))
+        },
+    ))
+}
+
+/// The values a resource's configs are replaced with, or why they cannot be
+type Values = Result<BTreeMap<String, String>, (ErrorCode, String)>;
+
+/// Validates the new configs of every resource and replaces the current ones with the
+/// valid ones unless `validate_only` is set, answering with the `body` of a response per
+/// resource
+fn alter(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+    resources: Vec<(i8, String, Values)>,
+    validate_only: bool,
+    body: impl Fn(CompactArray<AlterConfigsResourceResponse>) -> ResponseBody,
+) -> HandlerResponse {
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(resources.len());
+    let mut changes = Vec::new();
+    for (resource_type, name, values) in &resources {
+        let validated = entity(
+            state,
+            conn,
+            *resource_type,
+            name,
+            AclOperation::AlterConfigs,
+        )
+        .and_then(|entity| {
+            if !seen.insert(entity.clone()) {
+                let message = format!("Resource {entity} appears more than once");
+                return Err((ErrorCode::InvalidRequest, message));
+            }
+            let values = values.clone()?;
+            ConfigManager::validate(&entity, &values)
+                .map_err(|e| (ErrorCode::InvalidConfig, format!("{e:#}")))?;
+            Ok((entity, values))
+        });
+        results.push(match validated {
+            Ok(change) => {
+                changes.push(change);
+                None
+            }
+            Err(error) => Some(error),
+        });
+    }
+
+    let mut written = None;
+    if !changes.is_empty() && !validate_only {
+        let altered: Vec<_> = changes.iter().map(|(e, _)| e.to_string()).collect();
+        let (result, write) = alter_metadata(
+            state,
+            |controller| controller.alter_configs(&changes),
+            || state.configs.alter(changes.clone()),
+        );
+        written = write;
+        match result {
+            Ok(()) => info!("Altered configs of {}", altered.join(", ")),
+            Err(error) => {
+                error!("Altering configs failed: {}", error.1);
+                for result in results.iter_mut().filter(|r| r.is_none()) {
+                    *result = Some(error.clone());
+                }
+            }
+        }
+    }
+
+    let responses = |results: Vec<Option<(ErrorCode, String)>>| {
+        let mut responses = CompactArray::with_capacity(results.len());
+        for ((resource_type, name, _), result) in resources.iter().zip(results) {
+            responses.push(match result {
+                None => AlterConfigsResourceResponse::new(*resource_type, name.clone()),
+                Some((code, message)) => {
+                    AlterConfigsResourceResponse::error(*resource_type, name.clone(), code, message)
+                }
+            });
+        }
+        body(responses)
+    };
+    let timed_out: Vec<_> = results
+        .iter()
+        .map(|r| r.clone().or_else(|| Some(write_timed_out())))
+        .collect();
+    respond_written(req, written, responses(results), || responses(timed_out))
+}
+
+/// The configs of `entity` once the incremental `changes` are applied
+fn incremental_values<'a>(
+    configs: &ConfigManager,
+    entity: &ConfigEntity,
+    changes: impl Iterator<Item = &'a IncrementalAlterableConfig>,
+) -> Values {
+    let invalid = |message: String| (ErrorCode::InvalidRequest, message);
+    let mut values = configs.configs(entity);
+    let mut seen = HashSet::new();
+    for change in changes {
+        let key = change.name.0.clone();
+        if !seen.insert(key.clone()) {
+            return Err(invalid(format!("Config {key} appears more than once")));
+        }
+        match (change.config_operation, change.value.as_deref()) {
+            (IncrementalAlterableConfig::DELETE, _) => {
+                values.remove(&key);
+            }
+            (IncrementalAlterableConfig::SET, Some(value)) => {
+                values.insert(key, value.to_string());
+            }
+            (
+                op @ (IncrementalAlterableConfig::APPEND | IncrementalAlterableConfig::SUBTRACT),
+                Some(value),
+            ) => {
+                let current = list_value(configs, entity, &key, &values).map_err(invalid)?;
+                let mut items: Vec<_> = current.split(',').filter(|i| !i.is_empty()).collect();
+                for item in value.split(',').filter(|i| !i.is_empty()) {
+                    if op == IncrementalAlterableConfig::SUBTRACT {
+                        items.retain(|&i| i != item);
+                    } else if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+                values.insert(key, items.join(","));
+            }
+            (
+                IncrementalAlterableConfig::SET
+                | IncrementalAlterableConfig::APPEND
+                | IncrementalAlterableConfig::SUBTRACT,
+                None,
+            ) => return Err(invalid(format!("No value for config {key}"))),
+            (op, _) => return Err(invalid(format!("Unknown config operation {op}"))),
+        }
+    }
+    Ok(values)
+}
+
+/// The value in effect of the list config `key`, which appending to and subtracting from
+/// starts with. The cluster wide defaults start with the default when they are not set.
+fn list_value(
+    configs: &ConfigManager,
+    entity: &ConfigEntity,
+    key: &str,
+    values: &BTreeMap<String, String>,
+) -> Result<String, String> {
+    let described = match entity {
+        // only reports the configs that are set, the broker's own include the default
+        ConfigEntity::DefaultBroker => ConfigEntity::Broker(configs.node_id()),
+        _ => entity.clone(),
+    };
+    let Some(config) = configs.describe(&described, Some(&[key.to_string()])).pop() else {
+        return Err(format!("Unknown config {key}"));
+    };
+    if config.config_type != ConfigType::List {
+        return Err(format!("Config {key} is not a list"));
+    }
+    Ok(match (values.get(key), entity) {
+        (Some(value), _) => value.clone(),
+        (None, ConfigEntity::DefaultBroker) => config
+            .synonyms
+            .into_iter()
+            .find(|s| s.source == ConfigSource::Default)
+            .map(|s| s.value)
+            .unwrap_or_default(),
+        (None, _) => config.value,
+    })
+}
+
+/// The entity of a resource, if the client may perform `operation` on it
+fn entity(
+    state: &BrokerState,
+    conn: &ConnectionContext,
+    resource_type: i8,
+    name: &str,
+    operation: AclOperation,
+) -> Result<ConfigEntity, (ErrorCode, String)> {
+    let entity =
+        parse_entity(state, resource_type, name).map_err(|m| (ErrorCode::InvalidRequest, m))?;
+    match entity {
+        ConfigEntity::Topic(_) => {
+            if !authorize(state, conn, operation, ResourceType::Topic, name) {
+                let message = format!("Not authorized to access the configs of topic {name}");
+                return Err((ErrorCode::TopicAuthorizationFailed, message));
+            }
+            let exists = if state.raft.is_some() {
+                let image = state.metadata_image.read().unwrap();
+                image.topic_by_name(name).is_some()
+            } else {
+                state.log_manager.has_topic(name)
+            };
+            if !exists {
+                let message = format!("Topic {name} does not exist");
+                return Err((ErrorCode::UnknownTopicOrPartition, message));
+            }
+        }
+        ConfigEntity::Broker(_) | ConfigEntity::DefaultBroker => {
+            if !authorize_cluster(state, conn, operation) {
+                let message = "Not authorized to access broker configs".to_string();
+                return Err((ErrorCode::ClusterAuthorizationFailed, message));
+            }
+        }
+    }
+    Ok(entity)
+}
+
+/// The entity of a resource, a broker can only be this one or the cluster wide default
+fn parse_entity(
+    state: &BrokerState,
+    resource_type: i8,
+    name: &str,
+) -> Result<ConfigEntity, String> {
+    match resource_type {
+        DescribeConfigsResource::TOPIC if !name.is_empty() => {
+            Ok(ConfigEntity::Topic(name.to_string()))
+        }
+        DescribeConfigsResource::BROKER if name.is_empty() => Ok(ConfigEntity::DefaultBroker),
+        DescribeConfigsResource::BROKER => match name.parse() {
+            Ok(id) if id == state.configs.node_id() => Ok(ConfigEntity::Broker(id)),
+            _ => Err(format!(
+                "Unexpected broker id {name}, expected {}",
+                state.configs.node_id()
+            )),
+        },
+        DescribeConfigsResource::TOPIC => Err("Empty topic name".to_string()),
+        other => Err(format!("Unsupported resource type {other}")),
+    }
+}
@@ -0,0 +1,316 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{self, bail};
+use tracing::{debug, error};
+
+use super::lib::{HandlerResponse, authorize, authorize_cluster};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    controller::{ControllerResult, QuorumController},
+    metadata::MetadataImage,
+    purgatory::DelayedOperation,
+    raft::RaftClient,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            BrokerHeartbeatResponseBody, BrokerRegistrationResponseBody, CreateTopicsResponseBody,
+            ElectLeadersResponseBody, ResponseBody,
+        },
+    },
+    security::{AclOperation, ResourceType},
+    types::{ApiKeys, ErrorCode},
+};
+
+fn respond(req: &KafkaRequest, body: ResponseBody) -> KafkaResponse {
+    let header = ResponseHeaderV1::respond(req);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    KafkaResponse::new(message_size, header, body)
+}
+
+/// The controller of the node if it may answer `req`: requests sent to nodes that are
+/// not controllers fail with `NOT_CONTROLLER`, the ones of clients without `operation`
+/// on the cluster with `CLUSTER_AUTHORIZATION_FAILED`
+fn controller<'a>(
+    state: &'a BrokerState,
+    conn: &ConnectionContext,
+    operation: AclOperation,
+) -> Result<&'a Arc<QuorumController>, ErrorCode> {
+    match &state.controller {
+        None => Err(ErrorCode::NotController),
+        Some(_) if !authorize_cluster(state, conn, operation) => {
+            Err(ErrorCode::ClusterAuthorizationFailed)
+        }
+        Some(controller) => Ok(controller),
+    }
+}
+
+/// Answers with `body` once the metadata image replayed the records up to `offset`,
+/// with `timed_out` if it does not within the write timeout of the controller
+fn respond_once_written(
+    req: &KafkaRequest,
+    controller: &QuorumController,
+    offset: Option<i64>,
+    body: ResponseBody,
+    timed_out: ResponseBody,
+) -> HandlerResponse {
+    let Some(offset) = offset else {
+        return HandlerResponse::Ready(respond(req, body));
+    };
+    let operation = DelayedControllerWrite {
+        image: Arc::clone(controller.image()),
+        offset,
+        response: Some(respond(req, body)),
+        timed_out: respond(req, timed_out),
+    };
+    let completion = controller.write_purgatory().try_complete_else_watch(
+        operation,
+        vec![RaftClient::topic_partition()],
+        controller.write_timeout(),
+    );
+    HandlerResponse::Delayed(completion)
+}
+
+/// Where to wait for the metadata image to replay an admin change written through the
+/// controller, `None` if the change was applied right away
+pub(super) type Written<'a> = Option<(&'a QuorumController, i64)>;
+
+/// Applies an admin change. Nodes of a `KRaft` quorum write it through their controller
+/// for the publishers of every broker to apply, the others apply it with `local`. `Err`
+/// fails every item of the request the change was made of.
+pub(super) fn alter_metadata<'a, T>(
+    state: &'a BrokerState,
+    through_controller: impl FnOnce(
+        &QuorumController,
+    ) -> anyhow::Result<ControllerResult<Result<T, ErrorCode>>>,
+    local: impl FnOnce() -> anyhow::Result<T>,
+) -> (Result<T, (ErrorCode, String)>, Written<'a>) {
+    if state.raft.is_none() {
+        let result = local().map_err(|e| (ErrorCode::UnknownServerError, format!("{e:#}")));
+        return (result, None);
+    }
+    let Some(controller) = &state.controller else {
+        let message = "This node is not a controller".to_string();
+        return (Err((ErrorCode::NotController, message)), None);
+    };
+    match through_controller(controller) {
+        Ok(ControllerResult {
+            response: Ok(response),
+            offset,
+        }) => (Ok(response), offset.map(|offset| (&**controller, offset))),
+        Ok(ControllerResult {
+            response: Err(code),
+            ..
+        }) => {
+            let message = match code {
+                ErrorCode::NotController => "This node is not the active controller",
+                _ => "The controller did not replay its latest writes yet",
+            };
+            (Err((code, message.to_string())), None)
+        }
+        Err(e) => {
+            error!("Writing to the metadata log failed: {e:#}");
+            (Err((ErrorCode::UnknownServerError, format!("{e:#}"))), None)
+        }
+    }
+}
+
+/// Answers with `body` once the change `written` was replayed, with `timed_out` if it
+/// is not within the write timeout of the controller
+pub(super) fn respond_written(
+    req: &KafkaRequest,
+    written: Written<'_>,
+    body: ResponseBody,
+    timed_out: impl FnOnce() -> ResponseBody,
+) -> HandlerResponse {
+    match written {
+        None => HandlerResponse::Ready(respond(req, body)),
+        Some((controller, offset)) => {
+            respond_once_written(req, controller, Some(offset), body, timed_out())
+        }
+    }
+}
+
+/// What the items of an admin request fail with when its change was not replayed in time
+pub(super) fn write_timed_out() -> (ErrorCode, String) {
+    let message = "The change was not replayed within the write timeout".to_string();
+    (ErrorCode::RequestTimedOut, message)
+}
+
+/// Registers a broker with the active controller, answered once the registration was
+/// replayed so the broker's heartbeats find it. Needs `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_broker_registration(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::BrokerRegistration,
+        "request did not specify the BrokerRegistration apikey"
+    );
+    let RequestBody::BrokerRegistration(ref reqbody) = req.body else {
+        bail!("Invalid request body for BrokerRegistration")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let controller = match controller(state, conn, AclOperation::ClusterAction) {
+        Ok(controller) => controller,
+        Err(code) => {
+            let body = BrokerRegistrationResponseBody::error(code);
+            return Ok(HandlerResponse::Ready(respond(
+                req,
+                ResponseBody::BrokerRegistration(body),
+            )));
+        }
+    };
+    let result = controller.register_broker(reqbody)?;
+    let timed_out = BrokerRegistrationResponseBody::error(ErrorCode::RequestTimedOut);
+    Ok(respond_once_written(
+        req,
+        controller,
+        result.offset,
+        ResponseBody::BrokerRegistration(result.response),
+        ResponseBody::BrokerRegistration(timed_out),
+    ))
+}
+
+/// Keeps the session of a registered broker alive with the active controller, which
+/// fences or unfences it. Needs `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_broker_heartbeat(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::BrokerHeartbeat,
+        "request did not specify the BrokerHeartbeat apikey"
+    );
+    let RequestBody::BrokerHeartbeat(ref reqbody) = req.body else {
+        bail!("Invalid request body for BrokerHeartbeat")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let controller = match controller(state, conn, AclOperation::ClusterAction) {
+        Ok(controller) => controller,
+        Err(code) => {
+            let body = BrokerHeartbeatResponseBody::error(code);
+            return Ok(HandlerResponse::Ready(respond(
+                req,
+                ResponseBody::BrokerHeartbeat(body),
+            )));
+        }
+    };
+    let result = controller.heartbeat(reqbody)?;
+    let timed_out = BrokerHeartbeatResponseBody::error(ErrorCode::RequestTimedOut);
+    Ok(respond_once_written(
+        req,
+        controller,
+        result.offset,
+        ResponseBody::BrokerHeartbeat(result.response),
+        ResponseBody::BrokerHeartbeat(timed_out),
+    ))
+}
+
+/// Elects new leaders for the partitions of the request, answered once the elections
+/// were replayed. Needs `ALTER` on the cluster, only the flexible version 2 is supported.
+/// Admin clients send it to the active controller, found with `DescribeCluster`.
+pub(super) fn handle_elect_leaders(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::ElectLeaders,
+        "request did not specify the ElectLeaders apikey"
+    );
+    let RequestBody::ElectLeaders(ref reqbody) = req.body else {
+        bail!("Invalid request body for ElectLeaders")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let controller = match controller(state, conn, AclOperation::Alter) {
+        Ok(controller) => controller,
+        Err(code) => {
+            let body = ElectLeadersResponseBody::error(code);
+            return Ok(HandlerResponse::Ready(respond(
+                req,
+                ResponseBody::ElectLeaders(body),
+            )));
+        }
+    };
+    let result = controller.elect_leaders(reqbody)?;
+    let timed_out = ElectLeadersResponseBody::error(ErrorCode::RequestTimedOut);
+    Ok(respond_once_written(
+        req,
+        controller,
+        result.offset,
+        ResponseBody::ElectLeaders(result.response),
+        ResponseBody::ElectLeaders(timed_out),
+    ))
+}
+
+/// Creates the topics of the request, answered once they were replayed. Needs `CREATE`
+/// on the cluster or on each topic, only the flexible version 7 is supported. Admin
+/// clients send it to the active controller, found with `DescribeCluster`.
+pub(super) fn handle_create_topics(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::CreateTopics,
+        "request did not specify the CreateTopics apikey"
+    );
+    let RequestBody::CreateTopics(ref reqbody) = req.body else {
+        bail!("Invalid request body for CreateTopics")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let every_topic = |code| {
+        let names = reqbody.topics.iter().map(|t| t.name.0.as_str());
+        ResponseBody::CreateTopics(CreateTopicsResponseBody::error(names, code))
+    };
+    let Some(controller) = &state.controller else {
+        let body = every_topic(ErrorCode::NotController);
+        return Ok(HandlerResponse::Ready(respond(req, body)));
+    };
+    let create_cluster = authorize_cluster(state, conn, AclOperation::Create);
+    let result = controller.create_topics(reqbody, |name| {
+        create_cluster || authorize(state, conn, AclOperation::Create, ResourceType::Topic, name)
+    })?;
+    Ok(respond_once_written(
+        req,
+        controller,
+        result.offset,
+        ResponseBody::CreateTopics(result.response),
+        every_topic(ErrorCode::RequestTimedOut),
+    ))
+}
+
+/// A request to the controller waiting for the metadata image to replay the records
+/// written for it
+#[derive(Debug)]
+pub struct DelayedControllerWrite {
+    image: Arc<RwLock<MetadataImage>>,
+    /// Offset of the last record written for the request
+    offset: i64,
+    /// Taken once the operation completes
+    response: Option<KafkaResponse>,
+    timed_out: KafkaResponse,
+}
+
+impl DelayedOperation for DelayedControllerWrite {
+    type Output = KafkaResponse;
+
+    fn try_complete(&mut self) -> Option<KafkaResponse> {
+        if self.image.read().unwrap().offset < self.offset {
+            return None;
+        }
+        self.response.take()
+    }
+
+    fn on_expiration(self) -> KafkaResponse {
+        self.timed_out
+    }
+}
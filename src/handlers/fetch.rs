@@ -0,0 +1,286 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{self, bail};
+use tracing::{debug, error};
+
+use super::{
+    lib::{HandlerResponse, QuotaClient, authorize, authorize_cluster},
+    raft::handle_metadata_fetch,
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    metrics::BrokerMetrics,
+    primitives::{CompactArray, CompactString},
+    purgatory::DelayedOperation,
+    quota::QuotaType,
+    raft::METADATA_TOPIC,
+    replica::{ReplicaError, ReplicaManager},
+    request::{FetchPartition, KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{FetchPartitionResponse, FetchResponseBody, FetchTopicResponse, ResponseBody},
+    },
+    security::{AclOperation, ResourceType},
+    storage::TopicPartition,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Reads the records of the requested partitions from their logs, which only works on
+/// the leader of the partition. Only the flexible version 12 is supported, without fetch
+/// sessions: requests with a session id fail with `FETCH_SESSION_ID_NOT_FOUND`, the
+/// response always has session id 0, so clients list every partition in every request.
+///
+/// Consumers need `READ` permission on the topics, partitions of the others fail with
+/// `TOPIC_AUTHORIZATION_FAILED`, and read up to the high watermark. Followers, sending
+/// their node id as `replica_id`, need `CLUSTER_ACTION` on the cluster and read up to the
+/// log end offset. Their fetch offset tells the leader how far they got.
+///
+/// The response waits in the purgatory until `min_bytes` can be returned, or `max_wait_ms`
+/// passes. Fetches of the `__cluster_metadata` log are served by the raft client instead.
+/// The size of the responses to consumers counts against their fetch quota, consumers over
+/// it are told to back off in `throttle_time_ms` and their connection is muted as long.
+pub(super) fn handle_fetch(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::Fetch,
+        "request did not specify the Fetch apikey"
+    );
+    let RequestBody::Fetch(ref reqbody) = req.body else {
+        bail!("Invalid request body for Fetch")
+    };
+    debug!(reqbody = ?reqbody);
+    if reqbody.topics.iter().any(|t| t.topic.0 == METADATA_TOPIC) {
+        return handle_metadata_fetch(req, state, conn);
+    }
+
+    let from_follower = reqbody.is_from_follower();
+    let error = if reqbody.session_id != 0 {
+        Some(ErrorCode::FetchSessionIdNotFound)
+    } else if from_follower && !authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        Some(ErrorCode::ClusterAuthorizationFailed)
+    } else {
+        None
+    };
+    if let Some(code) = error {
+        let header = ResponseHeaderV1::respond(req);
+        let body = ResponseBody::Fetch(FetchResponseBody::error(0, code));
+        let message_size = (header.wire_len() + body.wire_len()) as i32;
+        return Ok(HandlerResponse::Ready(KafkaResponse::new(
+            message_size,
+            header,
+            body,
+        )));
+    }
+
+    let now = Instant::now();
+    let mut topics = Vec::with_capacity(reqbody.topics.len());
+    let mut keys = Vec::new();
+    for topic in reqbody.topics.iter() {
+        let authorized = from_follower
+            || authorize(
+                state,
+                conn,
+                AclOperation::Read,
+                ResourceType::Topic,
+                &topic.topic.0,
+            );
+        let mut partitions = Vec::with_capacity(topic.partitions.len());
+        for p in topic.partitions.iter() {
+            let tp = TopicPartition::new(topic.topic.0.clone(), p.partition);
+            if from_follower {
+                update_follower(state, &tp, reqbody.replica_id, p, now);
+            }
+            partitions.push(PartitionFetch {
+                partition: p.partition,
+                fetch_offset: p.fetch_offset,
+                leader_epoch: p.current_leader_epoch,
+                last_fetched_epoch: p.last_fetched_epoch,
+                max_bytes: usize::try_from(p.partition_max_bytes).unwrap_or_default(),
+                error: (!authorized).then_some(ErrorCode::TopicAuthorizationFailed),
+            });
+            keys.push(tp);
+        }
+        topics.push((topic.topic.clone(), partitions));
+    }
+
+    let operation = DelayedFetch {
+        correlation_id: req.header.correlation_id,
+        replica_manager: Arc::clone(&state.replica_manager),
+        metrics: Arc::clone(&state.metrics),
+        quota: QuotaClient::new(state, conn, req),
+        from_follower,
+        min_bytes: usize::try_from(reqbody.min_bytes).unwrap_or_default(),
+        max_bytes: usize::try_from(reqbody.max_bytes).unwrap_or_default(),
+        topics,
+    };
+    let timeout = Duration::from_millis(reqbody.max_wait_ms.max(0) as u64);
+    Ok(HandlerResponse::Delayed(
+        state
+            .fetch_purgatory
+            .try_complete_else_watch(operation, keys, timeout),
+    ))
+}
+
+/// Moves a follower's position on the leader to its fetch offset, completing the requests
+/// waiting for the high watermark if it advanced, and the ones waiting for the follower's
+/// log start offset. Failures are left for the read to report.
+fn update_follower(
+    state: &BrokerState,
+    tp: &TopicPartition,
+    replica_id: i32,
+    fetch: &FetchPartition,
+    now: Instant,
+) {
+    let updated = state.replica_manager.update_follower_fetch(
+        tp,
+        replica_id,
+        fetch.fetch_offset,
+        fetch.last_fetched_epoch,
+        fetch.log_start_offset,
+        now,
+    );
+    match updated {
+        Ok(true) => state.complete_delayed_requests(tp),
+        Ok(false) | Err(_) => {
+            state.delete_records_purgatory.check_and_complete(tp);
+        }
+    }
+}
+
+/// A partition of a fetch request, or the error it failed with before it was read
+#[derive(Debug)]
+struct PartitionFetch {
+    partition: i32,
+    fetch_offset: i64,
+    leader_epoch: i32,
+    last_fetched_epoch: i32,
+    max_bytes: usize,
+    error: Option<ErrorCode>,
+}
+
+/// A Fetch request waiting for its partitions to have `min_bytes` of records, or for one
+/// of them to fail. Watched by every fetched partition.
+#[derive(Debug)]
+pub struct DelayedFetch {
+    correlation_id: i32,
+    replica_manager: Arc<ReplicaManager>,
+    metrics: Arc<BrokerMetrics>,
+    quota: QuotaClient,
+    from_follower: bool,
+    min_bytes: usize,
+    max_bytes: usize,
+    topics: Vec<(CompactString, Vec<PartitionFetch>)>,
+}
+
+impl DelayedFetch {
+    /// Reads every partition, partitions after the first are only read as long as the
+    /// response stays within `max_bytes`. Returns the partitions read, the bytes read and
+    /// whether any partition failed.
+    fn read(
+        &self,
+    ) -> (
+        Vec<(CompactString, Vec<FetchPartitionResponse>)>,
+        usize,
+        bool,
+    ) {
+        let mut read = 0;
+        let mut failed = false;
+        let mut topics = Vec::with_capacity(self.topics.len());
+        for (topic, partitions) in &self.topics {
+            let mut responses = Vec::with_capacity(partitions.len());
+            for p in partitions {
+                if let Some(code) = p.error {
+                    failed = true;
+                    responses.push(FetchPartitionResponse::error(p.partition, code));
+                    continue;
+                }
+                let tp = TopicPartition::new(topic.0.clone(), p.partition);
+                let max_bytes = p.max_bytes.min(self.max_bytes.saturating_sub(read));
+                let result = self.replica_manager.read(
+                    &tp,
+                    p.fetch_offset,
+                    p.leader_epoch,
+                    p.last_fetched_epoch,
+                    max_bytes,
+                    self.from_follower,
+                );
+                let response = match result.and_then(|mut result| {
+                    if read >= self.max_bytes {
+                        result.records.clear();
+                    }
+                    read += result.records.len();
+                    let mut response = FetchPartitionResponse::new(
+                        p.partition,
+                        result.high_watermark,
+                        result.log_start_offset,
+                        result.records,
+                    );
+                    response.error_code = result.error.code();
+                    match &result.diverging_epoch {
+                        Some(diverging) => response.with_diverging_epoch(diverging),
+                        None => Ok(response),
+                    }
+                }) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        let code = if let Some(e) = e.downcast_ref::<ReplicaError>() {
+                            e.error_code()
+                        } else {
+                            error!("Reading {tp} failed: {e:#}");
+                            ErrorCode::UnknownServerError
+                        };
+                        FetchPartitionResponse::error(p.partition, code)
+                    }
+                };
+                failed |= response.error_code != ErrorCode::None.code();
+                responses.push(response);
+            }
+            topics.push((topic.clone(), responses));
+        }
+        (topics, read, failed)
+    }
+
+    fn response(&self, read: Vec<(CompactString, Vec<FetchPartitionResponse>)>) -> KafkaResponse {
+        let header = ResponseHeaderV1::new(self.correlation_id);
+        let mut topics = CompactArray::with_capacity(read.len());
+        for (name, responses) in read {
+            if !self.from_follower {
+                let bytes: usize = responses.iter().map(|r| r.records.0.len()).sum();
+                self.metrics.record_bytes_out(&name.0, bytes as u64);
+            }
+            let mut partitions = CompactArray::with_capacity(responses.len());
+            responses.into_iter().for_each(|r| partitions.push(r));
+            topics.push(FetchTopicResponse::new(name, partitions));
+        }
+
+        let mut body = ResponseBody::Fetch(FetchResponseBody::new(0, topics));
+        let message_size = header.wire_len() + body.wire_len();
+        // followers are not held back by the quotas of clients
+        if !self.from_follower {
+            let throttle_ms = self.quota.record(QuotaType::Fetch, message_size);
+            body.set_throttle_time_ms(throttle_ms);
+        }
+        KafkaResponse::new(message_size as i32, header, body)
+    }
+}
+
+impl DelayedOperation for DelayedFetch {
+    type Output = KafkaResponse;
+
+    fn try_complete(&mut self) -> Option<KafkaResponse> {
+        let (read, bytes, failed) = self.read();
+        (failed || bytes >= self.min_bytes).then(|| self.response(read))
+    }
+
+    fn on_expiration(self) -> KafkaResponse {
+        let (read, _, _) = self.read();
+        self.response(read)
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{self, bail};
+use tracing::{debug, warn};
+
+use super::lib::authorize_cluster;
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{LeaderAndIsrPartitionError, LeaderAndIsrResponseBody, ResponseBody},
+    },
+    security::AclOperation,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Makes this broker the leader or a follower of the partitions the controller assigned
+/// it, starting to fetch the followed ones from their leaders. Requires `CLUSTER_ACTION`
+/// on the cluster, only the flexible version 4 is supported. Requests of a controller
+/// older than the latest one fail as a whole with `STALE_CONTROLLER_EPOCH`, partitions
+/// whose leader epoch did not increase fail on their own with the same error.
+///
+/// Requests waiting on the partitions are retried, as the ones this broker no longer
+/// leads fail.
+pub(super) fn handle_leader_and_isr(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::LeaderAndIsr,
+        "request did not specify the LeaderAndIsr apikey"
+    );
+    let RequestBody::LeaderAndIsr(ref reqbody) = req.body else {
+        bail!("Invalid request body for LeaderAndIsr")
+    };
+    debug!(reqbody = ?reqbody);
+    let header = ResponseHeaderV1::respond(req);
+
+    let body = if authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        match state.replica_manager.become_leader_or_follower(reqbody) {
+            Ok(results) => {
+                let mut partition_errors = CompactArray::with_capacity(results.len());
+                for (tp, code) in results {
+                    state.complete_delayed_requests(&tp);
+                    partition_errors.push(LeaderAndIsrPartitionError::new(
+                        tp.topic,
+                        tp.partition,
+                        code,
+                    ));
+                }
+                LeaderAndIsrResponseBody::new(partition_errors)
+            }
+            Err(e) => {
+                warn!("Ignoring LeaderAndIsr request: {e}");
+                LeaderAndIsrResponseBody::error(e.error_code())
+            }
+        }
+    } else {
+        LeaderAndIsrResponseBody::error(ErrorCode::ClusterAuthorizationFailed)
+    };
+
+    let body = ResponseBody::LeaderAndIsr(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
@@ -1,25 +1,169 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use anyhow::{self, bail};
-use tracing::debug;
+use tracing::{debug, error};
 
+use super::{
+    acls::{handle_create_acls, handle_delete_acls, handle_describe_acls},
+    client_quotas::{handle_alter_client_quotas, handle_describe_client_quotas},
+    cluster::handle_describe_cluster,
+    configs::{handle_alter_configs, handle_describe_configs, handle_incremental_alter_configs},
+    controller::{
+        handle_broker_heartbeat, handle_broker_registration, handle_create_topics,
+        handle_elect_leaders,
+    },
+    fetch::handle_fetch,
+    leader_and_isr::handle_leader_and_isr,
+    list_offsets::handle_list_offsets,
+    log_dirs::handle_describe_log_dirs,
+    metadata::handle_metadata,
+    produce::handle_produce,
+    raft::{
+        handle_begin_quorum_epoch, handle_describe_quorum, handle_end_quorum_epoch,
+        handle_fetch_snapshot, handle_vote,
+    },
+    sasl::handle_sasl_request,
+    scram_credentials::{
+        handle_alter_user_scram_credentials, handle_describe_user_scram_credentials,
+    },
+};
 use crate::{
     WireLen,
-    primitives::CompactArray,
-    request::{KafkaRequest, RequestBody},
+    broker::{BrokerState, ConnectionContext},
+    primitives::{CompactArray, CompactString},
+    purgatory::{Completion, DelayedOperation},
+    quota::{QuotaManager, QuotaType},
+    request::{DeleteRecordsPartition, KafkaRequest, RequestBody},
+    security::{AclOperation, CLUSTER_NAME, ResourceType, user_principal},
     response::{
-        KafkaResponse, ResponseHeaderV0,
-        body::{ApiVersionsResponseBody, DescribeTopicPartitionsResponseBody, ResponseBody},
+        KafkaResponse, ResponseHeaderV0, ResponseHeaderV1,
+        body::{
+            ApiVersionsResponseBody, DeleteRecordsPartitionResult, DeleteRecordsResponseBody,
+            DeleteRecordsTopicResult, DescribeTopicPartitionsResponseBody, ResponseBody,
+        },
     },
-    types::{ApiKeys, ApiVersion, TopicInResponse},
+    replica::ReplicaManager,
+    storage::{OffsetOutOfRange, TopicPartition},
+    types::{ApiKeys, ApiVersion, ErrorCode, Partition, TopicInResponse},
 };
 
-pub fn handle_request(req: &KafkaRequest) -> anyhow::Result<KafkaResponse> {
+pub fn handle_request(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
     match req.header.request_api_key {
-        ApiKeys::ApiVersions => handle_api_version(req),
-        ApiKeys::DescribeTopicPartitions => handle_describe_topic_partition(req),
+        ApiKeys::Produce => handle_produce(req, state, conn),
+        ApiKeys::Fetch => handle_fetch(req, state, conn),
+        ApiKeys::ListOffsets => handle_list_offsets(req, state, conn).map(HandlerResponse::Ready),
+        ApiKeys::Metadata => handle_metadata(req, state, conn).map(HandlerResponse::Ready),
+        ApiKeys::LeaderAndIsr => {
+            handle_leader_and_isr(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::ApiVersions => handle_api_version(req).map(HandlerResponse::Ready),
+        ApiKeys::DeleteRecords => handle_delete_records(req, state, conn),
+        ApiKeys::DescribeTopicPartitions => {
+            handle_describe_topic_partition(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::DescribeAcls => handle_describe_acls(req, state, conn).map(HandlerResponse::Ready),
+        ApiKeys::CreateAcls => handle_create_acls(req, state, conn),
+        ApiKeys::DeleteAcls => handle_delete_acls(req, state, conn),
+        ApiKeys::DescribeLogDirs => {
+            handle_describe_log_dirs(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::DescribeClientQuotas => {
+            handle_describe_client_quotas(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::AlterClientQuotas => handle_alter_client_quotas(req, state, conn),
+        ApiKeys::DescribeUserScramCredentials => {
+            handle_describe_user_scram_credentials(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::AlterUserScramCredentials => {
+            handle_alter_user_scram_credentials(req, state, conn)
+        }
+        ApiKeys::DescribeConfigs => {
+            handle_describe_configs(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::AlterConfigs => handle_alter_configs(req, state, conn),
+        ApiKeys::IncrementalAlterConfigs => handle_incremental_alter_configs(req, state, conn),
+        ApiKeys::DescribeCluster => {
+            handle_describe_cluster(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::Vote => handle_vote(req, state, conn).map(HandlerResponse::Ready),
+        ApiKeys::BeginQuorumEpoch => {
+            handle_begin_quorum_epoch(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::EndQuorumEpoch => {
+            handle_end_quorum_epoch(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::DescribeQuorum => {
+            handle_describe_quorum(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::FetchSnapshot => {
+            handle_fetch_snapshot(req, state, conn).map(HandlerResponse::Ready)
+        }
+        ApiKeys::BrokerRegistration => handle_broker_registration(req, state, conn),
+        ApiKeys::BrokerHeartbeat => handle_broker_heartbeat(req, state, conn),
+        ApiKeys::CreateTopics => handle_create_topics(req, state, conn),
+        ApiKeys::ElectLeaders => handle_elect_leaders(req, state, conn),
+        // the connections of SASL listeners answer these themselves, before their
+        // requests get here
+        ApiKeys::SaslHandshake | ApiKeys::SaslAuthenticate => {
+            handle_sasl_request(req, None, state).map(HandlerResponse::Ready)
+        }
         _ => bail!("api key not implemented"),
     }
 }
 
+/// Whether the client of `conn` may perform `operation` on the resource of
+/// `resource_type` called `name`. Everything is allowed without an authorizer.
+pub(super) fn authorize(
+    state: &BrokerState,
+    conn: &ConnectionContext,
+    operation: AclOperation,
+    resource_type: ResourceType,
+    name: &str,
+) -> bool {
+    state.authorizer.as_ref().map_or(true, |authorizer| {
+        let principal = user_principal(&conn.principal);
+        authorizer.authorize(&principal, conn.peer_addr.ip(), operation, resource_type, name)
+    })
+}
+
+/// Whether the client of `conn` may perform `operation` on the cluster
+pub(super) fn authorize_cluster(
+    state: &BrokerState,
+    conn: &ConnectionContext,
+    operation: AclOperation,
+) -> bool {
+    authorize(state, conn, operation, ResourceType::Cluster, CLUSTER_NAME)
+}
+
+/// The operations the client of `conn` may perform on the resource, as reported in
+/// the authorized operations of responses
+pub(super) fn authorized_operations(
+    state: &BrokerState,
+    conn: &ConnectionContext,
+    resource_type: ResourceType,
+    name: &str,
+) -> i32 {
+    match &state.authorizer {
+        Some(authorizer) => authorizer.authorized_operations(
+            &user_principal(&conn.principal),
+            conn.peer_addr.ip(),
+            resource_type,
+            name,
+        ),
+        None => resource_type
+            .operations()
+            .iter()
+            .fold(0, |operations, operation| operations | 1 << operation.code()),
+    }
+}
+
 fn handle_api_version(req: &KafkaRequest) -> anyhow::Result<KafkaResponse> {
     anyhow::ensure!(
         req.header.request_api_key == ApiKeys::ApiVersions,
@@ -36,8 +180,37 @@ fn handle_api_version(req: &KafkaRequest) -> anyhow::Result<KafkaResponse> {
     let body = if req.header.request_api_version > 4 || req.header.request_api_version < 0 {
         ResponseBody::ApiVersions(ApiVersionsResponseBody::new(35, CompactArray::new(), 0))
     } else {
-        let mut api_versions = CompactArray::with_capacity(2);
+        let mut api_versions = CompactArray::with_capacity(31);
+        api_versions.push(ApiVersion::new(0, 9, 9));
+        api_versions.push(ApiVersion::new(1, 12, 12));
+        api_versions.push(ApiVersion::new(2, 6, 6));
+        api_versions.push(ApiVersion::new(3, 12, 12));
+        api_versions.push(ApiVersion::new(4, 4, 4));
+        api_versions.push(ApiVersion::new(17, 1, 1));
         api_versions.push(ApiVersion::new(18, 0, 4));
+        api_versions.push(ApiVersion::new(19, 7, 7));
+        api_versions.push(ApiVersion::new(21, 2, 2));
+        api_versions.push(ApiVersion::new(29, 2, 3));
+        api_versions.push(ApiVersion::new(30, 2, 3));
+        api_versions.push(ApiVersion::new(31, 2, 3));
+        api_versions.push(ApiVersion::new(32, 4, 4));
+        api_versions.push(ApiVersion::new(33, 2, 2));
+        api_versions.push(ApiVersion::new(35, 2, 4));
+        api_versions.push(ApiVersion::new(36, 0, 2));
+        api_versions.push(ApiVersion::new(43, 2, 2));
+        api_versions.push(ApiVersion::new(44, 1, 1));
+        api_versions.push(ApiVersion::new(48, 1, 1));
+        api_versions.push(ApiVersion::new(49, 1, 1));
+        api_versions.push(ApiVersion::new(50, 0, 0));
+        api_versions.push(ApiVersion::new(51, 0, 0));
+        api_versions.push(ApiVersion::new(52, 0, 0));
+        api_versions.push(ApiVersion::new(53, 1, 1));
+        api_versions.push(ApiVersion::new(54, 1, 1));
+        api_versions.push(ApiVersion::new(55, 1, 1));
+        api_versions.push(ApiVersion::new(59, 0, 0));
+        api_versions.push(ApiVersion::new(60, 0, 1));
+        api_versions.push(ApiVersion::new(62, 3, 3));
+        api_versions.push(ApiVersion::new(63, 0, 1));
         api_versions.push(ApiVersion::new(75, 0, 0));
 
         let body_inner = ApiVersionsResponseBody::new(0, api_versions, 0);
@@ -49,44 +222,281 @@ fn handle_api_version(req: &KafkaRequest) -> anyhow::Result<KafkaResponse> {
     Ok(res)
 }
 
-/// It'll then connect to your server on port 9092 and send a DescribeTopicPartitions (v0) request. The request will contain a single topic with 1 partition.
+/// What a handler answers a request with
+#[derive(Debug)]
+pub enum HandlerResponse {
+    Ready(KafkaResponse),
+    /// The request waits in a purgatory, the response is sent once it completes
+    Delayed(Completion<KafkaResponse>),
+    /// The request is not answered, like a produce with `acks=0`. The connection is still
+    /// muted for the given milliseconds if the client went over a quota.
+    NoResponse(i32),
+}
+
+/// The client of a request, as the byte rate quotas see it
+#[derive(Debug, Clone)]
+pub(super) struct QuotaClient {
+    quotas: Arc<QuotaManager>,
+    user: String,
+    client_id: String,
+}
+
+impl QuotaClient {
+    pub(super) fn new(state: &BrokerState, conn: &ConnectionContext, req: &KafkaRequest) -> Self {
+        Self {
+            quotas: Arc::clone(&state.quotas),
+            user: conn.principal.clone(),
+            client_id: req.header.client_id.as_deref().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Records `bytes` against the client's quota of `quota_type`. Returns the throttle
+    /// time to answer with, in milliseconds, 0 while the client is within its quota.
+    pub(super) fn record(&self, quota_type: QuotaType, bytes: usize) -> i32 {
+        let throttle = self.quotas.record(
+            quota_type,
+            &self.user,
+            &self.client_id,
+            bytes as f64,
+            Instant::now(),
+        );
+        i32::try_from(throttle.as_millis()).unwrap_or(i32::MAX)
+    }
+}
+
+/// Purges every record of the requested partitions before the given offset, or before
+/// the high watermark if the offset is -1, by advancing their log start offset. Only
+/// the flexible version 2 is supported. Partitions are handled one by one, a failure
+/// of one is reported in its error code without affecting the others, partitions of
+/// topics without `DELETE` permission fail with `TOPIC_AUTHORIZATION_FAILED`.
 ///
-/// ## The tester will validate that:
+/// The response waits in the purgatory until the low watermark of every partition
+/// reached the requested offset, or the request's timeout passes.
+fn handle_delete_records(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DeleteRecords,
+        "request did not specify the DeleteRecords apikey"
+    );
+    let RequestBody::DeleteRecords(ref reqbody) = req.body else {
+        bail!("Invalid request body for DeleteRecords")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let mut topics = Vec::with_capacity(reqbody.topics.len());
+    let mut keys = Vec::new();
+    for topic in reqbody.topics.iter() {
+        let authorized =
+            authorize(state, conn, AclOperation::Delete, ResourceType::Topic, &topic.name.0);
+        let mut partitions = Vec::with_capacity(topic.partitions.len());
+        for p in topic.partitions.iter() {
+            let tp = TopicPartition::new(topic.name.0.clone(), p.partition_index);
+            let result = if authorized {
+                delete_records(state, &tp, p.offset)
+            } else {
+                DeleteRecordsPartitionResult::error(
+                    p.partition_index,
+                    ErrorCode::TopicAuthorizationFailed,
+                )
+            };
+            if result.error_code == ErrorCode::None.code() {
+                keys.push(tp);
+            }
+            partitions.push(result);
+        }
+        topics.push((topic.name.clone(), partitions));
+    }
+
+    let operation = DelayedDeleteRecords {
+        correlation_id: req.header.correlation_id,
+        replica_manager: Arc::clone(&state.replica_manager),
+        topics,
+    };
+    let timeout = Duration::from_millis(reqbody.timeout_ms.max(0) as u64);
+    Ok(HandlerResponse::Delayed(
+        state
+            .delete_records_purgatory
+            .try_complete_else_watch(operation, keys, timeout),
+    ))
+}
+
+/// A DeleteRecords request waiting for the low watermark of its partitions, the
+/// smallest log start offset among their replicas, to reach the offsets the records
+/// were deleted before. Watched by the partitions that were deleted from.
+#[derive(Debug)]
+pub struct DelayedDeleteRecords {
+    correlation_id: i32,
+    replica_manager: Arc<ReplicaManager>,
+    /// The result of every partition, successful ones hold the low watermark they wait for
+    topics: Vec<(CompactString, Vec<DeleteRecordsPartitionResult>)>,
+}
+
+impl DelayedDeleteRecords {
+    fn is_acknowledged(
+        &self,
+        topic: &CompactString,
+        result: &DeleteRecordsPartitionResult,
+    ) -> bool {
+        if result.error_code != ErrorCode::None.code() {
+            return true;
+        }
+        let tp = TopicPartition::new(topic.0.clone(), result.partition_index);
+        self.replica_manager
+            .low_watermark(&tp)
+            .map_or(true, |low_watermark| low_watermark >= result.low_watermark)
+    }
+
+    fn response(&mut self) -> KafkaResponse {
+        let header = ResponseHeaderV1::new(self.correlation_id);
+        let mut topics = CompactArray::with_capacity(self.topics.len());
+        for (name, results) in std::mem::take(&mut self.topics) {
+            let mut partitions = CompactArray::with_capacity(results.len());
+            results.into_iter().for_each(|r| partitions.push(r));
+            topics.push(DeleteRecordsTopicResult::new(name, partitions));
+        }
+
+        let body = ResponseBody::DeleteRecords(DeleteRecordsResponseBody::new(0, topics));
+        let message_size = (header.wire_len() + body.wire_len()) as i32;
+        KafkaResponse::new(message_size, header, body)
+    }
+}
+
+impl DelayedOperation for DelayedDeleteRecords {
+    type Output = KafkaResponse;
+
+    fn try_complete(&mut self) -> Option<KafkaResponse> {
+        let acknowledged = self
+            .topics
+            .iter()
+            .all(|(topic, results)| results.iter().all(|r| self.is_acknowledged(topic, r)));
+        acknowledged.then(|| self.response())
+    }
+
+    fn on_expiration(mut self) -> KafkaResponse {
+        for (topic, results) in std::mem::take(&mut self.topics) {
+            let results = results
+                .into_iter()
+                .map(|r| {
+                    if self.is_acknowledged(&topic, &r) {
+                        r
+                    } else {
+                        DeleteRecordsPartitionResult::error(
+                            r.partition_index,
+                            ErrorCode::RequestTimedOut,
+                        )
+                    }
+                })
+                .collect();
+            self.topics.push((topic, results));
+        }
+        self.response()
+    }
+}
+
+fn delete_records(
+    state: &BrokerState,
+    tp: &TopicPartition,
+    offset: i64,
+) -> DeleteRecordsPartitionResult {
+    let Some(log) = state.log_manager.get_log(tp) else {
+        return DeleteRecordsPartitionResult::error(
+            tp.partition,
+            ErrorCode::UnknownTopicOrPartition,
+        );
+    };
+    let mut log = log.lock().unwrap();
+
+    let deleted = if offset == DeleteRecordsPartition::HIGH_WATERMARK {
+        let high_watermark = log.high_watermark();
+        log.delete_records_before(high_watermark)
+    } else {
+        log.delete_records_before(offset)
+    };
+
+    match deleted {
+        Ok(low_watermark) => DeleteRecordsPartitionResult::new(tp.partition, low_watermark),
+        Err(e) if e.downcast_ref::<OffsetOutOfRange>().is_some() => {
+            DeleteRecordsPartitionResult::error(tp.partition, ErrorCode::OffsetOutOfRange)
+        }
+        Err(e) => {
+            error!("Deleting records of {tp} failed: {e:#}");
+            DeleteRecordsPartitionResult::error(tp.partition, ErrorCode::UnknownServerError)
+        }
+    }
+}
+
+/// Describes the requested topics and their partitions. Nodes of a KRaft quorum answer
+/// from the metadata image they replayed from the metadata log, with the topic ids and
+/// where every partition is hosted. Standalone brokers only know which topics have logs,
+/// and report them without id nor partitions.
 ///
-/// - The first 4 bytes of your response (the "message length") are valid.
-/// - The correlation ID in the response header matches the correlation ID in the request header.
-/// - The error code in the response body is 3 (UNKNOWN_TOPIC_OR_PARTITION).
-/// - The response body should be valid DescribeTopicPartitions (v0) Response.
-/// - The topic_name field in the response should be equal to the topic name sent in the request.
-/// - The topic_id field in the response should be equal to 00000000-0000-0000-0000-000000000000.
-/// - The partitions field in the response should be empty. (As there are no partitions assigned to this non-existent topic.)
-fn handle_describe_topic_partition(req: &KafkaRequest) -> anyhow::Result<KafkaResponse> {
+/// Unknown topics fail with `UNKNOWN_TOPIC_OR_PARTITION` and the all zero topic id.
+/// Topics without `DESCRIBE` permission fail with `TOPIC_AUTHORIZATION_FAILED`, the others
+/// report the operations the client may perform on them.
+fn handle_describe_topic_partition(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
     anyhow::ensure!(
         req.header.request_api_key == ApiKeys::DescribeTopicPartitions,
         "request did not specify the DescribeTopicPartitions apikey"
     );
-    let header = ResponseHeaderV0::respond(req);
+    let header = ResponseHeaderV1::respond(req);
     debug!(header = ?header);
-    let mut topics = CompactArray::new();
     let RequestBody::DescribeTopicPartitions(ref reqbody) = req.body else {
         bail!("Invalid request body for DescribeTopicPartitions")
     };
     debug!(reqbody= ?reqbody);
 
-    // next for loop takes care of it, but i still want to signal if this happends for now
-    anyhow::ensure!(reqbody.topics.len() == 1, "Multiple topics were sent");
-
+    let image = state.metadata_image.read().unwrap();
+    let mut topics = CompactArray::with_capacity(reqbody.topics.len());
     for t in reqbody.topics.iter() {
-        topics.push(TopicInResponse::new(0, t.name.clone()));
+        let name = &t.name.0;
+        if !authorize(state, conn, AclOperation::Describe, ResourceType::Topic, name) {
+            let code = ErrorCode::TopicAuthorizationFailed.code() as u16;
+            topics.push(TopicInResponse::new(code, t.name.clone()));
+            continue;
+        }
+        let topic = if state.raft.is_some() {
+            image.topic_by_name(name).map(|topic| {
+                let partitions = topic
+                    .partitions
+                    .iter()
+                    .map(|(&index, p)| {
+                        Partition::new(index, p.leader, p.leader_epoch, &p.replicas, &p.isr)
+                    })
+                    .collect();
+                TopicInResponse::new(0, t.name.clone())
+                    .with_topic_id(topic.id)
+                    .with_partitions(partitions)
+            })
+        } else {
+            state
+                .log_manager
+                .has_topic(name)
+                .then(|| TopicInResponse::new(0, t.name.clone()))
+        };
+        topics.push(match topic {
+            Some(topic) => topic.with_authorized_operations(authorized_operations(
+                state,
+                conn,
+                ResourceType::Topic,
+                name,
+            )),
+            None => {
+                let code = ErrorCode::UnknownTopicOrPartition.code() as u16;
+                TopicInResponse::new(code, t.name.clone())
+            }
+        });
     }
 
-    let body_inner = DescribeTopicPartitionsResponseBody::new(0, topics, 0, 0);
+    let body_inner = DescribeTopicPartitionsResponseBody::new(0, topics);
     let body = ResponseBody::DescribeTopicPartitions(body_inner);
 
     let message_size = (header.wire_len() + body.wire_len()) as i32;
-    let res = KafkaResponse::new(message_size, header, body);
-
-    dbg!(&req);
-    dbg!(&res);
-    Ok(res)
+    Ok(KafkaResponse::new(message_size, header, body))
 }
@@ -0,0 +1,98 @@
+use anyhow::{self, bail};
+use tracing::{debug, error};
+
+use super::lib::authorize;
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    replica::ReplicaError,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            ListOffsetsPartitionResponse, ListOffsetsResponseBody, ListOffsetsTopicResponse,
+            ResponseBody,
+        },
+    },
+    security::{AclOperation, ResourceType},
+    storage::TopicPartition,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers with the offset of every requested partition for its timestamp: the log start
+/// offset, the high watermark, or the first record at or after the timestamp, found with
+/// the time indexes of the log. Only the flexible version 6 is supported, and only the
+/// leader of a partition answers for it.
+///
+/// Partitions of topics without `DESCRIBE` permission fail with `TOPIC_AUTHORIZATION_FAILED`,
+/// the failure of one partition does not affect the others. Timestamps no record reaches
+/// answer -1 as the offset.
+pub(super) fn handle_list_offsets(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::ListOffsets,
+        "request did not specify the ListOffsets apikey"
+    );
+    let RequestBody::ListOffsets(ref reqbody) = req.body else {
+        bail!("Invalid request body for ListOffsets")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let mut topics = CompactArray::with_capacity(reqbody.topics.len());
+    for topic in reqbody.topics.iter() {
+        let authorized = authorize(
+            state,
+            conn,
+            AclOperation::Describe,
+            ResourceType::Topic,
+            &topic.name.0,
+        );
+        let mut partitions = CompactArray::with_capacity(topic.partitions.len());
+        for p in topic.partitions.iter() {
+            if !authorized {
+                partitions.push(ListOffsetsPartitionResponse::error(
+                    p.partition_index,
+                    ErrorCode::TopicAuthorizationFailed,
+                ));
+                continue;
+            }
+            let tp = TopicPartition::new(topic.name.0.clone(), p.partition_index);
+            let found = state.replica_manager.offset_for_timestamp(
+                &tp,
+                p.timestamp,
+                p.current_leader_epoch,
+            );
+            partitions.push(match found {
+                Ok(Some(found)) => ListOffsetsPartitionResponse::new(
+                    p.partition_index,
+                    found.timestamp,
+                    found.offset,
+                    found.leader_epoch,
+                ),
+                Ok(None) => ListOffsetsPartitionResponse::new(p.partition_index, -1, -1, -1),
+                Err(e) => {
+                    let code = if let Some(e) = e.downcast_ref::<ReplicaError>() {
+                        e.error_code()
+                    } else {
+                        error!("Listing offsets of {tp} failed: {e:#}");
+                        ErrorCode::UnknownServerError
+                    };
+                    ListOffsetsPartitionResponse::error(p.partition_index, code)
+                }
+            });
+        }
+        topics.push(ListOffsetsTopicResponse::new(
+            topic.name.clone(),
+            partitions,
+        ));
+    }
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::ListOffsets(ListOffsetsResponseBody::new(0, topics));
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
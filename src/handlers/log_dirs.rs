@@ -0,0 +1,129 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{self, bail};
+use tracing::{debug, warn};
+
+use super::lib::authorize_cluster;
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            DescribeLogDirsPartition, DescribeLogDirsResponseBody, DescribeLogDirsResult,
+            DescribeLogDirsTopic, ResponseBody,
+        },
+    },
+    security::AclOperation,
+    storage::{self, TopicPartition},
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Partition index, size and offset lag of the logs of a topic in a log dir
+type TopicLogs = BTreeMap<String, Vec<(i32, i64, i64)>>;
+
+/// Answers with every log dir of the broker, the space on its file system and the
+/// requested partitions stored in it. A log dir that cannot be read reports
+/// `KAFKA_STORAGE_ERROR`. Needs `DESCRIBE` on the cluster.
+pub(super) fn handle_describe_log_dirs(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeLogDirs,
+        "request did not specify the DescribeLogDirs apikey"
+    );
+    let RequestBody::DescribeLogDirs(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeLogDirs")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let version = req.header.request_api_version;
+    let body = if authorize_cluster(state, conn, AclOperation::Describe) {
+        // null topics ask for every partition
+        let wanted: Option<HashSet<TopicPartition>> = reqbody.topics.as_ref().map(|topics| {
+            topics
+                .iter()
+                .flat_map(|t| {
+                    t.partitions
+                        .iter()
+                        .map(|&p| TopicPartition::new(&t.topic.0, p))
+                })
+                .collect()
+        });
+        let mut results = CompactArray::with_capacity(state.log_manager.log_dirs().len());
+        for log_dir in state.log_manager.log_dirs() {
+            let described = storage::disk_space(log_dir)
+                .map_err(anyhow::Error::from)
+                .and_then(|space| Ok((space, topic_logs(state, log_dir, wanted.as_ref())?)));
+            let name = log_dir.display().to_string();
+            results.push(match described {
+                Ok(((total_bytes, usable_bytes), logs)) => {
+                    let mut topics = CompactArray::with_capacity(logs.len());
+                    for (topic, logs) in logs {
+                        let mut partitions = CompactArray::with_capacity(logs.len());
+                        for (partition, size, offset_lag) in logs {
+                            // there are no future replicas, logs never move between log dirs
+                            partitions.push(DescribeLogDirsPartition::new(
+                                partition, size, offset_lag, false,
+                            ));
+                        }
+                        topics.push(DescribeLogDirsTopic::new(topic, partitions));
+                    }
+                    DescribeLogDirsResult::new(
+                        name,
+                        topics,
+                        i64::try_from(total_bytes).unwrap_or(i64::MAX),
+                        i64::try_from(usable_bytes).unwrap_or(i64::MAX),
+                    )
+                }
+                Err(e) => {
+                    warn!("Describing log dir {name} failed: {e:#}");
+                    DescribeLogDirsResult::error(name, ErrorCode::KafkaStorageError)
+                }
+            });
+        }
+        DescribeLogDirsResponseBody::new(version, 0, results)
+    } else {
+        DescribeLogDirsResponseBody::error(version, 0, ErrorCode::ClusterAuthorizationFailed)
+    };
+
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::DescribeLogDirs(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// The logs stored in `log_dir`, only the `wanted` ones if given
+fn topic_logs(
+    state: &BrokerState,
+    log_dir: &Path,
+    wanted: Option<&HashSet<TopicPartition>>,
+) -> anyhow::Result<TopicLogs> {
+    let mut topics = TopicLogs::new();
+    for log in state.log_manager.logs() {
+        let log = log.lock().unwrap();
+        let tp = log.topic_partition();
+        if state.log_manager.log_dir_of(&log) != Some(log_dir)
+            || wanted.is_some_and(|wanted| !wanted.contains(tp))
+        {
+            continue;
+        }
+        let size = i64::try_from(log.size()?).unwrap_or(i64::MAX);
+        let offset_lag = (log.high_watermark() - log.log_end_offset()).max(0);
+        topics
+            .entry(tp.topic.clone())
+            .or_default()
+            .push((tp.partition, size, offset_lag));
+    }
+    for partitions in topics.values_mut() {
+        partitions.sort_unstable();
+    }
+    Ok(topics)
+}
@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{self, bail};
+use tracing::debug;
+
+use super::{
+    cluster::{controller_id, listener_brokers},
+    lib::{authorize, authorized_operations},
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    metadata::{PartitionRegistration, TopicImage},
+    primitives::{CompactArray, Uuid},
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            MetadataResponseBody, MetadataResponseBroker, MetadataResponsePartition,
+            MetadataResponseTopic, ResponseBody,
+        },
+    },
+    security::{AclOperation, ResourceType},
+    storage::CONSUMER_OFFSETS_TOPIC,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers with the brokers, the controller and the partitions of the requested topics,
+/// or of every topic if the request asks for null topics. Brokers are given with their
+/// address on the listener the request arrived on, so are the leaders clients are sent to:
+/// partitions whose leader cannot be reached there fail with `LEADER_NOT_AVAILABLE`.
+/// Only the flexible version 12 is supported.
+///
+/// Topics are never created, `allow_auto_topic_creation` is ignored and unknown topics
+/// fail with `UNKNOWN_TOPIC_OR_PARTITION`, or `UNKNOWN_TOPIC_ID` when asked for by id.
+/// Requested topics without `DESCRIBE` permission fail with `TOPIC_AUTHORIZATION_FAILED`,
+/// they are left out when every topic is asked for.
+pub(super) fn handle_metadata(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::Metadata,
+        "request did not specify the Metadata apikey"
+    );
+    let RequestBody::Metadata(ref reqbody) = req.body else {
+        bail!("Invalid request body for Metadata")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let brokers = listener_brokers(state, &conn.listener_name);
+    let reachable: HashSet<i32> = brokers.iter().map(|b| b.id).collect();
+    let known = known_topics(state);
+
+    let describe = |topic: &TopicImage| {
+        let partitions = topic
+            .partitions
+            .iter()
+            .map(|(&index, p)| {
+                let (code, leader) = if reachable.contains(&p.leader) {
+                    (ErrorCode::None, p.leader)
+                } else {
+                    (ErrorCode::LeaderNotAvailable, -1)
+                };
+                MetadataResponsePartition::new(
+                    code,
+                    index,
+                    leader,
+                    p.leader_epoch,
+                    &p.replicas,
+                    &p.isr,
+                )
+            })
+            .collect::<Vec<_>>();
+        let operations = if reqbody.include_topic_authorized_operations.is_true() {
+            authorized_operations(state, conn, ResourceType::Topic, &topic.name)
+        } else {
+            i32::MIN
+        };
+        MetadataResponseTopic::new(
+            topic.name.clone(),
+            topic.id,
+            topic.name == CONSUMER_OFFSETS_TOPIC,
+            partitions.into(),
+            operations,
+        )
+    };
+    let may_describe = |name: &str| {
+        authorize(
+            state,
+            conn,
+            AclOperation::Describe,
+            ResourceType::Topic,
+            name,
+        )
+    };
+
+    let mut topics = CompactArray::new();
+    match &reqbody.topics {
+        None => {
+            for topic in known.iter().filter(|t| may_describe(&t.name)) {
+                topics.push(describe(topic));
+            }
+        }
+        Some(requested) => {
+            for t in requested.iter() {
+                let found = match &t.name.0 {
+                    Some(name) => known.iter().find(|k| &k.name == name),
+                    None => known.iter().find(|k| k.id == t.topic_id),
+                };
+                topics.push(match found {
+                    Some(topic) if may_describe(&topic.name) => describe(topic),
+                    Some(topic) => MetadataResponseTopic::error(
+                        ErrorCode::TopicAuthorizationFailed,
+                        Some(topic.name.clone()),
+                        t.topic_id,
+                    ),
+                    None if t.name.0.is_none() => {
+                        MetadataResponseTopic::error(ErrorCode::UnknownTopicId, None, t.topic_id)
+                    }
+                    None => MetadataResponseTopic::error(
+                        ErrorCode::UnknownTopicOrPartition,
+                        t.name.0.clone(),
+                        t.topic_id,
+                    ),
+                });
+            }
+        }
+    }
+
+    let brokers = brokers
+        .into_iter()
+        .map(|b| MetadataResponseBroker::new(b.id, b.host, i32::from(b.port), b.rack))
+        .collect::<Vec<_>>();
+    let header = ResponseHeaderV1::respond(req);
+    let body = ResponseBody::Metadata(MetadataResponseBody::new(
+        0,
+        brokers.into(),
+        state.cluster_id.clone(),
+        controller_id(state),
+        topics,
+    ));
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// Every topic this node knows, in the order of their names: the ones of the metadata
+/// image on nodes of a KRaft quorum, or else the ones of the partitions it hosts, which
+/// have no ids
+fn known_topics(state: &BrokerState) -> Vec<TopicImage> {
+    if state.raft.is_some() {
+        let image = state.metadata_image.read().unwrap();
+        let mut topics: Vec<_> = image.topics().cloned().collect();
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+        return topics;
+    }
+    let mut topics: BTreeMap<String, TopicImage> = BTreeMap::new();
+    for partition in state.replica_manager.partitions() {
+        let partition = partition.lock().unwrap();
+        let tp = partition.topic_partition();
+        let topic = topics
+            .entry(tp.topic.clone())
+            .or_insert_with(|| TopicImage {
+                name: tp.topic.clone(),
+                id: Uuid::ZERO,
+                partitions: BTreeMap::new(),
+            });
+        let assignment = partition.assignment();
+        topic.partitions.insert(
+            tp.partition,
+            PartitionRegistration {
+                replicas: assignment.replicas.clone(),
+                isr: partition.isr().to_vec(),
+                leader: partition.leader(),
+                leader_epoch: partition.leader_epoch(),
+                partition_epoch: assignment.partition_epoch,
+            },
+        );
+    }
+    topics.into_values().collect()
+}
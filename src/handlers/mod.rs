@@ -1,3 +1,22 @@
+mod acls;
+mod client_quotas;
+mod cluster;
+mod configs;
+mod controller;
+mod fetch;
+mod leader_and_isr;
 mod lib;
+mod list_offsets;
+mod log_dirs;
+mod metadata;
+mod produce;
+mod raft;
+mod sasl;
+mod scram_credentials;
 
-pub use lib::handle_request;
+pub use controller::DelayedControllerWrite;
+pub use fetch::DelayedFetch;
+pub use lib::{DelayedDeleteRecords, HandlerResponse, handle_request};
+pub use produce::DelayedProduce;
+pub use raft::DelayedRaftFetch;
+pub use sasl::handle_sasl_request;
@@ -0,0 +1,275 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{self, bail};
+use bytes::BytesMut;
+use tracing::{debug, error};
+
+use super::lib::{HandlerResponse, QuotaClient, authorize};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    codec::Decoder,
+    primitives::{CompactArray, CompactString},
+    purgatory::DelayedOperation,
+    quota::QuotaType,
+    raft::METADATA_TOPIC,
+    replica::{ReplicaError, ReplicaManager},
+    request::{KafkaRequest, ProduceRequestBody, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{ProducePartitionResponse, ProduceResponseBody, ProduceTopicResponse, ResponseBody},
+    },
+    security::{AclOperation, ResourceType},
+    storage::{RecordBatchTooLarge, TopicPartition},
+    types::{ApiKeys, ErrorCode, RecordBatch, RecordBatchError},
+};
+
+/// Appends the produced record batch of every partition to its log, which only works on
+/// the leader of the partition. Only the flexible version 9 is supported. Partitions are
+/// handled one by one, a failure of one is reported in its error code without affecting
+/// the others. Partitions of topics without `WRITE` permission fail with
+/// `TOPIC_AUTHORIZATION_FAILED`, every partition fails with `INVALID_REQUIRED_ACKS` if
+/// `acks` is not 0, 1 or -1. The `__cluster_metadata` log is only written by the KRaft
+/// quorum, its partitions fail with `UNKNOWN_TOPIC_OR_PARTITION`.
+///
+/// With `acks=0` there is no response. With `acks=all` the response waits in the
+/// purgatory until every in-sync replica has the records, or the request's timeout passes.
+/// The bytes of every batch count against the client's produce quota, clients over it
+/// are told to back off in `throttle_time_ms` and their connection is muted as long.
+pub(super) fn handle_produce(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::Produce,
+        "request did not specify the Produce apikey"
+    );
+    let RequestBody::Produce(ref reqbody) = req.body else {
+        bail!("Invalid request body for Produce")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let acks = reqbody.acks;
+    let valid_acks = matches!(
+        acks,
+        ProduceRequestBody::ACKS_NONE
+            | ProduceRequestBody::ACKS_LEADER
+            | ProduceRequestBody::ACKS_ALL
+    );
+    let mut topics = Vec::with_capacity(reqbody.topic_data.len());
+    let mut keys = Vec::new();
+    for topic in reqbody.topic_data.iter() {
+        let authorized = authorize(
+            state,
+            conn,
+            AclOperation::Write,
+            ResourceType::Topic,
+            &topic.name.0,
+        );
+        let mut partitions = Vec::with_capacity(topic.partition_data.len());
+        for p in topic.partition_data.iter() {
+            let tp = TopicPartition::new(topic.name.0.clone(), p.index);
+            let (result, required_offset) = if !valid_acks {
+                (
+                    ProducePartitionResponse::error(p.index, ErrorCode::InvalidRequiredAcks, None),
+                    None,
+                )
+            } else if !authorized {
+                let code = ErrorCode::TopicAuthorizationFailed;
+                (ProducePartitionResponse::error(p.index, code, None), None)
+            } else if topic.name.0 == METADATA_TOPIC {
+                let code = ErrorCode::UnknownTopicOrPartition;
+                (ProducePartitionResponse::error(p.index, code, None), None)
+            } else {
+                append(
+                    state,
+                    &tp,
+                    &p.records.0,
+                    acks == ProduceRequestBody::ACKS_ALL,
+                )
+            };
+            if result.error_code == ErrorCode::None.code() {
+                state
+                    .metrics
+                    .record_bytes_in(&tp.topic, p.records.0.len() as u64);
+                keys.push(tp);
+            }
+            partitions.push((result, required_offset));
+        }
+        topics.push((topic.name.clone(), partitions));
+    }
+
+    // fetches waiting for new records, and produces waiting on a leader without followers
+    for tp in &keys {
+        state.complete_delayed_requests(tp);
+    }
+
+    let bytes = reqbody
+        .topic_data
+        .iter()
+        .flat_map(|t| t.partition_data.iter())
+        .map(|p| p.records.0.len())
+        .sum();
+    let throttle_ms = QuotaClient::new(state, conn, req).record(QuotaType::Produce, bytes);
+
+    let mut operation = DelayedProduce {
+        correlation_id: req.header.correlation_id,
+        replica_manager: Arc::clone(&state.replica_manager),
+        throttle_ms,
+        topics,
+    };
+    match acks {
+        ProduceRequestBody::ACKS_NONE => Ok(HandlerResponse::NoResponse(throttle_ms)),
+        ProduceRequestBody::ACKS_ALL => {
+            let timeout = Duration::from_millis(reqbody.timeout_ms.max(0) as u64);
+            Ok(HandlerResponse::Delayed(
+                state
+                    .produce_purgatory
+                    .try_complete_else_watch(operation, keys, timeout),
+            ))
+        }
+        _ => Ok(HandlerResponse::Ready(operation.response())),
+    }
+}
+
+/// Appends the single batch of a partition as its leader. Returns the result of the
+/// partition and, if the append worked, the offset the high watermark has to reach for
+/// every in-sync replica to have the batch.
+fn append(
+    state: &BrokerState,
+    tp: &TopicPartition,
+    records: &[u8],
+    require_min_isr: bool,
+) -> PartitionResult {
+    let index = tp.partition;
+    let mut buf = BytesMut::from(records);
+    let batch = match RecordBatch::decode(&mut buf, None) {
+        Ok(Some(batch)) if buf.is_empty() => batch,
+        Ok(_) => {
+            let message = Some("Partition data has to be a single complete batch".to_string());
+            return (
+                ProducePartitionResponse::error(index, ErrorCode::CorruptMessage, message),
+                None,
+            );
+        }
+        Err(e) => {
+            let message = Some(format!("{e:#}"));
+            return (
+                ProducePartitionResponse::error(index, ErrorCode::CorruptMessage, message),
+                None,
+            );
+        }
+    };
+    let records = i64::from(batch.last_offset_delta) + 1;
+
+    match state
+        .replica_manager
+        .append_to_leader(tp, batch, require_min_isr)
+    {
+        Ok((base_offset, log_start_offset)) => (
+            ProducePartitionResponse::new(index, base_offset, log_start_offset),
+            Some(base_offset + records),
+        ),
+        Err(e) => {
+            let code = if let Some(e) = e.downcast_ref::<ReplicaError>() {
+                e.error_code()
+            } else if e.downcast_ref::<RecordBatchTooLarge>().is_some() {
+                ErrorCode::MessageTooLarge
+            } else if e.downcast_ref::<RecordBatchError>().is_some() {
+                ErrorCode::CorruptMessage
+            } else {
+                error!("Appending to {tp} failed: {e:#}");
+                ErrorCode::UnknownServerError
+            };
+            (
+                ProducePartitionResponse::error(index, code, Some(format!("{e:#}"))),
+                None,
+            )
+        }
+    }
+}
+
+/// A Produce request with `acks=all` waiting for the high watermark of its partitions,
+/// the offset every in-sync replica has, to reach the end of the appended batches.
+/// Watched by the partitions that were appended to.
+#[derive(Debug)]
+pub struct DelayedProduce {
+    correlation_id: i32,
+    replica_manager: Arc<ReplicaManager>,
+    /// Throttle time of the produce quota
+    throttle_ms: i32,
+    topics: Vec<(CompactString, Vec<PartitionResult>)>,
+}
+
+/// The result of a partition, with the offset the high watermark has to reach while
+/// it is still waiting
+type PartitionResult = (ProducePartitionResponse, Option<i64>);
+
+impl DelayedProduce {
+    /// Acknowledges the partitions whose batch got replicated, or that failed since.
+    /// Returns whether every partition is done.
+    fn check_partitions(&mut self) -> bool {
+        let mut done = true;
+        for (topic, results) in &mut self.topics {
+            for (result, required_offset) in results.iter_mut() {
+                let Some(offset) = *required_offset else {
+                    continue;
+                };
+                let tp = TopicPartition::new(topic.0.clone(), result.index);
+                match self
+                    .replica_manager
+                    .check_enough_replicas_reached(&tp, offset)
+                {
+                    None => done = false,
+                    Some(Ok(())) => *required_offset = None,
+                    Some(Err(e)) => {
+                        let message = Some(e.to_string());
+                        *result =
+                            ProducePartitionResponse::error(result.index, e.error_code(), message);
+                        *required_offset = None;
+                    }
+                }
+            }
+        }
+        done
+    }
+
+    fn response(&mut self) -> KafkaResponse {
+        let header = ResponseHeaderV1::new(self.correlation_id);
+        let mut topics = CompactArray::with_capacity(self.topics.len());
+        for (name, results) in std::mem::take(&mut self.topics) {
+            let mut partitions = CompactArray::with_capacity(results.len());
+            results.into_iter().for_each(|(r, _)| partitions.push(r));
+            topics.push(ProduceTopicResponse::new(name, partitions));
+        }
+
+        let body = ResponseBody::Produce(ProduceResponseBody::new(self.throttle_ms, topics));
+        let message_size = (header.wire_len() + body.wire_len()) as i32;
+        KafkaResponse::new(message_size, header, body)
+    }
+}
+
+impl DelayedOperation for DelayedProduce {
+    type Output = KafkaResponse;
+
+    fn try_complete(&mut self) -> Option<KafkaResponse> {
+        self.check_partitions().then(|| self.response())
+    }
+
+    fn on_expiration(mut self) -> KafkaResponse {
+        self.check_partitions();
+        for (_, results) in &mut self.topics {
+            for (result, required_offset) in results.iter_mut() {
+                if required_offset.take().is_some() {
+                    *result = ProducePartitionResponse::error(
+                        result.index,
+                        ErrorCode::RequestTimedOut,
+                        None,
+                    );
+                }
+            }
+        }
+        self.response()
+    }
+}
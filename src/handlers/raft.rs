@@ -0,0 +1,266 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{self, bail};
+use tracing::debug;
+
+use super::lib::{HandlerResponse, authorize_cluster};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    purgatory::DelayedOperation,
+    raft::{METADATA_TOPIC, RaftClient},
+    request::{FetchPartition, KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            BeginQuorumEpochResponseBody, DescribeQuorumResponseBody, FetchPartitionResponse,
+            FetchResponseBody, FetchSnapshotResponseBody, FetchTopicResponse, ResponseBody,
+            VoteResponseBody,
+        },
+    },
+    security::AclOperation,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// The raft client of the node, requests of the quorum fail on nodes outside of one
+fn raft_client(state: &BrokerState, api_key: ApiKeys) -> anyhow::Result<&RaftClient> {
+    match &state.raft {
+        Some(raft) => Ok(raft),
+        None => bail!("{api_key:?} is only served by nodes of a KRaft quorum"),
+    }
+}
+
+fn respond(req: &KafkaRequest, body: ResponseBody) -> KafkaResponse {
+    let header = ResponseHeaderV1::respond(req);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    KafkaResponse::new(message_size, header, body)
+}
+
+/// Votes for a candidate of the quorum. Only sent by controllers, which need
+/// `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_vote(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::Vote,
+        "request did not specify the Vote apikey"
+    );
+    let RequestBody::Vote(ref reqbody) = req.body else {
+        bail!("Invalid request body for Vote")
+    };
+    debug!(reqbody = ?reqbody);
+    let raft = raft_client(state, ApiKeys::Vote)?;
+
+    let body = if authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        raft.handle_vote(reqbody)?
+    } else {
+        VoteResponseBody::error(ErrorCode::ClusterAuthorizationFailed)
+    };
+    Ok(respond(req, ResponseBody::Vote(body)))
+}
+
+/// Follows the leader announcing its election. Needs `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_begin_quorum_epoch(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::BeginQuorumEpoch,
+        "request did not specify the BeginQuorumEpoch apikey"
+    );
+    let RequestBody::BeginQuorumEpoch(ref reqbody) = req.body else {
+        bail!("Invalid request body for BeginQuorumEpoch")
+    };
+    debug!(reqbody = ?reqbody);
+    let raft = raft_client(state, ApiKeys::BeginQuorumEpoch)?;
+
+    let body = if authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        raft.handle_begin_quorum_epoch(reqbody)?
+    } else {
+        BeginQuorumEpochResponseBody::error(ErrorCode::ClusterAuthorizationFailed)
+    };
+    Ok(respond(req, ResponseBody::BeginQuorumEpoch(body)))
+}
+
+/// Lets the leader of the quorum resign, the voters elect a new one right away. Needs
+/// `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_end_quorum_epoch(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::EndQuorumEpoch,
+        "request did not specify the EndQuorumEpoch apikey"
+    );
+    let RequestBody::EndQuorumEpoch(ref reqbody) = req.body else {
+        bail!("Invalid request body for EndQuorumEpoch")
+    };
+    debug!(reqbody = ?reqbody);
+    let raft = raft_client(state, ApiKeys::EndQuorumEpoch)?;
+
+    let body = if authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        raft.handle_end_quorum_epoch(reqbody)?
+    } else {
+        BeginQuorumEpochResponseBody::error(ErrorCode::ClusterAuthorizationFailed)
+    };
+    Ok(respond(req, ResponseBody::EndQuorumEpoch(body)))
+}
+
+/// Describes the voters and observers of the quorum, as known to its leader. Needs
+/// `DESCRIBE` on the cluster.
+pub(super) fn handle_describe_quorum(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeQuorum,
+        "request did not specify the DescribeQuorum apikey"
+    );
+    let RequestBody::DescribeQuorum(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeQuorum")
+    };
+    debug!(reqbody = ?reqbody);
+    let raft = raft_client(state, ApiKeys::DescribeQuorum)?;
+
+    let body = if authorize_cluster(state, conn, AclOperation::Describe) {
+        raft.handle_describe_quorum(reqbody)
+    } else {
+        DescribeQuorumResponseBody::error(ErrorCode::ClusterAuthorizationFailed)
+    };
+    Ok(respond(req, ResponseBody::DescribeQuorum(body)))
+}
+
+/// Serves snapshots of the metadata log to replicas that fell behind its start, which
+/// never happens as the log is kept whole. Needs `CLUSTER_ACTION` on the cluster.
+pub(super) fn handle_fetch_snapshot(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::FetchSnapshot,
+        "request did not specify the FetchSnapshot apikey"
+    );
+    let RequestBody::FetchSnapshot(ref reqbody) = req.body else {
+        bail!("Invalid request body for FetchSnapshot")
+    };
+    debug!(reqbody = ?reqbody);
+    let raft = raft_client(state, ApiKeys::FetchSnapshot)?;
+
+    let body = if authorize_cluster(state, conn, AclOperation::ClusterAction) {
+        raft.handle_fetch_snapshot(reqbody)?
+    } else {
+        FetchSnapshotResponseBody::error(0, ErrorCode::ClusterAuthorizationFailed)
+    };
+    Ok(respond(req, ResponseBody::FetchSnapshot(body)))
+}
+
+/// Serves a fetch of the `__cluster_metadata` log, sent by the replicas of the quorum,
+/// which need `CLUSTER_ACTION` on the cluster. Only the leader of the quorum answers
+/// without an error. Fetches without new records wait in the purgatory of the raft
+/// client until the leader appends some or the high watermark moves.
+pub(super) fn handle_metadata_fetch(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    let RequestBody::Fetch(ref reqbody) = req.body else {
+        bail!("Invalid request body for Fetch")
+    };
+    let error = match &state.raft {
+        None => Some(ErrorCode::UnknownTopicOrPartition),
+        Some(_) if !authorize_cluster(state, conn, AclOperation::ClusterAction) => {
+            Some(ErrorCode::ClusterAuthorizationFailed)
+        }
+        Some(raft) if !raft.is_same_cluster(reqbody.cluster_id()?.as_deref()) => {
+            Some(ErrorCode::InconsistentClusterId)
+        }
+        Some(_) => None,
+    };
+    let partition = reqbody
+        .topics
+        .iter()
+        .filter(|t| t.topic.0 == METADATA_TOPIC)
+        .flat_map(|t| t.partitions.iter())
+        .next();
+    let (Some(raft), None, Some(p)) = (&state.raft, error, partition) else {
+        let code = error.unwrap_or(ErrorCode::InvalidRequest);
+        let body = ResponseBody::Fetch(FetchResponseBody::error(0, code));
+        return Ok(HandlerResponse::Ready(respond(req, body)));
+    };
+
+    let operation = DelayedRaftFetch {
+        correlation_id: req.header.correlation_id,
+        raft: Arc::clone(raft),
+        replica_id: reqbody.replica_id,
+        partition: FetchPartition::new(
+            p.partition,
+            p.current_leader_epoch,
+            p.fetch_offset,
+            p.log_start_offset,
+            p.partition_max_bytes,
+        )
+        .with_last_fetched_epoch(p.last_fetched_epoch),
+        high_watermark: None,
+    };
+    let timeout = Duration::from_millis(reqbody.max_wait_ms.max(0) as u64);
+    let completion = raft.fetch_purgatory().try_complete_else_watch(
+        operation,
+        vec![RaftClient::topic_partition()],
+        timeout,
+    );
+    // the fetch may have committed records other replicas wait for
+    raft.fetch_purgatory()
+        .check_and_complete(&RaftClient::topic_partition());
+    Ok(HandlerResponse::Delayed(completion))
+}
+
+/// A fetch of the metadata log waiting for records to be appended, or for the high
+/// watermark to move, which followers learn of through their fetches
+#[derive(Debug)]
+pub struct DelayedRaftFetch {
+    correlation_id: i32,
+    raft: Arc<RaftClient>,
+    replica_id: i32,
+    partition: FetchPartition,
+    /// The high watermark of the first read, `None` before it
+    high_watermark: Option<i64>,
+}
+
+impl DelayedRaftFetch {
+    fn response(&self, partition: FetchPartitionResponse) -> KafkaResponse {
+        let header = ResponseHeaderV1::new(self.correlation_id);
+        let mut partitions = CompactArray::with_capacity(1);
+        partitions.push(partition);
+        let mut topics = CompactArray::with_capacity(1);
+        topics.push(FetchTopicResponse::new(METADATA_TOPIC, partitions));
+        let body = ResponseBody::Fetch(FetchResponseBody::new(0, topics));
+        let message_size = (header.wire_len() + body.wire_len()) as i32;
+        KafkaResponse::new(message_size, header, body)
+    }
+}
+
+impl DelayedOperation for DelayedRaftFetch {
+    type Output = KafkaResponse;
+
+    fn try_complete(&mut self) -> Option<KafkaResponse> {
+        let partition = self.raft.fetch_partition(self.replica_id, &self.partition);
+        let high_watermark = *self.high_watermark.get_or_insert(partition.high_watermark);
+        let complete = partition.error_code != ErrorCode::None.code()
+            || !partition.records.0.is_empty()
+            || partition.diverging_epoch().ok().flatten().is_some()
+            || partition.high_watermark != high_watermark;
+        complete.then(|| self.response(partition))
+    }
+
+    fn on_expiration(self) -> KafkaResponse {
+        let partition = self.raft.fetch_partition(self.replica_id, &self.partition);
+        self.response(partition)
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Instant;
+
+use anyhow::{self, bail};
+use tracing::{debug, info, warn};
+
+use crate::{
+    WireLen,
+    broker::BrokerState,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeader, ResponseHeaderV0, ResponseHeaderV1,
+        body::{ResponseBody, SaslAuthenticateResponseBody, SaslHandshakeResponseBody},
+    },
+    security::SaslAuthenticator,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers SaslHandshake and SaslAuthenticate requests, moving the connection's
+/// authenticator along. Connections of listeners without SASL have no authenticator
+/// and get `ILLEGAL_SASL_STATE`.
+pub fn handle_sasl_request(
+    req: &KafkaRequest,
+    auth: Option<&mut SaslAuthenticator>,
+    state: &BrokerState,
+) -> anyhow::Result<KafkaResponse> {
+    match req.header.request_api_key {
+        ApiKeys::SaslHandshake => handle_sasl_handshake(req, auth),
+        ApiKeys::SaslAuthenticate => handle_sasl_authenticate(req, auth, state),
+        key => bail!("{key:?} is not a SASL request"),
+    }
+}
+
+/// Picks the mechanism the client authenticates with. Only version 1 is supported,
+/// version 0 sent the tokens without Kafka's framing.
+fn handle_sasl_handshake(
+    req: &KafkaRequest,
+    auth: Option<&mut SaslAuthenticator>,
+) -> anyhow::Result<KafkaResponse> {
+    let RequestBody::SaslHandshake(ref reqbody) = req.body else {
+        bail!("Invalid request body for SaslHandshake")
+    };
+    debug!(mechanism = reqbody.mechanism);
+
+    let body = match auth {
+        _ if req.header.request_api_version != 1 => {
+            SaslHandshakeResponseBody::new(ErrorCode::UnsupportedVersion, Vec::new())
+        }
+        None => SaslHandshakeResponseBody::new(ErrorCode::IllegalSaslState, Vec::new()),
+        Some(auth) => {
+            let enabled = auth.enabled().iter().map(ToString::to_string).collect();
+            match auth.handshake(&reqbody.mechanism) {
+                Ok(()) => SaslHandshakeResponseBody::new(ErrorCode::None, enabled),
+                Err(e) => {
+                    warn!("SASL handshake failed: {e}");
+                    SaslHandshakeResponseBody::new(e.error_code(), enabled)
+                }
+            }
+        }
+    };
+
+    let header = ResponseHeaderV0::respond(req);
+    let body = ResponseBody::SaslHandshake(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+/// Passes the client's token to the mechanism picked by the handshake. Once
+/// authenticated, the response tells the client how long its session lasts.
+fn handle_sasl_authenticate(
+    req: &KafkaRequest,
+    auth: Option<&mut SaslAuthenticator>,
+    state: &BrokerState,
+) -> anyhow::Result<KafkaResponse> {
+    let RequestBody::SaslAuthenticate(ref reqbody) = req.body else {
+        bail!("Invalid request body for SaslAuthenticate")
+    };
+    let version = req.header.request_api_version;
+
+    let body = match auth {
+        _ if !(0..=2).contains(&version) => SaslAuthenticateResponseBody::error(
+            version,
+            ErrorCode::UnsupportedVersion,
+            format!("SaslAuthenticate version {version} is not supported"),
+        ),
+        None => SaslAuthenticateResponseBody::error(
+            version,
+            ErrorCode::IllegalSaslState,
+            "SASL is not enabled on this listener",
+        ),
+        Some(auth) => {
+            let now = Instant::now();
+            match auth.authenticate(&reqbody.auth_bytes, &state.credentials, now) {
+                Ok(auth_bytes) => {
+                    let session_lifetime_ms = auth.session_lifetime_ms(now);
+                    if let Some(principal) = auth.principal() {
+                        info!(principal, session_lifetime_ms, "Authenticated");
+                    }
+                    SaslAuthenticateResponseBody::new(version, auth_bytes, session_lifetime_ms)
+                }
+                Err(e) => {
+                    warn!("SASL authentication failed: {e}");
+                    SaslAuthenticateResponseBody::error(version, e.error_code(), e.to_string())
+                }
+            }
+        }
+    };
+
+    let header: ResponseHeader = if version >= 2 {
+        ResponseHeaderV1::respond(req).into()
+    } else {
+        ResponseHeaderV0::respond(req).into()
+    };
+    let body = ResponseBody::SaslAuthenticate(body);
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
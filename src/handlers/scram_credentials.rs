@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{self, bail};
+use tracing::{debug, error, info};
+
+use super::{
+    controller::{alter_metadata, respond_written, write_timed_out},
+    lib::{HandlerResponse, authorize_cluster},
+};
+use crate::{
+    WireLen,
+    broker::{BrokerState, ConnectionContext},
+    primitives::CompactArray,
+    request::{KafkaRequest, RequestBody},
+    response::{
+        KafkaResponse, ResponseHeaderV1,
+        body::{
+            AlterUserScramCredentialsResponseBody, AlterUserScramCredentialsResult, CredentialInfo,
+            DescribeUserScramCredentialsResponseBody, DescribeUserScramCredentialsResult,
+            ResponseBody,
+        },
+    },
+    security::{AclOperation, ScramCredential, ScramCredentialChange, ScramMechanism},
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Answers with the mechanisms and iterations of the SCRAM credentials of the requested
+/// users, or of every user with credentials if none are requested. Salts and keys are
+/// never returned. Needs `DESCRIBE` on the cluster.
+pub(super) fn handle_describe_user_scram_credentials(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<KafkaResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::DescribeUserScramCredentials,
+        "request did not specify the DescribeUserScramCredentials apikey"
+    );
+    let RequestBody::DescribeUserScramCredentials(ref reqbody) = req.body else {
+        bail!("Invalid request body for DescribeUserScramCredentials")
+    };
+    debug!(reqbody = ?reqbody);
+
+    let header = ResponseHeaderV1::respond(req);
+    if !authorize_cluster(state, conn, AclOperation::Describe) {
+        let body = ResponseBody::DescribeUserScramCredentials(
+            DescribeUserScramCredentialsResponseBody::error(
+                0,
+                ErrorCode::ClusterAuthorizationFailed,
+                "Not authorized to describe SCRAM credentials",
+            ),
+        );
+        let message_size = (header.wire_len() + body.wire_len()) as i32;
+        return Ok(KafkaResponse::new(message_size, header, body));
+    }
+
+    let users = state.credentials.scram_users();
+    let mut results = CompactArray::new();
+    if reqbody.users.is_empty() {
+        for (user, credentials) in &users {
+            results.push(DescribeUserScramCredentialsResult::new(
+                user.clone(),
+                credential_infos(credentials),
+            ));
+        }
+    } else {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for user in reqbody.users.iter() {
+            *counts.entry(user.name.0.as_str()).or_default() += 1;
+        }
+        let mut described = HashSet::new();
+        for user in reqbody.users.iter() {
+            let name = user.name.0.as_str();
+            if !described.insert(name) {
+                continue;
+            }
+            results.push(match users.get(name) {
+                _ if counts[name] > 1 => DescribeUserScramCredentialsResult::error(
+                    name,
+                    ErrorCode::DuplicateResource,
+                    "Cannot describe SCRAM credentials for the same user twice in a single request",
+                ),
+                Some(credentials) => {
+                    DescribeUserScramCredentialsResult::new(name, credential_infos(credentials))
+                }
+                None => DescribeUserScramCredentialsResult::error(
+                    name,
+                    ErrorCode::ResourceNotFound,
+                    "Attempt to describe a user credential that does not exist",
+                ),
+            });
+        }
+    }
+
+    let body = ResponseBody::DescribeUserScramCredentials(
+        DescribeUserScramCredentialsResponseBody::new(0, results),
+    );
+    let message_size = (header.wire_len() + body.wire_len()) as i32;
+    Ok(KafkaResponse::new(message_size, header, body))
+}
+
+fn credential_infos(credentials: &[(ScramMechanism, u32)]) -> CompactArray<CredentialInfo> {
+    let mut infos = CompactArray::with_capacity(credentials.len());
+    for &(mechanism, iterations) in credentials {
+        infos.push(CredentialInfo::new(
+            mechanism.type_code(),
+            iterations as i32,
+        ));
+    }
+    infos
+}
+
+/// Upserts and deletes SCRAM credentials. Alterations are validated by user: a user with
+/// any invalid alteration gets none applied, the valid users' alterations are then stored
+/// together, through the active controller on nodes of a `KRaft` quorum. The password
+/// arrives salted by the client, only the derived keys are kept.
+///
+/// Authenticators look credentials up at the start of every SCRAM exchange, so the
+/// changes apply to the next authentication, including re-authentication of open
+/// connections. Needs `ALTER` on the cluster, every user fails without it.
+pub(super) fn handle_alter_user_scram_credentials(
+    req: &KafkaRequest,
+    state: &BrokerState,
+    conn: &ConnectionContext,
+) -> anyhow::Result<HandlerResponse> {
+    anyhow::ensure!(
+        req.header.request_api_key == ApiKeys::AlterUserScramCredentials,
+        "request did not specify the AlterUserScramCredentials apikey"
+    );
+    let RequestBody::AlterUserScramCredentials(ref reqbody) = req.body else {
+        bail!("Invalid request body for AlterUserScramCredentials")
+    };
+
+    // the alterations of every user, in the order the users first appear
+    let mut users: Vec<&str> = Vec::new();
+    let mut alterations: BTreeMap<&str, Vec<Alteration>> = BTreeMap::new();
+    let deletions = reqbody
+        .deletions
+        .iter()
+        .map(|d| (d.name.0.as_str(), d.mechanism, None));
+    let upsertions = reqbody.upsertions.iter().map(|u| {
+        let upsertion = (u.iterations, &u.salt.0[..], &u.salted_password.0[..]);
+        (u.name.0.as_str(), u.mechanism, Some(upsertion))
+    });
+    for (user, mechanism, upsertion) in deletions.chain(upsertions) {
+        if !alterations.contains_key(user) {
+            users.push(user);
+        }
+        alterations.entry(user).or_default().push(Alteration {
+            mechanism,
+            upsertion,
+        });
+    }
+
+    let mut results = BTreeMap::new();
+    let mut changes = Vec::new();
+    let authorized = authorize_cluster(state, conn, AclOperation::Alter);
+    for (&user, user_alterations) in &alterations {
+        if !authorized {
+            let message = "Not authorized to alter SCRAM credentials".to_string();
+            results.insert(user, (ErrorCode::ClusterAuthorizationFailed, message));
+            continue;
+        }
+        match validate(state, user, user_alterations) {
+            Ok(user_changes) => changes.extend(user_changes),
+            Err((code, message)) => {
+                results.insert(user, (code, message));
+            }
+        }
+    }
+
+    let mut written = None;
+    if !changes.is_empty() {
+        let altered: Vec<_> = changes
+            .iter()
+            .map(|(mechanism, user, credential)| {
+                let action = if credential.is_some() {
+                    "set"
+                } else {
+                    "deleted"
+                };
+                format!("{mechanism} of {user} {action}")
+            })
+            .collect();
+        let (result, write) = alter_metadata(
+            state,
+            |controller| controller.alter_scram_credentials(&changes),
+            || state.credentials.alter_scram(changes.clone()),
+        );
+        written = write;
+        match result {
+            Ok(()) => info!("Altered SCRAM credentials: {}", altered.join(", ")),
+            Err(error) => {
+                error!("Altering SCRAM credentials failed: {}", error.1);
+                for &user in &users {
+                    results.entry(user).or_insert_with(|| error.clone());
+                }
+            }
+        }
+    }
+
+    let body = |results: &BTreeMap<&str, (ErrorCode, String)>| {
+        let mut entries = CompactArray::with_capacity(users.len());
+        for &user in &users {
+            entries.push(match results.get(user).cloned() {
+                None => AlterUserScramCredentialsResult::new(user),
+                Some((code, message)) => {
+                    AlterUserScramCredentialsResult::error(user, code, message)
+                }
+            });
+        }
+        ResponseBody::AlterUserScramCredentials(AlterUserScramCredentialsResponseBody::new(
+            0, entries,
+        ))
+    };
+    let mut timed_out = results.clone();
+    for &user in &users {
+        timed_out.entry(user).or_insert_with(write_timed_out);
+    }
+    Ok(respond_written(req, written, body(&results), || {
+        body(&timed_out)
+    }))
+}
+
+/// A deletion, or an upsertion with its iterations, salt and salted password
+struct Alteration<'a> {
+    mechanism: i8,
+    upsertion: Option<(i32, &'a [u8], &'a [u8])>,
+}
+
+/// Checks the alterations of a single user and turns them into changes of the store
+fn validate(
+    state: &BrokerState,
+    user: &str,
+    alterations: &[Alteration],
+) -> Result<Vec<ScramCredentialChange>, (ErrorCode, String)> {
+    if user.is_empty() {
+        return Err((
+            ErrorCode::UnacceptableCredential,
+            "Username must not be empty".to_string(),
+        ));
+    }
+    let mut mechanisms = HashSet::new();
+    let mut changes = Vec::with_capacity(alterations.len());
+    for alteration in alterations {
+        let Some(mechanism) = ScramMechanism::from_type_code(alteration.mechanism) else {
+            return Err((
+                ErrorCode::UnsupportedSaslMechanism,
+                format!("Unknown SCRAM mechanism {}", alteration.mechanism),
+            ));
+        };
+        if !mechanisms.insert(mechanism) {
+            return Err((
+                ErrorCode::DuplicateResource,
+                format!("The {mechanism} credential of {user} is altered more than once"),
+            ));
+        }
+        let credential = match alteration.upsertion {
+            None => {
+                if state.credentials.scram(mechanism, user).is_none() {
+                    return Err((
+                        ErrorCode::ResourceNotFound,
+                        "Attempt to delete a user credential that does not exist".to_string(),
+                    ));
+                }
+                None
+            }
+            Some((iterations, salt, salted_password)) => {
+                let iterations = u32::try_from(iterations).unwrap_or(0);
+                if iterations < ScramMechanism::MIN_ITERATIONS {
+                    return Err((
+                        ErrorCode::UnacceptableCredential,
+                        format!(
+                            "Too few iterations, at least {} are needed",
+                            ScramMechanism::MIN_ITERATIONS
+                        ),
+                    ));
+                }
+                if iterations > ScramMechanism::MAX_ITERATIONS {
+                    return Err((
+                        ErrorCode::UnacceptableCredential,
+                        format!(
+                            "Too many iterations, at most {} are allowed",
+                            ScramMechanism::MAX_ITERATIONS
+                        ),
+                    ));
+                }
+                if salt.is_empty() || salted_password.is_empty() {
+                    return Err((
+                        ErrorCode::UnacceptableCredential,
+                        "Salt and salted password must not be empty".to_string(),
+                    ));
+                }
+                Some(ScramCredential::from_salted_password(
+                    mechanism,
+                    salted_password,
+                    salt.to_vec(),
+                    iterations,
+                ))
+            }
+        };
+        changes.push((mechanism, user.to_string(), credential));
+    }
+    Ok(changes)
+}
@@ -1,9 +1,20 @@
 pub mod broker;
 pub mod codec;
+pub mod config;
+pub mod controller;
 pub mod handlers;
+pub mod metadata;
+pub mod metrics;
+pub mod network;
 pub mod primitives;
+pub mod purgatory;
+pub mod quota;
+pub mod raft;
+pub mod replica;
 pub mod request;
 pub mod response;
+pub mod security;
+pub mod storage;
 pub mod types;
 
 // public at the root for the macro crates
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{MetadataImage, TopicImage, records::MetadataRecord};
+use crate::{
+    config::ConfigEntity,
+    primitives::Uuid,
+    quota::QuotaEntity,
+    security::{AclBinding, ScramMechanism},
+};
+
+/// What changed between two [`MetadataImage`]s, built while replaying the records that
+/// lead from one to the other and handed to the [`MetadataPublisher`]s along with the
+/// newer image
+///
+/// [`MetadataPublisher`]: super::MetadataPublisher
+#[derive(Debug, Default)]
+pub struct MetadataDelta {
+    full: bool,
+    changed_brokers: BTreeSet<i32>,
+    changed_topics: BTreeSet<Uuid>,
+    removed_topics: Vec<TopicImage>,
+    changed_configs: BTreeSet<ConfigEntity>,
+    changed_quotas: BTreeSet<QuotaEntity>,
+    added_acls: BTreeMap<Uuid, AclBinding>,
+    removed_acls: BTreeMap<Uuid, AclBinding>,
+    changed_scram: BTreeSet<(ScramMechanism, String)>,
+}
+
+impl MetadataDelta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A delta standing for the whole image, as published after loading a snapshot.
+    /// Publishers bring all of their state in line with the image instead of only
+    /// what changed.
+    pub fn full() -> Self {
+        Self {
+            full: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.full
+            && self.changed_brokers.is_empty()
+            && self.changed_topics.is_empty()
+            && self.removed_topics.is_empty()
+            && self.changed_configs.is_empty()
+            && self.changed_quotas.is_empty()
+            && self.added_acls.is_empty()
+            && self.removed_acls.is_empty()
+            && self.changed_scram.is_empty()
+    }
+
+    /// Brokers registered, fenced or unfenced
+    pub fn changed_brokers(&self) -> &BTreeSet<i32> {
+        &self.changed_brokers
+    }
+
+    /// Topics created or whose partitions changed
+    pub fn changed_topics(&self) -> &BTreeSet<Uuid> {
+        &self.changed_topics
+    }
+
+    /// Topics removed, as they were before
+    pub fn removed_topics(&self) -> &[TopicImage] {
+        &self.removed_topics
+    }
+
+    /// Entities whose configs were set or removed
+    pub fn changed_configs(&self) -> &BTreeSet<ConfigEntity> {
+        &self.changed_configs
+    }
+
+    /// Entities whose client quotas were set or removed
+    pub fn changed_quotas(&self) -> &BTreeSet<QuotaEntity> {
+        &self.changed_quotas
+    }
+
+    pub fn added_acls(&self) -> &BTreeMap<Uuid, AclBinding> {
+        &self.added_acls
+    }
+
+    /// ACLs removed that were in the image before the delta
+    pub fn removed_acls(&self) -> &BTreeMap<Uuid, AclBinding> {
+        &self.removed_acls
+    }
+
+    /// SCRAM credentials set or removed, by mechanism and user
+    pub fn changed_scram_credentials(&self) -> &BTreeSet<(ScramMechanism, String)> {
+        &self.changed_scram
+    }
+
+    /// Applies the record at `offset` to `image`, taking note of what it changes
+    pub fn replay(&mut self, image: &mut MetadataImage, offset: i64, record: &MetadataRecord) {
+        match record {
+            MetadataRecord::RegisterBroker(record) => {
+                self.changed_brokers.insert(record.broker_id);
+            }
+            MetadataRecord::FenceBroker(record) => {
+                self.changed_brokers.insert(record.id);
+            }
+            MetadataRecord::UnfenceBroker(record) => {
+                self.changed_brokers.insert(record.id);
+            }
+            MetadataRecord::Topic(record) => {
+                self.changed_topics.insert(record.topic_id);
+            }
+            MetadataRecord::Partition(record) => {
+                self.changed_topics.insert(record.topic_id);
+            }
+            MetadataRecord::Config(record) => {
+                if let Ok(entity) = record.entity() {
+                    self.changed_configs.insert(entity);
+                }
+            }
+            MetadataRecord::RemoveTopic(record) => {
+                self.changed_topics.remove(&record.topic_id);
+                if let Some(topic) = image.topic(&record.topic_id) {
+                    self.changed_configs
+                        .insert(ConfigEntity::Topic(topic.name.clone()));
+                    self.removed_topics.push(topic.clone());
+                }
+            }
+            MetadataRecord::ClientQuota(record) => {
+                if let Ok(entity) = record.quota_entity() {
+                    self.changed_quotas.insert(entity);
+                }
+            }
+            MetadataRecord::AccessControlEntry(record) => {
+                if let Ok(binding) = record.binding() {
+                    self.added_acls.insert(record.id, binding);
+                }
+            }
+            MetadataRecord::RemoveAccessControlEntry(record) => {
+                if self.added_acls.remove(&record.id).is_none() {
+                    if let Some(binding) = image.acls().get(&record.id) {
+                        self.removed_acls.insert(record.id, binding.clone());
+                    }
+                }
+            }
+            MetadataRecord::UserScramCredential(record) => {
+                if let Ok((mechanism, _)) = record.credential() {
+                    self.changed_scram
+                        .insert((mechanism, record.name.0.clone()));
+                }
+            }
+            MetadataRecord::RemoveUserScramCredential(record) => {
+                if let Some(mechanism) = record.scram_mechanism() {
+                    self.changed_scram
+                        .insert((mechanism, record.name.0.clone()));
+                }
+            }
+            MetadataRecord::FeatureLevel(_) | MetadataRecord::NoOp(_) => {}
+        }
+        image.apply(offset, record);
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::warn;
+
+use super::records::{
+    AccessControlEntryRecord, ClientQuotaRecord, ConfigRecord, FeatureLevelRecord, MetadataRecord,
+    PartitionRecord, RegisterBrokerRecord, TopicRecord, UserScramCredentialRecord,
+};
+use crate::{
+    config::ConfigEntity,
+    primitives::Uuid,
+    quota::QuotaEntity,
+    security::{AclBinding, ScramCredential, ScramMechanism},
+};
+
+/// The state of the cluster built by replaying the metadata log: its registered brokers,
+/// its topics and their partitions, the dynamic configs, client quotas, ACLs and SCRAM
+/// credentials, and the finalized feature levels
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataImage {
+    /// Offset of the last record applied, -1 before the first one
+    pub offset: i64,
+    brokers: BTreeMap<i32, BrokerRegistration>,
+    topics: BTreeMap<Uuid, TopicImage>,
+    topic_ids: HashMap<String, Uuid>,
+    features: BTreeMap<String, i16>,
+    configs: BTreeMap<ConfigEntity, BTreeMap<String, String>>,
+    quotas: BTreeMap<QuotaEntity, BTreeMap<String, f64>>,
+    acls: BTreeMap<Uuid, AclBinding>,
+    scram: BTreeMap<(ScramMechanism, String), ScramCredential>,
+}
+
+/// A broker registered with the controller, and whether it is fenced. Fenced brokers
+/// are not sent heartbeats for long enough, or have not caught up with the metadata log
+/// yet, and lead no partitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokerRegistration {
+    pub id: i32,
+    /// The offset of the record registering the broker, which heartbeats have to carry
+    pub epoch: i64,
+    pub incarnation_id: Uuid,
+    pub endpoints: Vec<BrokerEndpoint>,
+    /// The range of versions the broker supports of every feature it knows
+    pub features: BTreeMap<String, (i16, i16)>,
+    pub rack: Option<String>,
+    pub fenced: bool,
+    pub in_controlled_shutdown: bool,
+    /// The directory ids of the broker's log dirs
+    pub log_dirs: Vec<Uuid>,
+}
+
+/// A listener of a [`BrokerRegistration`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokerEndpoint {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub security_protocol: i16,
+}
+
+/// A topic of the [`MetadataImage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicImage {
+    pub name: String,
+    pub id: Uuid,
+    pub partitions: BTreeMap<i32, PartitionRegistration>,
+}
+
+/// Where a partition is hosted and who leads it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionRegistration {
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    /// -1 without a leader
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+}
+
+impl PartitionRegistration {
+    /// The record registering the partition as it is
+    pub fn to_record(&self, partition_id: i32, topic_id: Uuid) -> PartitionRecord {
+        let mut record = PartitionRecord::new(partition_id, topic_id, self.replicas.clone());
+        record.isr = self.isr.clone().into();
+        record.leader = self.leader;
+        record.leader_epoch = self.leader_epoch;
+        record.partition_epoch = self.partition_epoch;
+        record
+    }
+}
+
+impl MetadataImage {
+    pub fn new() -> Self {
+        Self {
+            offset: -1,
+            ..Self::default()
+        }
+    }
+
+    pub fn broker(&self, id: i32) -> Option<&BrokerRegistration> {
+        self.brokers.get(&id)
+    }
+
+    pub fn brokers(&self) -> impl Iterator<Item = &BrokerRegistration> {
+        self.brokers.values()
+    }
+
+    /// Whether broker `id` is fenced, as are brokers that are not registered
+    pub fn is_fenced(&self, id: i32) -> bool {
+        self.brokers.get(&id).map_or(true, |broker| broker.fenced)
+    }
+
+    pub fn topic(&self, id: &Uuid) -> Option<&TopicImage> {
+        self.topics.get(id)
+    }
+
+    pub fn topic_by_name(&self, name: &str) -> Option<&TopicImage> {
+        self.topic_ids.get(name).and_then(|id| self.topics.get(id))
+    }
+
+    pub fn topics(&self) -> impl Iterator<Item = &TopicImage> {
+        self.topics.values()
+    }
+
+    /// The level of a finalized feature, `None` if it is not
+    pub fn feature_level(&self, name: &str) -> Option<i16> {
+        self.features.get(name).copied()
+    }
+
+    /// Every finalized feature and its level
+    pub fn finalized_features(&self) -> &BTreeMap<String, i16> {
+        &self.features
+    }
+
+    /// The dynamic configs set on `entity`
+    pub fn configs(&self, entity: &ConfigEntity) -> Option<&BTreeMap<String, String>> {
+        self.configs.get(entity)
+    }
+
+    /// The entities with dynamic configs set
+    pub fn config_entities(&self) -> impl Iterator<Item = &ConfigEntity> {
+        self.configs.keys()
+    }
+
+    /// The client quotas set on `entity`
+    pub fn quotas(&self, entity: &QuotaEntity) -> Option<&BTreeMap<String, f64>> {
+        self.quotas.get(entity)
+    }
+
+    /// The entities with client quotas set
+    pub fn quota_entities(&self) -> impl Iterator<Item = &QuotaEntity> {
+        self.quotas.keys()
+    }
+
+    /// The ACLs of the cluster by the id of the record that added them
+    pub fn acls(&self) -> &BTreeMap<Uuid, AclBinding> {
+        &self.acls
+    }
+
+    /// The SCRAM credentials by mechanism and user
+    pub fn scram_credentials(&self) -> &BTreeMap<(ScramMechanism, String), ScramCredential> {
+        &self.scram
+    }
+
+    /// The records rebuilding the image when applied in order to an empty one, as
+    /// written to snapshots of the metadata log
+    pub fn records(&self) -> Vec<MetadataRecord> {
+        let mut records = Vec::new();
+        for (name, &level) in &self.features {
+            records.push(MetadataRecord::FeatureLevel(FeatureLevelRecord::new(
+                name.clone(),
+                level,
+            )));
+        }
+        for broker in self.brokers.values() {
+            records.push(MetadataRecord::RegisterBroker(RegisterBrokerRecord::new(
+                broker,
+            )));
+        }
+        for topic in self.topics.values() {
+            records.push(MetadataRecord::Topic(TopicRecord::new(
+                topic.name.clone(),
+                topic.id,
+            )));
+            for (&index, partition) in &topic.partitions {
+                records.push(MetadataRecord::Partition(
+                    partition.to_record(index, topic.id),
+                ));
+            }
+        }
+        for (entity, configs) in &self.configs {
+            for (name, value) in configs {
+                records.push(MetadataRecord::Config(ConfigRecord::new(
+                    entity,
+                    name.clone(),
+                    Some(value.clone()),
+                )));
+            }
+        }
+        for (entity, quotas) in &self.quotas {
+            for (key, &value) in quotas {
+                records.push(MetadataRecord::ClientQuota(ClientQuotaRecord::new(
+                    entity,
+                    key.clone(),
+                    Some(value),
+                )));
+            }
+        }
+        for (&id, binding) in &self.acls {
+            records.push(MetadataRecord::AccessControlEntry(
+                AccessControlEntryRecord::new(id, binding),
+            ));
+        }
+        for ((mechanism, user), credential) in &self.scram {
+            records.push(MetadataRecord::UserScramCredential(
+                UserScramCredentialRecord::new(user.clone(), *mechanism, credential),
+            ));
+        }
+        records
+    }
+
+    /// Fences or unfences the registration of broker `id` with `epoch`, records of an
+    /// older registration are skipped
+    fn set_fenced(&mut self, offset: i64, id: i32, epoch: i64, fenced: bool) {
+        match self.brokers.get_mut(&id) {
+            Some(broker) if broker.epoch == epoch => {
+                broker.fenced = fenced;
+            }
+            _ => warn!(
+                "Fencing record at {offset} is for unknown registration {epoch} of broker {id}"
+            ),
+        }
+    }
+
+    /// Applies the record at `offset` of the metadata log. Records referring to topics
+    /// that do not exist are logged and skipped, the log is the source of truth.
+    pub fn apply(&mut self, offset: i64, record: &MetadataRecord) {
+        self.offset = offset;
+        match record {
+            MetadataRecord::RegisterBroker(record) => {
+                self.brokers.insert(record.broker_id, record.registration());
+            }
+            MetadataRecord::FenceBroker(record) => {
+                self.set_fenced(offset, record.id, record.epoch, true);
+            }
+            MetadataRecord::UnfenceBroker(record) => {
+                self.set_fenced(offset, record.id, record.epoch, false);
+            }
+            MetadataRecord::Topic(record) => {
+                self.topic_ids
+                    .insert(record.name.0.clone(), record.topic_id);
+                self.topics.insert(
+                    record.topic_id,
+                    TopicImage {
+                        name: record.name.0.clone(),
+                        id: record.topic_id,
+                        partitions: BTreeMap::new(),
+                    },
+                );
+            }
+            MetadataRecord::Partition(record) => {
+                let Some(topic) = self.topics.get_mut(&record.topic_id) else {
+                    warn!(
+                        "Partition record at {offset} is for unknown topic {}",
+                        record.topic_id
+                    );
+                    return;
+                };
+                topic.partitions.insert(
+                    record.partition_id,
+                    PartitionRegistration {
+                        replicas: record.replicas.iter().copied().collect(),
+                        isr: record.isr.iter().copied().collect(),
+                        leader: record.leader,
+                        leader_epoch: record.leader_epoch,
+                        partition_epoch: record.partition_epoch,
+                    },
+                );
+            }
+            MetadataRecord::Config(record) => match record.entity() {
+                Ok(entity) => {
+                    let configs = self.configs.entry(entity.clone()).or_default();
+                    match &record.value.0 {
+                        Some(value) => configs.insert(record.name.0.clone(), value.clone()),
+                        None => configs.remove(&record.name.0),
+                    };
+                    if configs.is_empty() {
+                        self.configs.remove(&entity);
+                    }
+                }
+                Err(e) => warn!("Skipping config record at {offset}: {e:#}"),
+            },
+            MetadataRecord::RemoveTopic(record) => {
+                if let Some(topic) = self.topics.remove(&record.topic_id) {
+                    self.topic_ids.remove(&topic.name);
+                    self.configs.remove(&ConfigEntity::Topic(topic.name));
+                }
+            }
+            MetadataRecord::FeatureLevel(record) => {
+                if record.feature_level == 0 {
+                    self.features.remove(&record.name.0);
+                } else {
+                    self.features
+                        .insert(record.name.0.clone(), record.feature_level);
+                }
+            }
+            MetadataRecord::ClientQuota(record) => match record.quota_entity() {
+                Ok(entity) => {
+                    let quotas = self.quotas.entry(entity.clone()).or_default();
+                    if record.remove.is_true() {
+                        quotas.remove(&record.key.0);
+                    } else {
+                        quotas.insert(record.key.0.clone(), record.value);
+                    }
+                    if quotas.is_empty() {
+                        self.quotas.remove(&entity);
+                    }
+                }
+                Err(e) => warn!("Skipping client quota record at {offset}: {e:#}"),
+            },
+            MetadataRecord::NoOp(_) => {}
+            MetadataRecord::AccessControlEntry(record) => match record.binding() {
+                Ok(binding) => {
+                    self.acls.insert(record.id, binding);
+                }
+                Err(e) => warn!("Skipping ACL record at {offset}: {e:#}"),
+            },
+            MetadataRecord::RemoveAccessControlEntry(record) => {
+                self.acls.remove(&record.id);
+            }
+            MetadataRecord::UserScramCredential(record) => match record.credential() {
+                Ok((mechanism, credential)) => {
+                    self.scram
+                        .insert((mechanism, record.name.0.clone()), credential);
+                }
+                Err(e) => warn!("Skipping SCRAM credential record at {offset}: {e:#}"),
+            },
+            MetadataRecord::RemoveUserScramCredential(record) => {
+                if let Some(mechanism) = record.scram_mechanism() {
+                    self.scram.remove(&(mechanism, record.name.0.clone()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metadata::records::{
+            RemoveTopicRecord, RemoveUserScramCredentialRecord, UserScramCredentialRecord,
+        },
+        security::{
+            AccessControlEntry, AclOperation, AclPermissionType, PatternType, ResourcePattern,
+            ResourceType, ScramCredential,
+        },
+    };
+
+    #[test]
+    fn test_apply() {
+        let id = Uuid::random();
+        let mut image = MetadataImage::new();
+        image.apply(0, &MetadataRecord::Topic(TopicRecord::new("foo", id)));
+        let partition = PartitionRecord::new(0, id, vec![1, 2]);
+        image.apply(1, &MetadataRecord::Partition(partition));
+        // partitions of unknown topics are skipped
+        let partition = PartitionRecord::new(0, Uuid::random(), vec![1]);
+        image.apply(2, &MetadataRecord::Partition(partition));
+
+        let topic = image.topic_by_name("foo").unwrap();
+        assert_eq!(id, topic.id);
+        assert_eq!(1, topic.partitions[&0].leader);
+        assert_eq!(1, image.topics().count());
+        assert_eq!(2, image.offset);
+
+        image.apply(3, &MetadataRecord::RemoveTopic(RemoveTopicRecord::new(id)));
+        assert!(image.topic_by_name("foo").is_none());
+        assert!(image.topic(&id).is_none());
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let id = Uuid::random();
+        let topic = ConfigEntity::Topic("foo".to_string());
+        let user = QuotaEntity::from_components([("user", Some("alice"))]).unwrap();
+        let binding = AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: "foo".to_string(),
+                pattern_type: PatternType::Literal,
+            },
+            entry: AccessControlEntry {
+                principal: "User:alice".to_string(),
+                host: "*".to_string(),
+                operation: AclOperation::Read,
+                permission_type: AclPermissionType::Allow,
+            },
+        };
+        let mut partition = PartitionRecord::new(0, id, vec![1, 2]);
+        partition.isr = vec![2].into();
+        partition.leader = 2;
+        partition.leader_epoch = 3;
+        let records = [
+            MetadataRecord::FeatureLevel(FeatureLevelRecord::new("metadata.version", 7)),
+            MetadataRecord::Topic(TopicRecord::new("foo", id)),
+            MetadataRecord::Partition(partition),
+            MetadataRecord::Config(ConfigRecord::new(&topic, "retention.ms", Some("1".into()))),
+            MetadataRecord::Config(ConfigRecord::new(
+                &topic,
+                "cleanup.policy",
+                Some("x".into()),
+            )),
+            MetadataRecord::Config(ConfigRecord::new(&topic, "cleanup.policy", None)),
+            MetadataRecord::ClientQuota(ClientQuotaRecord::new(
+                &user,
+                "producer_byte_rate",
+                Some(1024.0),
+            )),
+            MetadataRecord::AccessControlEntry(AccessControlEntryRecord::new(
+                Uuid::random(),
+                &binding,
+            )),
+            MetadataRecord::UserScramCredential(UserScramCredentialRecord::new(
+                "alice",
+                ScramMechanism::Sha256,
+                &ScramCredential::new(ScramMechanism::Sha256, "alice-secret", 4096),
+            )),
+            MetadataRecord::UserScramCredential(UserScramCredentialRecord::new(
+                "alice",
+                ScramMechanism::Sha512,
+                &ScramCredential::new(ScramMechanism::Sha512, "alice-secret", 4096),
+            )),
+            MetadataRecord::RemoveUserScramCredential(RemoveUserScramCredentialRecord::new(
+                "alice",
+                ScramMechanism::Sha512,
+            )),
+        ];
+        let mut image = MetadataImage::new();
+        for (offset, record) in (0..).zip(&records) {
+            image.apply(offset, record);
+        }
+        assert_eq!(1, image.configs(&topic).unwrap().len());
+        assert_eq!(
+            Some(&1024.0),
+            image.quotas(&user).unwrap().get("producer_byte_rate")
+        );
+        assert_eq!(1, image.acls().len());
+        let credentials: Vec<_> = image.scram_credentials().keys().collect();
+        assert_eq!(
+            vec![&(ScramMechanism::Sha256, "alice".to_string())],
+            credentials
+        );
+
+        let mut rebuilt = MetadataImage::new();
+        for record in image.records() {
+            rebuilt.apply(image.offset, &record);
+        }
+        assert_eq!(image, rebuilt);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use tracing::{debug, info, warn};
+
+use super::{MetadataDelta, MetadataImage, MetadataPublisher, MetadataRecord};
+use crate::{
+    WireLen,
+    config::BrokerConfig,
+    raft::RaftClient,
+    types::{RecordBatch, SnapshotId},
+};
+
+/// Most bytes of the metadata log replayed at once
+const LOAD_MAX_BYTES: usize = 1024 * 1024;
+/// Pause after failing to read the metadata log before trying again
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Keeps the metadata image of the node up to date with the metadata log: loads the
+/// latest snapshot on start, replays the records the quorum commits after it, hands
+/// every change to the publishers, and snapshots the image once enough of the log was
+/// replayed since the last one
+pub struct MetadataLoader {
+    client: Arc<RaftClient>,
+    image: Arc<RwLock<MetadataImage>>,
+    publishers: Vec<Box<dyn MetadataPublisher>>,
+    max_bytes_between_snapshots: u64,
+    /// Zero to only snapshot by size
+    max_snapshot_interval: Duration,
+}
+
+/// How far the loader got, and how much it replayed since the last snapshot
+#[derive(Debug, Clone, Copy)]
+struct Progress {
+    next_offset: i64,
+    /// Epoch and timestamp of the last batch replayed
+    last_epoch: i32,
+    last_timestamp: i64,
+    bytes_since_snapshot: u64,
+    last_snapshot: Instant,
+}
+
+impl MetadataLoader {
+    pub fn new(
+        client: Arc<RaftClient>,
+        image: Arc<RwLock<MetadataImage>>,
+        config: &BrokerConfig,
+    ) -> Self {
+        Self {
+            client,
+            image,
+            publishers: Vec::new(),
+            max_bytes_between_snapshots: config.metadata_log_max_record_bytes_between_snapshots,
+            max_snapshot_interval: config.metadata_log_max_snapshot_interval,
+        }
+    }
+
+    /// Adds a publisher, handed the changes in the order publishers were added
+    #[must_use]
+    pub fn with_publisher(mut self, publisher: impl MetadataPublisher + 'static) -> Self {
+        self.publishers.push(Box::new(publisher));
+        self
+    }
+
+    /// Runs for as long as the task is not aborted
+    pub async fn run(self) {
+        let loader = Arc::new(self);
+        let mut progress = loop {
+            match blocking(&loader, |l| l.load_snapshot()).await {
+                Ok(progress) => break progress,
+                Err(e) => {
+                    warn!("Loading the latest metadata snapshot failed: {e:#}");
+                    tokio::time::sleep(RETRY_BACKOFF).await;
+                }
+            }
+        };
+        let mut high_watermark = loader.client.subscribe();
+        loop {
+            let committed = *high_watermark.borrow_and_update();
+            let loaded = if progress.next_offset < committed {
+                blocking(&loader, move |l| l.load(progress)).await
+            } else {
+                Ok(progress)
+            };
+            match loaded {
+                Ok(loaded) if loaded.next_offset > progress.next_offset => progress = loaded,
+                Ok(_) => {
+                    let wait = loader.snapshot_due_in(&progress);
+                    tokio::select! {
+                        changed = high_watermark.changed() => {
+                            if changed.is_err() {
+                                return;
+                            }
+                        }
+                        () = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {
+                            match blocking(&loader, move |l| l.snapshot(progress)).await {
+                                Ok(snapshotted) => progress = snapshotted,
+                                Err(e) => {
+                                    warn!("Snapshotting the metadata image failed: {e:#}");
+                                    tokio::time::sleep(RETRY_BACKOFF).await;
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "Loading the metadata log from {} failed: {e:#}",
+                        progress.next_offset
+                    );
+                    tokio::time::sleep(RETRY_BACKOFF).await;
+                }
+            }
+        }
+    }
+
+    /// Replaces the image with the latest snapshot and publishes all of it, returning
+    /// where to replay the log from
+    fn load_snapshot(&self) -> anyhow::Result<Progress> {
+        let mut progress = Progress {
+            next_offset: 0,
+            last_epoch: 0,
+            last_timestamp: -1,
+            bytes_since_snapshot: 0,
+            last_snapshot: Instant::now(),
+        };
+        let snapshots = self.client.snapshots();
+        let Some(id) = snapshots.latest()? else {
+            return Ok(progress);
+        };
+        let batches = snapshots.read(&id)?;
+        let mut image = MetadataImage::new();
+        let mut delta = MetadataDelta::full();
+        replay(&mut delta, &mut image, &batches, 0)?;
+        image.offset = id.end_offset - 1;
+        info!(
+            "Loaded the metadata snapshot ending at {} in epoch {}",
+            id.end_offset, id.epoch
+        );
+        self.publish(&delta, &image);
+        *self.image.write().unwrap() = image;
+
+        progress.next_offset = id.end_offset;
+        progress.last_epoch = id.epoch;
+        Ok(progress)
+    }
+
+    /// Replays the committed batches from where `progress` left off, publishes the
+    /// changes, and snapshots the image if enough was replayed since the last snapshot
+    fn load(&self, progress: Progress) -> anyhow::Result<Progress> {
+        let batches = self
+            .client
+            .read_committed(progress.next_offset, LOAD_MAX_BYTES)?;
+        let Some(last) = batches.last() else {
+            return Ok(progress);
+        };
+        let mut loaded = Progress {
+            last_epoch: last.partition_leader_epoch,
+            last_timestamp: last.max_timestamp,
+            bytes_since_snapshot: progress.bytes_since_snapshot
+                + batches.iter().map(|b| b.wire_len() as u64).sum::<u64>(),
+            ..progress
+        };
+
+        let mut delta = MetadataDelta::new();
+        loaded.next_offset = replay(
+            &mut delta,
+            &mut self.image.write().unwrap(),
+            &batches,
+            progress.next_offset,
+        )?;
+        if !delta.is_empty() {
+            self.publish(&delta, &self.image.read().unwrap());
+        }
+        debug!("Loaded the metadata log up to {}", loaded.next_offset);
+
+        if self.snapshot_due_in(&loaded) == Some(Duration::ZERO) {
+            match self.snapshot(loaded) {
+                Ok(snapshotted) => loaded = snapshotted,
+                // retried after the next load
+                Err(e) => warn!("Snapshotting the metadata image failed: {e:#}"),
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) {
+        for publisher in &self.publishers {
+            if let Err(e) = publisher.publish(delta, image) {
+                warn!(
+                    "Publishing the metadata up to {} to {} failed: {e:#}",
+                    image.offset,
+                    publisher.name()
+                );
+            }
+        }
+    }
+
+    /// How long until the image is to be snapshotted, `None` if nothing was replayed
+    /// since the last snapshot
+    fn snapshot_due_in(&self, progress: &Progress) -> Option<Duration> {
+        if progress.bytes_since_snapshot == 0 {
+            return None;
+        }
+        if progress.bytes_since_snapshot >= self.max_bytes_between_snapshots {
+            return Some(Duration::ZERO);
+        }
+        if self.max_snapshot_interval.is_zero() {
+            return None;
+        }
+        Some(
+            self.max_snapshot_interval
+                .saturating_sub(progress.last_snapshot.elapsed()),
+        )
+    }
+
+    /// Snapshots the image as of `progress` and removes the older snapshots
+    fn snapshot(&self, progress: Progress) -> anyhow::Result<Progress> {
+        let id = SnapshotId::new(progress.next_offset, progress.last_epoch);
+        let values = self
+            .image
+            .read()
+            .unwrap()
+            .records()
+            .iter()
+            .map(MetadataRecord::to_value)
+            .collect::<anyhow::Result<_>>()?;
+        let snapshots = self.client.snapshots();
+        snapshots.write(&id, values, progress.last_timestamp)?;
+        snapshots.delete_before(&id)?;
+        Ok(Progress {
+            bytes_since_snapshot: 0,
+            last_snapshot: Instant::now(),
+            ..progress
+        })
+    }
+}
+
+/// Runs a loader call, which reads and writes files, on a blocking thread
+async fn blocking<T, F>(loader: &Arc<MetadataLoader>, f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&MetadataLoader) -> anyhow::Result<T> + Send + 'static,
+{
+    let loader = Arc::clone(loader);
+    tokio::task::spawn_blocking(move || f(&loader))
+        .await
+        .context("Metadata loader call panicked")?
+}
+
+/// Replays the records of `batches` from `offset` on into `image`, skipping control
+/// batches, and returns the offset after the last batch
+fn replay(
+    delta: &mut MetadataDelta,
+    image: &mut MetadataImage,
+    batches: &[RecordBatch],
+    offset: i64,
+) -> anyhow::Result<i64> {
+    let mut next_offset = offset;
+    for batch in batches {
+        next_offset = next_offset.max(batch.next_offset());
+        if batch.is_control() {
+            continue;
+        }
+        for record in &batch.records()? {
+            let record_offset = batch.offset_of(record);
+            if record_offset < offset {
+                continue;
+            }
+            let Some(value) = &record.value else {
+                continue;
+            };
+            let decoded = MetadataRecord::from_value(value)
+                .with_context(|| format!("Reading the metadata record at {record_offset}"))?;
+            match decoded {
+                Some(decoded) => delta.replay(image, record_offset, &decoded),
+                None => debug!("Skipping metadata record of an unknown type at {record_offset}"),
+            }
+        }
+    }
+    Ok(next_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metadata::{PartitionRecord, TopicRecord},
+        primitives::Uuid,
+        raft::{LeaderChangeMessage, RaftConfig},
+    };
+
+    #[test]
+    fn test_replay() {
+        let id = Uuid::random();
+        let leader_change = LeaderChangeMessage::new(1, &[1], &[1]).to_batch(0).unwrap();
+        let records = vec![
+            MetadataRecord::Topic(TopicRecord::new("foo", id))
+                .to_record(0)
+                .unwrap(),
+            MetadataRecord::Partition(PartitionRecord::new(0, id, vec![1]))
+                .to_record(1)
+                .unwrap(),
+        ];
+        let mut batch = RecordBatch::new(1, 0, records);
+        batch.base_offset = 1;
+
+        let mut image = MetadataImage::new();
+        let mut delta = MetadataDelta::new();
+        assert_eq!(
+            3,
+            replay(&mut delta, &mut image, &[leader_change, batch], 0).unwrap()
+        );
+        assert_eq!(2, image.offset);
+        assert_eq!(1, image.topic_by_name("foo").unwrap().partitions.len());
+        assert!(delta.changed_topics().contains(&id));
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = || {
+            let config = RaftConfig {
+                node_id: 1,
+                cluster_id: "loader-test".to_string(),
+                voters: [(1, "localhost:0".to_string())].into(),
+                election_timeout: Duration::from_secs(1),
+                fetch_timeout: Duration::from_secs(1),
+                election_backoff_max: Duration::from_secs(1),
+                request_timeout: Duration::from_secs(1),
+            };
+            Arc::new(RaftClient::open(config, dir.path()).unwrap())
+        };
+        let config = BrokerConfig {
+            metadata_log_max_record_bytes_between_snapshots: 1,
+            ..BrokerConfig::default()
+        };
+
+        // a single voter elects itself and commits its own appends
+        let client = open();
+        client.become_candidate().unwrap();
+        assert!(client.is_leader());
+        let id = Uuid::random();
+        let records = vec![
+            MetadataRecord::Topic(TopicRecord::new("foo", id))
+                .to_record(0)
+                .unwrap(),
+        ];
+        let end = client.append(records).unwrap() + 1;
+        let image = Arc::new(RwLock::new(MetadataImage::new()));
+        let loader = MetadataLoader::new(Arc::clone(&client), Arc::clone(&image), &config);
+        let progress = loader.load_snapshot().unwrap();
+        let progress = loader.load(progress).unwrap();
+        assert!(progress.next_offset >= end);
+        assert_eq!(0, progress.bytes_since_snapshot);
+        let snapshot = client.snapshots().latest().unwrap().unwrap();
+        assert_eq!(progress.next_offset, snapshot.end_offset);
+        drop(loader);
+        drop(client);
+
+        let client = open();
+        let restored = Arc::new(RwLock::new(MetadataImage::new()));
+        let loader = MetadataLoader::new(client, Arc::clone(&restored), &config);
+        let progress = loader.load_snapshot().unwrap();
+        assert_eq!(snapshot.end_offset, progress.next_offset);
+        assert_eq!(
+            id,
+            restored.read().unwrap().topic_by_name("foo").unwrap().id
+        );
+        assert_eq!(snapshot.end_offset - 1, restored.read().unwrap().offset);
+    }
+}
@@ -0,0 +1,28 @@
+//! The cluster metadata kept in the `__cluster_metadata` log replicated by the KRaft
+//! quorum. Every change to the cluster is a [`MetadataRecord`] appended by the active
+//! controller, and every node builds its [`MetadataImage`] by replaying the committed
+//! records in order. Nodes snapshot their image every so often, so a restart only
+//! replays the log after the latest snapshot, and [`MetadataPublisher`]s keep the
+//! topics, partition leaders, configs, quotas, ACLs and SCRAM credentials of the node in
+//! line with the image.
+mod delta;
+mod image;
+mod loader;
+mod publishers;
+mod records;
+
+pub use delta::MetadataDelta;
+pub use image::{
+    BrokerEndpoint, BrokerRegistration, MetadataImage, PartitionRegistration, TopicImage,
+};
+pub use loader::MetadataLoader;
+pub use publishers::{
+    AclsPublisher, ConfigsPublisher, ControllerPublisher, MetadataPublisher, PartitionsPublisher,
+    QuotasPublisher, ScramPublisher, TopicsPublisher,
+};
+pub use records::{
+    AccessControlEntryRecord, BrokerEndpointRecord, BrokerFeatureRecord, ClientQuotaRecord,
+    ConfigRecord, EntityData, FeatureLevelRecord, FenceBrokerRecord, MetadataRecord, NoOpRecord,
+    PartitionRecord, RegisterBrokerRecord, RemoveAccessControlEntryRecord, RemoveTopicRecord,
+    RemoveUserScramCredentialRecord, TopicRecord, UnfenceBrokerRecord, UserScramCredentialRecord,
+};
@@ -0,0 +1,484 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::Context;
+use tracing::{debug, info};
+
+use super::{MetadataDelta, MetadataImage};
+use crate::{
+    config::{ConfigEntity, ConfigManager, SecurityProtocol},
+    handlers::DelayedControllerWrite,
+    purgatory::Purgatory,
+    quota::{QuotaEntity, QuotaManager, QuotaOp, QuotaType},
+    raft::RaftClient,
+    replica::{PartitionAssignment, ReplicaManager},
+    security::{AclBinding, AclBindingFilter, Authorizer, CredentialStore},
+    storage::{LogManager, TopicPartition},
+};
+
+/// A subsystem kept in line with the metadata image. Publishers are handed every delta
+/// the loader replays, in order, along with the image it leads to.
+///
+/// A full delta hands over everything in the image, as after loading a snapshot.
+/// Publishers then replace what they had with it, dropping what the image does not
+/// know of.
+pub trait MetadataPublisher: Send + Sync {
+    /// Named in the logs when publishing fails
+    fn name(&self) -> &'static str;
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()>;
+}
+
+/// Creates the logs of the partitions the node hosts a replica of
+#[derive(Debug)]
+pub struct TopicsPublisher {
+    node_id: i32,
+    log_manager: Arc<LogManager>,
+}
+
+impl TopicsPublisher {
+    pub fn new(node_id: i32, log_manager: Arc<LogManager>) -> Self {
+        Self {
+            node_id,
+            log_manager,
+        }
+    }
+}
+
+impl MetadataPublisher for TopicsPublisher {
+    fn name(&self) -> &'static str {
+        "topics"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let topics: Vec<_> = if delta.is_full() {
+            image.topics().collect()
+        } else {
+            delta
+                .changed_topics()
+                .iter()
+                .filter_map(|id| image.topic(id))
+                .collect()
+        };
+        for topic in topics {
+            for (&index, partition) in &topic.partitions {
+                if partition.replicas.contains(&self.node_id) {
+                    let tp = TopicPartition::new(topic.name.clone(), index);
+                    self.log_manager
+                        .get_or_create_log(&tp)
+                        .with_context(|| format!("Creating the log of {tp:?}"))?;
+                }
+            }
+        }
+        // logs are never deleted, the data of removed topics stays on disk
+        for topic in delta.removed_topics() {
+            info!("Topic {} ({}) was removed", topic.name, topic.id);
+        }
+        Ok(())
+    }
+}
+
+/// Makes the node the leader or a follower of the partitions it hosts a replica of, as
+/// the controller elected their leaders. Followers fetch from the plaintext endpoint of
+/// their leader's registration.
+pub struct PartitionsPublisher {
+    replica_manager: Arc<ReplicaManager>,
+    /// Called with every partition whose leadership changed, to retry the requests
+    /// waiting on it
+    on_change: Box<dyn Fn(&TopicPartition) + Send + Sync>,
+}
+
+impl PartitionsPublisher {
+    pub fn new<F>(replica_manager: Arc<ReplicaManager>, on_change: F) -> Self
+    where
+        F: Fn(&TopicPartition) + Send + Sync + 'static,
+    {
+        Self {
+            replica_manager,
+            on_change: Box::new(on_change),
+        }
+    }
+}
+
+impl MetadataPublisher for PartitionsPublisher {
+    fn name(&self) -> &'static str {
+        "partitions"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let topics: Vec<_> = if delta.is_full() {
+            image.topics().collect()
+        } else {
+            delta
+                .changed_topics()
+                .iter()
+                .filter_map(|id| image.topic(id))
+                .collect()
+        };
+        let node_id = self.replica_manager.node_id();
+        let mut assignments = Vec::new();
+        for topic in topics {
+            for (&index, partition) in &topic.partitions {
+                if !partition.replicas.contains(&node_id) {
+                    continue;
+                }
+                let assignment = PartitionAssignment {
+                    leader: partition.leader,
+                    leader_epoch: partition.leader_epoch,
+                    partition_epoch: partition.partition_epoch,
+                    replicas: partition.replicas.clone(),
+                    isr: partition.isr.clone(),
+                };
+                assignments.push((TopicPartition::new(topic.name.clone(), index), assignment));
+            }
+        }
+        if assignments.is_empty() && delta.changed_brokers().is_empty() {
+            return Ok(());
+        }
+
+        let plaintext = SecurityProtocol::Plaintext.id();
+        let addresses = image
+            .brokers()
+            .filter_map(|broker| {
+                let endpoint = broker
+                    .endpoints
+                    .iter()
+                    .find(|e| e.security_protocol == plaintext)?;
+                Some((broker.id, format!("{}:{}", endpoint.host, endpoint.port)))
+            })
+            .collect();
+        for tp in self
+            .replica_manager
+            .apply_leaderships(assignments, addresses)
+        {
+            (self.on_change)(&tp);
+        }
+        Ok(())
+    }
+}
+
+/// Hands the dynamic topic and broker configs to the [`ConfigManager`]
+#[derive(Debug)]
+pub struct ConfigsPublisher {
+    configs: Arc<ConfigManager>,
+}
+
+impl ConfigsPublisher {
+    pub fn new(configs: Arc<ConfigManager>) -> Self {
+        Self { configs }
+    }
+}
+
+impl MetadataPublisher for ConfigsPublisher {
+    fn name(&self) -> &'static str {
+        "configs"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let entities: BTreeSet<ConfigEntity> = if delta.is_full() {
+            // the entities missing from the image lose their configs
+            let mut entities: BTreeSet<_> = self.configs.entities().into_iter().collect();
+            entities.extend(image.config_entities().cloned());
+            entities
+        } else {
+            delta.changed_configs().clone()
+        };
+        let changes: Vec<_> = entities
+            .into_iter()
+            .map(|entity| {
+                let values = image.configs(&entity).cloned().unwrap_or_default();
+                (entity, values)
+            })
+            .collect();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        debug!("Publishing the configs of {} entities", changes.len());
+        self.configs.alter(changes)
+    }
+}
+
+/// Hands the client quotas to the [`QuotaManager`]
+#[derive(Debug)]
+pub struct QuotasPublisher {
+    quotas: Arc<QuotaManager>,
+}
+
+impl QuotasPublisher {
+    pub fn new(quotas: Arc<QuotaManager>) -> Self {
+        Self { quotas }
+    }
+}
+
+impl MetadataPublisher for QuotasPublisher {
+    fn name(&self) -> &'static str {
+        "quotas"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let entities: BTreeSet<QuotaEntity> = if delta.is_full() {
+            // the entities missing from the image lose their quotas
+            let mut entities: BTreeSet<_> = self
+                .quotas
+                .describe(&[], false)?
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            entities.extend(image.quota_entities().cloned());
+            entities
+        } else {
+            delta.changed_quotas().clone()
+        };
+        if entities.is_empty() {
+            return Ok(());
+        }
+        let changes = entities
+            .into_iter()
+            .map(|entity| {
+                let values = image.quotas(&entity);
+                let ops = QuotaType::ALL
+                    .into_iter()
+                    .map(|quota_type| QuotaOp {
+                        key: quota_type.key().to_string(),
+                        value: values.and_then(|v| v.get(quota_type.key()).copied()),
+                    })
+                    .collect();
+                (entity, ops)
+            })
+            .collect();
+        self.quotas.alter(changes)
+    }
+}
+
+/// Hands the ACLs to the [`Authorizer`]
+pub struct AclsPublisher {
+    authorizer: Arc<dyn Authorizer>,
+}
+
+impl AclsPublisher {
+    pub fn new(authorizer: Arc<dyn Authorizer>) -> Self {
+        Self { authorizer }
+    }
+}
+
+impl MetadataPublisher for AclsPublisher {
+    fn name(&self) -> &'static str {
+        "acls"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let added: Vec<AclBinding> = if delta.is_full() {
+            image.acls().values().cloned().collect()
+        } else {
+            delta.added_acls().values().cloned().collect()
+        };
+        // the same binding may have been added again under a new id
+        let wanted: BTreeSet<&AclBinding> = image.acls().values().collect();
+        let removed: Vec<AclBinding> = if delta.is_full() {
+            self.authorizer
+                .acls(&AclBindingFilter::any())
+                .into_iter()
+                .filter(|b| !wanted.contains(b))
+                .collect()
+        } else {
+            delta
+                .removed_acls()
+                .values()
+                .filter(|b| !wanted.contains(b))
+                .cloned()
+                .collect()
+        };
+        if !removed.is_empty() {
+            let filters: Vec<AclBindingFilter> =
+                removed.iter().map(AclBindingFilter::from).collect();
+            self.authorizer.delete_acls(&filters)?;
+        }
+        // creating an ACL that exists already leaves it as is
+        if !added.is_empty() {
+            self.authorizer.create_acls(&added)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hands the SCRAM credentials to the [`CredentialStore`]
+#[derive(Debug)]
+pub struct ScramPublisher {
+    credentials: Arc<CredentialStore>,
+}
+
+impl ScramPublisher {
+    pub fn new(credentials: Arc<CredentialStore>) -> Self {
+        Self { credentials }
+    }
+}
+
+impl MetadataPublisher for ScramPublisher {
+    fn name(&self) -> &'static str {
+        "scram"
+    }
+
+    fn publish(&self, delta: &MetadataDelta, image: &MetadataImage) -> anyhow::Result<()> {
+        let credentials = image.scram_credentials();
+        let changed: BTreeSet<_> = if delta.is_full() {
+            // the users missing from the image lose their credentials
+            let mut changed: BTreeSet<_> = self
+                .credentials
+                .scram_users()
+                .into_iter()
+                .flat_map(|(user, mechanisms)| {
+                    mechanisms
+                        .into_iter()
+                        .map(move |(mechanism, _)| (mechanism, user.clone()))
+                })
+                .collect();
+            changed.extend(credentials.keys().cloned());
+            changed
+        } else {
+            delta.changed_scram_credentials().clone()
+        };
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let changes = changed
+            .into_iter()
+            .map(|key| {
+                let credential = credentials.get(&key).cloned();
+                (key.0, key.1, credential)
+            })
+            .collect();
+        self.credentials.alter_scram(changes)
+    }
+}
+
+/// Answers the requests to the controller that waited for the image to replay the
+/// records written for them
+#[derive(Debug)]
+pub struct ControllerPublisher {
+    write_purgatory: Arc<Purgatory<TopicPartition, DelayedControllerWrite>>,
+}
+
+impl ControllerPublisher {
+    pub fn new(write_purgatory: Arc<Purgatory<TopicPartition, DelayedControllerWrite>>) -> Self {
+        Self { write_purgatory }
+    }
+}
+
+impl MetadataPublisher for ControllerPublisher {
+    fn name(&self) -> &'static str {
+        "controller"
+    }
+
+    fn publish(&self, _: &MetadataDelta, _: &MetadataImage) -> anyhow::Result<()> {
+        self.write_purgatory
+            .check_and_complete(&RaftClient::topic_partition());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metadata::{
+            AccessControlEntryRecord, ClientQuotaRecord, MetadataRecord,
+            RemoveAccessControlEntryRecord,
+        },
+        primitives::Uuid,
+        security::{
+            AccessControlEntry, AclAuthorizer, AclOperation, AclPermissionType, PatternType,
+            ResourcePattern, ResourceType,
+        },
+    };
+
+    fn binding(principal: &str) -> AclBinding {
+        AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: "foo".to_string(),
+                pattern_type: PatternType::Literal,
+            },
+            entry: AccessControlEntry {
+                principal: principal.to_string(),
+                host: "*".to_string(),
+                operation: AclOperation::Read,
+                permission_type: AclPermissionType::Allow,
+            },
+        }
+    }
+
+    #[test]
+    fn test_publish_acls_and_quotas() {
+        let dir = tempfile::tempdir().unwrap();
+        let authorizer: Arc<dyn Authorizer> =
+            Arc::new(AclAuthorizer::load(dir.path(), &[], false).unwrap());
+        let quotas = Arc::new(
+            QuotaManager::load(dir.path(), 11, std::time::Duration::from_secs(1)).unwrap(),
+        );
+        let publishers: Vec<Box<dyn MetadataPublisher>> = vec![
+            Box::new(AclsPublisher::new(Arc::clone(&authorizer))),
+            Box::new(QuotasPublisher::new(Arc::clone(&quotas))),
+        ];
+        let alice = QuotaEntity::from_components([("user", Some("alice"))]).unwrap();
+        let (alice_acl, bob_acl) = (Uuid::random(), Uuid::random());
+
+        let mut image = MetadataImage::new();
+        let mut delta = MetadataDelta::new();
+        let records = [
+            MetadataRecord::AccessControlEntry(AccessControlEntryRecord::new(
+                alice_acl,
+                &binding("User:alice"),
+            )),
+            MetadataRecord::AccessControlEntry(AccessControlEntryRecord::new(
+                bob_acl,
+                &binding("User:bob"),
+            )),
+            MetadataRecord::ClientQuota(ClientQuotaRecord::new(
+                &alice,
+                "producer_byte_rate",
+                Some(1024.0),
+            )),
+        ];
+        for (offset, record) in (0..).zip(&records) {
+            delta.replay(&mut image, offset, record);
+        }
+        for publisher in &publishers {
+            publisher.publish(&delta, &image).unwrap();
+        }
+        assert_eq!(2, authorizer.acls(&AclBindingFilter::any()).len());
+        assert_eq!(1, quotas.describe(&[], false).unwrap().len());
+
+        let mut delta = MetadataDelta::new();
+        let records = [
+            MetadataRecord::RemoveAccessControlEntry(RemoveAccessControlEntryRecord::new(bob_acl)),
+            MetadataRecord::ClientQuota(ClientQuotaRecord::new(&alice, "producer_byte_rate", None)),
+        ];
+        for (offset, record) in (3..).zip(&records) {
+            delta.replay(&mut image, offset, record);
+        }
+        for publisher in &publishers {
+            publisher.publish(&delta, &image).unwrap();
+        }
+        assert_eq!(
+            vec![binding("User:alice")],
+            authorizer.acls(&AclBindingFilter::any())
+        );
+        assert!(quotas.describe(&[], false).unwrap().is_empty());
+
+        // a full publish replaces the ACLs and quotas with the ones in the image
+        authorizer.delete_acls(&[AclBindingFilter::any()]).unwrap();
+        authorizer.create_acls(&[binding("User:carol")]).unwrap();
+        let op = QuotaOp {
+            key: "producer_byte_rate".to_string(),
+            value: Some(1.0),
+        };
+        quotas.alter(vec![(alice, vec![op])]).unwrap();
+        for publisher in &publishers {
+            publisher.publish(&MetadataDelta::full(), &image).unwrap();
+        }
+        assert_eq!(
+            vec![binding("User:alice")],
+            authorizer.acls(&AclBindingFilter::any())
+        );
+        assert!(quotas.describe(&[], false).unwrap().is_empty());
+    }
+}
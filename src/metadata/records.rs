@@ -0,0 +1,1027 @@
+use anyhow::Context;
+use bytes::{Buf, Bytes, BytesMut};
+use kafka_macros::{Encoder, WireLen};
+
+use super::image::{BrokerEndpoint, BrokerRegistration};
+use crate::{
+    codec::{Decoder, Encoder},
+    config::ConfigEntity,
+    primitives::{
+        Bool, CompactArray, CompactBytes, CompactNullableString, CompactString, UVarint, Uuid,
+    },
+    quota::QuotaEntity,
+    security::{
+        AccessControlEntry, AclBinding, AclOperation, AclPermissionType, PatternType,
+        ResourcePattern, ResourceType, ScramCredential, ScramMechanism,
+    },
+    types::{Record, TagBuf},
+    unwrap_decode,
+};
+
+/// Version of the frame every record of the metadata log is wrapped in
+const FRAME_VERSION: u32 = 1;
+
+/// A record of the `__cluster_metadata` log, in the format of Kafka's metadata records.
+/// Each one is the value of a record of the log, framed as
+///
+/// ```text
+/// frame_version api_key version => UNSIGNED_VARINT UNSIGNED_VARINT UNSIGNED_VARINT
+/// ```
+///
+/// followed by the fields of the record, encoded as a flexible message. Only version 0
+/// of each record type is written, but for [`RegisterBrokerRecord`] which needs version 3
+/// to carry the log dirs of the broker.
+#[derive(Debug)]
+pub enum MetadataRecord {
+    RegisterBroker(RegisterBrokerRecord),
+    Topic(TopicRecord),
+    Partition(PartitionRecord),
+    Config(ConfigRecord),
+    FenceBroker(FenceBrokerRecord),
+    UnfenceBroker(UnfenceBrokerRecord),
+    RemoveTopic(RemoveTopicRecord),
+    UserScramCredential(UserScramCredentialRecord),
+    FeatureLevel(FeatureLevelRecord),
+    ClientQuota(ClientQuotaRecord),
+    NoOp(NoOpRecord),
+    RemoveUserScramCredential(RemoveUserScramCredentialRecord),
+    AccessControlEntry(AccessControlEntryRecord),
+    RemoveAccessControlEntry(RemoveAccessControlEntryRecord),
+}
+
+impl MetadataRecord {
+    pub fn api_key(&self) -> u32 {
+        match self {
+            Self::RegisterBroker(_) => 0,
+            Self::Topic(_) => 2,
+            Self::Partition(_) => 3,
+            Self::Config(_) => 4,
+            Self::FenceBroker(_) => 7,
+            Self::UnfenceBroker(_) => 8,
+            Self::RemoveTopic(_) => 9,
+            Self::UserScramCredential(_) => 11,
+            Self::FeatureLevel(_) => 12,
+            Self::ClientQuota(_) => 14,
+            Self::NoOp(_) => 20,
+            Self::RemoveUserScramCredential(_) => 22,
+            Self::AccessControlEntry(_) => 23,
+            Self::RemoveAccessControlEntry(_) => 24,
+        }
+    }
+
+    /// The version the record is written in
+    pub fn version(&self) -> u32 {
+        match self {
+            Self::RegisterBroker(_) => RegisterBrokerRecord::VERSION,
+            _ => 0,
+        }
+    }
+
+    /// Frames the record as the value of a record of the metadata log
+    pub fn to_value(&self) -> anyhow::Result<Bytes> {
+        let mut value = BytesMut::new();
+        UVarint(FRAME_VERSION).encode(&mut value)?;
+        UVarint(self.api_key()).encode(&mut value)?;
+        UVarint(self.version()).encode(&mut value)?;
+        match self {
+            Self::RegisterBroker(record) => record.encode(&mut value)?,
+            Self::Topic(record) => record.encode(&mut value)?,
+            Self::Partition(record) => record.encode(&mut value)?,
+            Self::Config(record) => record.encode(&mut value)?,
+            Self::FenceBroker(record) => record.encode(&mut value)?,
+            Self::UnfenceBroker(record) => record.encode(&mut value)?,
+            Self::RemoveTopic(record) => record.encode(&mut value)?,
+            Self::UserScramCredential(record) => record.encode(&mut value)?,
+            Self::FeatureLevel(record) => record.encode(&mut value)?,
+            Self::ClientQuota(record) => record.encode(&mut value)?,
+            Self::NoOp(record) => record.encode(&mut value)?,
+            Self::RemoveUserScramCredential(record) => record.encode(&mut value)?,
+            Self::AccessControlEntry(record) => record.encode(&mut value)?,
+            Self::RemoveAccessControlEntry(record) => record.encode(&mut value)?,
+        }
+        Ok(value.freeze())
+    }
+
+    /// A record of the metadata log holding this one, at `offset_delta` in its batch
+    pub fn to_record(&self, offset_delta: i32) -> anyhow::Result<Record> {
+        Ok(Record::new(offset_delta, None, Some(self.to_value()?)))
+    }
+
+    /// Reads the record framed in `value`. `None` for record types this node does not
+    /// know, which it skips.
+    pub fn from_value(value: &[u8]) -> anyhow::Result<Option<Self>> {
+        let mut src = BytesMut::from(value);
+        let frame_version = Self::read_uvarint(&mut src)?;
+        anyhow::ensure!(
+            frame_version == FRAME_VERSION,
+            "Unsupported frame version {frame_version} of metadata record"
+        );
+        let api_key = Self::read_uvarint(&mut src)?;
+        let version = Self::read_uvarint(&mut src)?;
+        let record = match (api_key, version) {
+            (0, RegisterBrokerRecord::VERSION) => {
+                RegisterBrokerRecord::decode(&mut src, None)?.map(Self::RegisterBroker)
+            }
+            (2, 0) => TopicRecord::decode(&mut src, None)?.map(Self::Topic),
+            (3, 0) => PartitionRecord::decode(&mut src, None)?.map(Self::Partition),
+            (4, 0) => ConfigRecord::decode(&mut src, None)?.map(Self::Config),
+            (7, 0) => FenceBrokerRecord::decode(&mut src, None)?.map(Self::FenceBroker),
+            (8, 0) => UnfenceBrokerRecord::decode(&mut src, None)?.map(Self::UnfenceBroker),
+            (9, 0) => RemoveTopicRecord::decode(&mut src, None)?.map(Self::RemoveTopic),
+            (11, 0) => {
+                UserScramCredentialRecord::decode(&mut src, None)?.map(Self::UserScramCredential)
+            }
+            (12, 0) => FeatureLevelRecord::decode(&mut src, None)?.map(Self::FeatureLevel),
+            (14, 0) => ClientQuotaRecord::decode(&mut src, None)?.map(Self::ClientQuota),
+            (20, 0) => NoOpRecord::decode(&mut src, None)?.map(Self::NoOp),
+            (22, 0) => RemoveUserScramCredentialRecord::decode(&mut src, None)?
+                .map(Self::RemoveUserScramCredential),
+            (23, 0) => {
+                AccessControlEntryRecord::decode(&mut src, None)?.map(Self::AccessControlEntry)
+            }
+            (24, 0) => RemoveAccessControlEntryRecord::decode(&mut src, None)?
+                .map(Self::RemoveAccessControlEntry),
+            _ => return Ok(None),
+        };
+        match record {
+            Some(record) => Ok(Some(record)),
+            None => anyhow::bail!("Metadata record of type {api_key} is truncated"),
+        }
+    }
+
+    fn read_uvarint(src: &mut BytesMut) -> anyhow::Result<u32> {
+        match UVarint::decode(src, None)? {
+            Some(value) => Ok(value.0),
+            None => anyhow::bail!("Metadata record frame is truncated"),
+        }
+    }
+}
+
+/// RegisterBrokerRecord (Version: 3) => broker_id is_migrating_zk_broker incarnation_id broker_epoch [end_points] [features] rack fenced in_controlled_shutdown [log_dirs] TAG_BUFFER
+///
+/// Registers a broker, replacing its previous registration. The broker epoch is the
+/// offset of the record.
+#[derive(Debug, WireLen, Encoder)]
+pub struct RegisterBrokerRecord {
+    pub broker_id: i32,
+    pub is_migrating_zk_broker: Bool,
+    pub incarnation_id: Uuid,
+    pub broker_epoch: i64,
+    pub end_points: CompactArray<BrokerEndpointRecord>,
+    pub features: CompactArray<BrokerFeatureRecord>,
+    pub rack: CompactNullableString,
+    pub fenced: Bool,
+    pub in_controlled_shutdown: Bool,
+    pub log_dirs: CompactArray<Uuid>,
+    tag_buffer: TagBuf,
+}
+
+/// end_points => name host port security_protocol TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerEndpointRecord {
+    pub name: CompactString,
+    pub host: CompactString,
+    pub port: u16,
+    pub security_protocol: i16,
+    tag_buffer: TagBuf,
+}
+
+/// features => name min_supported_version max_supported_version TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerFeatureRecord {
+    pub name: CompactString,
+    pub min_supported_version: i16,
+    pub max_supported_version: i16,
+    tag_buffer: TagBuf,
+}
+
+impl RegisterBrokerRecord {
+    const VERSION: u32 = 3;
+
+    /// Registers `broker`, fenced or not as it says
+    pub fn new(broker: &BrokerRegistration) -> Self {
+        let end_points: Vec<_> = broker
+            .endpoints
+            .iter()
+            .map(|e| BrokerEndpointRecord {
+                name: CompactString(e.name.clone()),
+                host: CompactString(e.host.clone()),
+                port: e.port,
+                security_protocol: e.security_protocol,
+                tag_buffer: TagBuf::new(),
+            })
+            .collect();
+        let features: Vec<_> = broker
+            .features
+            .iter()
+            .map(|(name, &(min, max))| BrokerFeatureRecord {
+                name: CompactString(name.clone()),
+                min_supported_version: min,
+                max_supported_version: max,
+                tag_buffer: TagBuf::new(),
+            })
+            .collect();
+        Self {
+            broker_id: broker.id,
+            is_migrating_zk_broker: Bool::False,
+            incarnation_id: broker.incarnation_id,
+            broker_epoch: broker.epoch,
+            end_points: end_points.into(),
+            features: features.into(),
+            rack: CompactNullableString(broker.rack.clone()),
+            fenced: broker.fenced.into(),
+            in_controlled_shutdown: broker.in_controlled_shutdown.into(),
+            log_dirs: broker.log_dirs.clone().into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The registration the record stands for
+    pub fn registration(&self) -> BrokerRegistration {
+        BrokerRegistration {
+            id: self.broker_id,
+            epoch: self.broker_epoch,
+            incarnation_id: self.incarnation_id,
+            endpoints: self
+                .end_points
+                .iter()
+                .map(|e| BrokerEndpoint {
+                    name: e.name.0.clone(),
+                    host: e.host.0.clone(),
+                    port: e.port,
+                    security_protocol: e.security_protocol,
+                })
+                .collect(),
+            features: self
+                .features
+                .iter()
+                .map(|f| {
+                    let range = (f.min_supported_version, f.max_supported_version);
+                    (f.name.0.clone(), range)
+                })
+                .collect(),
+            rack: self.rack.0.clone(),
+            fenced: self.fenced.is_true(),
+            in_controlled_shutdown: self.in_controlled_shutdown.is_true(),
+            log_dirs: self.log_dirs.iter().copied().collect(),
+        }
+    }
+}
+
+/// FenceBrokerRecord (Version: 0) => id epoch TAG_BUFFER
+///
+/// Fences the registration of broker `id` with `epoch`, later registrations stay as
+/// they are
+#[derive(Debug, WireLen, Encoder)]
+pub struct FenceBrokerRecord {
+    pub id: i32,
+    pub epoch: i64,
+    tag_buffer: TagBuf,
+}
+
+impl FenceBrokerRecord {
+    pub fn new(id: i32, epoch: i64) -> Self {
+        Self {
+            id,
+            epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// UnfenceBrokerRecord (Version: 0) => id epoch TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct UnfenceBrokerRecord {
+    pub id: i32,
+    pub epoch: i64,
+    tag_buffer: TagBuf,
+}
+
+impl UnfenceBrokerRecord {
+    pub fn new(id: i32, epoch: i64) -> Self {
+        Self {
+            id,
+            epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// TopicRecord (Version: 0) => name topic_id TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct TopicRecord {
+    pub name: CompactString,
+    pub topic_id: Uuid,
+    tag_buffer: TagBuf,
+}
+
+impl TopicRecord {
+    pub fn new(name: impl Into<String>, topic_id: Uuid) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            topic_id,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// PartitionRecord (Version: 0) => partition_id topic_id [replicas] [isr] [removing_replicas] [adding_replicas] leader leader_epoch partition_epoch TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct PartitionRecord {
+    pub partition_id: i32,
+    pub topic_id: Uuid,
+    pub replicas: CompactArray<i32>,
+    pub isr: CompactArray<i32>,
+    pub removing_replicas: CompactArray<i32>,
+    pub adding_replicas: CompactArray<i32>,
+    /// -1 without a leader
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl PartitionRecord {
+    /// A new partition of `topic_id` hosted by `replicas`, led by the first of them
+    pub fn new(partition_id: i32, topic_id: Uuid, replicas: Vec<i32>) -> Self {
+        Self {
+            partition_id,
+            topic_id,
+            leader: replicas.first().copied().unwrap_or(-1),
+            isr: replicas.clone().into(),
+            replicas: replicas.into(),
+            removing_replicas: CompactArray::new(),
+            adding_replicas: CompactArray::new(),
+            leader_epoch: 0,
+            partition_epoch: 0,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// ConfigRecord (Version: 0) => resource_type resource_name name value TAG_BUFFER
+///
+/// Sets a dynamic config of a topic or a broker, a null `value` removes it. The cluster
+/// wide broker defaults have an empty `resource_name`.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ConfigRecord {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl ConfigRecord {
+    pub const TOPIC: i8 = 2;
+    pub const BROKER: i8 = 4;
+
+    pub fn new(entity: &ConfigEntity, name: impl Into<String>, value: Option<String>) -> Self {
+        let (resource_type, resource_name) = match entity {
+            ConfigEntity::Topic(topic) => (Self::TOPIC, topic.clone()),
+            ConfigEntity::Broker(node_id) => (Self::BROKER, node_id.to_string()),
+            ConfigEntity::DefaultBroker => (Self::BROKER, String::new()),
+        };
+        Self {
+            resource_type,
+            resource_name: CompactString(resource_name),
+            name: CompactString(name.into()),
+            value: CompactNullableString(value),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The entity the config is set on
+    pub fn entity(&self) -> anyhow::Result<ConfigEntity> {
+        let name = &self.resource_name.0;
+        Ok(match self.resource_type {
+            Self::TOPIC => ConfigEntity::Topic(name.clone()),
+            Self::BROKER if name.is_empty() => ConfigEntity::DefaultBroker,
+            Self::BROKER => ConfigEntity::Broker(name.parse()?),
+            other => anyhow::bail!("Unknown config resource type {other}"),
+        })
+    }
+}
+
+/// RemoveTopicRecord (Version: 0) => topic_id TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct RemoveTopicRecord {
+    pub topic_id: Uuid,
+    tag_buffer: TagBuf,
+}
+
+impl RemoveTopicRecord {
+    pub fn new(topic_id: Uuid) -> Self {
+        Self {
+            topic_id,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// UserScramCredentialRecord (Version: 0) => name mechanism salt stored_key server_key iterations TAG_BUFFER
+///
+/// Sets the SCRAM credential of user `name` for `mechanism`, replacing any earlier one
+#[derive(Debug, WireLen, Encoder)]
+pub struct UserScramCredentialRecord {
+    pub name: CompactString,
+    pub mechanism: i8,
+    pub salt: CompactBytes,
+    pub stored_key: CompactBytes,
+    pub server_key: CompactBytes,
+    pub iterations: i32,
+    tag_buffer: TagBuf,
+}
+
+impl UserScramCredentialRecord {
+    pub fn new(
+        name: impl Into<String>,
+        mechanism: ScramMechanism,
+        credential: &ScramCredential,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            mechanism: mechanism.type_code(),
+            salt: CompactBytes(credential.salt.clone()),
+            stored_key: CompactBytes(credential.stored_key.clone()),
+            server_key: CompactBytes(credential.server_key.clone()),
+            iterations: i32::try_from(credential.iterations).unwrap_or(i32::MAX),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The mechanism and credential the record sets, failing for mechanisms this node
+    /// does not know
+    pub fn credential(&self) -> anyhow::Result<(ScramMechanism, ScramCredential)> {
+        let mechanism = ScramMechanism::from_type_code(self.mechanism).with_context(|| {
+            format!(
+                "Unknown SCRAM mechanism {} of {}",
+                self.mechanism, self.name.0
+            )
+        })?;
+        let credential = ScramCredential {
+            salt: self.salt.0.clone(),
+            stored_key: self.stored_key.0.clone(),
+            server_key: self.server_key.0.clone(),
+            iterations: u32::try_from(self.iterations).unwrap_or(0),
+        };
+        Ok((mechanism, credential))
+    }
+}
+
+/// RemoveUserScramCredentialRecord (Version: 0) => name mechanism TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct RemoveUserScramCredentialRecord {
+    pub name: CompactString,
+    pub mechanism: i8,
+    tag_buffer: TagBuf,
+}
+
+impl RemoveUserScramCredentialRecord {
+    pub fn new(name: impl Into<String>, mechanism: ScramMechanism) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            mechanism: mechanism.type_code(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The mechanism whose credential the record removes, `None` for mechanisms this
+    /// node does not know
+    pub fn scram_mechanism(&self) -> Option<ScramMechanism> {
+        ScramMechanism::from_type_code(self.mechanism)
+    }
+}
+
+/// FeatureLevelRecord (Version: 0) => name feature_level TAG_BUFFER
+///
+/// A `feature_level` of 0 removes the feature.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FeatureLevelRecord {
+    pub name: CompactString,
+    pub feature_level: i16,
+    tag_buffer: TagBuf,
+}
+
+impl FeatureLevelRecord {
+    pub fn new(name: impl Into<String>, feature_level: i16) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            feature_level,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// ClientQuotaRecord (Version: 0) => [entity] key value remove TAG_BUFFER
+///
+/// Sets a client quota of an entity, or removes it if `remove` is set
+#[derive(Debug, WireLen, Encoder)]
+pub struct ClientQuotaRecord {
+    pub entity: CompactArray<EntityData>,
+    pub key: CompactString,
+    pub value: f64,
+    pub remove: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// entity => entity_type entity_name TAG_BUFFER
+///
+/// A null `entity_name` stands for the default entity
+#[derive(Debug, WireLen, Encoder)]
+pub struct EntityData {
+    pub entity_type: CompactString,
+    pub entity_name: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl ClientQuotaRecord {
+    /// Sets `key` of `entity` to `value`, or removes it without one
+    pub fn new(entity: &QuotaEntity, key: impl Into<String>, value: Option<f64>) -> Self {
+        let entity: Vec<EntityData> = entity
+            .components()
+            .into_iter()
+            .map(|(entity_type, entity_name)| EntityData {
+                entity_type: CompactString(entity_type.to_string()),
+                entity_name: CompactNullableString(entity_name),
+                tag_buffer: TagBuf::new(),
+            })
+            .collect();
+        Self {
+            entity: entity.into(),
+            key: CompactString(key.into()),
+            value: value.unwrap_or_default(),
+            remove: Bool::from(value.is_none()),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn quota_entity(&self) -> anyhow::Result<QuotaEntity> {
+        QuotaEntity::from_components(
+            self.entity
+                .iter()
+                .map(|e| (e.entity_type.0.as_str(), e.entity_name.0.as_deref())),
+        )
+    }
+}
+
+/// AccessControlEntryRecord (Version: 0) => id resource_type resource_name pattern_type principal host operation permission_type TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AccessControlEntryRecord {
+    pub id: Uuid,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub pattern_type: i8,
+    pub principal: CompactString,
+    pub host: CompactString,
+    pub operation: i8,
+    pub permission_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl AccessControlEntryRecord {
+    pub fn new(id: Uuid, binding: &AclBinding) -> Self {
+        let (pattern, entry) = (&binding.pattern, &binding.entry);
+        Self {
+            id,
+            resource_type: pattern.resource_type.code(),
+            resource_name: CompactString(pattern.name.clone()),
+            pattern_type: pattern.pattern_type.code(),
+            principal: CompactString(entry.principal.clone()),
+            host: CompactString(entry.host.clone()),
+            operation: entry.operation.code(),
+            permission_type: entry.permission_type.code(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The ACL the record adds, failing for codes this node does not know
+    pub fn binding(&self) -> anyhow::Result<AclBinding> {
+        let code = |name: &str, code: i8| format!("Unknown {name} {code} of ACL {}", self.id);
+        Ok(AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::from_code(self.resource_type)
+                    .with_context(|| code("resource type", self.resource_type))?,
+                name: self.resource_name.0.clone(),
+                pattern_type: PatternType::from_code(self.pattern_type)
+                    .with_context(|| code("pattern type", self.pattern_type))?,
+            },
+            entry: AccessControlEntry {
+                principal: self.principal.0.clone(),
+                host: self.host.0.clone(),
+                operation: AclOperation::from_code(self.operation)
+                    .with_context(|| code("operation", self.operation))?,
+                permission_type: AclPermissionType::from_code(self.permission_type)
+                    .with_context(|| code("permission type", self.permission_type))?,
+            },
+        })
+    }
+}
+
+/// RemoveAccessControlEntryRecord (Version: 0) => id TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct RemoveAccessControlEntryRecord {
+    pub id: Uuid,
+    tag_buffer: TagBuf,
+}
+
+impl RemoveAccessControlEntryRecord {
+    pub fn new(id: Uuid) -> Self {
+        Self {
+            id,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// NoOpRecord (Version: 0) => TAG_BUFFER
+///
+/// Appended by the active controller to keep the high watermark moving.
+#[derive(Debug, Default, WireLen, Encoder)]
+pub struct NoOpRecord {
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for RegisterBrokerRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let broker_id = unwrap_decode!(i32::decode(src, None));
+        let is_migrating_zk_broker = unwrap_decode!(Bool::decode(src, None));
+        let incarnation_id = unwrap_decode!(Uuid::decode(src, None));
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let broker_epoch = src.get_i64();
+        let end_points = unwrap_decode!(CompactArray::decode(src, None));
+        let features = unwrap_decode!(CompactArray::decode(src, None));
+        let rack = unwrap_decode!(CompactNullableString::decode(src, None));
+        let fenced = unwrap_decode!(Bool::decode(src, None));
+        let in_controlled_shutdown = unwrap_decode!(Bool::decode(src, None));
+        let log_dirs = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            broker_id,
+            is_migrating_zk_broker,
+            incarnation_id,
+            broker_epoch,
+            end_points,
+            features,
+            rack,
+            fenced,
+            in_controlled_shutdown,
+            log_dirs,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BrokerEndpointRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let host = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let port = src.get_u16();
+        let security_protocol = src.get_i16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            host,
+            port,
+            security_protocol,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BrokerFeatureRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let min_supported_version = src.get_i16();
+        let max_supported_version = src.get_i16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            min_supported_version,
+            max_supported_version,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FenceBrokerRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let id = src.get_i32();
+        let epoch = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            id,
+            epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for UnfenceBrokerRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let id = src.get_i32();
+        let epoch = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            id,
+            epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for TopicRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let topic_id = unwrap_decode!(Uuid::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            topic_id,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for PartitionRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let partition_id = unwrap_decode!(i32::decode(src, None));
+        let topic_id = unwrap_decode!(Uuid::decode(src, None));
+        let replicas = unwrap_decode!(CompactArray::decode(src, None));
+        let isr = unwrap_decode!(CompactArray::decode(src, None));
+        let removing_replicas = unwrap_decode!(CompactArray::decode(src, None));
+        let adding_replicas = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let leader = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let partition_epoch = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_id,
+            topic_id,
+            replicas,
+            isr,
+            removing_replicas,
+            adding_replicas,
+            leader,
+            leader_epoch,
+            partition_epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ConfigRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let value = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type,
+            resource_name,
+            name,
+            value,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for RemoveTopicRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_id = unwrap_decode!(Uuid::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_id,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for UserScramCredentialRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let mechanism = src.get_i8();
+        let salt = unwrap_decode!(CompactBytes::decode(src, None));
+        let stored_key = unwrap_decode!(CompactBytes::decode(src, None));
+        let server_key = unwrap_decode!(CompactBytes::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let iterations = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            mechanism,
+            salt,
+            stored_key,
+            server_key,
+            iterations,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for RemoveUserScramCredentialRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let mechanism = src.get_i8();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            mechanism,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FeatureLevelRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let feature_level = src.get_i16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            feature_level,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ClientQuotaRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let entity = unwrap_decode!(CompactArray::decode(src, None));
+        let key = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 9 {
+            src.reserve(9);
+            return Ok(None);
+        }
+        let value = src.get_f64();
+        let remove = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            entity,
+            key,
+            value,
+            remove,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for EntityData {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let entity_type = unwrap_decode!(CompactString::decode(src, None));
+        let entity_name = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            entity_type,
+            entity_name,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AccessControlEntryRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let id = unwrap_decode!(Uuid::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let pattern_type = src.get_i8();
+        let principal = unwrap_decode!(CompactString::decode(src, None));
+        let host = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let operation = src.get_i8();
+        let permission_type = src.get_i8();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            id,
+            resource_type,
+            resource_name,
+            pattern_type,
+            principal,
+            host,
+            operation,
+            permission_type,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for RemoveAccessControlEntryRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let id = unwrap_decode!(Uuid::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self { id, tag_buffer }))
+    }
+}
+
+impl Decoder for NoOpRecord {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self { tag_buffer }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let topic_id = Uuid::random();
+        let record = MetadataRecord::Partition(PartitionRecord::new(1, topic_id, vec![2, 3]));
+        let value = record.to_value().unwrap();
+        // frame version 1, PartitionRecord, version 0
+        assert_eq!(&[1, 3, 0], &value[..3]);
+
+        let Some(MetadataRecord::Partition(partition)) =
+            MetadataRecord::from_value(&value).unwrap()
+        else {
+            panic!("not a partition record");
+        };
+        assert_eq!(topic_id, partition.topic_id);
+        assert_eq!(2, partition.leader);
+        assert_eq!(
+            vec![2, 3],
+            partition.isr.iter().copied().collect::<Vec<_>>()
+        );
+
+        // credentials come back as they were written
+        let credential = ScramCredential::new(ScramMechanism::Sha512, "secret", 4096);
+        let record = UserScramCredentialRecord::new("alice", ScramMechanism::Sha512, &credential);
+        let value = MetadataRecord::UserScramCredential(record)
+            .to_value()
+            .unwrap();
+        let Some(MetadataRecord::UserScramCredential(record)) =
+            MetadataRecord::from_value(&value).unwrap()
+        else {
+            panic!("not a SCRAM credential record");
+        };
+        assert_eq!("alice", record.name.0);
+        assert_eq!(
+            (ScramMechanism::Sha512, credential),
+            record.credential().unwrap()
+        );
+
+        // unknown record types are skipped, broken frames fail
+        assert!(
+            MetadataRecord::from_value(&[1, 17, 0, 0])
+                .unwrap()
+                .is_none()
+        );
+        assert!(MetadataRecord::from_value(&[2, 2, 0]).is_err());
+        assert!(MetadataRecord::from_value(&value[..value.len() - 2]).is_err());
+    }
+}
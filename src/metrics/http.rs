@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use tracing::{debug, warn};
+
+use crate::broker::BrokerState;
+
+/// Scrape requests larger than this are refused, they only need a request line and a few headers
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Answers `GET /metrics` with the broker's metrics until the broker shuts down.
+/// Only as much HTTP/1.1 as scrapers need is spoken: every connection gets a
+/// single response and is closed.
+pub async fn serve_metrics(
+    listener: TcpListener,
+    state: Arc<BrokerState>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown.wait_for(|&stopping| stopping) => return Ok(()),
+        };
+        let (stream, addr) = accepted.context("Accepting metrics connection")?;
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = answer(stream, state).await {
+                warn!("Serving metrics to {addr} failed: {e:#}");
+            }
+        });
+    }
+}
+
+async fn answer(mut stream: TcpStream, state: Arc<BrokerState>) -> anyhow::Result<()> {
+    let mut request = Vec::with_capacity(1024);
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        anyhow::ensure!(
+            request.len() < MAX_REQUEST_SIZE,
+            "Request exceeds {MAX_REQUEST_SIZE} bytes"
+        );
+        if stream.read_buf(&mut request).await? == 0 {
+            return Ok(());
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let request_line = request.lines().next().unwrap_or_default();
+    debug!("Metrics request {request_line:?}");
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    // scrapers may add a query string
+    let path = path.map(|p| p.split('?').next().unwrap_or_default());
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => {
+            // reading the log sizes touches the disk
+            let body = tokio::task::spawn_blocking(move || {
+                let under_replicated = state.replica_manager.under_replicated_partitions();
+                state.metrics.render(&state.log_manager, under_replicated)
+            })
+            .await
+            .context("Rendering metrics panicked")?;
+            ("200 OK", body)
+        }
+        (Some("GET"), _) => (
+            "404 Not Found",
+            "Metrics are served on /metrics\n".to_string(),
+        ),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Broker metrics, served over HTTP in the Prometheus text format on `/metrics`.
+//! Counters are updated as requests flow through the broker, gauges describing
+//! the logs are read from the log manager when scraped.
+mod histogram;
+mod http;
+
+pub use histogram::{Histogram, LATENCY_BUCKETS};
+pub use http::serve_metrics;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use tracing::warn;
+
+use crate::{storage::LogManager, types::ApiKeys};
+
+/// When a request reached each step of its handling
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimes {
+    /// Its frame was read from the connection
+    pub received: Instant,
+    /// A handler thread picked it up
+    pub started: Instant,
+    /// The handler returned
+    pub handled: Instant,
+    /// Its response was ready, later than `handled` if it waited in a purgatory
+    pub completed: Instant,
+    /// Its response was written to the connection
+    pub sent: Instant,
+}
+
+/// Latencies of the requests of one API key and version
+#[derive(Debug, Default)]
+struct RequestMetrics {
+    /// Waiting for a handler thread
+    queue: Histogram,
+    /// Running the handler
+    local: Histogram,
+    /// Waiting in a purgatory
+    remote: Histogram,
+    /// Waiting for the responses before it and writing the response
+    send: Histogram,
+    total: Histogram,
+}
+
+/// Picks one of the histograms of [`RequestMetrics`]
+type HistogramOf = fn(&RequestMetrics) -> &Histogram;
+
+/// Metrics updated by the connections and handlers, shared through the broker state
+#[derive(Debug, Default)]
+pub struct BrokerMetrics {
+    /// By API key and version
+    requests: Mutex<BTreeMap<(i16, i16), RequestMetrics>>,
+    /// Record bytes appended, by topic
+    bytes_in: Mutex<BTreeMap<String, u64>>,
+    /// Record bytes fetched, by topic
+    bytes_out: Mutex<BTreeMap<String, u64>>,
+    /// Open connections, by listener name
+    connections: Arc<Mutex<BTreeMap<String, i64>>>,
+    /// Connections closed right after being accepted, by listener name and reason
+    rejected_connections: Mutex<BTreeMap<(String, &'static str), u64>>,
+}
+
+/// Counts a connection as open until dropped
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    connections: Arc<Mutex<BTreeMap<String, i64>>>,
+    listener_name: String,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.listener_name) {
+            *count -= 1;
+        }
+    }
+}
+
+impl BrokerMetrics {
+    pub fn record_request(&self, api_key: ApiKeys, api_version: i16, times: &RequestTimes) {
+        let mut requests = self.requests.lock().unwrap();
+        let metrics = requests.entry((api_key as i16, api_version)).or_default();
+        metrics.queue.observe(times.started - times.received);
+        metrics.local.observe(times.handled - times.started);
+        metrics.remote.observe(times.completed - times.handled);
+        metrics.send.observe(times.sent - times.completed);
+        metrics.total.observe(times.sent - times.received);
+    }
+
+    pub fn record_bytes_in(&self, topic: &str, bytes: u64) {
+        Self::add(&self.bytes_in, topic, bytes);
+    }
+
+    pub fn record_bytes_out(&self, topic: &str, bytes: u64) {
+        Self::add(&self.bytes_out, topic, bytes);
+    }
+
+    fn add(counters: &Mutex<BTreeMap<String, u64>>, topic: &str, bytes: u64) {
+        let mut counters = counters.lock().unwrap();
+        match counters.get_mut(topic) {
+            Some(count) => *count += bytes,
+            None => {
+                counters.insert(topic.to_string(), bytes);
+            }
+        }
+    }
+
+    /// Counts a connection of `listener_name` as open until the guard is dropped
+    #[must_use]
+    pub fn connection_opened(&self, listener_name: &str) -> ConnectionGuard {
+        *self
+            .connections
+            .lock()
+            .unwrap()
+            .entry(listener_name.to_string())
+            .or_default() += 1;
+        ConnectionGuard {
+            connections: Arc::clone(&self.connections),
+            listener_name: listener_name.to_string(),
+        }
+    }
+
+    /// Counts a connection closed right away for exceeding a limit, `reason` names the limit
+    pub fn connection_rejected(&self, listener_name: &str, reason: &'static str) {
+        *self
+            .rejected_connections
+            .lock()
+            .unwrap()
+            .entry((listener_name.to_string(), reason))
+            .or_default() += 1;
+    }
+
+    /// Every metric in the Prometheus text exposition format, `under_replicated` being
+    /// the number of partitions led by the broker with replicas out of sync
+    pub fn render(&self, log_manager: &LogManager, under_replicated: usize) -> String {
+        let mut out = String::new();
+        self.render_requests(&mut out);
+
+        for (name, help, counters) in [
+            (
+                "kafka_server_bytes_in_total",
+                "Record bytes appended to the topic",
+                &self.bytes_in,
+            ),
+            (
+                "kafka_server_bytes_out_total",
+                "Record bytes fetched from the topic",
+                &self.bytes_out,
+            ),
+        ] {
+            header(&mut out, name, help, "counter");
+            for (topic, bytes) in counters.lock().unwrap().iter() {
+                let _ = writeln!(out, "{name}{{topic=\"{}\"}} {bytes}", escape(topic));
+            }
+        }
+
+        let name = "kafka_server_active_connections";
+        header(&mut out, name, "Open client connections", "gauge");
+        for (listener, count) in self.connections.lock().unwrap().iter() {
+            let _ = writeln!(out, "{name}{{listener=\"{}\"}} {count}", escape(listener));
+        }
+
+        let name = "kafka_server_rejected_connections_total";
+        header(&mut out, name, "Connections closed for exceeding a limit", "counter");
+        for ((listener, reason), count) in self.rejected_connections.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "{name}{{listener=\"{}\",reason=\"{reason}\"}} {count}",
+                escape(listener)
+            );
+        }
+
+        Self::render_logs(&mut out, log_manager, under_replicated);
+        out
+    }
+
+    fn render_requests(&self, out: &mut String) {
+        let requests = self.requests.lock().unwrap();
+        let name = "kafka_network_requests_total";
+        header(out, name, "Requests answered", "counter");
+        for (&(api_key, api_version), metrics) in requests.iter() {
+            let _ = writeln!(
+                out,
+                "{name}{{{}}} {}",
+                request_labels(api_key, api_version),
+                metrics.total.count()
+            );
+        }
+
+        let histograms: [(&str, &str, HistogramOf); 5] = [
+            (
+                "kafka_network_request_queue_time_seconds",
+                "Time requests waited for a handler thread",
+                |m| &m.queue,
+            ),
+            (
+                "kafka_network_request_local_time_seconds",
+                "Time spent handling requests",
+                |m| &m.local,
+            ),
+            (
+                "kafka_network_request_remote_time_seconds",
+                "Time requests waited in a purgatory",
+                |m| &m.remote,
+            ),
+            (
+                "kafka_network_request_send_time_seconds",
+                "Time from a response being ready until it was written",
+                |m| &m.send,
+            ),
+            (
+                "kafka_network_request_total_time_seconds",
+                "Time from a request being read until its response was written",
+                |m| &m.total,
+            ),
+        ];
+        for (name, help, histogram) in histograms {
+            header(out, name, help, "histogram");
+            for (&(api_key, api_version), metrics) in requests.iter() {
+                histogram(metrics).render(out, name, &request_labels(api_key, api_version));
+            }
+        }
+    }
+
+    fn render_logs(out: &mut String, log_manager: &LogManager, under_replicated: usize) {
+        let mut sizes = Vec::new();
+        for log in log_manager.logs() {
+            let log = log.lock().unwrap();
+            match log.size() {
+                Ok(size) => sizes.push((log.topic_partition().clone(), size)),
+                Err(e) => warn!("Reading size of {} failed: {e:#}", log.topic_partition()),
+            }
+        }
+        sizes.sort();
+
+        let name = "kafka_log_size_bytes";
+        header(out, name, "Size of the partition's segments", "gauge");
+        for (tp, size) in &sizes {
+            let _ = writeln!(
+                out,
+                "{name}{{topic=\"{}\",partition=\"{}\"}} {size}",
+                escape(&tp.topic),
+                tp.partition
+            );
+        }
+
+        let name = "kafka_server_under_replicated_partitions";
+        header(out, name, "Partitions with replicas out of sync", "gauge");
+        let _ = writeln!(out, "{name} {under_replicated}");
+        let name = "kafka_server_partition_count";
+        header(out, name, "Partitions hosted by the broker", "gauge");
+        let _ = writeln!(out, "{name} {}", sizes.len());
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn request_labels(api_key: i16, api_version: i16) -> String {
+    format!(
+        "request=\"{:?}\",version=\"{api_version}\"",
+        ApiKeys::from(api_key)
+    )
+}
+
+/// Escapes a label value, as the text format requires for backslashes, quotes and newlines
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_manager =
+            LogManager::load(vec![dir.path().to_path_buf()], Default::default()).unwrap();
+        let metrics = BrokerMetrics::default();
+
+        let received = Instant::now();
+        let ms = Duration::from_millis;
+        let times = RequestTimes {
+            received,
+            started: received + ms(1),
+            handled: received + ms(3),
+            completed: received + ms(3),
+            sent: received + ms(4),
+        };
+        metrics.record_request(ApiKeys::ApiVersions, 4, &times);
+        metrics.record_bytes_in("a\"b", 10);
+        metrics.record_bytes_in("a\"b", 5);
+        let connection = metrics.connection_opened("PLAINTEXT");
+
+        let rendered = metrics.render(&log_manager, 0);
+        assert!(
+            rendered
+                .contains("kafka_network_requests_total{request=\"ApiVersions\",version=\"4\"} 1")
+        );
+        assert!(rendered.contains(
+            "kafka_network_request_local_time_seconds_bucket{request=\"ApiVersions\",version=\"4\",le=\"0.0025\"} 1"
+        ));
+        assert!(rendered.contains(
+            "kafka_network_request_queue_time_seconds_bucket{request=\"ApiVersions\",version=\"4\",le=\"0.0005\"} 0"
+        ));
+        assert!(rendered.contains("kafka_server_bytes_in_total{topic=\"a\\\"b\"} 15"));
+        assert!(rendered.contains("kafka_server_active_connections{listener=\"PLAINTEXT\"} 1"));
+
+        drop(connection);
+        assert!(
+            metrics
+                .render(&log_manager, 0)
+                .contains("kafka_server_active_connections{listener=\"PLAINTEXT\"} 0")
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Requests this node sends to other nodes of the cluster: followers fetching from
+//! the leaders of their partitions, and the controllers of the KRaft quorum talking to
+//! each other. Only plaintext connections and flexible request versions are used.
+use std::time::Duration;
+
+use anyhow::Context;
+use bytes::{Buf, BytesMut};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+    primitives::NullableString,
+    request::RequestHeaderV2,
+    types::{ApiKeys, TagBuf},
+};
+
+/// Opens a connection to `address`, giving up after `timeout`
+pub async fn connect(address: &str, timeout: Duration) -> anyhow::Result<TcpStream> {
+    let stream = tokio::time::timeout(timeout, TcpStream::connect(address))
+        .await
+        .context("Connecting timed out")??;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// Sends a request of a flexible version, with request header v2, and reads its
+/// response, which has to be the next one on the connection
+pub async fn send_request<Req, Resp>(
+    stream: &mut TcpStream,
+    api_key: ApiKeys,
+    api_version: i16,
+    correlation_id: i32,
+    client_id: &str,
+    request: &Req,
+) -> anyhow::Result<Resp>
+where
+    Req: Encoder + WireLen,
+    Resp: Decoder + WireLen,
+{
+    let header = RequestHeaderV2::new(
+        api_key as i16,
+        api_version,
+        correlation_id,
+        NullableString::from_non_empty_str(client_id),
+        Some(TagBuf::new()),
+    );
+    let size = header.wire_len() + request.wire_len();
+    let mut buf = BytesMut::with_capacity(4 + size);
+    i32::try_from(size)?.encode(&mut buf)?;
+    header.encode(&mut buf)?;
+    request.encode(&mut buf)?;
+    stream
+        .write_all_buf(&mut buf)
+        .await
+        .with_context(|| format!("Sending {api_key:?}"))?;
+
+    let size = stream.read_i32().await.context("Reading response size")?;
+    let size = usize::try_from(size).context("Negative response size")?;
+    let mut buf = BytesMut::zeroed(size);
+    stream
+        .read_exact(&mut buf)
+        .await
+        .context("Reading response")?;
+
+    // response header v1: the correlation id and tagged fields
+    anyhow::ensure!(
+        buf.remaining() >= 4,
+        "Response of {size} bytes is too short"
+    );
+    let correlation = buf.get_i32();
+    anyhow::ensure!(
+        correlation == correlation_id,
+        "Response is for request {correlation}, expected {correlation_id}"
+    );
+    TagBuf::decode(&mut buf, None)?.context("Response header is incomplete")?;
+    let remaining = buf.remaining();
+    Resp::decode(&mut buf, Some(remaining))?.context("Response is incomplete")
+}
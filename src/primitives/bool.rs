@@ -1,6 +1,9 @@
-use bytes::BufMut;
+use bytes::{Buf, BufMut};
 
-use crate::{WireLen, codec::Encoder};
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
@@ -30,6 +33,23 @@ impl WireLen for Bool {
     }
 }
 
+impl Decoder for Bool {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        match src.get_u8() {
+            0 => Ok(Some(Bool::False)),
+            1 => Ok(Some(Bool::True)),
+            b => anyhow::bail!("Invalid boolean {b}"),
+        }
+    }
+}
+
 impl Encoder for Bool {
     fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
         dest.put_u8(*self as u8);
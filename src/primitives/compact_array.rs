@@ -30,6 +30,12 @@ impl<T: WireLen> Default for CompactArray<T> {
     }
 }
 
+impl<T: WireLen> From<Vec<T>> for CompactArray<T> {
+    fn from(inner: Vec<T>) -> Self {
+        Self { inner }
+    }
+}
+
 impl<T: WireLen> CompactArray<T> {
     pub const fn new() -> Self {
         Self {
@@ -48,6 +54,10 @@ impl<T: WireLen> CompactArray<T> {
         self.inner.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     pub fn push(&mut self, val: T) {
         self.inner.push(val);
     }
@@ -59,21 +69,12 @@ impl<T: WireLen> CompactArray<T> {
 
 impl<T: WireLen> WireLen for CompactArray<T> {
     fn wire_len(&self) -> usize {
-        // This down here is all incorrect, I was blind and they do infact
-        // send 0x00 indicating an empty compact array as the tag buffers
-        // this did infact take 3 days to figure out.
-        //
-        // current implementation assumes codecrafters does not even
-        // send the length prefix for an empty tag buffer (whre compact array is used)
-        if self.len() == 0 {
-            UVarint::wire_len_of(0)
-        } else {
-            assert!(
-                self.len() <= u32::MAX as usize,
-                "Compact array holds more than u32::MAX, the max size of uvarint"
-            );
-            UVarint::wire_len_of(self.len() as u32) + self.len() * self.inner[0].wire_len()
-        }
+        assert!(
+            self.len() < u32::MAX as usize,
+            "Compact array holds more than u32::MAX - 1, the max size of uvarint"
+        );
+        // the length prefix is N + 1, so an empty array is still a single 0x01 byte
+        UVarint::wire_len_of(self.len() as u32 + 1) + self.inner.wire_len()
     }
 }
 
@@ -86,7 +87,8 @@ impl<T: WireLen + Decoder> Decoder for CompactArray<T> {
         }
         let len = (len_plus_one - 1) as usize;
 
-        let mut me = CompactArray::with_capacity(len);
+        // the length comes from the wire, every element takes at least a byte of it
+        let mut me = CompactArray::with_capacity(len.min(src.len()));
         for _ in 0..len {
             let value = unwrap_decode!(T::decode(src, None));
             me.push(value);
@@ -133,7 +135,7 @@ mod tests {
         let c = buf.freeze();
         let mut buf = c.clone();
 
-        assert_eq!(api_versions.len() as u8, buf.get_u8());
+        assert_eq!(api_versions.len() as u8 + 1, buf.get_u8());
         assert_eq!(1, buf.get_u16());
         assert_eq!(0, buf.get_u16());
         assert_eq!(17, buf.get_u16());
@@ -142,4 +144,11 @@ mod tests {
 
         println!("{:?}", buf);
     }
+
+    #[test]
+    fn test_decode_huge_length() {
+        // u32::MAX - 1 elements announced, one sent
+        let mut buf = BytesMut::from(&[0xff, 0xff, 0xff, 0xff, 0x0f, 0, 0, 0, 1][..]);
+        assert!(CompactArray::<i32>::decode(&mut buf, None).unwrap().is_none());
+    }
 }
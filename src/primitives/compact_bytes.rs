@@ -0,0 +1,42 @@
+use bytes::{Buf, BufMut};
+
+use crate::{
+    codec::{Decoder, Encoder, WireLen},
+    unwrap_decode,
+};
+
+use super::UVarint;
+
+/// COMPACT_BYTES: the length N + 1 as an UNSIGNED_VARINT, then N bytes. A null value,
+/// with a length of 0, decodes as empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactBytes(pub Vec<u8>);
+
+impl WireLen for CompactBytes {
+    fn wire_len(&self) -> usize {
+        UVarint::wire_len_of(self.0.len() as u32 + 1) + self.0.len()
+    }
+}
+
+impl Encoder for CompactBytes {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        UVarint(self.0.len() as u32 + 1).encode(dest)?;
+        dest.put_slice(&self.0);
+        Ok(())
+    }
+}
+
+impl Decoder for CompactBytes {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let len_plus_one = unwrap_decode!(UVarint::decode(src, None)).0 as usize;
+        let len = len_plus_one.saturating_sub(1);
+        if src.remaining() < len {
+            src.reserve(len);
+            return Ok(None);
+        }
+        Ok(Some(Self(src.split_to(len).to_vec())))
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::Context;
+use bytes::BufMut;
+
+use crate::{
+    codec::{Decoder, Encoder, WireLen},
+    primitives::MAX_STRING_SIZE,
+    unwrap_decode,
+};
+
+use super::UVarint;
+
+/// A [`super::CompactString`] that can also be null, which is encoded with a length of 0
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactNullableString(pub Option<String>);
+
+impl CompactNullableString {
+    pub const fn null() -> Self {
+        Self(None)
+    }
+
+    pub fn as_deref(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl<S> From<Option<S>> for CompactNullableString
+where
+    S: Into<String>,
+{
+    fn from(value: Option<S>) -> Self {
+        Self(value.map(Into::into))
+    }
+}
+
+impl WireLen for CompactNullableString {
+    fn wire_len(&self) -> usize {
+        match &self.0 {
+            Some(s) => UVarint::wire_len_of(s.len() as u32 + 1) + s.len(),
+            None => 1,
+        }
+    }
+}
+
+impl Encoder for CompactNullableString {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        match &self.0 {
+            Some(s) => {
+                UVarint(s.len() as u32 + 1).encode(dest)?;
+                dest.put_slice(s.as_bytes());
+            }
+            None => dest.put_u8(0),
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for CompactNullableString {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let len_plus_one = unwrap_decode!(UVarint::decode(src, None)).0 as usize;
+        if len_plus_one == 0 {
+            return Ok(Some(Self::null()));
+        }
+        anyhow::ensure!(
+            len_plus_one < MAX_STRING_SIZE,
+            "compact nullable string length is bigger than allowed {MAX_STRING_SIZE}"
+        );
+
+        let len = len_plus_one - 1;
+        if src.len() < len {
+            src.reserve(len);
+            return Ok(None);
+        }
+        let data = src.split_to(len).to_vec();
+        let raw = String::from_utf8(data).context("Parsing compact nullable string")?;
+        Ok(Some(Self(Some(raw))))
+    }
+}
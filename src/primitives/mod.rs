@@ -7,12 +7,20 @@ pub const MAX_STRING_SIZE: usize = 128;
 
 mod bool;
 mod compact_array;
+mod compact_bytes;
+mod compact_nullable_string;
 mod compact_string;
 mod nullable_string;
+mod uuid;
 mod uvarint;
+mod varint;
 
 pub use bool::Bool;
 pub use compact_array::CompactArray;
+pub use compact_bytes::CompactBytes;
+pub use compact_nullable_string::CompactNullableString;
 pub use compact_string::CompactString;
 pub use nullable_string::NullableString;
+pub use uuid::Uuid;
 pub use uvarint::UVarint;
+pub use varint::{Varint, Varlong};
@@ -1,10 +1,10 @@
 use std::fmt::Debug;
 
 use anyhow::{Context, bail, ensure};
-use bytes::Buf;
+use bytes::{Buf, BufMut};
 
 use crate::{
-    codec::{Decoder, WireLen},
+    codec::{Decoder, Encoder, WireLen},
     primitives::MAX_STRING_SIZE,
 };
 
@@ -33,6 +33,10 @@ impl NullableString {
     pub fn null() -> Self {
         Self { inner: None }
     }
+
+    pub fn as_deref(&self) -> Option<&str> {
+        self.inner.as_deref()
+    }
 }
 
 impl WireLen for NullableString {
@@ -53,6 +57,19 @@ impl WireLen for NullableString {
     }
 }
 
+impl Encoder for NullableString {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        match self.inner {
+            Some(ref s) => {
+                dest.put_i16(i16::try_from(s.len())?);
+                dest.put_slice(s.as_bytes());
+            }
+            None => dest.put_i16(-1),
+        }
+        Ok(())
+    }
+}
+
 impl Decoder for NullableString {
     fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
     where
@@ -0,0 +1,74 @@
+use std::fmt::Display;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD as BASE64};
+use bytes::{Buf, BufMut};
+
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+};
+
+/// # Kafka protocol
+///
+/// A UUID, like the id of a topic, as its 16 raw bytes. Displayed the way Kafka does,
+/// in URL safe base64 without padding.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    /// The all zero UUID, standing for no id
+    pub const ZERO: Uuid = Uuid([0; 16]);
+
+    /// A new random id, never [`Uuid::ZERO`]
+    pub fn random() -> Self {
+        loop {
+            let id = Uuid(rand::random());
+            if id != Self::ZERO {
+                return id;
+            }
+        }
+    }
+
+    /// Parses the base64 form of the id
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let bytes = BASE64.decode(value)?;
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Id {value:?} is not 16 bytes long"))?;
+        Ok(Uuid(bytes))
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", BASE64.encode(self.0))
+    }
+}
+
+impl WireLen for Uuid {
+    fn wire_len(&self) -> usize {
+        16
+    }
+}
+
+impl Decoder for Uuid {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 16 {
+            src.reserve(16);
+            return Ok(None);
+        }
+        let mut bytes = [0; 16];
+        src.copy_to_slice(&mut bytes);
+        Ok(Some(Uuid(bytes)))
+    }
+}
+
+impl Encoder for Uuid {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        dest.put_slice(&self.0);
+        Ok(())
+    }
+}
@@ -49,7 +49,8 @@ impl Decoder for UVarint {
             trace!(" {}. iter:  byte = {:x}", count, byte);
             let value = (byte & 0x7F) as u32;
             trace!(" {}. iter: value = {:x}", count, value);
-            if shift >= 32 && value != 0 {
+            // the 5th byte can only hold the 4 most significant bits of a u32
+            if shift == 28 && (value > 0x0F || (byte & 0x80) != 0) {
                 bail!(UVarintDecodeError::Overflow);
             }
             result |= value << shift;
@@ -0,0 +1,150 @@
+use crate::codec::{Decoder, Encoder, WireLen};
+use anyhow::bail;
+use bytes::{Buf, BufMut};
+
+use super::uvarint::UVarintDecodeError;
+
+/// # Kafka protocol
+///
+/// Represents an integer between -2^31 and 2^31-1 inclusive.
+/// Encoding follows the variable-length zig-zag encoding from Google Protocol Buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Varint(pub i32);
+
+/// # Kafka protocol
+///
+/// Represents an integer between -2^63 and 2^63-1 inclusive.
+/// Encoding follows the variable-length zig-zag encoding from Google Protocol Buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Varlong(pub i64);
+
+/// Number of bytes an unsigned LEB128 encoded `value` takes up
+const fn unsigned_wire_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn put_unsigned(dest: &mut bytes::BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        dest.put_u8((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    dest.put_u8(value as u8);
+}
+
+/// Reads an unsigned LEB128 value of at most `max_bytes` bytes
+fn get_unsigned(src: &mut bytes::BytesMut, max_bytes: usize) -> anyhow::Result<u64> {
+    let mut result = 0u64;
+    for i in 0..max_bytes {
+        if !src.has_remaining() {
+            bail!(UVarintDecodeError::UnexpectedEndOfInput);
+        }
+        let byte = src.get_u8();
+        result |= u64::from(byte & 0x7F) << (7 * i);
+        if (byte & 0x80) == 0 {
+            return Ok(result);
+        }
+    }
+    bail!(UVarintDecodeError::Overflow)
+}
+
+impl Varint {
+    pub const fn wire_len_of(num: i32) -> usize {
+        unsigned_wire_len(((num << 1) ^ (num >> 31)) as u32 as u64)
+    }
+}
+
+impl Varlong {
+    pub const fn wire_len_of(num: i64) -> usize {
+        unsigned_wire_len(((num << 1) ^ (num >> 63)) as u64)
+    }
+}
+
+impl WireLen for Varint {
+    fn wire_len(&self) -> usize {
+        Varint::wire_len_of(self.0)
+    }
+}
+
+impl WireLen for Varlong {
+    fn wire_len(&self) -> usize {
+        Varlong::wire_len_of(self.0)
+    }
+}
+
+impl Encoder for Varint {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        let zigzag = ((self.0 << 1) ^ (self.0 >> 31)) as u32;
+        put_unsigned(dest, u64::from(zigzag));
+        Ok(())
+    }
+}
+
+impl Encoder for Varlong {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        let zigzag = ((self.0 << 1) ^ (self.0 >> 63)) as u64;
+        put_unsigned(dest, zigzag);
+        Ok(())
+    }
+}
+
+impl Decoder for Varint {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let zigzag = get_unsigned(src, 5)?;
+        if zigzag > u64::from(u32::MAX) {
+            bail!(UVarintDecodeError::Overflow);
+        }
+        let zigzag = zigzag as u32;
+        Ok(Some(Varint((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))))
+    }
+}
+
+impl Decoder for Varlong {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let zigzag = get_unsigned(src, 10)?;
+        Ok(Some(Varlong((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zigzag_encoding() {
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (-1, &[0x01][..]),
+            (1, &[0x02][..]),
+            (-64, &[0x7F][..]),
+            (64, &[0x80, 0x01][..]),
+        ] {
+            let mut buf = BytesMut::new();
+            Varint(value).encode(&mut buf).unwrap();
+            assert_eq!(expected, &buf[..], "encoding {value}");
+            assert_eq!(expected.len(), Varint(value).wire_len());
+            assert_eq!(value, Varint::decode(&mut buf, None).unwrap().unwrap().0);
+        }
+    }
+
+    #[test]
+    fn test_varlong_roundtrip() {
+        for value in [i64::MIN, -300, 0, 300, i64::MAX] {
+            let mut buf = BytesMut::new();
+            Varlong(value).encode(&mut buf).unwrap();
+            assert_eq!(buf.len(), Varlong(value).wire_len());
+            assert_eq!(value, Varlong::decode(&mut buf, None).unwrap().unwrap().0);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Requests that cannot be answered right away, like a produce waiting for its
+//! replicas or a fetch waiting for enough bytes, wait in a purgatory as delayed
+//! operations. Each operation watches some keys, e.g. the partitions or group it
+//! depends on, and is retried whenever one of them is checked after it changed.
+//! Operations that do not complete in time expire on a timing wheel.
+//!
+//! Waiting costs no task or thread: the handler returns a [`Completion`] that the
+//! connection awaits, and the operation is only looked at when something happens.
+mod timer;
+
+pub use timer::TimingWheel;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    sync::{Notify, oneshot},
+    time::MissedTickBehavior,
+};
+use tracing::debug;
+
+/// Resolution of the timeouts of delayed operations
+pub const TIMER_TICK: Duration = Duration::from_millis(10);
+/// Slots of the timing wheel, one turn covers about 5 seconds
+pub const TIMER_WHEEL_SIZE: usize = 512;
+
+/// An operation waiting in a [`Purgatory`] until it can complete or times out
+pub trait DelayedOperation: Send {
+    type Output: Send;
+
+    /// Returns the result of the operation if it can complete now. Called when the
+    /// operation is added and whenever one of its keys is checked, until it completes.
+    fn try_complete(&mut self) -> Option<Self::Output>;
+
+    /// The result of the operation once its timeout passed without it completing
+    fn on_expiration(self) -> Self::Output;
+}
+
+/// Resolves to the result of a delayed operation, once it completes or expires
+#[derive(Debug)]
+pub struct Completion<T>(oneshot::Receiver<T>);
+
+impl<T> Future for Completion<T> {
+    type Output = anyhow::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|result| {
+            result.map_err(|_| anyhow::anyhow!("Delayed operation was dropped before completing"))
+        })
+    }
+}
+
+/// Holds the delayed operations of one kind, watched by keys of type `K`
+pub struct Purgatory<K, O: DelayedOperation> {
+    name: &'static str,
+    inner: Mutex<Watchers<K, O>>,
+    /// Wakes the expiration task when an operation is added to an empty purgatory
+    added: Notify,
+}
+
+struct Watchers<K, O: DelayedOperation> {
+    next_id: u64,
+    operations: HashMap<u64, Watched<K, O>>,
+    /// Ids of the operations watching each key, in the order they were added
+    keys: HashMap<K, BTreeSet<u64>>,
+    /// Ids of the operations by their deadline. Ids of operations that completed
+    /// in the meantime are left in the wheel and skipped when they expire.
+    timer: TimingWheel<u64>,
+}
+
+struct Watched<K, O: DelayedOperation> {
+    operation: O,
+    keys: Vec<K>,
+    completion: oneshot::Sender<O::Output>,
+}
+
+impl<K, O> Purgatory<K, O>
+where
+    K: Eq + Hash + Clone,
+    O: DelayedOperation,
+{
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            inner: Mutex::new(Watchers {
+                next_id: 0,
+                operations: HashMap::new(),
+                keys: HashMap::new(),
+                timer: TimingWheel::new(TIMER_TICK, TIMER_WHEEL_SIZE, Instant::now()),
+            }),
+            added: Notify::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Number of operations waiting to complete
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Completes the operation right away if it can, otherwise watches it under
+    /// every one of `keys` until it completes or `timeout` passes
+    pub fn try_complete_else_watch(
+        &self,
+        mut operation: O,
+        keys: Vec<K>,
+        timeout: Duration,
+    ) -> Completion<O::Output> {
+        let (tx, rx) = oneshot::channel();
+        // holding the lock between the first try and watching the keys, so a key
+        // checked in between is only checked once the operation is watched
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(output) = operation.try_complete() {
+            let _ = tx.send(output);
+            return Completion(rx);
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        for key in &keys {
+            inner.keys.entry(key.clone()).or_default().insert(id);
+        }
+        inner.timer.add(Instant::now() + timeout, id);
+        inner.operations.insert(
+            id,
+            Watched {
+                operation,
+                keys,
+                completion: tx,
+            },
+        );
+        if inner.operations.len() == 1 {
+            self.added.notify_one();
+        }
+        Completion(rx)
+    }
+
+    /// Retries the operations watching `key`, to be called after whatever the key
+    /// stands for changed. Returns the number of operations that completed.
+    ///
+    /// The operations are tried while the purgatory is locked, so this must not be
+    /// called while holding a lock that their [`DelayedOperation::try_complete`] takes.
+    pub fn check_and_complete(&self, key: &K) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(ids) = inner.keys.get(key).cloned() else {
+            return 0;
+        };
+
+        let mut completed = 0;
+        for id in ids {
+            let watched = inner
+                .operations
+                .get_mut(&id)
+                .expect("watched operations are removed from their keys");
+            if let Some(output) = watched.operation.try_complete() {
+                let watched = inner.remove(id);
+                let _ = watched.completion.send(output);
+                completed += 1;
+            }
+        }
+        completed
+    }
+
+    /// Expires the operations whose timeout passed by `now`. Returns how many expired.
+    pub fn expire(&self, now: Instant) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let mut expired = 0;
+        for id in inner.timer.advance(now) {
+            if !inner.operations.contains_key(&id) {
+                continue;
+            }
+            let watched = inner.remove(id);
+            let _ = watched.completion.send(watched.operation.on_expiration());
+            expired += 1;
+        }
+        expired
+    }
+
+    /// Resolves once there is an operation that may expire
+    async fn watching(&self) {
+        let added = self.added.notified();
+        if self.is_empty() {
+            added.await;
+        }
+    }
+}
+
+impl<K, O> Watchers<K, O>
+where
+    K: Eq + Hash,
+    O: DelayedOperation,
+{
+    /// Removes an operation along with its keys
+    fn remove(&mut self, id: u64) -> Watched<K, O> {
+        let watched = self
+            .operations
+            .remove(&id)
+            .expect("only watched operations are removed");
+        for key in &watched.keys {
+            if let Some(ids) = self.keys.get_mut(key) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.keys.remove(key);
+                }
+            }
+        }
+        watched
+    }
+}
+
+impl<K, O: DelayedOperation> std::fmt::Debug for Purgatory<K, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("Purgatory")
+            .field("name", &self.name)
+            .field("operations", &inner.operations.len())
+            .field("keys", &inner.keys.len())
+            .finish()
+    }
+}
+
+/// Expires the timed out operations of a purgatory every tick of its timing wheel,
+/// sleeping while it has no operations
+pub async fn run_expiration<K, O>(purgatory: Arc<Purgatory<K, O>>)
+where
+    K: Eq + Hash + Clone,
+    O: DelayedOperation,
+{
+    let mut interval = tokio::time::interval(TIMER_TICK);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        purgatory.watching().await;
+        interval.tick().await;
+        let expired = purgatory.expire(Instant::now());
+        if expired > 0 {
+            debug!(
+                "{expired} operations expired in the {} purgatory",
+                purgatory.name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use super::*;
+
+    /// Waits for a counter to reach a target, like a fetch waiting for enough bytes
+    struct WaitFor {
+        counter: Arc<AtomicI64>,
+        target: i64,
+    }
+
+    impl DelayedOperation for WaitFor {
+        type Output = Result<i64, i64>;
+
+        fn try_complete(&mut self) -> Option<Self::Output> {
+            let value = self.counter.load(Ordering::SeqCst);
+            (value >= self.target).then_some(Ok(value))
+        }
+
+        fn on_expiration(self) -> Self::Output {
+            Err(self.counter.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_on_key_change() {
+        let purgatory = Purgatory::new("test");
+        let counter = Arc::new(AtomicI64::new(0));
+        let wait_for = |target| WaitFor {
+            counter: Arc::clone(&counter),
+            target,
+        };
+        let timeout = Duration::from_secs(60);
+
+        let ready = purgatory.try_complete_else_watch(wait_for(0), vec!["a"], timeout);
+        assert_eq!(Ok(0), ready.await.unwrap());
+
+        let first = purgatory.try_complete_else_watch(wait_for(1), vec!["a", "b"], timeout);
+        let second = purgatory.try_complete_else_watch(wait_for(2), vec!["b"], timeout);
+        assert_eq!(2, purgatory.len());
+
+        counter.store(1, Ordering::SeqCst);
+        assert_eq!(0, purgatory.check_and_complete(&"c"));
+        assert_eq!(1, purgatory.check_and_complete(&"a"));
+        assert_eq!(Ok(1), first.await.unwrap());
+        counter.store(2, Ordering::SeqCst);
+        assert_eq!(1, purgatory.check_and_complete(&"b"));
+        assert_eq!(Ok(2), second.await.unwrap());
+        assert!(purgatory.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expire() {
+        let purgatory = Arc::new(Purgatory::new("test"));
+        let counter = Arc::new(AtomicI64::new(0));
+        let expiration = tokio::spawn(run_expiration(Arc::clone(&purgatory)));
+
+        let operation = WaitFor {
+            counter: Arc::clone(&counter),
+            target: 1,
+        };
+        let waiting =
+            purgatory.try_complete_else_watch(operation, vec![0], Duration::from_millis(50));
+        counter.store(-1, Ordering::SeqCst);
+        assert_eq!(Err(-1), waiting.await.unwrap());
+        assert!(purgatory.is_empty());
+        expiration.abort();
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// A hashed timing wheel: items are put in the slot of the tick they expire on,
+/// modulo the number of slots, so adding an item is O(1) and advancing the wheel
+/// only looks at the slots of the ticks that passed. Items more than a full turn
+/// away share their slot with nearer ones and are skipped until their tick comes.
+///
+/// Items never expire early, their deadline is rounded up to the next tick.
+#[derive(Debug)]
+pub struct TimingWheel<T> {
+    tick: Duration,
+    start: Instant,
+    /// The first tick not yet processed by [`Self::advance`]
+    current: u64,
+    slots: Vec<Vec<TimerEntry<T>>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct TimerEntry<T> {
+    expires: u64,
+    item: T,
+}
+
+impl<T> TimingWheel<T> {
+    pub fn new(tick: Duration, slots: usize, start: Instant) -> Self {
+        assert!(!tick.is_zero(), "Tick of a timing wheel must not be zero");
+        assert!(slots > 0, "Timing wheel needs at least one slot");
+        Self {
+            tick,
+            start,
+            current: 0,
+            slots: (0..slots).map(|_| Vec::new()).collect(),
+            len: 0,
+        }
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+
+    /// Number of items waiting to expire
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds an item expiring at `deadline`. Deadlines that already passed
+    /// expire on the next tick.
+    pub fn add(&mut self, deadline: Instant, item: T) {
+        let elapsed = deadline.saturating_duration_since(self.start).as_nanos();
+        let expires = (elapsed.div_ceil(self.tick.as_nanos()) as u64).max(self.current);
+        let slot = self.slot(expires);
+        self.slots[slot].push(TimerEntry { expires, item });
+        self.len += 1;
+    }
+
+    /// Removes and returns every item whose deadline is at or before `now`
+    pub fn advance(&mut self, now: Instant) -> Vec<T> {
+        let elapsed = now.saturating_duration_since(self.start).as_nanos();
+        let target = (elapsed / self.tick.as_nanos()) as u64;
+        if target < self.current {
+            return Vec::new();
+        }
+
+        // after a full turn every slot has been looked at
+        let ticks = (target - self.current + 1).min(self.slots.len() as u64);
+        let mut expired = Vec::new();
+        for tick in self.current..self.current + ticks {
+            let slot = self.slot(tick);
+            let entries = &mut self.slots[slot];
+            let mut i = 0;
+            while i < entries.len() {
+                if entries[i].expires <= target {
+                    expired.push(entries.swap_remove(i).item);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        self.current = target + 1;
+        self.len -= expired.len();
+        expired
+    }
+
+    fn slot(&self, tick: u64) -> usize {
+        (tick % self.slots.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut wheel = TimingWheel::new(ms(10), 4, start);
+        wheel.add(start + ms(5), "first");
+        wheel.add(start + ms(25), "second");
+        // more than a turn away, shares its slot with "second"
+        wheel.add(start + ms(65), "third");
+        assert_eq!(3, wheel.len());
+
+        // rounded up to the next tick
+        assert!(wheel.advance(start + ms(9)).is_empty());
+        assert_eq!(vec!["first"], wheel.advance(start + ms(10)));
+        assert_eq!(vec!["second"], wheel.advance(start + ms(30)));
+        // already passed, expires on the next tick
+        wheel.add(start, "late");
+        assert!(wheel.advance(start + ms(39)).is_empty());
+        assert_eq!(vec!["late"], wheel.advance(start + ms(40)));
+        assert!(wheel.advance(start + ms(60)).is_empty());
+        assert_eq!(vec!["third"], wheel.advance(start + ms(500)));
+        assert!(wheel.is_empty());
+    }
+}
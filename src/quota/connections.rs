@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::{IpAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+
+use super::Rate;
+use crate::config::BrokerConfig;
+
+/// Why a connection was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRejected {
+    /// `max.connections` are open already
+    MaxConnections(usize),
+    /// `max.connections.per.ip`, or the override of the address, are open already
+    MaxConnectionsPerIp(usize),
+}
+
+impl ConnectionRejected {
+    /// The reason as a metric label
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::MaxConnections(_) => "max_connections",
+            Self::MaxConnectionsPerIp(_) => "max_connections_per_ip",
+        }
+    }
+}
+
+impl Display for ConnectionRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MaxConnections(max) => write!(f, "the broker has {max} connections open"),
+            Self::MaxConnectionsPerIp(max) => write!(f, "the address has {max} connections open"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    by_ip: HashMap<IpAddr, usize>,
+}
+
+/// Limits how many connections are open, in total and by client address, and how
+/// fast new ones are accepted
+#[derive(Debug)]
+pub struct ConnectionQuotas {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    /// Limits of single addresses, replacing `max_connections_per_ip`
+    overrides: HashMap<IpAddr, usize>,
+    max_creation_rate: f64,
+    counts: Arc<Mutex<Counts>>,
+    creation_rate: Mutex<Rate>,
+}
+
+/// Counts a connection as open until dropped
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    counts: Arc<Mutex<Counts>>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.total -= 1;
+        if let Some(count) = counts.by_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.by_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+impl ConnectionQuotas {
+    /// Builds the limits of `config`. Host names among the per address overrides are
+    /// resolved once, every address they resolve to gets the override.
+    pub fn new(config: &BrokerConfig) -> anyhow::Result<Self> {
+        let mut overrides = HashMap::new();
+        for (host, &max) in &config.max_connections_per_ip_overrides {
+            let addrs = (host.as_str(), 0)
+                .to_socket_addrs()
+                .with_context(|| format!("Resolving {host} of max.connections.per.ip.overrides"))?;
+            for addr in addrs {
+                overrides.insert(addr.ip(), max);
+            }
+        }
+        Ok(Self {
+            max_connections: config.max_connections,
+            max_connections_per_ip: config.max_connections_per_ip,
+            overrides,
+            max_creation_rate: f64::from(config.max_connection_creation_rate),
+            counts: Arc::default(),
+            creation_rate: Mutex::new(Rate::new(config.quota_window_num, config.quota_window_size)),
+        })
+    }
+
+    /// Counts a connection from `ip` as open until the slot is dropped, unless
+    /// that would exceed one of the limits
+    pub fn try_open(&self, ip: IpAddr) -> Result<ConnectionSlot, ConnectionRejected> {
+        let mut counts = self.counts.lock().unwrap();
+        if counts.total >= self.max_connections {
+            return Err(ConnectionRejected::MaxConnections(self.max_connections));
+        }
+        let max_per_ip = self
+            .overrides
+            .get(&ip)
+            .copied()
+            .unwrap_or(self.max_connections_per_ip);
+        let per_ip = counts.by_ip.entry(ip).or_default();
+        if *per_ip >= max_per_ip {
+            if *per_ip == 0 {
+                counts.by_ip.remove(&ip);
+            }
+            return Err(ConnectionRejected::MaxConnectionsPerIp(max_per_ip));
+        }
+        *per_ip += 1;
+        counts.total += 1;
+        Ok(ConnectionSlot {
+            counts: Arc::clone(&self.counts),
+            ip,
+        })
+    }
+
+    /// Records a connection being accepted, returns how long to wait before accepting
+    /// the next one to stay within `max.connection.creation.rate`
+    pub fn record_creation(&self, now: Instant) -> Duration {
+        let mut rate = self.creation_rate.lock().unwrap();
+        rate.record(1.0, now);
+        let measured = rate.measure(now);
+        if measured <= self.max_creation_rate {
+            return Duration::ZERO;
+        }
+        rate.elapsed(now)
+            .mul_f64((measured - self.max_creation_rate) / self.max_creation_rate)
+    }
+
+    /// How many connections are open
+    pub fn open(&self) -> usize {
+        self.counts.lock().unwrap().total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let config = BrokerConfig {
+            max_connections: 3,
+            max_connections_per_ip: 2,
+            max_connections_per_ip_overrides: [("127.0.0.2".to_string(), 0)].into(),
+            ..Default::default()
+        };
+        let quotas = ConnectionQuotas::new(&config).unwrap();
+        let (a, b, c) = (
+            "127.0.0.1".parse().unwrap(),
+            "127.0.0.2".parse().unwrap(),
+            "127.0.0.3".parse().unwrap(),
+        );
+
+        let first = quotas.try_open(a).unwrap();
+        let _second = quotas.try_open(a).unwrap();
+        assert_eq!(
+            ConnectionRejected::MaxConnectionsPerIp(2),
+            quotas.try_open(a).unwrap_err()
+        );
+        assert_eq!(
+            ConnectionRejected::MaxConnectionsPerIp(0),
+            quotas.try_open(b).unwrap_err()
+        );
+        let _third = quotas.try_open(c).unwrap();
+        assert_eq!(
+            ConnectionRejected::MaxConnections(3),
+            quotas.try_open(c).unwrap_err()
+        );
+
+        drop(first);
+        assert_eq!(2, quotas.open());
+        quotas.try_open(a).unwrap();
+    }
+}
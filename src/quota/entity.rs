@@ -0,0 +1,185 @@
+use std::fmt::Display;
+
+use anyhow::{Context, bail, ensure};
+
+/// Entity type of quotas applying to an authenticated user
+pub const USER: &str = "user";
+/// Entity type of quotas applying to a client id
+pub const CLIENT_ID: &str = "client-id";
+
+/// Written in place of the name of a default entity
+const DEFAULT: &str = "<default>";
+
+/// The name of one component of a quota entity
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EntityName {
+    /// Applies to every user or client id without a quota of its own
+    Default,
+    Name(String),
+}
+
+impl EntityName {
+    /// The name as sent in requests, null standing for the default entity
+    pub fn from_request(name: Option<&str>) -> Self {
+        name.map_or(Self::Default, |n| Self::Name(n.to_string()))
+    }
+
+    pub fn to_response(&self) -> Option<String> {
+        match self {
+            Self::Default => None,
+            Self::Name(name) => Some(name.clone()),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        matches!(self, Self::Name(n) if n == name)
+    }
+}
+
+/// What a set of quotas applies to: a user, a client id, or a client id of a
+/// user, each of which may be the default entity
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuotaEntity {
+    pub user: Option<EntityName>,
+    pub client_id: Option<EntityName>,
+}
+
+impl QuotaEntity {
+    /// Builds an entity from the `(entity_type, entity_name)` pairs of a request
+    pub fn from_components<'a, I>(components: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut entity = Self::default();
+        for (entity_type, name) in components {
+            let component = match entity_type {
+                USER => &mut entity.user,
+                CLIENT_ID => &mut entity.client_id,
+                other => bail!("Unknown entity type {other}"),
+            };
+            ensure!(
+                component.is_none(),
+                "Entity type {entity_type} is given more than once"
+            );
+            *component = Some(EntityName::from_request(name));
+        }
+        ensure!(!entity.is_empty(), "Entity has no components");
+        Ok(entity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none() && self.client_id.is_none()
+    }
+
+    /// The `(entity_type, entity_name)` pairs of the entity, as sent in responses
+    pub fn components(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut components = Vec::with_capacity(2);
+        if let Some(user) = &self.user {
+            components.push((USER, user.to_response()));
+        }
+        if let Some(client_id) = &self.client_id {
+            components.push((CLIENT_ID, client_id.to_response()));
+        }
+        components
+    }
+
+    /// The entity as a path like Kafka used in ZooKeeper, e.g. `users/alice/clients/<default>`.
+    /// Names are percent encoded, so they never contain a slash or whitespace.
+    pub fn path(&self) -> String {
+        let name = |name: &EntityName| match name {
+            EntityName::Default => DEFAULT.to_string(),
+            EntityName::Name(name) => sanitize(name),
+        };
+        match (&self.user, &self.client_id) {
+            (Some(user), Some(client_id)) => {
+                format!("users/{}/clients/{}", name(user), name(client_id))
+            }
+            (Some(user), None) => format!("users/{}", name(user)),
+            (None, Some(client_id)) => format!("clients/{}", name(client_id)),
+            (None, None) => String::new(),
+        }
+    }
+
+    pub fn parse_path(path: &str) -> anyhow::Result<Self> {
+        let name = |name: &str| -> anyhow::Result<EntityName> {
+            Ok(match name {
+                DEFAULT => EntityName::Default,
+                name => EntityName::Name(desanitize(name)?),
+            })
+        };
+        let parts: Vec<_> = path.split('/').collect();
+        let entity = match parts.as_slice() {
+            ["users", user, "clients", client_id] => Self {
+                user: Some(name(user)?),
+                client_id: Some(name(client_id)?),
+            },
+            ["users", user] => Self {
+                user: Some(name(user)?),
+                client_id: None,
+            },
+            ["clients", client_id] => Self {
+                user: None,
+                client_id: Some(name(client_id)?),
+            },
+            _ => bail!("Invalid quota entity path {path:?}"),
+        };
+        Ok(entity)
+    }
+}
+
+impl Display for QuotaEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path())
+    }
+}
+
+/// Percent encodes everything but ASCII letters, digits and `._-`, like Kafka's `Sanitizer`
+pub(crate) fn sanitize(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() || b"._-".contains(&b) {
+            sanitized.push(b as char);
+        } else {
+            sanitized.push_str(&format!("%{b:02X}"));
+        }
+    }
+    sanitized
+}
+
+pub(crate) fn desanitize(name: &str) -> anyhow::Result<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut iter = name.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [
+                iter.next().context("Truncated escape")?,
+                iter.next().context("Truncated escape")?,
+            ];
+            let hex = std::str::from_utf8(&hex)?;
+            bytes.push(u8::from_str_radix(hex, 16).context("Invalid escape")?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).context("Name is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_roundtrip() {
+        let entity =
+            QuotaEntity::from_components([(CLIENT_ID, None), (USER, Some("CN=alice, O=acme/eu"))])
+                .unwrap();
+        assert_eq!(
+            "users/CN%3Dalice%2C%20O%3Dacme%2Feu/clients/<default>",
+            entity.path()
+        );
+        assert_eq!(entity, QuotaEntity::parse_path(&entity.path()).unwrap());
+
+        assert!(QuotaEntity::from_components([(USER, None), (USER, Some("bob"))]).is_err());
+        assert!(QuotaEntity::from_components([("ip", None)]).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, bail, ensure};
+
+use super::{EntityName, QuotaEntity, Rate};
+use crate::storage::atomic_write;
+
+/// What a quota limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaType {
+    /// Record bytes produced per second
+    Produce,
+    /// Record bytes fetched per second
+    Fetch,
+    /// Percentage of a handler thread's time spent on the client's requests
+    Request,
+}
+
+impl QuotaType {
+    pub const ALL: [Self; 3] = [Self::Produce, Self::Fetch, Self::Request];
+
+    /// The key the quota is set through
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Produce => "producer_byte_rate",
+            Self::Fetch => "consumer_byte_rate",
+            Self::Request => "request_percentage",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.key() == key)
+    }
+}
+
+/// How a DescribeClientQuotas filter matches the component of one entity type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentMatch {
+    /// The entity of that name
+    Exact(String),
+    /// The default entity
+    Default,
+    /// Any entity but the default one
+    Specified,
+}
+
+impl ComponentMatch {
+    fn matches(&self, name: &EntityName) -> bool {
+        match (self, name) {
+            (Self::Exact(expected), name) => name.matches(expected),
+            (Self::Default, EntityName::Default) => true,
+            (Self::Specified, EntityName::Name(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Sets `key` to the value, or removes it if there is none
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaOp {
+    pub key: String,
+    pub value: Option<f64>,
+}
+
+/// Quotas by entity and key
+type Quotas = BTreeMap<QuotaEntity, BTreeMap<String, f64>>;
+
+/// Client quotas and the rates they are enforced on. Quotas are set through
+/// AlterClientQuotas and stored in the first log directory, so they survive restarts:
+///
+/// ```text
+/// 0                                                 <- version
+/// users/alice producer_byte_rate=1048576            <- entity path, then its quotas
+/// users/<default>/clients/app request_percentage=50
+/// ```
+#[derive(Debug)]
+pub struct QuotaManager {
+    path: PathBuf,
+    num_windows: u32,
+    window_size: Duration,
+    quotas: RwLock<Quotas>,
+    /// Rates of the clients sharing a quota, by quota type and the entity whose names they
+    /// share. A user quota is shared by all of the user's client ids, while a default user
+    /// quota gives every user a rate of their own.
+    rates: Mutex<HashMap<(QuotaType, QuotaEntity), Rate>>,
+}
+
+impl QuotaManager {
+    const VERSION: u32 = 0;
+    const FILE_NAME: &str = "client-quotas";
+
+    /// Loads the quotas stored in `dir`. Rates are measured over `num_windows` windows
+    /// of `window_size` each.
+    pub fn load(dir: &Path, num_windows: u32, window_size: Duration) -> anyhow::Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        let quotas = Self::read(&path)?;
+        Ok(Self {
+            path,
+            num_windows,
+            window_size,
+            quotas: RwLock::new(quotas),
+            rates: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Quotas> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Quotas::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing quota file version")?
+            .trim()
+            .parse()?;
+        ensure!(
+            version == Self::VERSION,
+            "Unsupported quota file version {version} in {}",
+            path.display()
+        );
+
+        let mut quotas = Quotas::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let entity = QuotaEntity::parse_path(fields.next().unwrap_or_default())?;
+            let mut values = BTreeMap::new();
+            for field in fields {
+                let Some((key, value)) = field.split_once('=') else {
+                    bail!("Malformed quota {field:?} of {entity}");
+                };
+                values.insert(key.to_string(), value.parse()?);
+            }
+            quotas.insert(entity, values);
+        }
+        Ok(quotas)
+    }
+
+    /// Replaces the stored quotas
+    fn write(&self, quotas: &Quotas) -> anyhow::Result<()> {
+        let mut content = format!("{}\n", Self::VERSION);
+        for (entity, values) in quotas {
+            content.push_str(&entity.path());
+            for (key, value) in values {
+                content.push_str(&format!(" {key}={value}"));
+            }
+            content.push('\n');
+        }
+
+        atomic_write(&self.path, content.as_bytes())
+    }
+
+    /// The quotas of every entity matching all of `components`, given as pairs of entity
+    /// type and match. With `strict` set, entities with components of other types do not
+    /// match.
+    pub fn describe(
+        &self,
+        components: &[(String, ComponentMatch)],
+        strict: bool,
+    ) -> anyhow::Result<Vec<(QuotaEntity, BTreeMap<String, f64>)>> {
+        let mut user = None;
+        let mut client_id = None;
+        for (entity_type, component) in components {
+            let filter = match entity_type.as_str() {
+                super::USER => &mut user,
+                super::CLIENT_ID => &mut client_id,
+                other => bail!("Unknown entity type {other}"),
+            };
+            ensure!(
+                filter.is_none(),
+                "Entity type {entity_type} is filtered more than once"
+            );
+            *filter = Some(component);
+        }
+
+        let matches =
+            |filter: Option<&ComponentMatch>, name: Option<&EntityName>| match (filter, name) {
+                (Some(filter), Some(name)) => filter.matches(name),
+                (Some(_), None) => false,
+                (None, Some(_)) => !strict,
+                (None, None) => true,
+            };
+        let quotas = self.quotas.read().unwrap();
+        Ok(quotas
+            .iter()
+            .filter(|(entity, _)| {
+                matches(user, entity.user.as_ref()) && matches(client_id, entity.client_id.as_ref())
+            })
+            .map(|(entity, values)| (entity.clone(), values.clone()))
+            .collect())
+    }
+
+    /// Checks that `ops` can be applied to `entity`
+    pub fn validate(entity: &QuotaEntity, ops: &[QuotaOp]) -> anyhow::Result<()> {
+        ensure!(!entity.is_empty(), "Entity has no components");
+        let mut keys = HashSet::new();
+        for op in ops {
+            ensure!(
+                keys.insert(&op.key),
+                "Quota {} is altered more than once",
+                op.key
+            );
+            let quota_type = QuotaType::from_key(&op.key)
+                .with_context(|| format!("Unknown quota {}", op.key))?;
+            let Some(value) = op.value else {
+                continue;
+            };
+            ensure!(
+                value.is_finite() && value > 0.0,
+                "Quota {} must be positive, not {value}",
+                op.key
+            );
+            if quota_type != QuotaType::Request {
+                ensure!(
+                    value.fract() == 0.0,
+                    "Quota {} must be a whole number of bytes, not {value}",
+                    op.key
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies already validated changes and stores the result. Either every change is
+    /// applied or, if storing fails, none is.
+    pub fn alter(&self, changes: Vec<(QuotaEntity, Vec<QuotaOp>)>) -> anyhow::Result<()> {
+        let mut quotas = self.quotas.write().unwrap();
+        let mut altered = quotas.clone();
+        for (entity, ops) in changes {
+            let values = altered.entry(entity.clone()).or_default();
+            for op in ops {
+                match op.value {
+                    Some(value) => values.insert(op.key, value),
+                    None => values.remove(&op.key),
+                };
+            }
+            if values.is_empty() {
+                altered.remove(&entity);
+            }
+        }
+        self.write(&altered)?;
+        *quotas = altered;
+        Ok(())
+    }
+
+    /// The quota of `quota_type` applying to `client_id` of `user`, along with the entity
+    /// identifying the clients sharing it. Quotas are looked up from the most to the least
+    /// specific entity, as in Kafka:
+    ///
+    /// 1. `users/<user>/clients/<client-id>`
+    /// 2. `users/<user>/clients/<default>`
+    /// 3. `users/<user>`
+    /// 4. `users/<default>/clients/<client-id>`
+    /// 5. `users/<default>/clients/<default>`
+    /// 6. `users/<default>`
+    /// 7. `clients/<client-id>`
+    /// 8. `clients/<default>`
+    pub fn quota(
+        &self,
+        quota_type: QuotaType,
+        user: &str,
+        client_id: &str,
+    ) -> Option<(f64, QuotaEntity)> {
+        let name = |name: &str| Some(EntityName::Name(name.to_string()));
+        let default = || Some(EntityName::Default);
+        let candidates = [
+            (name(user), name(client_id)),
+            (name(user), default()),
+            (name(user), None),
+            (default(), name(client_id)),
+            (default(), default()),
+            (default(), None),
+            (None, name(client_id)),
+            (None, default()),
+        ];
+
+        let quotas = self.quotas.read().unwrap();
+        candidates.into_iter().find_map(|(u, c)| {
+            let shared_by = QuotaEntity {
+                user: u.as_ref().and(name(user)),
+                client_id: c.as_ref().and(name(client_id)),
+            };
+            let entity = QuotaEntity {
+                user: u,
+                client_id: c,
+            };
+            let bound = *quotas.get(&entity)?.get(quota_type.key())?;
+            Some((bound, shared_by))
+        })
+    }
+
+    /// Records `value` against the quota of `quota_type` applying to the client and
+    /// returns how long the client should be throttled for, zero while within its quota.
+    /// A client over its quota is throttled for as long as it takes its rate to fall back
+    /// to the quota, at most the time the rate is measured over.
+    pub fn record(
+        &self,
+        quota_type: QuotaType,
+        user: &str,
+        client_id: &str,
+        value: f64,
+        now: Instant,
+    ) -> Duration {
+        let Some((bound, shared_by)) = self.quota(quota_type, user, client_id) else {
+            return Duration::ZERO;
+        };
+
+        let mut rates = self.rates.lock().unwrap();
+        let key = (quota_type, shared_by);
+        if !rates.contains_key(&key) {
+            // the rates of clients gone quiet are forgotten when new ones show up
+            rates.retain(|_, rate| !rate.is_idle(now));
+        }
+        let rate = rates
+            .entry(key)
+            .or_insert_with(|| Rate::new(self.num_windows, self.window_size));
+        rate.record(value, now);
+
+        let measured = rate.measure(now);
+        if measured <= bound {
+            return Duration::ZERO;
+        }
+        let window = rate.elapsed(now);
+        let throttle = window.mul_f64((measured - bound) / bound);
+        throttle.min(self.window_size * self.num_windows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(user: Option<&str>, client_id: Option<&str>) -> QuotaEntity {
+        let name = |n: &str| match n {
+            "" => EntityName::Default,
+            n => EntityName::Name(n.to_string()),
+        };
+        QuotaEntity {
+            user: user.map(name),
+            client_id: client_id.map(name),
+        }
+    }
+
+    fn set(key: &str, value: f64) -> QuotaOp {
+        QuotaOp {
+            key: key.to_string(),
+            value: Some(value),
+        }
+    }
+
+    #[test]
+    fn test_alter_and_describe() {
+        let dir = tempfile::tempdir().unwrap();
+        let second = Duration::from_secs(1);
+        let manager = QuotaManager::load(dir.path(), 11, second).unwrap();
+        let changes = vec![
+            (
+                entity(Some("alice"), None),
+                vec![set("producer_byte_rate", 1024.0)],
+            ),
+            (
+                entity(Some(""), Some("app")),
+                vec![set("request_percentage", 12.5)],
+            ),
+            (
+                entity(None, Some("")),
+                vec![set("consumer_byte_rate", 2048.0)],
+            ),
+        ];
+        for (entity, ops) in &changes {
+            QuotaManager::validate(entity, ops).unwrap();
+        }
+        assert!(QuotaManager::validate(&entity(None, Some("")), &[set("bogus", 1.0)]).is_err());
+        assert!(
+            QuotaManager::validate(&entity(None, Some("")), &[set("producer_byte_rate", 0.5)])
+                .is_err()
+        );
+        manager.alter(changes).unwrap();
+
+        // survives a restart
+        let manager = QuotaManager::load(dir.path(), 11, second).unwrap();
+        let users = manager
+            .describe(&[("user".to_string(), ComponentMatch::Specified)], false)
+            .unwrap();
+        assert_eq!(1, users.len());
+        assert_eq!(entity(Some("alice"), None), users[0].0);
+        let default_users = [("user".to_string(), ComponentMatch::Default)];
+        assert_eq!(1, manager.describe(&default_users, false).unwrap().len());
+        assert!(manager.describe(&default_users, true).unwrap().is_empty());
+        assert_eq!(3, manager.describe(&[], false).unwrap().len());
+
+        // alice's own quota wins over the default user's one, which wins over client ids
+        let (bound, shared_by) = manager.quota(QuotaType::Produce, "alice", "app").unwrap();
+        assert_eq!(1024.0, bound);
+        assert_eq!(entity(Some("alice"), None), shared_by);
+        let (bound, shared_by) = manager.quota(QuotaType::Request, "bob", "app").unwrap();
+        assert_eq!(12.5, bound);
+        assert_eq!(entity(Some("bob"), Some("app")), shared_by);
+        assert_eq!(None, manager.quota(QuotaType::Produce, "bob", "app"));
+
+        manager
+            .alter(vec![(
+                entity(Some("alice"), None),
+                vec![QuotaOp {
+                    key: "producer_byte_rate".to_string(),
+                    value: None,
+                }],
+            )])
+            .unwrap();
+        assert_eq!(2, manager.describe(&[], false).unwrap().len());
+    }
+
+    #[test]
+    fn test_throttle() {
+        let dir = tempfile::tempdir().unwrap();
+        let second = Duration::from_secs(1);
+        let manager = QuotaManager::load(dir.path(), 3, second).unwrap();
+        manager
+            .alter(vec![(
+                entity(None, Some("")),
+                vec![set("producer_byte_rate", 100.0)],
+            )])
+            .unwrap();
+
+        let now = Instant::now();
+        let record = |value| manager.record(QuotaType::Produce, "ANONYMOUS", "app", value, now);
+        // 200 bytes over 2 seconds is within the quota
+        assert_eq!(Duration::ZERO, record(200.0));
+        // 400 bytes are 100% over it, so the client waits as long again
+        assert_eq!(second * 2, record(200.0));
+        // but never longer than the rate is measured over
+        assert_eq!(second * 3, record(1000.0));
+        // other client ids have their own rate
+        assert_eq!(
+            Duration::ZERO,
+            manager.record(QuotaType::Produce, "ANONYMOUS", "other", 200.0, now)
+        );
+    }
+}
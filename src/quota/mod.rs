@@ -0,0 +1,22 @@
+//! Client quotas, limiting the produce and fetch byte rates and the share of handler
+//! time of users and client ids. A client over its quota gets a `throttle_time_ms` in
+//! its responses telling it to back off, and its connection is muted for that long, so
+//! clients ignoring it are slowed down just the same.
+//!
+//! Quotas are set on entities made of a user, a client id or both, where either may be
+//! the default entity applying to everyone without a quota of their own.
+//!
+//! Connections are limited separately, by [`ConnectionQuotas`].
+mod connections;
+mod entity;
+mod manager;
+mod rate;
+
+pub use connections::{ConnectionQuotas, ConnectionRejected, ConnectionSlot};
+pub use entity::{CLIENT_ID, EntityName, QuotaEntity, USER};
+pub(crate) use entity::{desanitize, sanitize};
+pub use manager::{ComponentMatch, QuotaManager, QuotaOp, QuotaType};
+pub use rate::Rate;
+
+/// The principal of connections that did not authenticate
+pub const ANONYMOUS: &str = "ANONYMOUS";
@@ -0,0 +1,86 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A rate per second measured over a number of sampled windows, like Kafka's `Rate`.
+/// Windows older than all of them together are dropped, so the rate follows the recent
+/// past only.
+#[derive(Debug, Clone)]
+pub struct Rate {
+    /// Start and total of every window still in use, oldest first
+    samples: VecDeque<(Instant, f64)>,
+    num_windows: u32,
+    window_size: Duration,
+}
+
+impl Rate {
+    pub fn new(num_windows: u32, window_size: Duration) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(num_windows as usize),
+            num_windows,
+            window_size,
+        }
+    }
+
+    pub fn record(&mut self, value: f64, now: Instant) {
+        self.purge(now);
+        match self.samples.back_mut() {
+            Some((start, total)) if now < *start + self.window_size => *total += value,
+            _ => self.samples.push_back((now, value)),
+        }
+    }
+
+    /// The rate per second at `now`
+    pub fn measure(&mut self, now: Instant) -> f64 {
+        self.purge(now);
+        let total: f64 = self.samples.iter().map(|(_, total)| total).sum();
+        total / self.elapsed(now).as_secs_f64()
+    }
+
+    /// The time the rate is measured over. Counts at least all but one of the windows,
+    /// as Kafka does, so a burst right after the first record is not taken for a
+    /// huge rate.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        let elapsed = self.samples.front().map_or(Duration::ZERO, |&(start, _)| {
+            now.saturating_duration_since(start)
+        });
+        elapsed.max(self.window_size * (self.num_windows - 1).max(1))
+    }
+
+    /// Whether nothing was recorded for as long as every window together lasts
+    pub fn is_idle(&mut self, now: Instant) -> bool {
+        self.purge(now);
+        self.samples.is_empty()
+    }
+
+    fn purge(&mut self, now: Instant) {
+        let expiry = self.window_size * self.num_windows;
+        while let Some(&(start, _)) = self.samples.front() {
+            if start + expiry > now {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        let second = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut rate = Rate::new(3, second);
+        // measured over at least 2 windows
+        rate.record(100.0, start);
+        assert_eq!(50.0, rate.measure(start));
+        rate.record(200.0, start + second * 2);
+        assert_eq!(100.0, rate.measure(start + second * 3));
+        // the first window is dropped once all 3 have passed
+        assert_eq!(100.0, rate.measure(start + second * 4));
+        assert!(rate.is_idle(start + second * 5));
+    }
+}
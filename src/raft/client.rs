@@ -0,0 +1,916 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use bytes::BytesMut;
+use tokio::sync::{Notify, watch};
+use tracing::{debug, info, warn};
+
+use super::{
+    METADATA_TOPIC,
+    control::LeaderChangeMessage,
+    log::RaftLog,
+    snapshot::Snapshots,
+    state::{EpochState, QuorumState, ReplicaProgress},
+};
+use crate::{
+    codec::{Decoder, Encoder},
+    config::BrokerConfig,
+    handlers::DelayedRaftFetch,
+    primitives::CompactArray,
+    purgatory::Purgatory,
+    request::{
+        BeginQuorumEpochPartition, BeginQuorumEpochRequestBody, DescribeQuorumRequestBody,
+        EndQuorumEpochPartition, EndQuorumEpochRequestBody, FetchPartition, FetchRequestBody,
+        FetchSnapshotRequestBody, FetchTopic, VotePartition, VoteRequestBody,
+    },
+    response::body::{
+        BeginQuorumEpochResponseBody, DescribeQuorumPartitionResponse, DescribeQuorumResponseBody,
+        DescribeQuorumTopicResponse, FetchPartitionResponse, FetchResponseBody,
+        FetchSnapshotPartitionResponse, FetchSnapshotResponseBody, FetchSnapshotTopicResponse,
+        QuorumEpochPartitionResponse, ReplicaState, VotePartitionResponse, VoteResponseBody,
+    },
+    storage::TopicPartition,
+    types::{ErrorCode, LeaderIdAndEpoch, Record, RecordBatch},
+};
+
+/// The quorum settings of a node, taken from its config
+#[derive(Debug, Clone)]
+pub struct RaftConfig {
+    pub node_id: i32,
+    pub cluster_id: String,
+    /// The address of the controller listener of every voter, by node id
+    pub voters: BTreeMap<i32, String>,
+    pub election_timeout: Duration,
+    pub fetch_timeout: Duration,
+    pub election_backoff_max: Duration,
+    pub request_timeout: Duration,
+}
+
+impl RaftConfig {
+    pub fn new(config: &BrokerConfig, cluster_id: &str) -> Self {
+        Self {
+            node_id: config.node_id,
+            cluster_id: cluster_id.to_string(),
+            voters: config
+                .controller_quorum_voters
+                .iter()
+                .map(|v| (v.id, v.address()))
+                .collect(),
+            election_timeout: config.controller_quorum_election_timeout,
+            fetch_timeout: config.controller_quorum_fetch_timeout,
+            election_backoff_max: config.controller_quorum_election_backoff_max,
+            request_timeout: config.controller_quorum_request_timeout,
+        }
+    }
+}
+
+/// The replica of the `__cluster_metadata` log on this node, and its part in the quorum
+/// replicating it: voters elect a leader among themselves, which appends to the log and
+/// commits records once a majority of the voters has them, while brokers that are not
+/// voters follow the log as observers.
+///
+/// Requests of the other nodes are answered by the `handle_` methods, what this node
+/// sends is driven by [`run_driver`](super::run_driver).
+#[derive(Debug)]
+pub struct RaftClient {
+    config: RaftConfig,
+    inner: Mutex<Inner>,
+    /// Offsets below it are committed, 0 until this node learns of any
+    high_watermark: watch::Sender<i64>,
+    /// Wakes the driver after the handlers changed the quorum state
+    changed: Notify,
+    /// Fetches of the metadata log waiting for records
+    fetch_purgatory: Arc<Purgatory<TopicPartition, DelayedRaftFetch>>,
+    snapshots: Snapshots,
+}
+
+#[derive(Debug)]
+struct Inner {
+    quorum: QuorumState,
+    log: RaftLog,
+    high_watermark: i64,
+    /// How long to wait before running for election instead of the election timeout,
+    /// after the leader resigned and preferred this node
+    election_delay: Option<Duration>,
+}
+
+/// What the driver has to do in the current epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    Leader,
+    Candidate,
+    Follower {
+        leader_id: i32,
+    },
+    /// No leader is known, voters run for election once the delay passed, observers
+    /// ask the voters who leads
+    Unattached {
+        election_delay: Option<Duration>,
+    },
+}
+
+impl RaftClient {
+    /// Opens the metadata log and quorum state kept in the first log dir
+    pub fn new(config: &BrokerConfig, cluster_id: &str) -> anyhow::Result<Self> {
+        Self::open(RaftConfig::new(config, cluster_id), &config.log_dirs[0])
+    }
+
+    pub fn open(config: RaftConfig, log_dir: &Path) -> anyhow::Result<Self> {
+        let voters = config.voters.keys().copied().collect();
+        let quorum =
+            QuorumState::load(log_dir, config.node_id, voters).context("Loading quorum state")?;
+        let log = RaftLog::open(log_dir).context("Opening metadata log")?;
+        info!(
+            "Opened metadata log ending at {} in epoch {}",
+            log.end_offset(),
+            log.last_epoch()
+        );
+        Ok(Self {
+            config,
+            inner: Mutex::new(Inner {
+                quorum,
+                log,
+                high_watermark: 0,
+                election_delay: None,
+            }),
+            high_watermark: watch::Sender::new(0),
+            changed: Notify::new(),
+            fetch_purgatory: Arc::new(Purgatory::new("RaftFetch")),
+            snapshots: Snapshots::new(log_dir.join(Self::topic_partition().dir_name())),
+        })
+    }
+
+    /// The partition of the metadata log, the only one of its topic
+    pub fn topic_partition() -> TopicPartition {
+        TopicPartition::new(METADATA_TOPIC, 0)
+    }
+
+    pub fn config(&self) -> &RaftConfig {
+        &self.config
+    }
+
+    pub fn node_id(&self) -> i32 {
+        self.config.node_id
+    }
+
+    pub fn is_voter(&self) -> bool {
+        self.config.voters.contains_key(&self.config.node_id)
+    }
+
+    /// The snapshots of the metadata image, next to the log
+    pub fn snapshots(&self) -> &Snapshots {
+        &self.snapshots
+    }
+
+    pub fn fetch_purgatory(&self) -> &Arc<Purgatory<TopicPartition, DelayedRaftFetch>> {
+        &self.fetch_purgatory
+    }
+
+    /// The leader of the current epoch, -1 if it is not known
+    pub fn leader(&self) -> LeaderIdAndEpoch {
+        let inner = self.inner.lock().unwrap();
+        Self::leader_of(&inner)
+    }
+
+    fn leader_of(inner: &Inner) -> LeaderIdAndEpoch {
+        LeaderIdAndEpoch::new(inner.quorum.leader_id().unwrap_or(-1), inner.quorum.epoch())
+    }
+
+    pub fn is_leader(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        matches!(inner.quorum.state(), EpochState::Leader(_))
+    }
+
+    /// The epoch of the quorum and what the driver has to do in it
+    pub fn role(&self) -> (i32, Role) {
+        let inner = self.inner.lock().unwrap();
+        let role = match inner.quorum.state() {
+            EpochState::Leader(_) => Role::Leader,
+            EpochState::Candidate { .. } => Role::Candidate,
+            EpochState::Follower { leader_id } => Role::Follower {
+                leader_id: *leader_id,
+            },
+            EpochState::Unattached | EpochState::Voted { .. } => Role::Unattached {
+                election_delay: inner.election_delay,
+            },
+        };
+        (inner.quorum.epoch(), role)
+    }
+
+    /// Resolves after the handlers changed the quorum state
+    pub async fn changed(&self) {
+        self.changed.notified().await;
+    }
+
+    /// Offsets below the high watermark are committed
+    pub fn high_watermark(&self) -> i64 {
+        *self.high_watermark.borrow()
+    }
+
+    /// Follows the high watermark
+    pub fn subscribe(&self) -> watch::Receiver<i64> {
+        self.high_watermark.subscribe()
+    }
+
+    fn set_high_watermark(&self, inner: &mut Inner, high_watermark: i64) {
+        if high_watermark > inner.high_watermark {
+            inner.high_watermark = high_watermark;
+            self.high_watermark.send_replace(high_watermark);
+        }
+    }
+
+    /// Appends records as the leader, returning the offset of the first one. They are
+    /// committed once a majority of the voters fetched them.
+    ///
+    /// # Errors
+    ///
+    /// Fails if this node is not the leader, or the records cannot be written
+    pub fn append(&self, records: Vec<Record>) -> anyhow::Result<i64> {
+        self.append_with(|_| Ok(records))
+    }
+
+    /// Appends the records `build` returns as the leader, handing it the offset the
+    /// first one will get. Lets records hold their own offset, like the epoch of a
+    /// broker registration.
+    ///
+    /// # Errors
+    ///
+    /// Fails if this node is not the leader, `build` fails, or the records cannot be
+    /// written
+    pub fn append_with<F>(&self, build: F) -> anyhow::Result<i64>
+    where
+        F: FnOnce(i64) -> anyhow::Result<Vec<Record>>,
+    {
+        let base_offset = {
+            let mut inner = self.inner.lock().unwrap();
+            anyhow::ensure!(
+                matches!(inner.quorum.state(), EpochState::Leader(_)),
+                "Node {} is not the leader of the quorum",
+                self.node_id()
+            );
+            let epoch = inner.quorum.epoch();
+            let records = build(inner.log.end_offset())?;
+            let batch = RecordBatch::new(0, now_ms(), records);
+            let base_offset = inner.log.append_as_leader(batch, epoch)?;
+            inner.log.flush()?;
+            self.update_leader_end_offset(&mut inner)?;
+            base_offset
+        };
+        self.fetch_purgatory
+            .check_and_complete(&Self::topic_partition());
+        Ok(base_offset)
+    }
+
+    /// Moves the leader's own progress to its log end offset, which may commit records
+    /// on its own in a quorum of one
+    fn update_leader_end_offset(&self, inner: &mut Inner) -> anyhow::Result<()> {
+        let end_offset = inner.log.end_offset();
+        let node_id = self.node_id();
+        let Some(leader) = inner.quorum.leader_state_mut() else {
+            return Ok(());
+        };
+        if leader.update_replica(node_id, end_offset, end_offset, SystemTime::now()) {
+            let high_watermark = leader.high_watermark.unwrap_or_default();
+            self.set_high_watermark(inner, high_watermark);
+        }
+        Ok(())
+    }
+
+    /// The committed batches from `offset` on, up to `max_bytes`
+    pub fn read_committed(
+        &self,
+        offset: i64,
+        max_bytes: usize,
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        let inner = self.inner.lock().unwrap();
+        inner.log.read(offset, inner.high_watermark, max_bytes)
+    }
+
+    /// Learns of `leader_id` leading `epoch`, from a request or a response. Moves to the
+    /// epoch if it is later than the current one, and follows the leader if it was not
+    /// known yet. -1 stands for an unknown leader.
+    fn observe_leader(&self, inner: &mut Inner, epoch: i32, leader_id: i32) -> anyhow::Result<()> {
+        let current = inner.quorum.epoch();
+        let known = inner.quorum.leader_id();
+        if epoch > current {
+            if leader_id >= 0 && leader_id != self.node_id() {
+                inner.quorum.transition_to_follower(epoch, leader_id)?;
+            } else {
+                inner.quorum.transition_to_unattached(epoch)?;
+            }
+        } else if epoch == current
+            && leader_id >= 0
+            && leader_id != self.node_id()
+            && known.is_none()
+        {
+            inner.quorum.transition_to_follower(epoch, leader_id)?;
+        } else {
+            return Ok(());
+        }
+        inner.election_delay = None;
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// Whether a request of `cluster_id` may be answered. Requests without one are.
+    pub fn is_same_cluster(&self, cluster_id: Option<&str>) -> bool {
+        cluster_id.map_or(true, |id| id == self.config.cluster_id)
+    }
+
+    fn is_metadata_partition(topic: &str, partition: i32) -> bool {
+        topic == METADATA_TOPIC && partition == 0
+    }
+
+    /// Votes for a candidate, at most one per epoch, and only for candidates whose log is
+    /// at least as long as the one of this node
+    pub fn handle_vote(&self, request: &VoteRequestBody) -> anyhow::Result<VoteResponseBody> {
+        if !self.is_same_cluster(request.cluster_id.as_deref()) {
+            return Ok(VoteResponseBody::error(ErrorCode::InconsistentClusterId));
+        }
+        let Some((topic, p)) = request
+            .topics
+            .iter()
+            .flat_map(|t| t.partitions.iter().map(move |p| (t, p)))
+            .next()
+        else {
+            return Ok(VoteResponseBody::error(ErrorCode::InvalidRequest));
+        };
+        let respond = |code: ErrorCode, leader: &LeaderIdAndEpoch, granted: bool| {
+            let partition = VotePartitionResponse::new(
+                p.partition_index,
+                code,
+                leader.leader_id,
+                leader.leader_epoch,
+                granted,
+            );
+            VoteResponseBody::new(topic.topic_name.0.clone(), partition)
+        };
+        let mut inner = self.inner.lock().unwrap();
+        if !Self::is_metadata_partition(&topic.topic_name.0, p.partition_index) {
+            let leader = Self::leader_of(&inner);
+            return Ok(respond(ErrorCode::UnknownTopicOrPartition, &leader, false));
+        }
+        if !inner.quorum.is_voter(self.node_id()) || !inner.quorum.is_voter(p.candidate_id) {
+            let leader = Self::leader_of(&inner);
+            return Ok(respond(ErrorCode::InconsistentVoterSet, &leader, false));
+        }
+        if p.candidate_epoch < inner.quorum.epoch() {
+            let leader = Self::leader_of(&inner);
+            return Ok(respond(ErrorCode::FencedLeaderEpoch, &leader, false));
+        }
+        if p.candidate_epoch > inner.quorum.epoch() {
+            inner.quorum.transition_to_unattached(p.candidate_epoch)?;
+            self.changed.notify_one();
+        }
+
+        let granted = match *inner.quorum.state() {
+            EpochState::Voted { candidate_id } => candidate_id == p.candidate_id,
+            EpochState::Unattached => {
+                let last = (inner.log.last_epoch(), inner.log.end_offset());
+                let up_to_date = (p.last_offset_epoch, p.last_offset) >= last;
+                if up_to_date {
+                    inner.quorum.transition_to_voted(p.candidate_id)?;
+                    self.changed.notify_one();
+                }
+                up_to_date
+            }
+            _ => false,
+        };
+        debug!(
+            "Vote for candidate {} in epoch {}: {granted}",
+            p.candidate_id, p.candidate_epoch
+        );
+        let leader = Self::leader_of(&inner);
+        Ok(respond(ErrorCode::None, &leader, granted))
+    }
+
+    /// Follows the leader announcing its election
+    pub fn handle_begin_quorum_epoch(
+        &self,
+        request: &BeginQuorumEpochRequestBody,
+    ) -> anyhow::Result<BeginQuorumEpochResponseBody> {
+        if !self.is_same_cluster(request.cluster_id.as_deref()) {
+            return Ok(BeginQuorumEpochResponseBody::error(
+                ErrorCode::InconsistentClusterId,
+            ));
+        }
+        let Some((topic, p)) = request
+            .topics
+            .iter()
+            .flat_map(|t| t.partitions.iter().map(move |p| (t, p)))
+            .next()
+        else {
+            return Ok(BeginQuorumEpochResponseBody::error(
+                ErrorCode::InvalidRequest,
+            ));
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let code = if !Self::is_metadata_partition(&topic.topic_name.0, p.partition_index) {
+            ErrorCode::UnknownTopicOrPartition
+        } else if p.leader_epoch < inner.quorum.epoch() {
+            ErrorCode::FencedLeaderEpoch
+        } else {
+            self.observe_leader(&mut inner, p.leader_epoch, p.leader_id)?;
+            ErrorCode::None
+        };
+        let leader = Self::leader_of(&inner);
+        let partition = QuorumEpochPartitionResponse::new(
+            p.partition_index,
+            code,
+            leader.leader_id,
+            leader.leader_epoch,
+        );
+        Ok(BeginQuorumEpochResponseBody::new(
+            topic.topic_name.0.clone(),
+            partition,
+        ))
+    }
+
+    /// Gives up on the leader resigning, running for election after a delay that is the
+    /// shorter the more the leader prefers this node
+    pub fn handle_end_quorum_epoch(
+        &self,
+        request: &EndQuorumEpochRequestBody,
+    ) -> anyhow::Result<BeginQuorumEpochResponseBody> {
+        if !self.is_same_cluster(request.cluster_id.as_deref()) {
+            return Ok(BeginQuorumEpochResponseBody::error(
+                ErrorCode::InconsistentClusterId,
+            ));
+        }
+        let Some((topic, p)) = request
+            .topics
+            .iter()
+            .flat_map(|t| t.partitions.iter().map(move |p| (t, p)))
+            .next()
+        else {
+            return Ok(BeginQuorumEpochResponseBody::error(
+                ErrorCode::InvalidRequest,
+            ));
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let code = if !Self::is_metadata_partition(&topic.topic_name.0, p.partition_index) {
+            ErrorCode::UnknownTopicOrPartition
+        } else if p.leader_epoch < inner.quorum.epoch() {
+            ErrorCode::FencedLeaderEpoch
+        } else {
+            let resigned = p.leader_epoch > inner.quorum.epoch()
+                || inner.quorum.leader_id() == Some(p.leader_id);
+            if resigned && p.leader_id != self.node_id() {
+                inner.quorum.transition_to_unattached(p.leader_epoch)?;
+                let position = p
+                    .preferred_candidates
+                    .iter()
+                    .position(|c| c.candidate_id == self.node_id())
+                    .unwrap_or(self.config.voters.len());
+                let step = self.config.election_backoff_max / self.config.voters.len() as u32;
+                inner.election_delay = Some(step * position as u32);
+                self.changed.notify_one();
+            }
+            ErrorCode::None
+        };
+        let leader = Self::leader_of(&inner);
+        let partition = QuorumEpochPartitionResponse::new(
+            p.partition_index,
+            code,
+            leader.leader_id,
+            leader.leader_epoch,
+        );
+        Ok(BeginQuorumEpochResponseBody::new(
+            topic.topic_name.0.clone(),
+            partition,
+        ))
+    }
+
+    /// Reads the metadata log for a replica, which only the leader of the current epoch
+    /// does. The replica's fetch offset and last fetched epoch are checked against the
+    /// log first, a replica that diverged gets the end of the last epoch the two logs
+    /// share to truncate to. Fetches of replicas record their progress, which may move
+    /// the high watermark, so the fetches in the purgatory have to be checked after.
+    pub fn fetch_partition(&self, replica_id: i32, p: &FetchPartition) -> FetchPartitionResponse {
+        self.try_fetch_partition(replica_id, p).unwrap_or_else(|e| {
+            warn!("Fetching the metadata log failed: {e:#}");
+            FetchPartitionResponse::error(p.partition, ErrorCode::UnknownServerError)
+        })
+    }
+
+    fn try_fetch_partition(
+        &self,
+        replica_id: i32,
+        p: &FetchPartition,
+    ) -> anyhow::Result<FetchPartitionResponse> {
+        let mut inner = self.inner.lock().unwrap();
+        let leader = Self::leader_of(&inner);
+        let error = if p.current_leader_epoch < leader.leader_epoch {
+            Some(ErrorCode::FencedLeaderEpoch)
+        } else if p.current_leader_epoch > leader.leader_epoch {
+            Some(ErrorCode::UnknownLeaderEpoch)
+        } else if inner.quorum.leader_state().is_none() {
+            Some(ErrorCode::NotLeaderOrFollower)
+        } else {
+            None
+        };
+        if let Some(code) = error {
+            return FetchPartitionResponse::error(p.partition, code).with_current_leader(&leader);
+        }
+
+        let start_offset = inner.log.start_offset();
+        if let Some(diverging) = inner
+            .log
+            .validate_fetch(p.fetch_offset, p.last_fetched_epoch)
+        {
+            let response = FetchPartitionResponse::new(
+                p.partition,
+                inner.high_watermark,
+                start_offset,
+                Vec::new(),
+            );
+            return response.with_diverging_epoch(&diverging);
+        }
+
+        if replica_id >= 0 {
+            let end_offset = inner.log.end_offset();
+            let leader_state = inner
+                .quorum
+                .leader_state_mut()
+                .context("Leader state is gone")?;
+            if leader_state.update_replica(
+                replica_id,
+                p.fetch_offset,
+                end_offset,
+                SystemTime::now(),
+            ) {
+                let high_watermark = leader_state.high_watermark.unwrap_or_default();
+                self.set_high_watermark(&mut inner, high_watermark);
+            }
+        }
+        let max_bytes = usize::try_from(p.partition_max_bytes).unwrap_or_default();
+        let batches = inner.log.read(p.fetch_offset, i64::MAX, max_bytes)?;
+        let mut records = BytesMut::new();
+        for batch in &batches {
+            batch.encode(&mut records)?;
+        }
+        Ok(FetchPartitionResponse::new(
+            p.partition,
+            inner.high_watermark,
+            start_offset,
+            records.to_vec(),
+        ))
+    }
+
+    /// Describes the voters and observers of the quorum, which only the leader knows
+    pub fn handle_describe_quorum(
+        &self,
+        request: &DescribeQuorumRequestBody,
+    ) -> DescribeQuorumResponseBody {
+        let inner = self.inner.lock().unwrap();
+        let mut topics = CompactArray::with_capacity(request.topics.len());
+        for topic in request.topics.iter() {
+            let mut partitions = CompactArray::with_capacity(topic.partitions.len());
+            for p in topic.partitions.iter() {
+                let index = p.partition_index;
+                let response = if !Self::is_metadata_partition(&topic.topic_name.0, index) {
+                    DescribeQuorumPartitionResponse::error(
+                        index,
+                        ErrorCode::UnknownTopicOrPartition,
+                    )
+                } else if let Some(leader) = inner.quorum.leader_state() {
+                    let states = |replicas: &BTreeMap<i32, ReplicaProgress>| {
+                        replicas
+                            .iter()
+                            .map(|(&id, progress)| {
+                                ReplicaState::new(
+                                    id,
+                                    progress.end_offset,
+                                    progress.last_fetch.map_or(-1, to_ms),
+                                    progress.last_caught_up.map_or(-1, to_ms),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .into()
+                    };
+                    DescribeQuorumPartitionResponse::new(
+                        index,
+                        self.node_id(),
+                        inner.quorum.epoch(),
+                        inner.high_watermark,
+                        states(&leader.voters),
+                        states(&leader.observers),
+                    )
+                } else {
+                    DescribeQuorumPartitionResponse::error(index, ErrorCode::NotLeaderOrFollower)
+                };
+                partitions.push(response);
+            }
+            topics.push(DescribeQuorumTopicResponse::new(
+                topic.topic_name.0.clone(),
+                partitions,
+            ));
+        }
+        DescribeQuorumResponseBody::new(topics)
+    }
+
+    /// Answers every partition with `SNAPSHOT_NOT_FOUND`, this node keeps the whole
+    /// metadata log instead of snapshots
+    pub fn handle_fetch_snapshot(
+        &self,
+        request: &FetchSnapshotRequestBody,
+    ) -> anyhow::Result<FetchSnapshotResponseBody> {
+        if !self.is_same_cluster(request.cluster_id()?.as_deref()) {
+            return Ok(FetchSnapshotResponseBody::error(
+                0,
+                ErrorCode::InconsistentClusterId,
+            ));
+        }
+        let leader = self.leader();
+        let is_leader = self.is_leader();
+        let mut topics = CompactArray::with_capacity(request.topics.len());
+        for topic in request.topics.iter() {
+            let mut partitions = CompactArray::with_capacity(topic.partitions.len());
+            for p in topic.partitions.iter() {
+                let response = if !Self::is_metadata_partition(&topic.name.0, p.partition) {
+                    FetchSnapshotPartitionResponse::error(
+                        p.partition,
+                        ErrorCode::UnknownTopicOrPartition,
+                    )
+                } else if !is_leader {
+                    FetchSnapshotPartitionResponse::error(
+                        p.partition,
+                        ErrorCode::NotLeaderOrFollower,
+                    )
+                    .with_current_leader(&leader)?
+                } else {
+                    FetchSnapshotPartitionResponse::error(p.partition, ErrorCode::SnapshotNotFound)
+                };
+                partitions.push(response);
+            }
+            topics.push(FetchSnapshotTopicResponse::new(
+                topic.name.0.clone(),
+                partitions,
+            ));
+        }
+        Ok(FetchSnapshotResponseBody::new(0, topics))
+    }
+
+    /// Starts an election in the next epoch. A quorum of one elects its only voter
+    /// right away.
+    pub fn become_candidate(&self) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        self.start_election(&mut inner)
+    }
+
+    fn start_election(&self, inner: &mut Inner) -> anyhow::Result<()> {
+        inner.quorum.transition_to_candidate()?;
+        inner.election_delay = None;
+        if inner.quorum.majority() == 1 {
+            self.become_leader(inner)?;
+        }
+        Ok(())
+    }
+
+    /// The vote request of the candidate of the current epoch
+    pub fn vote_request(&self) -> anyhow::Result<VoteRequestBody> {
+        let inner = self.inner.lock().unwrap();
+        let partition = VotePartition::new(
+            0,
+            inner.quorum.epoch(),
+            self.node_id(),
+            inner.log.last_epoch(),
+            inner.log.end_offset(),
+        );
+        Ok(VoteRequestBody::new(
+            &self.config.cluster_id,
+            METADATA_TOPIC,
+            partition,
+        ))
+    }
+
+    /// Counts the vote of `voter_id` in the election of `epoch`, taking over the
+    /// leadership once a majority voted for this node
+    pub fn handle_vote_response(
+        &self,
+        epoch: i32,
+        voter_id: i32,
+        response: &VoteResponseBody,
+    ) -> anyhow::Result<()> {
+        let Some(p) = response.partition() else {
+            warn!(
+                "Voter {voter_id} answered with error {}",
+                response.error_code
+            );
+            return Ok(());
+        };
+        let mut inner = self.inner.lock().unwrap();
+        self.observe_leader(&mut inner, p.leader_epoch, p.leader_id)?;
+        if inner.quorum.epoch() != epoch || p.vote_granted.is_false() {
+            return Ok(());
+        }
+        if inner.quorum.record_granted_vote(voter_id) {
+            self.become_leader(&mut inner)?;
+        }
+        Ok(())
+    }
+
+    /// Starts the epoch won by this candidate with a leader change marker
+    fn become_leader(&self, inner: &mut Inner) -> anyhow::Result<()> {
+        let EpochState::Candidate { granted } = inner.quorum.state() else {
+            return Ok(());
+        };
+        let granted: Vec<i32> = granted.iter().copied().collect();
+        let voters: Vec<i32> = inner.quorum.voters().iter().copied().collect();
+        let epoch_start_offset = inner.log.end_offset();
+        inner.quorum.transition_to_leader(epoch_start_offset)?;
+
+        let batch =
+            LeaderChangeMessage::new(self.node_id(), &voters, &granted).to_batch(now_ms())?;
+        let epoch = inner.quorum.epoch();
+        inner.log.append_as_leader(batch, epoch)?;
+        inner.log.flush()?;
+        self.update_leader_end_offset(inner)?;
+        info!("Elected leader of epoch {epoch}, starting at offset {epoch_start_offset}");
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// The request announcing the leadership of this node to `voter_id`
+    pub fn begin_quorum_epoch_request(&self, voter_id: i32) -> BeginQuorumEpochRequestBody {
+        let epoch = self.inner.lock().unwrap().quorum.epoch();
+        let partition = BeginQuorumEpochPartition::new(0, self.node_id(), epoch);
+        BeginQuorumEpochRequestBody::new(
+            &self.config.cluster_id,
+            voter_id,
+            METADATA_TOPIC,
+            partition,
+            Vec::new(),
+        )
+    }
+
+    /// The voters that did not fetch in the current epoch yet, which the leader keeps
+    /// announcing its leadership to
+    pub fn voters_to_announce(&self) -> Vec<i32> {
+        let inner = self.inner.lock().unwrap();
+        inner.quorum.leader_state().map_or_else(Vec::new, |leader| {
+            leader
+                .voters
+                .iter()
+                .filter(|&(&id, progress)| id != self.node_id() && progress.last_fetch.is_none())
+                .map(|(&id, _)| id)
+                .collect()
+        })
+    }
+
+    /// Learns of later epochs from the answer to a BeginQuorumEpoch or EndQuorumEpoch
+    pub fn handle_quorum_epoch_response(
+        &self,
+        response: &BeginQuorumEpochResponseBody,
+    ) -> anyhow::Result<()> {
+        let partition = response
+            .topics
+            .iter()
+            .flat_map(|t| t.partitions.iter())
+            .next();
+        if let Some(p) = partition {
+            let mut inner = self.inner.lock().unwrap();
+            self.observe_leader(&mut inner, p.leader_epoch, p.leader_id)?;
+        }
+        Ok(())
+    }
+
+    /// Resigns the leadership before shutting down. Returns the request telling the other
+    /// voters, which prefers the ones that got furthest as the next leader.
+    pub fn resign(&self) -> Option<EndQuorumEpochRequestBody> {
+        let inner = self.inner.lock().unwrap();
+        let leader = inner.quorum.leader_state()?;
+        let mut preferred: Vec<(i32, i64)> = leader
+            .voters
+            .iter()
+            .filter(|&(&id, _)| id != self.node_id())
+            .map(|(&id, progress)| (id, progress.end_offset))
+            .collect();
+        preferred.sort_by_key(|&(_, end_offset)| std::cmp::Reverse(end_offset));
+        let preferred: Vec<i32> = preferred.into_iter().map(|(id, _)| id).collect();
+        let partition =
+            EndQuorumEpochPartition::new(0, self.node_id(), inner.quorum.epoch(), &preferred);
+        Some(EndQuorumEpochRequestBody::new(
+            &self.config.cluster_id,
+            METADATA_TOPIC,
+            partition,
+        ))
+    }
+
+    /// The fetch of the metadata log sent to the leader, or to any voter by an observer
+    /// looking for the leader
+    pub fn fetch_request(
+        &self,
+        max_wait: Duration,
+        max_bytes: i32,
+    ) -> anyhow::Result<FetchRequestBody> {
+        let inner = self.inner.lock().unwrap();
+        let partition = FetchPartition::new(
+            0,
+            inner.quorum.epoch(),
+            inner.log.end_offset(),
+            inner.log.start_offset(),
+            max_bytes,
+        )
+        .with_last_fetched_epoch(inner.log.last_epoch());
+        let topics = vec![FetchTopic::new(METADATA_TOPIC, vec![partition].into())];
+        FetchRequestBody::new(
+            self.node_id(),
+            i32::try_from(max_wait.as_millis()).unwrap_or(i32::MAX),
+            1,
+            max_bytes,
+            topics.into(),
+        )
+        .with_cluster_id(&self.config.cluster_id)
+    }
+
+    /// Appends what `target_id` answered the fetch sent in `epoch` with, truncating the
+    /// log first if it diverged from the leader's. Returns whether the fetch succeeded,
+    /// errors naming a later epoch or another leader move this node there.
+    pub fn handle_fetch_response(
+        &self,
+        epoch: i32,
+        target_id: i32,
+        response: &FetchResponseBody,
+    ) -> anyhow::Result<bool> {
+        anyhow::ensure!(
+            response.error_code != ErrorCode::InconsistentClusterId.code(),
+            "Node {target_id} belongs to another cluster"
+        );
+        let Some(p) = response
+            .responses
+            .iter()
+            .filter(|t| t.topic.0 == METADATA_TOPIC)
+            .flat_map(|t| t.partitions.iter())
+            .next()
+        else {
+            debug!("Fetch from {target_id} failed with {}", response.error_code);
+            return Ok(false);
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(leader) = p.current_leader()? {
+            self.observe_leader(&mut inner, leader.leader_epoch, leader.leader_id)?;
+        }
+        if p.error_code != ErrorCode::None.code() {
+            debug!("Fetch from {target_id} failed with {}", p.error_code);
+            return Ok(false);
+        }
+        // only the leader of the epoch answers without an error
+        self.observe_leader(&mut inner, epoch, target_id)?;
+        if inner.quorum.epoch() != epoch || inner.quorum.leader_id() != Some(target_id) {
+            return Ok(false);
+        }
+
+        if let Some(diverging) = p.diverging_epoch()? {
+            let offset = inner.log.truncation_offset(&diverging);
+            let end_offset = inner.log.truncate_to(offset)?;
+            info!(
+                "Truncated the metadata log to {end_offset}, it diverged from leader {target_id} after epoch {}",
+                diverging.epoch
+            );
+            return Ok(true);
+        }
+        let mut buf = BytesMut::from(&p.records.0[..]);
+        let mut batches = Vec::new();
+        while let Some(batch) = RecordBatch::decode(&mut buf, None)? {
+            batches.push(batch);
+        }
+        if !batches.is_empty() {
+            inner.log.append_as_follower(&batches)?;
+            inner.log.flush()?;
+        }
+        let high_watermark = p.high_watermark.min(inner.log.end_offset());
+        self.set_high_watermark(&mut inner, high_watermark);
+        Ok(true)
+    }
+
+    /// Gives up on a leader that stopped answering fetches: voters run for election,
+    /// observers look for the leader again
+    pub fn handle_fetch_timeout(&self, epoch: i32) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.quorum.epoch() != epoch {
+            return Ok(());
+        }
+        if inner.quorum.is_voter(self.node_id()) {
+            self.start_election(&mut inner)?;
+        } else {
+            inner.quorum.transition_to_unattached(epoch)?;
+        }
+        Ok(())
+    }
+}
+
+fn to_ms(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+fn now_ms() -> i64 {
+    to_ms(SystemTime::now())
+}
@@ -0,0 +1,130 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::Encoder,
+    primitives::CompactArray,
+    types::{Record, RecordBatch, TagBuf},
+};
+
+/// Type of the control record a new leader starts its epoch with, in the key of the record
+const LEADER_CHANGE_TYPE: i16 = 2;
+/// Type of the control record starting a snapshot
+pub(super) const SNAPSHOT_HEADER_TYPE: i16 = 3;
+/// Type of the control record ending a snapshot
+pub(super) const SNAPSHOT_FOOTER_TYPE: i16 = 4;
+
+/// A control batch holding a single record of `record_type` with `value`
+fn control_batch(
+    record_type: i16,
+    value: &impl Encoder,
+    timestamp: i64,
+) -> anyhow::Result<RecordBatch> {
+    // control record keys are a version and the type of the record
+    let mut key = BytesMut::with_capacity(4);
+    key.put_i16(0);
+    key.put_i16(record_type);
+    let mut encoded = BytesMut::new();
+    value.encode(&mut encoded)?;
+    let record = Record::new(0, Some(key.freeze()), Some(Bytes::from(encoded)));
+    let mut batch = RecordBatch::new(0, timestamp, vec![record]);
+    batch.set_control();
+    Ok(batch)
+}
+
+/// The type of the control record starting `batch`, `None` if it is not a control batch
+pub(super) fn control_type(batch: &RecordBatch) -> Option<i16> {
+    if !batch.is_control() {
+        return None;
+    }
+    let records = batch.records().ok()?;
+    let record_type = records.first()?.key.as_ref()?.get(2..4)?;
+    Some(i16::from_be_bytes([record_type[0], record_type[1]]))
+}
+
+/// LeaderChangeMessage (Version: 0) => version leader_id [voters] [granting_voters] TAG_BUFFER
+///
+/// The value of the control record every leader appends when it takes over. Committing it
+/// commits the records of the previous epochs along with it.
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderChangeMessage {
+    pub version: i16,
+    pub leader_id: i32,
+    pub voters: CompactArray<Voter>,
+    /// The voters that elected the leader
+    pub granting_voters: CompactArray<Voter>,
+    tag_buffer: TagBuf,
+}
+
+/// voters => voter_id TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct Voter {
+    pub voter_id: i32,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderChangeMessage {
+    pub fn new(leader_id: i32, voters: &[i32], granting_voters: &[i32]) -> Self {
+        let to_voters = |ids: &[i32]| -> CompactArray<Voter> {
+            ids.iter()
+                .map(|&voter_id| Voter {
+                    voter_id,
+                    tag_buffer: TagBuf::new(),
+                })
+                .collect::<Vec<_>>()
+                .into()
+        };
+        Self {
+            version: 0,
+            leader_id,
+            voters: to_voters(voters),
+            granting_voters: to_voters(granting_voters),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// A control batch holding this message, with the current time as its timestamp
+    pub fn to_batch(&self, timestamp: i64) -> anyhow::Result<RecordBatch> {
+        control_batch(LEADER_CHANGE_TYPE, self, timestamp)
+    }
+}
+
+/// SnapshotHeaderRecord (Version: 0) => version last_contained_log_timestamp TAG_BUFFER
+///
+/// The value of the control record every snapshot starts with
+#[derive(Debug, WireLen, Encoder)]
+pub struct SnapshotHeaderRecord {
+    pub version: i16,
+    /// Timestamp of the last batch the snapshot covers
+    pub last_contained_log_timestamp: i64,
+    tag_buffer: TagBuf,
+}
+
+impl SnapshotHeaderRecord {
+    pub fn new(last_contained_log_timestamp: i64) -> Self {
+        Self {
+            version: 0,
+            last_contained_log_timestamp,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn to_batch(&self, timestamp: i64) -> anyhow::Result<RecordBatch> {
+        control_batch(SNAPSHOT_HEADER_TYPE, self, timestamp)
+    }
+}
+
+/// SnapshotFooterRecord (Version: 0) => version TAG_BUFFER
+///
+/// The value of the control record every complete snapshot ends with
+#[derive(Debug, Default, WireLen, Encoder)]
+pub struct SnapshotFooterRecord {
+    pub version: i16,
+    tag_buffer: TagBuf,
+}
+
+impl SnapshotFooterRecord {
+    pub fn to_batch(&self, timestamp: i64) -> anyhow::Result<RecordBatch> {
+        control_batch(SNAPSHOT_FOOTER_TYPE, self, timestamp)
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use rand::Rng;
+use tokio::{net::TcpStream, task::JoinSet};
+use tracing::{debug, info, warn};
+
+use super::client::{RaftClient, Role};
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+    network,
+    response::body::{BeginQuorumEpochResponseBody, FetchResponseBody, VoteResponseBody},
+    types::ApiKeys,
+};
+
+/// Client id of the requests sent to the other nodes of the quorum
+const CLIENT_ID: &str = "raft-client";
+const VOTE_VERSION: i16 = 0;
+const QUORUM_EPOCH_VERSION: i16 = 1;
+const FETCH_VERSION: i16 = 12;
+/// Most bytes of the metadata log read by a single fetch
+const FETCH_MAX_BYTES: i32 = 8 * 1024 * 1024;
+/// Pause after a failed request before the next one
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Takes part in the quorum for as long as the task is not aborted: runs for election
+/// when no leader shows up within the election timeout, announces its leadership once
+/// elected, and otherwise fetches the metadata log from the leader. Followers that get
+/// no answer from their leader within the fetch timeout run for election, observers look
+/// for the leader by fetching from random voters, which tell them who it is.
+pub async fn run_driver(client: Arc<RaftClient>) {
+    loop {
+        let (epoch, role) = client.role();
+        debug!("Driving the quorum as {role:?} in epoch {epoch}");
+        let driven = match role {
+            Role::Leader => lead(&client, epoch).await,
+            Role::Candidate => campaign(&client, epoch).await,
+            Role::Follower { leader_id } => follow(&client, epoch, leader_id).await,
+            Role::Unattached { election_delay } => {
+                if client.is_voter() {
+                    wait_for_election(&client, epoch, election_delay).await
+                } else {
+                    find_leader(&client, epoch).await
+                }
+            }
+        };
+        if let Err(e) = driven {
+            warn!("Driving the quorum in epoch {epoch} failed: {e:#}");
+            tokio::time::sleep(RETRY_BACKOFF).await;
+        }
+    }
+}
+
+/// Tells the other voters that this leader resigns, so they elect a new one right away
+/// instead of waiting for the fetch timeout. Sent on shutdown, failures are ignored.
+pub async fn resign(client: &RaftClient) {
+    let Some(request) = client.resign() else {
+        return;
+    };
+    let request = Arc::new(request);
+    info!("Resigning the leadership of the quorum");
+    let mut sent = JoinSet::new();
+    for (&id, address) in &client.config().voters {
+        if id == client.node_id() {
+            continue;
+        }
+        let address = address.clone();
+        let timeout = client.config().request_timeout;
+        let request = Arc::clone(&request);
+        sent.spawn(async move {
+            let response: anyhow::Result<BeginQuorumEpochResponseBody> = send(
+                &address,
+                timeout,
+                ApiKeys::EndQuorumEpoch,
+                QUORUM_EPOCH_VERSION,
+                &*request,
+            )
+            .await;
+            if let Err(e) = response {
+                debug!("Telling voter {id} about the resignation failed: {e:#}");
+            }
+        });
+    }
+    while sent.join_next().await.is_some() {}
+}
+
+/// Runs a client call, which may write to disk, on a blocking thread
+async fn blocking<T, F>(client: &Arc<RaftClient>, f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&RaftClient) -> anyhow::Result<T> + Send + 'static,
+{
+    let client = Arc::clone(client);
+    tokio::task::spawn_blocking(move || f(&client))
+        .await
+        .context("Raft client call panicked")?
+}
+
+/// Whether the node still has `role` in `epoch`, election delays aside
+fn still(client: &RaftClient, epoch: i32, role: &Role) -> bool {
+    let (current_epoch, current) = client.role();
+    current_epoch == epoch
+        && match (role, &current) {
+            (Role::Follower { leader_id }, Role::Follower { leader_id: current }) => {
+                leader_id == current
+            }
+            _ => std::mem::discriminant(role) == std::mem::discriminant(&current),
+        }
+}
+
+/// Sends a single request on a new connection
+async fn send<Req, Resp>(
+    address: &str,
+    timeout: Duration,
+    api_key: ApiKeys,
+    api_version: i16,
+    request: &Req,
+) -> anyhow::Result<Resp>
+where
+    Req: Encoder + WireLen,
+    Resp: Decoder + WireLen,
+{
+    tokio::time::timeout(timeout, async {
+        let mut stream = network::connect(address, timeout).await?;
+        network::send_request(&mut stream, api_key, api_version, 0, CLIENT_ID, request).await
+    })
+    .await
+    .with_context(|| format!("{api_key:?} to {address} timed out"))?
+}
+
+/// Announces the leadership to the voters that did not fetch in the epoch yet, until
+/// the node steps down
+async fn lead(client: &Arc<RaftClient>, epoch: i32) -> anyhow::Result<()> {
+    let timeout = client.config().request_timeout;
+    while still(client, epoch, &Role::Leader) {
+        let mut sent = JoinSet::new();
+        for id in client.voters_to_announce() {
+            let Some(address) = client.config().voters.get(&id).cloned() else {
+                continue;
+            };
+            let request = client.begin_quorum_epoch_request(id);
+            sent.spawn(async move {
+                let response = send(
+                    &address,
+                    timeout,
+                    ApiKeys::BeginQuorumEpoch,
+                    QUORUM_EPOCH_VERSION,
+                    &request,
+                )
+                .await;
+                (id, response)
+            });
+        }
+        while let Some(sent) = sent.join_next().await {
+            let (id, response): (i32, anyhow::Result<BeginQuorumEpochResponseBody>) = sent?;
+            match response {
+                Ok(response) => {
+                    blocking(client, move |c| c.handle_quorum_epoch_response(&response)).await?;
+                }
+                Err(e) => debug!("Announcing the leadership to voter {id} failed: {e:#}"),
+            }
+        }
+        tokio::select! {
+            () = client.changed() => {}
+            () = tokio::time::sleep(client.config().fetch_timeout / 2) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Asks every other voter for its vote, until the election is won or lost. Elections
+/// that neither this candidate nor another one won within the election timeout are
+/// retried in the next epoch, after a random backoff so the candidates do not keep
+/// splitting the votes.
+async fn campaign(client: &Arc<RaftClient>, epoch: i32) -> anyhow::Result<()> {
+    let config = client.config().clone();
+    info!("Running for leader of epoch {epoch}");
+    let request = Arc::new(client.vote_request()?);
+    let mut votes = JoinSet::new();
+    for (&id, address) in &config.voters {
+        if id == client.node_id() {
+            continue;
+        }
+        let (address, request) = (address.clone(), Arc::clone(&request));
+        let timeout = config.request_timeout;
+        votes.spawn(async move {
+            let response = send(&address, timeout, ApiKeys::Vote, VOTE_VERSION, &*request).await;
+            (id, response)
+        });
+    }
+
+    let deadline = tokio::time::Instant::now() + config.election_timeout;
+    loop {
+        if !still(client, epoch, &Role::Candidate) {
+            return Ok(());
+        }
+        tokio::select! {
+            Some(vote) = votes.join_next() => {
+                let (id, response): (i32, anyhow::Result<VoteResponseBody>) = vote?;
+                match response {
+                    Ok(response) => {
+                        blocking(client, move |c| c.handle_vote_response(epoch, id, &response))
+                            .await?;
+                    }
+                    Err(e) => debug!("Asking voter {id} for its vote failed: {e:#}"),
+                }
+            }
+            () = client.changed() => {}
+            () = tokio::time::sleep_until(deadline) => break,
+        }
+    }
+
+    let backoff = random_duration(Duration::ZERO, config.election_backoff_max);
+    tokio::time::sleep(backoff).await;
+    if still(client, epoch, &Role::Candidate) {
+        blocking(client, RaftClient::become_candidate).await?;
+    }
+    Ok(())
+}
+
+/// Waits for a leader to show up, running for election if none did within the election
+/// timeout, randomized so the voters do not all run at once
+async fn wait_for_election(
+    client: &Arc<RaftClient>,
+    epoch: i32,
+    election_delay: Option<Duration>,
+) -> anyhow::Result<()> {
+    let timeout = client.config().election_timeout;
+    let delay = election_delay.unwrap_or_else(|| random_duration(timeout, timeout * 2));
+    tokio::select! {
+        () = client.changed() => return Ok(()),
+        () = tokio::time::sleep(delay) => {}
+    }
+    let role = Role::Unattached {
+        election_delay: None,
+    };
+    if still(client, epoch, &role) {
+        blocking(client, RaftClient::become_candidate).await?;
+    }
+    Ok(())
+}
+
+/// Fetches from a random voter as an observer without a leader. Voters that are not
+/// the leader answer with the leader they know of, which the observer then follows.
+async fn find_leader(client: &Arc<RaftClient>, epoch: i32) -> anyhow::Result<()> {
+    let config = client.config();
+    let voters: Vec<(&i32, &String)> = config.voters.iter().collect();
+    let (&id, address) = voters[rand::thread_rng().gen_range(0..voters.len())];
+    let request = client.fetch_request(Duration::ZERO, FETCH_MAX_BYTES)?;
+    let response: anyhow::Result<FetchResponseBody> = send(
+        address,
+        config.request_timeout,
+        ApiKeys::Fetch,
+        FETCH_VERSION,
+        &request,
+    )
+    .await;
+    match response {
+        Ok(response) => {
+            blocking(client, move |c| {
+                c.handle_fetch_response(epoch, id, &response)
+            })
+            .await?;
+        }
+        Err(e) => debug!("Asking voter {id} for the leader failed: {e:#}"),
+    }
+    let role = Role::Unattached {
+        election_delay: None,
+    };
+    if still(client, epoch, &role) {
+        tokio::select! {
+            () = client.changed() => {}
+            () = tokio::time::sleep(config.election_backoff_max) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the metadata log from the leader on a connection kept open, for as long as
+/// it leads. A leader that did not answer a fetch within the fetch timeout is given up.
+async fn follow(client: &Arc<RaftClient>, epoch: i32, leader_id: i32) -> anyhow::Result<()> {
+    let config = client.config().clone();
+    let address = config
+        .voters
+        .get(&leader_id)
+        .with_context(|| format!("Leader {leader_id} is not a voter"))?;
+    info!("Following leader {leader_id} of epoch {epoch} at {address}");
+    let role = Role::Follower { leader_id };
+    let max_wait = config.fetch_timeout / 4;
+    let mut stream: Option<TcpStream> = None;
+    let mut correlation_id = 0;
+    let mut last_fetched = Instant::now();
+    while still(client, epoch, &role) {
+        if last_fetched.elapsed() >= config.fetch_timeout {
+            warn!(
+                "Leader {leader_id} did not answer within {:?}",
+                config.fetch_timeout
+            );
+            blocking(client, move |c| c.handle_fetch_timeout(epoch)).await?;
+            return Ok(());
+        }
+
+        let connection = match stream.as_mut() {
+            Some(connection) => connection,
+            None => match network::connect(address, config.request_timeout).await {
+                Ok(connection) => stream.insert(connection),
+                Err(e) => {
+                    debug!("Connecting to leader {leader_id} at {address} failed: {e:#}");
+                    tokio::time::sleep(RETRY_BACKOFF).await;
+                    continue;
+                }
+            },
+        };
+        correlation_id += 1;
+        let request = client.fetch_request(max_wait, FETCH_MAX_BYTES)?;
+        let fetched = tokio::time::timeout(
+            config.request_timeout + max_wait,
+            network::send_request(
+                connection,
+                ApiKeys::Fetch,
+                FETCH_VERSION,
+                correlation_id,
+                CLIENT_ID,
+                &request,
+            ),
+        )
+        .await
+        .context("Fetch timed out")
+        .and_then(|fetched| fetched);
+        let response: FetchResponseBody = match fetched {
+            Ok(response) => response,
+            Err(e) => {
+                debug!("Fetching from leader {leader_id} failed: {e:#}");
+                stream = None;
+                tokio::time::sleep(RETRY_BACKOFF).await;
+                continue;
+            }
+        };
+        if blocking(client, move |c| {
+            c.handle_fetch_response(epoch, leader_id, &response)
+        })
+        .await?
+        {
+            last_fetched = Instant::now();
+        } else {
+            tokio::time::sleep(RETRY_BACKOFF).await;
+        }
+    }
+    Ok(())
+}
+
+fn random_duration(min: Duration, max: Duration) -> Duration {
+    if max <= min {
+        return min;
+    }
+    rand::thread_rng().gen_range(min..max)
+}
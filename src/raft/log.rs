@@ -0,0 +1,161 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+
+use super::METADATA_TOPIC;
+use crate::{
+    storage::{LogConfig, PartitionLog, TopicPartition},
+    types::{EpochEndOffset, RecordBatch},
+};
+
+/// The `__cluster_metadata` log replicated by the quorum. Each batch carries the epoch
+/// of the leader that appended it as its partition leader epoch, which the partition log
+/// keeps track of.
+#[derive(Debug)]
+pub struct RaftLog {
+    log: PartitionLog,
+}
+
+impl RaftLog {
+    /// Opens the metadata log in `log_dir`, creating it if needed. The whole log is
+    /// recovered, it is small and nothing else checkpoints it.
+    pub fn open(log_dir: &Path) -> anyhow::Result<Self> {
+        let dir = log_dir.join(TopicPartition::new(METADATA_TOPIC, 0).dir_name());
+        fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+        let mut log = PartitionLog::open(&dir)?;
+        log.recover(log.log_start_offset())?;
+        Ok(Self { log })
+    }
+
+    pub fn start_offset(&self) -> i64 {
+        self.log.log_start_offset()
+    }
+
+    pub fn end_offset(&self) -> i64 {
+        self.log.log_end_offset()
+    }
+
+    /// The epoch of the last batch, 0 for an empty log
+    pub fn last_epoch(&self) -> i32 {
+        self.log.latest_epoch().unwrap_or(0)
+    }
+
+    /// Appends `batch` as the leader of `epoch`, returning the offset it got
+    pub fn append_as_leader(&mut self, mut batch: RecordBatch, epoch: i32) -> anyhow::Result<i64> {
+        batch.partition_leader_epoch = epoch;
+        self.log.append(batch, &LogConfig::default())
+    }
+
+    /// Appends batches fetched from the leader, returning the new log end offset
+    pub fn append_as_follower(&mut self, batches: &[RecordBatch]) -> anyhow::Result<i64> {
+        for batch in batches {
+            self.log.append_as_follower(batch, &LogConfig::default())?;
+        }
+        Ok(self.end_offset())
+    }
+
+    /// See [`PartitionLog::end_offset_for_epoch`]
+    pub fn end_offset_for_epoch(&self, epoch: i32) -> EpochEndOffset {
+        self.log.end_offset_for_epoch(epoch)
+    }
+
+    /// See [`PartitionLog::validate_fetch`]
+    pub fn validate_fetch(
+        &self,
+        fetch_offset: i64,
+        last_fetched_epoch: i32,
+    ) -> Option<EpochEndOffset> {
+        self.log.validate_fetch(fetch_offset, last_fetched_epoch)
+    }
+
+    /// See [`PartitionLog::truncation_offset`]
+    pub fn truncation_offset(&self, diverging: &EpochEndOffset) -> i64 {
+        self.log.truncation_offset(diverging)
+    }
+
+    /// Removes the batches at or after `offset`, returning the new log end offset
+    pub fn truncate_to(&mut self, offset: i64) -> anyhow::Result<i64> {
+        self.log.truncate_to(offset)
+    }
+
+    /// The batches from `offset` on, up to `max_offset` and `max_bytes`, see
+    /// [`PartitionLog::read`]
+    pub fn read(
+        &self,
+        offset: i64,
+        max_offset: i64,
+        max_bytes: usize,
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        self.log.read(offset, max_offset, max_bytes)
+    }
+
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.log.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::types::Record;
+
+    fn batch(records: i32) -> RecordBatch {
+        let records = (0..records)
+            .map(|i| Record::new(i, None, Some(Bytes::from_static(b"v"))))
+            .collect();
+        RecordBatch::new(0, 0, records)
+    }
+
+    #[test]
+    fn test_epochs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = RaftLog::open(dir.path()).unwrap();
+        assert_eq!(0, log.last_epoch());
+        log.append_as_leader(batch(2), 1).unwrap();
+        log.append_as_leader(batch(1), 1).unwrap();
+        log.append_as_leader(batch(3), 3).unwrap();
+
+        let log = RaftLog::open(dir.path()).unwrap();
+        assert_eq!(3, log.last_epoch());
+        assert_eq!(6, log.end_offset());
+        let end = log.end_offset_for_epoch(2);
+        assert_eq!((1, 3), (end.epoch, end.end_offset));
+        let end = log.end_offset_for_epoch(3);
+        assert_eq!((3, 6), (end.epoch, end.end_offset));
+        assert_eq!(-1, log.end_offset_for_epoch(0).epoch);
+
+        assert!(log.validate_fetch(0, 0).is_none());
+        assert!(log.validate_fetch(3, 1).is_none());
+        assert!(log.validate_fetch(5, 3).is_none());
+        // a replica with records of an epoch this log does not have
+        let diverging = log.validate_fetch(5, 2).unwrap();
+        assert_eq!((1, 3), (diverging.epoch, diverging.end_offset));
+        // or more records of an epoch than this log has
+        let diverging = log.validate_fetch(4, 1).unwrap();
+        assert_eq!((1, 3), (diverging.epoch, diverging.end_offset));
+    }
+
+    #[test]
+    fn test_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = RaftLog::open(dir.path()).unwrap();
+        log.append_as_leader(batch(2), 1).unwrap();
+        log.append_as_leader(batch(2), 2).unwrap();
+        log.append_as_leader(batch(2), 2).unwrap();
+
+        let offset = log.truncation_offset(&EpochEndOffset::new(1, 3));
+        assert_eq!(2, offset);
+        assert_eq!(2, log.truncate_to(offset).unwrap());
+        assert_eq!(1, log.last_epoch());
+
+        let mut fetched = batch(1);
+        fetched.base_offset = 2;
+        fetched.partition_leader_epoch = 4;
+        assert_eq!(3, log.append_as_follower(&[fetched]).unwrap());
+        assert_eq!(4, log.last_epoch());
+        let end = log.end_offset_for_epoch(3);
+        assert_eq!((1, 2), (end.epoch, end.end_offset));
+    }
+}
@@ -0,0 +1,26 @@
+//! The KRaft quorum replicating the `__cluster_metadata` log, a Raft variant where
+//! followers pull the log from the leader with Fetch requests instead of the leader
+//! pushing it. Controllers are the voters of the quorum: they elect a leader with Vote
+//! requests, which announces itself with BeginQuorumEpoch and steps down with
+//! EndQuorumEpoch. Brokers that are not controllers follow the log as observers,
+//! replaying it into their metadata image.
+//!
+//! The epoch, leader and vote of every node are persisted in its
+//! [`QUORUM_STATE_FILE`], the log lives in the `__cluster_metadata-0` directory of
+//! its first log dir, along with the [`Snapshots`] of the metadata image built from it.
+mod client;
+mod control;
+mod driver;
+mod log;
+mod snapshot;
+mod state;
+
+pub use client::{RaftClient, RaftConfig, Role};
+pub use control::{LeaderChangeMessage, SnapshotFooterRecord, SnapshotHeaderRecord};
+pub use driver::{resign, run_driver};
+pub use log::RaftLog;
+pub use snapshot::Snapshots;
+pub use state::{EpochState, LeaderState, QUORUM_STATE_FILE, QuorumState, ReplicaProgress};
+
+/// The topic of the metadata log, with a single partition
+pub const METADATA_TOPIC: &str = "__cluster_metadata";
@@ -0,0 +1,239 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, bail, ensure};
+use bytes::{Bytes, BytesMut};
+use tracing::{info, warn};
+
+use super::control::{
+    SNAPSHOT_FOOTER_TYPE, SNAPSHOT_HEADER_TYPE, SnapshotFooterRecord, SnapshotHeaderRecord,
+    control_type,
+};
+use crate::{
+    WireLen,
+    codec::{Decoder, Encoder},
+    types::{Record, RecordBatch, SnapshotId},
+};
+
+/// Most records in a batch of a snapshot
+const RECORDS_PER_BATCH: usize = 512;
+
+/// The snapshots of the metadata log, stored next to it in Kafka's format: a file per
+/// snapshot named after its end offset and epoch,
+/// `00000000000000001234-0000000003.checkpoint`, holding a header control batch, the
+/// batches of the snapshot's records, and a footer control batch. Snapshots are written
+/// to a `.checkpoint.part` file first and renamed once complete, so a snapshot either
+/// exists whole or not at all.
+#[derive(Debug)]
+pub struct Snapshots {
+    dir: PathBuf,
+}
+
+impl Snapshots {
+    const SUFFIX: &str = ".checkpoint";
+    const PARTIAL_SUFFIX: &str = ".checkpoint.part";
+
+    /// The snapshots in the directory of the metadata log
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &SnapshotId) -> PathBuf {
+        self.dir.join(format!(
+            "{:020}-{:010}{}",
+            id.end_offset,
+            id.epoch,
+            Self::SUFFIX
+        ))
+    }
+
+    fn parse_file_name(file_name: &str) -> Option<SnapshotId> {
+        let stem = file_name.strip_suffix(Self::SUFFIX)?;
+        let (end_offset, epoch) = stem.split_once('-')?;
+        if end_offset.len() != 20 || epoch.len() != 10 {
+            return None;
+        }
+        Some(SnapshotId::new(
+            end_offset.parse().ok()?,
+            epoch.parse().ok()?,
+        ))
+    }
+
+    /// Every complete snapshot, oldest first. Leftovers of snapshots that were being
+    /// written when the node stopped are removed.
+    pub fn list(&self) -> anyhow::Result<Vec<SnapshotId>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", self.dir.display())),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if name.ends_with(Self::PARTIAL_SUFFIX) {
+                warn!("Removing the partial snapshot {name}");
+                fs::remove_file(entry.path())
+                    .with_context(|| format!("Removing {}", entry.path().display()))?;
+            } else if let Some(id) = Self::parse_file_name(&name) {
+                snapshots.push(id);
+            }
+        }
+        snapshots.sort_by_key(|id| (id.end_offset, id.epoch));
+        Ok(snapshots)
+    }
+
+    /// The snapshot with the highest end offset, if any
+    pub fn latest(&self) -> anyhow::Result<Option<SnapshotId>> {
+        Ok(self.list()?.pop())
+    }
+
+    /// The batches of the records in snapshot `id`, without its header and footer
+    pub fn read(&self, id: &SnapshotId) -> anyhow::Result<Vec<RecordBatch>> {
+        let path = self.path(id);
+        let data = fs::read(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let mut buf = BytesMut::from(&data[..]);
+        let mut batches = Vec::new();
+        while let Some(batch) = RecordBatch::decode(&mut buf, None)
+            .with_context(|| format!("Decoding batch of {}", path.display()))?
+        {
+            batches.push(batch);
+        }
+        ensure!(buf.is_empty(), "Snapshot {} is truncated", path.display());
+        if batches.first().and_then(control_type) != Some(SNAPSHOT_HEADER_TYPE) {
+            bail!("Snapshot {} does not start with a header", path.display());
+        }
+        if batches.last().and_then(control_type) != Some(SNAPSHOT_FOOTER_TYPE) {
+            bail!("Snapshot {} does not end with a footer", path.display());
+        }
+        batches.pop();
+        batches.remove(0);
+        Ok(batches)
+    }
+
+    /// Writes snapshot `id` holding `values` as the values of its records, replacing one
+    /// written before. `timestamp` is that of the last batch the snapshot covers.
+    pub fn write(&self, id: &SnapshotId, values: Vec<Bytes>, timestamp: i64) -> anyhow::Result<()> {
+        let mut batches = vec![SnapshotHeaderRecord::new(timestamp).to_batch(timestamp)?];
+        let mut next_offset = 1;
+        for chunk in values.chunks(RECORDS_PER_BATCH) {
+            let records = (0..)
+                .zip(chunk)
+                .map(|(delta, value)| Record::new(delta, None, Some(value.clone())))
+                .collect();
+            let batch = RecordBatch::new(next_offset, timestamp, records);
+            next_offset = batch.next_offset();
+            batches.push(batch);
+        }
+        let mut footer = SnapshotFooterRecord::default().to_batch(timestamp)?;
+        footer.base_offset = next_offset;
+        batches.push(footer);
+
+        let mut content = BytesMut::with_capacity(batches.iter().map(WireLen::wire_len).sum());
+        for mut batch in batches {
+            batch.partition_leader_epoch = id.epoch;
+            batch.encode(&mut content)?;
+        }
+
+        let path = self.path(id);
+        let partial = self.dir.join(format!(
+            "{}.part",
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+        ));
+        let mut file = fs::File::create(&partial)
+            .with_context(|| format!("Creating {}", partial.display()))?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&partial, &path).with_context(|| format!("Replacing {}", path.display()))?;
+        info!(
+            "Wrote snapshot {} of {} records",
+            path.display(),
+            values.len()
+        );
+        Ok(())
+    }
+
+    /// Removes the snapshots ending before `id`, which it supersedes
+    pub fn delete_before(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        for old in self.list()? {
+            if old.end_offset < id.end_offset {
+                let path = self.path(&old);
+                fs::remove_file(&path).with_context(|| format!("Removing {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = Snapshots::new(dir.path());
+        assert!(snapshots.latest().unwrap().is_none());
+
+        let values: Vec<Bytes> = (0..1000)
+            .map(|i: i32| Bytes::from(i.to_be_bytes().to_vec()))
+            .collect();
+        snapshots
+            .write(&SnapshotId::new(10, 1), values[..1].to_vec(), 0)
+            .unwrap();
+        snapshots
+            .write(&SnapshotId::new(1234, 3), values.clone(), 0)
+            .unwrap();
+        assert!(
+            dir.path()
+                .join("00000000000000001234-0000000003.checkpoint")
+                .exists()
+        );
+        // leftovers of an interrupted write are removed
+        fs::write(
+            dir.path()
+                .join("00000000000000002000-0000000003.checkpoint.part"),
+            b"x",
+        )
+        .unwrap();
+
+        let latest = snapshots.latest().unwrap().unwrap();
+        assert_eq!((1234, 3), (latest.end_offset, latest.epoch));
+        assert_eq!(2, fs::read_dir(dir.path()).unwrap().count());
+
+        let batches = snapshots.read(&latest).unwrap();
+        assert_eq!(2, batches.len());
+        assert!(
+            batches
+                .iter()
+                .all(|b| !b.is_control() && b.partition_leader_epoch == 3)
+        );
+        let read: Vec<Bytes> = batches
+            .iter()
+            .flat_map(|b| b.records().unwrap().into_iter().filter_map(|r| r.value))
+            .collect();
+        assert_eq!(values, read);
+        assert_eq!(1, batches[0].base_offset);
+        assert_eq!(batches[0].next_offset(), batches[1].base_offset);
+
+        snapshots.delete_before(&latest).unwrap();
+        assert_eq!(1, snapshots.list().unwrap().len());
+
+        // a snapshot without its footer is rejected
+        let path = snapshots.path(&latest);
+        let data = fs::read(&path).unwrap();
+        let footer_len = SnapshotFooterRecord::default()
+            .to_batch(0)
+            .unwrap()
+            .wire_len();
+        fs::write(&path, &data[..data.len() - footer_len]).unwrap();
+        assert!(snapshots.read(&latest).is_err());
+    }
+}
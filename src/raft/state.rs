@@ -0,0 +1,401 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, bail, ensure};
+use tracing::info;
+
+use crate::{config::Properties, storage::atomic_write};
+
+/// File in the first log dir holding the epoch of the quorum, its leader and the vote
+/// cast in it, so a restarted voter cannot vote twice in the same epoch
+pub const QUORUM_STATE_FILE: &str = "quorum-state";
+
+/// What a node of the quorum does in the current epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpochState {
+    /// Neither a leader is known nor a vote was cast
+    Unattached,
+    /// Voted for `candidate_id`, which may still win the election
+    Voted {
+        candidate_id: i32,
+    },
+    /// Runs for leader, `granted` are the voters that voted for it, itself included
+    Candidate {
+        granted: BTreeSet<i32>,
+    },
+    Leader(LeaderState),
+    /// Follows `leader_id`, fetching the metadata log from it
+    Follower {
+        leader_id: i32,
+    },
+}
+
+/// How far the replicas of the metadata log got, as known to the leader from their
+/// fetches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderState {
+    /// Offset of the first record written in the epoch, the leader change marker. The
+    /// high watermark only advances once a majority has it, so the records of older
+    /// epochs are only committed along with one of the new epoch.
+    pub epoch_start_offset: i64,
+    /// `None` until a majority of the voters fetched the epoch start offset
+    pub high_watermark: Option<i64>,
+    pub voters: BTreeMap<i32, ReplicaProgress>,
+    /// Brokers following the log without voting
+    pub observers: BTreeMap<i32, ReplicaProgress>,
+}
+
+/// The position of a replica on the leader
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicaProgress {
+    /// -1 until the replica fetched
+    pub end_offset: i64,
+    pub last_fetch: Option<SystemTime>,
+    /// When the replica last fetched up to the log end offset of the leader
+    pub last_caught_up: Option<SystemTime>,
+}
+
+impl LeaderState {
+    fn new(node_id: i32, voters: &BTreeSet<i32>, epoch_start_offset: i64) -> Self {
+        let mut voters: BTreeMap<i32, ReplicaProgress> = voters
+            .iter()
+            .map(|&id| {
+                let progress = ReplicaProgress {
+                    end_offset: -1,
+                    ..ReplicaProgress::default()
+                };
+                (id, progress)
+            })
+            .collect();
+        if let Some(leader) = voters.get_mut(&node_id) {
+            leader.end_offset = epoch_start_offset;
+        }
+        Self {
+            epoch_start_offset,
+            high_watermark: None,
+            voters,
+            observers: BTreeMap::new(),
+        }
+    }
+
+    /// Records a fetch by `replica_id` up to `end_offset`, when the log of the leader
+    /// ends at `leader_end_offset`. Returns whether the high watermark advanced.
+    pub fn update_replica(
+        &mut self,
+        replica_id: i32,
+        end_offset: i64,
+        leader_end_offset: i64,
+        now: SystemTime,
+    ) -> bool {
+        let progress = match self.voters.get_mut(&replica_id) {
+            Some(progress) => progress,
+            None => self.observers.entry(replica_id).or_insert(ReplicaProgress {
+                end_offset: -1,
+                ..ReplicaProgress::default()
+            }),
+        };
+        progress.end_offset = end_offset;
+        progress.last_fetch = Some(now);
+        if end_offset >= leader_end_offset {
+            progress.last_caught_up = Some(now);
+        }
+        self.maybe_advance_high_watermark()
+    }
+
+    /// Moves the high watermark to the highest offset a majority of the voters has,
+    /// once that is past the epoch start offset. It never goes back.
+    fn maybe_advance_high_watermark(&mut self) -> bool {
+        let mut end_offsets: Vec<i64> = self.voters.values().map(|v| v.end_offset).collect();
+        end_offsets.sort_unstable_by(|a, b| b.cmp(a));
+        let majority_end_offset = end_offsets[end_offsets.len() / 2];
+        if majority_end_offset <= self.epoch_start_offset
+            || self
+                .high_watermark
+                .is_some_and(|hw| hw >= majority_end_offset)
+        {
+            return false;
+        }
+        self.high_watermark = Some(majority_end_offset);
+        true
+    }
+}
+
+/// The epoch of the quorum and what this node does in it. Every transition is persisted
+/// to the [`QUORUM_STATE_FILE`] before it takes effect.
+#[derive(Debug)]
+pub struct QuorumState {
+    path: PathBuf,
+    node_id: i32,
+    voters: BTreeSet<i32>,
+    epoch: i32,
+    state: EpochState,
+}
+
+impl QuorumState {
+    /// Restores the state persisted in `log_dir`, or starts unattached in epoch 0. A node
+    /// that led the quorum before it stopped comes back unattached, its followers elect
+    /// a new leader in a later epoch.
+    pub fn load(log_dir: &Path, node_id: i32, voters: BTreeSet<i32>) -> anyhow::Result<Self> {
+        let path = log_dir.join(QUORUM_STATE_FILE);
+        let mut quorum = Self {
+            path,
+            node_id,
+            voters,
+            epoch: 0,
+            state: EpochState::Unattached,
+        };
+        if !quorum.path.exists() {
+            return Ok(quorum);
+        }
+
+        let props = Properties::read(&quorum.path)?;
+        let get = |key: &str| -> anyhow::Result<i32> {
+            props
+                .get(key)
+                .with_context(|| format!("No {key} in {}", quorum.path.display()))?
+                .parse()
+                .with_context(|| format!("Invalid {key} in {}", quorum.path.display()))
+        };
+        quorum.epoch = get("leader.epoch")?;
+        let leader_id = get("leader.id")?;
+        let voted_id = get("voted.id")?;
+        quorum.state = if leader_id >= 0 && leader_id != node_id {
+            EpochState::Follower { leader_id }
+        } else if voted_id >= 0 && leader_id < 0 {
+            EpochState::Voted {
+                candidate_id: voted_id,
+            }
+        } else {
+            EpochState::Unattached
+        };
+        info!(
+            "Restored quorum state {:?} in epoch {}",
+            quorum.state, quorum.epoch
+        );
+        Ok(quorum)
+    }
+
+    pub fn node_id(&self) -> i32 {
+        self.node_id
+    }
+
+    pub fn epoch(&self) -> i32 {
+        self.epoch
+    }
+
+    pub fn state(&self) -> &EpochState {
+        &self.state
+    }
+
+    pub fn voters(&self) -> &BTreeSet<i32> {
+        &self.voters
+    }
+
+    pub fn is_voter(&self, id: i32) -> bool {
+        self.voters.contains(&id)
+    }
+
+    /// The number of votes needed to win an election
+    pub fn majority(&self) -> usize {
+        self.voters.len() / 2 + 1
+    }
+
+    /// The leader of the current epoch, if known
+    pub fn leader_id(&self) -> Option<i32> {
+        match self.state {
+            EpochState::Leader(_) => Some(self.node_id),
+            EpochState::Follower { leader_id } => Some(leader_id),
+            _ => None,
+        }
+    }
+
+    pub fn leader_state(&self) -> Option<&LeaderState> {
+        match &self.state {
+            EpochState::Leader(leader) => Some(leader),
+            _ => None,
+        }
+    }
+
+    pub fn leader_state_mut(&mut self) -> Option<&mut LeaderState> {
+        match &mut self.state {
+            EpochState::Leader(leader) => Some(leader),
+            _ => None,
+        }
+    }
+
+    /// Forgets the leader and vote of the current epoch, or moves to a later epoch
+    /// without either
+    pub fn transition_to_unattached(&mut self, epoch: i32) -> anyhow::Result<()> {
+        ensure!(
+            epoch >= self.epoch,
+            "Cannot go back from epoch {} to {epoch}",
+            self.epoch
+        );
+        self.transition(epoch, EpochState::Unattached)
+    }
+
+    /// Votes for `candidate_id` in the current epoch, which only an unattached voter can
+    pub fn transition_to_voted(&mut self, candidate_id: i32) -> anyhow::Result<()> {
+        ensure!(
+            self.state == EpochState::Unattached,
+            "Cannot vote in epoch {} while {:?}",
+            self.epoch,
+            self.state
+        );
+        self.transition(self.epoch, EpochState::Voted { candidate_id })
+    }
+
+    /// Starts an election in the next epoch, voting for itself
+    pub fn transition_to_candidate(&mut self) -> anyhow::Result<()> {
+        ensure!(
+            self.is_voter(self.node_id),
+            "Node {} is not a voter",
+            self.node_id
+        );
+        ensure!(
+            !matches!(self.state, EpochState::Leader(_)),
+            "The leader cannot run for election"
+        );
+        let granted = BTreeSet::from([self.node_id]);
+        self.transition(self.epoch + 1, EpochState::Candidate { granted })
+    }
+
+    /// Records a vote granted to this candidate. Returns whether it won the election.
+    pub fn record_granted_vote(&mut self, voter_id: i32) -> bool {
+        let majority = self.majority();
+        match &mut self.state {
+            EpochState::Candidate { granted } => {
+                if self.voters.contains(&voter_id) {
+                    granted.insert(voter_id);
+                }
+                granted.len() >= majority
+            }
+            _ => false,
+        }
+    }
+
+    /// Takes over the leadership of the current epoch, which the candidate won. Its log
+    /// ends at `epoch_start_offset`.
+    pub fn transition_to_leader(&mut self, epoch_start_offset: i64) -> anyhow::Result<()> {
+        let EpochState::Candidate { granted } = &self.state else {
+            bail!("Only a candidate can become leader, not {:?}", self.state);
+        };
+        ensure!(
+            granted.len() >= self.majority(),
+            "Candidate has {} of the {} votes needed",
+            granted.len(),
+            self.majority()
+        );
+        let leader = LeaderState::new(self.node_id, &self.voters, epoch_start_offset);
+        self.transition(self.epoch, EpochState::Leader(leader))
+    }
+
+    /// Follows `leader_id` in `epoch`, which has to be later than the current one, or the
+    /// current one while no other leader is known
+    pub fn transition_to_follower(&mut self, epoch: i32, leader_id: i32) -> anyhow::Result<()> {
+        ensure!(
+            epoch >= self.epoch,
+            "Cannot go back from epoch {} to {epoch}",
+            self.epoch
+        );
+        if epoch == self.epoch {
+            ensure!(
+                self.leader_id().map_or(true, |id| id == leader_id),
+                "Epoch {epoch} is already led by {:?}, not {leader_id}",
+                self.leader_id()
+            );
+        }
+        self.transition(epoch, EpochState::Follower { leader_id })
+    }
+
+    fn transition(&mut self, epoch: i32, state: EpochState) -> anyhow::Result<()> {
+        let (leader_id, voted_id) = match &state {
+            EpochState::Unattached => (-1, -1),
+            EpochState::Voted { candidate_id } => (-1, *candidate_id),
+            EpochState::Candidate { .. } => (-1, self.node_id),
+            EpochState::Leader(_) => (self.node_id, -1),
+            EpochState::Follower { leader_id } => (*leader_id, -1),
+        };
+        // a vote cast in the epoch is kept once its leader is known
+        let voted_id = match self.state {
+            EpochState::Voted { candidate_id } if epoch == self.epoch && voted_id < 0 => {
+                candidate_id
+            }
+            _ => voted_id,
+        };
+        Self::write(&self.path, epoch, leader_id, voted_id)?;
+        info!(
+            "Quorum state changed from {:?} in epoch {} to {state:?} in epoch {epoch}",
+            self.state, self.epoch
+        );
+        self.epoch = epoch;
+        self.state = state;
+        Ok(())
+    }
+
+    /// Replaces the persisted state
+    fn write(path: &Path, epoch: i32, leader_id: i32, voted_id: i32) -> anyhow::Result<()> {
+        let content = format!("leader.epoch={epoch}\nleader.id={leader_id}\nvoted.id={voted_id}\n");
+        atomic_write(path, content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let voters = BTreeSet::from([1, 2, 3]);
+        let mut state = QuorumState::load(dir.path(), 1, voters.clone()).unwrap();
+        assert_eq!((0, &EpochState::Unattached), (state.epoch(), state.state()));
+
+        state.transition_to_voted(2).unwrap();
+        assert!(state.transition_to_voted(3).is_err());
+        let restored = QuorumState::load(dir.path(), 1, voters.clone()).unwrap();
+        assert_eq!(&EpochState::Voted { candidate_id: 2 }, restored.state());
+
+        state.transition_to_candidate().unwrap();
+        assert!(!state.record_granted_vote(4));
+        assert!(state.record_granted_vote(3));
+        state.transition_to_leader(10).unwrap();
+        assert_eq!((1, Some(1)), (state.epoch(), state.leader_id()));
+        // a restarted leader does not resume its leadership
+        let restored = QuorumState::load(dir.path(), 1, voters.clone()).unwrap();
+        assert_eq!(
+            (1, &EpochState::Unattached),
+            (restored.epoch(), restored.state())
+        );
+
+        assert!(state.transition_to_follower(0, 2).is_err());
+        assert!(state.transition_to_follower(1, 2).is_err());
+        state.transition_to_follower(2, 2).unwrap();
+        let restored = QuorumState::load(dir.path(), 1, voters).unwrap();
+        assert_eq!(Some(2), restored.leader_id());
+    }
+
+    #[test]
+    fn test_high_watermark() {
+        let now = SystemTime::now();
+        let mut leader = LeaderState::new(1, &BTreeSet::from([1, 2, 3]), 5);
+        leader.voters.get_mut(&1).unwrap().end_offset = 8;
+        // a majority has to fetch past the epoch start offset
+        assert!(!leader.update_replica(2, 5, 8, now));
+        assert_eq!(None, leader.high_watermark);
+        assert!(leader.update_replica(2, 7, 8, now));
+        assert_eq!(Some(7), leader.high_watermark);
+        assert!(!leader.update_replica(2, 6, 8, now));
+        assert_eq!(Some(7), leader.high_watermark);
+        assert!(leader.update_replica(3, 8, 8, now));
+        assert_eq!(Some(8), leader.high_watermark);
+
+        // observers do not count
+        assert!(!leader.update_replica(4, 9, 8, now));
+        assert_eq!(Some(9), leader.observers.get(&4).map(|o| o.end_offset));
+        assert!(leader.voters[&3].last_caught_up.is_some());
+    }
+}
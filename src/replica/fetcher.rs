@@ -0,0 +1,181 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Context;
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+use super::{FollowerFetchState, ReplicaManager, manager::FetcherConfig};
+use crate::{
+    network,
+    primitives::CompactArray,
+    request::{FetchPartition, FetchRequestBody, FetchTopic},
+    response::body::FetchResponseBody,
+    storage::TopicPartition,
+    types::{ApiKeys, ErrorCode},
+};
+
+/// Version of the fetch requests sent by followers
+const FETCH_VERSION: i16 = 12;
+/// Client id of the fetch requests sent by followers
+const CLIENT_ID: &str = "replica-fetcher";
+
+/// Fetches every partition this broker follows on `leader_id` from the leader at
+/// `address`, appending what it gets to their logs, for as long as the task is not
+/// aborted. The partitions are looked up again before every fetch, so partitions that
+/// started or stopped following the leader are picked up. Failed fetches are retried
+/// after `replica.fetch.backoff.ms`, on a new connection if the old one broke.
+pub(super) async fn run_fetcher(manager: Arc<ReplicaManager>, leader_id: i32, address: String) {
+    let config = manager.fetcher_config().clone();
+    let mut stream: Option<TcpStream> = None;
+    let mut correlation_id = 0;
+    loop {
+        let states = manager.fetch_states(leader_id);
+        if states.is_empty() {
+            tokio::time::sleep(config.backoff).await;
+            continue;
+        }
+
+        let connection = match stream.as_mut() {
+            Some(connection) => connection,
+            None => match network::connect(&address, config.socket_timeout).await {
+                Ok(connection) => {
+                    info!("Connected to leader {leader_id} at {address}");
+                    stream.insert(connection)
+                }
+                Err(e) => {
+                    warn!("Connecting to leader {leader_id} at {address} failed: {e:#}");
+                    tokio::time::sleep(config.backoff).await;
+                    continue;
+                }
+            },
+        };
+
+        correlation_id += 1;
+        let request = fetch_request(&config, &states);
+        let fetched = tokio::time::timeout(
+            config.socket_timeout,
+            network::send_request(
+                connection,
+                ApiKeys::Fetch,
+                FETCH_VERSION,
+                correlation_id,
+                CLIENT_ID,
+                &request,
+            ),
+        )
+        .await
+        .context("Fetch timed out")
+        .and_then(|fetched| fetched);
+        let response: FetchResponseBody = match fetched {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Fetching from leader {leader_id} at {address} failed: {e:#}");
+                stream = None;
+                tokio::time::sleep(config.backoff).await;
+                continue;
+            }
+        };
+
+        let manager = Arc::clone(&manager);
+        let processed =
+            tokio::task::spawn_blocking(move || process_response(&manager, leader_id, &response))
+                .await;
+        match processed {
+            Ok(Ok(true)) => {}
+            Ok(Ok(false)) => tokio::time::sleep(config.backoff).await,
+            Ok(Err(e)) => {
+                warn!("Fetch from leader {leader_id} failed: {e:#}");
+                tokio::time::sleep(config.backoff).await;
+            }
+            Err(e) => {
+                warn!("Processing fetch from leader {leader_id} panicked: {e}");
+                tokio::time::sleep(config.backoff).await;
+            }
+        }
+    }
+}
+
+fn fetch_request(config: &FetcherConfig, states: &[FollowerFetchState]) -> FetchRequestBody {
+    let mut by_topic: BTreeMap<&str, Vec<&FollowerFetchState>> = BTreeMap::new();
+    for state in states {
+        by_topic
+            .entry(&state.topic_partition.topic)
+            .or_default()
+            .push(state);
+    }
+    let mut topics = CompactArray::with_capacity(by_topic.len());
+    for (topic, states) in by_topic {
+        let mut partitions = CompactArray::with_capacity(states.len());
+        for state in states {
+            partitions.push(
+                FetchPartition::new(
+                    state.topic_partition.partition,
+                    state.leader_epoch,
+                    state.fetch_offset,
+                    state.log_start_offset,
+                    config.max_bytes,
+                )
+                .with_last_fetched_epoch(state.last_fetched_epoch),
+            );
+        }
+        topics.push(FetchTopic::new(topic, partitions));
+    }
+    FetchRequestBody::new(
+        config.node_id,
+        i32::try_from(config.max_wait.as_millis()).unwrap_or(i32::MAX),
+        config.min_bytes,
+        config.response_max_bytes,
+        topics,
+    )
+}
+
+/// Appends the fetched batches, truncates the partitions that diverged from the leader,
+/// or gets partitions whose fetch offset was out of range back in range. Returns whether every partition was fetched without an error, the
+/// fetcher backs off before its next fetch otherwise.
+fn process_response(
+    manager: &ReplicaManager,
+    leader_id: i32,
+    response: &FetchResponseBody,
+) -> anyhow::Result<bool> {
+    if response.error_code != ErrorCode::None.code() {
+        anyhow::bail!("Leader answered with error {}", response.error_code);
+    }
+    let mut clean = true;
+    for topic in response.responses.iter() {
+        for partition in topic.partitions.iter() {
+            let tp = TopicPartition::new(topic.topic.0.clone(), partition.partition_index);
+            let processed = match partition.error_code {
+                code if code == ErrorCode::None.code() => {
+                    partition.diverging_epoch().and_then(|diverging| match diverging {
+                        Some(diverging) => manager.truncate_diverging(leader_id, &tp, &diverging),
+                        None => manager.append_fetched(
+                            leader_id,
+                            &tp,
+                            &partition.records.0,
+                            partition.high_watermark,
+                            partition.log_start_offset,
+                        ),
+                    })
+                }
+                code if code == ErrorCode::OffsetOutOfRange.code() => manager
+                    .handle_offset_out_of_range(
+                        leader_id,
+                        &tp,
+                        partition.high_watermark,
+                        partition.log_start_offset,
+                    ),
+                code => {
+                    // the controller tells this broker about a new leader soon
+                    debug!("Leader {leader_id} answered the fetch of {tp} with error {code}");
+                    clean = false;
+                    Ok(())
+                }
+            };
+            if let Err(e) = processed {
+                warn!("Processing the fetch of {tp} failed: {e:#}");
+                clean = false;
+            }
+        }
+    }
+    Ok(clean)
+}
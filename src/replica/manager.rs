@@ -0,0 +1,704 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use bytes::BytesMut;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+
+use super::{Partition, PartitionAssignment, ReplicaError, fetcher};
+use crate::{
+    codec::{Decoder, Encoder},
+    config::BrokerConfig,
+    request::{LeaderAndIsrRequestBody, ListOffsetsPartition},
+    storage::{LogManager, OffsetOutOfRange, TimestampAndOffset, TopicPartition},
+    types::{EpochEndOffset, ErrorCode, RecordBatch},
+};
+
+/// How the followers of this broker fetch from their leaders
+#[derive(Debug, Clone)]
+pub(super) struct FetcherConfig {
+    pub(super) node_id: i32,
+    pub(super) max_wait: Duration,
+    pub(super) min_bytes: i32,
+    pub(super) max_bytes: i32,
+    pub(super) response_max_bytes: i32,
+    pub(super) backoff: Duration,
+    pub(super) socket_timeout: Duration,
+}
+
+/// Where a follower partition fetches from next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FollowerFetchState {
+    pub topic_partition: TopicPartition,
+    pub leader_epoch: i32,
+    pub fetch_offset: i64,
+    /// The latest leader epoch of the log, -1 without one
+    pub last_fetched_epoch: i32,
+    pub log_start_offset: i64,
+}
+
+/// What a read from a partition's leader returned. Reads outside of the log report
+/// `OFFSET_OUT_OF_RANGE` along with the high watermark and log start offset, which a
+/// follower needs to get back in range.
+#[derive(Debug)]
+pub struct LogReadResult {
+    pub error: ErrorCode,
+    pub high_watermark: i64,
+    pub log_start_offset: i64,
+    /// Where the log of a follower stopped matching this one, nothing is read then
+    pub diverging_epoch: Option<EpochEndOffset>,
+    /// The record batches read, encoded
+    pub records: Vec<u8>,
+}
+
+/// A fetcher task, fetching every partition this broker follows on one leader
+#[derive(Debug)]
+struct ReplicaFetcher {
+    address: String,
+    task: JoinHandle<()>,
+}
+
+/// Every partition hosted by the broker, along with the fetchers keeping the ones it
+/// follows in sync. Logs found on disk at startup are led by this broker alone until
+/// the controller assigns them otherwise.
+#[derive(Debug)]
+pub struct ReplicaManager {
+    node_id: i32,
+    replica_lag_time_max: Duration,
+    fetcher_config: FetcherConfig,
+    log_manager: Arc<LogManager>,
+    partitions: RwLock<HashMap<TopicPartition, Arc<Mutex<Partition>>>>,
+    /// Epoch of the latest controller this broker heard from, older ones are ignored
+    controller_epoch: Mutex<i32>,
+    /// `host:port` of the leaders, as the controller told
+    leader_addresses: Mutex<HashMap<i32, String>>,
+    /// One fetcher per leader of a followed partition
+    fetchers: Mutex<HashMap<i32, ReplicaFetcher>>,
+}
+
+impl ReplicaManager {
+    /// # Errors
+    ///
+    /// Fails if the log end offset of a loaded log cannot be read
+    pub fn new(config: &BrokerConfig, log_manager: Arc<LogManager>) -> anyhow::Result<Self> {
+        let node_id = config.node_id;
+        let now = Instant::now();
+        let mut partitions = HashMap::new();
+        for log in log_manager.logs() {
+            let tp = log.lock().unwrap().topic_partition().clone();
+            let assignment = PartitionAssignment {
+                leader: node_id,
+                leader_epoch: -1,
+                partition_epoch: -1,
+                replicas: vec![node_id],
+                isr: vec![node_id],
+            };
+            let mut partition = Partition::new(tp.clone(), node_id, assignment.clone(), log);
+            partition.make_leader(assignment, now)?;
+            partitions.insert(tp, Arc::new(Mutex::new(partition)));
+        }
+
+        Ok(Self {
+            node_id,
+            replica_lag_time_max: config.replica_lag_time_max,
+            fetcher_config: FetcherConfig {
+                node_id,
+                max_wait: config.replica_fetch_wait_max,
+                min_bytes: config.replica_fetch_min_bytes,
+                max_bytes: config.replica_fetch_max_bytes,
+                response_max_bytes: config.replica_fetch_response_max_bytes,
+                backoff: config.replica_fetch_backoff,
+                socket_timeout: config.replica_socket_timeout,
+            },
+            log_manager,
+            partitions: RwLock::new(partitions),
+            controller_epoch: Mutex::new(0),
+            leader_addresses: Mutex::new(HashMap::new()),
+            fetchers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn node_id(&self) -> i32 {
+        self.node_id
+    }
+
+    pub(super) fn fetcher_config(&self) -> &FetcherConfig {
+        &self.fetcher_config
+    }
+
+    pub fn get_partition(&self, tp: &TopicPartition) -> Option<Arc<Mutex<Partition>>> {
+        self.partitions.read().unwrap().get(tp).cloned()
+    }
+
+    /// Every partition hosted by the broker, led or followed
+    pub fn partitions(&self) -> Vec<Arc<Mutex<Partition>>> {
+        self.partitions.read().unwrap().values().cloned().collect()
+    }
+
+    fn partition(&self, tp: &TopicPartition) -> Result<Arc<Mutex<Partition>>, ReplicaError> {
+        self.get_partition(tp)
+            .ok_or_else(|| ReplicaError::UnknownPartition(tp.clone()))
+    }
+
+    /// Number of partitions led by this broker with replicas out of the ISR
+    pub fn under_replicated_partitions(&self) -> usize {
+        self.partitions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|p| p.lock().unwrap().is_under_replicated())
+            .count()
+    }
+
+    /// Applies the leaders and ISRs the controller assigned. Partitions become led by this
+    /// broker or followed, creating their logs as needed, then the fetchers are updated to
+    /// fetch the followed ones from their leaders. Requests of an older controller fail
+    /// as a whole, partitions fail on their own if their leader epoch is not newer than
+    /// the current one or this broker is not one of their replicas.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ReplicaError::StaleControllerEpoch`] if a newer controller was seen
+    pub fn become_leader_or_follower(
+        self: &Arc<Self>,
+        request: &LeaderAndIsrRequestBody,
+    ) -> Result<Vec<(TopicPartition, ErrorCode)>, ReplicaError> {
+        let mut controller_epoch = self.controller_epoch.lock().unwrap();
+        if request.controller_epoch < *controller_epoch {
+            return Err(ReplicaError::StaleControllerEpoch {
+                controller_epoch: request.controller_epoch,
+                current: *controller_epoch,
+            });
+        }
+        *controller_epoch = request.controller_epoch;
+
+        {
+            let mut addresses = self.leader_addresses.lock().unwrap();
+            for leader in request.live_leaders.iter() {
+                addresses.insert(
+                    leader.broker_id,
+                    format!("{}:{}", leader.host_name.0, leader.port),
+                );
+            }
+        }
+
+        let now = Instant::now();
+        let mut results = Vec::new();
+        for topic in request.topic_states.iter() {
+            for state in topic.partition_states.iter() {
+                let tp = TopicPartition::new(topic.topic_name.0.clone(), state.partition_index);
+                let assignment = PartitionAssignment {
+                    leader: state.leader,
+                    leader_epoch: state.leader_epoch,
+                    partition_epoch: state.partition_epoch,
+                    replicas: state.replicas.iter().copied().collect(),
+                    isr: state.isr.iter().copied().collect(),
+                };
+                let code = match self.apply_assignment(&tp, assignment, now) {
+                    Ok(()) => ErrorCode::None,
+                    Err(e) => {
+                        warn!("Applying the assignment of {tp} failed: {e:#}");
+                        e.downcast_ref::<ReplicaError>()
+                            .map_or(ErrorCode::UnknownServerError, ReplicaError::error_code)
+                    }
+                };
+                results.push((tp, code));
+            }
+        }
+        drop(controller_epoch);
+
+        self.update_fetchers();
+        Ok(results)
+    }
+
+    /// Applies the leaders and ISRs of the metadata image to the partitions hosting a
+    /// replica on this broker, the way [`Self::become_leader_or_follower`] applies the
+    /// ones a controller sends. `leader_addresses` are the `host:port` the registered
+    /// brokers are reached at. Partitions whose leader epoch did not increase are left
+    /// alone, their leader keeps track of the ISR. Returns the partitions that changed.
+    pub fn apply_leaderships(
+        self: &Arc<Self>,
+        assignments: Vec<(TopicPartition, PartitionAssignment)>,
+        leader_addresses: HashMap<i32, String>,
+    ) -> Vec<TopicPartition> {
+        self.leader_addresses
+            .lock()
+            .unwrap()
+            .extend(leader_addresses);
+
+        let now = Instant::now();
+        let mut changed = Vec::new();
+        for (tp, assignment) in assignments {
+            let current = self
+                .get_partition(&tp)
+                .map(|partition| partition.lock().unwrap().leader_epoch());
+            if current.is_some_and(|current| current >= assignment.leader_epoch) {
+                continue;
+            }
+            match self.apply_assignment(&tp, assignment, now) {
+                Ok(()) => changed.push(tp),
+                Err(e) => warn!("Applying the assignment of {tp} failed: {e:#}"),
+            }
+        }
+        self.update_fetchers();
+        changed
+    }
+
+    fn apply_assignment(
+        &self,
+        tp: &TopicPartition,
+        assignment: PartitionAssignment,
+        now: Instant,
+    ) -> anyhow::Result<()> {
+        if !assignment.replicas.contains(&self.node_id) {
+            return Err(ReplicaError::UnknownPartition(tp.clone()).into());
+        }
+        let partition = match self.get_partition(tp) {
+            Some(partition) => partition,
+            None => {
+                let log = self.log_manager.get_or_create_log(tp)?;
+                let unassigned = PartitionAssignment {
+                    leader: -1,
+                    leader_epoch: -1,
+                    partition_epoch: -1,
+                    replicas: Vec::new(),
+                    isr: Vec::new(),
+                };
+                let partition = Partition::new(tp.clone(), self.node_id, unassigned, log);
+                Arc::clone(
+                    self.partitions
+                        .write()
+                        .unwrap()
+                        .entry(tp.clone())
+                        .or_insert_with(|| Arc::new(Mutex::new(partition))),
+                )
+            }
+        };
+
+        let mut partition = partition.lock().unwrap();
+        let current = partition.leader_epoch();
+        if assignment.leader_epoch <= current {
+            return Err(ReplicaError::StaleLeaderEpoch {
+                topic_partition: tp.clone(),
+                leader_epoch: assignment.leader_epoch,
+                current,
+            }
+            .into());
+        }
+        if assignment.leader == self.node_id {
+            info!("Leading {tp} in epoch {}", assignment.leader_epoch);
+            partition.make_leader(assignment, now)
+        } else {
+            info!(
+                "Following broker {} for {tp} in epoch {}",
+                assignment.leader, assignment.leader_epoch
+            );
+            partition.make_follower(assignment)
+        }
+    }
+
+    /// Starts a fetcher for every leader of a followed partition, restarting it if the
+    /// leader moved, and stops the fetchers of leaders no partition is followed on anymore
+    fn update_fetchers(self: &Arc<Self>) {
+        let leaders: HashSet<i32> = self
+            .partitions
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|p| {
+                let p = p.lock().unwrap();
+                (!p.is_leader() && p.leader() >= 0).then(|| p.leader())
+            })
+            .collect();
+        let addresses = self.leader_addresses.lock().unwrap().clone();
+
+        let mut fetchers = self.fetchers.lock().unwrap();
+        fetchers.retain(|leader, fetcher| {
+            let keep = leaders.contains(leader) && addresses.get(leader) == Some(&fetcher.address);
+            if !keep {
+                info!("Stopping fetcher of broker {leader}");
+                fetcher.task.abort();
+            }
+            keep
+        });
+        for leader in leaders {
+            if fetchers.contains_key(&leader) {
+                continue;
+            }
+            let Some(address) = addresses.get(&leader) else {
+                warn!("Address of leader {leader} is unknown, cannot fetch from it");
+                continue;
+            };
+            info!("Starting fetcher of broker {leader} at {address}");
+            let task = tokio::spawn(fetcher::run_fetcher(
+                Arc::clone(self),
+                leader,
+                address.clone(),
+            ));
+            fetchers.insert(
+                leader,
+                ReplicaFetcher {
+                    address: address.clone(),
+                    task,
+                },
+            );
+        }
+    }
+
+    /// Stops every fetcher
+    pub fn shutdown(&self) {
+        for (_, fetcher) in self.fetchers.lock().unwrap().drain() {
+            fetcher.task.abort();
+        }
+    }
+
+    /// Appends a produced batch to the partition this broker leads. With `acks=all`
+    /// the ISR has to have at least `min.insync.replicas` replicas.
+    /// Returns the base offset of the batch and the log start offset.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a [`ReplicaError`] if the partition is not led here or the ISR is too
+    /// small, or with the errors of [`crate::storage::PartitionLog::append`]
+    pub fn append_to_leader(
+        &self,
+        tp: &TopicPartition,
+        batch: RecordBatch,
+        require_min_isr: bool,
+    ) -> anyhow::Result<(i64, i64)> {
+        let partition = self.partition(tp)?;
+        let config = self.log_manager.config_for(&tp.topic);
+        let mut partition = partition.lock().unwrap();
+        partition.append_as_leader(batch, &config, require_min_isr)
+    }
+
+    /// Reads the batches from `fetch_offset` on, up to the high watermark for consumers or
+    /// up to the log end offset for followers. Followers whose log diverged from this one,
+    /// as told by the epoch of the last batch they fetched, are told where to truncate it
+    /// to instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a [`ReplicaError`] if the partition is not led here or `leader_epoch`
+    /// does not match, or if the log cannot be read
+    pub fn read(
+        &self,
+        tp: &TopicPartition,
+        fetch_offset: i64,
+        leader_epoch: i32,
+        last_fetched_epoch: i32,
+        max_bytes: usize,
+        from_follower: bool,
+    ) -> anyhow::Result<LogReadResult> {
+        let partition = self.partition(tp)?;
+        let partition = partition.lock().unwrap();
+        partition.check_leader_epoch(leader_epoch)?;
+        if !partition.is_leader() {
+            return Err(ReplicaError::NotLeader(tp.clone()).into());
+        }
+
+        let log = partition.log().lock().unwrap();
+        let high_watermark = log.high_watermark();
+        let max_offset = if from_follower {
+            i64::MAX
+        } else {
+            high_watermark
+        };
+        let mut result = LogReadResult {
+            error: ErrorCode::None,
+            high_watermark,
+            log_start_offset: log.log_start_offset(),
+            diverging_epoch: None,
+            records: Vec::new(),
+        };
+        if from_follower {
+            result.diverging_epoch = log.validate_fetch(fetch_offset, last_fetched_epoch);
+            if result.diverging_epoch.is_some() {
+                return Ok(result);
+            }
+        }
+        let batches = match log.read(fetch_offset, max_offset, max_bytes) {
+            Ok(batches) => batches,
+            Err(e) if e.downcast_ref::<OffsetOutOfRange>().is_some() => {
+                result.error = ErrorCode::OffsetOutOfRange;
+                return Ok(result);
+            }
+            Err(e) => return Err(e),
+        };
+        let mut records = BytesMut::new();
+        for batch in &batches {
+            batch.encode(&mut records)?;
+        }
+        result.records = records.to_vec();
+        Ok(result)
+    }
+
+    /// Looks up the offset a ListOffsets request asks for with `timestamp`: the log start
+    /// offset for [`ListOffsetsPartition::EARLIEST_TIMESTAMP`], the high watermark for
+    /// [`ListOffsetsPartition::LATEST_TIMESTAMP`], or else the first record at or after the
+    /// timestamp. Only committed records are found, both isolation levels read up to the
+    /// high watermark as there are no transactions. `None` if no record is that recent.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a [`ReplicaError`] if the partition is not led here or `leader_epoch`
+    /// does not match, or if the log cannot be read
+    pub fn offset_for_timestamp(
+        &self,
+        tp: &TopicPartition,
+        timestamp: i64,
+        leader_epoch: i32,
+    ) -> anyhow::Result<Option<TimestampAndOffset>> {
+        let partition = self.partition(tp)?;
+        let partition = partition.lock().unwrap();
+        partition.check_leader_epoch(leader_epoch)?;
+        if !partition.is_leader() {
+            return Err(ReplicaError::NotLeader(tp.clone()).into());
+        }
+
+        let log = partition.log().lock().unwrap();
+        let high_watermark = log.high_watermark();
+        Ok(match timestamp {
+            ListOffsetsPartition::EARLIEST_TIMESTAMP => Some(TimestampAndOffset {
+                timestamp: -1,
+                offset: log.log_start_offset(),
+                leader_epoch: -1,
+            }),
+            ListOffsetsPartition::LATEST_TIMESTAMP => Some(TimestampAndOffset {
+                timestamp: -1,
+                offset: high_watermark,
+                leader_epoch: log.latest_epoch().unwrap_or(-1),
+            }),
+            _ => log
+                .offset_for_timestamp(timestamp)?
+                .filter(|found| found.offset < high_watermark),
+        })
+    }
+
+    /// Records that follower `replica_id` fetched `tp` from `fetch_offset` on, which is
+    /// its log end offset. Fetch offsets beyond the leader's log, or of a log that diverged
+    /// from it after `last_fetched_epoch`, are ignored, as the follower is told to get back
+    /// in line first. Returns whether the high watermark advanced.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a [`ReplicaError`] if the partition is not led here or `replica_id` is
+    /// not one of its replicas
+    pub fn update_follower_fetch(
+        &self,
+        tp: &TopicPartition,
+        replica_id: i32,
+        fetch_offset: i64,
+        last_fetched_epoch: i32,
+        log_start_offset: i64,
+        now: Instant,
+    ) -> anyhow::Result<bool> {
+        let partition = self.partition(tp)?;
+        let mut partition = partition.lock().unwrap();
+        {
+            let log = partition.log().lock().unwrap();
+            if fetch_offset > log.log_end_offset()
+                || log.validate_fetch(fetch_offset, last_fetched_epoch).is_some()
+            {
+                return Ok(false);
+            }
+        }
+        partition.update_follower_fetch(replica_id, fetch_offset, log_start_offset, now)
+    }
+
+    /// Whether a produce waiting for `required_offset` of `tp` to be replicated can
+    /// complete: `None` while the high watermark is below it, the error of the partition
+    /// otherwise
+    pub fn check_enough_replicas_reached(
+        &self,
+        tp: &TopicPartition,
+        required_offset: i64,
+    ) -> Option<Result<(), ReplicaError>> {
+        let partition = match self.partition(tp) {
+            Ok(partition) => partition,
+            Err(e) => return Some(Err(e)),
+        };
+        let min_isr = self.log_manager.config_for(&tp.topic).min_insync_replicas;
+        let partition = partition.lock().unwrap();
+        partition.check_enough_replicas_reached(required_offset, min_isr)
+    }
+
+    /// The smallest log start offset among the replicas of `tp`
+    pub fn low_watermark(&self, tp: &TopicPartition) -> Option<i64> {
+        self.get_partition(tp)
+            .map(|partition| partition.lock().unwrap().low_watermark())
+    }
+
+    /// Removes the followers lagging for longer than `replica.lag.time.max.ms` from the
+    /// ISR of every partition this broker leads. Returns the partitions whose high
+    /// watermark advanced as a result.
+    pub fn maybe_shrink_isr(&self, now: Instant) -> Vec<TopicPartition> {
+        let partitions: Vec<_> = self.partitions.read().unwrap().values().cloned().collect();
+        let mut advanced = Vec::new();
+        for partition in partitions {
+            let mut partition = partition.lock().unwrap();
+            match partition.maybe_shrink_isr(now, self.replica_lag_time_max) {
+                Ok(true) => advanced.push(partition.topic_partition().clone()),
+                Ok(false) => {}
+                Err(e) => error!(
+                    "Shrinking the ISR of {} failed: {e:#}",
+                    partition.topic_partition()
+                ),
+            }
+        }
+        advanced
+    }
+
+    /// Where each partition followed on `leader_id` fetches from next
+    pub fn fetch_states(&self, leader_id: i32) -> Vec<FollowerFetchState> {
+        let partitions: Vec<_> = self.partitions.read().unwrap().values().cloned().collect();
+        let mut states = Vec::new();
+        for partition in partitions {
+            let partition = partition.lock().unwrap();
+            if partition.is_leader() || partition.leader() != leader_id {
+                continue;
+            }
+            let log = partition.log().lock().unwrap();
+            states.push(FollowerFetchState {
+                topic_partition: partition.topic_partition().clone(),
+                leader_epoch: partition.leader_epoch(),
+                fetch_offset: log.log_end_offset(),
+                last_fetched_epoch: log.latest_epoch().unwrap_or(-1),
+                log_start_offset: log.log_start_offset(),
+            });
+        }
+        states
+    }
+
+    /// Appends what was fetched from `leader_id` for a followed partition. Partitions that
+    /// changed leader since the fetch was sent are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the fetched batches cannot be decoded or appended
+    pub fn append_fetched(
+        &self,
+        leader_id: i32,
+        tp: &TopicPartition,
+        records: &[u8],
+        leader_high_watermark: i64,
+        leader_log_start_offset: i64,
+    ) -> anyhow::Result<()> {
+        let Some(partition) = self.get_partition(tp) else {
+            return Ok(());
+        };
+        let mut partition = partition.lock().unwrap();
+        if partition.is_leader() || partition.leader() != leader_id {
+            return Ok(());
+        }
+        let mut buf = BytesMut::from(records);
+        let mut batches = Vec::new();
+        while let Some(batch) = RecordBatch::decode(&mut buf, None)? {
+            batches.push(batch);
+        }
+        let config = self.log_manager.config_for(&tp.topic);
+        let log_end_offset = partition.append_as_follower(
+            &batches,
+            &config,
+            leader_high_watermark,
+            leader_log_start_offset,
+        )?;
+        debug!(
+            "Fetched {} batches of {tp}, now at {log_end_offset}",
+            batches.len()
+        );
+        Ok(())
+    }
+
+    /// Truncates a followed partition whose log diverged from the one of its leader to
+    /// the end of the diverging epoch, the records after it are fetched again
+    ///
+    /// # Errors
+    ///
+    /// Fails if the log cannot be truncated
+    pub fn truncate_diverging(
+        &self,
+        leader_id: i32,
+        tp: &TopicPartition,
+        diverging: &EpochEndOffset,
+    ) -> anyhow::Result<()> {
+        let Some(partition) = self.get_partition(tp) else {
+            return Ok(());
+        };
+        let partition = partition.lock().unwrap();
+        if partition.is_leader() || partition.leader() != leader_id {
+            return Ok(());
+        }
+        let mut log = partition.log().lock().unwrap();
+        let offset = log.truncation_offset(diverging);
+        info!(
+            "{tp} diverged from leader {leader_id} in epoch {}, truncating it to {offset}",
+            diverging.epoch
+        );
+        log.truncate_to(offset)?;
+        Ok(())
+    }
+
+    /// Brings a followed partition back in range after its leader answered a fetch with
+    /// `OFFSET_OUT_OF_RANGE`. A follower ahead of the leader's high watermark truncates
+    /// back to it, one that fell behind the leader's log start starts over from there.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the log cannot be truncated
+    pub fn handle_offset_out_of_range(
+        &self,
+        leader_id: i32,
+        tp: &TopicPartition,
+        leader_high_watermark: i64,
+        leader_log_start_offset: i64,
+    ) -> anyhow::Result<()> {
+        let Some(partition) = self.get_partition(tp) else {
+            return Ok(());
+        };
+        let partition = partition.lock().unwrap();
+        if partition.is_leader() || partition.leader() != leader_id {
+            return Ok(());
+        }
+        let mut log = partition.log().lock().unwrap();
+        let log_end_offset = log.log_end_offset();
+        if log_end_offset > leader_high_watermark {
+            warn!("{tp} is ahead of its leader, truncating it to {leader_high_watermark}");
+            log.truncate_to(leader_high_watermark)?;
+        } else {
+            warn!(
+                "{tp} fell behind the start of its leader, restarting it at {leader_log_start_offset}"
+            );
+            log.truncate_fully_and_start_at(leader_log_start_offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shrinks the ISRs every half `replica.lag.time.max.ms`, so followers are out of the
+/// ISR at most half that long after they stopped keeping up. `on_advance` is called with
+/// the partitions whose high watermark advanced, to complete the requests waiting for it.
+pub async fn run_isr_expiration<F>(manager: Arc<ReplicaManager>, on_advance: F)
+where
+    F: Fn(&TopicPartition) + Send + Sync + 'static,
+{
+    let period = manager.replica_lag_time_max / 2;
+    let on_advance = Arc::new(on_advance);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let (manager, on_advance) = (Arc::clone(&manager), Arc::clone(&on_advance));
+        let shrink = tokio::task::spawn_blocking(move || {
+            for tp in manager.maybe_shrink_isr(Instant::now()) {
+                on_advance(&tp);
+            }
+        });
+        if let Err(e) = shrink.await {
+            error!("ISR expiration task panicked: {e}");
+        }
+    }
+}
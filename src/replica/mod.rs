@@ -0,0 +1,88 @@
+//! Replication of partitions between brokers. The leader of a partition takes the
+//! produced records, its followers keep their logs in sync by fetching from it with
+//! their broker id as `replica_id`, like consumers do. From those fetches the leader
+//! learns how far every follower got: followers that caught up join the in-sync
+//! replicas (ISR), followers lagging for longer than `replica.lag.time.max.ms` are
+//! removed from it, and records become visible to consumers once every replica in
+//! the ISR has them, which is what `acks=all` produces wait for.
+//!
+//! Which broker leads which partition is decided by the controller and sent to the
+//! brokers in `LeaderAndIsr` requests, or on `KRaft` nodes read from the metadata image.
+//! Every leader bumps the leader epoch, and the log records the offset each epoch
+//! started at: a follower sends the epoch of its last fetched batch, and when its log
+//! diverged from the leader's it truncates to where their epochs last agreed.
+mod fetcher;
+mod manager;
+mod partition;
+
+pub use manager::{FollowerFetchState, LogReadResult, ReplicaManager, run_isr_expiration};
+pub use partition::{Partition, PartitionAssignment};
+
+use thiserror::Error;
+
+use crate::{storage::TopicPartition, types::ErrorCode};
+
+/// Why a partition cannot serve a request, or take on a new assignment
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReplicaError {
+    #[error("{0} is not hosted by this broker")]
+    UnknownPartition(TopicPartition),
+    #[error("This broker is not the leader of {0}")]
+    NotLeader(TopicPartition),
+    #[error("Broker {replica_id} is not a replica of {topic_partition}")]
+    UnknownReplica {
+        topic_partition: TopicPartition,
+        replica_id: i32,
+    },
+    #[error("Leader epoch {leader_epoch} of {topic_partition} is older than {current}")]
+    FencedLeaderEpoch {
+        topic_partition: TopicPartition,
+        leader_epoch: i32,
+        current: i32,
+    },
+    #[error("Leader epoch {leader_epoch} of {topic_partition} is newer than {current}")]
+    UnknownLeaderEpoch {
+        topic_partition: TopicPartition,
+        leader_epoch: i32,
+        current: i32,
+    },
+    #[error("Controller epoch {controller_epoch} is older than {current}")]
+    StaleControllerEpoch { controller_epoch: i32, current: i32 },
+    #[error("Leader epoch {leader_epoch} of {topic_partition} is not newer than {current}")]
+    StaleLeaderEpoch {
+        topic_partition: TopicPartition,
+        leader_epoch: i32,
+        current: i32,
+    },
+    #[error("{topic_partition} has {isr} in-sync replicas, {min_isr} are required")]
+    NotEnoughReplicas {
+        topic_partition: TopicPartition,
+        isr: usize,
+        min_isr: usize,
+    },
+    #[error(
+        "{topic_partition} has {isr} in-sync replicas after the append, {min_isr} are required"
+    )]
+    NotEnoughReplicasAfterAppend {
+        topic_partition: TopicPartition,
+        isr: usize,
+        min_isr: usize,
+    },
+}
+
+impl ReplicaError {
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::UnknownPartition(_) => ErrorCode::UnknownTopicOrPartition,
+            Self::NotLeader(_) | Self::UnknownReplica { .. } => ErrorCode::NotLeaderOrFollower,
+            Self::FencedLeaderEpoch { .. } => ErrorCode::FencedLeaderEpoch,
+            Self::UnknownLeaderEpoch { .. } => ErrorCode::UnknownLeaderEpoch,
+            // Kafka answers stale leader epochs with the controller's error as well
+            Self::StaleControllerEpoch { .. } | Self::StaleLeaderEpoch { .. } => {
+                ErrorCode::StaleControllerEpoch
+            }
+            Self::NotEnoughReplicas { .. } => ErrorCode::NotEnoughReplicas,
+            Self::NotEnoughReplicasAfterAppend { .. } => ErrorCode::NotEnoughReplicasAfterAppend,
+        }
+    }
+}
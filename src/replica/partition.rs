@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::ReplicaError;
+use crate::{
+    storage::{LogConfig, PartitionLog, TopicPartition},
+    types::RecordBatch,
+};
+
+/// Leader and replicas of a partition, as assigned by the controller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionAssignment {
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+}
+
+/// What the leader knows about a follower, from the fetch requests it sent
+#[derive(Debug, Clone, Copy)]
+struct FollowerState {
+    /// -1 until the follower fetched from this leader
+    log_end_offset: i64,
+    log_start_offset: i64,
+    /// The last time the follower had fetched everything the leader had
+    last_caught_up: Instant,
+    /// The leader's log end offset and the time of the previous fetch, the follower
+    /// caught up with that log end offset if its next fetch starts at or after it
+    last_fetch_leader_log_end_offset: i64,
+    last_fetch_time: Instant,
+}
+
+impl FollowerState {
+    fn new(now: Instant) -> Self {
+        Self {
+            log_end_offset: -1,
+            log_start_offset: -1,
+            last_caught_up: now,
+            last_fetch_leader_log_end_offset: -1,
+            last_fetch_time: now,
+        }
+    }
+}
+
+/// A partition hosted by the broker, either as its leader or as one of its followers.
+/// The leader tracks how far every follower got to advance the high watermark, the
+/// smallest log end offset of the in-sync replicas (ISR), and to tell which followers
+/// fell out of sync or caught up.
+///
+/// The partition has to be locked before its log.
+#[derive(Debug)]
+pub struct Partition {
+    topic_partition: TopicPartition,
+    node_id: i32,
+    assignment: PartitionAssignment,
+    /// The followers of the partition, only tracked while this broker leads it
+    followers: HashMap<i32, FollowerState>,
+    /// The log end offset when this broker became the leader. Followers only join the
+    /// ISR once they reached it, as the records before it may not be committed yet.
+    leader_epoch_start_offset: i64,
+    log: Arc<Mutex<PartitionLog>>,
+}
+
+impl Partition {
+    pub fn new(
+        topic_partition: TopicPartition,
+        node_id: i32,
+        assignment: PartitionAssignment,
+        log: Arc<Mutex<PartitionLog>>,
+    ) -> Self {
+        Self {
+            topic_partition,
+            node_id,
+            assignment,
+            followers: HashMap::new(),
+            leader_epoch_start_offset: 0,
+            log,
+        }
+    }
+
+    pub fn topic_partition(&self) -> &TopicPartition {
+        &self.topic_partition
+    }
+
+    pub fn assignment(&self) -> &PartitionAssignment {
+        &self.assignment
+    }
+
+    pub fn log(&self) -> &Arc<Mutex<PartitionLog>> {
+        &self.log
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.assignment.leader == self.node_id
+    }
+
+    pub fn leader(&self) -> i32 {
+        self.assignment.leader
+    }
+
+    pub fn leader_epoch(&self) -> i32 {
+        self.assignment.leader_epoch
+    }
+
+    pub fn isr(&self) -> &[i32] {
+        &self.assignment.isr
+    }
+
+    /// Whether some of the replicas are not in sync
+    pub fn is_under_replicated(&self) -> bool {
+        self.is_leader() && self.assignment.isr.len() < self.assignment.replicas.len()
+    }
+
+    /// Makes this broker the leader, tracking every follower from scratch.
+    /// The high watermark of the previous leader is kept until the followers caught up.
+    pub fn make_leader(
+        &mut self,
+        assignment: PartitionAssignment,
+        now: Instant,
+    ) -> anyhow::Result<()> {
+        {
+            // from now on the high watermark only advances as the ISR catches up
+            let mut log = self.log.lock().unwrap();
+            let high_watermark = log.high_watermark();
+            log.set_high_watermark(high_watermark);
+            self.leader_epoch_start_offset = log.log_end_offset();
+            log.assign_epoch(assignment.leader_epoch)?;
+        }
+        self.followers = assignment
+            .replicas
+            .iter()
+            .filter(|&&replica| replica != self.node_id)
+            .map(|&replica| (replica, FollowerState::new(now)))
+            .collect();
+        self.assignment = assignment;
+        Ok(())
+    }
+
+    /// Makes this broker a follower of `assignment.leader`. Records above the high
+    /// watermark may not exist on the new leader: the leader tells where the logs diverge
+    /// from the epoch of the last fetched batch, logs without leader epochs cannot tell
+    /// so they are truncated to the high watermark and fetched again.
+    pub fn make_follower(&mut self, assignment: PartitionAssignment) -> anyhow::Result<()> {
+        let mut log = self.log.lock().unwrap();
+        if log.latest_epoch().is_none() {
+            let high_watermark = log.high_watermark();
+            log.truncate_to(high_watermark)?;
+        }
+        self.followers.clear();
+        self.assignment = assignment;
+        Ok(())
+    }
+
+    /// Fails unless the leader epoch of a request matches the current one.
+    /// -1 skips the check, for clients that do not know the epoch.
+    pub fn check_leader_epoch(&self, leader_epoch: i32) -> Result<(), ReplicaError> {
+        let current = self.assignment.leader_epoch;
+        if leader_epoch == -1 || leader_epoch == current {
+            Ok(())
+        } else if leader_epoch < current {
+            Err(ReplicaError::FencedLeaderEpoch {
+                topic_partition: self.topic_partition.clone(),
+                leader_epoch,
+                current,
+            })
+        } else {
+            Err(ReplicaError::UnknownLeaderEpoch {
+                topic_partition: self.topic_partition.clone(),
+                leader_epoch,
+                current,
+            })
+        }
+    }
+
+    fn ensure_leader(&self) -> Result<(), ReplicaError> {
+        if self.is_leader() {
+            Ok(())
+        } else {
+            Err(ReplicaError::NotLeader(self.topic_partition.clone()))
+        }
+    }
+
+    /// Appends a produced batch as the leader, stamped with the current leader epoch.
+    /// With `acks=all` the ISR has to have at least `min.insync.replicas` replicas.
+    /// Returns the base offset of the batch and the log start offset.
+    pub fn append_as_leader(
+        &mut self,
+        mut batch: RecordBatch,
+        config: &LogConfig,
+        require_min_isr: bool,
+    ) -> anyhow::Result<(i64, i64)> {
+        self.ensure_leader()?;
+        let isr = self.assignment.isr.len();
+        let min_isr = usize::try_from(config.min_insync_replicas).unwrap_or_default();
+        if require_min_isr && isr < min_isr {
+            return Err(ReplicaError::NotEnoughReplicas {
+                topic_partition: self.topic_partition.clone(),
+                isr,
+                min_isr,
+            }
+            .into());
+        }
+
+        batch.partition_leader_epoch = self.assignment.leader_epoch;
+        let (base_offset, log_start_offset) = {
+            let mut log = self.log.lock().unwrap();
+            (log.append(batch, config)?, log.log_start_offset())
+        };
+        self.maybe_increment_high_watermark();
+        Ok((base_offset, log_start_offset))
+    }
+
+    /// Records the fetch of a follower starting at `fetch_offset`, its log end offset.
+    /// A follower that fetched everything the leader had at its previous fetch is caught
+    /// up, one whose log end offset reached the high watermark joins the ISR.
+    /// Returns whether the high watermark advanced.
+    pub fn update_follower_fetch(
+        &mut self,
+        replica_id: i32,
+        fetch_offset: i64,
+        log_start_offset: i64,
+        now: Instant,
+    ) -> anyhow::Result<bool> {
+        self.ensure_leader()?;
+        let Some(follower) = self.followers.get_mut(&replica_id) else {
+            return Err(ReplicaError::UnknownReplica {
+                topic_partition: self.topic_partition.clone(),
+                replica_id,
+            }
+            .into());
+        };
+        let (leader_log_end_offset, high_watermark) = {
+            let log = self.log.lock().unwrap();
+            (log.log_end_offset(), log.high_watermark())
+        };
+
+        if fetch_offset >= leader_log_end_offset {
+            follower.last_caught_up = now;
+        } else if fetch_offset >= follower.last_fetch_leader_log_end_offset {
+            follower.last_caught_up = follower.last_caught_up.max(follower.last_fetch_time);
+        }
+        follower.log_end_offset = fetch_offset;
+        follower.log_start_offset = log_start_offset;
+        follower.last_fetch_leader_log_end_offset = leader_log_end_offset;
+        follower.last_fetch_time = now;
+
+        if !self.assignment.isr.contains(&replica_id)
+            && fetch_offset >= high_watermark
+            && fetch_offset >= self.leader_epoch_start_offset
+        {
+            self.assignment.isr.push(replica_id);
+            self.assignment.partition_epoch += 1;
+            tracing::info!(
+                "Expanding ISR of {} to {:?}",
+                self.topic_partition,
+                self.assignment.isr
+            );
+        }
+        Ok(self.maybe_increment_high_watermark())
+    }
+
+    /// Removes the followers that were not caught up for longer than `lag_time_max`
+    /// from the ISR. Returns whether the high watermark advanced, as the smallest log
+    /// end offset among the ISR may have been one of theirs.
+    pub fn maybe_shrink_isr(
+        &mut self,
+        now: Instant,
+        lag_time_max: Duration,
+    ) -> anyhow::Result<bool> {
+        if !self.is_leader() {
+            return Ok(false);
+        }
+        let out_of_sync: Vec<i32> = self
+            .assignment
+            .isr
+            .iter()
+            .copied()
+            .filter(|replica| {
+                self.followers.get(replica).is_some_and(|follower| {
+                    now.saturating_duration_since(follower.last_caught_up) > lag_time_max
+                })
+            })
+            .collect();
+        if out_of_sync.is_empty() {
+            return Ok(false);
+        }
+        self.assignment
+            .isr
+            .retain(|replica| !out_of_sync.contains(replica));
+        self.assignment.partition_epoch += 1;
+        tracing::info!(
+            "Shrinking ISR of {} to {:?}, {out_of_sync:?} are out of sync",
+            self.topic_partition,
+            self.assignment.isr
+        );
+        Ok(self.maybe_increment_high_watermark())
+    }
+
+    /// Advances the high watermark to the smallest log end offset among the ISR.
+    /// Returns whether it moved.
+    fn maybe_increment_high_watermark(&self) -> bool {
+        let mut log = self.log.lock().unwrap();
+        let mut new_high_watermark = log.log_end_offset();
+        for replica in &self.assignment.isr {
+            if let Some(follower) = self.followers.get(replica) {
+                new_high_watermark = new_high_watermark.min(follower.log_end_offset);
+            }
+        }
+        if new_high_watermark <= log.high_watermark() {
+            return false;
+        }
+        log.set_high_watermark(new_high_watermark);
+        true
+    }
+
+    /// Whether a produce waiting for `required_offset` to be replicated can complete:
+    /// `None` while the high watermark is below it, the error of the partition otherwise
+    pub fn check_enough_replicas_reached(
+        &self,
+        required_offset: i64,
+        min_isr: i32,
+    ) -> Option<Result<(), ReplicaError>> {
+        if let Err(e) = self.ensure_leader() {
+            return Some(Err(e));
+        }
+        let high_watermark = self.log.lock().unwrap().high_watermark();
+        if high_watermark < required_offset {
+            return None;
+        }
+        let isr = self.assignment.isr.len();
+        let min_isr = usize::try_from(min_isr).unwrap_or_default();
+        if isr < min_isr {
+            return Some(Err(ReplicaError::NotEnoughReplicasAfterAppend {
+                topic_partition: self.topic_partition.clone(),
+                isr,
+                min_isr,
+            }));
+        }
+        Some(Ok(()))
+    }
+
+    /// The smallest log start offset among the replicas, records before it are gone
+    /// from every one of them. Followers that did not fetch yet are left out.
+    pub fn low_watermark(&self) -> i64 {
+        let log_start_offset = self.log.lock().unwrap().log_start_offset();
+        self.followers
+            .values()
+            .map(|follower| follower.log_start_offset)
+            .filter(|&offset| offset >= 0)
+            .fold(log_start_offset, i64::min)
+    }
+
+    /// Appends batches fetched from the leader as a follower, then takes on the leader's
+    /// high watermark and log start offset as far as the log reaches.
+    /// Returns the new log end offset.
+    pub fn append_as_follower(
+        &mut self,
+        batches: &[RecordBatch],
+        config: &LogConfig,
+        leader_high_watermark: i64,
+        leader_log_start_offset: i64,
+    ) -> anyhow::Result<i64> {
+        let mut log = self.log.lock().unwrap();
+        for batch in batches {
+            log.append_as_follower(batch, config)?;
+        }
+        let log_end_offset = log.log_end_offset();
+        log.set_high_watermark(leader_high_watermark.min(log_end_offset));
+        if leader_log_start_offset > log.log_start_offset() {
+            let high_watermark = log.high_watermark();
+            log.delete_records_before(leader_log_start_offset.min(high_watermark))?;
+        }
+        Ok(log_end_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::types::Record;
+
+    fn batch() -> RecordBatch {
+        RecordBatch::new(0, 0, vec![Record::new(0, None, Some(Bytes::from("v")))])
+    }
+
+    #[test]
+    fn test_isr_and_high_watermark() {
+        let dir = tempdir().unwrap();
+        let dir = dir.path().join("t-0");
+        std::fs::create_dir(&dir).unwrap();
+        let log = PartitionLog::open(&dir).unwrap();
+        let mut partition = Partition::new(
+            TopicPartition::new("t", 0),
+            1,
+            PartitionAssignment {
+                leader: 1,
+                leader_epoch: 0,
+                partition_epoch: 0,
+                replicas: vec![1, 2],
+                isr: vec![1],
+            },
+            Arc::new(Mutex::new(log)),
+        );
+        let start = Instant::now();
+        let assignment = partition.assignment().clone();
+        partition.make_leader(assignment, start).unwrap();
+        let config = LogConfig {
+            min_insync_replicas: 2,
+            ..LogConfig::default()
+        };
+
+        // the leader alone is the ISR, too few for acks=all
+        assert!(partition.append_as_leader(batch(), &config, true).is_err());
+        assert_eq!(
+            (0, 0),
+            partition.append_as_leader(batch(), &config, false).unwrap()
+        );
+        assert_eq!(1, partition.log().lock().unwrap().high_watermark());
+
+        // the follower reaches the high watermark and joins the ISR
+        assert!(!partition.update_follower_fetch(2, 1, 0, start).unwrap());
+        assert_eq!(&[1, 2], partition.isr());
+        partition.append_as_leader(batch(), &config, true).unwrap();
+        assert!(partition.check_enough_replicas_reached(2, 2).is_none());
+        assert!(partition.update_follower_fetch(2, 2, 0, start).unwrap());
+        assert!(matches!(
+            partition.check_enough_replicas_reached(2, 2),
+            Some(Ok(()))
+        ));
+
+        // the follower stops fetching, the leader goes on alone
+        partition.append_as_leader(batch(), &config, true).unwrap();
+        let lag = Duration::from_secs(10);
+        assert!(!partition.maybe_shrink_isr(start + lag, lag).unwrap());
+        assert!(partition.maybe_shrink_isr(start + 2 * lag, lag).unwrap());
+        assert_eq!(&[1], partition.isr());
+        assert!(partition.is_under_replicated());
+        assert_eq!(3, partition.log().lock().unwrap().high_watermark());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::{ClientQuotaEntityData, TagBuf};
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// AlterClientQuotas Request (Version: 1) => [entries] validate_only TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct AlterClientQuotasRequestBody {
+    pub entries: CompactArray<AlterClientQuotasEntry>,
+    pub validate_only: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// entries => [entity] [ops] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct AlterClientQuotasEntry {
+    pub entity: CompactArray<ClientQuotaEntityData>,
+    pub ops: CompactArray<AlterClientQuotasOp>,
+    tag_buffer: TagBuf,
+}
+
+/// ops => key value remove TAG_BUFFER
+///
+/// Sets the quota `key` to `value`, or removes it if `remove` is set.
+#[derive(Debug, WireLen)]
+pub struct AlterClientQuotasOp {
+    pub key: CompactString,
+    pub value: f64,
+    pub remove: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for AlterClientQuotasOp {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let key = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let value = src.get_f64();
+        let remove = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            key,
+            value,
+            remove,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AlterClientQuotasEntry {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let entity = unwrap_decode!(CompactArray::decode(src, None));
+        let ops = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            entity,
+            ops,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AlterClientQuotasRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let entries = unwrap_decode!(CompactArray::decode(src, None));
+        let validate_only = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = AlterClientQuotasRequestBody {
+            entries,
+            validate_only,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
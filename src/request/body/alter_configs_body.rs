@@ -0,0 +1,103 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// AlterConfigs Request (Version: 2) => [resources] validate_only TAG_BUFFER
+///
+/// Replaces every dynamic config of the resources, configs left out are removed.
+#[derive(Debug, WireLen)]
+pub struct AlterConfigsRequestBody {
+    pub resources: CompactArray<AlterConfigsResource>,
+    pub validate_only: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// resources => resource_type resource_name [configs] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct AlterConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub configs: CompactArray<AlterableConfig>,
+    tag_buffer: TagBuf,
+}
+
+/// configs => name value TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct AlterableConfig {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for AlterableConfig {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let value = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            value,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AlterConfigsResource {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        let configs = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type,
+            resource_name,
+            configs,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AlterConfigsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let resources = unwrap_decode!(CompactArray::decode(src, None));
+        let validate_only = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = AlterConfigsRequestBody {
+            resources,
+            validate_only,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
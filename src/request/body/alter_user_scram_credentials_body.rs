@@ -0,0 +1,115 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// AlterUserScramCredentials Request (Version: 0) => [deletions] [upsertions] TAG_BUFFER
+///
+/// Mechanisms are given by their type: 1 for SCRAM-SHA-256, 2 for SCRAM-SHA-512.
+#[derive(Debug, WireLen)]
+pub struct AlterUserScramCredentialsRequestBody {
+    pub deletions: CompactArray<ScramCredentialDeletion>,
+    pub upsertions: CompactArray<ScramCredentialUpsertion>,
+    tag_buffer: TagBuf,
+}
+
+/// deletions => name mechanism TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct ScramCredentialDeletion {
+    pub name: CompactString,
+    pub mechanism: i8,
+    tag_buffer: TagBuf,
+}
+
+/// upsertions => name mechanism iterations salt salted_password TAG_BUFFER
+///
+/// The password comes salted by the client, the broker never sees it.
+#[derive(Debug, WireLen)]
+pub struct ScramCredentialUpsertion {
+    pub name: CompactString,
+    pub mechanism: i8,
+    pub iterations: i32,
+    pub salt: CompactBytes,
+    pub salted_password: CompactBytes,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for ScramCredentialDeletion {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let mechanism = src.get_i8();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            mechanism,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ScramCredentialUpsertion {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 5 {
+            src.reserve(5);
+            return Ok(None);
+        }
+        let mechanism = src.get_i8();
+        let iterations = src.get_i32();
+        let salt = unwrap_decode!(CompactBytes::decode(src, None));
+        let salted_password = unwrap_decode!(CompactBytes::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            mechanism,
+            iterations,
+            salt,
+            salted_password,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for AlterUserScramCredentialsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let deletions = unwrap_decode!(CompactArray::decode(src, None));
+        let upsertions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = AlterUserScramCredentialsRequestBody {
+            deletions,
+            upsertions,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
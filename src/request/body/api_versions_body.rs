@@ -1,6 +1,6 @@
 use crate::codec::{Decoder, WireLen};
 use crate::primitives::*;
-use crate::types::Tag;
+use crate::types::TagBuf;
 use crate::unwrap_decode;
 use anyhow;
 use bytes::Buf;
@@ -11,7 +11,7 @@ use tracing::trace;
 pub struct ApiVersionsRequestBody {
     pub(crate) client_software_name: CompactString,
     pub(crate) client_software_version: CompactString,
-    tag_buffer: CompactArray<Tag>,
+    tag_buffer: TagBuf,
 }
 
 impl Decoder for ApiVersionsRequestBody {
@@ -34,7 +34,7 @@ impl Decoder for ApiVersionsRequestBody {
         let client_software_version = unwrap_decode!(CompactString::decode(src, None));
         trace!(client_software_version = ?client_software_version, wire_len = client_software_version.wire_len());
 
-        let tag_buffer = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
 
         let body = ApiVersionsRequestBody {
             client_software_name,
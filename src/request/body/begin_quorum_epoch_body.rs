@@ -0,0 +1,143 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::{LeaderEndpoint, TagBuf};
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// BeginQuorumEpoch Request (Version: 1) => cluster_id voter_id [topics] [leader_endpoints] TAG_BUFFER
+///
+/// Sent by a newly elected leader of the KRaft quorum to every other voter, so they
+/// start following it without waiting for their election timeout. Also encoded, by the
+/// leaders of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BeginQuorumEpochRequestBody {
+    pub cluster_id: CompactNullableString,
+    /// The voter the request is sent to
+    pub voter_id: i32,
+    pub topics: CompactArray<BeginQuorumEpochTopic>,
+    pub leader_endpoints: CompactArray<LeaderEndpoint>,
+    tag_buffer: TagBuf,
+}
+
+impl BeginQuorumEpochRequestBody {
+    /// A request for a single partition
+    pub fn new(
+        cluster_id: &str,
+        voter_id: i32,
+        topic_name: &str,
+        partition: BeginQuorumEpochPartition,
+        leader_endpoints: Vec<LeaderEndpoint>,
+    ) -> Self {
+        Self {
+            cluster_id: Some(cluster_id).into(),
+            voter_id,
+            topics: vec![BeginQuorumEpochTopic {
+                topic_name: CompactString(topic_name.to_string()),
+                partitions: vec![partition].into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            leader_endpoints: leader_endpoints.into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct BeginQuorumEpochTopic {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<BeginQuorumEpochPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index voter_directory_id leader_id leader_epoch TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct BeginQuorumEpochPartition {
+    pub partition_index: i32,
+    /// The zero uuid, log directories of voters are not tracked
+    pub voter_directory_id: Uuid,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl BeginQuorumEpochPartition {
+    pub fn new(partition_index: i32, leader_id: i32, leader_epoch: i32) -> Self {
+        Self {
+            partition_index,
+            voter_directory_id: Uuid::ZERO,
+            leader_id,
+            leader_epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for BeginQuorumEpochPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 28 {
+            src.reserve(28);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let voter_directory_id = unwrap_decode!(Uuid::decode(src, None));
+        let leader_id = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            voter_directory_id,
+            leader_id,
+            leader_epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BeginQuorumEpochTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BeginQuorumEpochRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let cluster_id = unwrap_decode!(CompactNullableString::decode(src, None));
+        let voter_id = unwrap_decode!(i32::decode(src, None));
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let leader_endpoints = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            cluster_id,
+            voter_id,
+            topics,
+            leader_endpoints,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// BrokerHeartbeat Request (Version: 0-1) => broker_id broker_epoch current_metadata_offset want_fence want_shut_down TAG_BUFFER
+///
+/// Sent by registered brokers to the active controller every
+/// `broker.heartbeat.interval.ms`. Version 1 adds the tagged field `offline_log_dirs`,
+/// so both versions decode alike. Also encoded, by the brokers of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerHeartbeatRequestBody {
+    pub broker_id: i32,
+    /// The epoch the controller handed out when the broker registered
+    pub broker_epoch: i64,
+    /// The offset of the metadata log the broker replayed up to
+    pub current_metadata_offset: i64,
+    /// Whether the broker wants to be fenced, or to stay fenced
+    pub want_fence: Bool,
+    /// Whether the broker is shutting down
+    pub want_shut_down: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl BrokerHeartbeatRequestBody {
+    /// Tag of the directory ids of the broker's failed log dirs, since version 1
+    const OFFLINE_LOG_DIRS_TAG: u32 = 0;
+
+    pub fn new(
+        broker_id: i32,
+        broker_epoch: i64,
+        current_metadata_offset: i64,
+        want_fence: bool,
+        want_shut_down: bool,
+    ) -> Self {
+        Self {
+            broker_id,
+            broker_epoch,
+            current_metadata_offset,
+            want_fence: want_fence.into(),
+            want_shut_down: want_shut_down.into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The directory ids of the log dirs the broker lost
+    pub fn offline_log_dirs(&self) -> anyhow::Result<Vec<Uuid>> {
+        let dirs: Option<CompactArray<Uuid>> = self.tag_buffer.get(Self::OFFLINE_LOG_DIRS_TAG)?;
+        Ok(dirs
+            .map(|dirs| dirs.iter().copied().collect())
+            .unwrap_or_default())
+    }
+}
+
+impl Decoder for BrokerHeartbeatRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 20 {
+            src.reserve(20);
+            return Ok(None);
+        }
+        let broker_id = src.get_i32();
+        let broker_epoch = src.get_i64();
+        let current_metadata_offset = src.get_i64();
+        let want_fence = unwrap_decode!(Bool::decode(src, None));
+        let want_shut_down = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            broker_id,
+            broker_epoch,
+            current_metadata_offset,
+            want_fence,
+            want_shut_down,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,198 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// BrokerRegistration Request (Version: 3) => broker_id cluster_id incarnation_id [listeners] [features] rack is_migrating_zk_broker [log_dirs] previous_broker_epoch TAG_BUFFER
+///
+/// Sent by a broker to the active controller when it starts, and again whenever the
+/// controller no longer knows it. Also encoded, by the brokers of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerRegistrationRequestBody {
+    pub broker_id: i32,
+    pub cluster_id: CompactString,
+    /// A random id of this run of the broker, telling a restarted broker apart from
+    /// another one registering with the same id
+    pub incarnation_id: Uuid,
+    pub listeners: CompactArray<RegistrationListener>,
+    pub features: CompactArray<RegistrationFeature>,
+    pub rack: CompactNullableString,
+    pub is_migrating_zk_broker: Bool,
+    /// The directory ids of the broker's log dirs
+    pub log_dirs: CompactArray<Uuid>,
+    /// The epoch of the broker's previous registration if it shut down cleanly, -1 if not
+    pub previous_broker_epoch: i64,
+    tag_buffer: TagBuf,
+}
+
+impl BrokerRegistrationRequestBody {
+    pub fn new(broker_id: i32, cluster_id: &str, incarnation_id: Uuid) -> Self {
+        Self {
+            broker_id,
+            cluster_id: CompactString(cluster_id.to_string()),
+            incarnation_id,
+            listeners: CompactArray::new(),
+            features: CompactArray::new(),
+            rack: CompactNullableString::null(),
+            is_migrating_zk_broker: Bool::False,
+            log_dirs: CompactArray::new(),
+            previous_broker_epoch: -1,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn with_listeners(mut self, listeners: Vec<RegistrationListener>) -> Self {
+        self.listeners = listeners.into();
+        self
+    }
+
+    pub fn with_features(mut self, features: Vec<RegistrationFeature>) -> Self {
+        self.features = features.into();
+        self
+    }
+
+    pub fn with_rack(mut self, rack: Option<String>) -> Self {
+        self.rack = CompactNullableString(rack);
+        self
+    }
+
+    pub fn with_log_dirs(mut self, log_dirs: Vec<Uuid>) -> Self {
+        self.log_dirs = log_dirs.into();
+        self
+    }
+}
+
+/// listeners => name host port security_protocol TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct RegistrationListener {
+    pub name: CompactString,
+    pub host: CompactString,
+    pub port: u16,
+    pub security_protocol: i16,
+    tag_buffer: TagBuf,
+}
+
+impl RegistrationListener {
+    pub fn new(name: &str, host: &str, port: u16, security_protocol: i16) -> Self {
+        Self {
+            name: CompactString(name.to_string()),
+            host: CompactString(host.to_string()),
+            port,
+            security_protocol,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// features => name min_supported_version max_supported_version TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct RegistrationFeature {
+    pub name: CompactString,
+    pub min_supported_version: i16,
+    pub max_supported_version: i16,
+    tag_buffer: TagBuf,
+}
+
+impl RegistrationFeature {
+    pub fn new(name: &str, min_supported_version: i16, max_supported_version: i16) -> Self {
+        Self {
+            name: CompactString(name.to_string()),
+            min_supported_version,
+            max_supported_version,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for RegistrationListener {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let host = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let port = src.get_u16();
+        let security_protocol = src.get_i16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            host,
+            port,
+            security_protocol,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for RegistrationFeature {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let min_supported_version = src.get_i16();
+        let max_supported_version = src.get_i16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            min_supported_version,
+            max_supported_version,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BrokerRegistrationRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let broker_id = src.get_i32();
+        let cluster_id = unwrap_decode!(CompactString::decode(src, None));
+        let incarnation_id = unwrap_decode!(Uuid::decode(src, None));
+        let listeners = unwrap_decode!(CompactArray::decode(src, None));
+        let features = unwrap_decode!(CompactArray::decode(src, None));
+        let rack = unwrap_decode!(CompactNullableString::decode(src, None));
+        let is_migrating_zk_broker = unwrap_decode!(Bool::decode(src, None));
+        let log_dirs = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let previous_broker_epoch = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            broker_id,
+            cluster_id,
+            incarnation_id,
+            listeners,
+            features,
+            rack,
+            is_migrating_zk_broker,
+            log_dirs,
+            previous_broker_epoch,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// CreateAcls Request (Version: 2-3) => [creations] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct CreateAclsRequestBody {
+    pub creations: CompactArray<AclCreation>,
+    tag_buffer: TagBuf,
+}
+
+/// creations => resource_type resource_name resource_pattern_type principal host operation permission_type TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct AclCreation {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub resource_pattern_type: i8,
+    pub principal: CompactString,
+    pub host: CompactString,
+    pub operation: i8,
+    pub permission_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for AclCreation {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_pattern_type = src.get_i8();
+        let principal = unwrap_decode!(CompactString::decode(src, None));
+        let host = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let operation = src.get_i8();
+        let permission_type = src.get_i8();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type,
+            resource_name,
+            resource_pattern_type,
+            principal,
+            host,
+            operation,
+            permission_type,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for CreateAclsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let creations = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = CreateAclsRequestBody {
+            creations,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,146 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// CreateTopics Request (Version: 7) => [topics] timeout_ms validate_only TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct CreateTopicsRequestBody {
+    pub topics: CompactArray<CreatableTopic>,
+    pub timeout_ms: i32,
+    /// Only checks whether the topics could be created
+    pub validate_only: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// topics => name num_partitions replication_factor [assignments] [configs] TAG_BUFFER
+///
+/// `num_partitions` and `replication_factor` are -1 to take the broker defaults, and
+/// must be when `assignments` place the replicas themselves.
+#[derive(Debug, WireLen)]
+pub struct CreatableTopic {
+    pub name: CompactString,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub assignments: CompactArray<CreatableReplicaAssignment>,
+    pub configs: CompactArray<CreatableTopicConfig>,
+    tag_buffer: TagBuf,
+}
+
+/// assignments => partition_index [broker_ids] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct CreatableReplicaAssignment {
+    pub partition_index: i32,
+    pub broker_ids: CompactArray<i32>,
+    tag_buffer: TagBuf,
+}
+
+/// configs => name value TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct CreatableTopicConfig {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for CreatableReplicaAssignment {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let broker_ids = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            broker_ids,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for CreatableTopicConfig {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let value = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            value,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for CreatableTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 6 {
+            src.reserve(6);
+            return Ok(None);
+        }
+        let num_partitions = src.get_i32();
+        let replication_factor = src.get_i16();
+        let assignments = unwrap_decode!(CompactArray::decode(src, None));
+        let configs = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            num_partitions,
+            replication_factor,
+            assignments,
+            configs,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for CreateTopicsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let timeout_ms = src.get_i32();
+        let validate_only = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = CreateTopicsRequestBody {
+            topics,
+            timeout_ms,
+            validate_only,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,47 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+use super::AclFilter;
+
+/// DeleteAcls Request (Version: 2-3) => [filters] TAG_BUFFER
+///
+/// Every ACL matching any of the filters is deleted.
+#[derive(Debug, WireLen)]
+pub struct DeleteAclsRequestBody {
+    pub filters: CompactArray<AclFilter>,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for DeleteAclsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let filters = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DeleteAclsRequestBody {
+            filters,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
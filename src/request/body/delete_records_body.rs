@@ -0,0 +1,109 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DeleteRecords Request (Version: 2) => [topics] timeout_ms TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct DeleteRecordsRequestBody {
+    pub topics: CompactArray<DeleteRecordsTopic>,
+    pub timeout_ms: i32,
+    tag_buffer: TagBuf,
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct DeleteRecordsTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<DeleteRecordsPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index offset TAG_BUFFER
+///
+/// `offset` is the offset records are deleted before, -1 stands for the high watermark.
+#[derive(Debug, WireLen)]
+pub struct DeleteRecordsPartition {
+    pub partition_index: i32,
+    pub offset: i64,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteRecordsPartition {
+    /// Deletes every record up to the high watermark
+    pub const HIGH_WATERMARK: i64 = -1;
+}
+
+impl Decoder for DeleteRecordsPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let offset = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            offset,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DeleteRecordsTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DeleteRecordsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let timeout_ms = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DeleteRecordsRequestBody {
+            topics,
+            timeout_ms,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DescribeAcls Request (Version: 2-3) => resource_type_filter resource_name_filter pattern_type_filter principal_filter host_filter operation permission_type TAG_BUFFER
+///
+/// The request is a single filter, laid out like the filters of DeleteAcls.
+#[derive(Debug, WireLen)]
+pub struct DescribeAclsRequestBody {
+    pub filter: AclFilter,
+}
+
+/// resource_type_filter resource_name_filter pattern_type_filter principal_filter host_filter operation permission_type TAG_BUFFER
+///
+/// Null names, principals and hosts match any. The enums are given by their codes,
+/// 1 standing for any.
+#[derive(Debug, WireLen)]
+pub struct AclFilter {
+    pub resource_type_filter: i8,
+    pub resource_name_filter: CompactNullableString,
+    pub pattern_type_filter: i8,
+    pub principal_filter: CompactNullableString,
+    pub host_filter: CompactNullableString,
+    pub operation: i8,
+    pub permission_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for AclFilter {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type_filter = src.get_i8();
+        let resource_name_filter = unwrap_decode!(CompactNullableString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let pattern_type_filter = src.get_i8();
+        let principal_filter = unwrap_decode!(CompactNullableString::decode(src, None));
+        let host_filter = unwrap_decode!(CompactNullableString::decode(src, None));
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let operation = src.get_i8();
+        let permission_type = src.get_i8();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type_filter,
+            resource_name_filter,
+            pattern_type_filter,
+            principal_filter,
+            host_filter,
+            operation,
+            permission_type,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeAclsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let filter = unwrap_decode!(AclFilter::decode(src, None));
+        let body = DescribeAclsRequestBody { filter };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,89 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DescribeClientQuotas Request (Version: 1) => [components] strict TAG_BUFFER
+///
+/// With `strict` set only entities made of exactly the filtered entity types match,
+/// otherwise entities with further components match as well.
+#[derive(Debug, WireLen)]
+pub struct DescribeClientQuotasRequestBody {
+    pub components: CompactArray<DescribeClientQuotasComponent>,
+    pub strict: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// components => entity_type match_type match TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct DescribeClientQuotasComponent {
+    pub entity_type: CompactString,
+    pub match_type: i8,
+    pub match_name: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClientQuotasComponent {
+    /// Matches the entity with the name in `match_name`
+    pub const MATCH_EXACT: i8 = 0;
+    /// Matches the default entity of the type
+    pub const MATCH_DEFAULT: i8 = 1;
+    /// Matches any entity of the type with a name, that is any but the default one
+    pub const MATCH_SPECIFIED: i8 = 2;
+}
+
+impl Decoder for DescribeClientQuotasComponent {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let entity_type = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let match_type = src.get_i8();
+        let match_name = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            entity_type,
+            match_type,
+            match_name,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeClientQuotasRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let components = unwrap_decode!(CompactArray::decode(src, None));
+        let strict = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DescribeClientQuotasRequestBody {
+            components,
+            strict,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::Bool, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+
+/// DescribeCluster Request (Version: 0-1) => include_cluster_authorized_operations endpoint_type TAG_BUFFER
+///
+/// Version 1 adds `endpoint_type`, older requests always ask for the brokers.
+#[derive(Debug)]
+pub struct DescribeClusterRequestBody {
+    version: i16,
+    pub include_cluster_authorized_operations: Bool,
+    pub endpoint_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClusterRequestBody {
+    /// Endpoint type asking for the endpoints of the brokers
+    pub const BROKERS: i8 = 1;
+    /// Endpoint type asking for the endpoints of the controllers
+    pub const CONTROLLERS: i8 = 2;
+
+    /// Decodes the body of a request of `version`
+    pub fn decode_version(
+        src: &mut bytes::BytesMut,
+        version: i16,
+        size: Option<usize>,
+    ) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let include_cluster_authorized_operations = unwrap_decode!(Bool::decode(src, None));
+        let endpoint_type = if version >= 1 {
+            if src.remaining() < 1 {
+                src.reserve(1);
+                return Ok(None);
+            }
+            src.get_i8()
+        } else {
+            Self::BROKERS
+        };
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DescribeClusterRequestBody {
+            version,
+            include_cluster_authorized_operations,
+            endpoint_type,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
+
+impl WireLen for DescribeClusterRequestBody {
+    fn wire_len(&self) -> usize {
+        let endpoint_type = if self.version >= 1 {
+            size_of::<i8>()
+        } else {
+            0
+        };
+        self.include_cluster_authorized_operations.wire_len()
+            + endpoint_type
+            + self.tag_buffer.wire_len()
+    }
+}
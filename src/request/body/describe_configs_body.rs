@@ -0,0 +1,90 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DescribeConfigs Request (Version: 4) => [resources] include_synonyms include_documentation TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct DescribeConfigsRequestBody {
+    pub resources: CompactArray<DescribeConfigsResource>,
+    pub include_synonyms: Bool,
+    pub include_documentation: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// resources => resource_type resource_name [configuration_keys] TAG_BUFFER
+///
+/// A null or empty `configuration_keys` asks for every config of the resource.
+#[derive(Debug, WireLen)]
+pub struct DescribeConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub configuration_keys: CompactArray<CompactString>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeConfigsResource {
+    /// Resource type of topics, named by the topic
+    pub const TOPIC: i8 = 2;
+    /// Resource type of brokers, named by their node id, or empty for the defaults of
+    /// every broker
+    pub const BROKER: i8 = 4;
+}
+
+impl Decoder for DescribeConfigsResource {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        let configuration_keys = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type,
+            resource_name,
+            configuration_keys,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeConfigsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let resources = unwrap_decode!(CompactArray::decode(src, None));
+        let include_synonyms = unwrap_decode!(Bool::decode(src, None));
+        let include_documentation = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DescribeConfigsRequestBody {
+            resources,
+            include_synonyms,
+            include_documentation,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,78 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DescribeLogDirs Request (Version: 2-4) => [topics] TAG_BUFFER
+///
+/// Null `topics` ask for every partition of the broker.
+#[derive(Debug)]
+pub struct DescribeLogDirsRequestBody {
+    pub topics: Option<CompactArray<DescribableLogDirTopic>>,
+    tag_buffer: TagBuf,
+}
+
+/// topics => topic [partitions] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct DescribableLogDirTopic {
+    pub topic: CompactString,
+    pub partitions: CompactArray<i32>,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for DescribableLogDirTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeLogDirsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        // CompactArray decodes null as empty, which asks for nothing here
+        let topics = if src.first() == Some(&0) {
+            src.advance(1);
+            None
+        } else {
+            Some(unwrap_decode!(CompactArray::decode(src, None)))
+        };
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DescribeLogDirsRequestBody { topics, tag_buffer };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
+
+impl WireLen for DescribeLogDirsRequestBody {
+    fn wire_len(&self) -> usize {
+        self.topics.as_ref().map_or(1, WireLen::wire_len) + self.tag_buffer.wire_len()
+    }
+}
@@ -0,0 +1,99 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// DescribeQuorum Request (Version: 1) => [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumRequestBody {
+    pub topics: CompactArray<DescribeQuorumTopic>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeQuorumRequestBody {
+    /// Describes partition 0 of `topic_name`
+    pub fn new(topic_name: &str) -> Self {
+        Self {
+            topics: vec![DescribeQuorumTopic {
+                topic_name: CompactString(topic_name.to_string()),
+                partitions: vec![DescribeQuorumPartition {
+                    partition_index: 0,
+                    tag_buffer: TagBuf::new(),
+                }]
+                .into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumTopic {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<DescribeQuorumPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumPartition {
+    pub partition_index: i32,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for DescribeQuorumPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeQuorumTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for DescribeQuorumRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self { topics, tag_buffer };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
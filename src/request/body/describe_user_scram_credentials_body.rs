@@ -0,0 +1,60 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// DescribeUserScramCredentials Request (Version: 0) => [users] TAG_BUFFER
+///
+/// A null or empty `users` describes every user with credentials.
+#[derive(Debug, WireLen)]
+pub struct DescribeUserScramCredentialsRequestBody {
+    pub users: CompactArray<UserName>,
+    tag_buffer: TagBuf,
+}
+
+/// users => name TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct UserName {
+    pub name: CompactString,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for UserName {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self { name, tag_buffer }))
+    }
+}
+
+impl Decoder for DescribeUserScramCredentialsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let users = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = DescribeUserScramCredentialsRequestBody { users, tag_buffer };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
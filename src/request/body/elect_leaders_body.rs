@@ -0,0 +1,146 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// ElectLeaders Request (Version: 2) => election_type [topic_partitions] timeout_ms TAG_BUFFER
+///
+/// Null `topic_partitions` ask for an election in every partition.
+#[derive(Debug)]
+pub struct ElectLeadersRequestBody {
+    /// 0 for a preferred election, 1 for an unclean one
+    pub election_type: i8,
+    pub topic_partitions: Option<CompactArray<ElectLeadersTopic>>,
+    pub timeout_ms: i32,
+    tag_buffer: TagBuf,
+}
+
+/// topic_partitions => topic [partitions] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct ElectLeadersTopic {
+    pub topic: CompactString,
+    pub partitions: CompactArray<i32>,
+    tag_buffer: TagBuf,
+}
+
+/// The elections an ElectLeaders request may ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElectionType {
+    /// Moves the leadership back to the first replica, if it is in sync
+    Preferred,
+    /// Elects a leader out of the replicas that are not in sync if no in-sync replica
+    /// is left, which loses the records only the previous leader had
+    Unclean,
+}
+
+impl ElectLeadersRequestBody {
+    pub fn new(
+        election_type: ElectionType,
+        topic_partitions: Option<CompactArray<ElectLeadersTopic>>,
+        timeout_ms: i32,
+    ) -> Self {
+        Self {
+            election_type: match election_type {
+                ElectionType::Preferred => 0,
+                ElectionType::Unclean => 1,
+            },
+            topic_partitions,
+            timeout_ms,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The election asked for, `None` for an unknown election type
+    pub fn election(&self) -> Option<ElectionType> {
+        match self.election_type {
+            0 => Some(ElectionType::Preferred),
+            1 => Some(ElectionType::Unclean),
+            _ => None,
+        }
+    }
+}
+
+impl ElectLeadersTopic {
+    pub fn new(topic: impl Into<CompactString>, partitions: CompactArray<i32>) -> Self {
+        Self {
+            topic: topic.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for ElectLeadersTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ElectLeadersRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let election_type = src.get_i8();
+        // CompactArray decodes null as empty, which asks for nothing here
+        let topic_partitions = if src.first() == Some(&0) {
+            src.advance(1);
+            None
+        } else {
+            Some(unwrap_decode!(CompactArray::decode(src, None)))
+        };
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let timeout_ms = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = ElectLeadersRequestBody {
+            election_type,
+            topic_partitions,
+            timeout_ms,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
+
+impl WireLen for ElectLeadersRequestBody {
+    fn wire_len(&self) -> usize {
+        self.election_type.wire_len()
+            + self.topic_partitions.as_ref().map_or(1, WireLen::wire_len)
+            + self.timeout_ms.wire_len()
+            + self.tag_buffer.wire_len()
+    }
+}
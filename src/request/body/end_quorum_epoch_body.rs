@@ -0,0 +1,168 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::{LeaderEndpoint, TagBuf};
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// EndQuorumEpoch Request (Version: 1) => cluster_id [topics] [leader_endpoints] TAG_BUFFER
+///
+/// Sent by a leader of the KRaft quorum resigning, on shutdown, so the voters elect a new
+/// leader without waiting for their fetch timeout. Also encoded, by the leaders of this
+/// node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct EndQuorumEpochRequestBody {
+    pub cluster_id: CompactNullableString,
+    pub topics: CompactArray<EndQuorumEpochTopic>,
+    pub leader_endpoints: CompactArray<LeaderEndpoint>,
+    tag_buffer: TagBuf,
+}
+
+impl EndQuorumEpochRequestBody {
+    /// A request for a single partition
+    pub fn new(cluster_id: &str, topic_name: &str, partition: EndQuorumEpochPartition) -> Self {
+        Self {
+            cluster_id: Some(cluster_id).into(),
+            topics: vec![EndQuorumEpochTopic {
+                topic_name: CompactString(topic_name.to_string()),
+                partitions: vec![partition].into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            leader_endpoints: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct EndQuorumEpochTopic {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<EndQuorumEpochPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index leader_id leader_epoch [preferred_candidates] TAG_BUFFER
+///
+/// The preferred candidates are the voters sorted by how far their logs got, the ones
+/// first in the list are meant to start their election first.
+#[derive(Debug, WireLen, Encoder)]
+pub struct EndQuorumEpochPartition {
+    pub partition_index: i32,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub preferred_candidates: CompactArray<PreferredCandidate>,
+    tag_buffer: TagBuf,
+}
+
+impl EndQuorumEpochPartition {
+    pub fn new(
+        partition_index: i32,
+        leader_id: i32,
+        leader_epoch: i32,
+        preferred_candidates: &[i32],
+    ) -> Self {
+        Self {
+            partition_index,
+            leader_id,
+            leader_epoch,
+            preferred_candidates: preferred_candidates
+                .iter()
+                .map(|&candidate_id| PreferredCandidate {
+                    candidate_id,
+                    candidate_directory_id: Uuid::ZERO,
+                    tag_buffer: TagBuf::new(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// preferred_candidates => candidate_id candidate_directory_id TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct PreferredCandidate {
+    pub candidate_id: i32,
+    pub candidate_directory_id: Uuid,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for PreferredCandidate {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let candidate_id = unwrap_decode!(i32::decode(src, None));
+        let candidate_directory_id = unwrap_decode!(Uuid::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            candidate_id,
+            candidate_directory_id,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for EndQuorumEpochPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let leader_id = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let preferred_candidates = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            leader_id,
+            leader_epoch,
+            preferred_candidates,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for EndQuorumEpochTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for EndQuorumEpochRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let cluster_id = unwrap_decode!(CompactNullableString::decode(src, None));
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let leader_endpoints = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            cluster_id,
+            topics,
+            leader_endpoints,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
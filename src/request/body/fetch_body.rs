@@ -0,0 +1,259 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// Fetch Request (Version: 12) => replica_id max_wait_ms min_bytes max_bytes isolation_level session_id session_epoch [topics] [forgotten_topics_data] rack_id TAG_BUFFER
+///
+/// Consumers send a `replica_id` of -1, followers their node id. Fetch sessions are not
+/// supported, requests have to use session id 0 and list every partition they fetch.
+/// Also encoded, by followers fetching from their leader. Replicas of the KRaft quorum
+/// send their cluster id as tagged field 0.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchRequestBody {
+    pub replica_id: i32,
+    pub max_wait_ms: i32,
+    pub min_bytes: i32,
+    pub max_bytes: i32,
+    pub isolation_level: i8,
+    pub session_id: i32,
+    pub session_epoch: i32,
+    pub topics: CompactArray<FetchTopic>,
+    pub forgotten_topics_data: CompactArray<ForgottenTopic>,
+    pub rack_id: CompactString,
+    tag_buffer: TagBuf,
+}
+
+impl FetchRequestBody {
+    /// The `replica_id` of consumers
+    pub const CONSUMER_REPLICA_ID: i32 = -1;
+    /// The `session_epoch` of requests not using a fetch session
+    pub const FINAL_EPOCH: i32 = -1;
+    /// Tag of the `cluster_id` field
+    const CLUSTER_ID_TAG: u32 = 0;
+
+    /// A fetch of follower `replica_id` without a session
+    pub fn new(
+        replica_id: i32,
+        max_wait_ms: i32,
+        min_bytes: i32,
+        max_bytes: i32,
+        topics: CompactArray<FetchTopic>,
+    ) -> Self {
+        Self {
+            replica_id,
+            max_wait_ms,
+            min_bytes,
+            max_bytes,
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: Self::FINAL_EPOCH,
+            topics,
+            forgotten_topics_data: CompactArray::new(),
+            rack_id: CompactString::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn is_from_follower(&self) -> bool {
+        self.replica_id >= 0
+    }
+
+    /// Tells the leader which cluster the fetching replica belongs to
+    pub fn with_cluster_id(mut self, cluster_id: &str) -> anyhow::Result<Self> {
+        self.tag_buffer.push(
+            Self::CLUSTER_ID_TAG,
+            &CompactNullableString::from(Some(cluster_id)),
+        )?;
+        Ok(self)
+    }
+
+    /// The cluster the fetching replica belongs to, if it said so
+    pub fn cluster_id(&self) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .tag_buffer
+            .get::<CompactNullableString>(Self::CLUSTER_ID_TAG)?
+            .and_then(|id| id.0))
+    }
+}
+
+/// topics => topic [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchTopic {
+    pub topic: CompactString,
+    pub partitions: CompactArray<FetchPartition>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchTopic {
+    pub fn new(topic: impl Into<String>, partitions: CompactArray<FetchPartition>) -> Self {
+        Self {
+            topic: CompactString(topic.into()),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition current_leader_epoch fetch_offset last_fetched_epoch log_start_offset partition_max_bytes TAG_BUFFER
+///
+/// A `current_leader_epoch` of -1 skips the check of the leader epoch.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchPartition {
+    pub partition: i32,
+    pub current_leader_epoch: i32,
+    pub fetch_offset: i64,
+    pub last_fetched_epoch: i32,
+    pub log_start_offset: i64,
+    pub partition_max_bytes: i32,
+    tag_buffer: TagBuf,
+}
+
+impl FetchPartition {
+    pub fn new(
+        partition: i32,
+        current_leader_epoch: i32,
+        fetch_offset: i64,
+        log_start_offset: i64,
+        partition_max_bytes: i32,
+    ) -> Self {
+        Self {
+            partition,
+            current_leader_epoch,
+            fetch_offset,
+            last_fetched_epoch: -1,
+            log_start_offset,
+            partition_max_bytes,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// A fetch of a replica of the KRaft quorum, which tells the leader the epoch of the
+    /// last batch it has so the leader can find where their logs diverge
+    pub fn with_last_fetched_epoch(mut self, last_fetched_epoch: i32) -> Self {
+        self.last_fetched_epoch = last_fetched_epoch;
+        self
+    }
+}
+
+/// forgotten_topics_data => topic [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ForgottenTopic {
+    pub topic: CompactString,
+    pub partitions: CompactArray<i32>,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for FetchPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 32 {
+            src.reserve(32);
+            return Ok(None);
+        }
+        let partition = src.get_i32();
+        let current_leader_epoch = src.get_i32();
+        let fetch_offset = src.get_i64();
+        let last_fetched_epoch = src.get_i32();
+        let log_start_offset = src.get_i64();
+        let partition_max_bytes = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition,
+            current_leader_epoch,
+            fetch_offset,
+            last_fetched_epoch,
+            log_start_offset,
+            partition_max_bytes,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ForgottenTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 25 {
+            src.reserve(25);
+            return Ok(None);
+        }
+        let replica_id = src.get_i32();
+        let max_wait_ms = src.get_i32();
+        let min_bytes = src.get_i32();
+        let max_bytes = src.get_i32();
+        let isolation_level = src.get_i8();
+        let session_id = src.get_i32();
+        let session_epoch = src.get_i32();
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let forgotten_topics_data = unwrap_decode!(CompactArray::decode(src, None));
+        let rack_id = unwrap_decode!(CompactString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = FetchRequestBody {
+            replica_id,
+            max_wait_ms,
+            min_bytes,
+            max_bytes,
+            isolation_level,
+            session_id,
+            session_epoch,
+            topics,
+            forgotten_topics_data,
+            rack_id,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,168 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::{SnapshotId, TagBuf};
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// FetchSnapshot Request (Version: 0) => replica_id max_bytes [topics] TAG_BUFFER
+///
+/// Sent by replicas of the KRaft quorum whose fetch offset the leader no longer has in
+/// its log, to read a snapshot of the metadata log in chunks instead. The cluster id is
+/// tagged field 0. Also encoded, by the followers of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotRequestBody {
+    pub replica_id: i32,
+    pub max_bytes: i32,
+    pub topics: CompactArray<FetchSnapshotTopic>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchSnapshotRequestBody {
+    /// Tag of the `cluster_id` field
+    const CLUSTER_ID_TAG: u32 = 0;
+
+    /// A request for a single partition
+    pub fn new(
+        cluster_id: &str,
+        replica_id: i32,
+        max_bytes: i32,
+        topic_name: &str,
+        partition: FetchSnapshotPartition,
+    ) -> anyhow::Result<Self> {
+        let mut tag_buffer = TagBuf::new();
+        tag_buffer.push(
+            Self::CLUSTER_ID_TAG,
+            &CompactNullableString::from(Some(cluster_id)),
+        )?;
+        Ok(Self {
+            replica_id,
+            max_bytes,
+            topics: vec![FetchSnapshotTopic {
+                name: CompactString(topic_name.to_string()),
+                partitions: vec![partition].into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            tag_buffer,
+        })
+    }
+
+    /// The cluster the sender belongs to, if it said so
+    pub fn cluster_id(&self) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .tag_buffer
+            .get::<CompactNullableString>(Self::CLUSTER_ID_TAG)?
+            .and_then(|id| id.0))
+    }
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<FetchSnapshotPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition current_leader_epoch snapshot_id position TAG_BUFFER
+///
+/// `position` is the byte of the snapshot to continue reading at.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotPartition {
+    pub partition: i32,
+    pub current_leader_epoch: i32,
+    pub snapshot_id: SnapshotId,
+    pub position: i64,
+    tag_buffer: TagBuf,
+}
+
+impl FetchSnapshotPartition {
+    pub fn new(
+        partition: i32,
+        current_leader_epoch: i32,
+        snapshot_id: SnapshotId,
+        position: i64,
+    ) -> Self {
+        Self {
+            partition,
+            current_leader_epoch,
+            snapshot_id,
+            position,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for FetchSnapshotPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let partition = src.get_i32();
+        let current_leader_epoch = src.get_i32();
+        let snapshot_id = unwrap_decode!(SnapshotId::decode(src, None));
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let position = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition,
+            current_leader_epoch,
+            snapshot_id,
+            position,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchSnapshotTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchSnapshotRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let replica_id = src.get_i32();
+        let max_bytes = src.get_i32();
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            replica_id,
+            max_bytes,
+            topics,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
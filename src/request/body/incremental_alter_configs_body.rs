@@ -0,0 +1,121 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// IncrementalAlterConfigs Request (Version: 1) => [resources] validate_only TAG_BUFFER
+///
+/// Changes single configs of the resources, leaving the others as they are.
+#[derive(Debug, WireLen)]
+pub struct IncrementalAlterConfigsRequestBody {
+    pub resources: CompactArray<IncrementalAlterConfigsResource>,
+    pub validate_only: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// resources => resource_type resource_name [configs] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct IncrementalAlterConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub configs: CompactArray<IncrementalAlterableConfig>,
+    tag_buffer: TagBuf,
+}
+
+/// configs => name config_operation value TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct IncrementalAlterableConfig {
+    pub name: CompactString,
+    pub config_operation: i8,
+    pub value: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl IncrementalAlterableConfig {
+    /// Sets the config to `value`
+    pub const SET: i8 = 0;
+    /// Removes the config, reverting it to its default
+    pub const DELETE: i8 = 1;
+    /// Adds `value` to a list config
+    pub const APPEND: i8 = 2;
+    /// Removes `value` from a list config
+    pub const SUBTRACT: i8 = 3;
+}
+
+impl Decoder for IncrementalAlterableConfig {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let config_operation = src.get_i8();
+        let value = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            config_operation,
+            value,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for IncrementalAlterConfigsResource {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 1 {
+            src.reserve(1);
+            return Ok(None);
+        }
+        let resource_type = src.get_i8();
+        let resource_name = unwrap_decode!(CompactString::decode(src, None));
+        let configs = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            resource_type,
+            resource_name,
+            configs,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for IncrementalAlterConfigsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let resources = unwrap_decode!(CompactArray::decode(src, None));
+        let validate_only = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = IncrementalAlterConfigsRequestBody {
+            resources,
+            validate_only,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
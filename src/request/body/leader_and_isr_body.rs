@@ -0,0 +1,246 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// LeaderAndIsr Request (Version: 4) => controller_id controller_epoch broker_epoch [topic_states] [live_leaders] TAG_BUFFER
+///
+/// Sent by the controller to tell a broker which of its partitions it leads and which
+/// ones it follows, along with the endpoints of the leaders it has to fetch from.
+/// Also encoded, by tests acting as the controller.
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrRequestBody {
+    pub controller_id: i32,
+    pub controller_epoch: i32,
+    pub broker_epoch: i64,
+    pub topic_states: CompactArray<LeaderAndIsrTopicState>,
+    pub live_leaders: CompactArray<LeaderAndIsrLiveLeader>,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrRequestBody {
+    pub fn new(
+        controller_id: i32,
+        controller_epoch: i32,
+        topic_states: CompactArray<LeaderAndIsrTopicState>,
+        live_leaders: CompactArray<LeaderAndIsrLiveLeader>,
+    ) -> Self {
+        Self {
+            controller_id,
+            controller_epoch,
+            broker_epoch: -1,
+            topic_states,
+            live_leaders,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topic_states => topic_name [partition_states] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrTopicState {
+    pub topic_name: CompactString,
+    pub partition_states: CompactArray<LeaderAndIsrPartitionState>,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrTopicState {
+    pub fn new(
+        topic_name: impl Into<String>,
+        partition_states: CompactArray<LeaderAndIsrPartitionState>,
+    ) -> Self {
+        Self {
+            topic_name: CompactString(topic_name.into()),
+            partition_states,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partition_states => partition_index controller_epoch leader leader_epoch [isr] partition_epoch [replicas] [adding_replicas] [removing_replicas] is_new TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrPartitionState {
+    pub partition_index: i32,
+    pub controller_epoch: i32,
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub isr: CompactArray<i32>,
+    pub partition_epoch: i32,
+    pub replicas: CompactArray<i32>,
+    pub adding_replicas: CompactArray<i32>,
+    pub removing_replicas: CompactArray<i32>,
+    pub is_new: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrPartitionState {
+    /// The state of a partition whose replicas are not being reassigned
+    pub fn new(
+        partition_index: i32,
+        controller_epoch: i32,
+        leader: i32,
+        leader_epoch: i32,
+        isr: Vec<i32>,
+        replicas: Vec<i32>,
+    ) -> Self {
+        Self {
+            partition_index,
+            controller_epoch,
+            leader,
+            leader_epoch,
+            isr: isr.into(),
+            partition_epoch: 0,
+            replicas: replicas.into(),
+            adding_replicas: CompactArray::new(),
+            removing_replicas: CompactArray::new(),
+            is_new: Bool::False,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// live_leaders => broker_id host_name port TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrLiveLeader {
+    pub broker_id: i32,
+    pub host_name: CompactString,
+    pub port: i32,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrLiveLeader {
+    pub fn new(broker_id: i32, host_name: impl Into<String>, port: i32) -> Self {
+        Self {
+            broker_id,
+            host_name: CompactString(host_name.into()),
+            port,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for LeaderAndIsrPartitionState {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 16 {
+            src.reserve(16);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let controller_epoch = src.get_i32();
+        let leader = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let isr = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let partition_epoch = src.get_i32();
+        let replicas = unwrap_decode!(CompactArray::decode(src, None));
+        let adding_replicas = unwrap_decode!(CompactArray::decode(src, None));
+        let removing_replicas = unwrap_decode!(CompactArray::decode(src, None));
+        let is_new = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            controller_epoch,
+            leader,
+            leader_epoch,
+            isr,
+            partition_epoch,
+            replicas,
+            adding_replicas,
+            removing_replicas,
+            is_new,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for LeaderAndIsrTopicState {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partition_states = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partition_states,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for LeaderAndIsrLiveLeader {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let broker_id = src.get_i32();
+        let host_name = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let port = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            broker_id,
+            host_name,
+            port,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for LeaderAndIsrRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 16 {
+            src.reserve(16);
+            return Ok(None);
+        }
+        let controller_id = src.get_i32();
+        let controller_epoch = src.get_i32();
+        let broker_epoch = src.get_i64();
+        let topic_states = unwrap_decode!(CompactArray::decode(src, None));
+        let live_leaders = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = LeaderAndIsrRequestBody {
+            controller_id,
+            controller_epoch,
+            broker_epoch,
+            topic_states,
+            live_leaders,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
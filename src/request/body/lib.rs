@@ -7,30 +7,210 @@ use crate::{
     unwrap_decode,
 };
 
+use super::alter_client_quotas_body::AlterClientQuotasRequestBody;
+use super::alter_configs_body::AlterConfigsRequestBody;
+use super::alter_user_scram_credentials_body::AlterUserScramCredentialsRequestBody;
 use super::api_versions_body::ApiVersionsRequestBody;
+use super::begin_quorum_epoch_body::BeginQuorumEpochRequestBody;
+use super::broker_heartbeat_body::BrokerHeartbeatRequestBody;
+use super::broker_registration_body::BrokerRegistrationRequestBody;
+use super::create_acls_body::CreateAclsRequestBody;
+use super::create_topics_body::CreateTopicsRequestBody;
+use super::delete_acls_body::DeleteAclsRequestBody;
+use super::delete_records_body::DeleteRecordsRequestBody;
+use super::describe_acls_body::DescribeAclsRequestBody;
+use super::describe_client_quotas_body::DescribeClientQuotasRequestBody;
+use super::describe_cluster_body::DescribeClusterRequestBody;
+use super::describe_configs_body::DescribeConfigsRequestBody;
+use super::describe_log_dirs_body::DescribeLogDirsRequestBody;
+use super::describe_quorum_body::DescribeQuorumRequestBody;
 use super::describe_topic_partitions_body::DescribeTopicPartitionsRequestBody;
+use super::describe_user_scram_credentials_body::DescribeUserScramCredentialsRequestBody;
+use super::elect_leaders_body::ElectLeadersRequestBody;
+use super::end_quorum_epoch_body::EndQuorumEpochRequestBody;
+use super::fetch_body::FetchRequestBody;
+use super::fetch_snapshot_body::FetchSnapshotRequestBody;
+use super::incremental_alter_configs_body::IncrementalAlterConfigsRequestBody;
+use super::leader_and_isr_body::LeaderAndIsrRequestBody;
+use super::list_offsets_body::ListOffsetsRequestBody;
+use super::metadata_body::MetadataRequestBody;
+use super::produce_body::ProduceRequestBody;
+use super::sasl_authenticate_body::SaslAuthenticateRequestBody;
+use super::sasl_handshake_body::SaslHandshakeRequestBody;
+use super::vote_body::VoteRequestBody;
 
 #[derive(Debug)]
 pub enum RequestBody {
+    Produce(ProduceRequestBody),
+    Fetch(FetchRequestBody),
+    ListOffsets(ListOffsetsRequestBody),
+    Metadata(MetadataRequestBody),
+    LeaderAndIsr(LeaderAndIsrRequestBody),
     ApiVersions(ApiVersionsRequestBody),
+    DeleteRecords(DeleteRecordsRequestBody),
+    CreateTopics(CreateTopicsRequestBody),
+    ElectLeaders(ElectLeadersRequestBody),
+    DescribeAcls(DescribeAclsRequestBody),
+    CreateAcls(CreateAclsRequestBody),
+    DeleteAcls(DeleteAclsRequestBody),
+    DescribeConfigs(DescribeConfigsRequestBody),
+    AlterConfigs(AlterConfigsRequestBody),
+    IncrementalAlterConfigs(IncrementalAlterConfigsRequestBody),
+    DescribeLogDirs(DescribeLogDirsRequestBody),
+    DescribeClientQuotas(DescribeClientQuotasRequestBody),
+    AlterClientQuotas(AlterClientQuotasRequestBody),
+    DescribeUserScramCredentials(DescribeUserScramCredentialsRequestBody),
+    AlterUserScramCredentials(AlterUserScramCredentialsRequestBody),
+    Vote(VoteRequestBody),
+    BeginQuorumEpoch(BeginQuorumEpochRequestBody),
+    EndQuorumEpoch(EndQuorumEpochRequestBody),
+    DescribeQuorum(DescribeQuorumRequestBody),
+    FetchSnapshot(FetchSnapshotRequestBody),
+    DescribeCluster(DescribeClusterRequestBody),
+    BrokerRegistration(BrokerRegistrationRequestBody),
+    BrokerHeartbeat(BrokerHeartbeatRequestBody),
     DescribeTopicPartitions(DescribeTopicPartitionsRequestBody),
+    SaslHandshake(SaslHandshakeRequestBody),
+    SaslAuthenticate(SaslAuthenticateRequestBody),
 }
 
 impl RequestBody {
+    /// Decodes the body of a request of `version` of the API `key`. Only the bodies
+    /// that differ between the supported versions look at the version.
     pub fn decode_by_key(
         key: &ApiKeys,
+        version: i16,
         src: &mut BytesMut,
         size: Option<usize>,
     ) -> anyhow::Result<Option<Self>> {
         match key {
+            ApiKeys::Produce => {
+                let inner = unwrap_decode!(ProduceRequestBody::decode(src, size));
+                Ok(Some(RequestBody::Produce(inner)))
+            }
+            ApiKeys::Fetch => {
+                let inner = unwrap_decode!(FetchRequestBody::decode(src, size));
+                Ok(Some(RequestBody::Fetch(inner)))
+            }
+            ApiKeys::ListOffsets => {
+                let inner = unwrap_decode!(ListOffsetsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::ListOffsets(inner)))
+            }
+            ApiKeys::Metadata => {
+                let inner = unwrap_decode!(MetadataRequestBody::decode(src, size));
+                Ok(Some(RequestBody::Metadata(inner)))
+            }
+            ApiKeys::LeaderAndIsr => {
+                let inner = unwrap_decode!(LeaderAndIsrRequestBody::decode(src, size));
+                Ok(Some(RequestBody::LeaderAndIsr(inner)))
+            }
             ApiKeys::ApiVersions => {
                 let inner = unwrap_decode!(ApiVersionsRequestBody::decode(src, size));
                 Ok(Some(RequestBody::ApiVersions(inner)))
             }
+            ApiKeys::DeleteRecords => {
+                let inner = unwrap_decode!(DeleteRecordsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DeleteRecords(inner)))
+            }
+            ApiKeys::CreateTopics => {
+                let inner = unwrap_decode!(CreateTopicsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::CreateTopics(inner)))
+            }
+            ApiKeys::ElectLeaders => {
+                let inner = unwrap_decode!(ElectLeadersRequestBody::decode(src, size));
+                Ok(Some(RequestBody::ElectLeaders(inner)))
+            }
+            ApiKeys::DescribeAcls => {
+                let inner = unwrap_decode!(DescribeAclsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeAcls(inner)))
+            }
+            ApiKeys::CreateAcls => {
+                let inner = unwrap_decode!(CreateAclsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::CreateAcls(inner)))
+            }
+            ApiKeys::DeleteAcls => {
+                let inner = unwrap_decode!(DeleteAclsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DeleteAcls(inner)))
+            }
+            ApiKeys::DescribeConfigs => {
+                let inner = unwrap_decode!(DescribeConfigsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeConfigs(inner)))
+            }
+            ApiKeys::AlterConfigs => {
+                let inner = unwrap_decode!(AlterConfigsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::AlterConfigs(inner)))
+            }
+            ApiKeys::IncrementalAlterConfigs => {
+                let inner = unwrap_decode!(IncrementalAlterConfigsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::IncrementalAlterConfigs(inner)))
+            }
+            ApiKeys::DescribeLogDirs => {
+                let inner = unwrap_decode!(DescribeLogDirsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeLogDirs(inner)))
+            }
+            ApiKeys::DescribeClientQuotas => {
+                let inner = unwrap_decode!(DescribeClientQuotasRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeClientQuotas(inner)))
+            }
+            ApiKeys::AlterClientQuotas => {
+                let inner = unwrap_decode!(AlterClientQuotasRequestBody::decode(src, size));
+                Ok(Some(RequestBody::AlterClientQuotas(inner)))
+            }
+            ApiKeys::DescribeUserScramCredentials => {
+                let inner =
+                    unwrap_decode!(DescribeUserScramCredentialsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeUserScramCredentials(inner)))
+            }
+            ApiKeys::AlterUserScramCredentials => {
+                let inner = unwrap_decode!(AlterUserScramCredentialsRequestBody::decode(src, size));
+                Ok(Some(RequestBody::AlterUserScramCredentials(inner)))
+            }
+            ApiKeys::Vote => {
+                let inner = unwrap_decode!(VoteRequestBody::decode(src, size));
+                Ok(Some(RequestBody::Vote(inner)))
+            }
+            ApiKeys::BeginQuorumEpoch => {
+                let inner = unwrap_decode!(BeginQuorumEpochRequestBody::decode(src, size));
+                Ok(Some(RequestBody::BeginQuorumEpoch(inner)))
+            }
+            ApiKeys::EndQuorumEpoch => {
+                let inner = unwrap_decode!(EndQuorumEpochRequestBody::decode(src, size));
+                Ok(Some(RequestBody::EndQuorumEpoch(inner)))
+            }
+            ApiKeys::DescribeQuorum => {
+                let inner = unwrap_decode!(DescribeQuorumRequestBody::decode(src, size));
+                Ok(Some(RequestBody::DescribeQuorum(inner)))
+            }
+            ApiKeys::FetchSnapshot => {
+                let inner = unwrap_decode!(FetchSnapshotRequestBody::decode(src, size));
+                Ok(Some(RequestBody::FetchSnapshot(inner)))
+            }
+            ApiKeys::DescribeCluster => {
+                let inner =
+                    unwrap_decode!(DescribeClusterRequestBody::decode_version(src, version, size));
+                Ok(Some(RequestBody::DescribeCluster(inner)))
+            }
+            ApiKeys::BrokerRegistration => {
+                let inner = unwrap_decode!(BrokerRegistrationRequestBody::decode(src, size));
+                Ok(Some(RequestBody::BrokerRegistration(inner)))
+            }
+            ApiKeys::BrokerHeartbeat => {
+                let inner = unwrap_decode!(BrokerHeartbeatRequestBody::decode(src, size));
+                Ok(Some(RequestBody::BrokerHeartbeat(inner)))
+            }
             ApiKeys::DescribeTopicPartitions => {
                 let inner = unwrap_decode!(DescribeTopicPartitionsRequestBody::decode(src, size));
                 Ok(Some(RequestBody::DescribeTopicPartitions(inner)))
             }
+            ApiKeys::SaslHandshake => {
+                let inner = unwrap_decode!(SaslHandshakeRequestBody::decode(src, size));
+                Ok(Some(RequestBody::SaslHandshake(inner)))
+            }
+            ApiKeys::SaslAuthenticate => {
+                let inner =
+                    unwrap_decode!(SaslAuthenticateRequestBody::decode_version(src, version, size));
+                Ok(Some(RequestBody::SaslAuthenticate(inner)))
+            }
             k => {
                 bail!("Couldnt decode body based on api key {k} as it is unimplemented!")
             }
@@ -41,8 +221,37 @@ impl RequestBody {
 impl WireLen for RequestBody {
     fn wire_len(&self) -> usize {
         match self {
+            RequestBody::Produce(b) => b.wire_len(),
+            RequestBody::Fetch(b) => b.wire_len(),
+            RequestBody::ListOffsets(b) => b.wire_len(),
+            RequestBody::Metadata(b) => b.wire_len(),
+            RequestBody::LeaderAndIsr(b) => b.wire_len(),
             RequestBody::ApiVersions(b) => b.wire_len(),
+            RequestBody::DeleteRecords(b) => b.wire_len(),
+            RequestBody::CreateTopics(b) => b.wire_len(),
+            RequestBody::ElectLeaders(b) => b.wire_len(),
+            RequestBody::DescribeAcls(b) => b.wire_len(),
+            RequestBody::CreateAcls(b) => b.wire_len(),
+            RequestBody::DeleteAcls(b) => b.wire_len(),
+            RequestBody::DescribeConfigs(b) => b.wire_len(),
+            RequestBody::AlterConfigs(b) => b.wire_len(),
+            RequestBody::IncrementalAlterConfigs(b) => b.wire_len(),
+            RequestBody::DescribeLogDirs(b) => b.wire_len(),
+            RequestBody::DescribeClientQuotas(b) => b.wire_len(),
+            RequestBody::AlterClientQuotas(b) => b.wire_len(),
+            RequestBody::DescribeUserScramCredentials(b) => b.wire_len(),
+            RequestBody::AlterUserScramCredentials(b) => b.wire_len(),
+            RequestBody::Vote(b) => b.wire_len(),
+            RequestBody::BeginQuorumEpoch(b) => b.wire_len(),
+            RequestBody::EndQuorumEpoch(b) => b.wire_len(),
+            RequestBody::DescribeQuorum(b) => b.wire_len(),
+            RequestBody::FetchSnapshot(b) => b.wire_len(),
+            RequestBody::DescribeCluster(b) => b.wire_len(),
+            RequestBody::BrokerRegistration(b) => b.wire_len(),
+            RequestBody::BrokerHeartbeat(b) => b.wire_len(),
             RequestBody::DescribeTopicPartitions(b) => b.wire_len(),
+            RequestBody::SaslHandshake(b) => b.wire_len(),
+            RequestBody::SaslAuthenticate(b) => b.wire_len(),
         }
     }
 }
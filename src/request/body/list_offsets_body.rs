@@ -0,0 +1,118 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// ListOffsets Request (Version: 6) => replica_id isolation_level [topics] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct ListOffsetsRequestBody {
+    pub replica_id: i32,
+    pub isolation_level: i8,
+    pub topics: CompactArray<ListOffsetsTopic>,
+    tag_buffer: TagBuf,
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen)]
+pub struct ListOffsetsTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<ListOffsetsPartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index current_leader_epoch timestamp TAG_BUFFER
+///
+/// `timestamp` is the one the first offset at or after is looked up, or one of
+/// [`Self::LATEST_TIMESTAMP`] and [`Self::EARLIEST_TIMESTAMP`].
+#[derive(Debug, WireLen)]
+pub struct ListOffsetsPartition {
+    pub partition_index: i32,
+    pub current_leader_epoch: i32,
+    pub timestamp: i64,
+    tag_buffer: TagBuf,
+}
+
+impl ListOffsetsPartition {
+    /// Asks for the offset the next record will get, as far as the client may read
+    pub const LATEST_TIMESTAMP: i64 = -1;
+    /// Asks for the log start offset
+    pub const EARLIEST_TIMESTAMP: i64 = -2;
+}
+
+impl Decoder for ListOffsetsPartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 16 {
+            src.reserve(16);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let current_leader_epoch = src.get_i32();
+        let timestamp = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            current_leader_epoch,
+            timestamp,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ListOffsetsTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ListOffsetsRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 5 {
+            src.reserve(5);
+            return Ok(None);
+        }
+        let replica_id = src.get_i32();
+        let isolation_level = src.get_i8();
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = ListOffsetsRequestBody {
+            replica_id,
+            isolation_level,
+            topics,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,92 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::WireLen;
+
+/// Metadata Request (Version: 12) => [topics] allow_auto_topic_creation include_topic_authorized_operations TAG_BUFFER
+///
+/// Null `topics` ask for every topic of the cluster, an empty array for none.
+#[derive(Debug)]
+pub struct MetadataRequestBody {
+    pub topics: Option<CompactArray<MetadataRequestTopic>>,
+    pub allow_auto_topic_creation: Bool,
+    pub include_topic_authorized_operations: Bool,
+    tag_buffer: TagBuf,
+}
+
+/// topics => topic_id name TAG_BUFFER
+///
+/// Topics are asked for by name, or by id with a null name.
+#[derive(Debug, WireLen)]
+pub struct MetadataRequestTopic {
+    pub topic_id: Uuid,
+    pub name: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for MetadataRequestTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let topic_id = unwrap_decode!(Uuid::decode(src, None));
+        let name = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_id,
+            name,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for MetadataRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        // CompactArray decodes null as empty, which asks for nothing here
+        let topics = if src.first() == Some(&0) {
+            src.advance(1);
+            None
+        } else {
+            Some(unwrap_decode!(CompactArray::decode(src, None)))
+        };
+        let allow_auto_topic_creation = unwrap_decode!(Bool::decode(src, None));
+        let include_topic_authorized_operations = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = MetadataRequestBody {
+            topics,
+            allow_auto_topic_creation,
+            include_topic_authorized_operations,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
+
+impl WireLen for MetadataRequestBody {
+    fn wire_len(&self) -> usize {
+        self.topics.as_ref().map_or(1, WireLen::wire_len)
+            + self.allow_auto_topic_creation.wire_len()
+            + self.include_topic_authorized_operations.wire_len()
+            + self.tag_buffer.wire_len()
+    }
+}
@@ -1,7 +1,89 @@
+mod alter_client_quotas_body;
+mod alter_configs_body;
+mod alter_user_scram_credentials_body;
 mod api_versions_body;
+mod begin_quorum_epoch_body;
+mod broker_heartbeat_body;
+mod broker_registration_body;
+mod create_acls_body;
+mod create_topics_body;
+mod delete_acls_body;
+mod delete_records_body;
+mod describe_acls_body;
+mod describe_client_quotas_body;
+mod describe_cluster_body;
+mod describe_configs_body;
+mod describe_log_dirs_body;
+mod describe_quorum_body;
 mod describe_topic_partitions_body;
+mod describe_user_scram_credentials_body;
+mod elect_leaders_body;
+mod end_quorum_epoch_body;
+mod fetch_body;
+mod fetch_snapshot_body;
+mod incremental_alter_configs_body;
+mod leader_and_isr_body;
 mod lib;
+mod list_offsets_body;
+mod metadata_body;
+mod produce_body;
+mod sasl_authenticate_body;
+mod sasl_handshake_body;
+mod vote_body;
 
+pub use alter_client_quotas_body::{
+    AlterClientQuotasEntry, AlterClientQuotasOp, AlterClientQuotasRequestBody,
+};
+pub use alter_configs_body::{AlterConfigsRequestBody, AlterConfigsResource, AlterableConfig};
+pub use alter_user_scram_credentials_body::{
+    AlterUserScramCredentialsRequestBody, ScramCredentialDeletion, ScramCredentialUpsertion,
+};
 pub use api_versions_body::ApiVersionsRequestBody;
+pub use begin_quorum_epoch_body::{
+    BeginQuorumEpochPartition, BeginQuorumEpochRequestBody, BeginQuorumEpochTopic,
+};
+pub use broker_heartbeat_body::BrokerHeartbeatRequestBody;
+pub use broker_registration_body::{
+    BrokerRegistrationRequestBody, RegistrationFeature, RegistrationListener,
+};
+pub use create_acls_body::{AclCreation, CreateAclsRequestBody};
+pub use create_topics_body::{
+    CreatableReplicaAssignment, CreatableTopic, CreatableTopicConfig, CreateTopicsRequestBody,
+};
+pub use delete_acls_body::DeleteAclsRequestBody;
+pub use delete_records_body::{DeleteRecordsPartition, DeleteRecordsRequestBody, DeleteRecordsTopic};
+pub use describe_acls_body::{AclFilter, DescribeAclsRequestBody};
+pub use describe_client_quotas_body::{
+    DescribeClientQuotasComponent, DescribeClientQuotasRequestBody,
+};
+pub use describe_cluster_body::DescribeClusterRequestBody;
+pub use describe_configs_body::{DescribeConfigsRequestBody, DescribeConfigsResource};
+pub use describe_log_dirs_body::{DescribableLogDirTopic, DescribeLogDirsRequestBody};
+pub use describe_quorum_body::{
+    DescribeQuorumPartition, DescribeQuorumRequestBody, DescribeQuorumTopic,
+};
 pub use describe_topic_partitions_body::DescribeTopicPartitionsRequestBody;
+pub use describe_user_scram_credentials_body::{DescribeUserScramCredentialsRequestBody, UserName};
+pub use elect_leaders_body::{ElectLeadersRequestBody, ElectLeadersTopic, ElectionType};
+pub use end_quorum_epoch_body::{
+    EndQuorumEpochPartition, EndQuorumEpochRequestBody, EndQuorumEpochTopic, PreferredCandidate,
+};
+pub use fetch_body::{FetchPartition, FetchRequestBody, FetchTopic, ForgottenTopic};
+pub use fetch_snapshot_body::{
+    FetchSnapshotPartition, FetchSnapshotRequestBody, FetchSnapshotTopic,
+};
+pub use incremental_alter_configs_body::{
+    IncrementalAlterConfigsRequestBody, IncrementalAlterConfigsResource,
+    IncrementalAlterableConfig,
+};
+pub use leader_and_isr_body::{
+    LeaderAndIsrLiveLeader, LeaderAndIsrPartitionState, LeaderAndIsrRequestBody,
+    LeaderAndIsrTopicState,
+};
 pub use lib::RequestBody;
+pub use list_offsets_body::{ListOffsetsPartition, ListOffsetsRequestBody, ListOffsetsTopic};
+pub use metadata_body::{MetadataRequestBody, MetadataRequestTopic};
+pub use produce_body::{ProducePartitionData, ProduceRequestBody, ProduceTopicData};
+pub use sasl_authenticate_body::SaslAuthenticateRequestBody;
+pub use sasl_handshake_body::SaslHandshakeRequestBody;
+pub use vote_body::{VotePartition, VoteRequestBody, VoteTopic};
@@ -0,0 +1,154 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// Produce Request (Version: 9) => transactional_id acks timeout_ms [topic_data] TAG_BUFFER
+///
+/// `acks` is the number of acknowledgements the leader waits for: 0 for none, which
+/// gets no response at all, 1 for its own, -1 for every in sync replica.
+/// Also encoded, by tests producing to the broker.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProduceRequestBody {
+    pub transactional_id: CompactNullableString,
+    pub acks: i16,
+    pub timeout_ms: i32,
+    pub topic_data: CompactArray<ProduceTopicData>,
+    tag_buffer: TagBuf,
+}
+
+impl ProduceRequestBody {
+    pub const ACKS_NONE: i16 = 0;
+    pub const ACKS_LEADER: i16 = 1;
+    pub const ACKS_ALL: i16 = -1;
+
+    /// A produce outside of a transaction
+    pub fn new(acks: i16, timeout_ms: i32, topic_data: CompactArray<ProduceTopicData>) -> Self {
+        Self {
+            transactional_id: CompactNullableString::null(),
+            acks,
+            timeout_ms,
+            topic_data,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topic_data => name [partition_data] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProduceTopicData {
+    pub name: CompactString,
+    pub partition_data: CompactArray<ProducePartitionData>,
+    tag_buffer: TagBuf,
+}
+
+impl ProduceTopicData {
+    pub fn new(
+        name: impl Into<String>,
+        partition_data: CompactArray<ProducePartitionData>,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            partition_data,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partition_data => index records TAG_BUFFER
+///
+/// `records` holds the encoded record batches.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProducePartitionData {
+    pub index: i32,
+    pub records: CompactBytes,
+    tag_buffer: TagBuf,
+}
+
+impl ProducePartitionData {
+    pub fn new(index: i32, records: Vec<u8>) -> Self {
+        Self {
+            index,
+            records: CompactBytes(records),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for ProducePartitionData {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let index = src.get_i32();
+        let records = unwrap_decode!(CompactBytes::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            index,
+            records,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ProduceTopicData {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let partition_data = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            partition_data,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for ProduceRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let transactional_id = unwrap_decode!(CompactNullableString::decode(src, None));
+        if src.remaining() < 6 {
+            src.reserve(6);
+            return Ok(None);
+        }
+        let acks = src.get_i16();
+        let timeout_ms = src.get_i32();
+        let topic_data = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = ProduceRequestBody {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topic_data,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,82 @@
+use crate::codec::{Decoder, WireLen};
+use crate::primitives::UVarint;
+use crate::types::TagBuf;
+use crate::unwrap_decode;
+use anyhow;
+use bytes::Buf;
+
+/// SaslAuthenticate Request (Version: 0-2) => auth_bytes TAG_BUFFER
+///
+/// `auth_bytes` are BYTES up to version 1 and COMPACT_BYTES in the flexible version 2,
+/// which adds the tagged fields.
+#[derive(Debug)]
+pub struct SaslAuthenticateRequestBody {
+    pub auth_bytes: Vec<u8>,
+    tag_buffer: Option<TagBuf>,
+}
+
+impl SaslAuthenticateRequestBody {
+    /// Decodes the body of a request of `version`
+    pub fn decode_version(
+        src: &mut bytes::BytesMut,
+        version: i16,
+        size: Option<usize>,
+    ) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let flexible = version >= 2;
+        let len = if flexible {
+            let len_plus_one = unwrap_decode!(UVarint::decode(src, None)).0 as usize;
+            anyhow::ensure!(len_plus_one > 0, "auth_bytes cannot be null");
+            len_plus_one - 1
+        } else {
+            if src.remaining() < 4 {
+                src.reserve(4);
+                return Ok(None);
+            }
+            let len = src.get_i32();
+            anyhow::ensure!(len >= 0, "auth_bytes cannot be null");
+            len as usize
+        };
+        if src.remaining() < len {
+            src.reserve(len);
+            return Ok(None);
+        }
+        let auth_bytes = src.split_to(len).to_vec();
+        let tag_buffer = if flexible {
+            Some(unwrap_decode!(TagBuf::decode(src, None)))
+        } else {
+            None
+        };
+        let body = SaslAuthenticateRequestBody {
+            auth_bytes,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
+
+impl WireLen for SaslAuthenticateRequestBody {
+    fn wire_len(&self) -> usize {
+        let len = self.auth_bytes.len();
+        match &self.tag_buffer {
+            Some(tag_buffer) => {
+                UVarint::wire_len_of(len as u32 + 1) + len + tag_buffer.wire_len()
+            }
+            None => size_of::<i32>() + len,
+        }
+    }
+}
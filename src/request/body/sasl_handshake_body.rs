@@ -0,0 +1,55 @@
+use crate::codec::{Decoder, WireLen};
+use anyhow::{self, Context};
+use bytes::Buf;
+
+/// SaslHandshake Request (Version: 1) => mechanism
+///
+/// Never flexible, so it has no tagged fields.
+#[derive(Debug)]
+pub struct SaslHandshakeRequestBody {
+    pub mechanism: String,
+}
+
+impl WireLen for SaslHandshakeRequestBody {
+    fn wire_len(&self) -> usize {
+        size_of::<i16>() + self.mechanism.len()
+    }
+}
+
+impl Decoder for SaslHandshakeRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let len = src.get_i16();
+        anyhow::ensure!(len >= 0, "Mechanism cannot be null");
+        let len = len as usize;
+        if src.remaining() < len {
+            src.reserve(len);
+            return Ok(None);
+        }
+        let mechanism = String::from_utf8(src.split_to(len).to_vec())
+            .context("Parsing SASL mechanism")?;
+        let body = SaslHandshakeRequestBody { mechanism };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,139 @@
+use crate::codec::{Decoder, WireLen};
+use crate::types::TagBuf;
+use crate::{primitives::*, unwrap_decode};
+use anyhow;
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+/// Vote Request (Version: 0) => cluster_id [topics] TAG_BUFFER
+///
+/// Sent by a candidate of the KRaft quorum to every other voter, asking for its vote in
+/// the candidate's epoch. Also encoded, by the candidates of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct VoteRequestBody {
+    pub cluster_id: CompactNullableString,
+    pub topics: CompactArray<VoteTopic>,
+    tag_buffer: TagBuf,
+}
+
+impl VoteRequestBody {
+    /// A vote request for a single partition
+    pub fn new(cluster_id: &str, topic_name: &str, partition: VotePartition) -> Self {
+        Self {
+            cluster_id: Some(cluster_id).into(),
+            topics: vec![VoteTopic {
+                topic_name: CompactString(topic_name.to_string()),
+                partitions: vec![partition].into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct VoteTopic {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<VotePartition>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index candidate_epoch candidate_id last_offset_epoch last_offset TAG_BUFFER
+///
+/// `last_offset_epoch` and `last_offset` describe the end of the candidate's log, voters
+/// only vote for candidates whose log is at least as long as theirs.
+#[derive(Debug, WireLen, Encoder)]
+pub struct VotePartition {
+    pub partition_index: i32,
+    pub candidate_epoch: i32,
+    pub candidate_id: i32,
+    pub last_offset_epoch: i32,
+    pub last_offset: i64,
+    tag_buffer: TagBuf,
+}
+
+impl VotePartition {
+    pub fn new(
+        partition_index: i32,
+        candidate_epoch: i32,
+        candidate_id: i32,
+        last_offset_epoch: i32,
+        last_offset: i64,
+    ) -> Self {
+        Self {
+            partition_index,
+            candidate_epoch,
+            candidate_id,
+            last_offset_epoch,
+            last_offset,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for VotePartition {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 24 {
+            src.reserve(24);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let candidate_epoch = src.get_i32();
+        let candidate_id = src.get_i32();
+        let last_offset_epoch = src.get_i32();
+        let last_offset = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            candidate_epoch,
+            candidate_id,
+            last_offset_epoch,
+            last_offset,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for VoteTopic {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for VoteRequestBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        let cluster_id = unwrap_decode!(CompactNullableString::decode(src, None));
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            cluster_id,
+            topics,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -1,20 +1,22 @@
 use crate::{
-    codec::Decoder,
-    primitives::{CompactArray, NullableString},
-    types::{ApiKeys, Tag},
+    codec::{Decoder, Encoder},
+    primitives::NullableString,
+    types::{ApiKeys, TagBuf},
     unwrap_decode,
 };
-use bytes::Buf;
+use bytes::{Buf, BufMut};
 use kafka_macros::WireLen;
 use std::fmt::Debug;
 
+/// Request header v2, or v1 for requests that are not flexible yet, which lacks the
+/// tagged fields
 #[derive(Debug, WireLen)]
 pub struct RequestHeaderV2 {
     pub(crate) request_api_key: ApiKeys,
     pub(crate) request_api_version: i16,
     pub(crate) correlation_id: i32,
     pub(crate) client_id: NullableString,
-    pub(crate) tag_buffer: CompactArray<Tag>,
+    pub(crate) tag_buffer: Option<TagBuf>,
 }
 
 impl RequestHeaderV2 {
@@ -27,7 +29,7 @@ impl RequestHeaderV2 {
         request_api_version: i16,
         correlation_id: i32,
         client_id: NullableString,
-        tag_buffer: CompactArray<Tag>,
+        tag_buffer: Option<TagBuf>,
     ) -> Self {
         Self {
             request_api_key: request_api_key.into(),
@@ -39,6 +41,20 @@ impl RequestHeaderV2 {
     }
 }
 
+/// Encodes the header of requests the broker sends itself, like the fetches of followers
+impl Encoder for RequestHeaderV2 {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        dest.put_i16(self.request_api_key as i16);
+        dest.put_i16(self.request_api_version);
+        dest.put_i32(self.correlation_id);
+        self.client_id.encode(dest)?;
+        if let Some(ref tag_buffer) = self.tag_buffer {
+            tag_buffer.encode(dest)?;
+        }
+        Ok(())
+    }
+}
+
 impl Decoder for RequestHeaderV2 {
     fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
     where
@@ -53,7 +69,11 @@ impl Decoder for RequestHeaderV2 {
         let correlation_id = src.get_i32();
 
         let client_id = unwrap_decode!(NullableString::decode(src, None));
-        let tag_buffer = unwrap_decode!(CompactArray::<Tag>::decode(src, None));
+        let tag_buffer = if ApiKeys::from(request_api_key).is_flexible(request_api_version) {
+            Some(unwrap_decode!(TagBuf::decode(src, None)))
+        } else {
+            None
+        };
 
         let h = RequestHeaderV2::new(
             request_api_key,
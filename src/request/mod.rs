@@ -1,5 +1,6 @@
 mod body;
 mod header;
+#[allow(clippy::module_inception)]
 mod request;
 
 pub use body::*;
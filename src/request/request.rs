@@ -1,7 +1,7 @@
 use anyhow::bail;
 use bytes::Buf;
 use kafka_macros::WireLen;
-use tracing::{info, trace};
+use tracing::trace;
 
 use super::body::RequestBody;
 use super::header::RequestHeaderV2;
@@ -72,13 +72,14 @@ impl Decoder for KafkaRequest {
 
         let body = unwrap_decode!(RequestBody::decode_by_key(
             &header.request_api_key,
+            header.request_api_version,
             src,
             Some(body_size)
         ));
 
         // an i32 cast is safe due to previos assertion 0 <= message_size <= MAX_BODY_SIZE
         let req = KafkaRequest::new(message_size as i32, header, body);
-        info!(
+        trace!(
             "Parsed Request! Bytes remainging in buffer: {}",
             src.remaining()
         );
@@ -0,0 +1,57 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString},
+    types::{ClientQuotaEntityData, ErrorCode, TagBuf},
+};
+
+/// AlterClientQuotas Response (Version: 1) => throttle_time_ms [entries] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterClientQuotasResponseBody {
+    pub throttle_time: i32,
+    pub entries: CompactArray<AlterClientQuotasEntryResult>,
+    tag_buffer: TagBuf,
+}
+
+impl AlterClientQuotasResponseBody {
+    pub fn new(throttle_time: i32, entries: CompactArray<AlterClientQuotasEntryResult>) -> Self {
+        Self {
+            throttle_time,
+            entries,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// entries => error_code error_message [entity] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterClientQuotasEntryResult {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub entity: CompactArray<ClientQuotaEntityData>,
+    tag_buffer: TagBuf,
+}
+
+impl AlterClientQuotasEntryResult {
+    pub fn new(entity: CompactArray<ClientQuotaEntityData>) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            entity,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        entity: CompactArray<ClientQuotaEntityData>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            entity,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
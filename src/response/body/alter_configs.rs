@@ -0,0 +1,68 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// AlterConfigs Response (Version: 2) => throttle_time_ms [responses] TAG_BUFFER
+///
+/// Holds a result for every resource, in the order of the request.
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterConfigsResponseBody {
+    pub throttle_time: i32,
+    pub responses: CompactArray<AlterConfigsResourceResponse>,
+    tag_buffer: TagBuf,
+}
+
+/// IncrementalAlterConfigs Response (Version: 1) => throttle_time_ms [responses] TAG_BUFFER
+///
+/// Laid out exactly like the AlterConfigs response.
+pub type IncrementalAlterConfigsResponseBody = AlterConfigsResponseBody;
+
+impl AlterConfigsResponseBody {
+    pub fn new(throttle_time: i32, responses: CompactArray<AlterConfigsResourceResponse>) -> Self {
+        Self {
+            throttle_time,
+            responses,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// responses => error_code error_message resource_type resource_name TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterConfigsResourceResponse {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    tag_buffer: TagBuf,
+}
+
+impl AlterConfigsResourceResponse {
+    pub fn new(resource_type: i8, resource_name: impl Into<String>) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        resource_type: i8,
+        resource_name: impl Into<String>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
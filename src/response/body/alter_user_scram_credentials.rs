@@ -0,0 +1,57 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// AlterUserScramCredentials Response (Version: 0) => throttle_time_ms [results] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterUserScramCredentialsResponseBody {
+    pub throttle_time: i32,
+    pub results: CompactArray<AlterUserScramCredentialsResult>,
+    tag_buffer: TagBuf,
+}
+
+impl AlterUserScramCredentialsResponseBody {
+    pub fn new(throttle_time: i32, results: CompactArray<AlterUserScramCredentialsResult>) -> Self {
+        Self {
+            throttle_time,
+            results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// results => user error_code error_message TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AlterUserScramCredentialsResult {
+    pub user: CompactString,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl AlterUserScramCredentialsResult {
+    pub fn new(user: impl Into<String>) -> Self {
+        Self {
+            user: CompactString(user.into()),
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        user: impl Into<String>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            user: CompactString(user.into()),
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     codec::Encoder,
     primitives::CompactArray,
-    types::{ApiVersion, Tag},
+    types::{ApiVersion, TagBuf},
 };
 use bytes::{BufMut, BytesMut};
 use kafka_macros::WireLen;
@@ -12,7 +12,7 @@ pub struct ApiVersionsResponseBody {
     pub(crate) error_code: u16,
     pub(crate) api_versions: CompactArray<ApiVersion>,
     pub(crate) throttle_time: u32,
-    pub(crate) tag_buffer: CompactArray<Tag>,
+    pub(crate) tag_buffer: TagBuf,
 }
 
 impl ApiVersionsResponseBody {
@@ -25,7 +25,7 @@ impl ApiVersionsResponseBody {
             error_code,
             api_versions,
             throttle_time,
-            tag_buffer: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
         }
     }
 }
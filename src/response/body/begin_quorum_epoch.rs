@@ -0,0 +1,151 @@
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Decoder, WireLen},
+    primitives::{CompactArray, CompactString},
+    types::{ErrorCode, TagBuf},
+    unwrap_decode,
+};
+
+/// BeginQuorumEpoch Response (Version: 1) => error_code [topics] TAG_BUFFER
+///
+/// EndQuorumEpoch responses (Version: 1) look the same. Also decoded, by the leaders of
+/// this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BeginQuorumEpochResponseBody {
+    pub error_code: i16,
+    pub topics: CompactArray<QuorumEpochTopicResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl BeginQuorumEpochResponseBody {
+    /// The response for a single partition
+    pub fn new(
+        topic_name: impl Into<CompactString>,
+        partition: QuorumEpochPartitionResponse,
+    ) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            topics: vec![QuorumEpochTopicResponse {
+                topic_name: topic_name.into(),
+                partitions: vec![partition].into(),
+                tag_buffer: TagBuf::new(),
+            }]
+            .into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            topics: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct QuorumEpochTopicResponse {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<QuorumEpochPartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+/// partitions => partition_index error_code leader_id leader_epoch TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct QuorumEpochPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    /// The leader the receiver knows of, -1 if none
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl QuorumEpochPartitionResponse {
+    pub fn new(
+        partition_index: i32,
+        error_code: ErrorCode,
+        leader_id: i32,
+        leader_epoch: i32,
+    ) -> Self {
+        Self {
+            partition_index,
+            error_code: error_code.code(),
+            leader_id,
+            leader_epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for QuorumEpochPartitionResponse {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 14 {
+            src.reserve(14);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let error_code = src.get_i16();
+        let leader_id = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            error_code,
+            leader_id,
+            leader_epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for QuorumEpochTopicResponse {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic_name = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic_name,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for BeginQuorumEpochResponseBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let error_code = src.get_i16();
+        let topics = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            error_code,
+            topics,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,84 @@
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Decoder, WireLen},
+    primitives::Bool,
+    types::{ErrorCode, TagBuf},
+    unwrap_decode,
+};
+
+/// BrokerHeartbeat Response (Version: 0-1) => throttle_time_ms error_code is_caught_up is_fenced should_shut_down TAG_BUFFER
+///
+/// Also decoded, by the brokers of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerHeartbeatResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    /// Whether the broker replayed the metadata log far enough to be unfenced
+    pub is_caught_up: Bool,
+    pub is_fenced: Bool,
+    /// Whether the broker may finish shutting down
+    pub should_shut_down: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl BrokerHeartbeatResponseBody {
+    pub fn new(is_caught_up: bool, is_fenced: bool, should_shut_down: bool) -> Self {
+        Self {
+            throttle_time: 0,
+            error_code: ErrorCode::None.code(),
+            is_caught_up: is_caught_up.into(),
+            is_fenced: is_fenced.into(),
+            should_shut_down: should_shut_down.into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the heartbeat, the broker stays fenced
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(false, true, false)
+        }
+    }
+}
+
+impl Decoder for BrokerHeartbeatResponseBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 6 {
+            src.reserve(6);
+            return Ok(None);
+        }
+        let throttle_time = src.get_i32();
+        let error_code = src.get_i16();
+        let is_caught_up = unwrap_decode!(Bool::decode(src, None));
+        let is_fenced = unwrap_decode!(Bool::decode(src, None));
+        let should_shut_down = unwrap_decode!(Bool::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            throttle_time,
+            error_code,
+            is_caught_up,
+            is_fenced,
+            should_shut_down,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
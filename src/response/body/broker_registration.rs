@@ -0,0 +1,73 @@
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Decoder, WireLen},
+    types::{ErrorCode, TagBuf},
+    unwrap_decode,
+};
+
+/// BrokerRegistration Response (Version: 3) => throttle_time_ms error_code broker_epoch TAG_BUFFER
+///
+/// Also decoded, by the brokers of this node.
+#[derive(Debug, WireLen, Encoder)]
+pub struct BrokerRegistrationResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    /// The epoch of the new registration, -1 if it failed
+    pub broker_epoch: i64,
+    tag_buffer: TagBuf,
+}
+
+impl BrokerRegistrationResponseBody {
+    pub fn new(broker_epoch: i64) -> Self {
+        Self {
+            throttle_time: 0,
+            error_code: ErrorCode::None.code(),
+            broker_epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(-1)
+        }
+    }
+}
+
+impl Decoder for BrokerRegistrationResponseBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 14 {
+            src.reserve(14);
+            return Ok(None);
+        }
+        let throttle_time = src.get_i32();
+        let error_code = src.get_i16();
+        let broker_epoch = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            throttle_time,
+            error_code,
+            broker_epoch,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
@@ -0,0 +1,58 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// CreateAcls Response (Version: 2-3) => throttle_time_ms [results] TAG_BUFFER
+///
+/// Holds a result for every creation, in the order of the request.
+#[derive(Debug, WireLen, Encoder)]
+pub struct CreateAclsResponseBody {
+    pub throttle_time: i32,
+    pub results: CompactArray<AclCreationResult>,
+    tag_buffer: TagBuf,
+}
+
+impl CreateAclsResponseBody {
+    pub fn new(throttle_time: i32, results: CompactArray<AclCreationResult>) -> Self {
+        Self {
+            throttle_time,
+            results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// results => error_code error_message TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AclCreationResult {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl AclCreationResult {
+    pub fn new() -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Default for AclCreationResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
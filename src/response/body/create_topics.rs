@@ -0,0 +1,111 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{Bool, CompactArray, CompactNullableString, CompactString, Uuid},
+    types::{ErrorCode, TagBuf},
+};
+
+/// CreateTopics Response (Version: 7) => throttle_time_ms [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct CreateTopicsResponseBody {
+    pub throttle_time: i32,
+    pub topics: CompactArray<CreatableTopicResult>,
+    tag_buffer: TagBuf,
+}
+
+impl CreateTopicsResponseBody {
+    pub fn new(topics: CompactArray<CreatableTopicResult>) -> Self {
+        Self {
+            throttle_time: 0,
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// The response failing every topic of `names` with `error_code`
+    pub fn error<'a>(names: impl IntoIterator<Item = &'a str>, error_code: ErrorCode) -> Self {
+        let topics = names
+            .into_iter()
+            .map(|name| {
+                let message = format!("Creating topic {name} failed: {error_code}");
+                CreatableTopicResult::error(name, error_code, message)
+            })
+            .collect::<Vec<_>>();
+        Self::new(topics.into())
+    }
+}
+
+/// topics => name topic_id error_code error_message num_partitions replication_factor [configs] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct CreatableTopicResult {
+    pub name: CompactString,
+    pub topic_id: Uuid,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub configs: CompactArray<CreatableTopicConfigs>,
+    tag_buffer: TagBuf,
+}
+
+impl CreatableTopicResult {
+    pub fn new(
+        name: impl Into<String>,
+        topic_id: Uuid,
+        num_partitions: i32,
+        replication_factor: i16,
+        configs: CompactArray<CreatableTopicConfigs>,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            topic_id,
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            num_partitions,
+            replication_factor,
+            configs,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        name: impl Into<String>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            topic_id: Uuid::ZERO,
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            num_partitions: -1,
+            replication_factor: -1,
+            configs: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// configs => name value read_only config_source is_sensitive TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct CreatableTopicConfigs {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub read_only: Bool,
+    pub config_source: i8,
+    pub is_sensitive: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl CreatableTopicConfigs {
+    pub fn new(name: impl Into<String>, value: impl Into<String>, config_source: i8) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            value: CompactNullableString(Some(value.into())),
+            read_only: Bool::False,
+            config_source,
+            is_sensitive: Bool::False,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DeleteAcls Response (Version: 2-3) => throttle_time_ms [filter_results] TAG_BUFFER
+///
+/// Holds a result for every filter, in the order of the request.
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteAclsResponseBody {
+    pub throttle_time: i32,
+    pub filter_results: CompactArray<DeleteAclsFilterResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteAclsResponseBody {
+    pub fn new(throttle_time: i32, filter_results: CompactArray<DeleteAclsFilterResult>) -> Self {
+        Self {
+            throttle_time,
+            filter_results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// filter_results => error_code error_message [matching_acls] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteAclsFilterResult {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub matching_acls: CompactArray<DeleteAclsMatchingAcl>,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteAclsFilterResult {
+    pub fn new(matching_acls: CompactArray<DeleteAclsMatchingAcl>) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            matching_acls,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            matching_acls: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// matching_acls => error_code error_message resource_type resource_name pattern_type principal host operation permission_type TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteAclsMatchingAcl {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub pattern_type: i8,
+    pub principal: CompactString,
+    pub host: CompactString,
+    pub operation: i8,
+    pub permission_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteAclsMatchingAcl {
+    /// A deleted ACL, given by the same fields as in CreateAcls
+    pub fn new(
+        resource_type: i8,
+        resource_name: impl Into<String>,
+        pattern_type: i8,
+        principal: impl Into<String>,
+        host: impl Into<String>,
+        operation: i8,
+        permission_type: i8,
+    ) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            pattern_type,
+            principal: CompactString(principal.into()),
+            host: CompactString(host.into()),
+            operation,
+            permission_type,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
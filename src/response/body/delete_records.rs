@@ -0,0 +1,74 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DeleteRecords Response (Version: 2) => throttle_time_ms [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteRecordsResponseBody {
+    pub throttle_time: i32,
+    pub topics: CompactArray<DeleteRecordsTopicResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteRecordsResponseBody {
+    pub fn new(throttle_time: i32, topics: CompactArray<DeleteRecordsTopicResult>) -> Self {
+        Self {
+            throttle_time,
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteRecordsTopicResult {
+    pub name: CompactString,
+    pub partitions: CompactArray<DeleteRecordsPartitionResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteRecordsTopicResult {
+    pub fn new(
+        name: impl Into<CompactString>,
+        partitions: CompactArray<DeleteRecordsPartitionResult>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition_index low_watermark error_code TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DeleteRecordsPartitionResult {
+    pub partition_index: i32,
+    pub low_watermark: i64,
+    pub error_code: i16,
+    tag_buffer: TagBuf,
+}
+
+impl DeleteRecordsPartitionResult {
+    pub fn new(partition_index: i32, low_watermark: i64) -> Self {
+        Self {
+            partition_index,
+            low_watermark,
+            error_code: ErrorCode::None.code(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(partition_index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            partition_index,
+            low_watermark: -1,
+            error_code: error_code.code(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
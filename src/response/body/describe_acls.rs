@@ -0,0 +1,92 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeAcls Response (Version: 2-3) => throttle_time_ms error_code error_message [resources] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeAclsResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub resources: CompactArray<DescribeAclsResource>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeAclsResponseBody {
+    pub fn new(throttle_time: i32, resources: CompactArray<DescribeAclsResource>) -> Self {
+        Self {
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            resources,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(throttle_time: i32, error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            throttle_time,
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            resources: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// resources => resource_type resource_name pattern_type [acls] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeAclsResource {
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub pattern_type: i8,
+    pub acls: CompactArray<AclDescription>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeAclsResource {
+    pub fn new(
+        resource_type: i8,
+        resource_name: impl Into<String>,
+        pattern_type: i8,
+        acls: CompactArray<AclDescription>,
+    ) -> Self {
+        Self {
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            pattern_type,
+            acls,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// acls => principal host operation permission_type TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AclDescription {
+    pub principal: CompactString,
+    pub host: CompactString,
+    pub operation: i8,
+    pub permission_type: i8,
+    tag_buffer: TagBuf,
+}
+
+impl AclDescription {
+    pub fn new(
+        principal: impl Into<String>,
+        host: impl Into<String>,
+        operation: i8,
+        permission_type: i8,
+    ) -> Self {
+        Self {
+            principal: CompactString(principal.into()),
+            host: CompactString(host.into()),
+            operation,
+            permission_type,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ClientQuotaEntityData, ErrorCode, TagBuf},
+};
+
+/// DescribeClientQuotas Response (Version: 1) => throttle_time_ms error_code error_message [entries] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeClientQuotasResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub entries: CompactArray<DescribeClientQuotasEntry>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClientQuotasResponseBody {
+    pub fn new(throttle_time: i32, entries: CompactArray<DescribeClientQuotasEntry>) -> Self {
+        Self {
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            entries,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(throttle_time: i32, error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            throttle_time,
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            entries: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// entries => [entity] [values] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeClientQuotasEntry {
+    pub entity: CompactArray<ClientQuotaEntityData>,
+    pub values: CompactArray<ClientQuotaValue>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClientQuotasEntry {
+    pub fn new(
+        entity: CompactArray<ClientQuotaEntityData>,
+        values: CompactArray<ClientQuotaValue>,
+    ) -> Self {
+        Self {
+            entity,
+            values,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// values => key value TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ClientQuotaValue {
+    pub key: CompactString,
+    pub value: f64,
+    tag_buffer: TagBuf,
+}
+
+impl ClientQuotaValue {
+    pub fn new(key: impl Into<CompactString>, value: f64) -> Self {
+        Self {
+            key: key.into(),
+            value,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use bytes::{BufMut, BytesMut};
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Encoder, WireLen},
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeCluster Response (Version: 0-1) => throttle_time_ms error_code error_message endpoint_type cluster_id controller_id [brokers] cluster_authorized_operations TAG_BUFFER
+///
+/// Version 1 adds `endpoint_type`.
+#[derive(Debug)]
+pub struct DescribeClusterResponseBody {
+    version: i16,
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    /// Whether `brokers` holds the brokers or the controllers
+    pub endpoint_type: i8,
+    pub cluster_id: CompactString,
+    /// -1 if there is no active controller
+    pub controller_id: i32,
+    pub brokers: CompactArray<DescribeClusterBroker>,
+    /// `i32::MIN` unless the request asked for them
+    pub cluster_authorized_operations: i32,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClusterResponseBody {
+    pub fn new(
+        version: i16,
+        throttle_time: i32,
+        endpoint_type: i8,
+        cluster_id: impl Into<String>,
+        controller_id: i32,
+        brokers: CompactArray<DescribeClusterBroker>,
+        cluster_authorized_operations: i32,
+    ) -> Self {
+        Self {
+            version,
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            endpoint_type,
+            cluster_id: CompactString(cluster_id.into()),
+            controller_id,
+            brokers,
+            cluster_authorized_operations,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        version: i16,
+        throttle_time: i32,
+        endpoint_type: i8,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            ..Self::new(
+                version,
+                throttle_time,
+                endpoint_type,
+                "",
+                -1,
+                CompactArray::new(),
+                i32::MIN,
+            )
+        }
+    }
+}
+
+impl WireLen for DescribeClusterResponseBody {
+    fn wire_len(&self) -> usize {
+        let endpoint_type = if self.version >= 1 {
+            size_of::<i8>()
+        } else {
+            0
+        };
+        size_of::<i32>()
+            + size_of::<i16>()
+            + self.error_message.wire_len()
+            + endpoint_type
+            + self.cluster_id.wire_len()
+            + size_of::<i32>()
+            + self.brokers.wire_len()
+            + size_of::<i32>()
+            + self.tag_buffer.wire_len()
+    }
+}
+
+impl Encoder for DescribeClusterResponseBody {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        dest.put_i32(self.throttle_time);
+        dest.put_i16(self.error_code);
+        self.error_message.encode(dest)?;
+        if self.version >= 1 {
+            dest.put_i8(self.endpoint_type);
+        }
+        self.cluster_id.encode(dest)?;
+        dest.put_i32(self.controller_id);
+        self.brokers.encode(dest)?;
+        dest.put_i32(self.cluster_authorized_operations);
+        self.tag_buffer.encode(dest)
+    }
+}
+
+/// brokers => broker_id host port rack TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeClusterBroker {
+    pub broker_id: i32,
+    pub host: CompactString,
+    pub port: i32,
+    pub rack: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeClusterBroker {
+    pub fn new(broker_id: i32, host: impl Into<String>, port: i32, rack: Option<String>) -> Self {
+        Self {
+            broker_id,
+            host: CompactString(host.into()),
+            port,
+            rack: CompactNullableString(rack),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{Bool, CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeConfigs Response (Version: 4) => throttle_time_ms [results] TAG_BUFFER
+///
+/// Holds a result for every resource, in the order of the request.
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeConfigsResponseBody {
+    pub throttle_time: i32,
+    pub results: CompactArray<DescribeConfigsResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeConfigsResponseBody {
+    pub fn new(throttle_time: i32, results: CompactArray<DescribeConfigsResult>) -> Self {
+        Self {
+            throttle_time,
+            results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// results => error_code error_message resource_type resource_name [configs] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeConfigsResult {
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub configs: CompactArray<DescribeConfigsResourceResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeConfigsResult {
+    pub fn new(
+        resource_type: i8,
+        resource_name: impl Into<String>,
+        configs: CompactArray<DescribeConfigsResourceResult>,
+    ) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            configs,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        resource_type: i8,
+        resource_name: impl Into<String>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            resource_type,
+            resource_name: CompactString(resource_name.into()),
+            configs: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// configs => name value read_only config_source is_sensitive [synonyms] config_type documentation TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeConfigsResourceResult {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub read_only: Bool,
+    pub config_source: i8,
+    pub is_sensitive: Bool,
+    pub synonyms: CompactArray<DescribeConfigsSynonym>,
+    pub config_type: i8,
+    pub documentation: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeConfigsResourceResult {
+    /// A config that can be altered and is not sensitive, along with its synonyms and
+    /// documentation if they were asked for
+    pub fn new(
+        name: impl Into<String>,
+        value: impl Into<String>,
+        config_source: i8,
+        synonyms: CompactArray<DescribeConfigsSynonym>,
+        config_type: i8,
+        documentation: Option<String>,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            value: CompactNullableString(Some(value.into())),
+            read_only: Bool::False,
+            config_source,
+            is_sensitive: Bool::False,
+            synonyms,
+            config_type,
+            documentation: CompactNullableString(documentation),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// synonyms => name value source TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeConfigsSynonym {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub source: i8,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeConfigsSynonym {
+    pub fn new(name: impl Into<String>, value: impl Into<String>, source: i8) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            value: CompactNullableString(Some(value.into())),
+            source,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use bytes::{BufMut, BytesMut};
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Encoder, WireLen},
+    primitives::{Bool, CompactArray, CompactString, UVarint},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeLogDirs Response (Version: 2-4) => throttle_time_ms error_code [results] TAG_BUFFER
+///
+/// Version 3 adds the top level `error_code`, version 4 the `total_bytes` and
+/// `usable_bytes` of every result.
+#[derive(Debug)]
+pub struct DescribeLogDirsResponseBody {
+    version: i16,
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub results: CompactArray<DescribeLogDirsResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeLogDirsResponseBody {
+    pub fn new(
+        version: i16,
+        throttle_time: i32,
+        results: CompactArray<DescribeLogDirsResult>,
+    ) -> Self {
+        Self {
+            version,
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request, which versions before 3 can only tell by the lack of results
+    pub fn error(version: i16, throttle_time: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(version, throttle_time, CompactArray::new())
+        }
+    }
+}
+
+impl WireLen for DescribeLogDirsResponseBody {
+    fn wire_len(&self) -> usize {
+        let error_code = if self.version >= 3 {
+            size_of::<i16>()
+        } else {
+            0
+        };
+        // every result carries its sizes from version 4 on
+        let sizes = if self.version >= 4 {
+            2 * size_of::<i64>()
+        } else {
+            0
+        };
+        size_of::<i32>()
+            + error_code
+            + self.results.wire_len()
+            + self.results.len() * sizes
+            + self.tag_buffer.wire_len()
+    }
+}
+
+impl Encoder for DescribeLogDirsResponseBody {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        dest.put_i32(self.throttle_time);
+        if self.version >= 3 {
+            dest.put_i16(self.error_code);
+        }
+        // the same as CompactArray::encode, with the sizes of the version
+        UVarint(self.results.len() as u32 + 1).encode(dest)?;
+        for result in self.results.iter() {
+            dest.put_i16(result.error_code);
+            result.log_dir.encode(dest)?;
+            result.topics.encode(dest)?;
+            if self.version >= 4 {
+                dest.put_i64(result.total_bytes);
+                dest.put_i64(result.usable_bytes);
+            }
+            result.tag_buffer.encode(dest)?;
+        }
+        self.tag_buffer.encode(dest)
+    }
+}
+
+/// results => error_code log_dir [topics] total_bytes usable_bytes TAG_BUFFER
+///
+/// Encoded by [`DescribeLogDirsResponseBody`], its wire length leaves out the sizes
+/// missing before version 4.
+#[derive(Debug)]
+pub struct DescribeLogDirsResult {
+    pub error_code: i16,
+    pub log_dir: CompactString,
+    pub topics: CompactArray<DescribeLogDirsTopic>,
+    /// Size of the file system holding the log dir, -1 if unknown
+    pub total_bytes: i64,
+    /// Bytes still available on that file system, -1 if unknown
+    pub usable_bytes: i64,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeLogDirsResult {
+    pub fn new(
+        log_dir: impl Into<String>,
+        topics: CompactArray<DescribeLogDirsTopic>,
+        total_bytes: i64,
+        usable_bytes: i64,
+    ) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            log_dir: CompactString(log_dir.into()),
+            topics,
+            total_bytes,
+            usable_bytes,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(log_dir: impl Into<String>, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(log_dir, CompactArray::new(), -1, -1)
+        }
+    }
+}
+
+impl WireLen for DescribeLogDirsResult {
+    fn wire_len(&self) -> usize {
+        size_of::<i16>()
+            + self.log_dir.wire_len()
+            + self.topics.wire_len()
+            + self.tag_buffer.wire_len()
+    }
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeLogDirsTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<DescribeLogDirsPartition>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeLogDirsTopic {
+    pub fn new(
+        name: impl Into<String>,
+        partitions: CompactArray<DescribeLogDirsPartition>,
+    ) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition_index partition_size offset_lag is_future_key TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeLogDirsPartition {
+    pub partition_index: i32,
+    pub partition_size: i64,
+    /// How far the log end offset is behind the high watermark, or that of the current
+    /// replica for future ones
+    pub offset_lag: i64,
+    /// Whether this is a future replica, which replaces the current one once it caught up
+    pub is_future_key: Bool,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeLogDirsPartition {
+    pub fn new(
+        partition_index: i32,
+        partition_size: i64,
+        offset_lag: i64,
+        is_future_key: bool,
+    ) -> Self {
+        Self {
+            partition_index,
+            partition_size,
+            offset_lag,
+            is_future_key: is_future_key.into(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
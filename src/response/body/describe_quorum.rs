@@ -0,0 +1,132 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeQuorum Response (Version: 1) => error_code [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumResponseBody {
+    pub error_code: i16,
+    pub topics: CompactArray<DescribeQuorumTopicResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeQuorumResponseBody {
+    pub fn new(topics: CompactArray<DescribeQuorumTopicResponse>) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(CompactArray::new())
+        }
+    }
+}
+
+/// topics => topic_name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumTopicResponse {
+    pub topic_name: CompactString,
+    pub partitions: CompactArray<DescribeQuorumPartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeQuorumTopicResponse {
+    pub fn new(
+        topic_name: impl Into<CompactString>,
+        partitions: CompactArray<DescribeQuorumPartitionResponse>,
+    ) -> Self {
+        Self {
+            topic_name: topic_name.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition_index error_code leader_id leader_epoch high_watermark [current_voters] [observers] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeQuorumPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub high_watermark: i64,
+    pub current_voters: CompactArray<ReplicaState>,
+    pub observers: CompactArray<ReplicaState>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeQuorumPartitionResponse {
+    pub fn new(
+        partition_index: i32,
+        leader_id: i32,
+        leader_epoch: i32,
+        high_watermark: i64,
+        current_voters: CompactArray<ReplicaState>,
+        observers: CompactArray<ReplicaState>,
+    ) -> Self {
+        Self {
+            partition_index,
+            error_code: ErrorCode::None.code(),
+            leader_id,
+            leader_epoch,
+            high_watermark,
+            current_voters,
+            observers,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(partition_index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(
+                partition_index,
+                -1,
+                -1,
+                -1,
+                CompactArray::new(),
+                CompactArray::new(),
+            )
+        }
+    }
+}
+
+/// current_voters => replica_id log_end_offset last_fetch_timestamp last_caught_up_timestamp TAG_BUFFER
+///
+/// The timestamps are in milliseconds since the epoch, -1 if the replica never fetched
+/// or never caught up with the leader.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ReplicaState {
+    pub replica_id: i32,
+    pub log_end_offset: i64,
+    pub last_fetch_timestamp: i64,
+    pub last_caught_up_timestamp: i64,
+    tag_buffer: TagBuf,
+}
+
+impl ReplicaState {
+    pub fn new(
+        replica_id: i32,
+        log_end_offset: i64,
+        last_fetch_timestamp: i64,
+        last_caught_up_timestamp: i64,
+    ) -> Self {
+        Self {
+            replica_id,
+            log_end_offset,
+            last_fetch_timestamp,
+            last_caught_up_timestamp,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -5,56 +5,26 @@ use crate::{
     types::{TagBuf, TopicInResponse},
 };
 
+/// DescribeTopicPartitions Response (Version: 0) => throttle_time_ms [topics] next_cursor TAG_BUFFER
+///
+/// The cursor to continue from is always null, every partition of the requested topics
+/// is returned at once.
 #[derive(Debug, WireLen, Encoder)]
 pub struct DescribeTopicPartitionsResponseBody {
     pub throttle_time: i32,
     pub topics: CompactArray<TopicInResponse>,
-    pub partition_limit: i32,
-    pub cursor: u8,
+    /// -1 for a null cursor
+    pub next_cursor: i8,
     tag_buffer: TagBuf,
 }
 
 impl DescribeTopicPartitionsResponseBody {
-    pub fn new(
-        throttle_time: i32,
-        topics: CompactArray<TopicInResponse>,
-        partition_limit: i32,
-        cursor: u8,
-    ) -> Self {
+    pub fn new(throttle_time: i32, topics: CompactArray<TopicInResponse>) -> Self {
         Self {
             throttle_time,
             topics,
-            partition_limit,
-            cursor,
+            next_cursor: -1,
             tag_buffer: TagBuf::new(),
         }
     }
 }
-
-// impl Encoder for DescribeTopicPartitionsResponseBody {
-//     fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
-//         dest.put_i32(self.throttle_time);
-//         self.topics.encode(dest)?;
-//         dest.put_i32(self.partition_limit);
-//         dest.put_u8(self.cursor);
-//         self.tag_buffer.encode(dest)?;
-//         Ok(())
-//     }
-// }
-
-// #[derive(Debug, WireLen)]
-// struct Cursor {
-//     pub topic_name: CompactString,
-//     pub partition_index: i32,
-//     pub tag_buffer: TagBuf,
-// }
-//
-// impl Cursor {
-//     pub fn new<C: Into<CompactString>>(topic_name: C, partition_index: i32) -> Self {
-//         Self {
-//             topic_name: topic_name.into(),
-//             partition_index,
-//             tag_buffer: TagBuf::new(),
-//         }
-//     }
-// }
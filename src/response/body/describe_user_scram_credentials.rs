@@ -0,0 +1,95 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// DescribeUserScramCredentials Response (Version: 0) => throttle_time_ms error_code error_message [results] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeUserScramCredentialsResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub results: CompactArray<DescribeUserScramCredentialsResult>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeUserScramCredentialsResponseBody {
+    pub fn new(
+        throttle_time: i32,
+        results: CompactArray<DescribeUserScramCredentialsResult>,
+    ) -> Self {
+        Self {
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(throttle_time: i32, error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            throttle_time,
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            results: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// results => user error_code error_message [credential_infos] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct DescribeUserScramCredentialsResult {
+    pub user: CompactString,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub credential_infos: CompactArray<CredentialInfo>,
+    tag_buffer: TagBuf,
+}
+
+impl DescribeUserScramCredentialsResult {
+    pub fn new(user: impl Into<String>, credential_infos: CompactArray<CredentialInfo>) -> Self {
+        Self {
+            user: CompactString(user.into()),
+            error_code: ErrorCode::None.code(),
+            error_message: CompactNullableString::null(),
+            credential_infos,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(
+        user: impl Into<String>,
+        error_code: ErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            user: CompactString(user.into()),
+            error_code: error_code.code(),
+            error_message: CompactNullableString(Some(message.into())),
+            credential_infos: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// credential_infos => mechanism iterations TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct CredentialInfo {
+    pub mechanism: i8,
+    pub iterations: i32,
+    tag_buffer: TagBuf,
+}
+
+impl CredentialInfo {
+    pub fn new(mechanism: i8, iterations: i32) -> Self {
+        Self {
+            mechanism,
+            iterations,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
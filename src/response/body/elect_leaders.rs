@@ -0,0 +1,77 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// ElectLeaders Response (Version: 2) => throttle_time_ms error_code [replica_election_results] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ElectLeadersResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub replica_election_results: CompactArray<ElectLeadersTopicResult>,
+    tag_buffer: TagBuf,
+}
+
+impl ElectLeadersResponseBody {
+    pub fn new(replica_election_results: CompactArray<ElectLeadersTopicResult>) -> Self {
+        Self {
+            throttle_time: 0,
+            error_code: ErrorCode::None.code(),
+            replica_election_results,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            throttle_time: 0,
+            error_code: error_code.code(),
+            replica_election_results: CompactArray::with_capacity(0),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// replica_election_results => topic [partition_result] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ElectLeadersTopicResult {
+    pub topic: CompactString,
+    pub partition_result: CompactArray<ElectLeadersPartitionResult>,
+    tag_buffer: TagBuf,
+}
+
+impl ElectLeadersTopicResult {
+    pub fn new(
+        topic: impl Into<CompactString>,
+        partition_result: CompactArray<ElectLeadersPartitionResult>,
+    ) -> Self {
+        Self {
+            topic: topic.into(),
+            partition_result,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partition_result => partition_id error_code error_message TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ElectLeadersPartitionResult {
+    pub partition_id: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl ElectLeadersPartitionResult {
+    pub fn new(partition_id: i32, error_code: ErrorCode, error_message: Option<String>) -> Self {
+        Self {
+            partition_id,
+            error_code: error_code.code(),
+            error_message: CompactNullableString(error_message),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Decoder, WireLen},
+    primitives::{CompactArray, CompactBytes, CompactString},
+    types::{EpochEndOffset, ErrorCode, LeaderIdAndEpoch, TagBuf},
+    unwrap_decode,
+};
+
+/// Fetch Response (Version: 12) => throttle_time_ms error_code session_id [responses] TAG_BUFFER
+///
+/// Also decoded, by followers reading the responses of their leader. The session id is
+/// always 0, as fetch sessions are not supported.
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub session_id: i32,
+    pub responses: CompactArray<FetchTopicResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchResponseBody {
+    pub fn new(throttle_time: i32, responses: CompactArray<FetchTopicResponse>) -> Self {
+        Self {
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            session_id: 0,
+            responses,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(throttle_time: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(throttle_time, CompactArray::new())
+        }
+    }
+}
+
+/// responses => topic [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchTopicResponse {
+    pub topic: CompactString,
+    pub partitions: CompactArray<FetchPartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchTopicResponse {
+    pub fn new(
+        topic: impl Into<CompactString>,
+        partitions: CompactArray<FetchPartitionResponse>,
+    ) -> Self {
+        Self {
+            topic: topic.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition_index error_code high_watermark last_stable_offset log_start_offset [aborted_transactions] preferred_read_replica records TAG_BUFFER
+///
+/// There are no transactions, so the last stable offset is the high watermark and
+/// nothing was aborted. `records` holds the encoded record batches. Leaders of the KRaft
+/// quorum add the tagged fields `DivergingEpoch` (0) and `CurrentLeader` (1).
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    pub aborted_transactions: CompactArray<AbortedTransaction>,
+    pub preferred_read_replica: i32,
+    pub records: CompactBytes,
+    tag_buffer: TagBuf,
+}
+
+impl FetchPartitionResponse {
+    const DIVERGING_EPOCH_TAG: u32 = 0;
+    const CURRENT_LEADER_TAG: u32 = 1;
+
+    pub fn new(
+        partition_index: i32,
+        high_watermark: i64,
+        log_start_offset: i64,
+        records: Vec<u8>,
+    ) -> Self {
+        Self {
+            partition_index,
+            error_code: ErrorCode::None.code(),
+            high_watermark,
+            last_stable_offset: high_watermark,
+            log_start_offset,
+            aborted_transactions: CompactArray::new(),
+            preferred_read_replica: -1,
+            records: CompactBytes(records),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(partition_index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(partition_index, -1, -1, Vec::new())
+        }
+    }
+
+    /// Tells the follower where its log diverges from the leader's. Has to be set before
+    /// [`Self::with_current_leader`].
+    pub fn with_diverging_epoch(mut self, diverging: &EpochEndOffset) -> anyhow::Result<Self> {
+        self.tag_buffer.push(Self::DIVERGING_EPOCH_TAG, diverging)?;
+        Ok(self)
+    }
+
+    /// Tells the follower who leads the quorum
+    pub fn with_current_leader(mut self, leader: &LeaderIdAndEpoch) -> anyhow::Result<Self> {
+        self.tag_buffer.push(Self::CURRENT_LEADER_TAG, leader)?;
+        Ok(self)
+    }
+
+    pub fn diverging_epoch(&self) -> anyhow::Result<Option<EpochEndOffset>> {
+        self.tag_buffer.get(Self::DIVERGING_EPOCH_TAG)
+    }
+
+    pub fn current_leader(&self) -> anyhow::Result<Option<LeaderIdAndEpoch>> {
+        self.tag_buffer.get(Self::CURRENT_LEADER_TAG)
+    }
+}
+
+/// aborted_transactions => producer_id first_offset TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct AbortedTransaction {
+    pub producer_id: i64,
+    pub first_offset: i64,
+    tag_buffer: TagBuf,
+}
+
+impl Decoder for AbortedTransaction {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 16 {
+            src.reserve(16);
+            return Ok(None);
+        }
+        let producer_id = src.get_i64();
+        let first_offset = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            producer_id,
+            first_offset,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchPartitionResponse {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 30 {
+            src.reserve(30);
+            return Ok(None);
+        }
+        let partition_index = src.get_i32();
+        let error_code = src.get_i16();
+        let high_watermark = src.get_i64();
+        let last_stable_offset = src.get_i64();
+        let log_start_offset = src.get_i64();
+        let aborted_transactions = unwrap_decode!(CompactArray::decode(src, None));
+        if src.remaining() < 4 {
+            src.reserve(4);
+            return Ok(None);
+        }
+        let preferred_read_replica = src.get_i32();
+        let records = unwrap_decode!(CompactBytes::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            partition_index,
+            error_code,
+            high_watermark,
+            last_stable_offset,
+            log_start_offset,
+            aborted_transactions,
+            preferred_read_replica,
+            records,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchTopicResponse {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let topic = unwrap_decode!(CompactString::decode(src, None));
+        let partitions = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            topic,
+            partitions,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for FetchResponseBody {
+    fn decode(src: &mut bytes::BytesMut, size: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if let Some(sz) = size {
+            if src.remaining() < sz {
+                src.reserve(sz);
+                return Ok(None);
+            }
+        }
+        if src.remaining() < 10 {
+            src.reserve(10);
+            return Ok(None);
+        }
+        let throttle_time = src.get_i32();
+        let error_code = src.get_i16();
+        let session_id = src.get_i32();
+        let responses = unwrap_decode!(CompactArray::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        let body = Self {
+            throttle_time,
+            error_code,
+            session_id,
+            responses,
+            tag_buffer,
+        };
+
+        if let Some(sz) = size {
+            let wl = body.wire_len();
+            anyhow::ensure!(
+                sz == wl,
+                "Size of body does not meet expectations, got: {wl}, expected: {sz}"
+            );
+        }
+
+        Ok(Some(body))
+    }
+}
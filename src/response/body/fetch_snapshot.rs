@@ -0,0 +1,106 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactBytes, CompactString},
+    types::{ErrorCode, LeaderIdAndEpoch, SnapshotId, TagBuf},
+};
+
+/// FetchSnapshot Response (Version: 0) => throttle_time_ms error_code [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotResponseBody {
+    pub throttle_time: i32,
+    pub error_code: i16,
+    pub topics: CompactArray<FetchSnapshotTopicResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchSnapshotResponseBody {
+    pub fn new(throttle_time: i32, topics: CompactArray<FetchSnapshotTopicResponse>) -> Self {
+        Self {
+            throttle_time,
+            error_code: ErrorCode::None.code(),
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(throttle_time: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(throttle_time, CompactArray::new())
+        }
+    }
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotTopicResponse {
+    pub name: CompactString,
+    pub partitions: CompactArray<FetchSnapshotPartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl FetchSnapshotTopicResponse {
+    pub fn new(
+        name: impl Into<CompactString>,
+        partitions: CompactArray<FetchSnapshotPartitionResponse>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => index error_code snapshot_id size position unaligned_records TAG_BUFFER
+///
+/// `size` is the size of the whole snapshot, `unaligned_records` the chunk of it starting
+/// at `position`, which may end in the middle of a batch. The leader adds the
+/// `CurrentLeader` tagged field (0).
+#[derive(Debug, WireLen, Encoder)]
+pub struct FetchSnapshotPartitionResponse {
+    pub index: i32,
+    pub error_code: i16,
+    pub snapshot_id: SnapshotId,
+    pub size: i64,
+    pub position: i64,
+    pub unaligned_records: CompactBytes,
+    tag_buffer: TagBuf,
+}
+
+impl FetchSnapshotPartitionResponse {
+    const CURRENT_LEADER_TAG: u32 = 0;
+
+    pub fn new(
+        index: i32,
+        snapshot_id: SnapshotId,
+        size: i64,
+        position: i64,
+        unaligned_records: Vec<u8>,
+    ) -> Self {
+        Self {
+            index,
+            error_code: ErrorCode::None.code(),
+            snapshot_id,
+            size,
+            position,
+            unaligned_records: CompactBytes(unaligned_records),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(index, SnapshotId::new(-1, -1), -1, -1, Vec::new())
+        }
+    }
+
+    /// Tells the follower who leads the quorum
+    pub fn with_current_leader(mut self, leader: &LeaderIdAndEpoch) -> anyhow::Result<Self> {
+        self.tag_buffer.push(Self::CURRENT_LEADER_TAG, leader)?;
+        Ok(self)
+    }
+}
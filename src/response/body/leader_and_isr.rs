@@ -0,0 +1,56 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// LeaderAndIsr Response (Version: 4) => error_code [partition_errors] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrResponseBody {
+    pub error_code: i16,
+    pub partition_errors: CompactArray<LeaderAndIsrPartitionError>,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrResponseBody {
+    pub fn new(partition_errors: CompactArray<LeaderAndIsrPartitionError>) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            partition_errors,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// Fails the whole request
+    pub fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code: error_code.code(),
+            ..Self::new(CompactArray::new())
+        }
+    }
+}
+
+/// partition_errors => topic_name partition_index error_code TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct LeaderAndIsrPartitionError {
+    pub topic_name: CompactString,
+    pub partition_index: i32,
+    pub error_code: i16,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderAndIsrPartitionError {
+    pub fn new(
+        topic_name: impl Into<CompactString>,
+        partition_index: i32,
+        error_code: ErrorCode,
+    ) -> Self {
+        Self {
+            topic_name: topic_name.into(),
+            partition_index,
+            error_code: error_code.code(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -2,20 +2,332 @@ use crate::codec::{Encoder, WireLen};
 use bytes::BytesMut;
 
 use super::{
-    ApiVersionsResponseBody, describe_topic_partitions::DescribeTopicPartitionsResponseBody,
+    AlterClientQuotasResponseBody, AlterConfigsResponseBody, AlterUserScramCredentialsResponseBody,
+    ApiVersionsResponseBody, BeginQuorumEpochResponseBody, BrokerHeartbeatResponseBody,
+    BrokerRegistrationResponseBody, CreateAclsResponseBody, CreateTopicsResponseBody,
+    DeleteAclsResponseBody, DeleteRecordsResponseBody, DescribeAclsResponseBody,
+    ElectLeadersResponseBody,
+    DescribeClientQuotasResponseBody, DescribeClusterResponseBody, DescribeConfigsResponseBody,
+    DescribeLogDirsResponseBody, DescribeQuorumResponseBody,
+    DescribeUserScramCredentialsResponseBody, FetchResponseBody, FetchSnapshotResponseBody,
+    IncrementalAlterConfigsResponseBody, LeaderAndIsrResponseBody, ListOffsetsResponseBody,
+    MetadataResponseBody, ProduceResponseBody,
+    SaslAuthenticateResponseBody, SaslHandshakeResponseBody, VoteResponseBody,
+    describe_topic_partitions::DescribeTopicPartitionsResponseBody,
 };
 
 #[derive(Debug)]
 pub enum ResponseBody {
+    Produce(ProduceResponseBody),
+    Fetch(FetchResponseBody),
+    ListOffsets(ListOffsetsResponseBody),
+    Metadata(MetadataResponseBody),
+    LeaderAndIsr(LeaderAndIsrResponseBody),
     ApiVersions(ApiVersionsResponseBody),
+    DeleteRecords(DeleteRecordsResponseBody),
+    CreateTopics(CreateTopicsResponseBody),
+    ElectLeaders(ElectLeadersResponseBody),
+    DescribeAcls(DescribeAclsResponseBody),
+    CreateAcls(CreateAclsResponseBody),
+    DeleteAcls(DeleteAclsResponseBody),
+    DescribeConfigs(DescribeConfigsResponseBody),
+    AlterConfigs(AlterConfigsResponseBody),
+    IncrementalAlterConfigs(IncrementalAlterConfigsResponseBody),
+    DescribeLogDirs(DescribeLogDirsResponseBody),
+    DescribeClientQuotas(DescribeClientQuotasResponseBody),
+    AlterClientQuotas(AlterClientQuotasResponseBody),
+    DescribeUserScramCredentials(DescribeUserScramCredentialsResponseBody),
+    AlterUserScramCredentials(AlterUserScramCredentialsResponseBody),
+    Vote(VoteResponseBody),
+    BeginQuorumEpoch(BeginQuorumEpochResponseBody),
+    EndQuorumEpoch(BeginQuorumEpochResponseBody),
+    DescribeQuorum(DescribeQuorumResponseBody),
+    FetchSnapshot(FetchSnapshotResponseBody),
+    DescribeCluster(DescribeClusterResponseBody),
+    BrokerRegistration(BrokerRegistrationResponseBody),
+    BrokerHeartbeat(BrokerHeartbeatResponseBody),
     DescribeTopicPartitions(DescribeTopicPartitionsResponseBody),
+    SaslHandshake(SaslHandshakeResponseBody),
+    SaslAuthenticate(SaslAuthenticateResponseBody),
+}
+
+impl ResponseBody {
+    /// The top level error of the response, or else the first error of one of its
+    /// topics or partitions. 0 if nothing failed.
+    pub fn error_code(&self) -> i16 {
+        match self {
+            ResponseBody::Produce(body) => body
+                .responses
+                .iter()
+                .flat_map(|t| t.partition_responses.iter())
+                .map(|p| p.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::Fetch(body) => std::iter::once(body.error_code)
+                .chain(
+                    body.responses
+                        .iter()
+                        .flat_map(|t| t.partitions.iter())
+                        .map(|p| p.error_code),
+                )
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::LeaderAndIsr(body) => std::iter::once(body.error_code)
+                .chain(body.partition_errors.iter().map(|p| p.error_code))
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::ApiVersions(body) => body.error_code as i16,
+            ResponseBody::ListOffsets(body) => body
+                .topics
+                .iter()
+                .flat_map(|t| t.partitions.iter())
+                .map(|p| p.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::Metadata(body) => body
+                .topics
+                .iter()
+                .flat_map(|t| {
+                    std::iter::once(t.error_code).chain(t.partitions.iter().map(|p| p.error_code))
+                })
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DeleteRecords(body) => body
+                .topics
+                .iter()
+                .flat_map(|t| t.partitions.iter())
+                .map(|p| p.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::CreateTopics(body) => body
+                .topics
+                .iter()
+                .map(|t| t.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::ElectLeaders(body) => std::iter::once(body.error_code)
+                .chain(
+                    body.replica_election_results
+                        .iter()
+                        .flat_map(|t| t.partition_result.iter())
+                        .map(|p| p.error_code),
+                )
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeAcls(body) => body.error_code,
+            ResponseBody::CreateAcls(body) => body
+                .results
+                .iter()
+                .map(|r| r.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DeleteAcls(body) => body
+                .filter_results
+                .iter()
+                .map(|r| r.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeConfigs(body) => body
+                .results
+                .iter()
+                .map(|r| r.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::AlterConfigs(body) | ResponseBody::IncrementalAlterConfigs(body) => body
+                .responses
+                .iter()
+                .map(|r| r.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeLogDirs(body) => std::iter::once(body.error_code)
+                .chain(body.results.iter().map(|r| r.error_code))
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeClientQuotas(body) => body.error_code,
+            ResponseBody::AlterClientQuotas(body) => body
+                .entries
+                .iter()
+                .map(|e| e.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeUserScramCredentials(body) => std::iter::once(body.error_code)
+                .chain(body.results.iter().map(|r| r.error_code))
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::AlterUserScramCredentials(body) => body
+                .results
+                .iter()
+                .map(|r| r.error_code)
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::Vote(body) => std::iter::once(body.error_code)
+                .chain(
+                    body.topics
+                        .iter()
+                        .flat_map(|t| t.partitions.iter())
+                        .map(|p| p.error_code),
+                )
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::BeginQuorumEpoch(body) | ResponseBody::EndQuorumEpoch(body) => {
+                std::iter::once(body.error_code)
+                    .chain(
+                        body.topics
+                            .iter()
+                            .flat_map(|t| t.partitions.iter())
+                            .map(|p| p.error_code),
+                    )
+                    .find(|&code| code != 0)
+                    .unwrap_or_default()
+            }
+            ResponseBody::DescribeQuorum(body) => std::iter::once(body.error_code)
+                .chain(
+                    body.topics
+                        .iter()
+                        .flat_map(|t| t.partitions.iter())
+                        .map(|p| p.error_code),
+                )
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::FetchSnapshot(body) => std::iter::once(body.error_code)
+                .chain(
+                    body.topics
+                        .iter()
+                        .flat_map(|t| t.partitions.iter())
+                        .map(|p| p.error_code),
+                )
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::DescribeCluster(body) => body.error_code,
+            ResponseBody::BrokerRegistration(body) => body.error_code,
+            ResponseBody::BrokerHeartbeat(body) => body.error_code,
+            ResponseBody::DescribeTopicPartitions(body) => body
+                .topics
+                .iter()
+                .map(|t| t.error_code())
+                .find(|&code| code != 0)
+                .unwrap_or_default(),
+            ResponseBody::SaslHandshake(body) => body.error_code,
+            ResponseBody::SaslAuthenticate(body) => body.error_code,
+        }
+    }
+
+    pub fn throttle_time_ms(&self) -> i32 {
+        match self {
+            ResponseBody::Produce(body) => body.throttle_time,
+            ResponseBody::Fetch(body) => body.throttle_time,
+            ResponseBody::ApiVersions(body) => body.throttle_time as i32,
+            ResponseBody::ListOffsets(body) => body.throttle_time,
+            ResponseBody::Metadata(body) => body.throttle_time,
+            ResponseBody::DeleteRecords(body) => body.throttle_time,
+            ResponseBody::CreateTopics(body) => body.throttle_time,
+            ResponseBody::ElectLeaders(body) => body.throttle_time,
+            ResponseBody::DescribeAcls(body) => body.throttle_time,
+            ResponseBody::CreateAcls(body) => body.throttle_time,
+            ResponseBody::DeleteAcls(body) => body.throttle_time,
+            ResponseBody::DescribeConfigs(body) => body.throttle_time,
+            ResponseBody::AlterConfigs(body) | ResponseBody::IncrementalAlterConfigs(body) => {
+                body.throttle_time
+            }
+            ResponseBody::DescribeLogDirs(body) => body.throttle_time,
+            ResponseBody::DescribeClientQuotas(body) => body.throttle_time,
+            ResponseBody::AlterClientQuotas(body) => body.throttle_time,
+            ResponseBody::DescribeUserScramCredentials(body) => body.throttle_time,
+            ResponseBody::AlterUserScramCredentials(body) => body.throttle_time,
+            ResponseBody::FetchSnapshot(body) => body.throttle_time,
+            ResponseBody::DescribeCluster(body) => body.throttle_time,
+            ResponseBody::BrokerRegistration(body) => body.throttle_time,
+            ResponseBody::BrokerHeartbeat(body) => body.throttle_time,
+            ResponseBody::DescribeTopicPartitions(body) => body.throttle_time,
+            // these bodies have no throttle_time_ms field
+            ResponseBody::LeaderAndIsr(_)
+            | ResponseBody::Vote(_)
+            | ResponseBody::BeginQuorumEpoch(_)
+            | ResponseBody::EndQuorumEpoch(_)
+            | ResponseBody::DescribeQuorum(_)
+            | ResponseBody::SaslHandshake(_)
+            | ResponseBody::SaslAuthenticate(_) => 0,
+        }
+    }
+
+    /// Tells the client how long it is throttled for. Its size on the wire is fixed,
+    /// so this can be set after the response's size was computed.
+    pub fn set_throttle_time_ms(&mut self, throttle_time_ms: i32) {
+        match self {
+            ResponseBody::Produce(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::Fetch(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::ApiVersions(body) => body.throttle_time = throttle_time_ms as u32,
+            ResponseBody::ListOffsets(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::Metadata(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DeleteRecords(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::CreateTopics(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::ElectLeaders(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeAcls(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::CreateAcls(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DeleteAcls(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeConfigs(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::AlterConfigs(body) | ResponseBody::IncrementalAlterConfigs(body) => {
+                body.throttle_time = throttle_time_ms;
+            }
+            ResponseBody::DescribeLogDirs(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeClientQuotas(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::AlterClientQuotas(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeUserScramCredentials(body) => {
+                body.throttle_time = throttle_time_ms;
+            }
+            ResponseBody::AlterUserScramCredentials(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::FetchSnapshot(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeCluster(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::BrokerRegistration(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::BrokerHeartbeat(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::DescribeTopicPartitions(body) => body.throttle_time = throttle_time_ms,
+            ResponseBody::LeaderAndIsr(_)
+            | ResponseBody::Vote(_)
+            | ResponseBody::BeginQuorumEpoch(_)
+            | ResponseBody::EndQuorumEpoch(_)
+            | ResponseBody::DescribeQuorum(_)
+            | ResponseBody::SaslHandshake(_)
+            | ResponseBody::SaslAuthenticate(_) => {}
+        }
+    }
 }
 
 impl WireLen for ResponseBody {
     fn wire_len(&self) -> usize {
         match self {
+            ResponseBody::Produce(body) => body.wire_len(),
+            ResponseBody::Fetch(body) => body.wire_len(),
+            ResponseBody::LeaderAndIsr(body) => body.wire_len(),
             ResponseBody::ApiVersions(body) => body.wire_len(),
+            ResponseBody::ListOffsets(body) => body.wire_len(),
+            ResponseBody::Metadata(body) => body.wire_len(),
+            ResponseBody::DeleteRecords(body) => body.wire_len(),
+            ResponseBody::CreateTopics(body) => body.wire_len(),
+            ResponseBody::ElectLeaders(body) => body.wire_len(),
+            ResponseBody::DescribeAcls(body) => body.wire_len(),
+            ResponseBody::CreateAcls(body) => body.wire_len(),
+            ResponseBody::DeleteAcls(body) => body.wire_len(),
+            ResponseBody::DescribeConfigs(body) => body.wire_len(),
+            ResponseBody::AlterConfigs(body) | ResponseBody::IncrementalAlterConfigs(body) => {
+                body.wire_len()
+            }
+            ResponseBody::DescribeLogDirs(body) => body.wire_len(),
+            ResponseBody::DescribeClientQuotas(body) => body.wire_len(),
+            ResponseBody::AlterClientQuotas(body) => body.wire_len(),
+            ResponseBody::DescribeUserScramCredentials(body) => body.wire_len(),
+            ResponseBody::AlterUserScramCredentials(body) => body.wire_len(),
+            ResponseBody::Vote(body) => body.wire_len(),
+            ResponseBody::BeginQuorumEpoch(body) | ResponseBody::EndQuorumEpoch(body) => {
+                body.wire_len()
+            }
+            ResponseBody::DescribeQuorum(body) => body.wire_len(),
+            ResponseBody::FetchSnapshot(body) => body.wire_len(),
+            ResponseBody::DescribeCluster(body) => body.wire_len(),
+            ResponseBody::BrokerRegistration(body) => body.wire_len(),
+            ResponseBody::BrokerHeartbeat(body) => body.wire_len(),
             ResponseBody::DescribeTopicPartitions(body) => body.wire_len(),
+            ResponseBody::SaslHandshake(body) => body.wire_len(),
+            ResponseBody::SaslAuthenticate(body) => body.wire_len(),
         }
     }
 }
@@ -23,8 +335,39 @@ impl WireLen for ResponseBody {
 impl Encoder for ResponseBody {
     fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
         match self {
+            ResponseBody::Produce(body) => body.encode(dest),
+            ResponseBody::Fetch(body) => body.encode(dest),
+            ResponseBody::LeaderAndIsr(body) => body.encode(dest),
             ResponseBody::ApiVersions(body) => body.encode(dest),
+            ResponseBody::ListOffsets(body) => body.encode(dest),
+            ResponseBody::Metadata(body) => body.encode(dest),
+            ResponseBody::DeleteRecords(body) => body.encode(dest),
+            ResponseBody::CreateTopics(body) => body.encode(dest),
+            ResponseBody::ElectLeaders(body) => body.encode(dest),
+            ResponseBody::DescribeAcls(body) => body.encode(dest),
+            ResponseBody::CreateAcls(body) => body.encode(dest),
+            ResponseBody::DeleteAcls(body) => body.encode(dest),
+            ResponseBody::DescribeConfigs(body) => body.encode(dest),
+            ResponseBody::AlterConfigs(body) | ResponseBody::IncrementalAlterConfigs(body) => {
+                body.encode(dest)
+            }
+            ResponseBody::DescribeLogDirs(body) => body.encode(dest),
+            ResponseBody::DescribeClientQuotas(body) => body.encode(dest),
+            ResponseBody::AlterClientQuotas(body) => body.encode(dest),
+            ResponseBody::DescribeUserScramCredentials(body) => body.encode(dest),
+            ResponseBody::AlterUserScramCredentials(body) => body.encode(dest),
+            ResponseBody::Vote(body) => body.encode(dest),
+            ResponseBody::BeginQuorumEpoch(body) | ResponseBody::EndQuorumEpoch(body) => {
+                body.encode(dest)
+            }
+            ResponseBody::DescribeQuorum(body) => body.encode(dest),
+            ResponseBody::FetchSnapshot(body) => body.encode(dest),
+            ResponseBody::DescribeCluster(body) => body.encode(dest),
+            ResponseBody::BrokerRegistration(body) => body.encode(dest),
+            ResponseBody::BrokerHeartbeat(body) => body.encode(dest),
             ResponseBody::DescribeTopicPartitions(body) => body.encode(dest),
+            ResponseBody::SaslHandshake(body) => body.encode(dest),
+            ResponseBody::SaslAuthenticate(body) => body.encode(dest),
         }
     }
 }
@@ -0,0 +1,82 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// ListOffsets Response (Version: 6) => throttle_time_ms [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ListOffsetsResponseBody {
+    pub throttle_time: i32,
+    pub topics: CompactArray<ListOffsetsTopicResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl ListOffsetsResponseBody {
+    pub fn new(throttle_time: i32, topics: CompactArray<ListOffsetsTopicResponse>) -> Self {
+        Self {
+            throttle_time,
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => name [partitions] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ListOffsetsTopicResponse {
+    pub name: CompactString,
+    pub partitions: CompactArray<ListOffsetsPartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl ListOffsetsTopicResponse {
+    pub fn new(
+        name: impl Into<CompactString>,
+        partitions: CompactArray<ListOffsetsPartitionResponse>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            partitions,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partitions => partition_index error_code timestamp offset leader_epoch TAG_BUFFER
+///
+/// Lookups that found nothing answer -1 for the timestamp, the offset and the epoch.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ListOffsetsPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub timestamp: i64,
+    pub offset: i64,
+    pub leader_epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl ListOffsetsPartitionResponse {
+    pub fn new(partition_index: i32, timestamp: i64, offset: i64, leader_epoch: i32) -> Self {
+        Self {
+            partition_index,
+            error_code: ErrorCode::None.code(),
+            timestamp,
+            offset,
+            leader_epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(partition_index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            partition_index,
+            error_code: error_code.code(),
+            timestamp: -1,
+            offset: -1,
+            leader_epoch: -1,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
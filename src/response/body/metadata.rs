@@ -0,0 +1,137 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{Bool, CompactArray, CompactNullableString, CompactString, Uuid},
+    types::{ErrorCode, TagBuf},
+};
+
+/// Metadata Response (Version: 12) => throttle_time_ms [brokers] cluster_id controller_id [topics] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct MetadataResponseBody {
+    pub throttle_time: i32,
+    pub brokers: CompactArray<MetadataResponseBroker>,
+    pub cluster_id: CompactNullableString,
+    /// -1 if there is no active controller
+    pub controller_id: i32,
+    pub topics: CompactArray<MetadataResponseTopic>,
+    tag_buffer: TagBuf,
+}
+
+impl MetadataResponseBody {
+    pub fn new(
+        throttle_time: i32,
+        brokers: CompactArray<MetadataResponseBroker>,
+        cluster_id: impl Into<String>,
+        controller_id: i32,
+        topics: CompactArray<MetadataResponseTopic>,
+    ) -> Self {
+        Self {
+            throttle_time,
+            brokers,
+            cluster_id: CompactNullableString(Some(cluster_id.into())),
+            controller_id,
+            topics,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// brokers => node_id host port rack TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct MetadataResponseBroker {
+    pub node_id: i32,
+    pub host: CompactString,
+    pub port: i32,
+    pub rack: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl MetadataResponseBroker {
+    pub fn new(node_id: i32, host: impl Into<String>, port: i32, rack: Option<String>) -> Self {
+        Self {
+            node_id,
+            host: CompactString(host.into()),
+            port,
+            rack: CompactNullableString(rack),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// topics => error_code name topic_id is_internal [partitions] topic_authorized_operations TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct MetadataResponseTopic {
+    pub error_code: i16,
+    pub name: CompactNullableString,
+    pub topic_id: Uuid,
+    pub is_internal: Bool,
+    pub partitions: CompactArray<MetadataResponsePartition>,
+    /// `i32::MIN` unless the request asked for them
+    pub topic_authorized_operations: i32,
+    tag_buffer: TagBuf,
+}
+
+impl MetadataResponseTopic {
+    pub fn new(
+        name: impl Into<String>,
+        topic_id: Uuid,
+        is_internal: bool,
+        partitions: CompactArray<MetadataResponsePartition>,
+        topic_authorized_operations: i32,
+    ) -> Self {
+        Self {
+            error_code: ErrorCode::None.code(),
+            name: CompactNullableString(Some(name.into())),
+            topic_id,
+            is_internal: is_internal.into(),
+            partitions,
+            topic_authorized_operations,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    /// A topic that failed, with the name or the id it was asked for by
+    pub fn error(error_code: ErrorCode, name: Option<String>, topic_id: Uuid) -> Self {
+        Self {
+            error_code: error_code.code(),
+            name: CompactNullableString(name),
+            ..Self::new("", topic_id, false, CompactArray::new(), i32::MIN)
+        }
+    }
+}
+
+/// partitions => error_code partition_index leader_id leader_epoch [replica_nodes] [isr_nodes] [offline_replicas] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct MetadataResponsePartition {
+    pub error_code: i16,
+    pub partition_index: i32,
+    /// -1 with `LEADER_NOT_AVAILABLE` if the leader cannot be reached
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub replica_nodes: CompactArray<i32>,
+    pub isr_nodes: CompactArray<i32>,
+    pub offline_replicas: CompactArray<i32>,
+    tag_buffer: TagBuf,
+}
+
+impl MetadataResponsePartition {
+    pub fn new(
+        error_code: ErrorCode,
+        partition_index: i32,
+        leader_id: i32,
+        leader_epoch: i32,
+        replicas: &[i32],
+        isr: &[i32],
+    ) -> Self {
+        Self {
+            error_code: error_code.code(),
+            partition_index,
+            leader_id,
+            leader_epoch,
+            replica_nodes: replicas.to_vec().into(),
+            isr_nodes: isr.to_vec().into(),
+            offline_replicas: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
@@ -1,7 +1,61 @@
+mod alter_client_quotas;
+mod alter_configs;
+mod alter_user_scram_credentials;
 mod api_versions;
+mod begin_quorum_epoch;
+mod broker_heartbeat;
+mod broker_registration;
+mod create_acls;
+mod create_topics;
+mod delete_acls;
+mod delete_records;
+mod describe_acls;
+mod describe_client_quotas;
+mod describe_cluster;
+mod describe_configs;
+mod describe_log_dirs;
+mod describe_quorum;
 mod describe_topic_partitions;
+mod describe_user_scram_credentials;
+mod elect_leaders;
+mod fetch;
+mod fetch_snapshot;
+mod leader_and_isr;
 mod lib;
+mod list_offsets;
+mod metadata;
+mod produce;
+mod sasl_authenticate;
+mod sasl_handshake;
+mod vote;
 
+pub use alter_client_quotas::*;
+pub use alter_configs::*;
+pub use alter_user_scram_credentials::*;
 pub use api_versions::*;
+pub use begin_quorum_epoch::*;
+pub use broker_heartbeat::*;
+pub use broker_registration::*;
+pub use create_acls::*;
+pub use create_topics::*;
+pub use delete_acls::*;
+pub use delete_records::*;
+pub use describe_acls::*;
+pub use describe_client_quotas::*;
+pub use describe_cluster::*;
+pub use describe_configs::*;
+pub use describe_log_dirs::*;
+pub use describe_quorum::*;
 pub use describe_topic_partitions::*;
+pub use describe_user_scram_credentials::*;
+pub use elect_leaders::*;
+pub use fetch::*;
+pub use fetch_snapshot::*;
+pub use leader_and_isr::*;
 pub use lib::ResponseBody;
+pub use list_offsets::*;
+pub use metadata::*;
+pub use produce::*;
+pub use sasl_authenticate::*;
+pub use sasl_handshake::*;
+pub use vote::*;
@@ -0,0 +1,93 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    primitives::{CompactArray, CompactNullableString, CompactString},
+    types::{ErrorCode, TagBuf},
+};
+
+/// Produce Response (Version: 9) => [responses] throttle_time_ms TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProduceResponseBody {
+    pub responses: CompactArray<ProduceTopicResponse>,
+    pub throttle_time: i32,
+    tag_buffer: TagBuf,
+}
+
+impl ProduceResponseBody {
+    pub fn new(throttle_time: i32, responses: CompactArray<ProduceTopicResponse>) -> Self {
+        Self {
+            responses,
+            throttle_time,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// responses => name [partition_responses] TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProduceTopicResponse {
+    pub name: CompactString,
+    pub partition_responses: CompactArray<ProducePartitionResponse>,
+    tag_buffer: TagBuf,
+}
+
+impl ProduceTopicResponse {
+    pub fn new(
+        name: impl Into<CompactString>,
+        partition_responses: CompactArray<ProducePartitionResponse>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            partition_responses,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// partition_responses => index error_code base_offset log_append_time_ms log_start_offset [record_errors] error_message TAG_BUFFER
+///
+/// Batches keep the timestamps they were produced with, so `log_append_time_ms` is
+/// always -1.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ProducePartitionResponse {
+    pub index: i32,
+    pub error_code: i16,
+    /// Offset of the first appended record
+    pub base_offset: i64,
+    pub log_append_time_ms: i64,
+    pub log_start_offset: i64,
+    pub record_errors: CompactArray<BatchIndexAndErrorMessage>,
+    pub error_message: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl ProducePartitionResponse {
+    pub fn new(index: i32, base_offset: i64, log_start_offset: i64) -> Self {
+        Self {
+            index,
+            error_code: ErrorCode::None.code(),
+            base_offset,
+            log_append_time_ms: -1,
+            log_start_offset,
+            record_errors: CompactArray::new(),
+            error_message: CompactNullableString::null(),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+
+    pub fn error(index: i32, error_code: ErrorCode, error_message: Option<String>) -> Self {
+        Self {
+            error_code: error_code.code(),
+            error_message: CompactNullableString(error_message),
+            ..Self::new(index, -1, -1)
+        }
+    }
+}
+
+/// record_errors => batch_index batch_index_error_message TAG_BUFFER
+#[derive(Debug, WireLen, Encoder)]
+pub struct BatchIndexAndErrorMessage {
+    pub batch_index: i32,
+    pub batch_index_error_message: CompactNullableString,
+    tag_buffer: TagBuf,
+}
@@ -0,0 +1,93 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::{
+    codec::{Encoder, WireLen},
+    primitives::{CompactNullableString, UVarint},
+    types::{ErrorCode, TagBuf},
+};
+
+/// SaslAuthenticate Response (Version: 0-2) => error_code error_message auth_bytes session_lifetime_ms TAG_BUFFER
+///
+/// Version 1 adds `session_lifetime_ms`, version 2 is flexible: its strings and bytes
+/// are compact and it has tagged fields.
+#[derive(Debug)]
+pub struct SaslAuthenticateResponseBody {
+    version: i16,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    /// The server's SASL token, empty once the exchange is over for most mechanisms
+    pub auth_bytes: Vec<u8>,
+    /// How long the client may use the session before it has to re-authenticate,
+    /// 0 if it never has to
+    pub session_lifetime_ms: i64,
+}
+
+impl SaslAuthenticateResponseBody {
+    pub fn new(version: i16, auth_bytes: Vec<u8>, session_lifetime_ms: i64) -> Self {
+        Self {
+            version,
+            error_code: ErrorCode::None.code(),
+            error_message: None,
+            auth_bytes,
+            session_lifetime_ms,
+        }
+    }
+
+    pub fn error(version: i16, error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            version,
+            error_code: error_code.code(),
+            error_message: Some(message.into()),
+            auth_bytes: Vec::new(),
+            session_lifetime_ms: 0,
+        }
+    }
+
+    fn flexible(&self) -> bool {
+        self.version >= 2
+    }
+}
+
+impl WireLen for SaslAuthenticateResponseBody {
+    fn wire_len(&self) -> usize {
+        let message_len = self.error_message.as_ref().map_or(0, String::len);
+        let bytes_len = self.auth_bytes.len();
+        let session_lifetime = if self.version >= 1 { size_of::<i64>() } else { 0 };
+        let rest = if self.flexible() {
+            CompactNullableString(self.error_message.clone()).wire_len()
+                + UVarint::wire_len_of(bytes_len as u32 + 1)
+                + bytes_len
+                + TagBuf::new().wire_len()
+        } else {
+            size_of::<i16>() + message_len + size_of::<i32>() + bytes_len
+        };
+        size_of::<i16>() + rest + session_lifetime
+    }
+}
+
+impl Encoder for SaslAuthenticateResponseBody {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        dest.put_i16(self.error_code);
+        if self.flexible() {
+            CompactNullableString(self.error_message.clone()).encode(dest)?;
+            UVarint(self.auth_bytes.len() as u32 + 1).encode(dest)?;
+        } else {
+            match &self.error_message {
+                Some(message) => {
+                    dest.put_i16(message.len() as i16);
+                    dest.put_slice(message.as_bytes());
+                }
+                None => dest.put_i16(-1),
+            }
+            dest.put_i32(self.auth_bytes.len() as i32);
+        }
+        dest.put_slice(&self.auth_bytes);
+        if self.version >= 1 {
+            dest.put_i64(self.session_lifetime_ms);
+        }
+        if self.flexible() {
+            TagBuf::new().encode(dest)?;
+        }
+        Ok(())
+    }
+}
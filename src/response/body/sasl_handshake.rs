@@ -0,0 +1,49 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::{
+    codec::{Encoder, WireLen},
+    types::ErrorCode,
+};
+
+/// SaslHandshake Response (Version: 1) => error_code [mechanisms]
+///
+/// Never flexible, so the mechanisms are a plain ARRAY of STRING.
+#[derive(Debug)]
+pub struct SaslHandshakeResponseBody {
+    pub error_code: i16,
+    /// The mechanisms enabled on the listener
+    pub mechanisms: Vec<String>,
+}
+
+impl SaslHandshakeResponseBody {
+    pub fn new(error_code: ErrorCode, mechanisms: Vec<String>) -> Self {
+        Self {
+            error_code: error_code.code(),
+            mechanisms,
+        }
+    }
+}
+
+impl WireLen for SaslHandshakeResponseBody {
+    fn wire_len(&self) -> usize {
+        size_of::<i16>()
+            + size_of::<i32>()
+            + self
+                .mechanisms
+                .iter()
+                .map(|m| size_of::<i16>() + m.len())
+                .sum::<usize>()
+    }
+}
+
+impl Encoder for SaslHandshakeResponseBody {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        dest.put_i16(self.error_code);
+        dest.put_i32(self.mechanisms.len() as i32);
+        for mechanism in &self.mechanisms {
+            dest.put_i16(mechanism.len() as i16);
+            dest.put_slice(mechanism.as_bytes());
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{request::KafkaRequest, types::TagBuf};
+use crate::{
+    codec::{Encoder, WireLen},
+    request::KafkaRequest,
+    types::TagBuf,
+};
+use bytes::BytesMut;
 use kafka_macros::{Encoder, WireLen};
 
 #[derive(Debug, WireLen, Encoder)]
@@ -13,7 +18,7 @@ impl ResponseHeaderV0 {
 
     /// Creates a new `ResponseHeaderV0`, with the same correlation id as in the request's header
     /// This is a shorthand for
-    /// ```
+    /// ```ignore
     /// let request = {...};
     /// ResponseHeaderV0::new(request.header.correlation_id)
     /// ```
@@ -35,13 +40,60 @@ impl ResponseHeaderV1 {
         Self { correlation_id, tag_buffer: TagBuf::new() }
     }
 
-    /// Creates a new `ResponseHeaderV0`, with the same correlation id as in the request's header
+    /// Creates a new `ResponseHeaderV1`, with the same correlation id as in the request's header
     /// This is a shorthand for
-    /// ```
+    /// ```ignore
     /// let request = {...};
-    /// ResponseHeaderV0::new(request.header.correlation_id)
+    /// ResponseHeaderV1::new(request.header.correlation_id)
     /// ```
     pub fn respond(request: &KafkaRequest) -> Self {
         Self::new(request.header.correlation_id)
     }
 }
+
+/// Version 0 of the response header is used by non flexible responses,
+/// version 1 adds a tagged fields section for flexible ones.
+#[derive(Debug)]
+pub enum ResponseHeader {
+    V0(ResponseHeaderV0),
+    V1(ResponseHeaderV1),
+}
+
+impl ResponseHeader {
+    pub fn correlation_id(&self) -> i32 {
+        match self {
+            ResponseHeader::V0(h) => h.correlation_id,
+            ResponseHeader::V1(h) => h.correlation_id,
+        }
+    }
+}
+
+impl From<ResponseHeaderV0> for ResponseHeader {
+    fn from(value: ResponseHeaderV0) -> Self {
+        ResponseHeader::V0(value)
+    }
+}
+
+impl From<ResponseHeaderV1> for ResponseHeader {
+    fn from(value: ResponseHeaderV1) -> Self {
+        ResponseHeader::V1(value)
+    }
+}
+
+impl WireLen for ResponseHeader {
+    fn wire_len(&self) -> usize {
+        match self {
+            ResponseHeader::V0(h) => h.wire_len(),
+            ResponseHeader::V1(h) => h.wire_len(),
+        }
+    }
+}
+
+impl Encoder for ResponseHeader {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        match self {
+            ResponseHeader::V0(h) => h.encode(dest),
+            ResponseHeader::V1(h) => h.encode(dest),
+        }
+    }
+}
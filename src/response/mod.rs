@@ -2,5 +2,5 @@ pub mod body;
 mod headers;
 mod lib;
 
-pub(crate) use headers::ResponseHeaderV0;
+pub(crate) use headers::{ResponseHeader, ResponseHeaderV0, ResponseHeaderV1};
 pub(crate) use lib::KafkaResponse;
@@ -0,0 +1,339 @@
+use std::{fmt::Display, net::IpAddr};
+
+/// Declares a Kafka ACL enum along with its wire codes and the names it is written with
+macro_rules! acl_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident = $code:literal, $text:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum $name {
+            $($variant = $code),+
+        }
+
+        impl $name {
+            pub const ALL: &[Self] = &[$(Self::$variant),+];
+
+            pub fn code(self) -> i8 {
+                self as i8
+            }
+
+            /// `None` for unknown codes, including Kafka's 0 for unknown values
+            pub fn from_code(code: i8) -> Option<Self> {
+                Self::ALL.iter().copied().find(|v| v.code() == code)
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $text),+
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|v| v.name().eq_ignore_ascii_case(name))
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+    };
+}
+
+acl_enum! {
+    /// The kinds of resources ACLs protect. `Any` only appears in filters.
+    ResourceType {
+        Any = 1, "ANY",
+        Topic = 2, "TOPIC",
+        Group = 3, "GROUP",
+        Cluster = 4, "CLUSTER",
+        TransactionalId = 5, "TRANSACTIONAL_ID",
+        DelegationToken = 6, "DELEGATION_TOKEN",
+        User = 7, "USER",
+    }
+}
+
+acl_enum! {
+    /// How the name of an ACL's resource is matched. `Any` and `Match` only appear in
+    /// filters: `Any` matches bindings of either pattern type with exactly the filter's
+    /// name, `Match` the bindings that would apply to a resource of that name.
+    PatternType {
+        Any = 1, "ANY",
+        Match = 2, "MATCH",
+        Literal = 3, "LITERAL",
+        Prefixed = 4, "PREFIXED",
+    }
+}
+
+acl_enum! {
+    /// What an ACL allows or denies. `Any` only appears in filters, `All` covers every
+    /// operation.
+    AclOperation {
+        Any = 1, "ANY",
+        All = 2, "ALL",
+        Read = 3, "READ",
+        Write = 4, "WRITE",
+        Create = 5, "CREATE",
+        Delete = 6, "DELETE",
+        Alter = 7, "ALTER",
+        Describe = 8, "DESCRIBE",
+        ClusterAction = 9, "CLUSTER_ACTION",
+        DescribeConfigs = 10, "DESCRIBE_CONFIGS",
+        AlterConfigs = 11, "ALTER_CONFIGS",
+        IdempotentWrite = 12, "IDEMPOTENT_WRITE",
+        CreateTokens = 13, "CREATE_TOKENS",
+        DescribeTokens = 14, "DESCRIBE_TOKENS",
+    }
+}
+
+acl_enum! {
+    /// Whether an ACL allows or denies its operation. `Any` only appears in filters.
+    AclPermissionType {
+        Any = 1, "ANY",
+        Deny = 2, "DENY",
+        Allow = 3, "ALLOW",
+    }
+}
+
+impl ResourceType {
+    /// The operations that apply to resources of this type, the ones reported as
+    /// authorized operations
+    pub fn operations(self) -> &'static [AclOperation] {
+        use AclOperation::*;
+        match self {
+            Self::Topic => &[
+                Read,
+                Write,
+                Create,
+                Delete,
+                Alter,
+                Describe,
+                DescribeConfigs,
+                AlterConfigs,
+            ],
+            Self::Group => &[Read, Describe, Delete],
+            Self::Cluster => &[
+                Create,
+                ClusterAction,
+                DescribeConfigs,
+                AlterConfigs,
+                IdempotentWrite,
+                Alter,
+                Describe,
+            ],
+            Self::TransactionalId => &[Describe, Write],
+            Self::DelegationToken => &[Describe],
+            Self::User => &[CreateTokens, DescribeTokens],
+            Self::Any => &[],
+        }
+    }
+}
+
+impl AclOperation {
+    /// Whether an ACL for this operation covers `operation`. Allowing to read, write,
+    /// delete or alter a resource also allows to describe it, allowing to alter its
+    /// configs also allows to describe them.
+    pub fn implies(self, operation: AclOperation) -> bool {
+        use AclOperation::*;
+        self == All
+            || self == operation
+            || (operation == Describe && matches!(self, Read | Write | Delete | Alter))
+            || (operation == DescribeConfigs && self == AlterConfigs)
+    }
+}
+
+/// The name of the single cluster resource
+pub const CLUSTER_NAME: &str = "kafka-cluster";
+/// A literal resource name matching every resource of its type
+pub const WILDCARD_RESOURCE: &str = "*";
+/// The principal of ACLs that apply to every user
+pub const WILDCARD_PRINCIPAL: &str = "User:*";
+/// The host of ACLs that apply to every client address
+pub const WILDCARD_HOST: &str = "*";
+
+/// The principal of `user` as written in ACLs, e.g. `User:alice`
+pub fn user_principal(user: &str) -> String {
+    format!("User:{user}")
+}
+
+/// The resources an ACL applies to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourcePattern {
+    pub resource_type: ResourceType,
+    pub name: String,
+    pub pattern_type: PatternType,
+}
+
+impl ResourcePattern {
+    /// Whether the ACLs of this pattern apply to the resource of `resource_type` called
+    /// `name`
+    pub fn matches(&self, resource_type: ResourceType, name: &str) -> bool {
+        self.resource_type == resource_type
+            && match self.pattern_type {
+                PatternType::Literal => self.name == name || self.name == WILDCARD_RESOURCE,
+                PatternType::Prefixed => name.starts_with(&self.name),
+                PatternType::Any | PatternType::Match => false,
+            }
+    }
+}
+
+/// Who may or may not perform an operation, from where
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccessControlEntry {
+    /// `Type:name`, e.g. `User:alice`, or [`WILDCARD_PRINCIPAL`]
+    pub principal: String,
+    /// The client's IP address, or [`WILDCARD_HOST`]
+    pub host: String,
+    pub operation: AclOperation,
+    pub permission_type: AclPermissionType,
+}
+
+impl AccessControlEntry {
+    /// Whether the entry applies to `principal` connecting from `host`
+    pub fn applies_to(&self, principal: &str, host: IpAddr) -> bool {
+        (self.principal == principal || self.principal == WILDCARD_PRINCIPAL)
+            && (self.host == WILDCARD_HOST || self.host == host.to_string())
+    }
+}
+
+/// An ACL: an entry and the resources it applies to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AclBinding {
+    pub pattern: ResourcePattern,
+    pub entry: AccessControlEntry,
+}
+
+impl AclBinding {
+    /// Checks that the binding only uses concrete values, which filters need not
+    pub fn validate(&self) -> Result<(), String> {
+        let pattern = &self.pattern;
+        let entry = &self.entry;
+        if pattern.resource_type == ResourceType::Any {
+            return Err("Resource type ANY is only valid in filters".to_string());
+        }
+        if !matches!(
+            pattern.pattern_type,
+            PatternType::Literal | PatternType::Prefixed
+        ) {
+            return Err(format!(
+                "Pattern type {} is only valid in filters",
+                pattern.pattern_type
+            ));
+        }
+        if pattern.name.is_empty() {
+            return Err("Resource name must not be empty".to_string());
+        }
+        if pattern.resource_type == ResourceType::Cluster && pattern.name != CLUSTER_NAME {
+            return Err(format!(
+                "The cluster resource must be called {CLUSTER_NAME}"
+            ));
+        }
+        if entry.operation == AclOperation::Any || entry.permission_type == AclPermissionType::Any {
+            return Err("Operation and permission type ANY are only valid in filters".to_string());
+        }
+        match entry.principal.split_once(':') {
+            Some((kind, name)) if !kind.is_empty() && !name.is_empty() => {}
+            _ => {
+                return Err(format!(
+                    "Principal {:?} is not of the form Type:name",
+                    entry.principal
+                ));
+            }
+        }
+        if entry.host.is_empty() {
+            return Err("Host must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Display for AclBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (pattern, entry) = (&self.pattern, &self.entry);
+        write!(
+            f,
+            "{} {} on {}:{}:{} from {} for {}",
+            entry.permission_type,
+            entry.principal,
+            pattern.resource_type,
+            pattern.pattern_type,
+            pattern.name,
+            entry.host,
+            entry.operation,
+        )
+    }
+}
+
+impl From<&AclBinding> for AclBindingFilter {
+    /// Matches exactly `binding`
+    fn from(binding: &AclBinding) -> Self {
+        let (pattern, entry) = (&binding.pattern, &binding.entry);
+        Self {
+            resource_type: pattern.resource_type,
+            name: Some(pattern.name.clone()),
+            pattern_type: pattern.pattern_type,
+            principal: Some(entry.principal.clone()),
+            host: Some(entry.host.clone()),
+            operation: entry.operation,
+            permission_type: entry.permission_type,
+        }
+    }
+}
+
+/// Selects ACLs, as used by DescribeAcls and DeleteAcls. Unset names, principals and
+/// hosts and the `Any` values match everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclBindingFilter {
+    pub resource_type: ResourceType,
+    pub name: Option<String>,
+    pub pattern_type: PatternType,
+    pub principal: Option<String>,
+    pub host: Option<String>,
+    pub operation: AclOperation,
+    pub permission_type: AclPermissionType,
+}
+
+impl AclBindingFilter {
+    /// Matches every ACL
+    pub fn any() -> Self {
+        Self {
+            resource_type: ResourceType::Any,
+            name: None,
+            pattern_type: PatternType::Any,
+            principal: None,
+            host: None,
+            operation: AclOperation::Any,
+            permission_type: AclPermissionType::Any,
+        }
+    }
+
+    pub fn matches(&self, binding: &AclBinding) -> bool {
+        let (pattern, entry) = (&binding.pattern, &binding.entry);
+        let name_matches = match (&self.name, self.pattern_type) {
+            (None, PatternType::Any | PatternType::Match) => true,
+            (None, pattern_type) => pattern.pattern_type == pattern_type,
+            (Some(name), PatternType::Any) => &pattern.name == name,
+            (Some(name), PatternType::Match) => match pattern.pattern_type {
+                PatternType::Literal => &pattern.name == name || pattern.name == WILDCARD_RESOURCE,
+                PatternType::Prefixed => name.starts_with(&pattern.name),
+                PatternType::Any | PatternType::Match => false,
+            },
+            (Some(name), pattern_type) => {
+                pattern.pattern_type == pattern_type && &pattern.name == name
+            }
+        };
+        (self.resource_type == ResourceType::Any || self.resource_type == pattern.resource_type)
+            && name_matches
+            && self
+                .principal
+                .as_ref()
+                .map_or(true, |p| p == &entry.principal)
+            && self.host.as_ref().map_or(true, |h| h == &entry.host)
+            && (self.operation == AclOperation::Any || self.operation == entry.operation)
+            && (self.permission_type == AclPermissionType::Any
+                || self.permission_type == entry.permission_type)
+    }
+}
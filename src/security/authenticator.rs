@@ -0,0 +1,316 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use super::{CredentialStore, ScramMechanism, ScramServer};
+use crate::{config::BrokerConfig, types::ErrorCode};
+
+/// A SASL mechanism the broker can authenticate clients with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SaslMechanism {
+    Plain,
+    Scram(ScramMechanism),
+}
+
+impl SaslMechanism {
+    pub const ALL: [Self; 3] = [
+        Self::Plain,
+        Self::Scram(ScramMechanism::Sha256),
+        Self::Scram(ScramMechanism::Sha512),
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "PLAIN",
+            Self::Scram(mechanism) => mechanism.name(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+impl Display for SaslMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Why a SASL request failed
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SaslError {
+    #[error("Unsupported SASL mechanism {0}")]
+    UnsupportedMechanism(String),
+    #[error("{0}")]
+    IllegalState(String),
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+}
+
+impl SaslError {
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::UnsupportedMechanism(_) => ErrorCode::UnsupportedSaslMechanism,
+            Self::IllegalState(_) => ErrorCode::IllegalSaslState,
+            Self::AuthenticationFailed(_) => ErrorCode::SaslAuthenticationFailed,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Exchange {
+    Plain,
+    Scram(ScramServer),
+}
+
+#[derive(Debug)]
+enum State {
+    /// Waiting for a SaslHandshake
+    Handshake,
+    /// Exchanging SaslAuthenticate requests for the mechanism of the handshake
+    Authenticating(Exchange),
+    /// Authenticated, until the session expires or the client re-authenticates
+    Authenticated,
+}
+
+/// The SASL state of a connection: a SaslHandshake picking the mechanism, then
+/// SaslAuthenticate requests until the client is authenticated.
+///
+/// With `connections.max.reauth.ms` set, sessions expire and clients have to
+/// re-authenticate, with the same mechanism and as the same principal, before they do.
+#[derive(Debug)]
+pub struct SaslAuthenticator {
+    enabled: Vec<SaslMechanism>,
+    max_reauth: Duration,
+    state: State,
+    mechanism: Option<SaslMechanism>,
+    principal: Option<String>,
+    session_expiry: Option<Instant>,
+    failed: bool,
+}
+
+impl SaslAuthenticator {
+    /// Authenticates with one of the `enabled` mechanisms. A zero `max_reauth` lets
+    /// sessions last as long as the connection.
+    pub fn new(enabled: Vec<SaslMechanism>, max_reauth: Duration) -> Self {
+        Self {
+            enabled,
+            max_reauth,
+            state: State::Handshake,
+            mechanism: None,
+            principal: None,
+            session_expiry: None,
+            failed: false,
+        }
+    }
+
+    /// Authenticates with the `sasl.enabled.mechanisms` of `config`, for sessions of
+    /// `connections.max.reauth.ms`
+    pub fn from_config(config: &BrokerConfig) -> Self {
+        Self::new(
+            config.sasl_enabled_mechanisms.clone(),
+            config.connections_max_reauth,
+        )
+    }
+
+    /// The enabled mechanisms, as told to clients in SaslHandshake responses
+    pub fn enabled(&self) -> &[SaslMechanism] {
+        &self.enabled
+    }
+
+    /// The authenticated user, kept while re-authenticating
+    pub fn principal(&self) -> Option<&str> {
+        self.principal.as_deref()
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.principal.is_some()
+    }
+
+    /// Whether authentication failed, after which the connection is closed
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Whether the session ran out without the client re-authenticating
+    pub fn session_expired(&self, now: Instant) -> bool {
+        self.session_expiry.is_some_and(|expiry| now >= expiry)
+    }
+
+    /// The `session_lifetime_ms` of a SaslAuthenticate response: what is left of the
+    /// session once authenticated, 0 while still authenticating or without expiry
+    pub fn session_lifetime_ms(&self, now: Instant) -> i64 {
+        match (&self.state, self.session_expiry) {
+            (State::Authenticated, Some(expiry)) => {
+                expiry.saturating_duration_since(now).as_millis() as i64
+            }
+            _ => 0,
+        }
+    }
+
+    /// Starts authenticating with the mechanism called `name`
+    pub fn handshake(&mut self, name: &str) -> Result<(), SaslError> {
+        let result = self.try_handshake(name);
+        self.failed |= result.is_err();
+        result
+    }
+
+    fn try_handshake(&mut self, name: &str) -> Result<(), SaslError> {
+        if matches!(self.state, State::Authenticating(_)) {
+            return Err(SaslError::IllegalState(
+                "Unexpected SaslHandshake while authenticating".to_string(),
+            ));
+        }
+        let mechanism = SaslMechanism::from_name(name)
+            .filter(|m| self.enabled.contains(m))
+            .ok_or_else(|| SaslError::UnsupportedMechanism(name.to_string()))?;
+        if let Some(previous) = self.mechanism.filter(|&m| m != mechanism) {
+            return Err(SaslError::IllegalState(format!(
+                "Re-authentication with {mechanism} after authenticating with {previous}"
+            )));
+        }
+        self.mechanism = Some(mechanism);
+        self.state = State::Authenticating(match mechanism {
+            SaslMechanism::Plain => Exchange::Plain,
+            SaslMechanism::Scram(mechanism) => Exchange::Scram(ScramServer::new(mechanism)),
+        });
+        Ok(())
+    }
+
+    /// Processes the `auth_bytes` of a SaslAuthenticate request and returns those of
+    /// the response
+    pub fn authenticate(
+        &mut self,
+        token: &[u8],
+        credentials: &CredentialStore,
+        now: Instant,
+    ) -> Result<Vec<u8>, SaslError> {
+        let result = self.try_authenticate(token, credentials, now);
+        self.failed |= result.is_err();
+        result
+    }
+
+    fn try_authenticate(
+        &mut self,
+        token: &[u8],
+        credentials: &CredentialStore,
+        now: Instant,
+    ) -> Result<Vec<u8>, SaslError> {
+        let State::Authenticating(exchange) = &mut self.state else {
+            return Err(SaslError::IllegalState(
+                "Unexpected SaslAuthenticate before SaslHandshake".to_string(),
+            ));
+        };
+        match exchange {
+            Exchange::Plain => {
+                let user = authenticate_plain(token, credentials)?;
+                self.complete(user, now)?;
+                Ok(Vec::new())
+            }
+            Exchange::Scram(server) => {
+                let answer = server
+                    .step(token, credentials)
+                    .map_err(|e| SaslError::AuthenticationFailed(e.to_string()))?;
+                if let Some(user) = server.username() {
+                    let user = user.to_string();
+                    self.complete(user, now)?;
+                }
+                Ok(answer)
+            }
+        }
+    }
+
+    fn complete(&mut self, user: String, now: Instant) -> Result<(), SaslError> {
+        if let Some(principal) = self.principal.as_ref().filter(|&p| *p != user) {
+            return Err(SaslError::AuthenticationFailed(format!(
+                "Re-authentication as {user} after authenticating as {principal}"
+            )));
+        }
+        self.principal = Some(user);
+        self.session_expiry = (!self.max_reauth.is_zero()).then(|| now + self.max_reauth);
+        self.state = State::Authenticated;
+        Ok(())
+    }
+}
+
+/// Checks a PLAIN message (RFC 4616), `[authzid] NUL authcid NUL passwd`, and returns
+/// the user
+fn authenticate_plain(token: &[u8], credentials: &CredentialStore) -> Result<String, SaslError> {
+    let malformed = || SaslError::AuthenticationFailed("Malformed PLAIN message".to_string());
+    let token = std::str::from_utf8(token).map_err(|_| malformed())?;
+    let mut parts = token.split('\0');
+    let (Some(authzid), Some(user), Some(password), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(malformed());
+    };
+    if user.is_empty() {
+        return Err(malformed());
+    }
+    if !authzid.is_empty() && authzid != user {
+        return Err(SaslError::AuthenticationFailed(
+            "Authorization id differs from the username".to_string(),
+        ));
+    }
+    if !credentials.verify_plain(user, password) {
+        return Err(SaslError::AuthenticationFailed(
+            "Invalid username or password".to_string(),
+        ));
+    }
+    Ok(user.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_plain_and_reauthentication() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_file = dir.path().join("plain.properties");
+        fs::write(&plain_file, "alice=alice-secret\nbob=bob-secret\n").unwrap();
+        let credentials = CredentialStore::load(Some(&plain_file), dir.path()).unwrap();
+        let now = Instant::now();
+        let mut auth = SaslAuthenticator::new(
+            vec![
+                SaslMechanism::Plain,
+                SaslMechanism::Scram(ScramMechanism::Sha256),
+            ],
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(
+            SaslError::UnsupportedMechanism("SCRAM-SHA-512".to_string()),
+            auth.handshake("SCRAM-SHA-512").unwrap_err()
+        );
+        auth.handshake("PLAIN").unwrap();
+        auth.authenticate(b"\0alice\0alice-secret", &credentials, now)
+            .unwrap();
+        assert_eq!(Some("alice"), auth.principal());
+        assert_eq!(60_000, auth.session_lifetime_ms(now));
+        assert!(auth.session_expired(now + Duration::from_secs(60)));
+
+        // re-authentication has to keep the mechanism and the principal
+        assert!(matches!(
+            auth.handshake("SCRAM-SHA-256"),
+            Err(SaslError::IllegalState(_))
+        ));
+        auth.handshake("PLAIN").unwrap();
+        let later = now + Duration::from_secs(30);
+        auth.authenticate(b"alice\0alice\0alice-secret", &credentials, later)
+            .unwrap();
+        assert!(!auth.session_expired(now + Duration::from_secs(60)));
+        auth.handshake("PLAIN").unwrap();
+        assert!(matches!(
+            auth.authenticate(b"\0bob\0bob-secret", &credentials, later),
+            Err(SaslError::AuthenticationFailed(_))
+        ));
+        assert!(auth.failed());
+    }
+}
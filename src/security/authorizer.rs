@@ -0,0 +1,378 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Debug,
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::{Context, bail, ensure};
+use tracing::debug;
+
+use super::{
+    AccessControlEntry, AclBinding, AclBindingFilter, AclOperation, AclPermissionType, PatternType,
+    ResourcePattern, ResourceType,
+};
+use crate::{
+    quota::{desanitize, sanitize},
+    storage::atomic_write,
+};
+
+/// Decides which operations principals may perform on which resources. Handlers consult
+/// it before touching a resource, the ACL APIs manage the ACLs it decides by.
+///
+/// Principals are written as in ACLs, e.g. `User:alice`.
+pub trait Authorizer: Debug + Send + Sync {
+    /// Whether `principal`, connected from `host`, may perform `operation` on the
+    /// resource of `resource_type` called `name`
+    fn authorize(
+        &self,
+        principal: &str,
+        host: IpAddr,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> bool;
+
+    /// The operations applying to resources of `resource_type` that `principal` may
+    /// perform on the one called `name`, as a bit field with the bit of every operation's
+    /// code set
+    fn authorized_operations(
+        &self,
+        principal: &str,
+        host: IpAddr,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> i32 {
+        resource_type
+            .operations()
+            .iter()
+            .filter(|&&operation| self.authorize(principal, host, operation, resource_type, name))
+            .fold(0, |operations, operation| {
+                operations | 1 << operation.code()
+            })
+    }
+
+    /// Adds ACLs, all of them or none. Adding an ACL that already exists does nothing.
+    fn create_acls(&self, bindings: &[AclBinding]) -> anyhow::Result<()>;
+
+    /// Removes the ACLs matching any of `filters`, all of them or none. Returns the ACLs
+    /// each filter matched.
+    fn delete_acls(&self, filters: &[AclBindingFilter]) -> anyhow::Result<Vec<Vec<AclBinding>>>;
+
+    /// The ACLs matching `filter`
+    fn acls(&self, filter: &AclBindingFilter) -> Vec<AclBinding>;
+}
+
+/// The default [`Authorizer`], deciding by ACLs kept in the first log directory.
+///
+/// Deny ACLs take precedence over allow ones. Resources without any ACL are only
+/// accessible with `allow.everyone.if.no.acl.found`, the `super.users` may do anything.
+///
+/// ```text
+/// 0                                       <- version
+/// TOPIC LITERAL orders User%3Aalice %2A READ ALLOW
+/// ```
+#[derive(Debug)]
+pub struct AclAuthorizer {
+    super_users: HashSet<String>,
+    allow_everyone_if_no_acl_found: bool,
+    path: PathBuf,
+    acls: RwLock<BTreeSet<AclBinding>>,
+}
+
+impl AclAuthorizer {
+    const VERSION: u32 = 0;
+    const FILE_NAME: &str = "acls";
+
+    /// The values of `authorizer.class.name` enabling it, the names of Kafka's ACL
+    /// authorizers
+    pub const CLASS_NAMES: &[&str] = &[
+        "org.apache.kafka.metadata.authorizer.StandardAuthorizer",
+        "kafka.security.authorizer.AclAuthorizer",
+    ];
+
+    /// Loads the ACLs stored in `dir`
+    pub fn load(
+        dir: &Path,
+        super_users: &[String],
+        allow_everyone_if_no_acl_found: bool,
+    ) -> anyhow::Result<Self> {
+        let path = dir.join(Self::FILE_NAME);
+        let acls = Self::read(&path)?;
+        Ok(Self {
+            super_users: super_users.iter().cloned().collect(),
+            allow_everyone_if_no_acl_found,
+            path,
+            acls: RwLock::new(acls),
+        })
+    }
+
+    fn read(path: &Path) -> anyhow::Result<BTreeSet<AclBinding>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing ACL file version")?
+            .trim()
+            .parse()?;
+        ensure!(
+            version == Self::VERSION,
+            "Unsupported ACL file version {version} in {}",
+            path.display()
+        );
+
+        let mut acls = BTreeSet::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let &[
+                resource_type,
+                pattern_type,
+                name,
+                principal,
+                host,
+                operation,
+                permission_type,
+            ] = &fields[..]
+            else {
+                bail!("Malformed ACL {line:?}");
+            };
+            let binding = AclBinding {
+                pattern: ResourcePattern {
+                    resource_type: ResourceType::from_name(resource_type)
+                        .with_context(|| format!("Unknown resource type {resource_type}"))?,
+                    name: desanitize(name)?,
+                    pattern_type: PatternType::from_name(pattern_type)
+                        .with_context(|| format!("Unknown pattern type {pattern_type}"))?,
+                },
+                entry: AccessControlEntry {
+                    principal: desanitize(principal)?,
+                    host: desanitize(host)?,
+                    operation: AclOperation::from_name(operation)
+                        .with_context(|| format!("Unknown operation {operation}"))?,
+                    permission_type: AclPermissionType::from_name(permission_type)
+                        .with_context(|| format!("Unknown permission type {permission_type}"))?,
+                },
+            };
+            acls.insert(binding);
+        }
+        Ok(acls)
+    }
+
+    /// Replaces the stored ACLs
+    fn write(&self, acls: &BTreeSet<AclBinding>) -> anyhow::Result<()> {
+        let mut content = format!("{}\n", Self::VERSION);
+        for AclBinding { pattern, entry } in acls {
+            content.push_str(&format!(
+                "{} {} {} {} {} {} {}\n",
+                pattern.resource_type,
+                pattern.pattern_type,
+                sanitize(&pattern.name),
+                sanitize(&entry.principal),
+                sanitize(&entry.host),
+                entry.operation,
+                entry.permission_type,
+            ));
+        }
+
+        atomic_write(&self.path, content.as_bytes())
+    }
+}
+
+impl Authorizer for AclAuthorizer {
+    fn authorize(
+        &self,
+        principal: &str,
+        host: IpAddr,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> bool {
+        if self.super_users.contains(principal) {
+            return true;
+        }
+        let acls = self.acls.read().unwrap();
+        let mut resource_acls = acls
+            .iter()
+            .filter(|acl| acl.pattern.matches(resource_type, name))
+            .peekable();
+        if resource_acls.peek().is_none() {
+            return self.allow_everyone_if_no_acl_found;
+        }
+
+        let mut allowed = false;
+        for AclBinding { entry, .. } in resource_acls {
+            if !entry.applies_to(principal, host) {
+                continue;
+            }
+            match entry.permission_type {
+                AclPermissionType::Deny
+                    if entry.operation == AclOperation::All || entry.operation == operation =>
+                {
+                    allowed = false;
+                    break;
+                }
+                AclPermissionType::Allow if entry.operation.implies(operation) => allowed = true,
+                _ => {}
+            }
+        }
+        if !allowed {
+            debug!(
+                principal,
+                %host,
+                %operation,
+                "Denied access to {resource_type} {name}"
+            );
+        }
+        allowed
+    }
+
+    fn create_acls(&self, bindings: &[AclBinding]) -> anyhow::Result<()> {
+        let mut acls = self.acls.write().unwrap();
+        let mut created = acls.clone();
+        created.extend(bindings.iter().cloned());
+        if created.len() != acls.len() {
+            self.write(&created)?;
+            *acls = created;
+        }
+        Ok(())
+    }
+
+    fn delete_acls(&self, filters: &[AclBindingFilter]) -> anyhow::Result<Vec<Vec<AclBinding>>> {
+        let mut acls = self.acls.write().unwrap();
+        let matched: Vec<Vec<_>> = filters
+            .iter()
+            .map(|filter| {
+                acls.iter()
+                    .filter(|acl| filter.matches(acl))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        let mut remaining = acls.clone();
+        for acl in matched.iter().flatten() {
+            remaining.remove(acl);
+        }
+        if remaining.len() != acls.len() {
+            self.write(&remaining)?;
+            *acls = remaining;
+        }
+        Ok(matched)
+    }
+
+    fn acls(&self, filter: &AclBindingFilter) -> Vec<AclBinding> {
+        let acls = self.acls.read().unwrap();
+        acls.iter()
+            .filter(|acl| filter.matches(acl))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{WILDCARD_HOST, WILDCARD_PRINCIPAL};
+
+    fn acl(
+        resource_type: ResourceType,
+        name: &str,
+        pattern_type: PatternType,
+        principal: &str,
+        operation: AclOperation,
+        permission_type: AclPermissionType,
+    ) -> AclBinding {
+        AclBinding {
+            pattern: ResourcePattern {
+                resource_type,
+                name: name.to_string(),
+                pattern_type,
+            },
+            entry: AccessControlEntry {
+                principal: principal.to_string(),
+                host: WILDCARD_HOST.to_string(),
+                operation,
+                permission_type,
+            },
+        }
+    }
+
+    #[test]
+    fn test_authorize_and_store() {
+        use AclOperation::*;
+        use AclPermissionType::*;
+        use PatternType::*;
+        use ResourceType::*;
+
+        let dir = tempfile::tempdir().unwrap();
+        let host = IpAddr::from([127, 0, 0, 1]);
+        let authorizer =
+            AclAuthorizer::load(dir.path(), &["User:admin".to_string()], false).unwrap();
+        authorizer
+            .create_acls(&[
+                acl(Topic, "orders", Literal, "User:alice", Write, Allow),
+                acl(Topic, "orders-", Prefixed, WILDCARD_PRINCIPAL, Read, Allow),
+                acl(Topic, "orders-secret", Literal, "User:bob", All, Deny),
+            ])
+            .unwrap();
+
+        let can = |principal, operation, name| {
+            authorizer.authorize(principal, host, operation, Topic, name)
+        };
+        assert!(can("User:alice", Write, "orders"));
+        // writing implies describing, not reading
+        assert!(can("User:alice", Describe, "orders"));
+        assert!(!can("User:alice", Read, "orders"));
+        assert!(!can("User:bob", Write, "orders"));
+        assert!(can("User:bob", Read, "orders-eu"));
+        assert!(!can("User:bob", Read, "orders-secret"));
+        assert!(can("User:alice", Read, "orders-secret"));
+        // no ACLs, only super users
+        assert!(!can("User:alice", Read, "payments"));
+        assert!(can("User:admin", Delete, "payments"));
+        assert_eq!(
+            1 << Read.code() | 1 << Describe.code(),
+            authorizer.authorized_operations("User:bob", host, Topic, "orders-eu")
+        );
+
+        let prefixed = AclBindingFilter {
+            resource_type: Topic,
+            name: None,
+            pattern_type: Prefixed,
+            principal: None,
+            host: None,
+            operation: AclOperation::Any,
+            permission_type: AclPermissionType::Any,
+        };
+        let matching = AclBindingFilter {
+            name: Some("orders-secret".to_string()),
+            pattern_type: Match,
+            ..prefixed.clone()
+        };
+        assert_eq!(2, authorizer.acls(&matching).len());
+        let deleted = authorizer
+            .delete_acls(std::slice::from_ref(&prefixed))
+            .unwrap();
+        assert_eq!(1, deleted[0].len());
+        assert!(!can("User:bob", Read, "orders-eu"));
+
+        let reloaded = AclAuthorizer::load(dir.path(), &[], true).unwrap();
+        assert!(reloaded.acls(&prefixed).is_empty());
+        assert_eq!(
+            2,
+            reloaded
+                .acls(&AclBindingFilter {
+                    pattern_type: PatternType::Any,
+                    ..prefixed
+                })
+                .len()
+        );
+        assert!(reloaded.authorize("User:alice", host, Write, Topic, "orders"));
+        assert!(reloaded.authorize("User:alice", host, Read, Topic, "payments"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::{Context, bail, ensure};
+
+use super::{ScramCredential, ScramMechanism, scram::constant_time_eq};
+use crate::{
+    config::Properties,
+    quota::{desanitize, sanitize},
+    storage::atomic_write,
+};
+
+/// A SCRAM credential to set for a mechanism and user, or to remove if `None`
+pub type ScramCredentialChange = (ScramMechanism, String, Option<ScramCredential>);
+
+/// SCRAM credentials by mechanism and user
+type ScramCredentials = BTreeMap<(ScramMechanism, String), ScramCredential>;
+
+/// The credentials clients authenticate with.
+///
+/// PLAIN passwords are read from `sasl.plain.credentials.file`, a properties file of
+/// `user=password` lines. SCRAM credentials are salted, never stored in the clear, and
+/// kept in the first log directory so they survive restarts:
+///
+/// ```text
+/// 0                                       <- version
+/// SCRAM-SHA-256 alice salt=...,stored_key=...,server_key=...,iterations=4096
+/// ```
+#[derive(Debug)]
+pub struct CredentialStore {
+    plain: HashMap<String, String>,
+    path: PathBuf,
+    scram: RwLock<ScramCredentials>,
+}
+
+impl CredentialStore {
+    const VERSION: u32 = 0;
+    const FILE_NAME: &str = "scram-credentials";
+
+    /// Loads the PLAIN passwords of `plain_file`, if any, and the SCRAM credentials stored
+    /// in `dir`
+    pub fn load(plain_file: Option<&Path>, dir: &Path) -> anyhow::Result<Self> {
+        let plain = match plain_file {
+            Some(file) => Properties::read(file)?
+                .iter()
+                .map(|(user, password)| (user.to_string(), password.to_string()))
+                .collect(),
+            None => HashMap::new(),
+        };
+        let path = dir.join(Self::FILE_NAME);
+        let scram = Self::read(&path)?;
+        Ok(Self {
+            plain,
+            path,
+            scram: RwLock::new(scram),
+        })
+    }
+
+    fn read(path: &Path) -> anyhow::Result<ScramCredentials> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ScramCredentials::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing credentials file version")?
+            .trim()
+            .parse()?;
+        ensure!(
+            version == Self::VERSION,
+            "Unsupported credentials file version {version} in {}",
+            path.display()
+        );
+
+        let mut credentials = ScramCredentials::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let (Some(mechanism), Some(user), Some(credential)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Malformed credential {line:?}");
+            };
+            let mechanism = ScramMechanism::from_name(mechanism)
+                .with_context(|| format!("Unknown mechanism {mechanism}"))?;
+            let user = desanitize(user)?;
+            let credential = ScramCredential::parse(credential)
+                .with_context(|| format!("Parsing the {mechanism} credential of {user}"))?;
+            credentials.insert((mechanism, user), credential);
+        }
+        Ok(credentials)
+    }
+
+    /// Replaces the stored credentials
+    fn write(&self, credentials: &ScramCredentials) -> anyhow::Result<()> {
+        let mut content = format!("{}\n", Self::VERSION);
+        for ((mechanism, user), credential) in credentials {
+            content.push_str(&format!("{mechanism} {} {credential}\n", sanitize(user)));
+        }
+
+        atomic_write(&self.path, content.as_bytes())
+    }
+
+    /// Whether `password` is the PLAIN password of `user`
+    pub fn verify_plain(&self, user: &str, password: &str) -> bool {
+        self.plain
+            .get(user)
+            .is_some_and(|expected| constant_time_eq(expected.as_bytes(), password.as_bytes()))
+    }
+
+    /// The SCRAM credential of `user` for `mechanism`
+    pub fn scram(&self, mechanism: ScramMechanism, user: &str) -> Option<ScramCredential> {
+        let credentials = self.scram.read().unwrap();
+        credentials.get(&(mechanism, user.to_string())).cloned()
+    }
+
+    /// The users with SCRAM credentials and the mechanisms they have them for
+    pub fn scram_users(&self) -> BTreeMap<String, Vec<(ScramMechanism, u32)>> {
+        let credentials = self.scram.read().unwrap();
+        let mut users: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for ((mechanism, user), credential) in credentials.iter() {
+            users
+                .entry(user.clone())
+                .or_default()
+                .push((*mechanism, credential.iterations));
+        }
+        users
+    }
+
+    /// Sets the SCRAM credential of `user` for `mechanism`, replacing any earlier one
+    pub fn set_scram(
+        &self,
+        mechanism: ScramMechanism,
+        user: &str,
+        credential: ScramCredential,
+    ) -> anyhow::Result<()> {
+        self.alter_scram(vec![(mechanism, user.to_string(), Some(credential))])
+    }
+
+    /// Sets or removes SCRAM credentials. All of the changes are stored together or not
+    /// at all.
+    pub fn alter_scram(&self, changes: Vec<ScramCredentialChange>) -> anyhow::Result<()> {
+        let mut credentials = self.scram.write().unwrap();
+        let mut altered = credentials.clone();
+        for (mechanism, user, credential) in changes {
+            match credential {
+                Some(credential) => altered.insert((mechanism, user), credential),
+                None => altered.remove(&(mechanism, user)),
+            };
+        }
+        self.write(&altered)?;
+        *credentials = altered;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain_file = dir.path().join("plain.properties");
+        fs::write(&plain_file, "alice=alice-secret\nbob = bob-secret\n").unwrap();
+
+        let store = CredentialStore::load(Some(&plain_file), dir.path()).unwrap();
+        assert!(store.verify_plain("alice", "alice-secret"));
+        assert!(store.verify_plain("bob", "bob-secret"));
+        assert!(!store.verify_plain("alice", "bob-secret"));
+        assert!(!store.verify_plain("carol", ""));
+
+        let credential = ScramCredential::new(ScramMechanism::Sha512, "secret", 4096);
+        store
+            .set_scram(
+                ScramMechanism::Sha512,
+                "user with spaces",
+                credential.clone(),
+            )
+            .unwrap();
+        store
+            .set_scram(
+                ScramMechanism::Sha256,
+                "alice",
+                ScramCredential::new(ScramMechanism::Sha256, "secret", 8192),
+            )
+            .unwrap();
+        store
+            .alter_scram(vec![(ScramMechanism::Sha256, "alice".to_string(), None)])
+            .unwrap();
+
+        let reloaded = CredentialStore::load(None, dir.path()).unwrap();
+        assert_eq!(
+            Some(credential),
+            reloaded.scram(ScramMechanism::Sha512, "user with spaces")
+        );
+        assert_eq!(None, reloaded.scram(ScramMechanism::Sha256, "alice"));
+        assert!(!reloaded.verify_plain("alice", "alice-secret"));
+    }
+}
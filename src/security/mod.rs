@@ -0,0 +1,34 @@
+//! Encryption and authentication of client connections.
+//!
+//! `SSL` and `SASL_SSL` listeners wrap their connections in TLS. Clients of `SSL`
+//! listeners presenting a certificate are authenticated by it, their principal is mapped
+//! from its subject.
+//!
+//! Clients of `SASL_PLAINTEXT` and `SASL_SSL` listeners authenticate with SASL. They pick a
+//! mechanism with SaslHandshake, then authenticate with SaslAuthenticate requests:
+//! PLAIN checks a password from `sasl.plain.credentials.file`, SCRAM-SHA-256 and
+//! SCRAM-SHA-512 prove knowledge of a password against salted credentials without
+//! sending it. Until then only ApiVersions and the SASL requests are served.
+//!
+//! The authenticated user becomes the principal of the connection, which handlers and
+//! quotas see. Once an [`Authorizer`] is configured, handlers ask it whether the
+//! principal may perform an operation on a resource before doing so.
+mod acl;
+mod authenticator;
+mod authorizer;
+mod credentials;
+mod principal_mapper;
+mod scram;
+mod tls;
+
+pub use acl::{
+    AccessControlEntry, AclBinding, AclBindingFilter, AclOperation, AclPermissionType,
+    CLUSTER_NAME, PatternType, ResourcePattern, ResourceType, WILDCARD_HOST, WILDCARD_PRINCIPAL,
+    WILDCARD_RESOURCE, user_principal,
+};
+pub use authenticator::{SaslAuthenticator, SaslError, SaslMechanism};
+pub use authorizer::{AclAuthorizer, Authorizer};
+pub use credentials::{CredentialStore, ScramCredentialChange};
+pub use principal_mapper::SslPrincipalMapper;
+pub use scram::{ScramCredential, ScramMechanism, ScramServer};
+pub use tls::{SslClientAuth, TlsServer};
@@ -0,0 +1,329 @@
+use std::fmt::{Display, Write};
+
+use anyhow::{Context, bail};
+use regex::Regex;
+use x509_parser::{certificate::X509Certificate, prelude::FromDer, x509::X509Name};
+
+/// Maps the subject of a client certificate to the principal of its connection, as
+/// configured by `ssl.principal.mapping.rules`. Same syntax as Kafka's: a comma separated
+/// list of `RULE:pattern/replacement/` rules, optionally followed by `L` or `U` to lower or
+/// upper case the result, and `DEFAULT`, which keeps the subject as is. The first rule
+/// whose pattern matches the whole subject, written as in RFC 2253
+/// (`CN=alice,OU=eng,O=corp`), decides the principal:
+///
+/// ```text
+/// RULE:^CN=(.*?),OU=ServiceUsers.*$/$1/,RULE:^CN=([^,]*).*$/$1/L,DEFAULT
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SslPrincipalMapper {
+    rules: Vec<PrincipalRule>,
+}
+
+#[derive(Debug, Clone)]
+enum PrincipalRule {
+    Default,
+    Rule {
+        /// The pattern as configured, the replacement is applied with it
+        pattern: Regex,
+        /// The same pattern, only matching whole subjects
+        anchored: Regex,
+        /// The replacement as configured, in Java's syntax
+        replacement: String,
+        /// The replacement in the syntax of [`Regex::replace_all`]
+        substitution: String,
+        case: Option<Case>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Lower,
+    Upper,
+}
+
+impl PartialEq for PrincipalRule {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Default, Self::Default) => true,
+            (
+                Self::Rule {
+                    pattern,
+                    replacement,
+                    case,
+                    ..
+                },
+                Self::Rule {
+                    pattern: other_pattern,
+                    replacement: other_replacement,
+                    case: other_case,
+                    ..
+                },
+            ) => {
+                pattern.as_str() == other_pattern.as_str()
+                    && replacement == other_replacement
+                    && case == other_case
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for SslPrincipalMapper {
+    fn default() -> Self {
+        Self {
+            rules: vec![PrincipalRule::Default],
+        }
+    }
+}
+
+impl SslPrincipalMapper {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+        let mut rest = value.trim();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("DEFAULT") {
+                rules.push(PrincipalRule::Default);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("RULE:") {
+                let (pattern, after) = split_unescaped(after)
+                    .with_context(|| format!("Rule {rest:?} is missing its replacement"))?;
+                let (replacement, after) = split_unescaped(after)
+                    .with_context(|| format!("Rule {rest:?} is not terminated by a /"))?;
+                let (case, after) = match after.as_bytes().first() {
+                    Some(b'L') => (Some(Case::Lower), &after[1..]),
+                    Some(b'U') => (Some(Case::Upper), &after[1..]),
+                    _ => (None, after),
+                };
+                rules.push(PrincipalRule::Rule {
+                    pattern: Regex::new(pattern)
+                        .with_context(|| format!("Invalid pattern {pattern:?}"))?,
+                    anchored: Regex::new(&format!("^(?:{pattern})$"))?,
+                    replacement: replacement.to_string(),
+                    substitution: java_replacement(replacement),
+                    case,
+                });
+                rest = after;
+            } else {
+                bail!("Invalid principal mapping rule {rest:?}");
+            }
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after.trim_start();
+            } else if !rest.is_empty() {
+                bail!("Principal mapping rules must be separated by commas, not {rest:?}");
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    /// The principal of a client presenting the DER encoded `certificate`
+    pub fn principal(&self, certificate: &[u8]) -> anyhow::Result<String> {
+        let (_, certificate) = X509Certificate::from_der(certificate)
+            .map_err(|e| anyhow::anyhow!("Invalid client certificate: {e}"))?;
+        self.apply(&distinguished_name(certificate.subject()))
+    }
+
+    /// Applies the first of the rules matching `subject`
+    pub fn apply(&self, subject: &str) -> anyhow::Result<String> {
+        for rule in &self.rules {
+            match rule {
+                PrincipalRule::Default => return Ok(subject.to_string()),
+                PrincipalRule::Rule {
+                    pattern,
+                    anchored,
+                    substitution,
+                    case,
+                    ..
+                } if anchored.is_match(subject) => {
+                    let principal = pattern.replace_all(subject, substitution.as_str());
+                    return Ok(match case {
+                        Some(Case::Lower) => principal.to_lowercase(),
+                        Some(Case::Upper) => principal.to_uppercase(),
+                        None => principal.into_owned(),
+                    });
+                }
+                PrincipalRule::Rule { .. } => {}
+            }
+        }
+        bail!("No principal mapping rule applies to {subject}")
+    }
+}
+
+impl Display for SslPrincipalMapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            match rule {
+                PrincipalRule::Default => f.write_str("DEFAULT")?,
+                PrincipalRule::Rule {
+                    pattern,
+                    replacement,
+                    case,
+                    ..
+                } => {
+                    let case = match case {
+                        Some(Case::Lower) => "L",
+                        Some(Case::Upper) => "U",
+                        None => "",
+                    };
+                    write!(f, "RULE:{pattern}/{replacement}/{case}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits at the first `/` not escaped by a backslash
+fn split_unescaped(value: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' => return Some((&value[..i], &value[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Turns a replacement written for Java's `replaceAll` into one for [`Regex::replace_all`]:
+/// `$1` becomes `${1}` so `$1abc` keeps meaning group 1, and backslashes escape the next
+/// character
+fn java_replacement(replacement: &str) -> String {
+    let mut converted = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('$') => converted.push_str("$$"),
+                Some(escaped) => converted.push(escaped),
+                None => {}
+            },
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                converted.push_str("${");
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    converted.push(digit);
+                }
+                converted.push('}');
+            }
+            '$' => converted.push_str("$$"),
+            c => converted.push(c),
+        }
+    }
+    converted
+}
+
+/// Writes `name` as in RFC 2253, the last of its relative distinguished names first
+fn distinguished_name(name: &X509Name) -> String {
+    let mut dn = String::new();
+    let rdns: Vec<_> = name.iter().collect();
+    for (i, rdn) in rdns.into_iter().rev().enumerate() {
+        if i > 0 {
+            dn.push(',');
+        }
+        for (j, attribute) in rdn.iter().enumerate() {
+            if j > 0 {
+                dn.push('+');
+            }
+            let oid = attribute.attr_type().to_id_string();
+            let keyword = match oid.as_str() {
+                "2.5.4.3" => "CN",
+                "2.5.4.6" => "C",
+                "2.5.4.7" => "L",
+                "2.5.4.8" => "ST",
+                "2.5.4.9" => "STREET",
+                "2.5.4.10" => "O",
+                "2.5.4.11" => "OU",
+                "0.9.2342.19200300.100.1.1" => "UID",
+                "0.9.2342.19200300.100.1.25" => "DC",
+                oid => oid,
+            };
+            dn.push_str(keyword);
+            dn.push('=');
+            match attribute.as_str() {
+                Ok(value) => escape_value(&mut dn, value),
+                // values that are not strings are written as their hex encoded bytes
+                Err(_) => {
+                    dn.push('#');
+                    for byte in attribute.as_slice() {
+                        let _ = write!(dn, "{byte:02x}");
+                    }
+                }
+            }
+        }
+    }
+    dn
+}
+
+fn escape_value(dn: &mut String, value: &str) {
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        let special = matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';')
+            || (i == 0 && matches!(c, '#' | ' '))
+            || (i == last && c == ' ');
+        if special {
+            dn.push('\\');
+        }
+        dn.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let mapper = SslPrincipalMapper::parse(
+            "RULE:^CN=(.*?),OU=ServiceUsers.*$/$1/, RULE:^CN=([^,]*),O=(.*)$/$1@$2/U,\
+             RULE:^cn=([^,]*).*$/$1/L , DEFAULT",
+        )
+        .unwrap();
+        assert_eq!(
+            "kafka-connect",
+            mapper
+                .apply("CN=kafka-connect,OU=ServiceUsers,O=corp")
+                .unwrap()
+        );
+        assert_eq!("ALICE@CORP", mapper.apply("CN=alice,O=corp").unwrap());
+        assert_eq!("bob", mapper.apply("cn=Bob,C=HU").unwrap());
+        assert_eq!("CN=carol,C=HU", mapper.apply("CN=carol,C=HU").unwrap());
+        assert_eq!(
+            SslPrincipalMapper::parse(&mapper.to_string()).unwrap(),
+            mapper
+        );
+
+        let no_default = SslPrincipalMapper::parse("RULE:^CN=(.*)$/$1/").unwrap();
+        assert_eq!("dave", no_default.apply("CN=dave").unwrap());
+        assert!(no_default.apply("O=corp").is_err());
+
+        assert!(SslPrincipalMapper::parse("RULE:^CN=(.*)$/$1").is_err());
+        assert!(SslPrincipalMapper::parse("RULE:(/$1/").is_err());
+        assert!(SslPrincipalMapper::parse("DEFAULT RULE:a/b/").is_err());
+        assert!(SslPrincipalMapper::parse("SOMETHING").is_err());
+    }
+
+    #[test]
+    fn test_certificate_subject() {
+        let mut params = rcgen::CertificateParams::default();
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "Corp, Inc.");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "alice");
+        let key = rcgen::KeyPair::generate().unwrap();
+        let certificate = params.self_signed(&key).unwrap();
+
+        let mapper = SslPrincipalMapper::default();
+        assert_eq!(
+            "CN=alice,O=Corp\\, Inc.",
+            mapper.principal(certificate.der()).unwrap()
+        );
+    }
+}
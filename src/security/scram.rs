@@ -0,0 +1,413 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::{Context, bail, ensure};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use hmac::{Hmac, Mac};
+use rand::{Rng, distributions::Alphanumeric};
+use sha2::{Digest, Sha256, Sha512};
+
+use super::CredentialStore;
+
+/// The hash function of a SCRAM mechanism
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ScramMechanism {
+    Sha256,
+    Sha512,
+}
+
+impl ScramMechanism {
+    pub const ALL: [Self; 2] = [Self::Sha256, Self::Sha512];
+    /// Fewest iterations credentials may be salted with, as in Kafka
+    pub const MIN_ITERATIONS: u32 = 4096;
+    /// Most iterations credentials may be salted with, as in Kafka
+    pub const MAX_ITERATIONS: u32 = 16384;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "SCRAM-SHA-256",
+            Self::Sha512 => "SCRAM-SHA-512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    /// How the mechanism is identified in the SCRAM credential APIs
+    pub fn type_code(self) -> i8 {
+        match self {
+            Self::Sha256 => 1,
+            Self::Sha512 => 2,
+        }
+    }
+
+    pub fn from_type_code(code: i8) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.type_code() == code)
+    }
+
+    pub(crate) fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    pub(crate) fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Self::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// `Hi()` of RFC 5802, that is PBKDF2 with HMAC of the mechanism's hash
+    pub(crate) fn salt_password(self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        match self {
+            Self::Sha256 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password, salt, iterations).to_vec()
+            }
+            Self::Sha512 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha512, 64>(password, salt, iterations).to_vec()
+            }
+        }
+    }
+}
+
+impl Display for ScramMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What the broker stores of a SCRAM password: enough to verify clients knowing it,
+/// but not to impersonate them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramCredential {
+    pub salt: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+    pub iterations: u32,
+}
+
+impl ScramCredential {
+    /// Salts `password` with a random salt
+    pub fn new(mechanism: ScramMechanism, password: &str, iterations: u32) -> Self {
+        let salt: [u8; 32] = rand::random();
+        let salted_password = mechanism.salt_password(password.as_bytes(), &salt, iterations);
+        Self::from_salted_password(mechanism, &salted_password, salt.to_vec(), iterations)
+    }
+
+    /// Derives the keys from a password already salted with `salt`
+    pub fn from_salted_password(
+        mechanism: ScramMechanism,
+        salted_password: &[u8],
+        salt: Vec<u8>,
+        iterations: u32,
+    ) -> Self {
+        let client_key = mechanism.hmac(salted_password, b"Client Key");
+        Self {
+            salt,
+            stored_key: mechanism.hash(&client_key),
+            server_key: mechanism.hmac(salted_password, b"Server Key"),
+            iterations,
+        }
+    }
+
+    /// Parses the credential as written by [`Display`], e.g.
+    /// `salt=...,stored_key=...,server_key=...,iterations=4096`
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let fields: HashMap<_, _> = value.split(',').filter_map(|f| f.split_once('=')).collect();
+        let bytes = |key: &str| -> anyhow::Result<Vec<u8>> {
+            let value = fields.get(key).with_context(|| format!("Missing {key}"))?;
+            BASE64
+                .decode(value)
+                .with_context(|| format!("Invalid {key}"))
+        };
+        Ok(Self {
+            salt: bytes("salt")?,
+            stored_key: bytes("stored_key")?,
+            server_key: bytes("server_key")?,
+            iterations: fields
+                .get("iterations")
+                .context("Missing iterations")?
+                .parse()?,
+        })
+    }
+}
+
+impl Display for ScramCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "salt={},stored_key={},server_key={},iterations={}",
+            BASE64.encode(&self.salt),
+            BASE64.encode(&self.stored_key),
+            BASE64.encode(&self.server_key),
+            self.iterations
+        )
+    }
+}
+
+/// Where the server side of a SCRAM exchange is at
+#[derive(Debug)]
+enum ScramState {
+    /// Waiting for the client-first-message
+    ClientFirst,
+    /// Sent the server-first-message, waiting for the client-final-message
+    ClientFinal {
+        username: String,
+        credential: ScramCredential,
+        /// `client-first-message-bare + "," + server-first-message`, the start of the
+        /// message both sides sign
+        auth_message: String,
+        /// The gs2 header the channel binding of the final message has to repeat
+        gs2_header: String,
+        nonce: String,
+    },
+    Complete {
+        username: String,
+    },
+}
+
+/// The server side of a SCRAM exchange (RFC 5802): two messages from the client, each
+/// answered by the server. Channel binding is not supported.
+#[derive(Debug)]
+pub struct ScramServer {
+    mechanism: ScramMechanism,
+    state: ScramState,
+}
+
+impl ScramServer {
+    pub fn new(mechanism: ScramMechanism) -> Self {
+        Self {
+            mechanism,
+            state: ScramState::ClientFirst,
+        }
+    }
+
+    /// The authenticated user, once the exchange is complete
+    pub fn username(&self) -> Option<&str> {
+        match &self.state {
+            ScramState::Complete { username } => Some(username),
+            _ => None,
+        }
+    }
+
+    /// Processes the next message of the client and returns the answer to it. Any
+    /// error fails the exchange.
+    pub fn step(
+        &mut self,
+        message: &[u8],
+        credentials: &CredentialStore,
+    ) -> anyhow::Result<Vec<u8>> {
+        let message = std::str::from_utf8(message).context("Message is not valid UTF-8")?;
+        match std::mem::replace(&mut self.state, ScramState::ClientFirst) {
+            ScramState::ClientFirst => self.client_first(message, credentials),
+            ScramState::ClientFinal {
+                username,
+                credential,
+                auth_message,
+                gs2_header,
+                nonce,
+            } => {
+                let answer =
+                    self.client_final(message, &credential, &auth_message, &gs2_header, &nonce)?;
+                self.state = ScramState::Complete { username };
+                Ok(answer)
+            }
+            ScramState::Complete { .. } => bail!("The exchange is already complete"),
+        }
+    }
+
+    fn client_first(
+        &mut self,
+        message: &str,
+        credentials: &CredentialStore,
+    ) -> anyhow::Result<Vec<u8>> {
+        // gs2-header: gs2-cbind-flag "," [ authzid ] ","
+        let mut parts = message.splitn(3, ',');
+        let (Some(cbind_flag), Some(authzid), Some(bare)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("Malformed client-first-message");
+        };
+        ensure!(
+            matches!(cbind_flag, "n" | "y"),
+            "Channel binding is not supported"
+        );
+
+        let attributes = attributes(bare)?;
+        let username = decode_saslname(attributes.get("n").context("Missing username")?)?;
+        let client_nonce = attributes.get("r").context("Missing nonce")?;
+        if let Some(authzid) = authzid.strip_prefix("a=") {
+            ensure!(
+                decode_saslname(authzid)? == username,
+                "Authorization id differs from the username"
+            );
+        }
+        let credential = credentials
+            .scram(self.mechanism, &username)
+            .context("Invalid user credentials")?;
+
+        let server_nonce: String = rand::thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let nonce = format!("{client_nonce}{server_nonce}");
+        let server_first = format!(
+            "r={nonce},s={},i={}",
+            BASE64.encode(&credential.salt),
+            credential.iterations
+        );
+        let answer = server_first.clone().into_bytes();
+        self.state = ScramState::ClientFinal {
+            username,
+            credential,
+            auth_message: format!("{bare},{server_first}"),
+            gs2_header: format!("{cbind_flag},{authzid},"),
+            nonce,
+        };
+        Ok(answer)
+    }
+
+    fn client_final(
+        &self,
+        message: &str,
+        credential: &ScramCredential,
+        auth_message: &str,
+        gs2_header: &str,
+        nonce: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (without_proof, proof) = message.rsplit_once(",p=").context("Missing client proof")?;
+        let attributes = attributes(without_proof)?;
+        ensure!(
+            attributes.get("c").copied() == Some(&BASE64.encode(gs2_header)),
+            "Invalid channel binding"
+        );
+        ensure!(attributes.get("r").copied() == Some(nonce), "Invalid nonce");
+
+        let auth_message = format!("{auth_message},{without_proof}");
+        let proof = BASE64.decode(proof).context("Invalid client proof")?;
+        let signature = self
+            .mechanism
+            .hmac(&credential.stored_key, auth_message.as_bytes());
+        ensure!(proof.len() == signature.len(), "Invalid client proof");
+        let client_key: Vec<u8> = proof.iter().zip(&signature).map(|(p, s)| p ^ s).collect();
+        ensure!(
+            constant_time_eq(&self.mechanism.hash(&client_key), &credential.stored_key),
+            "Invalid user credentials"
+        );
+
+        let server_signature = self
+            .mechanism
+            .hmac(&credential.server_key, auth_message.as_bytes());
+        Ok(format!("v={}", BASE64.encode(server_signature)).into_bytes())
+    }
+}
+
+/// The `key=value` attributes of a SCRAM message. Values may contain `=`, never `,`.
+fn attributes(message: &str) -> anyhow::Result<HashMap<&str, &str>> {
+    message
+        .split(',')
+        .map(|attribute| {
+            attribute
+                .split_once('=')
+                .with_context(|| format!("Malformed attribute {attribute:?}"))
+        })
+        .collect()
+}
+
+/// Undoes the escaping of `,` and `=` in names
+fn decode_saslname(name: &str) -> anyhow::Result<String> {
+    let decoded = name.replace("=2C", ",").replace("=3D", "=");
+    ensure!(!decoded.is_empty(), "Empty username");
+    Ok(decoded)
+}
+
+/// Compares without returning early, so the time taken does not tell how much matched
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The client side of the exchange, as in the example of RFC 7677
+    #[test]
+    fn test_exchange() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials = CredentialStore::load(None, dir.path()).unwrap();
+        let mechanism = ScramMechanism::Sha256;
+        let salt = BASE64.decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap();
+        let salted_password = mechanism.salt_password(b"pencil", &salt, 4096);
+        let credential =
+            ScramCredential::from_salted_password(mechanism, &salted_password, salt, 4096);
+        assert_eq!(
+            credential,
+            ScramCredential::parse(&credential.to_string()).unwrap()
+        );
+        credentials
+            .set_scram(mechanism, "user", credential)
+            .unwrap();
+
+        let mut server = ScramServer::new(mechanism);
+        let client_first_bare = "n=user,r=rOprNGfwEbeRWgbNEkqO";
+        let server_first = server
+            .step(format!("n,,{client_first_bare}").as_bytes(), &credentials)
+            .unwrap();
+        let server_first = String::from_utf8(server_first).unwrap();
+        let nonce = server_first
+            .strip_prefix("r=")
+            .and_then(|s| s.split(',').next())
+            .unwrap();
+        assert!(nonce.starts_with("rOprNGfwEbeRWgbNEkqO"));
+        assert!(server_first.ends_with(",s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"));
+
+        let without_proof = format!("c=biws,r={nonce}");
+        let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+        let client_key = mechanism.hmac(&salted_password, b"Client Key");
+        let signature = mechanism.hmac(&mechanism.hash(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(&signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+
+        // a wrong proof fails the exchange
+        let mut wrong = ScramServer::new(mechanism);
+        wrong
+            .step(format!("n,,{client_first_bare}").as_bytes(), &credentials)
+            .unwrap();
+        assert!(
+            wrong
+                .step(
+                    format!("{without_proof},p={}", BASE64.encode(&signature)).as_bytes(),
+                    &credentials
+                )
+                .is_err()
+        );
+
+        let server_final = server
+            .step(
+                format!("{without_proof},p={}", BASE64.encode(&proof)).as_bytes(),
+                &credentials,
+            )
+            .unwrap();
+        let server_key = mechanism.hmac(&salted_password, b"Server Key");
+        let expected = mechanism.hmac(&server_key, auth_message.as_bytes());
+        assert_eq!(
+            format!("v={}", BASE64.encode(expected)).into_bytes(),
+            server_final
+        );
+        assert_eq!(Some("user"), server.username());
+    }
+}
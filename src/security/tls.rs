@@ -0,0 +1,146 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{Context, bail, ensure};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        RootCertStore, ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+    },
+    server::TlsStream,
+};
+
+use super::SslPrincipalMapper;
+use crate::config::BrokerConfig;
+
+/// `ssl.client.auth`, whether clients of SSL listeners present certificates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslClientAuth {
+    /// No certificates are asked for
+    None,
+    /// Clients without certificates are accepted, those presenting one have to be trusted
+    Requested,
+    /// Clients have to present a trusted certificate
+    Required,
+}
+
+impl SslClientAuth {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "requested" => Self::Requested,
+            "required" => Self::Required,
+            v => bail!("Unknown client authentication {v}, expected none, requested or required"),
+        })
+    }
+}
+
+impl Display for SslClientAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Requested => "requested",
+            Self::Required => "required",
+        })
+    }
+}
+
+/// Terminates TLS on the connections of `SSL` and `SASL_SSL` listeners, with the
+/// certificate and key of `ssl.keystore.location` and `ssl.key.location`. Client
+/// certificates are verified against the CAs of `ssl.truststore.location`.
+#[derive(Clone)]
+pub struct TlsServer {
+    acceptor: TlsAcceptor,
+    principal_mapper: SslPrincipalMapper,
+}
+
+impl TlsServer {
+    pub fn from_config(config: &BrokerConfig) -> anyhow::Result<Self> {
+        let keystore = config
+            .ssl_keystore_location
+            .as_deref()
+            .context("ssl.keystore.location is not set")?;
+        let certificates = read_certificates(keystore)?;
+        let key_file = config.ssl_key_location.as_deref().unwrap_or(keystore);
+        let key = read_private_key(key_file)?;
+
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?;
+        let builder = match config.ssl_client_auth {
+            SslClientAuth::None => builder.with_no_client_auth(),
+            client_auth => {
+                let truststore = config
+                    .ssl_truststore_location
+                    .as_deref()
+                    .context("ssl.truststore.location is not set")?;
+                let mut roots = RootCertStore::empty();
+                for certificate in read_certificates(truststore)? {
+                    roots.add(certificate).with_context(|| {
+                        format!("Invalid CA certificate in {}", truststore.display())
+                    })?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match client_auth {
+                    SslClientAuth::Requested => verifier.allow_unauthenticated(),
+                    _ => verifier,
+                };
+                builder.with_client_cert_verifier(verifier.build()?)
+            }
+        };
+        let server_config = builder
+            .with_single_cert(certificates, key)
+            .with_context(|| format!("Invalid certificate or key in {}", keystore.display()))?;
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            principal_mapper: config.ssl_principal_mapping_rules.clone(),
+        })
+    }
+
+    /// Performs the TLS handshake of an accepted connection
+    pub async fn accept(&self, stream: TcpStream) -> io::Result<TlsStream<TcpStream>> {
+        self.acceptor.accept(stream).await
+    }
+
+    /// The principal of a client authenticated by its certificate, mapped from the
+    /// certificate's subject by `ssl.principal.mapping.rules`. `None` if the client
+    /// presented no certificate.
+    pub fn principal(&self, stream: &TlsStream<TcpStream>) -> anyhow::Result<Option<String>> {
+        let (_, connection) = stream.get_ref();
+        match connection.peer_certificates().and_then(<[_]>::first) {
+            Some(certificate) => self.principal_mapper.principal(certificate).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn read_certificates(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let file = fs::File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Reading certificates from {}", path.display()))?;
+    ensure!(
+        !certificates.is_empty(),
+        "No certificates in {}",
+        path.display()
+    );
+    Ok(certificates)
+}
+
+fn read_private_key(path: &Path) -> anyhow::Result<PrivateKeyDer<'static>> {
+    let file = fs::File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| format!("Reading private key from {}", path.display()))?
+        .with_context(|| format!("No private key in {}", path.display()))
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail, ensure};
+
+use super::TopicPartition;
+
+/// A file mapping partitions to offsets, stored in a log directory.
+/// Uses the same text format as Kafka's checkpoint files:
+///
+/// ```text
+/// 0              <- version
+/// 2              <- number of entries
+/// foo 0 42       <- topic partition offset
+/// bar 1 1337
+/// ```
+#[derive(Debug, Clone)]
+pub struct OffsetCheckpoint {
+    path: PathBuf,
+}
+
+impl OffsetCheckpoint {
+    const VERSION: u32 = 0;
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads every entry of the checkpoint, a missing file is treated as an empty one
+    pub fn read(&self) -> anyhow::Result<HashMap<TopicPartition, i64>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", self.path.display())),
+        };
+
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing checkpoint version")?
+            .trim()
+            .parse()?;
+        if version != Self::VERSION {
+            bail!(
+                "Unsupported checkpoint version {version} in {}",
+                self.path.display()
+            );
+        }
+        let count: usize = lines
+            .next()
+            .context("Missing checkpoint entry count")?
+            .trim()
+            .parse()?;
+
+        let mut entries = HashMap::with_capacity(count);
+        for line in lines.by_ref().take(count) {
+            let mut fields = line.split_whitespace();
+            let (Some(topic), Some(partition), Some(offset), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Malformed checkpoint entry {line:?}");
+            };
+            entries.insert(
+                TopicPartition::new(topic, partition.parse()?),
+                offset.parse()?,
+            );
+        }
+        ensure!(
+            entries.len() == count,
+            "Expected {count} checkpoint entries, found {}",
+            entries.len()
+        );
+        Ok(entries)
+    }
+
+    /// Replaces the checkpoint with `entries`, see [`atomic_write`]
+    pub fn write(&self, entries: &HashMap<TopicPartition, i64>) -> anyhow::Result<()> {
+        let mut sorted: Vec<_> = entries.iter().collect();
+        sorted.sort();
+
+        let mut content = format!("{}\n{}\n", Self::VERSION, sorted.len());
+        for (tp, offset) in sorted {
+            content.push_str(&format!("{} {} {offset}\n", tp.topic, tp.partition));
+        }
+        atomic_write(&self.path, content.as_bytes())
+    }
+}
+
+/// Replaces the file at `path` with `content`. The content is written to a temporary file
+/// next to it first, synced, and then renamed over the old one, so a crash never leaves a
+/// torn file behind.
+pub fn atomic_write(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp).with_context(|| format!("Creating {}", tmp.display()))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = OffsetCheckpoint::new(dir.path().join("cleaner-offset-checkpoint"));
+        assert!(checkpoint.read().unwrap().is_empty());
+
+        let entries = HashMap::from([
+            (TopicPartition::new("foo", 0), 42),
+            (TopicPartition::new("bar", 3), 7),
+        ]);
+        checkpoint.write(&entries).unwrap();
+        assert_eq!(
+            "0\n2\nbar 3 7\nfoo 0 42\n",
+            fs::read_to_string(checkpoint.path()).unwrap()
+        );
+        assert_eq!(entries, checkpoint.read().unwrap());
+    }
+}
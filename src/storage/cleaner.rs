@@ -0,0 +1,475 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use tracing::{debug, error, info};
+
+use super::{LogConfig, LogManager, LogSegment, OffsetCheckpoint, PartitionLog, TopicPartition};
+use crate::codec::WireLen;
+
+/// Default of `log.cleaner.backoff.ms`, how long the cleaner sleeps when there is nothing to clean
+pub const DEFAULT_CLEANER_BACKOFF: Duration = Duration::from_secs(15);
+
+/// Name of the file in every log dir remembering where the dirty part of each compacted log starts
+pub const CLEANER_CHECKPOINT_FILE: &str = "cleaner-offset-checkpoint";
+
+/// Summary of a single compaction pass over a log
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanerStats {
+    pub segments_rewritten: usize,
+    pub records_read: usize,
+    pub records_retained: usize,
+    pub tombstones_removed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Split of the inactive segments of a log into the part that is already
+/// compacted and the dirty part written since the last compaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanableLog {
+    pub first_dirty_offset: i64,
+    pub clean_bytes: u64,
+    pub dirty_bytes: u64,
+}
+
+impl CleanableLog {
+    /// Share of the cleanable bytes that have not been compacted yet
+    pub fn dirty_ratio(&self) -> f64 {
+        let total = self.clean_bytes + self.dirty_bytes;
+        if total == 0 {
+            0.0
+        } else {
+            self.dirty_bytes as f64 / total as f64
+        }
+    }
+
+    /// Measures `log`, whose dirty part begins at `first_dirty_offset`.
+    /// The active segment is never cleaned, so it is not accounted for either.
+    pub fn measure(log: &PartitionLog, first_dirty_offset: i64) -> anyhow::Result<Self> {
+        let first_dirty_offset = first_dirty_offset.max(log.log_start_offset());
+        let active = log.active_segment().map(LogSegment::base_offset);
+
+        let mut clean_bytes = 0;
+        let mut dirty_bytes = 0;
+        for segment in log.segments().filter(|s| Some(s.base_offset()) != active) {
+            if segment.base_offset() >= first_dirty_offset {
+                dirty_bytes += segment.size()?;
+            } else {
+                clean_bytes += segment.size()?;
+            }
+        }
+
+        Ok(Self {
+            first_dirty_offset,
+            clean_bytes,
+            dirty_bytes,
+        })
+    }
+}
+
+/// Compacts every inactive segment of `log`, keeping only the last record of
+/// every key seen at or after `first_dirty_offset`. Offsets are preserved, the
+/// retained records are written back into batches with their original base offsets.
+/// Tombstones are dropped once their segment is older than `delete.retention.ms`.
+///
+/// The log is only locked to list its segments and to swap the cleaned copies in, so
+/// appends and fetches go on while segments are read and rewritten. Segments truncated
+/// or deleted in the meantime are left alone.
+///
+/// Returns where the dirty part of the log begins after cleaning,
+/// which is the base offset of the active segment.
+pub fn clean_log(
+    log: &Mutex<PartitionLog>,
+    first_dirty_offset: i64,
+    config: &LogConfig,
+    now: SystemTime,
+) -> anyhow::Result<(i64, CleanerStats)> {
+    let (active, segments) = {
+        let log = log.lock().unwrap();
+        let Some(active) = log.active_segment().map(LogSegment::base_offset) else {
+            return Ok((first_dirty_offset, CleanerStats::default()));
+        };
+        let segments = log
+            .segments()
+            .filter(|s| s.base_offset() < active)
+            .map(|s| Ok((s.clone(), s.size()?)))
+            .collect::<anyhow::Result<Vec<(LogSegment, u64)>>>()?;
+        (active, segments)
+    };
+
+    // the latest offset of every key in the dirty part of the log
+    let mut offset_map: HashMap<Bytes, i64> = HashMap::new();
+    for (segment, _) in &segments {
+        for batch in segment.read_batches()? {
+            if batch.is_control() {
+                continue;
+            }
+            for record in &batch.records()? {
+                let offset = batch.offset_of(record);
+                match &record.key {
+                    Some(key) if offset >= first_dirty_offset => {
+                        offset_map.insert(key.clone(), offset);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let delete_horizon = now
+        .checked_sub(Duration::from_millis(
+            config.delete_retention_ms.unsigned_abs(),
+        ))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut stats = CleanerStats::default();
+    let mut rewritten = Vec::new();
+    for (segment, size) in segments {
+        stats.bytes_before += size;
+        let tombstones_expired = segment.last_modified()? < delete_horizon;
+
+        let mut changed = false;
+        let mut cleaned = Vec::new();
+        for mut batch in segment.read_batches()? {
+            if batch.is_control() {
+                cleaned.push(batch);
+                continue;
+            }
+
+            let mut records = batch.records()?;
+            let before = records.len();
+            stats.records_read += before;
+            let base_offset = batch.base_offset;
+            records.retain(|record| {
+                let offset = base_offset + i64::from(record.offset_delta);
+                let Some(key) = &record.key else {
+                    return true;
+                };
+                let latest = offset_map.get(key).map_or(true, |&latest| offset >= latest);
+                if latest && record.is_tombstone() && tombstones_expired {
+                    stats.tombstones_removed += 1;
+                    return false;
+                }
+                latest
+            });
+            stats.records_retained += records.len();
+
+            if records.len() != before {
+                changed = true;
+                batch.set_records(&records)?;
+            }
+            if !records.is_empty() {
+                cleaned.push(batch);
+            }
+        }
+
+        if changed {
+            let index = segment.write_cleaned(&cleaned)?;
+            rewritten.push((segment, size, index, cleaned.wire_len() as u64));
+        } else {
+            stats.bytes_after += size;
+        }
+    }
+
+    let log = log.lock().unwrap();
+    for (segment, size, index, cleaned_size) in rewritten {
+        // truncation and retention only ever shrink or remove inactive segments
+        let unchanged = log
+            .segments()
+            .any(|s| s.base_offset() == segment.base_offset())
+            && segment.size()? == size;
+        if unchanged {
+            segment.swap_cleaned(&index)?;
+            stats.segments_rewritten += 1;
+            stats.bytes_after += cleaned_size;
+        } else {
+            segment.discard_cleaned()?;
+        }
+    }
+
+    Ok((active, stats))
+}
+
+/// Compacts the logs of topics with `cleanup.policy=compact`, one log at a time,
+/// always picking the one with the highest dirty ratio. The first dirty offset of
+/// every log is checkpointed into its log dir so cleaning resumes where it left off.
+#[derive(Debug)]
+pub struct LogCleaner {
+    manager: Arc<LogManager>,
+    first_dirty_offsets: Mutex<HashMap<TopicPartition, i64>>,
+}
+
+impl LogCleaner {
+    pub fn new(manager: Arc<LogManager>) -> anyhow::Result<Self> {
+        let mut first_dirty_offsets = HashMap::new();
+        for log_dir in manager.log_dirs() {
+            first_dirty_offsets.extend(checkpoint_of(log_dir).read()?);
+        }
+        Ok(Self {
+            manager,
+            first_dirty_offsets: Mutex::new(first_dirty_offsets),
+        })
+    }
+
+    fn first_dirty_offset(&self, tp: &TopicPartition) -> i64 {
+        self.first_dirty_offsets
+            .lock()
+            .unwrap()
+            .get(tp)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Cleans the filthiest log whose dirty ratio is at least its `min.cleanable.dirty.ratio`.
+    /// Returns `None` if no log needs cleaning.
+    pub fn clean_filthiest_log(
+        &self,
+        now: SystemTime,
+    ) -> anyhow::Result<Option<(TopicPartition, CleanerStats)>> {
+        let mut filthiest = None;
+        let mut highest_ratio = 0.0;
+        for log in self.manager.logs() {
+            let guard = log.lock().unwrap();
+            let tp = guard.topic_partition().clone();
+            let config = self.manager.config_for(&tp.topic);
+            if !config.cleanup_policy.compact {
+                continue;
+            }
+            let cleanable = CleanableLog::measure(&guard, self.first_dirty_offset(&tp))?;
+            let ratio = cleanable.dirty_ratio();
+            if cleanable.dirty_bytes == 0 || ratio < config.min_cleanable_dirty_ratio {
+                continue;
+            }
+            if filthiest.is_none() || ratio > highest_ratio {
+                highest_ratio = ratio;
+                filthiest = Some((Arc::clone(&log), tp, config));
+            }
+        }
+
+        let Some((log, tp, config)) = filthiest else {
+            return Ok(None);
+        };
+
+        debug!("Compacting {tp} with dirty ratio {highest_ratio:.2}");
+        let (first_dirty_offset, stats) =
+            clean_log(&log, self.first_dirty_offset(&tp), &config, now)
+                .with_context(|| format!("Compacting {tp}"))?;
+
+        let log_dir = log.lock().unwrap().dir().parent().map(Path::to_path_buf);
+        self.first_dirty_offsets
+            .lock()
+            .unwrap()
+            .insert(tp.clone(), first_dirty_offset);
+        if let Some(log_dir) = log_dir {
+            self.checkpoint(&log_dir)?;
+        }
+
+        Ok(Some((tp, stats)))
+    }
+
+    /// Writes the first dirty offsets of the logs stored in `log_dir` to its checkpoint
+    fn checkpoint(&self, log_dir: &Path) -> anyhow::Result<()> {
+        let in_dir: HashMap<TopicPartition, i64> = self
+            .first_dirty_offsets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(tp, _)| log_dir.join(tp.dir_name()).is_dir())
+            .map(|(tp, offset)| (tp.clone(), *offset))
+            .collect();
+        checkpoint_of(log_dir).write(&in_dir)
+    }
+}
+
+fn checkpoint_of(log_dir: &Path) -> OffsetCheckpoint {
+    OffsetCheckpoint::new(log_dir.join(CLEANER_CHECKPOINT_FILE))
+}
+
+/// Background task compacting logs until there is nothing left to clean,
+/// then backing off for `backoff` before checking again.
+pub async fn run_cleaner(cleaner: Arc<LogCleaner>, backoff: Duration) {
+    loop {
+        let task_cleaner = Arc::clone(&cleaner);
+        let cleaned = tokio::task::spawn_blocking(move || {
+            task_cleaner.clean_filthiest_log(SystemTime::now())
+        })
+        .await;
+        match cleaned {
+            Ok(Ok(Some((tp, stats)))) => {
+                info!(
+                    "Compacted {tp}: {} of {} records retained, {} tombstones removed, {} -> {} bytes",
+                    stats.records_retained,
+                    stats.records_read,
+                    stats.tombstones_removed,
+                    stats.bytes_before,
+                    stats.bytes_after
+                );
+                continue;
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => error!("Log cleaner failed: {e:#}"),
+            Err(e) => error!("Log cleaner task panicked: {e}"),
+        }
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::{
+        codec::Encoder,
+        types::{Record, RecordBatch},
+    };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn record(offset_delta: i32, key: &'static str, value: Option<&'static str>) -> Record {
+        Record::new(offset_delta, Some(Bytes::from(key)), value.map(Bytes::from))
+    }
+
+    fn write_segment(dir: &Path, batches: &[RecordBatch], modified: SystemTime) {
+        let mut buf = BytesMut::new();
+        for batch in batches {
+            batch.encode(&mut buf).unwrap();
+        }
+        let path = dir.join(LogSegment::file_name(
+            batches[0].base_offset,
+            LogSegment::LOG_SUFFIX,
+        ));
+        fs::write(&path, &buf).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn retained(log: &PartitionLog) -> Vec<(i64, Option<Bytes>)> {
+        log.segments()
+            .flat_map(|s| s.read_batches().unwrap())
+            .flat_map(|b| {
+                b.records()
+                    .unwrap()
+                    .iter()
+                    .map(|r| (b.offset_of(r), r.value.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn compacted_log(root: &Path, tombstone_age: Duration) -> PartitionLog {
+        let dir = root.join("changelog-0");
+        fs::create_dir(&dir).unwrap();
+        let now = SystemTime::now();
+        write_segment(
+            &dir,
+            &[RecordBatch::new(
+                0,
+                0,
+                vec![record(0, "a", Some("a1")), record(1, "b", Some("b1"))],
+            )],
+            now - tombstone_age,
+        );
+        write_segment(
+            &dir,
+            &[RecordBatch::new(
+                2,
+                0,
+                vec![record(0, "a", Some("a2")), record(1, "b", None)],
+            )],
+            now - tombstone_age,
+        );
+        write_segment(
+            &dir,
+            &[RecordBatch::new(4, 0, vec![record(0, "a", Some("a3"))])],
+            now,
+        );
+        PartitionLog::open(&dir).unwrap()
+    }
+
+    #[test]
+    fn test_keeps_latest_value_per_key() {
+        let root = tempfile::tempdir().unwrap();
+        let log = Mutex::new(compacted_log(root.path(), Duration::ZERO));
+
+        let cleanable = CleanableLog::measure(&log.lock().unwrap(), 0).unwrap();
+        assert_eq!(1.0, cleanable.dirty_ratio());
+
+        let (first_dirty, stats) =
+            clean_log(&log, 0, &LogConfig::default(), SystemTime::now()).unwrap();
+        assert_eq!(4, first_dirty);
+        assert_eq!(4, stats.records_read);
+        assert_eq!(2, stats.records_retained);
+        assert_eq!(
+            vec![
+                (2, Some(Bytes::from("a2"))),
+                (3, None),
+                (4, Some(Bytes::from("a3")))
+            ],
+            retained(&log.lock().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_removes_expired_tombstones() {
+        let root = tempfile::tempdir().unwrap();
+        let log = Mutex::new(compacted_log(root.path(), 2 * HOUR));
+        let config = LogConfig::default()
+            .with_overrides([("delete.retention.ms", "3600000")])
+            .unwrap();
+
+        let (_, stats) = clean_log(&log, 0, &config, SystemTime::now()).unwrap();
+        assert_eq!(1, stats.tombstones_removed);
+        assert_eq!(
+            vec![(2, Some(Bytes::from("a2"))), (4, Some(Bytes::from("a3")))],
+            retained(&log.lock().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_cleaner_checkpoints_first_dirty_offset() {
+        let root = tempfile::tempdir().unwrap();
+        compacted_log(root.path(), Duration::ZERO);
+        let manager = Arc::new(
+            LogManager::load(vec![root.path().to_path_buf()], LogConfig::default()).unwrap(),
+        );
+
+        let cleaner = LogCleaner::new(Arc::clone(&manager)).unwrap();
+        assert!(
+            cleaner
+                .clean_filthiest_log(SystemTime::now())
+                .unwrap()
+                .is_none()
+        );
+
+        manager
+            .set_topic_overrides("changelog", [("cleanup.policy", "compact")])
+            .unwrap();
+        let (tp, _) = cleaner
+            .clean_filthiest_log(SystemTime::now())
+            .unwrap()
+            .unwrap();
+        assert_eq!(TopicPartition::new("changelog", 0), tp);
+        assert!(
+            cleaner
+                .clean_filthiest_log(SystemTime::now())
+                .unwrap()
+                .is_none()
+        );
+
+        let cleaner = LogCleaner::new(manager).unwrap();
+        assert_eq!(4, cleaner.first_dirty_offset(&tp));
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::Display;
+
+use anyhow::{Context, bail};
+
+use crate::types::Compression;
+
+/// `cleanup.policy`, what happens to old data of a topic.
+/// Both can be enabled at once with `compact,delete`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupPolicy {
+    /// Segments are deleted once they breach `retention.ms` or `retention.bytes`
+    pub delete: bool,
+    /// Only the latest record of every key is kept
+    pub compact: bool,
+}
+
+impl CleanupPolicy {
+    pub const DELETE: Self = Self {
+        delete: true,
+        compact: false,
+    };
+    pub const COMPACT: Self = Self {
+        delete: false,
+        compact: true,
+    };
+
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let mut policy = Self {
+            delete: false,
+            compact: false,
+        };
+        for p in value.split(',').map(str::trim) {
+            match p {
+                "delete" => policy.delete = true,
+                "compact" => policy.compact = true,
+                _ => bail!("Invalid cleanup.policy {p}"),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl Display for CleanupPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.delete, self.compact) {
+            (true, true) => f.write_str("compact,delete"),
+            (false, true) => f.write_str("compact"),
+            (true, false) => f.write_str("delete"),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+/// `compression.type`, the codec batches are stored with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionType {
+    /// Keep whatever codec the producer used
+    Producer,
+    /// Recompress every batch with this codec, `uncompressed` maps to `Compression::None`
+    Codec(Compression),
+}
+
+impl CompressionType {
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let codec = match value.trim() {
+            "producer" => return Ok(Self::Producer),
+            "uncompressed" => Compression::None,
+            "gzip" => Compression::Gzip,
+            "snappy" => Compression::Snappy,
+            "lz4" => Compression::Lz4,
+            "zstd" => Compression::Zstd,
+            v => bail!("Invalid compression.type {v}"),
+        };
+        Ok(Self::Codec(codec))
+    }
+
+    /// The codec a batch compressed with `produced` is stored with
+    pub fn target(self, produced: Compression) -> Compression {
+        match self {
+            Self::Producer => produced,
+            Self::Codec(codec) => codec,
+        }
+    }
+}
+
+impl Display for CompressionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Producer => "producer",
+            Self::Codec(Compression::None) => "uncompressed",
+            Self::Codec(Compression::Gzip) => "gzip",
+            Self::Codec(Compression::Snappy) => "snappy",
+            Self::Codec(Compression::Lz4) => "lz4",
+            Self::Codec(Compression::Zstd) => "zstd",
+        })
+    }
+}
+
+/// Settings of a partition log that can be overridden per topic.
+/// Field names mirror the topic level config keys of Kafka.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    /// `retention.ms`, segments older than this are deleted, -1 means no limit
+    pub retention_ms: i64,
+    /// `retention.bytes`, the log is shrunk from its head until it fits, -1 means no limit
+    pub retention_bytes: i64,
+    /// `cleanup.policy`
+    pub cleanup_policy: CleanupPolicy,
+    /// `delete.retention.ms`, how long tombstones of compacted topics are kept around
+    pub delete_retention_ms: i64,
+    /// `min.cleanable.dirty.ratio`, the share of uncompacted bytes needed before a log is compacted
+    pub min_cleanable_dirty_ratio: f64,
+    /// `compression.type`
+    pub compression_type: CompressionType,
+    /// `max.message.bytes`, the largest batch that can be appended, after recompression
+    pub max_message_bytes: i32,
+    /// `min.insync.replicas`, how many replicas have to be in sync for `acks=all`
+    /// produces to be accepted
+    pub min_insync_replicas: i32,
+    /// `segment.bytes`, the size the active segment is rolled at
+    pub segment_bytes: i32,
+    /// `segment.ms`, the age the active segment is rolled at even if it is not full
+    pub segment_ms: i64,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            retention_ms: 7 * 24 * 60 * 60 * 1000,
+            retention_bytes: -1,
+            cleanup_policy: CleanupPolicy::DELETE,
+            delete_retention_ms: 24 * 60 * 60 * 1000,
+            min_cleanable_dirty_ratio: 0.5,
+            compression_type: CompressionType::Producer,
+            max_message_bytes: 1024 * 1024 + 12,
+            min_insync_replicas: 1,
+            segment_bytes: 1024 * 1024 * 1024,
+            segment_ms: 7 * 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Every topic level key, along with the broker level key setting its default
+    pub const KEYS: &[(&str, &str)] = &[
+        ("cleanup.policy", "log.cleanup.policy"),
+        ("compression.type", "compression.type"),
+        ("delete.retention.ms", "log.cleaner.delete.retention.ms"),
+        ("max.message.bytes", "message.max.bytes"),
+        ("min.cleanable.dirty.ratio", "log.cleaner.min.cleanable.ratio"),
+        ("min.insync.replicas", "min.insync.replicas"),
+        ("retention.bytes", "log.retention.bytes"),
+        ("retention.ms", "log.retention.ms"),
+        ("segment.bytes", "log.segment.bytes"),
+        ("segment.ms", "log.roll.ms"),
+    ];
+
+    /// The broker level key setting the default of the topic level `key`
+    pub fn broker_key(key: &str) -> Option<&'static str> {
+        Self::KEYS
+            .iter()
+            .find(|(topic_key, _)| *topic_key == key)
+            .map(|&(_, broker_key)| broker_key)
+    }
+
+    /// The topic level key whose default the broker level `key` sets
+    pub fn topic_key(key: &str) -> Option<&'static str> {
+        Self::KEYS
+            .iter()
+            .find(|(_, broker_key)| *broker_key == key)
+            .map(|&(topic_key, _)| topic_key)
+    }
+
+    /// The string form of the config named by the topic level `key`, as accepted by [`Self::set`]
+    pub fn get(&self, key: &str) -> Option<String> {
+        Some(match key {
+            "retention.ms" => self.retention_ms.to_string(),
+            "retention.bytes" => self.retention_bytes.to_string(),
+            "cleanup.policy" => self.cleanup_policy.to_string(),
+            "delete.retention.ms" => self.delete_retention_ms.to_string(),
+            "min.cleanable.dirty.ratio" => self.min_cleanable_dirty_ratio.to_string(),
+            "compression.type" => self.compression_type.to_string(),
+            "max.message.bytes" => self.max_message_bytes.to_string(),
+            "min.insync.replicas" => self.min_insync_replicas.to_string(),
+            "segment.bytes" => self.segment_bytes.to_string(),
+            "segment.ms" => self.segment_ms.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Sets the config named by the topic level `key` (e.g. `retention.ms`) from its string form
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "retention.ms" => self.retention_ms = parse_limit(key, value)?,
+            "retention.bytes" => self.retention_bytes = parse_limit(key, value)?,
+            "cleanup.policy" => self.cleanup_policy = CleanupPolicy::parse(value)?,
+            "delete.retention.ms" => {
+                self.delete_retention_ms = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(self.delete_retention_ms >= 0, "{key} cannot be negative");
+            }
+            "min.cleanable.dirty.ratio" => {
+                let ratio: f64 = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(
+                    (0.0..=1.0).contains(&ratio),
+                    "{key} must be between 0 and 1"
+                );
+                self.min_cleanable_dirty_ratio = ratio;
+            }
+            "compression.type" => self.compression_type = CompressionType::parse(value)?,
+            "max.message.bytes" => {
+                self.max_message_bytes = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(self.max_message_bytes >= 0, "{key} cannot be negative");
+            }
+            "min.insync.replicas" => {
+                self.min_insync_replicas = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(self.min_insync_replicas >= 1, "{key} must be at least 1");
+            }
+            "segment.bytes" => {
+                self.segment_bytes = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(self.segment_bytes >= 1, "{key} must be at least 1");
+            }
+            "segment.ms" => {
+                self.segment_ms = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid value {value} for {key}"))?;
+                anyhow::ensure!(self.segment_ms >= 1, "{key} must be at least 1");
+            }
+            _ => bail!("Unknown topic config {key}"),
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of `self` with the given `(key, value)` overrides applied on top
+    pub fn with_overrides<'a, I>(&self, overrides: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut config = self.clone();
+        for (key, value) in overrides {
+            config.set(key, value)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Parses a limit where -1 stands for unlimited
+fn parse_limit(key: &str, value: &str) -> anyhow::Result<i64> {
+    let limit: i64 = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid value {value} for {key}"))?;
+    anyhow::ensure!(
+        limit >= -1,
+        "Value of {key} must be at least -1, got {limit}"
+    );
+    Ok(limit)
+}
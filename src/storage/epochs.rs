@@ -0,0 +1,150 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail, ensure};
+
+use super::atomic_write;
+use crate::types::EpochEndOffset;
+
+/// Name of the file of a partition directory holding its leader epochs
+pub const LEADER_EPOCH_CHECKPOINT_FILE: &str = "leader-epoch-checkpoint";
+
+/// The offset every leader epoch of a partition started at, in the
+/// `leader-epoch-checkpoint` file of its directory. Every batch carries the epoch of
+/// the leader that appended it, so the cache can always be rebuilt from the log; the
+/// file only spares reading the whole log on startup. Uses the text format of Kafka:
+///
+/// ```text
+/// 0              <- version
+/// 2              <- number of entries
+/// 3 0            <- epoch start_offset
+/// 5 1337
+/// ```
+#[derive(Debug)]
+pub struct LeaderEpochCache {
+    path: PathBuf,
+    /// Start offset of every epoch that appended batches, in order
+    epochs: BTreeMap<i32, i64>,
+}
+
+impl LeaderEpochCache {
+    const VERSION: u32 = 0;
+
+    /// Reads the checkpoint of the partition in `dir`, a missing file is treated as an
+    /// empty one
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(LEADER_EPOCH_CHECKPOINT_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    path,
+                    epochs: BTreeMap::new(),
+                });
+            }
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        };
+
+        let mut lines = content.lines();
+        let version: u32 = lines
+            .next()
+            .context("Missing checkpoint version")?
+            .trim()
+            .parse()?;
+        if version != Self::VERSION {
+            bail!(
+                "Unsupported checkpoint version {version} in {}",
+                path.display()
+            );
+        }
+        let count: usize = lines
+            .next()
+            .context("Missing checkpoint entry count")?
+            .trim()
+            .parse()?;
+        let mut epochs = BTreeMap::new();
+        for line in lines.by_ref().take(count) {
+            let mut fields = line.split_whitespace();
+            let (Some(epoch), Some(start_offset), None) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Malformed leader epoch entry {line:?}");
+            };
+            epochs.insert(epoch.parse()?, start_offset.parse()?);
+        }
+        ensure!(
+            epochs.len() == count,
+            "Expected {count} leader epoch entries, found {}",
+            epochs.len()
+        );
+        Ok(Self { path, epochs })
+    }
+
+    /// Whether the checkpoint was ever written, the cache has to be rebuilt from the
+    /// log otherwise
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// The latest epoch, if a leader with an epoch ever took over the log
+    pub fn latest_epoch(&self) -> Option<i32> {
+        self.epochs.keys().next_back().copied()
+    }
+
+    /// Records that `epoch` appended its first batch at `start_offset`. Epochs that are
+    /// negative, as the ones of logs led without a controller, or not newer than the
+    /// latest one are ignored.
+    pub fn assign(&mut self, epoch: i32, start_offset: i64) -> anyhow::Result<()> {
+        if epoch < 0 || self.latest_epoch().is_some_and(|latest| latest >= epoch) {
+            return Ok(());
+        }
+        self.epochs.insert(epoch, start_offset);
+        self.flush()
+    }
+
+    /// Forgets the epochs starting at or after `end_offset`, the new end of the log
+    pub fn truncate_from_end(&mut self, end_offset: i64) -> anyhow::Result<()> {
+        let before = self.epochs.len();
+        self.epochs.retain(|_, &mut start| start < end_offset);
+        if self.epochs.len() == before {
+            return Ok(());
+        }
+        self.flush()
+    }
+
+    /// Forgets every epoch, as the whole log was deleted
+    pub fn clear(&mut self) -> anyhow::Result<()> {
+        self.epochs.clear();
+        self.flush()
+    }
+
+    /// The latest epoch up to `epoch` that appended batches, and the offset it ended at:
+    /// where the next epoch started, or `log_end_offset` for the last one. Epochs older
+    /// than every known one end where the first known one started, `(-1, -1)` if no
+    /// epoch is known at all.
+    pub fn end_offset_for(&self, epoch: i32, log_end_offset: i64) -> EpochEndOffset {
+        let Some((&found, _)) = self.epochs.range(..=epoch).next_back() else {
+            let first_start = self.epochs.values().next().copied().unwrap_or(-1);
+            return EpochEndOffset::new(-1, first_start);
+        };
+        let end_offset = self
+            .epochs
+            .range(found + 1..)
+            .next()
+            .map_or(log_end_offset, |(_, &next_start)| next_start);
+        EpochEndOffset::new(found, end_offset)
+    }
+
+    /// Replaces the checkpoint with the cache
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let mut content = format!("{}\n{}\n", Self::VERSION, self.epochs.len());
+        for (epoch, start_offset) in &self.epochs {
+            content.push_str(&format!("{epoch} {start_offset}\n"));
+        }
+
+        atomic_write(&self.path, content.as_bytes())
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{Context, ensure};
+
+use crate::types::RecordBatch;
+
+/// Bytes of log written between two index entries, Kafka's `index.interval.bytes`
+pub const INDEX_INTERVAL_BYTES: u64 = 4096;
+
+/// An entry of the `.index` file: the last offset of a batch, relative to the
+/// base offset of the segment, and the position in the `.log` file the batch starts at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetIndexEntry {
+    pub relative_offset: u32,
+    pub position: u32,
+}
+
+/// An entry of the `.timeindex` file: the largest timestamp seen so far
+/// and the relative offset of the batch it was seen in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeIndexEntry {
+    pub timestamp: i64,
+    pub relative_offset: u32,
+}
+
+impl OffsetIndexEntry {
+    pub const LEN: usize = 8;
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let mut buf = [0; Self::LEN];
+        buf[..4].copy_from_slice(&self.relative_offset.to_be_bytes());
+        buf[4..].copy_from_slice(&self.position.to_be_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Self {
+        Self {
+            relative_offset: u32::from_be_bytes(buf[..4].try_into().unwrap()),
+            position: u32::from_be_bytes(buf[4..8].try_into().unwrap()),
+        }
+    }
+}
+
+impl TimeIndexEntry {
+    pub const LEN: usize = 12;
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let mut buf = [0; Self::LEN];
+        buf[..8].copy_from_slice(&self.timestamp.to_be_bytes());
+        buf[8..].copy_from_slice(&self.relative_offset.to_be_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Self {
+        Self {
+            timestamp: i64::from_be_bytes(buf[..8].try_into().unwrap()),
+            relative_offset: u32::from_be_bytes(buf[8..12].try_into().unwrap()),
+        }
+    }
+}
+
+/// The sparse `.index` and `.timeindex` of a segment. A batch gets an entry once
+/// [`INDEX_INTERVAL_BYTES`] were written since the previous one, so both files can be
+/// rebuilt from the `.log` alone and come out the same as when written during appends.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SegmentIndex {
+    pub offsets: Vec<OffsetIndexEntry>,
+    pub timestamps: Vec<TimeIndexEntry>,
+    /// The largest timestamp of the batches pushed so far
+    max_timestamp: Option<i64>,
+}
+
+impl SegmentIndex {
+    /// Reads both index files, failing if either is missing or not made of whole entries
+    pub fn read(index_path: &Path, time_index_path: &Path) -> anyhow::Result<Self> {
+        let offsets = read_entries(index_path, OffsetIndexEntry::LEN)?
+            .chunks(OffsetIndexEntry::LEN)
+            .map(OffsetIndexEntry::from_bytes)
+            .collect();
+        let timestamps = read_entries(time_index_path, TimeIndexEntry::LEN)?
+            .chunks(TimeIndexEntry::LEN)
+            .map(TimeIndexEntry::from_bytes)
+            .collect();
+        Ok(Self {
+            offsets,
+            timestamps,
+            max_timestamp: None,
+        })
+    }
+
+    /// Checks that entries are increasing and point inside of a `.log` of `log_size` bytes
+    pub fn validate(&self, log_size: u64) -> anyhow::Result<()> {
+        for pair in self.offsets.windows(2) {
+            ensure!(
+                pair[0].relative_offset < pair[1].relative_offset
+                    && pair[0].position < pair[1].position,
+                "Offset index entries {pair:?} are not increasing"
+            );
+        }
+        if let Some(last) = self.offsets.last() {
+            ensure!(
+                u64::from(last.position) < log_size,
+                "Offset index points at {} past the end of the log ({log_size} bytes)",
+                last.position
+            );
+        }
+        for pair in self.timestamps.windows(2) {
+            ensure!(
+                pair[0].timestamp < pair[1].timestamp
+                    && pair[0].relative_offset <= pair[1].relative_offset,
+                "Time index entries {pair:?} are not increasing"
+            );
+        }
+        Ok(())
+    }
+
+    /// Replaces both index files with the entries of `self`
+    pub fn write(&self, index_path: &Path, time_index_path: &Path) -> anyhow::Result<()> {
+        let offsets: Vec<u8> = self.offsets.iter().flat_map(|e| e.to_bytes()).collect();
+        fs::write(index_path, offsets)
+            .with_context(|| format!("Writing {}", index_path.display()))?;
+        let timestamps: Vec<u8> = self.timestamps.iter().flat_map(|e| e.to_bytes()).collect();
+        fs::write(time_index_path, timestamps)
+            .with_context(|| format!("Writing {}", time_index_path.display()))?;
+        Ok(())
+    }
+
+    /// Adds the entries for `batch`, written at `position` of the segment starting
+    /// at `base_offset`, if it is due for them
+    pub fn push(&mut self, base_offset: i64, position: u64, batch: &RecordBatch) {
+        let max_timestamp = self
+            .max_timestamp
+            .map_or(batch.max_timestamp, |max| max.max(batch.max_timestamp));
+        self.max_timestamp = Some(max_timestamp);
+        let (offset, time) = next_entries(
+            self.offsets.last(),
+            self.timestamps.last(),
+            base_offset,
+            position,
+            batch,
+            max_timestamp,
+        );
+        self.offsets.extend(offset);
+        self.timestamps.extend(time);
+    }
+}
+
+/// Like [`SegmentIndex::push`], but only reads the last entries of the index files
+/// and appends to them, so appending to a large segment stays cheap. Once `batch` is
+/// due for entries, `max_timestamp_from` gives the largest timestamp of the batches
+/// written from the position of the last offset entry on, which the last time entry
+/// does not cover yet.
+pub fn append_entries(
+    index_path: &Path,
+    time_index_path: &Path,
+    base_offset: i64,
+    position: u64,
+    batch: &RecordBatch,
+    max_timestamp_from: impl FnOnce(u64) -> anyhow::Result<Option<i64>>,
+) -> anyhow::Result<()> {
+    let last_offset = read_last_entry(index_path, OffsetIndexEntry::LEN)?
+        .map(|b| OffsetIndexEntry::from_bytes(&b));
+    let last_time = read_last_entry(time_index_path, TimeIndexEntry::LEN)?
+        .map(|b| TimeIndexEntry::from_bytes(&b));
+    let indexed = last_offset.map_or(0, |e| u64::from(e.position));
+    let mut max_timestamp = batch.max_timestamp;
+    if position >= indexed + INDEX_INTERVAL_BYTES {
+        if let Some(max) = max_timestamp_from(indexed)? {
+            max_timestamp = max_timestamp.max(max);
+        }
+    }
+    let (offset, time) = next_entries(
+        last_offset.as_ref(),
+        last_time.as_ref(),
+        base_offset,
+        position,
+        batch,
+        max_timestamp,
+    );
+
+    if position == 0 {
+        // a new segment, the index files exist from the start even while still empty
+        append_to(index_path, &[])?;
+        append_to(time_index_path, &[])?;
+    }
+    if let Some(entry) = offset {
+        append_to(index_path, &entry.to_bytes())?;
+    }
+    if let Some(entry) = time {
+        append_to(time_index_path, &entry.to_bytes())?;
+    }
+    Ok(())
+}
+
+/// The position of the last offset entry, 0 if there is none, and the timestamp of the
+/// last time entry, the largest of the batches up to the one at that position
+pub fn last_indexed(
+    index_path: &Path,
+    time_index_path: &Path,
+) -> anyhow::Result<(u64, Option<i64>)> {
+    let position = read_last_entry(index_path, OffsetIndexEntry::LEN)?
+        .map_or(0, |b| u64::from(OffsetIndexEntry::from_bytes(&b).position));
+    let timestamp = read_last_entry(time_index_path, TimeIndexEntry::LEN)?
+        .map(|b| TimeIndexEntry::from_bytes(&b).timestamp);
+    Ok((position, timestamp))
+}
+
+/// Decides which entries `batch` gets, given the last entries of both indexes and the
+/// largest timestamp of the segment up to and including `batch`
+fn next_entries(
+    last_offset: Option<&OffsetIndexEntry>,
+    last_time: Option<&TimeIndexEntry>,
+    base_offset: i64,
+    position: u64,
+    batch: &RecordBatch,
+    max_timestamp: i64,
+) -> (Option<OffsetIndexEntry>, Option<TimeIndexEntry>) {
+    let indexed = last_offset.map_or(0, |e| u64::from(e.position));
+    if position < indexed + INDEX_INTERVAL_BYTES {
+        return (None, None);
+    }
+    // segments never grow past 4 GiB or 2^32 offsets, like in Kafka
+    let relative_offset = (batch.last_offset() - base_offset) as u32;
+    let offset = OffsetIndexEntry {
+        relative_offset,
+        position: position as u32,
+    };
+    let time = last_time
+        .map_or(true, |e| max_timestamp > e.timestamp)
+        .then_some(TimeIndexEntry {
+            timestamp: max_timestamp,
+            relative_offset,
+        });
+    (Some(offset), time)
+}
+
+/// The position of the `.log` file a read of `offset` can start at: the one of the last
+/// indexed batch ending before it, or the start of the file if there is none
+pub fn position_for_offset(
+    index_path: &Path,
+    base_offset: i64,
+    offset: i64,
+) -> anyhow::Result<u64> {
+    let entry = find_last_entry(index_path, OffsetIndexEntry::LEN, |b| {
+        base_offset + i64::from(OffsetIndexEntry::from_bytes(b).relative_offset) < offset
+    })?;
+    Ok(entry.map_or(0, |b| u64::from(OffsetIndexEntry::from_bytes(&b).position)))
+}
+
+/// The offset a search for the first record at or after `timestamp` can start at: every
+/// batch up to the last time entry below `timestamp` only holds older records. `None` if
+/// there is no such entry, and the search starts at the beginning of the segment.
+pub fn offset_for_timestamp(
+    time_index_path: &Path,
+    base_offset: i64,
+    timestamp: i64,
+) -> anyhow::Result<Option<i64>> {
+    let entry = find_last_entry(time_index_path, TimeIndexEntry::LEN, |b| {
+        TimeIndexEntry::from_bytes(b).timestamp < timestamp
+    })?;
+    Ok(entry.map(|b| base_offset + i64::from(TimeIndexEntry::from_bytes(&b).relative_offset) + 1))
+}
+
+/// Binary searches the index file at `path` for the last entry `below` holds for, which
+/// has to hold for a prefix of the entries. Only the entries the search visits are read,
+/// a missing file has no entries.
+fn find_last_entry(
+    path: &Path,
+    entry_len: usize,
+    below: impl Fn(&[u8]) -> bool,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+    };
+    let mut entry = vec![0; entry_len];
+    let mut found = None;
+    // entries [0, low) hold, entries [high, len) do not
+    let (mut low, mut high) = (0, file.metadata()?.len() / entry_len as u64);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        file.seek(SeekFrom::Start(middle * entry_len as u64))?;
+        file.read_exact(&mut entry)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        if below(&entry) {
+            found = Some(entry.clone());
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(found)
+}
+
+fn read_entries(path: &Path, entry_len: usize) -> anyhow::Result<Vec<u8>> {
+    let data = fs::read(path).with_context(|| format!("Reading {}", path.display()))?;
+    ensure!(
+        data.len() % entry_len == 0,
+        "{} has a partial entry, its size {} is not a multiple of {entry_len}",
+        path.display(),
+        data.len()
+    );
+    Ok(data)
+}
+
+fn read_last_entry(path: &Path, entry_len: usize) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+    };
+    let len = file.metadata()?.len();
+    if len < entry_len as u64 {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(
+        len - len % entry_len as u64 - entry_len as u64,
+    ))?;
+    let mut entry = vec![0; entry_len];
+    file.read_exact(&mut entry)?;
+    Ok(Some(entry))
+}
+
+fn append_to(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    fs::File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(bytes))
+        .with_context(|| format!("Appending to {}", path.display()))
+}
@@ -0,0 +1,941 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use thiserror::Error;
+use tracing::warn;
+
+use super::{LeaderEpochCache, LogConfig, LogSegment};
+use crate::{
+    WireLen,
+    types::{EpochEndOffset, RecordBatch},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TopicPartition {
+    pub topic: String,
+    pub partition: i32,
+}
+
+impl TopicPartition {
+    pub fn new(topic: impl Into<String>, partition: i32) -> Self {
+        Self {
+            topic: topic.into(),
+            partition,
+        }
+    }
+
+    /// Parses a partition directory name of the form `<topic>-<partition>`.
+    /// Topic names may contain dashes, so the partition is whatever
+    /// follows the last one.
+    pub fn from_dir_name(name: &str) -> Option<Self> {
+        let (topic, partition) = name.rsplit_once('-')?;
+        if topic.is_empty() {
+            return None;
+        }
+        Some(Self::new(topic, partition.parse().ok()?))
+    }
+
+    pub fn dir_name(&self) -> String {
+        format!("{}-{}", self.topic, self.partition)
+    }
+}
+
+impl Display for TopicPartition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.topic, self.partition)
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("batch of {size} bytes is larger than max.message.bytes {max_message_bytes}")]
+pub struct RecordBatchTooLarge {
+    pub size: usize,
+    pub max_message_bytes: i32,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("offset {offset} is out of range, it has to be between {start} and {end}")]
+pub struct OffsetOutOfRange {
+    pub offset: i64,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// A record found by its timestamp, along with the leader epoch it was written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampAndOffset {
+    pub timestamp: i64,
+    pub offset: i64,
+    pub leader_epoch: i32,
+}
+
+/// The log of a single partition: the ordered set of segments
+/// found in its directory, and the first offset still available to readers.
+#[derive(Debug)]
+pub struct PartitionLog {
+    topic_partition: TopicPartition,
+    dir: PathBuf,
+    segments: BTreeMap<i64, LogSegment>,
+    log_start_offset: i64,
+    /// Set by the replica manager once the partition is replicated, until then every
+    /// record counts as committed
+    high_watermark: Option<i64>,
+    /// The offset the next appended record gets, the log end offset
+    next_offset: i64,
+    /// The max timestamp of the first batch of the active segment, `segment.ms` is
+    /// measured from it
+    rolling_timestamp: Option<i64>,
+    epochs: LeaderEpochCache,
+}
+
+impl PartitionLog {
+    /// Opens the partition log stored in `dir`, picking up every segment in it.
+    pub fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        let name = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .with_context(|| format!("Invalid partition directory {}", dir.display()))?;
+        let topic_partition = TopicPartition::from_dir_name(name)
+            .with_context(|| format!("Directory {name} is not a partition directory"))?;
+
+        let mut segments = BTreeMap::new();
+        for entry in fs::read_dir(&dir).with_context(|| format!("Reading {}", dir.display()))? {
+            let entry = entry?;
+            let Some(base_offset) = entry
+                .file_name()
+                .to_str()
+                .and_then(LogSegment::parse_base_offset)
+            else {
+                continue;
+            };
+            segments.insert(base_offset, LogSegment::new(&dir, base_offset));
+        }
+
+        let log_start_offset = segments.keys().next().copied().unwrap_or(0);
+        let epochs = LeaderEpochCache::load(&dir)?;
+
+        let mut log = Self {
+            topic_partition,
+            dir,
+            segments,
+            log_start_offset,
+            high_watermark: None,
+            next_offset: log_start_offset,
+            rolling_timestamp: None,
+            epochs,
+        };
+        log.next_offset = log.read_log_end_offset()?;
+        log.rolling_timestamp = log.read_rolling_timestamp()?;
+        Ok(log)
+    }
+
+    pub fn topic_partition(&self) -> &TopicPartition {
+        &self.topic_partition
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn log_start_offset(&self) -> i64 {
+        self.log_start_offset
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &LogSegment> {
+        self.segments.values()
+    }
+
+    /// The segment currently being appended to, which is always the one
+    /// with the highest base offset
+    pub fn active_segment(&self) -> Option<&LogSegment> {
+        self.segments.values().next_back()
+    }
+
+    /// Total size of every segment in bytes
+    pub fn size(&self) -> anyhow::Result<u64> {
+        let mut total = 0;
+        for segment in self.segments.values() {
+            total += segment.size()?;
+        }
+        Ok(total)
+    }
+
+    /// The offset the next appended record will get
+    pub fn log_end_offset(&self) -> i64 {
+        self.next_offset
+    }
+
+    /// Finds the log end offset in the newest segment holding any batch. Only needed
+    /// when the log is opened or its segments were truncated.
+    fn read_log_end_offset(&self) -> anyhow::Result<i64> {
+        let base = self
+            .active_segment()
+            .map_or(self.log_start_offset, LogSegment::base_offset);
+        for segment in self.segments.values().rev() {
+            if let Some(next_offset) = segment.next_offset()? {
+                return Ok(next_offset.max(base));
+            }
+        }
+        Ok(base.max(self.log_start_offset))
+    }
+
+    fn read_rolling_timestamp(&self) -> anyhow::Result<Option<i64>> {
+        match self.active_segment() {
+            Some(segment) => segment.first_timestamp(),
+            None => Ok(None),
+        }
+    }
+
+    /// Offsets below this one are committed, that is in the log of every in sync replica,
+    /// and visible to consumers. Logs whose high watermark was never set commit every
+    /// record as soon as it is written.
+    pub fn high_watermark(&self) -> i64 {
+        self.high_watermark.unwrap_or(self.next_offset)
+    }
+
+    /// Moves the high watermark, kept within the log start and log end offsets
+    pub fn set_high_watermark(&mut self, offset: i64) {
+        self.high_watermark = Some(offset.clamp(self.log_start_offset, self.next_offset));
+    }
+
+    /// Validates the segments which may hold data written after `recovery_point`, the
+    /// offset up to which the log was known to be flushed, truncating torn batches off
+    /// their end. Once a segment had to be truncated every segment after it is deleted,
+    /// as their data cannot follow a gap. Segments below the recovery point only get
+    /// their indexes checked. The leader epochs are brought in line with what is left.
+    /// Returns the new recovery point, the log end offset.
+    pub fn recover(&mut self, recovery_point: i64) -> anyhow::Result<i64> {
+        // the segment holding the recovery point is the last one starting at or before it
+        let first_unflushed = self
+            .segments
+            .range(..=recovery_point)
+            .next_back()
+            .map_or(i64::MIN, |(&base, _)| base);
+
+        let mut truncated_at = None;
+        for (&base_offset, segment) in &self.segments {
+            if base_offset < first_unflushed {
+                segment.check_indexes()?;
+                continue;
+            }
+            let truncated = segment.recover().with_context(|| {
+                format!(
+                    "Recovering segment {base_offset} of {}",
+                    self.topic_partition
+                )
+            })?;
+            if truncated > 0 {
+                warn!(
+                    "Truncated {truncated} bytes of torn batches off segment {base_offset} of {}",
+                    self.topic_partition
+                );
+                truncated_at = Some(base_offset);
+                break;
+            }
+        }
+
+        if let Some(truncated_at) = truncated_at {
+            let later: Vec<i64> = self
+                .segments
+                .range(truncated_at + 1..)
+                .map(|(&base, _)| base)
+                .collect();
+            for base_offset in later {
+                warn!(
+                    "Deleting segment {base_offset} of {}, which follows a truncated one",
+                    self.topic_partition
+                );
+                self.segments[&base_offset].delete()?;
+                self.segments.remove(&base_offset);
+            }
+        }
+
+        // epochs of batches appended after the epochs were last checkpointed are picked
+        // up from the unflushed segments, without a checkpoint from every segment
+        let rescan_from = if self.epochs.exists() {
+            first_unflushed
+        } else {
+            i64::MIN
+        };
+        let log_end_offset = self.read_log_end_offset()?;
+        self.next_offset = log_end_offset;
+        self.rolling_timestamp = self.read_rolling_timestamp()?;
+        self.epochs.truncate_from_end(log_end_offset)?;
+        for segment in self.segments.range(rescan_from..).map(|(_, segment)| segment) {
+            for batch in segment.read_batches()? {
+                self.epochs
+                    .assign(batch.partition_leader_epoch, batch.base_offset)?;
+            }
+        }
+        self.epochs.flush()?;
+        Ok(log_end_offset)
+    }
+
+    /// Restores a log start offset checkpointed on shutdown. It is kept within the
+    /// segments still on disk, as recovery may have truncated the log below it.
+    pub fn restore_log_start_offset(&mut self, offset: i64) {
+        self.log_start_offset = self.log_start_offset.max(offset.min(self.next_offset));
+    }
+
+    /// Forces the active segment, the only one still written to, to disk. Segments are
+    /// flushed when they are rolled.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        match self.active_segment() {
+            Some(segment) => segment.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends `batch` to the active segment, assigning it offsets starting at the log end
+    /// offset. A new segment is rolled first if the batch would take the active one past
+    /// `segment.bytes`, or if it is `segment.ms` younger than the first batch of it. The
+    /// records are decoded once, which fails if they are invalid, and the batch is
+    /// recompressed only if `compression.type` asks for a different codec than the
+    /// producer used. Batches larger than `max.message.bytes` after that fail with
+    /// [`RecordBatchTooLarge`]. Returns the base offset given to the batch.
+    pub fn append(&mut self, mut batch: RecordBatch, config: &LogConfig) -> anyhow::Result<i64> {
+        let produced = batch.compression()?;
+        let target = config.compression_type.target(produced);
+        if target == produced {
+            batch.records()?;
+        } else {
+            batch.set_compression(target)?;
+        }
+        let size = batch.wire_len();
+        anyhow::ensure!(
+            size <= usize::try_from(config.max_message_bytes).unwrap_or_default(),
+            RecordBatchTooLarge {
+                size,
+                max_message_bytes: config.max_message_bytes
+            }
+        );
+
+        let base_offset = self.next_offset;
+        batch.base_offset = base_offset;
+        self.append_batch(&batch, config)?;
+        Ok(base_offset)
+    }
+
+    /// Appends a batch fetched from the leader as is, keeping its offsets, which have to
+    /// follow the ones already in the log. Validation and recompression were done by the
+    /// leader when it appended the batch. Segments are rolled as in [`Self::append`].
+    pub fn append_as_follower(
+        &mut self,
+        batch: &RecordBatch,
+        config: &LogConfig,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            batch.base_offset >= self.next_offset,
+            "Batch at {} of {} is below the log end offset {}",
+            batch.base_offset,
+            self.topic_partition,
+            self.next_offset
+        );
+        self.append_batch(batch, config)
+    }
+
+    fn append_batch(&mut self, batch: &RecordBatch, config: &LogConfig) -> anyhow::Result<()> {
+        let base_offset = batch.base_offset;
+        if self.should_roll(batch, config)? {
+            self.roll(base_offset)?;
+        }
+        let segment = self
+            .segments
+            .entry(
+                self.active_segment()
+                    .map_or(base_offset, LogSegment::base_offset),
+            )
+            .or_insert_with(|| LogSegment::new(&self.dir, base_offset));
+        segment.append(batch).with_context(|| {
+            format!(
+                "Appending batch at {base_offset} to {}",
+                self.topic_partition
+            )
+        })?;
+        self.next_offset = batch.next_offset();
+        self.rolling_timestamp.get_or_insert(batch.max_timestamp);
+        self.epochs
+            .assign(batch.partition_leader_epoch, base_offset)
+    }
+
+    /// Whether `batch` has to go to a new segment: the active one would grow past
+    /// `segment.bytes`, is older than `segment.ms`, or cannot index the batch offsets
+    /// relative to its base offset. An empty active segment is never rolled.
+    fn should_roll(&self, batch: &RecordBatch, config: &LogConfig) -> anyhow::Result<bool> {
+        let Some(segment) = self.active_segment() else {
+            return Ok(false);
+        };
+        let size = match segment.size() {
+            Ok(size) => size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if size == 0 {
+            return Ok(false);
+        }
+        let full = size + batch.wire_len() as u64 > u64::try_from(config.segment_bytes)?;
+        let expired = self
+            .rolling_timestamp
+            .is_some_and(|first| batch.max_timestamp - first > config.segment_ms);
+        let out_of_range = batch.last_offset() - segment.base_offset() > i64::from(u32::MAX);
+        Ok(full || expired || out_of_range)
+    }
+
+    /// Flushes the active segment and starts a new one at `base_offset`
+    fn roll(&mut self, base_offset: i64) -> anyhow::Result<()> {
+        self.flush()
+            .with_context(|| format!("Flushing {} before rolling it", self.topic_partition))?;
+        self.segments
+            .insert(base_offset, LogSegment::new(&self.dir, base_offset));
+        self.rolling_timestamp = None;
+        Ok(())
+    }
+
+    /// The batches holding the records from `offset` on, stopping before the first batch
+    /// reaching `max_offset` and once `max_bytes` were read. The first batch is returned
+    /// even if it is larger than `max_bytes`, so a large batch cannot stall its readers.
+    /// Offsets outside of the log fail with [`OffsetOutOfRange`].
+    pub fn read(
+        &self,
+        offset: i64,
+        max_offset: i64,
+        max_bytes: usize,
+    ) -> anyhow::Result<Vec<RecordBatch>> {
+        let log_end_offset = self.next_offset;
+        anyhow::ensure!(
+            (self.log_start_offset..=log_end_offset).contains(&offset),
+            OffsetOutOfRange {
+                offset,
+                start: self.log_start_offset,
+                end: log_end_offset
+            }
+        );
+
+        // the segment holding the offset is the last one starting at or before it
+        let first = self
+            .segments
+            .range(..=offset)
+            .next_back()
+            .map_or(i64::MIN, |(&base, _)| base);
+        let mut batches = Vec::new();
+        let mut size = 0;
+        for segment in self.segments.range(first..).map(|(_, segment)| segment) {
+            // the offset index skips most of the batches of the first segment below `offset`
+            let position = if segment.base_offset() == first {
+                segment.position_for_offset(offset)?
+            } else {
+                0
+            };
+            for batch in segment.batches_from(position)? {
+                let batch = batch?;
+                if batch.next_offset() <= offset {
+                    continue;
+                }
+                let len = batch.wire_len();
+                if batch.last_offset() >= max_offset
+                    || (!batches.is_empty() && size + len > max_bytes)
+                {
+                    return Ok(batches);
+                }
+                size += len;
+                batches.push(batch);
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Finds the first record from the log start offset on with a timestamp at or after
+    /// `timestamp`. The time index of every segment tells where the search through its
+    /// batches starts, segments holding older records only are passed over that way.
+    pub fn offset_for_timestamp(
+        &self,
+        timestamp: i64,
+    ) -> anyhow::Result<Option<TimestampAndOffset>> {
+        for segment in self.segments.values() {
+            let start = segment
+                .search_start_for_timestamp(timestamp)?
+                .max(self.log_start_offset);
+            for batch in segment.batches_from(segment.position_for_offset(start)?)? {
+                let batch = batch?;
+                if batch.next_offset() <= start || batch.max_timestamp < timestamp {
+                    continue;
+                }
+                let records = batch.records()?;
+                let found = records.iter().find(|r| {
+                    batch.offset_of(r) >= start && batch.timestamp_of(r) >= timestamp
+                });
+                if let Some(record) = found {
+                    return Ok(Some(TimestampAndOffset {
+                        timestamp: batch.timestamp_of(record),
+                        offset: batch.offset_of(record),
+                        leader_epoch: batch.partition_leader_epoch,
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes every batch holding records at or after `offset`, as a follower does with
+    /// records its new leader may not have. Batches are removed as a whole, so the new log
+    /// end offset may end up below `offset`. Returns the new log end offset.
+    pub fn truncate_to(&mut self, offset: i64) -> anyhow::Result<i64> {
+        if offset >= self.next_offset {
+            return Ok(self.next_offset);
+        }
+        let later: Vec<i64> = self
+            .segments
+            .range(offset.max(self.log_start_offset) + 1..)
+            .map(|(&base, _)| base)
+            .collect();
+        // the first segment stays, even if empty, as it holds the log start offset
+        for base_offset in later {
+            self.segments[&base_offset].delete()?;
+            self.segments.remove(&base_offset);
+        }
+        if let Some(segment) = self.active_segment() {
+            let batches: Vec<RecordBatch> = segment
+                .read_batches()?
+                .into_iter()
+                .filter(|b| b.next_offset() <= offset)
+                .collect();
+            segment.replace_batches(&batches).with_context(|| {
+                format!("Truncating {} to {offset}", self.topic_partition)
+            })?;
+        }
+
+        let log_end_offset = self.read_log_end_offset()?;
+        self.next_offset = log_end_offset;
+        self.rolling_timestamp = self.read_rolling_timestamp()?;
+        self.high_watermark = self.high_watermark.map(|hw| hw.min(log_end_offset));
+        self.epochs.truncate_from_end(log_end_offset)?;
+        Ok(log_end_offset)
+    }
+
+    /// Deletes every segment and restarts the log, empty, at `offset`. Used by followers
+    /// that fell so far behind that the leader already deleted the records they need.
+    pub fn truncate_fully_and_start_at(&mut self, offset: i64) -> anyhow::Result<()> {
+        for segment in std::mem::take(&mut self.segments).into_values() {
+            segment.delete().with_context(|| {
+                format!(
+                    "Deleting segment {} of {}",
+                    segment.base_offset(),
+                    self.topic_partition
+                )
+            })?;
+        }
+        self.log_start_offset = offset;
+        self.high_watermark = Some(offset);
+        self.next_offset = offset;
+        self.rolling_timestamp = None;
+        self.epochs.clear()
+    }
+
+    /// Records that `epoch` starts at the log end offset, as a new leader does before it
+    /// appends anything
+    pub fn assign_epoch(&mut self, epoch: i32) -> anyhow::Result<()> {
+        self.epochs.assign(epoch, self.next_offset)
+    }
+
+    /// The latest leader epoch of the log, if it was ever led by a leader with an epoch
+    pub fn latest_epoch(&self) -> Option<i32> {
+        self.epochs.latest_epoch()
+    }
+
+    /// The latest leader epoch up to `epoch` that appended batches, and the offset it
+    /// ended at: where the next epoch started, or the log end offset for the last one.
+    /// See [`LeaderEpochCache::end_offset_for`] for epochs that are not in the log.
+    pub fn end_offset_for_epoch(&self, epoch: i32) -> EpochEndOffset {
+        self.epochs.end_offset_for(epoch, self.next_offset)
+    }
+
+    /// Checks that the log of a replica ending at `fetch_offset`, whose last batch was
+    /// appended in `last_fetched_epoch`, is a prefix of this one. If not, returns the
+    /// epoch and end offset the replica has to truncate to. Replicas without a last
+    /// fetched epoch are not checked.
+    pub fn validate_fetch(
+        &self,
+        fetch_offset: i64,
+        last_fetched_epoch: i32,
+    ) -> Option<EpochEndOffset> {
+        if last_fetched_epoch < 0 || fetch_offset <= self.log_start_offset {
+            return None;
+        }
+        let end = self.end_offset_for_epoch(last_fetched_epoch);
+        let diverged = end.epoch != last_fetched_epoch || end.end_offset < fetch_offset;
+        diverged.then_some(end)
+    }
+
+    /// Where a replica diverging from its leader truncates to: the end of the diverging
+    /// epoch in whichever of the two logs it ends first
+    pub fn truncation_offset(&self, diverging: &EpochEndOffset) -> i64 {
+        let local = self.end_offset_for_epoch(diverging.epoch);
+        diverging
+            .end_offset
+            .min(local.end_offset)
+            .max(self.log_start_offset)
+    }
+
+    /// Makes every record before `offset` unavailable by advancing the log start offset,
+    /// then deletes the segments which only hold records below it.
+    /// Returns the new log start offset, the low watermark of the partition.
+    pub fn delete_records_before(&mut self, offset: i64) -> anyhow::Result<i64> {
+        let high_watermark = self.high_watermark();
+        anyhow::ensure!(
+            (0..=high_watermark).contains(&offset),
+            OffsetOutOfRange {
+                offset,
+                start: 0,
+                end: high_watermark
+            }
+        );
+
+        self.log_start_offset = self.log_start_offset.max(offset);
+        let start = self.log_start_offset;
+        let bases: Vec<i64> = self.segments.keys().copied().collect();
+        self.delete_oldest_segments(|segment| {
+            // a segment only holds records below the start if the next one begins at or before it
+            let next = bases.iter().find(|&&b| b > segment.base_offset());
+            Ok(next.is_some_and(|&next| next <= start))
+        })?;
+        Ok(self.log_start_offset)
+    }
+
+    /// Deletes segments from the head of the log for as long as `should_delete`
+    /// returns true, stopping at the first segment it rejects. The active segment
+    /// is never deleted. The log start offset is advanced to the base offset of
+    /// the first remaining segment. Returns the number of deleted segments.
+    pub fn delete_oldest_segments<F>(&mut self, mut should_delete: F) -> anyhow::Result<usize>
+    where
+        F: FnMut(&LogSegment) -> anyhow::Result<bool>,
+    {
+        let deletable: Vec<i64> = self
+            .segments
+            .keys()
+            .take(self.segments.len().saturating_sub(1))
+            .copied()
+            .collect();
+
+        let mut deleted = 0;
+        for base_offset in deletable {
+            let segment = &self.segments[&base_offset];
+            if !should_delete(segment)? {
+                break;
+            }
+            segment.delete().with_context(|| {
+                format!("Deleting segment {base_offset} of {}", self.topic_partition)
+            })?;
+            self.segments.remove(&base_offset);
+            deleted += 1;
+        }
+
+        if let Some(&first) = self.segments.keys().next() {
+            self.log_start_offset = self.log_start_offset.max(first);
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::{
+        codec::Encoder,
+        storage::{CompressionType, LEADER_EPOCH_CHECKPOINT_FILE, SegmentIndex},
+        types::{Compression, Record},
+    };
+
+    #[test]
+    fn test_topic_partition_from_dir_name() {
+        assert_eq!(
+            Some(TopicPartition::new("foo", 0)),
+            TopicPartition::from_dir_name("foo-0")
+        );
+        assert_eq!(
+            Some(TopicPartition::new("__consumer-offsets", 12)),
+            TopicPartition::from_dir_name("__consumer-offsets-12")
+        );
+        assert_eq!(None, TopicPartition::from_dir_name("foo"));
+        assert_eq!(None, TopicPartition::from_dir_name("-1"));
+        assert_eq!(None, TopicPartition::from_dir_name("foo-bar"));
+    }
+
+    #[test]
+    fn test_never_deletes_active_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        for base_offset in [0, 10, 20] {
+            fs::write(
+                dir.join(LogSegment::file_name(base_offset, ".log")),
+                b"data",
+            )
+            .unwrap();
+        }
+
+        let mut log = PartitionLog::open(&dir).unwrap();
+        assert_eq!(0, log.log_start_offset());
+
+        let deleted = log.delete_oldest_segments(|_| Ok(true)).unwrap();
+        assert_eq!(2, deleted);
+        assert_eq!(20, log.log_start_offset());
+        assert_eq!(Some(20), log.active_segment().map(LogSegment::base_offset));
+        assert!(!dir.join(LogSegment::file_name(0, ".log")).exists());
+    }
+
+    #[test]
+    fn test_delete_records_before() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        for base_offset in [0, 2, 4] {
+            let batch = RecordBatch::new(
+                base_offset,
+                0,
+                vec![Record::new(0, None, None), Record::new(1, None, None)],
+            );
+            let mut buf = BytesMut::new();
+            batch.encode(&mut buf).unwrap();
+            fs::write(dir.join(LogSegment::file_name(base_offset, ".log")), buf).unwrap();
+        }
+
+        let mut log = PartitionLog::open(&dir).unwrap();
+        assert_eq!(6, log.high_watermark());
+
+        // offset 3 is in the middle of the second segment, so only the first one can go
+        assert_eq!(3, log.delete_records_before(3).unwrap());
+        assert_eq!(2, log.segments().count());
+
+        // moving backwards keeps the current start
+        assert_eq!(3, log.delete_records_before(1).unwrap());
+
+        let err = log.delete_records_before(7).unwrap_err();
+        assert!(err.downcast_ref::<OffsetOutOfRange>().is_some());
+    }
+
+    #[test]
+    fn test_append_recompresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let records = || vec![Record::new(0, None, Some(bytes::Bytes::from("value")))];
+
+        let mut config = LogConfig::default();
+        let mut batch = RecordBatch::new(0, 0, records());
+        batch.set_compression(Compression::Gzip).unwrap();
+        assert_eq!(0, log.append(batch, &config).unwrap());
+
+        config.compression_type = CompressionType::Codec(Compression::Zstd);
+        assert_eq!(
+            1,
+            log.append(RecordBatch::new(0, 0, records()), &config)
+                .unwrap()
+        );
+
+        let batches = log.active_segment().unwrap().read_batches().unwrap();
+        let codecs: Vec<_> = batches.iter().map(|b| b.compression().unwrap()).collect();
+        assert_eq!(vec![Compression::Gzip, Compression::Zstd], codecs);
+        assert_eq!(
+            vec![0, 1],
+            batches.iter().map(|b| b.base_offset).collect::<Vec<_>>()
+        );
+        assert_eq!(2, log.log_end_offset());
+
+        config.max_message_bytes = 10;
+        let err = log
+            .append(RecordBatch::new(0, 0, records()), &config)
+            .unwrap_err();
+        assert!(err.downcast_ref::<RecordBatchTooLarge>().is_some());
+    }
+
+    #[test]
+    fn test_append_rolls_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let batch = |timestamp| {
+            let records = vec![Record::new(0, None, Some(bytes::Bytes::from("value")))];
+            RecordBatch::new(0, timestamp, records)
+        };
+
+        let mut config = LogConfig {
+            segment_bytes: i32::try_from(batch(0).wire_len() * 2).unwrap(),
+            ..LogConfig::default()
+        };
+        for _ in 0..3 {
+            log.append(batch(0), &config).unwrap();
+        }
+        let bases: Vec<i64> = log.segments().map(LogSegment::base_offset).collect();
+        assert_eq!(vec![0, 2], bases);
+
+        config.segment_bytes = i32::MAX;
+        config.segment_ms = 1000;
+        log.append(batch(1000), &config).unwrap();
+        assert_eq!(2, log.segments().count());
+        log.append(batch(1001), &config).unwrap();
+        assert_eq!(Some(4), log.active_segment().map(LogSegment::base_offset));
+
+        // the age of the active segment is picked up again on open
+        let mut log = PartitionLog::open(&dir).unwrap();
+        log.append(batch(2002), &config).unwrap();
+        assert_eq!(Some(5), log.active_segment().map(LogSegment::base_offset));
+        assert_eq!(6, log.log_end_offset());
+    }
+
+    #[test]
+    fn test_indexed_lookups() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default();
+        // timestamps go up by 10 per record, except for a late one at offset 60
+        for i in 0..200 {
+            let timestamp = if i == 60 { 5000 } else { i * 10 };
+            let records = vec![Record::new(0, None, Some(bytes::Bytes::from(vec![0; 100])))];
+            log.append(RecordBatch::new(0, timestamp, records), &config)
+                .unwrap();
+        }
+
+        assert!(log.active_segment().unwrap().position_for_offset(150).unwrap() > 0);
+        let batches = log.read(150, i64::MAX, 1).unwrap();
+        assert_eq!(vec![150], batches.iter().map(|b| b.base_offset).collect::<Vec<_>>());
+
+        let find = |timestamp| {
+            log.offset_for_timestamp(timestamp)
+                .unwrap()
+                .map(|found| (found.offset, found.timestamp))
+        };
+        assert_eq!(Some((0, 0)), find(-5));
+        assert_eq!(Some((40, 400)), find(395));
+        // later entries of the time index are past the late record, it is found anyway
+        assert_eq!(Some((60, 5000)), find(2000));
+        assert_eq!(None, find(5001));
+
+        // rebuilding the indexes from the log gives the entries written by the appends
+        let segment = log.active_segment().unwrap();
+        let read = || SegmentIndex::read(&segment.index_path(), &segment.time_index_path());
+        let appended = read().unwrap();
+        segment.rebuild_indexes().unwrap();
+        assert_eq!(appended, read().unwrap());
+    }
+
+    #[test]
+    fn test_recover_truncates_torn_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default();
+        for _ in 0..3 {
+            let records = vec![Record::new(0, None, Some(bytes::Bytes::from("value")))];
+            log.append(RecordBatch::new(0, 0, records), &config)
+                .unwrap();
+        }
+        let segment = log.active_segment().unwrap().clone();
+        let size = segment.size().unwrap();
+
+        // a second segment after the torn one cannot be trusted either
+        fs::write(dir.join(LogSegment::file_name(3, ".log")), b"").unwrap();
+        // tear the last batch
+        let file = fs::File::options()
+            .write(true)
+            .open(segment.log_path())
+            .unwrap();
+        file.set_len(size - 3).unwrap();
+        fs::remove_file(segment.index_path()).unwrap();
+
+        let mut log = PartitionLog::open(&dir).unwrap();
+        assert_eq!(2, log.recover(0).unwrap());
+        assert_eq!(1, log.segments().count());
+        assert_eq!(size / 3 * 2, segment.size().unwrap());
+        assert!(segment.index_path().exists());
+        assert!(!segment.check_indexes().unwrap());
+    }
+
+    #[test]
+    fn test_leader_epochs() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default();
+        for epoch in [1, 1, 3] {
+            let mut batch = RecordBatch::new(0, 0, vec![Record::new(0, None, None)]);
+            batch.partition_leader_epoch = epoch;
+            log.append(batch, &config).unwrap();
+        }
+        let end = |log: &PartitionLog, epoch| {
+            let end = log.end_offset_for_epoch(epoch);
+            (end.epoch, end.end_offset)
+        };
+        assert_eq!(Some(3), log.latest_epoch());
+        assert_eq!((1, 2), end(&log, 1));
+        assert_eq!((1, 2), end(&log, 2));
+        assert_eq!((3, 3), end(&log, 3));
+        assert_eq!((-1, 0), end(&log, 0));
+
+        // a replica that appended offset 2 in epoch 2 diverged at the end of epoch 1
+        assert!(log.validate_fetch(3, 3).is_none());
+        let diverging = log.validate_fetch(3, 2).unwrap();
+        assert_eq!((1, 2), (diverging.epoch, diverging.end_offset));
+        assert_eq!(2, log.truncation_offset(&diverging));
+
+        // the epochs are rebuilt from the batches without a checkpoint
+        fs::remove_file(dir.join(LEADER_EPOCH_CHECKPOINT_FILE)).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        log.recover(0).unwrap();
+        assert_eq!((3, 3), end(&log, 3));
+
+        log.truncate_to(2).unwrap();
+        assert_eq!(Some(1), log.latest_epoch());
+        log.assign_epoch(4).unwrap();
+        let log = PartitionLog::open(&dir).unwrap();
+        assert_eq!((4, 2), end(&log, 4));
+        assert_eq!((1, 2), end(&log, 3));
+    }
+
+    #[test]
+    fn test_read_and_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default();
+        for _ in 0..3 {
+            let records = vec![Record::new(0, None, None), Record::new(1, None, None)];
+            log.append(RecordBatch::new(0, 0, records), &config)
+                .unwrap();
+        }
+        let offsets = |batches: Vec<RecordBatch>| -> Vec<i64> {
+            batches.iter().map(|b| b.base_offset).collect()
+        };
+
+        // reading from the middle of a batch returns the whole batch
+        assert_eq!(vec![2, 4], offsets(log.read(3, i64::MAX, usize::MAX).unwrap()));
+        assert_eq!(vec![0], offsets(log.read(0, 2, usize::MAX).unwrap()));
+        assert_eq!(vec![0], offsets(log.read(0, i64::MAX, 1).unwrap()));
+        assert!(log.read(6, i64::MAX, 1).unwrap().is_empty());
+        let err = log.read(7, i64::MAX, 1).unwrap_err();
+        assert!(err.downcast_ref::<OffsetOutOfRange>().is_some());
+
+        log.set_high_watermark(5);
+        assert_eq!(4, log.truncate_to(5).unwrap());
+        assert_eq!(4, log.high_watermark());
+        assert_eq!(vec![0, 2], offsets(log.read(0, i64::MAX, usize::MAX).unwrap()));
+
+        log.truncate_fully_and_start_at(10).unwrap();
+        assert_eq!(0, log.segments().count());
+        assert_eq!(10, log.log_end_offset());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+use super::{CleanupPolicy, LogConfig, OffsetCheckpoint, PartitionLog, TopicPartition};
+use crate::raft::METADATA_TOPIC;
+
+/// The internal topic consumer groups commit their offsets to
+pub const CONSUMER_OFFSETS_TOPIC: &str = "__consumer_offsets";
+/// Per log dir file holding the offset up to which every log was flushed to disk
+pub const RECOVERY_POINT_CHECKPOINT_FILE: &str = "recovery-point-offset-checkpoint";
+/// Per log dir file holding the log start offset of every log, as moved by DeleteRecords
+pub const LOG_START_OFFSET_CHECKPOINT_FILE: &str = "log-start-offset-checkpoint";
+/// Per log dir file holding the high watermark of every log
+pub const REPLICATION_OFFSET_CHECKPOINT_FILE: &str = "replication-offset-checkpoint";
+
+/// Owns every partition log found in the broker's log directories,
+/// along with the default and per topic log configs. Both can change at runtime, logs
+/// pick the new values up the next time their config is looked up.
+#[derive(Debug)]
+pub struct LogManager {
+    log_dirs: Vec<PathBuf>,
+    default_config: RwLock<LogConfig>,
+    /// The `(key, value)` overrides of every topic that has some, by topic
+    topic_overrides: RwLock<HashMap<String, BTreeMap<String, String>>>,
+    logs: RwLock<HashMap<TopicPartition, Arc<Mutex<PartitionLog>>>>,
+}
+
+impl LogManager {
+    /// Loads the partition logs stored in `log_dirs`, creating the directories if they
+    /// don't exist yet. Entries that are not partition directories are skipped.
+    /// The logs are taken as they are on disk, [`Self::recover`] brings them back to
+    /// where they were on the last shutdown.
+    pub fn load(log_dirs: Vec<PathBuf>, default_config: LogConfig) -> anyhow::Result<Self> {
+        let mut logs = HashMap::new();
+        for log_dir in &log_dirs {
+            fs::create_dir_all(log_dir)
+                .with_context(|| format!("Creating log dir {}", log_dir.display()))?;
+
+            for entry in fs::read_dir(log_dir)? {
+                let path = entry?.path();
+                // the metadata log belongs to the raft client of the KRaft quorum
+                let is_partition_dir = path.is_dir()
+                    && path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .and_then(TopicPartition::from_dir_name)
+                        .is_some_and(|tp| tp.topic != METADATA_TOPIC);
+                if !is_partition_dir {
+                    continue;
+                }
+
+                match PartitionLog::open(&path) {
+                    Ok(log) => {
+                        info!(
+                            "Loaded log {} from {}",
+                            log.topic_partition(),
+                            path.display()
+                        );
+                        logs.insert(log.topic_partition().clone(), Arc::new(Mutex::new(log)));
+                    }
+                    Err(e) => warn!("Skipping {}: {e:#}", path.display()),
+                }
+            }
+        }
+
+        Ok(Self {
+            log_dirs,
+            default_config: RwLock::new(default_config),
+            topic_overrides: RwLock::new(HashMap::new()),
+            logs: RwLock::new(logs),
+        })
+    }
+
+    /// Recovers every log from the recovery point checkpointed in its log dir on the last
+    /// clean shutdown, or from its start if there is none, then restores its checkpointed
+    /// log start offset and high watermark. Logs that cannot be recovered are dropped.
+    pub fn recover(&self) -> anyhow::Result<()> {
+        for log_dir in &self.log_dirs {
+            let recovery_points =
+                OffsetCheckpoint::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE)).read()?;
+            let log_start_offsets =
+                OffsetCheckpoint::new(log_dir.join(LOG_START_OFFSET_CHECKPOINT_FILE)).read()?;
+            let high_watermarks =
+                OffsetCheckpoint::new(log_dir.join(REPLICATION_OFFSET_CHECKPOINT_FILE)).read()?;
+
+            let mut logs = self.logs.write().unwrap();
+            logs.retain(|tp, log| {
+                let mut log = log.lock().unwrap();
+                if !log.dir().starts_with(log_dir) {
+                    return true;
+                }
+                let recovery_point = recovery_points
+                    .get(tp)
+                    .copied()
+                    .unwrap_or(log.log_start_offset());
+                let recovered = log.recover(recovery_point).map(|_| {
+                    if let Some(&offset) = log_start_offsets.get(tp) {
+                        log.restore_log_start_offset(offset);
+                    }
+                    if let Some(&offset) = high_watermarks.get(tp) {
+                        log.set_high_watermark(offset);
+                    }
+                });
+                if let Err(e) = &recovered {
+                    warn!("Dropping {tp}, recovering it failed: {e:#}");
+                }
+                recovered.is_ok()
+            });
+        }
+        Ok(())
+    }
+
+    /// Flushes every log and checkpoints its log end offset as the recovery point,
+    /// along with its log start offset and high watermark, in the log dir it lives in.
+    /// Called on clean shutdown, so the next start does not have to recover logs that
+    /// were flushed.
+    pub fn checkpoint(&self) -> anyhow::Result<()> {
+        let mut recovery_points: HashMap<&Path, HashMap<TopicPartition, i64>> = HashMap::new();
+        let mut log_start_offsets: HashMap<&Path, HashMap<TopicPartition, i64>> = HashMap::new();
+        let mut high_watermarks: HashMap<&Path, HashMap<TopicPartition, i64>> = HashMap::new();
+        for log in self.logs() {
+            let log = log.lock().unwrap();
+            let Some(log_dir) = self.log_dir_of(&log) else {
+                continue;
+            };
+            log.flush()
+                .with_context(|| format!("Flushing {}", log.topic_partition()))?;
+            let tp = log.topic_partition().clone();
+            recovery_points
+                .entry(log_dir)
+                .or_default()
+                .insert(tp.clone(), log.log_end_offset());
+            log_start_offsets
+                .entry(log_dir)
+                .or_default()
+                .insert(tp.clone(), log.log_start_offset());
+            high_watermarks
+                .entry(log_dir)
+                .or_default()
+                .insert(tp, log.high_watermark());
+        }
+
+        for log_dir in &self.log_dirs {
+            let recovery_points = recovery_points
+                .remove(log_dir.as_path())
+                .unwrap_or_default();
+            OffsetCheckpoint::new(log_dir.join(RECOVERY_POINT_CHECKPOINT_FILE))
+                .write(&recovery_points)?;
+            let log_start_offsets = log_start_offsets
+                .remove(log_dir.as_path())
+                .unwrap_or_default();
+            OffsetCheckpoint::new(log_dir.join(LOG_START_OFFSET_CHECKPOINT_FILE))
+                .write(&log_start_offsets)?;
+            let high_watermarks = high_watermarks
+                .remove(log_dir.as_path())
+                .unwrap_or_default();
+            OffsetCheckpoint::new(log_dir.join(REPLICATION_OFFSET_CHECKPOINT_FILE))
+                .write(&high_watermarks)?;
+        }
+        Ok(())
+    }
+
+    pub fn log_dirs(&self) -> &[PathBuf] {
+        &self.log_dirs
+    }
+
+    /// The log dir `log` lives in
+    pub fn log_dir_of(&self, log: &PartitionLog) -> Option<&Path> {
+        self.log_dirs
+            .iter()
+            .find(|d| log.dir().starts_with(d))
+            .map(PathBuf::as_path)
+    }
+
+    pub fn get_log(&self, tp: &TopicPartition) -> Option<Arc<Mutex<PartitionLog>>> {
+        self.logs.read().unwrap().get(tp).cloned()
+    }
+
+    /// The log of `tp`, created empty if there is none yet. New logs go to the log dir
+    /// holding the fewest of them.
+    pub fn get_or_create_log(&self, tp: &TopicPartition) -> anyhow::Result<Arc<Mutex<PartitionLog>>> {
+        let mut logs = self.logs.write().unwrap();
+        if let Some(log) = logs.get(tp) {
+            return Ok(Arc::clone(log));
+        }
+
+        let mut counts: HashMap<&Path, usize> = HashMap::new();
+        for log in logs.values() {
+            if let Some(log_dir) = self.log_dir_of(&log.lock().unwrap()) {
+                *counts.entry(log_dir).or_default() += 1;
+            }
+        }
+        let log_dir = self
+            .log_dirs
+            .iter()
+            .min_by_key(|d| counts.get(d.as_path()).copied().unwrap_or_default())
+            .context("No log dirs")?;
+        let dir = log_dir.join(tp.dir_name());
+        fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+        let log = Arc::new(Mutex::new(PartitionLog::open(&dir)?));
+        info!("Created log {tp} in {}", log_dir.display());
+        logs.insert(tp.clone(), Arc::clone(&log));
+        Ok(log)
+    }
+
+    /// A snapshot of every log currently managed
+    pub fn logs(&self) -> Vec<Arc<Mutex<PartitionLog>>> {
+        self.logs.read().unwrap().values().cloned().collect()
+    }
+
+    /// Whether any partition of `topic` has a log
+    pub fn has_topic(&self, topic: &str) -> bool {
+        self.logs.read().unwrap().keys().any(|tp| tp.topic == topic)
+    }
+
+    /// The effective config of `topic`, that is the defaults with its overrides applied
+    pub fn config_for(&self, topic: &str) -> LogConfig {
+        let base = self.base_config(topic);
+        match self.topic_overrides.read().unwrap().get(topic) {
+            Some(overrides) => base
+                .with_overrides(overrides.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+                .expect("overrides are validated when set"),
+            None => base,
+        }
+    }
+
+    /// The config overrides of `topic` are applied on top of. Internal topics
+    /// differ from the broker defaults, as they only ever need the latest value of a key.
+    fn base_config(&self, topic: &str) -> LogConfig {
+        let mut config = self.default_config.read().unwrap().clone();
+        if topic == CONSUMER_OFFSETS_TOPIC {
+            config.cleanup_policy = CleanupPolicy::COMPACT;
+        }
+        config
+    }
+
+    /// Replaces the defaults every topic's overrides are applied on top of
+    pub fn set_default_config(&self, config: LogConfig) {
+        *self.default_config.write().unwrap() = config;
+    }
+
+    /// Replaces the overrides of the defaults for `topic` with the given `(key, value)`
+    /// pairs. No pairs remove the topic's overrides.
+    pub fn set_topic_overrides<'a, I>(&self, topic: &str, overrides: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let overrides: BTreeMap<_, _> = overrides
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        LogConfig::default().with_overrides(overrides.iter().map(|(k, v)| (k.as_str(), v.as_str())))?;
+        let mut topic_overrides = self.topic_overrides.write().unwrap();
+        if overrides.is_empty() {
+            topic_overrides.remove(topic);
+        } else {
+            topic_overrides.insert(topic.to_string(), overrides);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail, ensure};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD as BASE64};
+use tracing::info;
+
+use super::atomic_write;
+use crate::{config::Properties, primitives::Uuid};
+
+/// Per log dir file naming the cluster and node the directory belongs to
+pub const META_PROPERTIES_FILE: &str = "meta.properties";
+
+/// The identity stored in the `meta.properties` of a log dir, in Kafka's format:
+///
+/// ```text
+/// version=1
+/// cluster.id=5L6g3nShT-eMCtK--X86sw
+/// node.id=1
+/// directory.id=J8aAPcfLQt2bqs1JT_rMgQ
+/// ```
+///
+/// Keeps a log dir from being used by another node, or by a node of another cluster.
+/// The directory id tells the log dirs of the cluster apart, brokers register with the
+/// ids of theirs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaProperties {
+    pub cluster_id: String,
+    pub node_id: i32,
+    /// `None` in files written before directory ids were assigned
+    pub directory_id: Option<Uuid>,
+}
+
+impl MetaProperties {
+    const VERSION: &str = "1";
+
+    /// Reads the `meta.properties` of `log_dir`, `None` if it has none
+    pub fn read(log_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = log_dir.join(META_PROPERTIES_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let props = Properties::read(&path)?;
+        let version = props.get("version").unwrap_or_default();
+        ensure!(
+            version == Self::VERSION,
+            "Unsupported version {version:?} of {}",
+            path.display()
+        );
+        let cluster_id = props
+            .get("cluster.id")
+            .with_context(|| format!("No cluster.id in {}", path.display()))?;
+        let node_id = props
+            .get("node.id")
+            .with_context(|| format!("No node.id in {}", path.display()))?
+            .parse()
+            .with_context(|| format!("Invalid node.id in {}", path.display()))?;
+        let directory_id = props
+            .get("directory.id")
+            .map(Uuid::parse)
+            .transpose()
+            .with_context(|| format!("Invalid directory.id in {}", path.display()))?;
+        Ok(Some(Self {
+            cluster_id: cluster_id.to_string(),
+            node_id,
+            directory_id,
+        }))
+    }
+
+    /// Writes the `meta.properties` of `log_dir`, replacing the file atomically
+    pub fn write(&self, log_dir: &Path) -> anyhow::Result<()> {
+        let path = log_dir.join(META_PROPERTIES_FILE);
+        let mut content = format!(
+            "#\n#Written by the broker on first start\nversion={}\ncluster.id={}\nnode.id={}\n",
+            Self::VERSION,
+            self.cluster_id,
+            self.node_id
+        );
+        if let Some(directory_id) = self.directory_id {
+            content.push_str(&format!("directory.id={directory_id}\n"));
+        }
+        atomic_write(&path, content.as_bytes())
+    }
+
+    /// Checks that every one of `log_dirs` belongs to the same cluster and to `node_id`,
+    /// and returns the cluster id. Log dirs without a `meta.properties` get one, with a
+    /// new cluster id if none of them has one yet, and those without a directory id get
+    /// a new one.
+    pub fn load(log_dirs: &[PathBuf], node_id: i32) -> anyhow::Result<String> {
+        let mut cluster_id: Option<String> = None;
+        let mut missing = Vec::new();
+        for log_dir in log_dirs {
+            let Some(mut meta) = Self::read(log_dir)? else {
+                missing.push(log_dir);
+                continue;
+            };
+            if meta.node_id != node_id {
+                bail!(
+                    "Log dir {} belongs to node {}, not to node {node_id}",
+                    log_dir.display(),
+                    meta.node_id
+                );
+            }
+            match &cluster_id {
+                Some(id) if *id != meta.cluster_id => bail!(
+                    "Log dir {} belongs to cluster {}, the others to cluster {id}",
+                    log_dir.display(),
+                    meta.cluster_id
+                ),
+                _ => cluster_id = Some(meta.cluster_id.clone()),
+            }
+            if meta.directory_id.is_none() {
+                meta.directory_id = Some(Uuid::random());
+                meta.write(log_dir)?;
+            }
+        }
+
+        let cluster_id = cluster_id.unwrap_or_else(|| {
+            let id = random_id();
+            info!("Generated cluster id {id}");
+            id
+        });
+        for log_dir in missing {
+            let meta = Self {
+                cluster_id: cluster_id.clone(),
+                node_id,
+                directory_id: Some(Uuid::random()),
+            };
+            meta.write(log_dir)?;
+        }
+        Ok(cluster_id)
+    }
+
+    /// The directory id of every one of `log_dirs`, in order. They all have one once
+    /// [`MetaProperties::load`] checked them.
+    pub fn directory_ids(log_dirs: &[PathBuf]) -> anyhow::Result<Vec<Uuid>> {
+        log_dirs
+            .iter()
+            .map(|log_dir| {
+                Self::read(log_dir)?
+                    .and_then(|meta| meta.directory_id)
+                    .with_context(|| format!("No directory.id in {}", log_dir.display()))
+            })
+            .collect()
+    }
+}
+
+/// A random id formatted like Kafka's, 16 bytes in URL safe base64 without padding
+pub fn random_id() -> String {
+    BASE64.encode(rand::random::<[u8; 16]>())
+}
+
+/// The size of the file system holding `path` and the bytes still available on it, in
+/// that order
+pub fn disk_space(path: &Path) -> io::Result<(u64, u64)> {
+    let stat = rustix::fs::statvfs(path)?;
+    Ok((stat.f_blocks * stat.f_frsize, stat.f_bavail * stat.f_frsize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_creates_and_checks() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let log_dirs = vec![first.path().to_path_buf()];
+        let cluster_id = MetaProperties::load(&log_dirs, 1).unwrap();
+        assert_eq!(22, cluster_id.len());
+
+        // a new log dir joins the cluster of the existing one
+        let log_dirs = vec![first.path().to_path_buf(), second.path().to_path_buf()];
+        assert_eq!(cluster_id, MetaProperties::load(&log_dirs, 1).unwrap());
+        let meta = MetaProperties::read(second.path()).unwrap().unwrap();
+        assert_eq!(cluster_id, meta.cluster_id);
+
+        // every log dir has its own directory id
+        let ids = MetaProperties::directory_ids(&log_dirs).unwrap();
+        assert_ne!(ids[0], ids[1]);
+        MetaProperties::load(&log_dirs, 1).unwrap();
+        assert_eq!(ids, MetaProperties::directory_ids(&log_dirs).unwrap());
+
+        assert!(MetaProperties::load(&log_dirs, 2).is_err());
+        MetaProperties {
+            cluster_id: random_id(),
+            node_id: 1,
+            directory_id: None,
+        }
+        .write(second.path())
+        .unwrap();
+        assert!(MetaProperties::load(&log_dirs, 1).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! On disk storage of partition logs, using the same layout as Kafka:
+//! every partition lives in a `<topic>-<partition>` directory inside one
+//! of the broker's log directories, split into segments.
+mod checkpoint;
+mod cleaner;
+mod config;
+mod epochs;
+mod index;
+mod log;
+mod manager;
+mod meta;
+mod retention;
+mod segment;
+
+pub use checkpoint::{OffsetCheckpoint, atomic_write};
+pub use cleaner::{
+    CLEANER_CHECKPOINT_FILE, CleanableLog, CleanerStats, DEFAULT_CLEANER_BACKOFF, LogCleaner,
+    clean_log, run_cleaner,
+};
+pub use config::{CleanupPolicy, CompressionType, LogConfig};
+pub use epochs::{LEADER_EPOCH_CHECKPOINT_FILE, LeaderEpochCache};
+pub use index::{INDEX_INTERVAL_BYTES, OffsetIndexEntry, SegmentIndex, TimeIndexEntry};
+pub use log::{
+    OffsetOutOfRange, PartitionLog, RecordBatchTooLarge, TimestampAndOffset, TopicPartition,
+};
+pub use manager::{
+    CONSUMER_OFFSETS_TOPIC, LOG_START_OFFSET_CHECKPOINT_FILE, LogManager,
+    RECOVERY_POINT_CHECKPOINT_FILE, REPLICATION_OFFSET_CHECKPOINT_FILE,
+};
+pub use meta::{META_PROPERTIES_FILE, MetaProperties, disk_space, random_id};
+pub use retention::{DEFAULT_RETENTION_CHECK_INTERVAL, delete_retained_segments, run_retention};
+pub use segment::LogSegment;
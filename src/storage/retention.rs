@@ -0,0 +1,253 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, error, info};
+
+use super::{LogConfig, LogManager, LogSegment, PartitionLog};
+
+/// Default of `log.retention.check.interval.ms`
+pub const DEFAULT_RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+impl LogManager {
+    /// Runs retention on every log once, returning the number of deleted segments.
+    /// A log failing to clean up is logged and does not stop the others from being cleaned.
+    pub fn delete_retained_segments(&self, now: SystemTime) -> usize {
+        let mut total = 0;
+        for log in self.logs() {
+            let mut log = log.lock().unwrap();
+            let config = self.config_for(&log.topic_partition().topic);
+            match delete_retained_segments(&mut log, &config, now) {
+                Ok(0) => {}
+                Ok(deleted) => {
+                    info!(
+                        "Deleted {deleted} segment(s) of {} due to retention, log start offset is now {}",
+                        log.topic_partition(),
+                        log.log_start_offset()
+                    );
+                    total += deleted;
+                }
+                Err(e) => error!("Retention of {} failed: {e:#}", log.topic_partition()),
+            }
+        }
+        total
+    }
+}
+
+/// Deletes the segments of `log` breaching either `retention.ms` or `retention.bytes`.
+/// Time based retention runs first, measuring the age of a segment from its largest
+/// record timestamp, then the log is shrunk until it fits into `retention.bytes`, always
+/// keeping whole segments and the active one. Segments holding records at or above the
+/// high watermark are kept too, consumers have not been able to read those yet.
+/// Logs whose cleanup policy does not include `delete` are left alone.
+pub fn delete_retained_segments(
+    log: &mut PartitionLog,
+    config: &LogConfig,
+    now: SystemTime,
+) -> anyhow::Result<usize> {
+    if !config.cleanup_policy.delete {
+        return Ok(0);
+    }
+    let mut deleted = 0;
+    let high_watermark = log.high_watermark();
+    let bases: Vec<i64> = log.segments().map(LogSegment::base_offset).collect();
+    // a segment ends where the next one begins, the active one is never deleted anyway
+    let below_high_watermark = |segment: &LogSegment| {
+        bases
+            .iter()
+            .find(|&&b| b > segment.base_offset())
+            .is_some_and(|&next| next <= high_watermark)
+    };
+
+    if config.retention_ms >= 0 {
+        let retention = Duration::from_millis(config.retention_ms.unsigned_abs());
+        let now_ms = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        deleted += log.delete_oldest_segments(|segment| {
+            if !below_high_watermark(segment) {
+                return Ok(false);
+            }
+            // segments without any timestamp fall back to the time they were last written
+            let age = match segment.max_timestamp()? {
+                Some(timestamp) => {
+                    Duration::from_millis(now_ms.saturating_sub(timestamp).max(0) as u64)
+                }
+                None => now
+                    .duration_since(segment.last_modified()?)
+                    .unwrap_or_default(),
+            };
+            Ok(age > retention)
+        })?;
+    }
+
+    if config.retention_bytes >= 0 {
+        let mut excess = i128::from(log.size()?) - i128::from(config.retention_bytes);
+        deleted += log.delete_oldest_segments(|segment| {
+            if !below_high_watermark(segment) {
+                return Ok(false);
+            }
+            let size = i128::from(segment.size()?);
+            if excess - size >= 0 {
+                excess -= size;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        })?;
+    }
+
+    Ok(deleted)
+}
+
+/// Background task enforcing retention on every log of `manager`, waking up every `check_interval`.
+pub async fn run_retention(manager: Arc<LogManager>, check_interval: Duration) {
+    let mut interval = tokio::time::interval_at(Instant::now() + check_interval, check_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        debug!("Running log retention");
+        let manager = Arc::clone(&manager);
+        let cleanup = tokio::task::spawn_blocking(move || {
+            manager.delete_retained_segments(SystemTime::now())
+        });
+        if let Err(e) = cleanup.await {
+            error!("Log retention task panicked: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        codec::WireLen,
+        storage::TopicPartition,
+        types::{Record, RecordBatch},
+    };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn write_segment(dir: &Path, base_offset: i64, size: usize, modified: SystemTime) {
+        let path = dir.join(LogSegment::file_name(base_offset, LogSegment::LOG_SUFFIX));
+        fs::write(&path, vec![0; size]).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn partition_dir(root: &Path) -> std::path::PathBuf {
+        let dir = root.join("foo-0");
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_time_retention() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = partition_dir(root.path());
+        let now = SystemTime::now();
+        write_segment(&dir, 0, 10, now - 3 * HOUR);
+        write_segment(&dir, 5, 10, now - 2 * HOUR);
+        write_segment(&dir, 9, 10, now - 3 * HOUR);
+
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default()
+            .with_overrides([("retention.ms", "5400000")])
+            .unwrap();
+
+        assert_eq!(2, delete_retained_segments(&mut log, &config, now).unwrap());
+        assert_eq!(9, log.log_start_offset());
+    }
+
+    #[test]
+    fn test_time_retention_uses_record_timestamps_below_high_watermark() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = partition_dir(root.path());
+        let now = SystemTime::now();
+        let millis = |t: SystemTime| {
+            t.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64
+        };
+        let batch = |timestamp| {
+            let value = Some(bytes::Bytes::from("value"));
+            RecordBatch::new(0, timestamp, vec![Record::new(0, None, value)])
+        };
+
+        let mut config = LogConfig::default()
+            .with_overrides([("retention.ms", "5400000")])
+            .unwrap();
+        // every batch gets a segment of its own
+        config.segment_bytes = batch(0).wire_len() as i32;
+        let mut log = PartitionLog::open(&dir).unwrap();
+        for timestamp in [now - 3 * HOUR, now - 3 * HOUR, now] {
+            log.append(batch(millis(timestamp)), &config).unwrap();
+        }
+        assert_eq!(3, log.segments().count());
+
+        // the segments were all written just now, their records are what is old
+        log.set_high_watermark(1);
+        assert_eq!(1, delete_retained_segments(&mut log, &config, now).unwrap());
+        assert_eq!(1, log.log_start_offset());
+
+        log.set_high_watermark(3);
+        assert_eq!(1, delete_retained_segments(&mut log, &config, now).unwrap());
+        assert_eq!(2, log.log_start_offset());
+    }
+
+    #[test]
+    fn test_size_retention_keeps_whole_segments() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = partition_dir(root.path());
+        let now = SystemTime::now();
+        write_segment(&dir, 0, 100, now);
+        write_segment(&dir, 10, 100, now);
+        write_segment(&dir, 20, 100, now);
+
+        let mut log = PartitionLog::open(&dir).unwrap();
+        let config = LogConfig::default()
+            .with_overrides([("retention.bytes", "150")])
+            .unwrap();
+
+        // deleting the second segment would leave the log at 100 bytes, below the limit
+        assert_eq!(1, delete_retained_segments(&mut log, &config, now).unwrap());
+        assert_eq!(10, log.log_start_offset());
+        assert_eq!(200, log.size().unwrap());
+    }
+
+    #[test]
+    fn test_topic_overrides() {
+        let root = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for topic in ["keep", "drop"] {
+            let dir = root.path().join(format!("{topic}-0"));
+            fs::create_dir(&dir).unwrap();
+            write_segment(&dir, 0, 10, now - 2 * HOUR);
+            write_segment(&dir, 1, 10, now);
+        }
+
+        let manager =
+            LogManager::load(vec![root.path().to_path_buf()], LogConfig::default()).unwrap();
+        manager
+            .set_topic_overrides("drop", [("retention.ms", "3600000")])
+            .unwrap();
+
+        assert_eq!(1, manager.delete_retained_segments(now));
+        let log_start = |topic| {
+            let log = manager.get_log(&TopicPartition::new(topic, 0)).unwrap();
+            let start = log.lock().unwrap().log_start_offset();
+            start
+        };
+        assert_eq!(0, log_start("keep"));
+        assert_eq!(1, log_start("drop"));
+    }
+}
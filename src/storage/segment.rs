@@ -0,0 +1,409 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use bytes::BytesMut;
+use tracing::warn;
+
+use super::index::{self, SegmentIndex};
+use crate::{
+    codec::{Decoder, Encoder, WireLen},
+    types::RecordBatch,
+};
+
+/// A single segment of a partition log. Every segment is made up of
+/// a `.log` file holding the record batches and its `.index` and
+/// `.timeindex` companions, all named after the first offset stored in
+/// the segment, zero padded to 20 digits like Kafka does
+/// (e.g. `00000000000000000042.log`).
+#[derive(Debug, Clone)]
+pub struct LogSegment {
+    base_offset: i64,
+    dir: PathBuf,
+}
+
+impl LogSegment {
+    pub const LOG_SUFFIX: &str = ".log";
+    pub const INDEX_SUFFIX: &str = ".index";
+    pub const TIME_INDEX_SUFFIX: &str = ".timeindex";
+    /// A compacted copy of the `.log` file, waiting to be swapped in
+    const CLEANED_SUFFIX: &str = ".log.cleaned";
+
+    pub fn new(dir: impl Into<PathBuf>, base_offset: i64) -> Self {
+        Self {
+            base_offset,
+            dir: dir.into(),
+        }
+    }
+
+    /// Returns the file name of the segment starting at `base_offset` with the given suffix
+    pub fn file_name(base_offset: i64, suffix: &str) -> String {
+        format!("{base_offset:020}{suffix}")
+    }
+
+    /// Parses the base offset out of a `.log` file name,
+    /// returns `None` if the name does not belong to a segment
+    pub fn parse_base_offset(file_name: &str) -> Option<i64> {
+        let stem = file_name.strip_suffix(Self::LOG_SUFFIX)?;
+        if stem.len() != 20 || !stem.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        stem.parse().ok()
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.base_offset
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn log_path(&self) -> PathBuf {
+        self.path_with_suffix(Self::LOG_SUFFIX)
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.path_with_suffix(Self::INDEX_SUFFIX)
+    }
+
+    pub fn time_index_path(&self) -> PathBuf {
+        self.path_with_suffix(Self::TIME_INDEX_SUFFIX)
+    }
+
+    fn path_with_suffix(&self, suffix: &str) -> PathBuf {
+        self.dir.join(Self::file_name(self.base_offset, suffix))
+    }
+
+    /// Size of the `.log` file in bytes
+    pub fn size(&self) -> io::Result<u64> {
+        Ok(fs::metadata(self.log_path())?.len())
+    }
+
+    /// The last time the segment was appended to. Used as the age of
+    /// the segment by time based retention.
+    pub fn last_modified(&self) -> io::Result<SystemTime> {
+        fs::metadata(self.log_path())?.modified()
+    }
+
+    /// Reads every complete record batch of the segment. A partially written
+    /// batch at the end of the file is ignored.
+    pub fn read_batches(&self) -> anyhow::Result<Vec<RecordBatch>> {
+        let path = self.log_path();
+        let data = fs::read(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let mut buf = BytesMut::from(&data[..]);
+        let mut batches = Vec::new();
+        while let Some(batch) = RecordBatch::decode(&mut buf, None)
+            .with_context(|| format!("Decoding batch of {}", path.display()))?
+        {
+            batches.push(batch);
+        }
+        Ok(batches)
+    }
+
+    /// The offset following the last batch of the segment, `None` if it holds none. Only
+    /// the batch headers are read, see [`Self::walk_headers`].
+    pub fn next_offset(&self) -> anyhow::Result<Option<i64>> {
+        let mut next_offset = None;
+        self.walk_headers(0, |header| {
+            let base_offset = i64::from_be_bytes(header[..8].try_into().unwrap());
+            let last_offset_delta = i32::from_be_bytes(header[23..27].try_into().unwrap());
+            next_offset = Some(base_offset + i64::from(last_offset_delta) + 1);
+        })?;
+        Ok(next_offset)
+    }
+
+    /// The largest max timestamp of the batches from `position` on, `None` if there are
+    /// none. Only the batch headers are read, see [`Self::walk_headers`].
+    pub fn max_timestamp_from(&self, position: u64) -> anyhow::Result<Option<i64>> {
+        let mut max_timestamp: Option<i64> = None;
+        self.walk_headers(position, |header| {
+            let timestamp = i64::from_be_bytes(header[35..43].try_into().unwrap());
+            max_timestamp = Some(max_timestamp.map_or(timestamp, |max| max.max(timestamp)));
+        })?;
+        Ok(max_timestamp)
+    }
+
+    /// The largest max timestamp of the batches of the segment, `None` if it holds none.
+    /// The last time index entry covers every batch up to the last offset index entry,
+    /// only the headers of the batches from there on are read.
+    pub fn max_timestamp(&self) -> anyhow::Result<Option<i64>> {
+        let (position, indexed) = index::last_indexed(&self.index_path(), &self.time_index_path())?;
+        let from_log = self.max_timestamp_from(position)?;
+        Ok(indexed.max(from_log))
+    }
+
+    /// Calls `f` with the start of the header of every batch from `position` on, hopping
+    /// from one header to the next, up to the first batch that is cut off or too short to
+    /// be a batch. Torn batches are left to [`Self::recover`].
+    fn walk_headers(&self, mut position: u64, mut f: impl FnMut(&[u8; 43])) -> anyhow::Result<()> {
+        let path = self.log_path();
+        let mut file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+        };
+        let len = file.metadata()?.len();
+        // base offset, batch length, partition leader epoch, magic, crc, attributes,
+        // last offset delta, first timestamp and max timestamp
+        let mut header = [0; 43];
+        while position + RecordBatch::HEADER_LEN as u64 <= len {
+            file.seek(SeekFrom::Start(position))?;
+            file.read_exact(&mut header)
+                .with_context(|| format!("Reading {}", path.display()))?;
+            let batch_length = i32::from_be_bytes(header[8..12].try_into()?);
+            let total = RecordBatch::LOG_OVERHEAD as u64 + u64::try_from(batch_length).unwrap_or(0);
+            if total < RecordBatch::HEADER_LEN as u64 || position + total > len {
+                break;
+            }
+            f(&header);
+            position += total;
+        }
+        Ok(())
+    }
+
+    /// Reads the complete batches from `position` of the `.log` file on, one at a time.
+    /// A partially written batch at the end of the file ends them.
+    pub fn batches_from(
+        &self,
+        position: u64,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<RecordBatch>>> {
+        let path = self.log_path();
+        let mut reader = match fs::File::open(&path) {
+            Ok(mut file) => {
+                file.seek(SeekFrom::Start(position))?;
+                Some(io::BufReader::new(file))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+        };
+        Ok(std::iter::from_fn(move || {
+            read_batch(reader.as_mut()?)
+                .with_context(|| format!("Reading batch of {}", path.display()))
+                .transpose()
+        }))
+    }
+
+    /// The position reads of `offset` start at, as found in the offset index
+    pub fn position_for_offset(&self, offset: i64) -> anyhow::Result<u64> {
+        index::position_for_offset(&self.index_path(), self.base_offset, offset)
+    }
+
+    /// The offset a search for the first record at or after `timestamp` starts at, as
+    /// found in the time index
+    pub fn search_start_for_timestamp(&self, timestamp: i64) -> anyhow::Result<i64> {
+        let offset =
+            index::offset_for_timestamp(&self.time_index_path(), self.base_offset, timestamp)?;
+        Ok(offset.unwrap_or(self.base_offset))
+    }
+
+    /// The max timestamp of the first batch of the segment, `None` if it holds none.
+    /// The age of the segment is measured from it when deciding whether to roll it.
+    pub fn first_timestamp(&self) -> anyhow::Result<Option<i64>> {
+        let path = self.log_path();
+        let mut file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+        };
+        let mut header = [0; RecordBatch::HEADER_LEN];
+        match file.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Reading {}", path.display())),
+        }
+        // the first timestamp follows the last offset delta, the max timestamp follows it
+        Ok(Some(i64::from_be_bytes(header[35..43].try_into()?)))
+    }
+
+    /// Writes `batch` to the end of the segment, creating the segment if it does not exist
+    /// yet, and adds it to the indexes if enough bytes were written since their last entry
+    pub fn append(&self, batch: &RecordBatch) -> anyhow::Result<()> {
+        let mut buf = BytesMut::with_capacity(batch.wire_len());
+        batch.encode(&mut buf)?;
+        let position = match self.size() {
+            Ok(size) => size,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let path = self.log_path();
+        fs::File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&buf))
+            .with_context(|| format!("Appending to {}", path.display()))?;
+        index::append_entries(
+            &self.index_path(),
+            &self.time_index_path(),
+            self.base_offset,
+            position,
+            batch,
+            |from| self.max_timestamp_from(from),
+        )
+    }
+
+    /// Forces the data written to the segment to disk
+    pub fn flush(&self) -> anyhow::Result<()> {
+        for path in [self.log_path(), self.index_path(), self.time_index_path()] {
+            match fs::File::open(&path) {
+                Ok(file) => file.sync_all()?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("Opening {}", path.display())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-reads every batch of the segment, verifying its CRC and that its offsets follow
+    /// the previous batch. Everything from the first batch failing either check on was torn
+    /// by a crash and is truncated, then the indexes are rebuilt from what is left.
+    /// Returns the number of bytes truncated.
+    pub fn recover(&self) -> anyhow::Result<u64> {
+        let path = self.log_path();
+        let data = fs::read(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let mut buf = BytesMut::from(&data[..]);
+        let mut index = SegmentIndex::default();
+        let mut valid = 0;
+        let mut next_offset = self.base_offset;
+        loop {
+            let batch = match RecordBatch::decode(&mut buf, None) {
+                Ok(Some(batch)) => batch,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Invalid batch at {valid} of {}: {e:#}", path.display());
+                    break;
+                }
+            };
+            if batch.base_offset < next_offset {
+                warn!(
+                    "Batch at {valid} of {} starts at offset {}, expected at least {next_offset}",
+                    path.display(),
+                    batch.base_offset
+                );
+                break;
+            }
+            index.push(self.base_offset, valid as u64, &batch);
+            next_offset = batch.next_offset();
+            valid = data.len() - buf.len();
+        }
+
+        let truncated = (data.len() - valid) as u64;
+        if truncated > 0 {
+            let file = fs::File::options().write(true).open(&path)?;
+            file.set_len(valid as u64)
+                .with_context(|| format!("Truncating {}", path.display()))?;
+            file.sync_all()?;
+        }
+        index.write(&self.index_path(), &self.time_index_path())?;
+        Ok(truncated)
+    }
+
+    /// Rebuilds the index files if they are missing or corrupt. Returns whether they were.
+    pub fn check_indexes(&self) -> anyhow::Result<bool> {
+        let index = SegmentIndex::read(&self.index_path(), &self.time_index_path())
+            .and_then(|index| index.validate(self.size()?));
+        match index {
+            Ok(()) => Ok(false),
+            Err(e) => {
+                warn!("Rebuilding indexes of {}: {e:#}", self.log_path().display());
+                self.rebuild_indexes()?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Recreates the index files from the batches in the `.log` file
+    pub fn rebuild_indexes(&self) -> anyhow::Result<()> {
+        let mut index = SegmentIndex::default();
+        let mut position = 0;
+        for batch in self.read_batches()? {
+            index.push(self.base_offset, position, &batch);
+            position += batch.wire_len() as u64;
+        }
+        index.write(&self.index_path(), &self.time_index_path())
+    }
+
+    /// Atomically replaces the content of the segment with `batches`, see
+    /// [`Self::write_cleaned`] and [`Self::swap_cleaned`]
+    pub fn replace_batches(&self, batches: &[RecordBatch]) -> anyhow::Result<()> {
+        let index = self.write_cleaned(batches)?;
+        self.swap_cleaned(&index)
+    }
+
+    /// Writes `batches` to a `.log.cleaned` file next to the segment, with the last
+    /// modified time of the segment, as retention and tombstone expiry depend on it. The
+    /// segment is left as is until [`Self::swap_cleaned`] replaces it with that file.
+    /// Returns the indexes of the new content.
+    pub fn write_cleaned(&self, batches: &[RecordBatch]) -> anyhow::Result<SegmentIndex> {
+        let mut buf = BytesMut::with_capacity(batches.wire_len());
+        let mut index = SegmentIndex::default();
+        for batch in batches {
+            index.push(self.base_offset, buf.len() as u64, batch);
+            batch.encode(&mut buf)?;
+        }
+
+        let modified = self.last_modified()?;
+        let cleaned = self.path_with_suffix(Self::CLEANED_SUFFIX);
+        fs::write(&cleaned, &buf).with_context(|| format!("Writing {}", cleaned.display()))?;
+        let file = fs::File::options().write(true).open(&cleaned)?;
+        file.set_modified(modified)?;
+        file.sync_all()?;
+        Ok(index)
+    }
+
+    /// Renames the file written by [`Self::write_cleaned`] over the segment. The index
+    /// files are removed before, since the positions stored in them no longer hold, and
+    /// replaced with `index` after. Missing ones are rebuilt on recovery.
+    pub fn swap_cleaned(&self, index: &SegmentIndex) -> anyhow::Result<()> {
+        self.delete_indexes()?;
+        fs::rename(self.path_with_suffix(Self::CLEANED_SUFFIX), self.log_path())
+            .with_context(|| format!("Replacing {}", self.log_path().display()))?;
+        index.write(&self.index_path(), &self.time_index_path())
+    }
+
+    /// Removes the file written by [`Self::write_cleaned`] without swapping it in
+    pub fn discard_cleaned(&self) -> io::Result<()> {
+        fs::remove_file(self.path_with_suffix(Self::CLEANED_SUFFIX))
+    }
+
+    /// Removes the segment and its index files from disk.
+    /// Missing index files are not an error, as they are optional.
+    pub fn delete(&self) -> io::Result<()> {
+        fs::remove_file(self.log_path())?;
+        self.delete_indexes()
+    }
+
+    fn delete_indexes(&self) -> io::Result<()> {
+        for path in [self.index_path(), self.time_index_path()] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the batch at the current position of `reader`, `None` at the end of the file
+/// or if the batch was only partially written
+fn read_batch(reader: &mut impl Read) -> anyhow::Result<Option<RecordBatch>> {
+    let mut data = vec![0; RecordBatch::LOG_OVERHEAD];
+    match reader.read_exact(&mut data) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let batch_length = i32::from_be_bytes(data[8..12].try_into()?);
+    let batch_length = u64::try_from(batch_length)
+        .with_context(|| format!("Invalid batch length {batch_length}"))?;
+    reader.take(batch_length).read_to_end(&mut data)?;
+    if (data.len() as u64) < RecordBatch::LOG_OVERHEAD as u64 + batch_length {
+        return Ok(None);
+    }
+    RecordBatch::decode(&mut BytesMut::from(&data[..]), None)
+}
@@ -5,15 +5,44 @@ use crate::codec::WireLen;
 
 /// This enum is not a one to one port of the original Kafka
 /// enum. As I wont be implementing every api request,
-/// I reserved 0 as the Unimplemented request, which makes it
+/// I reserved -1 as the Unimplemented request, which makes it
 /// easier for me to code as I dont have to wrap everything into
 /// Options and Results or use monadic functions.
 #[repr(i16)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ApiKeys {
+    Produce = 0,
+    Fetch = 1,
+    ListOffsets = 2,
+    Metadata = 3,
+    LeaderAndIsr = 4,
+    SaslHandshake = 17,
     ApiVersions = 18,
+    CreateTopics = 19,
+    DeleteRecords = 21,
+    DescribeAcls = 29,
+    CreateAcls = 30,
+    DeleteAcls = 31,
+    DescribeConfigs = 32,
+    AlterConfigs = 33,
+    DescribeLogDirs = 35,
+    SaslAuthenticate = 36,
+    ElectLeaders = 43,
+    IncrementalAlterConfigs = 44,
+    DescribeClientQuotas = 48,
+    AlterClientQuotas = 49,
+    DescribeUserScramCredentials = 50,
+    AlterUserScramCredentials = 51,
+    Vote = 52,
+    BeginQuorumEpoch = 53,
+    EndQuorumEpoch = 54,
+    DescribeQuorum = 55,
+    FetchSnapshot = 59,
+    DescribeCluster = 60,
+    BrokerRegistration = 62,
+    BrokerHeartbeat = 63,
     DescribeTopicPartitions = 75,
-    Unimplemented = 0,
+    Unimplemented = -1,
 }
 
 impl WireLen for ApiKeys {
@@ -22,10 +51,80 @@ impl WireLen for ApiKeys {
     }
 }
 
+impl ApiKeys {
+    /// Whether `version` of the API is flexible, that is its messages and headers have
+    /// tagged fields. Requests of other versions use header v1 instead of v2.
+    pub fn is_flexible(self, version: i16) -> bool {
+        match self {
+            ApiKeys::Produce => version >= 9,
+            ApiKeys::Fetch => version >= 12,
+            ApiKeys::ListOffsets => version >= 6,
+            ApiKeys::Metadata => version >= 9,
+            ApiKeys::LeaderAndIsr => version >= 4,
+            ApiKeys::SaslHandshake => false,
+            ApiKeys::ApiVersions => version >= 3,
+            ApiKeys::CreateTopics => version >= 5,
+            ApiKeys::DeleteRecords
+            | ApiKeys::DescribeAcls
+            | ApiKeys::CreateAcls
+            | ApiKeys::DeleteAcls
+            | ApiKeys::AlterConfigs
+            | ApiKeys::DescribeLogDirs
+            | ApiKeys::SaslAuthenticate
+            | ApiKeys::ElectLeaders => version >= 2,
+            ApiKeys::DescribeConfigs => version >= 4,
+            ApiKeys::IncrementalAlterConfigs
+            | ApiKeys::DescribeClientQuotas
+            | ApiKeys::AlterClientQuotas
+            | ApiKeys::BeginQuorumEpoch
+            | ApiKeys::EndQuorumEpoch => version >= 1,
+            ApiKeys::DescribeUserScramCredentials
+            | ApiKeys::AlterUserScramCredentials
+            | ApiKeys::Vote
+            | ApiKeys::DescribeQuorum
+            | ApiKeys::FetchSnapshot
+            | ApiKeys::DescribeCluster
+            | ApiKeys::BrokerRegistration
+            | ApiKeys::BrokerHeartbeat
+            | ApiKeys::DescribeTopicPartitions
+            | ApiKeys::Unimplemented => true,
+        }
+    }
+}
+
 impl From<i16> for ApiKeys {
     fn from(value: i16) -> Self {
         match value {
+            0 => ApiKeys::Produce,
+            1 => ApiKeys::Fetch,
+            2 => ApiKeys::ListOffsets,
+            3 => ApiKeys::Metadata,
+            4 => ApiKeys::LeaderAndIsr,
+            17 => ApiKeys::SaslHandshake,
             18 => ApiKeys::ApiVersions,
+            19 => ApiKeys::CreateTopics,
+            21 => ApiKeys::DeleteRecords,
+            29 => ApiKeys::DescribeAcls,
+            30 => ApiKeys::CreateAcls,
+            31 => ApiKeys::DeleteAcls,
+            32 => ApiKeys::DescribeConfigs,
+            33 => ApiKeys::AlterConfigs,
+            35 => ApiKeys::DescribeLogDirs,
+            36 => ApiKeys::SaslAuthenticate,
+            43 => ApiKeys::ElectLeaders,
+            44 => ApiKeys::IncrementalAlterConfigs,
+            48 => ApiKeys::DescribeClientQuotas,
+            49 => ApiKeys::AlterClientQuotas,
+            50 => ApiKeys::DescribeUserScramCredentials,
+            51 => ApiKeys::AlterUserScramCredentials,
+            52 => ApiKeys::Vote,
+            53 => ApiKeys::BeginQuorumEpoch,
+            54 => ApiKeys::EndQuorumEpoch,
+            55 => ApiKeys::DescribeQuorum,
+            59 => ApiKeys::FetchSnapshot,
+            60 => ApiKeys::DescribeCluster,
+            62 => ApiKeys::BrokerRegistration,
+            63 => ApiKeys::BrokerHeartbeat,
             75 => ApiKeys::DescribeTopicPartitions,
             _ => ApiKeys::Unimplemented,
         }
@@ -1,14 +1,14 @@
 use bytes::{BufMut, BytesMut};
 use kafka_macros::WireLen;
 
-use crate::{codec::Encoder, primitives::CompactArray, types::Tag};
+use crate::{codec::Encoder, types::TagBuf};
 
 #[derive(Debug, WireLen)]
 pub struct ApiVersion {
     pub(crate) api_key: u16,
     pub(crate) min_version: u16,
     pub(crate) max_version: u16,
-    pub(crate) tag_buffer: CompactArray<Tag>,
+    pub(crate) tag_buffer: TagBuf,
 }
 
 impl ApiVersion {
@@ -17,7 +17,7 @@ impl ApiVersion {
             api_key,
             min_version: min_sup_version,
             max_version: max_sup_version,
-            tag_buffer: CompactArray::new(),
+            tag_buffer: TagBuf::new(),
         }
     }
 }
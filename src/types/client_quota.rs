@@ -0,0 +1,46 @@
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{
+    codec::{Decoder, WireLen},
+    primitives::{CompactNullableString, CompactString},
+    unwrap_decode,
+};
+
+use super::TagBuf;
+
+/// entity => entity_type entity_name TAG_BUFFER
+///
+/// One component of a client quota entity, e.g. the `user` it applies to.
+/// A null `entity_name` stands for the default entity of the type.
+#[derive(Debug, WireLen, Encoder)]
+pub struct ClientQuotaEntityData {
+    pub entity_type: CompactString,
+    pub entity_name: CompactNullableString,
+    tag_buffer: TagBuf,
+}
+
+impl ClientQuotaEntityData {
+    pub fn new(entity_type: impl Into<CompactString>, entity_name: Option<String>) -> Self {
+        Self {
+            entity_type: entity_type.into(),
+            entity_name: CompactNullableString(entity_name),
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for ClientQuotaEntityData {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let entity_type = unwrap_decode!(CompactString::decode(src, None));
+        let entity_name = unwrap_decode!(CompactNullableString::decode(src, None));
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            entity_type,
+            entity_name,
+            tag_buffer,
+        }))
+    }
+}
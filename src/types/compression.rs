@@ -0,0 +1,231 @@
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+};
+
+use anyhow::{Context, bail, ensure};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("unknown compression codec {0}")]
+pub struct UnknownCompression(pub i16);
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("decompressed data is larger than {0} bytes")]
+pub struct DecompressedTooLarge(pub usize);
+
+/// The compression codec of a record batch, stored in the lowest 3 bits of its attributes.
+/// Only the records are compressed, the batch header is always readable.
+#[repr(i16)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Compression {
+    None = 0,
+    Gzip = 1,
+    Snappy = 2,
+    Lz4 = 3,
+    Zstd = 4,
+}
+
+impl TryFrom<i16> for Compression {
+    type Error = UnknownCompression;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Gzip),
+            2 => Ok(Compression::Snappy),
+            3 => Ok(Compression::Lz4),
+            4 => Ok(Compression::Zstd),
+            v => Err(UnknownCompression(v)),
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Snappy => "snappy",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Compression {
+    pub fn compress(self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            Compression::Snappy => xerial::compress(data),
+            Compression::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            Compression::Zstd => Ok(zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)?),
+        }
+    }
+
+    /// Decompresses `data`, failing with [`DecompressedTooLarge`] as soon as the output
+    /// would get larger than `max_len`, so that small inputs cannot expand without limit
+    pub fn decompress(self, data: &[u8], max_len: usize) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        // one more byte than allowed tells whether the output went past the limit
+        let limit = max_len as u64 + 1;
+        match self {
+            Compression::None => out.extend_from_slice(data),
+            Compression::Gzip => {
+                flate2::read::GzDecoder::new(data)
+                    .take(limit)
+                    .read_to_end(&mut out)?;
+            }
+            Compression::Snappy => out = xerial::decompress(data, max_len)?,
+            Compression::Lz4 => {
+                lz4_flex::frame::FrameDecoder::new(data)
+                    .take(limit)
+                    .read_to_end(&mut out)?;
+            }
+            Compression::Zstd => {
+                zstd::stream::read::Decoder::new(data)?
+                    .take(limit)
+                    .read_to_end(&mut out)?;
+            }
+        }
+        ensure!(out.len() <= max_len, DecompressedTooLarge(max_len));
+        Ok(out)
+    }
+}
+
+/// The Java client does not write raw snappy, but wraps it the way snappy-java's
+/// `SnappyOutputStream` does: a magic header followed by length prefixed raw snappy blocks.
+/// Other clients, like librdkafka, may send a single raw snappy block instead, both are accepted.
+mod xerial {
+    use super::*;
+
+    pub(super) const MAGIC: [u8; 8] = [0x82, b'S', b'N', b'A', b'P', b'P', b'Y', 0];
+    const VERSION: i32 = 1;
+    const MIN_COMPATIBLE_VERSION: i32 = 1;
+    const HEADER_LEN: usize = MAGIC.len() + 8;
+    /// Size of the uncompressed chunks, same as snappy-java's default
+    const BLOCK_SIZE: usize = 32 * 1024;
+
+    pub fn compress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(HEADER_LEN + data.len());
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_be_bytes());
+        out.extend_from_slice(&MIN_COMPATIBLE_VERSION.to_be_bytes());
+
+        let mut encoder = snap::raw::Encoder::new();
+        for chunk in data.chunks(BLOCK_SIZE) {
+            let block = encoder.compress_vec(chunk)?;
+            out.extend_from_slice(&(block.len() as i32).to_be_bytes());
+            out.extend_from_slice(&block);
+        }
+        Ok(out)
+    }
+
+    /// Snappy blocks start with their decompressed length, which is checked against
+    /// `max_len` before anything is decompressed
+    pub fn decompress(data: &[u8], max_len: usize) -> anyhow::Result<Vec<u8>> {
+        let mut decoder = snap::raw::Decoder::new();
+        if !data.starts_with(&MAGIC) {
+            let len = snap::raw::decompress_len(data).context("Decompressing raw snappy")?;
+            ensure!(len <= max_len, DecompressedTooLarge(max_len));
+            return decoder
+                .decompress_vec(data)
+                .context("Decompressing raw snappy");
+        }
+
+        ensure!(data.len() >= HEADER_LEN, "Truncated xerial snappy header");
+        let mut rest = &data[HEADER_LEN..];
+        let mut out = Vec::new();
+        while !rest.is_empty() {
+            ensure!(rest.len() >= 4, "Truncated xerial snappy block length");
+            let len = i32::from_be_bytes(rest[..4].try_into()?);
+            if len < 0 || rest.len() - 4 < len as usize {
+                bail!("Invalid xerial snappy block length {len}");
+            }
+            let (block, tail) = rest[4..].split_at(len as usize);
+            let block_len = snap::raw::decompress_len(block)?;
+            ensure!(
+                out.len() + block_len <= max_len,
+                DecompressedTooLarge(max_len)
+            );
+            out.extend_from_slice(&decoder.decompress_vec(block)?);
+            rest = tail;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_every_codec() {
+        let data: Vec<u8> = (0..100_000u32)
+            .flat_map(|i| (i % 251).to_be_bytes())
+            .collect();
+        for codec in [
+            Compression::None,
+            Compression::Gzip,
+            Compression::Snappy,
+            Compression::Lz4,
+            Compression::Zstd,
+        ] {
+            let compressed = codec.compress(&data).unwrap();
+            assert_eq!(
+                data,
+                codec.decompress(&compressed, data.len()).unwrap(),
+                "{codec}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_snappy_framing() {
+        let compressed = Compression::Snappy.compress(b"hello hello hello").unwrap();
+        assert!(compressed.starts_with(&xerial::MAGIC));
+
+        // raw snappy, as sent by clients not using the xerial framing
+        let raw = snap::raw::Encoder::new()
+            .compress_vec(b"hello hello hello")
+            .unwrap();
+        assert_eq!(
+            b"hello hello hello".to_vec(),
+            Compression::Snappy.decompress(&raw, 17).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decompress_is_bounded() {
+        let data = vec![0u8; 1024 * 1024];
+        for codec in [
+            Compression::None,
+            Compression::Gzip,
+            Compression::Snappy,
+            Compression::Lz4,
+            Compression::Zstd,
+        ] {
+            let compressed = codec.compress(&data).unwrap();
+            let err = codec.decompress(&compressed, data.len() - 1).unwrap_err();
+            assert_eq!(
+                Some(&DecompressedTooLarge(data.len() - 1)),
+                err.downcast_ref(),
+                "{codec}"
+            );
+        }
+
+        let raw = snap::raw::Encoder::new().compress_vec(&data).unwrap();
+        assert!(Compression::Snappy.decompress(&raw, 1024).is_err());
+    }
+}
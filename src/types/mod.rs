@@ -1,10 +1,20 @@
 //! Types that are not primitives but are used by both requests and responses
 mod api_keys;
 mod api_version;
+mod client_quota;
+mod compression;
+mod error_code;
+mod quorum;
+mod record_batch;
 mod tag;
 mod topic;
 
 pub use api_keys::*;
 pub use api_version::*;
+pub use client_quota::*;
+pub use compression::*;
+pub use error_code::*;
+pub use quorum::*;
+pub use record_batch::*;
 pub use tag::*;
 pub use topic::*;
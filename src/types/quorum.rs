@@ -0,0 +1,162 @@
+use bytes::Buf;
+use kafka_macros::{Encoder, WireLen};
+
+use crate::{codec::Decoder, primitives::CompactString, types::TagBuf, unwrap_decode};
+
+/// LeaderIdAndEpoch => leader_id leader_epoch TAG_BUFFER
+///
+/// Who a replica of the KRaft quorum believes leads it, sent along with errors caused
+/// by a stale epoch. The `CurrentLeader` tagged field of Fetch and FetchSnapshot
+/// responses.
+#[derive(Debug, Clone, WireLen, Encoder)]
+pub struct LeaderIdAndEpoch {
+    /// -1 if the leader is not known
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderIdAndEpoch {
+    pub fn new(leader_id: i32, leader_epoch: i32) -> Self {
+        Self {
+            leader_id,
+            leader_epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// EpochEndOffset => epoch end_offset TAG_BUFFER
+///
+/// The largest epoch of the leader's log not after the one a follower asked about, and
+/// where that epoch ends. The `DivergingEpoch` tagged field of Fetch responses, telling
+/// the follower to truncate its log.
+#[derive(Debug, Clone, WireLen, Encoder)]
+pub struct EpochEndOffset {
+    pub epoch: i32,
+    pub end_offset: i64,
+    tag_buffer: TagBuf,
+}
+
+impl EpochEndOffset {
+    pub fn new(epoch: i32, end_offset: i64) -> Self {
+        Self {
+            epoch,
+            end_offset,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// SnapshotId => end_offset epoch TAG_BUFFER
+///
+/// A snapshot of the metadata log, holding the state up to `end_offset`, the offset of
+/// the first record it does not include
+#[derive(Debug, Clone, WireLen, Encoder)]
+pub struct SnapshotId {
+    pub end_offset: i64,
+    pub epoch: i32,
+    tag_buffer: TagBuf,
+}
+
+impl SnapshotId {
+    pub fn new(end_offset: i64, epoch: i32) -> Self {
+        Self {
+            end_offset,
+            epoch,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+/// leader_endpoints => name host port TAG_BUFFER
+///
+/// A listener of the leader of the quorum
+#[derive(Debug, Clone, WireLen, Encoder)]
+pub struct LeaderEndpoint {
+    pub name: CompactString,
+    pub host: CompactString,
+    pub port: u16,
+    tag_buffer: TagBuf,
+}
+
+impl LeaderEndpoint {
+    pub fn new(name: impl Into<String>, host: impl Into<String>, port: u16) -> Self {
+        Self {
+            name: CompactString(name.into()),
+            host: CompactString(host.into()),
+            port,
+            tag_buffer: TagBuf::new(),
+        }
+    }
+}
+
+impl Decoder for LeaderIdAndEpoch {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 8 {
+            src.reserve(8);
+            return Ok(None);
+        }
+        let leader_id = src.get_i32();
+        let leader_epoch = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            leader_id,
+            leader_epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for EpochEndOffset {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let epoch = src.get_i32();
+        let end_offset = src.get_i64();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            epoch,
+            end_offset,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for SnapshotId {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if src.remaining() < 12 {
+            src.reserve(12);
+            return Ok(None);
+        }
+        let end_offset = src.get_i64();
+        let epoch = src.get_i32();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            end_offset,
+            epoch,
+            tag_buffer,
+        }))
+    }
+}
+
+impl Decoder for LeaderEndpoint {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>> {
+        let name = unwrap_decode!(CompactString::decode(src, None));
+        let host = unwrap_decode!(CompactString::decode(src, None));
+        if src.remaining() < 2 {
+            src.reserve(2);
+            return Ok(None);
+        }
+        let port = src.get_u16();
+        let tag_buffer = unwrap_decode!(TagBuf::decode(src, None));
+        Ok(Some(Self {
+            name,
+            host,
+            port,
+            tag_buffer,
+        }))
+    }
+}
@@ -0,0 +1,619 @@
+use anyhow::{bail, ensure};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::{
+    codec::{Decoder, Encoder, WireLen},
+    primitives::{Varint, Varlong},
+    unwrap_decode,
+};
+
+use super::{Compression, UnknownCompression};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RecordBatchError {
+    #[error("unsupported record batch magic {0}")]
+    UnsupportedMagic(i8),
+    #[error("record batch CRC mismatch, expected {expected:#010x}, computed {computed:#010x}")]
+    CrcMismatch { expected: u32, computed: u32 },
+    #[error("record batch length {0} is smaller than its header")]
+    InvalidLength(i32),
+    #[error("record batch is truncated")]
+    Truncated,
+    #[error("record batch records are invalid")]
+    InvalidRecords,
+    #[error("record batch claims {expected} records but holds {actual}")]
+    RecordCountMismatch { expected: i32, actual: usize },
+}
+
+/// A header attached to a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordHeader {
+    pub key: String,
+    pub value: Option<Bytes>,
+}
+
+/// # Kafka protocol
+///
+/// A single record inside of a record batch. Its length and every
+/// length prefix inside of it are VARINTs, the timestamp and offset are
+/// stored as deltas from the batch's base timestamp and base offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub attributes: i8,
+    pub timestamp_delta: i64,
+    pub offset_delta: i32,
+    pub key: Option<Bytes>,
+    pub value: Option<Bytes>,
+    pub headers: Vec<RecordHeader>,
+}
+
+impl Record {
+    /// Length of the smallest record: a length prefix, the attributes, both deltas,
+    /// a null key, a null value and no headers all take a single byte
+    const MIN_LEN: usize = 7;
+
+    pub fn new(offset_delta: i32, key: Option<Bytes>, value: Option<Bytes>) -> Self {
+        Self {
+            attributes: 0,
+            timestamp_delta: 0,
+            offset_delta,
+            key,
+            value,
+            headers: Vec::new(),
+        }
+    }
+
+    /// A record with a key but no value marks the key as deleted in compacted topics
+    pub fn is_tombstone(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Length of the record without its own length prefix
+    fn body_len(&self) -> usize {
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|h| bytes_wire_len(Some(h.key.as_bytes())) + bytes_wire_len(h.value.as_deref()))
+            .sum();
+
+        self.attributes.wire_len()
+            + Varlong::wire_len_of(self.timestamp_delta)
+            + Varint::wire_len_of(self.offset_delta)
+            + bytes_wire_len(self.key.as_deref())
+            + bytes_wire_len(self.value.as_deref())
+            + Varint::wire_len_of(self.headers.len() as i32)
+            + headers
+    }
+}
+
+fn bytes_wire_len(bytes: Option<&[u8]>) -> usize {
+    match bytes {
+        Some(b) => Varint::wire_len_of(b.len() as i32) + b.len(),
+        None => Varint::wire_len_of(-1),
+    }
+}
+
+fn put_bytes(dest: &mut BytesMut, bytes: Option<&[u8]>) -> anyhow::Result<()> {
+    match bytes {
+        Some(b) => {
+            Varint(b.len() as i32).encode(dest)?;
+            dest.put_slice(b);
+        }
+        None => Varint(-1).encode(dest)?,
+    }
+    Ok(())
+}
+
+/// Reads VARINT length prefixed bytes, where a length of -1 stands for null
+fn get_bytes(src: &mut BytesMut) -> anyhow::Result<Option<Bytes>> {
+    let len = unwrap_varint(src)?;
+    if len < 0 {
+        return Ok(None);
+    }
+    let len = len as usize;
+    ensure!(src.remaining() >= len, RecordBatchError::Truncated);
+    Ok(Some(src.split_to(len).freeze()))
+}
+
+fn unwrap_varint(src: &mut BytesMut) -> anyhow::Result<i32> {
+    match Varint::decode(src, None)? {
+        Some(v) => Ok(v.0),
+        None => bail!(RecordBatchError::Truncated),
+    }
+}
+
+impl WireLen for Record {
+    fn wire_len(&self) -> usize {
+        let body_len = self.body_len();
+        Varint::wire_len_of(body_len as i32) + body_len
+    }
+}
+
+impl Encoder for Record {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        Varint(self.body_len() as i32).encode(dest)?;
+        dest.put_i8(self.attributes);
+        Varlong(self.timestamp_delta).encode(dest)?;
+        Varint(self.offset_delta).encode(dest)?;
+        put_bytes(dest, self.key.as_deref())?;
+        put_bytes(dest, self.value.as_deref())?;
+        Varint(self.headers.len() as i32).encode(dest)?;
+        for header in &self.headers {
+            put_bytes(dest, Some(header.key.as_bytes()))?;
+            put_bytes(dest, header.value.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for Record {
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let len = unwrap_decode!(Varint::decode(src, None)).0;
+        ensure!(len >= 0, "Negative record length {len}");
+        let len = len as usize;
+        if src.remaining() < len {
+            src.reserve(len);
+            return Ok(None);
+        }
+
+        let mut body = src.split_to(len);
+        ensure!(body.has_remaining(), RecordBatchError::Truncated);
+        let attributes = body.get_i8();
+        let timestamp_delta = unwrap_decode!(Varlong::decode(&mut body, None)).0;
+        let offset_delta = unwrap_varint(&mut body)?;
+        let key = get_bytes(&mut body)?;
+        let value = get_bytes(&mut body)?;
+
+        let header_count = unwrap_varint(&mut body)?;
+        // a header takes at least the two length prefixes
+        let capacity = (header_count.max(0) as usize).min(body.remaining() / 2);
+        let mut headers = Vec::with_capacity(capacity);
+        for _ in 0..header_count {
+            let key = get_bytes(&mut body)?.unwrap_or_default();
+            let key = String::from_utf8(key.to_vec())?;
+            let value = get_bytes(&mut body)?;
+            headers.push(RecordHeader { key, value });
+        }
+        ensure!(
+            !body.has_remaining(),
+            "Record has {} trailing bytes",
+            body.remaining()
+        );
+
+        Ok(Some(Self {
+            attributes,
+            timestamp_delta,
+            offset_delta,
+            key,
+            value,
+            headers,
+        }))
+    }
+}
+
+/// # Kafka protocol
+///
+/// The unit of data written to and read from partition logs, and sent inside
+/// of Produce and Fetch. Only the current (magic = 2) format is supported.
+///
+/// ```text
+/// baseOffset: int64
+/// batchLength: int32
+/// partitionLeaderEpoch: int32
+/// magic: int8 (current magic value is 2)
+/// crc: uint32
+/// attributes: int16
+/// lastOffsetDelta: int32
+/// baseTimestamp: int64
+/// maxTimestamp: int64
+/// producerId: int64
+/// producerEpoch: int16
+/// baseSequence: int32
+/// records: [Record]
+/// ```
+///
+/// The CRC-32C covers everything from the attributes to the end of the batch.
+/// If the attributes name a compression codec, the records are compressed as a whole.
+/// They are kept the way they are on the wire, so batches are stored and served without
+/// being decompressed, and only decoded by [`Self::records`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordBatch {
+    pub base_offset: i64,
+    pub partition_leader_epoch: i32,
+    pub attributes: i16,
+    pub last_offset_delta: i32,
+    pub base_timestamp: i64,
+    pub max_timestamp: i64,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub base_sequence: i32,
+    record_count: i32,
+    /// The encoded records, compressed with the codec of the attributes
+    records: Bytes,
+}
+
+impl RecordBatch {
+    pub const MAGIC: i8 = 2;
+    /// Size of the base offset and the batch length, which are not counted in the batch length
+    pub const LOG_OVERHEAD: usize = 12;
+    /// Size of every field up to and including the number of records
+    pub const HEADER_LEN: usize = 61;
+    /// How large the records of a batch may get once decompressed, the default
+    /// `socket.request.max.bytes`, which no uncompressed batch could be sent past
+    pub const MAX_RECORDS_LEN: usize = 100 * 1024 * 1024;
+    /// Where the part covered by the CRC starts
+    const CRC_START: usize = 21;
+
+    const COMPRESSION_MASK: i16 = 0x07;
+    const TRANSACTIONAL_FLAG: i16 = 0x10;
+    const CONTROL_FLAG: i16 = 0x20;
+
+    /// Creates a non-transactional, uncompressed batch, deriving the offset delta and
+    /// max timestamp from `records`
+    pub fn new(base_offset: i64, base_timestamp: i64, records: Vec<Record>) -> Self {
+        let last_offset_delta = records.iter().map(|r| r.offset_delta).max().unwrap_or(0);
+        let max_timestamp = records
+            .iter()
+            .map(|r| base_timestamp + r.timestamp_delta)
+            .max()
+            .unwrap_or(base_timestamp);
+        Self {
+            base_offset,
+            partition_leader_epoch: 0,
+            attributes: 0,
+            last_offset_delta,
+            base_timestamp,
+            max_timestamp,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            record_count: records.len() as i32,
+            records: encode_records(&records).freeze(),
+        }
+    }
+
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset + i64::from(self.last_offset_delta)
+    }
+
+    /// The offset the batch following this one starts at
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset() + 1
+    }
+
+    /// The number of records the batch holds
+    pub fn record_count(&self) -> i32 {
+        self.record_count
+    }
+
+    /// The compression codec stored in the lowest 3 bits of the attributes
+    pub fn compression(&self) -> Result<Compression, UnknownCompression> {
+        Compression::try_from(self.attributes & Self::COMPRESSION_MASK)
+    }
+
+    /// Recompresses the records with `compression`, failing like [`Self::records`]
+    /// if they cannot be decoded
+    pub fn set_compression(&mut self, compression: Compression) -> anyhow::Result<()> {
+        let records = self.records()?;
+        self.attributes = (self.attributes & !Self::COMPRESSION_MASK) | compression as i16;
+        self.set_records(&records)
+    }
+
+    pub fn is_transactional(&self) -> bool {
+        self.attributes & Self::TRANSACTIONAL_FLAG != 0
+    }
+
+    /// Control batches hold transaction markers instead of user data
+    pub fn is_control(&self) -> bool {
+        self.attributes & Self::CONTROL_FLAG != 0
+    }
+
+    /// Marks the batch as a control batch, like the ones of the KRaft quorum
+    pub fn set_control(&mut self) {
+        self.attributes |= Self::CONTROL_FLAG;
+    }
+
+    /// The absolute offset of `record`, which has to belong to this batch
+    pub fn offset_of(&self, record: &Record) -> i64 {
+        self.base_offset + i64::from(record.offset_delta)
+    }
+
+    /// The timestamp of `record`, which has to belong to this batch
+    pub fn timestamp_of(&self, record: &Record) -> i64 {
+        self.base_timestamp + record.timestamp_delta
+    }
+
+    /// Decompresses and decodes the records, which also validates them. Records that
+    /// decompress past [`Self::MAX_RECORDS_LEN`], are malformed or are not as many as
+    /// the batch claims fail with a [`RecordBatchError`].
+    pub fn records(&self) -> anyhow::Result<Vec<Record>> {
+        self.decode_records()
+            .map_err(|e| match e.downcast::<RecordBatchError>() {
+                Ok(e) => e.into(),
+                Err(e) => e.context(RecordBatchError::InvalidRecords),
+            })
+    }
+
+    /// Replaces the records, compressing them with the codec of the batch. The offset
+    /// delta and timestamps are kept, as compaction leaves them unchanged.
+    pub fn set_records(&mut self, records: &[Record]) -> anyhow::Result<()> {
+        let encoded = encode_records(records);
+        self.records = match self.compression()? {
+            Compression::None => encoded.freeze(),
+            codec => codec.compress(&encoded)?.into(),
+        };
+        self.record_count = records.len() as i32;
+        Ok(())
+    }
+
+    fn decode_records(&self) -> anyhow::Result<Vec<Record>> {
+        let mut data = match self.compression()? {
+            Compression::None => BytesMut::from(&self.records[..]),
+            codec => BytesMut::from(&codec.decompress(&self.records, Self::MAX_RECORDS_LEN)?[..]),
+        };
+
+        // the count comes from the wire, so it only bounds what the records could fill
+        let capacity = (self.record_count.max(0) as usize).min(data.remaining() / Record::MIN_LEN);
+        let mut records = Vec::with_capacity(capacity);
+        while data.has_remaining() {
+            match Record::decode(&mut data, None)? {
+                Some(record) => records.push(record),
+                None => bail!(RecordBatchError::Truncated),
+            }
+        }
+        ensure!(
+            usize::try_from(self.record_count) == Ok(records.len()),
+            RecordBatchError::RecordCountMismatch {
+                expected: self.record_count,
+                actual: records.len(),
+            }
+        );
+        Ok(records)
+    }
+}
+
+/// The records back to back, uncompressed
+fn encode_records(records: &[Record]) -> BytesMut {
+    let mut encoded = BytesMut::with_capacity(records.wire_len());
+    for record in records {
+        record
+            .encode(&mut encoded)
+            .expect("records encode into memory");
+    }
+    encoded
+}
+
+impl WireLen for RecordBatch {
+    fn wire_len(&self) -> usize {
+        Self::HEADER_LEN + self.records.len()
+    }
+}
+
+impl Encoder for RecordBatch {
+    fn encode(&self, dest: &mut BytesMut) -> anyhow::Result<()> {
+        let batch_length = Self::HEADER_LEN - Self::LOG_OVERHEAD + self.records.len();
+        dest.put_i64(self.base_offset);
+        dest.put_i32(batch_length as i32);
+        dest.put_i32(self.partition_leader_epoch);
+        dest.put_i8(Self::MAGIC);
+        let crc_at = dest.len();
+        dest.put_u32(0); // filled in once the rest is written
+
+        let crc_start = dest.len();
+        dest.put_i16(self.attributes);
+        dest.put_i32(self.last_offset_delta);
+        dest.put_i64(self.base_timestamp);
+        dest.put_i64(self.max_timestamp);
+        dest.put_i64(self.producer_id);
+        dest.put_i16(self.producer_epoch);
+        dest.put_i32(self.base_sequence);
+        dest.put_i32(self.record_count);
+        dest.put_slice(&self.records);
+
+        let crc = crc32c::crc32c(&dest[crc_start..]);
+        dest[crc_at..crc_start].copy_from_slice(&crc.to_be_bytes());
+        Ok(())
+    }
+}
+
+impl Decoder for RecordBatch {
+    /// Decodes a single batch, verifying its CRC. Returns `Ok(None)` if
+    /// `src` does not yet hold the entire batch. The records are left as they are,
+    /// [`Self::records`] decodes them.
+    fn decode(src: &mut BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        if src.len() < Self::LOG_OVERHEAD {
+            src.reserve(Self::LOG_OVERHEAD);
+            return Ok(None);
+        }
+        let batch_length = i32::from_be_bytes(src[8..12].try_into()?);
+        if batch_length < (Self::HEADER_LEN - Self::LOG_OVERHEAD) as i32 {
+            bail!(RecordBatchError::InvalidLength(batch_length));
+        }
+        let total = Self::LOG_OVERHEAD + batch_length as usize;
+        if src.len() < total {
+            src.reserve(total - src.len());
+            return Ok(None);
+        }
+
+        let magic = src[16] as i8;
+        if magic != Self::MAGIC {
+            bail!(RecordBatchError::UnsupportedMagic(magic));
+        }
+
+        let mut batch = src.split_to(total);
+        let expected = u32::from_be_bytes(batch[17..Self::CRC_START].try_into()?);
+        let computed = crc32c::crc32c(&batch[Self::CRC_START..]);
+        if expected != computed {
+            bail!(RecordBatchError::CrcMismatch { expected, computed });
+        }
+
+        let base_offset = batch.get_i64();
+        let _batch_length = batch.get_i32();
+        let partition_leader_epoch = batch.get_i32();
+        let _magic = batch.get_i8();
+        let _crc = batch.get_u32();
+        let attributes = batch.get_i16();
+        let last_offset_delta = batch.get_i32();
+        let base_timestamp = batch.get_i64();
+        let max_timestamp = batch.get_i64();
+        let producer_id = batch.get_i64();
+        let producer_epoch = batch.get_i16();
+        let base_sequence = batch.get_i32();
+        let record_count = batch.get_i32();
+
+        Ok(Some(Self {
+            base_offset,
+            partition_leader_epoch,
+            attributes,
+            last_offset_delta,
+            base_timestamp,
+            max_timestamp,
+            producer_id,
+            producer_epoch,
+            base_sequence,
+            record_count,
+            records: batch.freeze(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DecompressedTooLarge;
+
+    fn sample_batch() -> RecordBatch {
+        let mut record = Record::new(1, Some(Bytes::from("key")), Some(Bytes::from("value")));
+        record.headers.push(RecordHeader {
+            key: "h".to_string(),
+            value: None,
+        });
+        RecordBatch::new(
+            42,
+            1_700_000_000_000,
+            vec![Record::new(0, None, Some(Bytes::from("first"))), record],
+        )
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let batch = sample_batch();
+        let mut buf = BytesMut::new();
+        batch.encode(&mut buf).unwrap();
+        assert_eq!(batch.wire_len(), buf.len());
+
+        let decoded = RecordBatch::decode(&mut buf, None).unwrap().unwrap();
+        assert_eq!(batch, decoded);
+        assert_eq!(43, decoded.last_offset());
+        assert_eq!(2, decoded.records().unwrap().len());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip_compressed() {
+        for codec in [
+            Compression::Gzip,
+            Compression::Snappy,
+            Compression::Lz4,
+            Compression::Zstd,
+        ] {
+            let mut batch = sample_batch();
+            batch.set_compression(codec).unwrap();
+            let mut buf = BytesMut::new();
+            batch.encode(&mut buf).unwrap();
+            assert_eq!(batch.wire_len(), buf.len(), "{codec}");
+
+            let decoded = RecordBatch::decode(&mut buf, None).unwrap().unwrap();
+            assert_eq!(Ok(codec), decoded.compression());
+            assert_eq!(batch, decoded, "{codec}");
+            assert_eq!(
+                sample_batch().records().unwrap(),
+                decoded.records().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_partial_batch_needs_more_bytes() {
+        let mut buf = BytesMut::new();
+        sample_batch().encode(&mut buf).unwrap();
+        let mut partial = BytesMut::from(&buf[..buf.len() - 1]);
+        assert!(RecordBatch::decode(&mut partial, None).unwrap().is_none());
+    }
+
+    /// Rewrites the record count of an encoded batch and the CRC that covers it
+    fn set_record_count(buf: &mut BytesMut, count: i32) {
+        buf[57..61].copy_from_slice(&count.to_be_bytes());
+        let crc = crc32c::crc32c(&buf[RecordBatch::CRC_START..]);
+        buf[17..RecordBatch::CRC_START].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    #[test]
+    fn test_rejects_wrong_record_count() {
+        for count in [i32::MAX, 3, 1, 0, -1] {
+            let mut buf = BytesMut::new();
+            sample_batch().encode(&mut buf).unwrap();
+            set_record_count(&mut buf, count);
+            let batch = RecordBatch::decode(&mut buf, None).unwrap().unwrap();
+            let err = batch.records().unwrap_err();
+            assert_eq!(
+                Some(&RecordBatchError::RecordCountMismatch {
+                    expected: count,
+                    actual: 2
+                }),
+                err.downcast_ref::<RecordBatchError>(),
+                "{count}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_huge_record_count_without_records() {
+        let mut buf = BytesMut::new();
+        RecordBatch::new(0, 0, Vec::new()).encode(&mut buf).unwrap();
+        assert_eq!(RecordBatch::HEADER_LEN, buf.len());
+        set_record_count(&mut buf, i32::MAX);
+        let batch = RecordBatch::decode(&mut buf, None).unwrap().unwrap();
+        let err = batch.records().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RecordBatchError>(),
+            Some(RecordBatchError::RecordCountMismatch { actual: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_decompressed_records_are_bounded() {
+        let value = Bytes::from(vec![0; RecordBatch::MAX_RECORDS_LEN]);
+        let mut batch = RecordBatch::new(0, 0, vec![Record::new(0, None, Some(value))]);
+        // compressed by hand, as records past the limit only get here from the wire
+        batch.attributes |= Compression::Zstd as i16;
+        let records = batch.records.clone();
+        batch.records = Compression::Zstd.compress(&records).unwrap().into();
+        let err = batch.records().unwrap_err();
+        assert_eq!(
+            Some(&RecordBatchError::InvalidRecords),
+            err.downcast_ref::<RecordBatchError>()
+        );
+        assert!(err.root_cause().is::<DecompressedTooLarge>());
+    }
+
+    #[test]
+    fn test_detects_corruption() {
+        let mut buf = BytesMut::new();
+        sample_batch().encode(&mut buf).unwrap();
+        let last = buf.len() - 1;
+        buf[last] ^= 0xFF;
+        let err = RecordBatch::decode(&mut buf, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RecordBatchError>(),
+            Some(RecordBatchError::CrcMismatch { .. })
+        ));
+    }
+}
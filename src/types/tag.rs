@@ -1,37 +1,177 @@
-#![allow(dead_code)]
+use crate::codec::{Decoder, Encoder, WireLen};
+use crate::primitives::UVarint;
+use crate::unwrap_decode;
+use bytes::{Buf, BufMut, BytesMut};
 
-use crate::codec::{Decoder, Encoder};
-use crate::primitives::CompactArray;
-use bytes::BufMut;
-use kafka_macros::WireLen;
-
-pub type TagBuf = CompactArray<Tag>;
 pub const fn empty_tagbuf() -> TagBuf {
     TagBuf::new()
 }
 
-#[derive(Debug, WireLen)]
+/// A single tagged field, kept around as raw bytes. The messages using a field
+/// encode and decode it through [`TagBuf::push`] and [`TagBuf::get`].
+#[derive(Debug, Clone)]
 pub struct Tag {
-    pub(crate) inner: u8,
+    pub(crate) tag: u32,
+    pub(crate) data: Vec<u8>,
 }
 
-impl Tag {
-    fn new(b: u8) -> Self {
-        Self { inner: b }
+impl WireLen for Tag {
+    fn wire_len(&self) -> usize {
+        UVarint::wire_len_of(self.tag)
+            + UVarint::wire_len_of(self.data.len() as u32)
+            + self.data.len()
     }
 }
+
 impl Decoder for Tag {
-    fn decode(_: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
     where
-        Self: Sized + crate::WireLen,
+        Self: Sized + WireLen,
     {
-        unreachable!()
+        let tag = unwrap_decode!(UVarint::decode(src, None)).0;
+        let len = unwrap_decode!(UVarint::decode(src, None)).0 as usize;
+        // nothing is reserved for a size straight from the wire
+        if src.remaining() < len {
+            return Ok(None);
+        }
+        let data = src.split_to(len).to_vec();
+        Ok(Some(Self { tag, data }))
     }
 }
 
 impl Encoder for Tag {
     fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
-        dest.put_u8(self.inner);
+        UVarint(self.tag).encode(dest)?;
+        UVarint(self.data.len() as u32).encode(dest)?;
+        dest.put_slice(&self.data);
         Ok(())
     }
 }
+
+/// # Kafka protocol
+///
+/// The tagged fields section of flexible messages. Unlike a COMPACT_ARRAY
+/// the number of fields is given as is as an UNSIGNED_VARINT, so an
+/// empty buffer is a single `0x00` byte.
+#[derive(Debug, Default, Clone)]
+pub struct TagBuf {
+    fields: Vec<Tag>,
+}
+
+impl TagBuf {
+    pub const fn new() -> Self {
+        Self { fields: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Adds the field `tag` holding `value`. Fields have to be added in the ascending
+    /// order of their tags.
+    pub fn push<T: Encoder>(&mut self, tag: u32, value: &T) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.fields.last().map_or(true, |last| last.tag < tag),
+            "Tagged field {tag} is out of order"
+        );
+        let mut data = BytesMut::new();
+        value.encode(&mut data)?;
+        self.fields.push(Tag {
+            tag,
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Decodes the field `tag`, `None` if the buffer does not have it
+    pub fn get<T: Decoder + WireLen>(&self, tag: u32) -> anyhow::Result<Option<T>> {
+        let Some(field) = self.fields.iter().find(|f| f.tag == tag) else {
+            return Ok(None);
+        };
+        let mut src = BytesMut::from(field.data.as_slice());
+        match T::decode(&mut src, None)? {
+            Some(value) => Ok(Some(value)),
+            None => anyhow::bail!("Tagged field {tag} is truncated"),
+        }
+    }
+}
+
+impl WireLen for TagBuf {
+    fn wire_len(&self) -> usize {
+        UVarint::wire_len_of(self.fields.len() as u32) + self.fields.wire_len()
+    }
+}
+
+impl Decoder for TagBuf {
+    fn decode(src: &mut bytes::BytesMut, _: Option<usize>) -> anyhow::Result<Option<Self>>
+    where
+        Self: Sized + WireLen,
+    {
+        let count = unwrap_decode!(UVarint::decode(src, None)).0 as usize;
+        // the count comes from the wire, every field takes at least two bytes of it
+        let mut fields = Vec::with_capacity(count.min(src.remaining() / 2));
+        for _ in 0..count {
+            fields.push(unwrap_decode!(Tag::decode(src, None)));
+        }
+        Ok(Some(Self { fields }))
+    }
+}
+
+impl Encoder for TagBuf {
+    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
+        UVarint(self.fields.len() as u32).encode(dest)?;
+        for field in &self.fields {
+            field.encode(dest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_tagbuf_is_a_single_zero() {
+        let mut buf = BytesMut::new();
+        TagBuf::new().encode(&mut buf).unwrap();
+        assert_eq!(&[0x00][..], &buf[..]);
+        assert_eq!(1, TagBuf::new().wire_len());
+    }
+
+    #[test]
+    fn test_decode_skips_unknown_fields() {
+        let mut buf = BytesMut::from(&[0x01, 0x00, 0x02, 0xAA, 0xBB, 0x42][..]);
+        let tags = TagBuf::decode(&mut buf, None).unwrap().unwrap();
+        assert_eq!(1, tags.len());
+        assert_eq!(5, tags.wire_len());
+        assert_eq!(&[0x42][..], &buf[..]);
+    }
+
+    #[test]
+    fn test_push_and_get() {
+        let mut tags = TagBuf::new();
+        tags.push(1, &7_i32).unwrap();
+        assert!(tags.push(0, &8_i32).is_err());
+        let mut buf = BytesMut::new();
+        tags.encode(&mut buf).unwrap();
+        assert_eq!(&[0x01, 0x01, 0x04, 0, 0, 0, 7][..], &buf[..]);
+
+        let tags = TagBuf::decode(&mut buf, None).unwrap().unwrap();
+        assert_eq!(Some(7), tags.get::<i32>(1).unwrap());
+        assert_eq!(None, tags.get::<i32>(0).unwrap());
+    }
+
+    #[test]
+    fn test_decode_huge_count() {
+        // u32::MAX fields announced, none sent
+        let mut buf = BytesMut::from(&[0xff, 0xff, 0xff, 0xff, 0x0f][..]);
+        assert!(!matches!(TagBuf::decode(&mut buf, None), Ok(Some(_))));
+    }
+}
@@ -1,9 +1,8 @@
-use bytes::BufMut;
 use kafka_macros::{Encoder, WireLen};
 
 use crate::{
-    codec::{Decoder, Encoder},
-    primitives::{Bool, CompactArray, CompactString},
+    codec::Decoder,
+    primitives::{Bool, CompactArray, CompactString, Uuid},
     types::empty_tagbuf,
     unwrap_decode,
 };
@@ -40,11 +39,11 @@ impl Decoder for TopicInRequest {
     }
 }
 
-#[derive(Debug, WireLen)]
+#[derive(Debug, WireLen, Encoder)]
 pub struct TopicInResponse {
     error_code: u16,
     topic_name: CompactString,
-    topic_id: Vec<u8>,
+    topic_id: Uuid,
     is_internal: Bool,
     partitions: CompactArray<Partition>,
     topic_authorized_ops: u32,
@@ -53,31 +52,36 @@ pub struct TopicInResponse {
 
 impl TopicInResponse {
     pub fn new(error_code: u16, topic_name: impl Into<CompactString>) -> Self {
-        let topic_id = (0..32).map(|_| 0).collect();
         Self {
             error_code,
             topic_name: topic_name.into(),
-            topic_id,
+            topic_id: Uuid::ZERO,
             is_internal: Bool::False,
             partitions: CompactArray::new(),
             topic_authorized_ops: 0,
             tag_buffer: empty_tagbuf(),
         }
     }
-}
 
-impl Encoder for TopicInResponse {
-    fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
-        dest.put_u16(self.error_code);
-        self.topic_name.encode(dest)?;
-        for i in 0..16 {
-            dest.put_u8(self.topic_id[i]);
-        }
-        self.is_internal.encode(dest)?;
-        self.partitions.encode(dest)?;
-        dest.put_u32(self.topic_authorized_ops);
-        self.tag_buffer.encode(dest)?;
-        Ok(())
+    pub fn with_topic_id(mut self, topic_id: Uuid) -> Self {
+        self.topic_id = topic_id;
+        self
+    }
+
+    pub fn with_partitions(mut self, partitions: Vec<Partition>) -> Self {
+        self.partitions = partitions.into();
+        self
+    }
+
+    /// Reports the operations the client may perform on the topic, as a bit field with
+    /// the bit of every operation's code set
+    pub fn with_authorized_operations(mut self, operations: i32) -> Self {
+        self.topic_authorized_ops = operations as u32;
+        self
+    }
+
+    pub fn error_code(&self) -> i16 {
+        self.error_code as i16
     }
 }
 
@@ -94,20 +98,34 @@ pub struct Partition {
     tag_buffer: TagBuf,
 }
 
-// impl Encoder for Partition {
-//     fn encode(&self, dest: &mut bytes::BytesMut) -> anyhow::Result<()> {
-//         dest.put_u16(self.error_code);
-//         dest.put_u32(self.partition_index);
-//         dest.put_u32(self.leader_id);
-//         dest.put_u32(self.leader_epoch);
-//         self.replicas.encode(dest)?;
-//         self.in_sync_replicas.encode(dest)?;
-//         self.eligble_leader_replicas.encode(dest)?;
-//         self.offline_replicas.encode(dest)?;
-//         self.tag_buffer.encode(dest)?;
-//         Ok(())
-//     }
-// }
+impl Partition {
+    /// A partition led by `leader_id`, -1 without a leader
+    pub fn new(
+        partition_index: i32,
+        leader_id: i32,
+        leader_epoch: i32,
+        replicas: &[i32],
+        in_sync_replicas: &[i32],
+    ) -> Self {
+        let nodes = |ids: &[i32]| -> CompactArray<ReplicaNode> {
+            ids.iter()
+                .map(|&id| ReplicaNode(id as u32))
+                .collect::<Vec<_>>()
+                .into()
+        };
+        Self {
+            error_code: 0,
+            partition_index: partition_index as u32,
+            leader_id: leader_id as u32,
+            leader_epoch: leader_epoch as u32,
+            replicas: nodes(replicas),
+            in_sync_replicas: nodes(in_sync_replicas),
+            eligble_leader_replicas: CompactArray::new(),
+            offline_replicas: CompactArray::new(),
+            tag_buffer: empty_tagbuf(),
+        }
+    }
+}
 
 #[derive(Debug, WireLen, Encoder)]
 pub struct ReplicaNode(u32);